@@ -1,8 +1,43 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mcp_utc_time_server::bench::{LatencyRecorder, DEFAULT_ITERATIONS};
+use mcp_utc_time_server::server_sdk::TimeServer;
+use mcp_utc_time_server::time::resolution;
 use mcp_utc_time_server::time::utc::EnhancedTimeResponse;
-use mcp_utc_time_server::time::UnixTime;
+use mcp_utc_time_server::time::{FormatCache, StrftimeFormatter, TimezoneConverter, UnixTime};
+use rmcp::model::CallToolRequestParam;
+use rmcp::ServiceExt;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that counts allocations, for per-call allocation figures
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Allocations made by one call of `f`, after a warm-up call
+fn allocations<T>(mut f: impl FnMut() -> T) -> usize {
+    black_box(f());
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
 
 fn benchmark_unix_time(c: &mut Criterion) {
+    report_clock_resolution();
     c.bench_function("unix_time_now", |b| {
         b.iter(|| {
             let time = UnixTime::now();
@@ -11,7 +46,20 @@ fn benchmark_unix_time(c: &mut Criterion) {
     });
 }
 
+/// The precision behind the unix_time_now figure: smallest observable step
+fn report_clock_resolution() {
+    let measured = resolution::measure_deltas(resolution::DEFAULT_SAMPLES);
+    eprintln!(
+        "clock: min step {:?} ns, p50 {} ns, p99 {} ns, {:.1} ns per read",
+        measured.min_delta_ns, measured.p50_delta_ns, measured.p99_delta_ns, measured.now_cost_ns
+    );
+}
+
 fn benchmark_enhanced_time(c: &mut Criterion) {
+    eprintln!(
+        "enhanced_time_response: {} allocations per call",
+        allocations(EnhancedTimeResponse::now)
+    );
     c.bench_function("enhanced_time_response", |b| {
         b.iter(|| {
             let response = EnhancedTimeResponse::now();
@@ -24,16 +72,174 @@ fn benchmark_custom_format(c: &mut Criterion) {
     c.bench_function("custom_format", |b| {
         let response = EnhancedTimeResponse::now();
         b.iter(|| {
-            let formatted = response.format_custom("%Y-%m-%d %H:%M:%S").unwrap();
+            let formatted = response.format_custom("%Y-%m-%d %H:%M:%S", None).unwrap();
             black_box(formatted);
         });
     });
 }
 
+/// The same few formats requested over and over, as a chatty agent does
+fn benchmark_repeated_format(c: &mut Criterion) {
+    const FORMATS: [&str; 4] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%A, %B %e %Y",
+        "%H:%M %Z",
+        "%Y-%m-%dT%H:%M:%S%:z",
+    ];
+    let now = chrono::Utc::now();
+    c.bench_function("repeated_format_uncached", |b| {
+        b.iter(|| {
+            for format in FORMATS {
+                black_box(StrftimeFormatter::format(&now, format).unwrap());
+            }
+        });
+    });
+    let cache = FormatCache::default();
+    c.bench_function("repeated_format_cached", |b| {
+        b.iter(|| {
+            for format in FORMATS {
+                black_box(cache.format(now, format, None).unwrap());
+            }
+        });
+    });
+    c.bench_function("repeated_format_parsed_only", |b| {
+        // A fresh second each time, so only the parsed-format cache helps
+        let mut second = 0;
+        b.iter(|| {
+            second += 1;
+            let now = now + chrono::Duration::seconds(second);
+            for format in FORMATS {
+                black_box(cache.format(now, format, None).unwrap());
+            }
+        });
+    });
+}
+
+/// get_unix_time's body: the serde renderings it used, and the fast path
+fn benchmark_unix_time_body(c: &mut Criterion) {
+    use mcp_utc_time_server::mcp::fast;
+    use mcp_utc_time_server::time::correction::{Corrected, Correction};
+    use mcp_utc_time_server::time::source::TimeSource;
+    use mcp_utc_time_server::validity::Validity;
+
+    // One reading for every iteration: the clock read is not what is measured
+    let now = chrono::Utc::now();
+    let serde_body = |pretty: bool| {
+        let response = Validity::for_duration(now, std::time::Duration::from_millis(1000)).attach(
+            Corrected::<UnixTime> {
+                time: UnixTime::from_datetime(now),
+                source: TimeSource::System,
+                correction: None::<Correction>,
+            },
+        );
+        mcp_utc_time_server::output::to_json(&response, Some(pretty)).unwrap()
+    };
+    let fast_body =
+        || fast::unix_time_json(&UnixTime::from_datetime(now), TimeSource::System, 1000, now);
+    eprintln!(
+        "unix_time_body: {} allocations per call pretty, {} compact, {} fast",
+        allocations(|| serde_body(true)),
+        allocations(|| serde_body(false)),
+        allocations(fast_body)
+    );
+    c.bench_function("unix_time_body_serde_pretty", |b| {
+        b.iter(|| black_box(serde_body(true)))
+    });
+    c.bench_function("unix_time_body_serde_compact", |b| {
+        b.iter(|| black_box(serde_body(false)))
+    });
+    c.bench_function("unix_time_body_fast", |b| b.iter(|| black_box(fast_body())));
+}
+
+fn benchmark_list_timezones(c: &mut Criterion) {
+    eprintln!(
+        "list_timezones: {} allocations per call",
+        allocations(TimezoneConverter::list_timezones)
+    );
+    c.bench_function("list_timezones", |b| {
+        b.iter(|| black_box(TimezoneConverter::list_timezones()));
+    });
+    c.bench_function("timezone_names", |b| {
+        b.iter(|| black_box(TimezoneConverter::timezone_names()));
+    });
+}
+
+/// Full MCP round trip over an in-memory pipe, as a client sees it
+fn benchmark_tools(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let client = runtime.block_on(async {
+        let (client_io, server_io) = tokio::io::duplex(1024 * 1024);
+        tokio::spawn(async move {
+            let running = TimeServer::new().serve(server_io).await.unwrap();
+            let _ = running.waiting().await;
+        });
+        ().serve(client_io).await.unwrap()
+    });
+
+    for tool in ["get_time", "get_unix_time", "list_timezones"] {
+        report_tool_latency(&runtime, &client, tool);
+        c.bench_function(&format!("tool_{}", tool), |b| {
+            b.to_async(&runtime).iter(|| async {
+                let result = client
+                    .call_tool(CallToolRequestParam {
+                        name: tool.into(),
+                        arguments: None,
+                    })
+                    .await
+                    .unwrap();
+                black_box(result);
+            });
+        });
+    }
+
+    // get_unix_time before the fast path: serde with the pretty printer
+    let pretty = serde_json::json!({"pretty": true}).as_object().cloned();
+    c.bench_function("tool_get_unix_time_pretty", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let result = client
+                .call_tool(CallToolRequestParam {
+                    name: "get_unix_time".into(),
+                    arguments: pretty.clone(),
+                })
+                .await
+                .unwrap();
+            black_box(result);
+        });
+    });
+}
+
+/// Latency distribution of round trips, with the recorder run_selftest_benchmark uses
+fn report_tool_latency(
+    runtime: &tokio::runtime::Runtime,
+    client: &rmcp::service::RunningService<rmcp::RoleClient, ()>,
+    tool: &'static str,
+) {
+    let stats = runtime.block_on(async {
+        let mut recorder = LatencyRecorder::new(DEFAULT_ITERATIONS);
+        for _ in 0..DEFAULT_ITERATIONS {
+            let _ = recorder
+                .time(client.call_tool(CallToolRequestParam {
+                    name: tool.into(),
+                    arguments: None,
+                }))
+                .await;
+        }
+        recorder.finish().unwrap()
+    });
+    eprintln!(
+        "tool_{}: p50 {} ns, p99 {} ns, max {} ns, {:.0} calls/s",
+        tool, stats.p50_ns, stats.p99_ns, stats.max_ns, stats.calls_per_second
+    );
+}
+
 criterion_group!(
     benches,
     benchmark_unix_time,
     benchmark_enhanced_time,
-    benchmark_custom_format
+    benchmark_custom_format,
+    benchmark_repeated_format,
+    benchmark_unix_time_body,
+    benchmark_list_timezones,
+    benchmark_tools
 );
 criterion_main!(benches);
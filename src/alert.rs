@@ -0,0 +1,484 @@
+// Webhook alerts on NTP health transitions
+//
+// With `ALERT_WEBHOOK_URL` set, every NTP status the server reads passes
+// through a `Detector`, which compares it with the previous one: losing or
+// regaining sync, the offset crossing `ALERT_OFFSET_MS` either way, and the
+// stratum jumping to 16 are transitions. Each is POSTed as JSON to the URL
+// by a separate task, with up to three attempts and exponential backoff, so
+// a slow or failing receiver never holds up a status read. A transition of
+// the same kind within `ALERT_COOLDOWN_SECS` of the last one sent is
+// dropped, which keeps a flapping clock from flooding the receiver. Every
+// delivery, and every delivery that gave up, is published on the event bus.
+
+use crate::events::{self, ServerEvent};
+use crate::ntp::sync::NtpStatus;
+use chrono::{SecondsFormat, Utc};
+use rmcp::model::LoggingLevel;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Offset beyond which `ntp.offset_exceeded` fires, by default
+pub const DEFAULT_ALERT_OFFSET_MS: f64 = 100.0;
+
+/// Shortest gap between two alerts of the same kind, by default
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 300;
+
+/// Attempts per alert before it is given up
+const ATTEMPTS: u32 = 3;
+
+/// Wait before the second attempt; doubled before each further one
+const RETRY_BASE: Duration = Duration::from_secs(1);
+
+/// Per-attempt limit on the whole request
+const POST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Alerts waiting for the sender; more are dropped rather than queued
+const QUEUE_CAPACITY: usize = 32;
+
+/// ALERT_WEBHOOK_URL, ALERT_OFFSET_MS and ALERT_COOLDOWN_SECS
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertConfig {
+    pub url: String,
+    pub offset_ms: f64,
+    pub cooldown_secs: u64,
+}
+
+/// The parts of an NTP status the alerts are about
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StatusSummary {
+    pub synced: bool,
+    pub offset_ms: f64,
+    pub stratum: u8,
+}
+
+impl From<&NtpStatus> for StatusSummary {
+    fn from(status: &NtpStatus) -> Self {
+        Self {
+            synced: status.synced,
+            offset_ms: status.offset_ms,
+            stratum: status.stratum,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerIdentity {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub pid: u32,
+}
+
+/// The JSON body POSTed to the webhook
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertPayload {
+    /// "ntp.sync_lost", "ntp.offset_exceeded", ...
+    pub event: &'static str,
+    pub previous: StatusSummary,
+    pub current: StatusSummary,
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    pub server_info: ServerIdentity,
+}
+
+/// Finds transitions between consecutive statuses
+#[derive(Debug)]
+pub struct Detector {
+    offset_ms: f64,
+    last: Option<StatusSummary>,
+}
+
+impl Detector {
+    pub fn new(offset_ms: f64) -> Self {
+        Self {
+            offset_ms,
+            last: None,
+        }
+    }
+
+    /// Transitions from the previous status to `current`; none for the first
+    pub fn observe(&mut self, current: StatusSummary) -> Vec<&'static str> {
+        let Some(previous) = self.last.replace(current) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        match (previous.synced, current.synced) {
+            (true, false) => events.push("ntp.sync_lost"),
+            (false, true) => events.push("ntp.sync_restored"),
+            _ => {}
+        }
+        let exceeded = |status: StatusSummary| status.offset_ms.abs() > self.offset_ms;
+        match (exceeded(previous), exceeded(current)) {
+            (false, true) => events.push("ntp.offset_exceeded"),
+            (true, false) => events.push("ntp.offset_recovered"),
+            _ => {}
+        }
+        if previous.stratum != 16 && current.stratum == 16 {
+            events.push("ntp.stratum_unsynchronized");
+        }
+        events
+    }
+
+    pub fn last(&self) -> Option<StatusSummary> {
+        self.last
+    }
+}
+
+/// Lets an alert kind through at most once per period
+#[derive(Debug)]
+pub struct Cooldown {
+    period: Duration,
+    sent: HashMap<&'static str, Instant>,
+}
+
+impl Cooldown {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            sent: HashMap::new(),
+        }
+    }
+
+    pub fn admit(&mut self, event: &'static str, now: Instant) -> bool {
+        match self.sent.get(event) {
+            Some(&last) if now.duration_since(last) < self.period => false,
+            _ => {
+                self.sent.insert(event, now);
+                true
+            }
+        }
+    }
+}
+
+/// Detects transitions and hands them to the delivery task
+#[derive(Debug)]
+pub struct AlertNotifier {
+    detector: Mutex<Detector>,
+    cooldown: Mutex<Cooldown>,
+    queue: mpsc::Sender<AlertPayload>,
+}
+
+impl AlertNotifier {
+    /// Spawn the delivery task; needs a tokio runtime
+    pub fn start(config: &AlertConfig) -> Self {
+        Self::with_retry_base(config, RETRY_BASE)
+    }
+
+    fn with_retry_base(config: &AlertConfig, retry_base: Duration) -> Self {
+        let (queue, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(deliver_all(config.url.clone(), receiver, retry_base));
+        Self {
+            detector: Mutex::new(Detector::new(config.offset_ms)),
+            cooldown: Mutex::new(Cooldown::new(Duration::from_secs(config.cooldown_secs))),
+            queue,
+        }
+    }
+
+    /// Queue an alert for each transition to `status`; returns how many
+    pub fn observe(&self, status: &NtpStatus) -> usize {
+        let current = StatusSummary::from(status);
+        let mut detector = self
+            .detector
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = detector.last();
+        let transitions = detector.observe(current);
+        drop(detector);
+
+        let Some(previous) = previous else {
+            return 0;
+        };
+        let now = Instant::now();
+        let mut cooldown = self
+            .cooldown
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut queued = 0;
+        for event in transitions {
+            if !cooldown.admit(event, now) {
+                tracing::debug!(event = "alert.suppressed", alert = event);
+                continue;
+            }
+            let payload = AlertPayload {
+                event,
+                previous,
+                current,
+                timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                server_info: ServerIdentity {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                    pid: std::process::id(),
+                },
+            };
+            match self.queue.try_send(payload) {
+                Ok(()) => queued += 1,
+                Err(e) => tracing::warn!(event = "alert.dropped", alert = event, error = %e),
+            }
+        }
+        queued
+    }
+}
+
+/// Send queued alerts one at a time until the notifier is dropped
+async fn deliver_all(
+    url: String,
+    mut receiver: mpsc::Receiver<AlertPayload>,
+    retry_base: Duration,
+) {
+    let client = match reqwest::Client::builder().timeout(POST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!(event = "alert.client_failed", error = %e, "Webhook alerts disabled");
+            return;
+        }
+    };
+    while let Some(payload) = receiver.recv().await {
+        let details = |attempts: u32, error: Option<&str>| {
+            serde_json::json!({
+                "alert": payload.event,
+                "url": url,
+                "attempts": attempts,
+                "error": error,
+            })
+        };
+        let event = match post(&client, &url, &payload, retry_base).await {
+            Ok(attempts) => ServerEvent::new(
+                "alert.delivered",
+                LoggingLevel::Info,
+                format!("Webhook alert {} delivered", payload.event),
+            )
+            .with_details(details(attempts, None)),
+            Err(error) => ServerEvent::new(
+                "alert.failed",
+                LoggingLevel::Warning,
+                format!("Webhook alert {} not delivered", payload.event),
+            )
+            .with_details(details(ATTEMPTS, Some(&error))),
+        };
+        events::publish(event);
+    }
+}
+
+/// POST `payload`, retrying failures; the number of attempts it took
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    payload: &AlertPayload,
+    retry_base: Duration,
+) -> Result<u32, String> {
+    let mut wait = retry_base;
+    let mut last_error = String::new();
+    for attempt in 1..=ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(wait).await;
+            wait *= 2;
+        }
+        match client.post(url).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return Ok(attempt),
+            Ok(response) => last_error = format!("HTTP {}", response.status()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+static GLOBAL: OnceLock<AlertNotifier> = OnceLock::new();
+
+/// Start webhook alerts when `config` is set
+pub fn init(config: Option<&AlertConfig>) {
+    if let Some(config) = config {
+        let _ = GLOBAL.set(AlertNotifier::start(config));
+    }
+}
+
+pub fn global() -> Option<&'static AlertNotifier> {
+    GLOBAL.get()
+}
+
+/// Feed a status read to the global notifier, if there is one
+pub fn observe(status: &NtpStatus) {
+    if let Some(notifier) = global() {
+        notifier.observe(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    /// An HTTP receiver answering with `statuses` in turn, then 200
+    async fn receiver(statuses: Vec<u16>) -> (String, Received) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received: Received = Arc::default();
+        let bodies = received.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                let body = loop {
+                    let n = stream.read(&mut buffer).await.unwrap_or(0);
+                    if n == 0 {
+                        break None;
+                    }
+                    request.extend_from_slice(&buffer[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break Some(body.to_string());
+                    }
+                };
+                if let Some(body) = body {
+                    bodies
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push(serde_json::from_str(&body).unwrap());
+                }
+                let status = statuses.next().unwrap_or(200);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, received)
+    }
+
+    async fn wait_for(received: &Received, count: usize) -> Vec<serde_json::Value> {
+        for _ in 0..200 {
+            if received
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .len()
+                >= count
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Anything beyond `count` would arrive in this window
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        received
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn status(synced: bool, offset_ms: f64, stratum: u8) -> NtpStatus {
+        NtpStatus {
+            synced,
+            offset_ms,
+            stratum,
+            ..Default::default()
+        }
+    }
+
+    fn notifier(url: &str, cooldown_secs: u64) -> AlertNotifier {
+        let config = AlertConfig {
+            url: url.to_string(),
+            offset_ms: 50.0,
+            cooldown_secs,
+        };
+        AlertNotifier::with_retry_base(&config, Duration::from_millis(10))
+    }
+
+    #[test]
+    fn test_detector() {
+        let mut detector = Detector::new(50.0);
+        let summary = |s: NtpStatus| StatusSummary::from(&s);
+        assert!(detector.observe(summary(status(true, 1.0, 2))).is_empty());
+        assert!(detector.observe(summary(status(true, -40.0, 2))).is_empty());
+        assert_eq!(
+            detector.observe(summary(status(true, -60.0, 2))),
+            ["ntp.offset_exceeded"]
+        );
+        assert_eq!(
+            detector.observe(summary(status(false, 0.0, 16))),
+            [
+                "ntp.sync_lost",
+                "ntp.offset_recovered",
+                "ntp.stratum_unsynchronized"
+            ]
+        );
+        assert!(detector.observe(summary(status(false, 0.0, 16))).is_empty());
+        assert_eq!(
+            detector.observe(summary(status(true, 0.0, 3))),
+            ["ntp.sync_restored"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_one_delivery_per_transition() {
+        let (url, received) = receiver(Vec::new()).await;
+        let notifier = notifier(&url, 300);
+        assert_eq!(notifier.observe(&status(true, 1.0, 2)), 0);
+        assert_eq!(notifier.observe(&status(true, 2.0, 2)), 0);
+        assert_eq!(notifier.observe(&status(false, 2.0, 16)), 2);
+
+        let bodies = wait_for(&received, 2).await;
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["event"], "ntp.sync_lost");
+        assert_eq!(bodies[0]["previous"]["synced"], true);
+        assert_eq!(bodies[0]["current"]["stratum"], 16);
+        assert_eq!(bodies[0]["server_info"]["name"], env!("CARGO_PKG_NAME"));
+        assert_eq!(bodies[1]["event"], "ntp.stratum_unsynchronized");
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_suppresses_flapping() {
+        let (url, received) = receiver(Vec::new()).await;
+        let notifier = notifier(&url, 300);
+        notifier.observe(&status(true, 1.0, 2));
+        for _ in 0..3 {
+            notifier.observe(&status(false, 1.0, 2));
+            notifier.observe(&status(true, 1.0, 2));
+        }
+        let bodies = wait_for(&received, 2).await;
+        let events: Vec<_> = bodies.iter().map(|b| b["event"].clone()).collect();
+        assert_eq!(events, ["ntp.sync_lost", "ntp.sync_restored"]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_server_error() {
+        let (url, received) = receiver(vec![500]).await;
+        let mut bus = events::global().subscribe();
+        let notifier = notifier(&url, 0);
+        notifier.observe(&status(true, 1.0, 2));
+        notifier.observe(&status(true, 80.0, 2));
+
+        let bodies = wait_for(&received, 2).await;
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], bodies[1]);
+
+        let delivered = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = bus.recv().await.unwrap();
+                if event.kind == "alert.delivered" && event.details["url"] == url.as_str() {
+                    return event;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(delivered.details["attempts"], 2);
+        assert_eq!(delivered.details["alert"], "ntp.offset_exceeded");
+    }
+}
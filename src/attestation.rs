@@ -0,0 +1,516 @@
+// Signed time attestations
+//
+// `get_signed_time` returns a statement of "the time was X when asked": the
+// Unix time, a summary of NTP quality and a sequence number, signed with
+// Ed25519. The key is a 32-byte seed stored as 64 hex digits in
+// `SIGNING_KEY_FILE` and generated on first run when the file does not exist;
+// without the variable a throwaway key lives for the process only. The last
+// sequence number used is kept in `SIGNING_STATE_FILE` (default: the key file
+// with `.seq` appended) and written before a signature is returned, so numbers
+// are never reused across restarts.
+//
+// Canonical form: the signature covers the UTF-8 bytes of RFC 8785 JSON (see
+// `canonical`), built from exactly the fields `ntp`, `sequence` and `time`,
+// e.g.
+//
+//   {"ntp":{"available":true,"offset_ms":0.25,"stratum":2,"synced":true},
+//    "sequence":42,"time":{"nanos":5,"nanos_since_epoch":"1700000000000000005",
+//    "seconds":1700000000}}
+//
+// (one line, no whitespace, keys sorted). Integers print in decimal
+// (nanosecond counts past 2^53 as strings, see `time::unix`), floats as
+// ECMAScript prints them, and absent optional fields are omitted rather than
+// written as null. Keys and signatures are lowercase hex.
+
+use crate::config::{ConfigError, EnvVars};
+use crate::error::TimeServerError;
+#[cfg(feature = "ntp")]
+use crate::ntp::NtpStatus;
+use crate::time::UnixTime;
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+pub const ALGORITHM: &str = "ed25519";
+
+/// Fields covered by the signature, in canonical (sorted) order
+pub const SIGNED_FIELDS: [&str; 3] = ["ntp", "sequence", "time"];
+
+/// Key and sequence files, normally read from `SIGNING_*` variables
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SigningConfig {
+    /// Hex Ed25519 seed; created on first run. None: a per-process key
+    pub key_file: Option<String>,
+    /// Last sequence number used; defaults to `<key_file>.seq`
+    pub state_file: Option<String>,
+}
+
+impl SigningConfig {
+    pub fn from_vars(env: &EnvVars) -> Result<Self, ConfigError> {
+        Ok(Self {
+            key_file: env.string(&["SIGNING_KEY_FILE"]).filter(|p| !p.is_empty()),
+            state_file: env
+                .string(&["SIGNING_STATE_FILE"])
+                .filter(|p| !p.is_empty()),
+        })
+    }
+
+    /// Where the sequence counter is kept, if anywhere
+    pub fn sequence_path(&self) -> Option<PathBuf> {
+        self.state_file
+            .clone()
+            .or_else(|| self.key_file.as_ref().map(|key| format!("{}.seq", key)))
+            .map(PathBuf::from)
+    }
+}
+
+/// NTP quality at signing time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NtpQuality {
+    /// ntpd status could be read
+    pub available: bool,
+    pub synced: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stratum: Option<u8>,
+}
+
+impl NtpQuality {
+    #[cfg(feature = "ntp")]
+    pub fn from_status(status: Result<&NtpStatus, &TimeServerError>) -> Self {
+        match status {
+            Ok(status) => Self {
+                available: true,
+                synced: status.synced,
+                offset_ms: Some(status.offset_ms),
+                stratum: Some(status.stratum),
+            },
+            Err(_) => Self::unavailable(),
+        }
+    }
+
+    pub fn unavailable() -> Self {
+        Self {
+            available: false,
+            synced: false,
+            offset_ms: None,
+            stratum: None,
+        }
+    }
+}
+
+/// The signed fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestedTime {
+    pub ntp: NtpQuality,
+    pub sequence: u64,
+    pub time: UnixTime,
+}
+
+impl AttestedTime {
+    /// The bytes the signature covers
+    pub fn canonical(&self) -> Result<String, TimeServerError> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| TimeServerError::SigningUnavailable(e.to_string()))?;
+        Ok(crate::canonical::to_string(&value))
+    }
+}
+
+/// A `get_signed_time` result
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedTime {
+    #[serde(flatten)]
+    pub attested: AttestedTime,
+    pub algorithm: &'static str,
+    pub public_key: String,
+    pub signature: String,
+    /// Exactly what was signed, for verifiers that would rather not rebuild it
+    pub canonical: String,
+}
+
+/// Public half of the signing key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SigningKeyInfo {
+    pub algorithm: &'static str,
+    pub public_key: String,
+    /// False when the key lives only as long as this process
+    pub persistent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+}
+
+/// Result of checking an attestation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Verification {
+    pub valid: bool,
+    /// Why the attestation was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// The attested time, RFC 3339 UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl Verification {
+    fn rejected(reason: impl Into<String>) -> Self {
+        Self {
+            valid: false,
+            reason: Some(reason.into()),
+            sequence: None,
+            signed_at: None,
+            public_key: None,
+        }
+    }
+}
+
+/// Sequence numbers, persisted before each one is handed out
+struct SequenceCounter {
+    path: Option<PathBuf>,
+    last: u64,
+}
+
+impl SequenceCounter {
+    fn open(path: Option<PathBuf>) -> Result<Self, TimeServerError> {
+        let last = match &path {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(text) => text.trim().parse().map_err(|_| {
+                    unavailable(path, "does not hold a sequence number".to_string())
+                })?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(unavailable(path, e.to_string())),
+            },
+            None => 0,
+        };
+        Ok(Self { path, last })
+    }
+
+    fn next(&mut self) -> Result<u64, TimeServerError> {
+        let next = self
+            .last
+            .checked_add(1)
+            .ok_or_else(|| TimeServerError::SigningUnavailable("sequence exhausted".into()))?;
+        if let Some(path) = &self.path {
+            write_atomically(path, format!("{}\n", next).as_bytes())
+                .map_err(|e| unavailable(path, e.to_string()))?;
+        }
+        self.last = next;
+        Ok(next)
+    }
+}
+
+pub struct Signer {
+    key: SigningKey,
+    key_file: Option<String>,
+    sequence: Mutex<SequenceCounter>,
+}
+
+impl Signer {
+    /// Load (or create) the configured key and sequence files
+    pub fn open(config: &SigningConfig) -> Result<Self, TimeServerError> {
+        let key = match &config.key_file {
+            Some(path) => load_or_create_key(Path::new(path))?,
+            None => generate_key()?,
+        };
+        Ok(Self {
+            key,
+            key_file: config.key_file.clone(),
+            sequence: Mutex::new(SequenceCounter::open(config.sequence_path())?),
+        })
+    }
+
+    pub fn public_key(&self) -> String {
+        to_hex(self.key.verifying_key().as_bytes())
+    }
+
+    pub fn key_info(&self) -> SigningKeyInfo {
+        SigningKeyInfo {
+            algorithm: ALGORITHM,
+            public_key: self.public_key(),
+            persistent: self.key_file.is_some(),
+            key_file: self.key_file.clone(),
+        }
+    }
+
+    /// Take the next sequence number and sign `time` and `ntp` with it
+    pub fn sign(&self, time: UnixTime, ntp: NtpQuality) -> Result<SignedTime, TimeServerError> {
+        // Held across signing so sequence order matches signing order
+        let mut sequence = self.sequence.lock().unwrap_or_else(|p| p.into_inner());
+        let attested = AttestedTime {
+            ntp,
+            sequence: sequence.next()?,
+            time,
+        };
+        let canonical = attested.canonical()?;
+        let signature = self.key.sign(canonical.as_bytes());
+        Ok(SignedTime {
+            attested,
+            algorithm: ALGORITHM,
+            public_key: self.public_key(),
+            signature: to_hex(&signature.to_bytes()),
+            canonical,
+        })
+    }
+}
+
+/// Check `attestation` (a `get_signed_time` result) against `public_key`, or
+/// the key it names when none is given. Needs nothing from the server.
+pub fn verify(attestation: &Value, public_key: Option<&str>) -> Verification {
+    let Some(fields) = attestation.as_object() else {
+        return Verification::rejected("attestation must be a JSON object");
+    };
+    let mut signed = Map::new();
+    for name in SIGNED_FIELDS {
+        match fields.get(name) {
+            Some(value) => signed.insert(name.to_string(), value.clone()),
+            None => return Verification::rejected(format!("missing signed field '{}'", name)),
+        };
+    }
+    let attested: AttestedTime = match serde_json::from_value(Value::Object(signed.clone())) {
+        Ok(attested) => attested,
+        Err(e) => return Verification::rejected(format!("malformed signed fields: {}", e)),
+    };
+
+    let Some(public_key) = public_key.or_else(|| fields.get("public_key")?.as_str()) else {
+        return Verification::rejected("no public key given");
+    };
+    let Some(key) = from_hex::<32>(public_key).and_then(|b| VerifyingKey::from_bytes(&b).ok())
+    else {
+        return Verification::rejected("public key is not 64 hex digits of an Ed25519 key");
+    };
+    let Some(signature) = fields
+        .get("signature")
+        .and_then(Value::as_str)
+        .and_then(from_hex::<64>)
+    else {
+        return Verification::rejected("signature is missing or not 128 hex digits");
+    };
+
+    let canonical = crate::canonical::to_string(&Value::Object(signed));
+    if let Some(claimed) = fields.get("canonical").and_then(Value::as_str) {
+        if claimed != canonical {
+            return Verification::rejected("canonical does not match the signed fields");
+        }
+    }
+    if let Err(e) = key.verify_strict(canonical.as_bytes(), &Signature::from_bytes(&signature)) {
+        return Verification::rejected(format!("signature check failed: {}", e));
+    }
+
+    Verification {
+        valid: true,
+        reason: None,
+        sequence: Some(attested.sequence),
+        signed_at: Some(
+            attested
+                .time
+                .to_datetime()
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+        ),
+        public_key: Some(public_key.to_ascii_lowercase()),
+    }
+}
+
+static GLOBAL: OnceLock<Result<Signer, String>> = OnceLock::new();
+
+/// Open the process-wide signer. A key file that cannot be read is reported
+/// by the signing tools rather than stopping the server.
+pub fn init(config: &SigningConfig) -> Result<(), TimeServerError> {
+    let signer = GLOBAL.get_or_init(|| Signer::open(config).map_err(|e| e.to_string()));
+    signer
+        .as_ref()
+        .map(|_| ())
+        .map_err(|e| TimeServerError::SigningUnavailable(e.clone()))
+}
+
+/// The process-wide signer; a per-process key unless [`init`] ran first
+pub fn global() -> Result<&'static Signer, TimeServerError> {
+    GLOBAL
+        .get_or_init(|| Signer::open(&SigningConfig::default()).map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| TimeServerError::SigningUnavailable(e.clone()))
+}
+
+fn unavailable(path: &Path, reason: String) -> TimeServerError {
+    TimeServerError::SigningUnavailable(format!("{}: {}", path.display(), reason))
+}
+
+fn generate_key() -> Result<SigningKey, TimeServerError> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed)
+        .map_err(|e| TimeServerError::SigningUnavailable(format!("no randomness: {}", e)))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Read the seed at `path`, creating it (mode 0600) only if it does not exist
+fn load_or_create_key(path: &Path) -> Result<SigningKey, TimeServerError> {
+    match fs::read_to_string(path) {
+        Ok(text) => from_hex::<32>(text.trim())
+            .map(|seed| SigningKey::from_bytes(&seed))
+            .ok_or_else(|| unavailable(path, "is not a 64-digit hex Ed25519 seed".into())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = generate_key()?;
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", to_hex(&key.to_bytes())))
+                .map_err(|e| unavailable(path, e.to_string()))?;
+            Ok(key)
+        }
+        Err(e) => Err(unavailable(path, e.to_string())),
+    }
+}
+
+/// Write to a sibling temp file and rename it over `path`
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != N * 2 || !text.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(dir: &tempfile::TempDir) -> SigningConfig {
+        SigningConfig {
+            key_file: Some(dir.path().join("signing.key").display().to_string()),
+            state_file: None,
+        }
+    }
+
+    fn synced() -> NtpQuality {
+        NtpQuality {
+            available: true,
+            synced: true,
+            offset_ms: Some(0.25),
+            stratum: Some(2),
+        }
+    }
+
+    fn sign(signer: &Signer) -> Value {
+        serde_json::to_value(signer.sign(UnixTime::now(), synced()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_sign_then_verify_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::open(&config(&dir)).unwrap();
+        let attestation = sign(&signer);
+
+        let verification = verify(&attestation, None);
+        assert!(verification.valid, "{:?}", verification.reason);
+        assert_eq!(verification.sequence, Some(1));
+        assert_eq!(verification.public_key, Some(signer.public_key()));
+        assert!(verify(&attestation, Some(&signer.public_key())).valid);
+
+        // The documented form: sorted keys, no whitespace
+        let canonical = attestation["canonical"].as_str().unwrap();
+        assert!(canonical.starts_with(r#"{"ntp":{"available":true,"offset_ms":0.25,"stratum":2,"synced":true},"sequence":1,"time":{"nanos":"#));
+        assert!(!canonical.contains(' '));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::open(&config(&dir)).unwrap();
+        let attestation = sign(&signer);
+
+        let mut moved = attestation.clone();
+        moved["time"]["seconds"] = json!(attestation["time"]["seconds"].as_i64().unwrap() - 60);
+        moved.as_object_mut().unwrap().remove("canonical");
+        let verification = verify(&moved, None);
+        assert!(!verification.valid);
+        assert!(verification.reason.unwrap().contains("signature"));
+
+        let mut renumbered = attestation.clone();
+        renumbered["sequence"] = json!(7);
+        assert!(!verify(&renumbered, None).valid);
+
+        let other = Signer::open(&SigningConfig::default()).unwrap();
+        assert!(!verify(&attestation, Some(&other.public_key())).valid);
+
+        let mut missing = attestation;
+        missing.as_object_mut().unwrap().remove("ntp");
+        assert_eq!(
+            verify(&missing, None).reason.as_deref(),
+            Some("missing signed field 'ntp'")
+        );
+    }
+
+    #[test]
+    fn test_sequence_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::open(&config(&dir)).unwrap();
+        let first: Vec<u64> = (0..3)
+            .map(|_| sign(&signer)["sequence"].as_u64().unwrap())
+            .collect();
+        assert_eq!(first, [1, 2, 3]);
+        let public_key = signer.public_key();
+        drop(signer);
+
+        let restarted = Signer::open(&config(&dir)).unwrap();
+        assert_eq!(restarted.public_key(), public_key);
+        assert_eq!(sign(&restarted)["sequence"], 4);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("signing.key.seq")).unwrap(),
+            "4\n"
+        );
+    }
+
+    #[test]
+    fn test_unreadable_key_file_is_reported_and_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // A directory cannot be read as a key
+        let unreadable = SigningConfig {
+            key_file: Some(dir.path().display().to_string()),
+            state_file: None,
+        };
+        let error = Signer::open(&unreadable).err().unwrap();
+        assert_eq!(error.code(), "signing_unavailable");
+        assert!(error
+            .to_string()
+            .contains(&dir.path().display().to_string()));
+
+        // A corrupt key is not replaced with a fresh one
+        let path = dir.path().join("corrupt.key");
+        fs::write(&path, "not a key\n").unwrap();
+        let corrupt = SigningConfig {
+            key_file: Some(path.display().to_string()),
+            state_file: None,
+        };
+        assert!(Signer::open(&corrupt).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not a key\n");
+    }
+}
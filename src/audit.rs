@@ -0,0 +1,409 @@
+// Audit log of tool invocations
+//
+// Events are sent over a channel to a dedicated writer thread, so recording
+// never blocks request handling. The log is JSON Lines with size-based
+// rotation (`file.jsonl` -> `file.jsonl.1` -> ... -> `file.jsonl.N`).
+
+use crate::config::{ConfigError, EnvVars};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info};
+
+/// Default rotation threshold (10 MiB)
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated files kept next to the active log
+pub const DEFAULT_KEEP_FILES: usize = 5;
+
+const REDACTED: &str = "[redacted]";
+
+/// Audit log settings, normally read from `AUDIT_LOG_*` variables
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditConfig {
+    pub path: PathBuf,
+    /// Rotate before a write would take the file past this size
+    pub max_bytes: u64,
+    /// Rotated files to keep; older ones are deleted
+    pub keep_files: usize,
+    /// Replace argument values with "[redacted]", keeping the names
+    pub redact_arguments: bool,
+}
+
+impl AuditConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            keep_files: DEFAULT_KEEP_FILES,
+            redact_arguments: false,
+        }
+    }
+
+    /// `None` unless `AUDIT_LOG_FILE` is set
+    pub fn from_vars(env: &EnvVars) -> Result<Option<Self>, ConfigError> {
+        let Some(path) = env.string(&["AUDIT_LOG_FILE"]).filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let mut config = Self::new(path);
+        if let Some(max_bytes) = env.parse(&["AUDIT_LOG_MAX_BYTES"], "a byte count")? {
+            config.max_bytes = max_bytes;
+        }
+        if let Some(keep_files) = env.parse(&["AUDIT_LOG_KEEP_FILES"], "a file count")? {
+            config.keep_files = keep_files;
+        }
+        config.redact_arguments = env.flag(&["AUDIT_LOG_REDACT_ARGS"])?.unwrap_or(false);
+        Ok(Some(config))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Ok,
+    Error,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEvent {
+    /// Completion time (RFC 3339, UTC)
+    pub timestamp: String,
+    /// `mcp`, `stdio` or `http`
+    pub transport: &'static str,
+    /// Tool name, or `METHOD /path` for HTTP requests
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+    pub outcome: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub latency_ms: f64,
+    /// HTTP status code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Name of the API key presented, never the key itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// `name/version` from the client's initialize request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+}
+
+impl AuditEvent {
+    /// Event for a call that started at `started` and has just finished
+    pub fn new(transport: &'static str, tool: impl Into<String>, started: Instant) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            transport,
+            tool: tool.into(),
+            arguments: None,
+            outcome: AuditOutcome::Ok,
+            error: None,
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+            status: None,
+            api_key: None,
+            client: None,
+        }
+    }
+
+    pub fn with_arguments(mut self, arguments: Option<Value>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.outcome = AuditOutcome::Error;
+        self.error = Some(error.into());
+        self
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        if status >= 400 {
+            self.outcome = AuditOutcome::Error;
+        }
+        self
+    }
+
+    pub fn with_api_key(mut self, name: Option<String>) -> Self {
+        self.api_key = name;
+        self
+    }
+
+    pub fn with_client(mut self, name: &str, version: &str) -> Self {
+        self.client = Some(format!("{}/{}", name, version));
+        self
+    }
+}
+
+enum Command {
+    Record(Box<AuditEvent>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Cheap-to-clone handle to the audit writer
+#[derive(Debug, Clone)]
+pub struct AuditLogger {
+    tx: mpsc::UnboundedSender<Command>,
+    redact_arguments: bool,
+}
+
+impl AuditLogger {
+    /// Open the log file and start the writer thread.
+    ///
+    /// Fails if the file cannot be opened, so a misconfigured path is caught
+    /// at startup rather than silently dropping records.
+    pub fn start(config: AuditConfig) -> io::Result<Self> {
+        let writer = RotatingWriter::open(&config)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        std::thread::Builder::new()
+            .name("audit-log".into())
+            .spawn(move || writer_loop(writer, rx))?;
+
+        info!(event = "audit.start", path = %config.path.display(), "Audit logging enabled");
+        Ok(Self {
+            tx,
+            redact_arguments: config.redact_arguments,
+        })
+    }
+
+    /// Queue an event; never blocks
+    pub fn record(&self, mut event: AuditEvent) {
+        if self.redact_arguments {
+            event.arguments = event.arguments.map(redact);
+        }
+        if self.tx.send(Command::Record(Box::new(event))).is_err() {
+            error!(
+                event = "audit.closed",
+                "Audit writer has stopped; event dropped"
+            );
+        }
+    }
+
+    /// Wait until every event queued so far is on disk
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(Command::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+static GLOBAL: OnceLock<AuditLogger> = OnceLock::new();
+
+/// Start the process-wide logger when audit logging is configured
+pub fn init(config: Option<&AuditConfig>) -> io::Result<()> {
+    if let Some(config) = config {
+        let logger = AuditLogger::start(config.clone())?;
+        let _ = GLOBAL.set(logger);
+    }
+    Ok(())
+}
+
+/// The process-wide logger, if audit logging is enabled
+pub fn global() -> Option<&'static AuditLogger> {
+    GLOBAL.get()
+}
+
+/// Keep argument names, hide values
+fn redact(arguments: Value) -> Value {
+    match arguments {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, _)| (k, Value::String(REDACTED.into())))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Null => Value::Null,
+        _ => Value::String(REDACTED.into()),
+    }
+}
+
+fn writer_loop(mut writer: RotatingWriter, mut rx: mpsc::UnboundedReceiver<Command>) {
+    while let Some(command) = rx.blocking_recv() {
+        let mut pending = Some(command);
+        let mut waiters = Vec::new();
+
+        // Drain whatever is queued, then flush once
+        while let Some(command) = pending.take().or_else(|| rx.try_recv().ok()) {
+            match command {
+                Command::Record(event) => {
+                    if let Err(e) = writer.write_event(&event) {
+                        error!(event = "audit.write_error", error = %e);
+                    }
+                }
+                Command::Flush(done) => waiters.push(done),
+            }
+        }
+
+        if let Err(e) = writer.flush() {
+            error!(event = "audit.flush_error", error = %e);
+        }
+        for done in waiters {
+            let _ = done.send(());
+        }
+    }
+
+    // All handles dropped: final flush on shutdown
+    let _ = writer.flush();
+}
+
+struct RotatingWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    size: u64,
+    max_bytes: u64,
+    keep_files: usize,
+}
+
+impl RotatingWriter {
+    fn open(config: &AuditConfig) -> io::Result<Self> {
+        let file = open_append(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: config.path.clone(),
+            file: BufWriter::new(file),
+            size,
+            max_bytes: config.max_bytes,
+            keep_files: config.keep_files,
+        })
+    }
+
+    fn write_event(&mut self, event: &AuditEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    /// Shift `path.N-1` -> `path.N`, ..., `path` -> `path.1` and reopen
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.keep_files));
+            for n in (1..self.keep_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = BufWriter::new(open_append(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn read_lines(path: &Path) -> Vec<Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("audit line is not JSON"))
+            .collect()
+    }
+
+    fn event(tool: &str) -> AuditEvent {
+        AuditEvent::new("mcp", tool, Instant::now())
+            .with_arguments(Some(json!({"timezone": "Europe/London"})))
+    }
+
+    #[tokio::test]
+    async fn test_one_line_per_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let logger = AuditLogger::start(AuditConfig::new(&path)).unwrap();
+
+        logger.record(event("get_time"));
+        logger.record(event("list_timezones").with_error("boom"));
+        logger.flush().await;
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["tool"], "get_time");
+        assert_eq!(lines[0]["outcome"], "ok");
+        assert_eq!(lines[0]["arguments"]["timezone"], "Europe/London");
+        assert_eq!(lines[1]["outcome"], "error");
+        assert_eq!(lines[1]["error"], "boom");
+        assert!(lines[1]["latency_ms"].is_number());
+    }
+
+    #[tokio::test]
+    async fn test_redaction_keeps_argument_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut config = AuditConfig::new(&path);
+        config.redact_arguments = true;
+        let logger = AuditLogger::start(config).unwrap();
+
+        logger.record(event("get_time_with_timezone"));
+        logger.flush().await;
+
+        assert_eq!(read_lines(&path)[0]["arguments"]["timezone"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut config = AuditConfig::new(&path);
+        config.max_bytes = 300;
+        config.keep_files = 2;
+        let logger = AuditLogger::start(config).unwrap();
+
+        for _ in 0..20 {
+            logger.record(event("get_time"));
+        }
+        logger.flush().await;
+
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        for file in [path.clone(), rotated_path(&path, 1)] {
+            assert!(fs::metadata(&file).unwrap().len() <= 300);
+            assert!(!read_lines(&file).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_unopenable_path_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("audit.jsonl");
+        assert!(AuditLogger::start(AuditConfig::new(path)).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 // API Key Authentication
+use crate::config::EnvVars;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::env;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +12,53 @@ pub struct ApiKey {
     pub rate_limit: Option<u32>,
 }
 
+/// Keys from `API_KEY_<NAME>` variables (plain or JSON with metadata) and
+/// the legacy comma-separated `API_KEYS`
+pub fn load_keys(env: &EnvVars) -> Vec<ApiKey> {
+    let mut keys = Vec::new();
+
+    for (key, value) in env.with_prefix("API_KEY_") {
+        let key_suffix = &key["API_KEY_".len()..];
+        // Parse the value - could be just a key or JSON with metadata
+        if value.starts_with('{') {
+            match serde_json::from_str::<ApiKey>(value) {
+                Ok(api_key) => {
+                    debug!("Loaded API key {} with metadata", key_suffix);
+                    keys.push(api_key);
+                    continue;
+                }
+                Err(e) => warn!(
+                    "Failed to parse API_KEY_{} as JSON: {}, treating as plain key",
+                    key_suffix, e
+                ),
+            }
+        } else {
+            debug!("Loaded API key {}", key_suffix);
+        }
+        keys.push(ApiKey {
+            key: value.to_string(),
+            name: Some(format!("Key {}", key_suffix)),
+            rate_limit: None,
+        });
+    }
+
+    // Also support legacy API_KEYS environment variable (comma-separated)
+    if let Some(api_keys_csv) = env.string(&["API_KEYS"]) {
+        info!("Loading keys from API_KEYS environment variable");
+        for key in api_keys_csv.split(',').map(|s| s.trim()) {
+            if !key.is_empty() {
+                keys.push(ApiKey {
+                    key: key.to_string(),
+                    name: Some("Legacy key".to_string()),
+                    rate_limit: None,
+                });
+            }
+        }
+    }
+
+    keys
+}
+
 #[derive(Debug, Clone)]
 pub struct ApiKeyValidator {
     valid_keys: HashSet<String>,
@@ -20,69 +68,15 @@ pub struct ApiKeyValidator {
 impl ApiKeyValidator {
     /// Create a new validator by reading API_KEY_* environment variables
     pub fn from_env() -> Self {
-        let mut valid_keys = HashSet::new();
-        let mut keys_with_metadata = Vec::new();
-
-        // Read all environment variables
-        for (key, value) in env::vars() {
-            // Check if the key starts with API_KEY_
-            if let Some(key_suffix) = key.strip_prefix("API_KEY_") {
-                // Parse the value - could be just a key or JSON with metadata
-                if value.starts_with('{') {
-                    // Try to parse as JSON with metadata
-                    match serde_json::from_str::<ApiKey>(&value) {
-                        Ok(api_key) => {
-                            info!("Loaded API key {} with metadata", key_suffix);
-                            valid_keys.insert(api_key.key.clone());
-                            keys_with_metadata.push(api_key);
-                        }
-                        Err(e) => {
-                            warn!(
-                                "Failed to parse API_KEY_{} as JSON: {}, treating as plain key",
-                                key_suffix, e
-                            );
-                            valid_keys.insert(value.clone());
-                            keys_with_metadata.push(ApiKey {
-                                key: value.clone(),
-                                name: Some(format!("Key {}", key_suffix)),
-                                rate_limit: None,
-                            });
-                        }
-                    }
-                } else {
-                    // Plain API key string
-                    info!("Loaded API key {}", key_suffix);
-                    valid_keys.insert(value.clone());
-                    keys_with_metadata.push(ApiKey {
-                        key: value.clone(),
-                        name: Some(format!("Key {}", key_suffix)),
-                        rate_limit: None,
-                    });
-                }
-            }
-        }
-
-        // Also support legacy API_KEYS environment variable (comma-separated)
-        if let Ok(api_keys_csv) = env::var("API_KEYS") {
-            info!("Loading keys from API_KEYS environment variable");
-            for key in api_keys_csv.split(',').map(|s| s.trim()) {
-                if !key.is_empty() {
-                    valid_keys.insert(key.to_string());
-                    keys_with_metadata.push(ApiKey {
-                        key: key.to_string(),
-                        name: Some("Legacy key".to_string()),
-                        rate_limit: None,
-                    });
-                }
-            }
-        }
+        Self::from_api_keys(load_keys(&EnvVars::from_process()))
+    }
 
+    pub fn from_api_keys(keys: Vec<ApiKey>) -> Self {
+        let valid_keys: HashSet<String> = keys.iter().map(|k| k.key.clone()).collect();
         info!("Loaded {} API keys total", valid_keys.len());
-        debug!("Valid keys count: {:?}", valid_keys.len());
-
         Self {
             valid_keys,
-            keys_with_metadata,
+            keys_with_metadata: keys,
         }
     }
 
@@ -116,6 +110,11 @@ impl ApiKeyValidator {
         self.keys_with_metadata.iter().find(|k| k.key == key)
     }
 
+    /// Every loaded key with its metadata
+    pub fn keys(&self) -> &[ApiKey] {
+        &self.keys_with_metadata
+    }
+
     /// Get the number of loaded keys
     pub fn key_count(&self) -> usize {
         self.valid_keys.len()
@@ -133,6 +132,71 @@ impl ApiKeyValidator {
     }
 }
 
+/// A key as listed to operators: its metadata without the secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeySummary {
+    pub name: Option<String>,
+    pub rate_limit: Option<u32>,
+}
+
+/// One validator shared by every connection and replaced as a whole when
+/// the keys are reloaded, so a request sees either the old or the new set
+#[derive(Debug, Clone)]
+pub struct SharedApiKeys(Arc<RwLock<ApiKeyValidator>>);
+
+impl SharedApiKeys {
+    pub fn new(validator: ApiKeyValidator) -> Self {
+        Self(Arc::new(RwLock::new(validator)))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, ApiKeyValidator> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn validate(&self, key: &str) -> bool {
+        self.read().validate(key)
+    }
+
+    /// Name of the key, for audit records
+    pub fn key_name(&self, key: &str) -> Option<String> {
+        self.read()
+            .get_key_metadata(key)
+            .and_then(|key| key.name.clone())
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.read().key_count()
+    }
+
+    pub fn has_keys(&self) -> bool {
+        self.read().has_keys()
+    }
+
+    pub fn summaries(&self) -> Vec<KeySummary> {
+        self.read()
+            .keys()
+            .iter()
+            .map(|key| KeySummary {
+                name: key.name.clone(),
+                rate_limit: key.rate_limit,
+            })
+            .collect()
+    }
+
+    /// Swap in `keys`; returns how many are now valid
+    pub fn replace(&self, keys: Vec<ApiKey>) -> usize {
+        let validator = ApiKeyValidator::from_api_keys(keys);
+        let count = validator.key_count();
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = validator;
+        count
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +220,26 @@ mod tests {
         assert!(metadata.is_some());
         assert_eq!(metadata.unwrap().key, "test-key");
     }
+
+    #[test]
+    fn test_shared_keys_replace_for_every_clone() {
+        let keys = SharedApiKeys::new(ApiKeyValidator::from_keys(vec!["old".to_string()]));
+        let clone = keys.clone();
+        let count = keys.replace(vec![ApiKey {
+            key: "new".to_string(),
+            name: Some("Key CI".to_string()),
+            rate_limit: Some(10),
+        }]);
+        assert_eq!(count, 1);
+        assert!(!clone.validate("old"));
+        assert!(clone.validate("new"));
+        assert_eq!(clone.key_name("new").as_deref(), Some("Key CI"));
+        assert_eq!(
+            clone.summaries(),
+            [KeySummary {
+                name: Some("Key CI".to_string()),
+                rate_limit: Some(10),
+            }]
+        );
+    }
 }
@@ -1,4 +1,57 @@
 // Authentication Module
 pub mod api_key;
 
-pub use api_key::{ApiKey, ApiKeyValidator};
+pub use api_key::{ApiKey, ApiKeyValidator, KeySummary, SharedApiKeys};
+
+use crate::events::{FailureThreshold, ServerEvent};
+use rmcp::model::LoggingLevel;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Unknown keys within [`AUTH_FAILURE_WINDOW`] that raise an `auth.failures` event
+pub const AUTH_FAILURE_THRESHOLD: usize = 10;
+pub const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counts requests presenting an unknown API key and publishes
+/// `auth.failures` each time the threshold is reached
+#[derive(Debug)]
+pub struct AuthFailureMonitor {
+    failures: Mutex<FailureThreshold>,
+}
+
+impl Default for AuthFailureMonitor {
+    fn default() -> Self {
+        Self::new(AUTH_FAILURE_THRESHOLD, AUTH_FAILURE_WINDOW)
+    }
+}
+
+impl AuthFailureMonitor {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            failures: Mutex::new(FailureThreshold::new(threshold, window)),
+        }
+    }
+
+    /// Record a rejected key presented on `transport` ("http", ...)
+    pub fn record(&self, transport: &str) {
+        let count = self
+            .failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record(Instant::now());
+        if let Some(count) = count {
+            crate::events::publish(
+                ServerEvent::new(
+                    "auth.failures",
+                    LoggingLevel::Warning,
+                    format!("{} requests with an unknown API key", count),
+                )
+                .with_details(serde_json::json!({
+                    "count": count,
+                    "window_secs": AUTH_FAILURE_WINDOW.as_secs(),
+                    "transport": transport,
+                })),
+            );
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Server-side latency measurement for run_selftest_benchmark
+//
+// Client-side timings include the transport and the client itself; this
+// measures calls inside the server with the monotonic clock. A recorder
+// takes one sample per call into a buffer sized up front, so recording does
+// not allocate and the mean is a running sum. The same recorder reports the
+// in-process tool figures in `benches/time_benchmarks.rs`.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Calls per run unless the caller says otherwise
+pub const DEFAULT_ITERATIONS: usize = 1000;
+
+/// Upper bound for caller-chosen iteration counts
+pub const MAX_ITERATIONS: usize = 100_000;
+
+/// Tools that run ntpq or reach outside the process; benchmarking them needs
+/// `allow_external` so a large run cannot fork thousands of subprocesses
+pub const EXTERNAL_TOOLS: &[&str] = &[
+    "get_ntp_status",
+    "get_ntp_peers",
+    "get_signed_time",
+    "check_time_sanity",
+    "get_ptp_status",
+];
+
+/// Latency distribution of a run, in nanoseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub iterations: usize,
+    /// Calls that returned an error
+    pub errors: usize,
+    pub min_ns: u64,
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub p99_ns: u64,
+    pub max_ns: u64,
+    pub mean_ns: f64,
+    /// Wall time of the whole run
+    pub total_ms: f64,
+    pub calls_per_second: f64,
+}
+
+/// Per-call latencies of one run
+#[derive(Debug)]
+pub struct LatencyRecorder {
+    samples: Vec<u64>,
+    sum_ns: u128,
+    errors: usize,
+    started: Instant,
+}
+
+impl LatencyRecorder {
+    /// A recorder with room for `iterations` samples; the run's wall time
+    /// starts now
+    pub fn new(iterations: usize) -> Self {
+        Self {
+            samples: Vec::with_capacity(iterations),
+            sum_ns: 0,
+            errors: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, latency: Duration, ok: bool) {
+        let ns = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.samples.push(ns);
+        self.sum_ns += u128::from(ns);
+        if !ok {
+            self.errors += 1;
+        }
+    }
+
+    /// Time `call` and record it
+    pub async fn time<F, T, E>(&mut self, call: F) -> Result<T, E>
+    where
+        F: std::future::Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let result = call.await;
+        self.record(started.elapsed(), result.is_ok());
+        result
+    }
+
+    /// Summary of the samples so far; `None` before the first one
+    pub fn finish(mut self) -> Option<LatencyStats> {
+        let total = self.started.elapsed();
+        let count = self.samples.len();
+        if count == 0 {
+            return None;
+        }
+        self.samples.sort_unstable();
+        let percentile = |p: usize| self.samples[((count - 1) * p).div_ceil(100)];
+        Some(LatencyStats {
+            iterations: count,
+            errors: self.errors,
+            min_ns: self.samples[0],
+            p50_ns: percentile(50),
+            p95_ns: percentile(95),
+            p99_ns: percentile(99),
+            max_ns: self.samples[count - 1],
+            mean_ns: self.sum_ns as f64 / count as f64,
+            total_ms: total.as_secs_f64() * 1000.0,
+            calls_per_second: count as f64 / total.as_secs_f64().max(f64::MIN_POSITIVE),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_of_known_samples() {
+        let mut recorder = LatencyRecorder::new(100);
+        for ns in (1..=100).rev() {
+            recorder.record(Duration::from_nanos(ns), ns != 7);
+        }
+        let stats = recorder.finish().unwrap();
+        assert_eq!(stats.iterations, 100);
+        assert_eq!(stats.errors, 1);
+        assert_eq!((stats.min_ns, stats.max_ns), (1, 100));
+        assert_eq!((stats.p50_ns, stats.p95_ns, stats.p99_ns), (51, 96, 100));
+        assert_eq!(stats.mean_ns, 50.5);
+        assert!(stats.calls_per_second > 0.0);
+    }
+
+    #[test]
+    fn test_empty_run_has_no_stats() {
+        assert!(LatencyRecorder::new(0).finish().is_none());
+    }
+}
@@ -0,0 +1,320 @@
+// Canonical JSON (RFC 8785) and content hashes over it
+//
+// The same logical value always gives the same bytes: no whitespace, object
+// keys sorted by their UTF-16 code units, strings escaped as ECMAScript's
+// JSON.stringify escapes them, and numbers in the shortest form that reads
+// back as the same IEEE 754 double (so integers beyond 2^53 lose precision,
+// as I-JSON says they may; responses carry such values as strings). Signed
+// time attestations sign this text, and `content_hash` is its SHA-256.
+//
+// A content hash leaves out the `content_hash` field itself and any volatile
+// fields the caller names, as dotted paths ("unix.nanos"), so two polls with
+// the same meaningful content hash the same.
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// Field responses carry the hash in
+pub const CONTENT_HASH_FIELD: &str = "content_hash";
+
+/// `value` as canonical JSON text
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Lowercase hex SHA-256 of `value`'s canonical text
+pub fn sha256_hex(value: &Value) -> String {
+    Sha256::digest(to_string(value).as_bytes()).iter().fold(
+        String::with_capacity(64),
+        |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        },
+    )
+}
+
+/// Hash of `body` without its `content_hash` and the `volatile` paths
+pub fn content_hash(body: &Value, volatile: &[&str]) -> String {
+    let mut body = body.clone();
+    if let Value::Object(map) = &mut body {
+        map.remove(CONTENT_HASH_FIELD);
+        for path in volatile {
+            remove_path(map, path);
+        }
+    }
+    sha256_hex(&body)
+}
+
+/// Remove the field at dotted `path`, if there is one
+fn remove_path(map: &mut Map<String, Value>, path: &str) {
+    match path.split_once('.') {
+        None => {
+            map.remove(path);
+        }
+        Some((head, rest)) => {
+            if let Some(Value::Object(inner)) = map.get_mut(head) {
+                remove_path(inner, rest);
+            }
+        }
+    }
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            // Exact as a double, and printed the same either way
+            (Some(i), _) if i.unsigned_abs() <= 1 << 53 => {
+                let _ = write!(out, "{}", i);
+            }
+            (_, Some(f)) => write_number(out, f),
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Escapes as JSON.stringify does: the two-character forms where JSON has
+/// them, `\u00xx` for other controls, everything else as is
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{09}' => out.push_str("\\t"),
+            '\u{0a}' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\u{0d}' => out.push_str("\\r"),
+            c if c < '\u{20}' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// ECMAScript's Number::toString: shortest round-trip digits, plain
+/// notation from 1e-6 up to 1e21 and exponent notation outside it
+fn write_number(out: &mut String, f: f64) {
+    if f == 0.0 {
+        // Negative zero too
+        out.push('0');
+        return;
+    }
+    if f < 0.0 {
+        out.push('-');
+    }
+    // "d.ddde-x" with the shortest digits that read back as `f`
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("{:e} always has an exponent");
+    let mut digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().expect("{:e} exponent is an integer");
+    if let Some(even) = even_of_tie(f.abs(), &digits, exponent) {
+        digits = even;
+    }
+    let k = digits.len() as i32;
+    // The decimal point goes after n digits
+    let n = exponent + 1;
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let _ = write!(out, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs());
+    }
+}
+
+/// When `f` lies exactly halfway between `digits` rounded down and rounded
+/// up, the one ending in an even digit, as ECMAScript breaks the tie; Rust's
+/// formatter rounds such ties up
+fn even_of_tie(f: f64, digits: &str, exponent: i32) -> Option<String> {
+    // Neighbouring doubles are closer than one unit in the 15th digit, so a
+    // shorter form is never halfway
+    if digits.len() < 15 {
+        return None;
+    }
+    // Enough places for the exact expansion of any double
+    let exact = format!("{:.800e}", f);
+    let (mantissa, exact_exponent) = exact.split_once('e')?;
+    if exact_exponent.parse::<i32>().ok()? != exponent {
+        return None;
+    }
+    let exact: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let (head, tail) = exact.split_at(digits.len());
+    let halfway = tail.starts_with('5') && tail[1..].bytes().all(|b| b == b'0');
+    let even = head
+        .bytes()
+        .last()
+        .is_some_and(|b| (b - b'0').is_multiple_of(2));
+    (halfway && even && head != digits).then(|| head.trim_end_matches('0').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn number(bits: u64) -> String {
+        to_string(&json!(f64::from_bits(bits)))
+    }
+
+    /// RFC 8785 Appendix B
+    #[test]
+    fn test_number_vectors() {
+        let vectors = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors {
+            assert_eq!(number(bits), expected, "{:#018x}", bits);
+        }
+        assert_eq!(to_string(&json!(-42)), "-42");
+        assert_eq!(to_string(&json!(u64::MAX)), "18446744073709552000");
+    }
+
+    /// RFC 8785 section 3.2.2
+    #[test]
+    fn test_rfc_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let value: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(
+            to_string(&value),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    /// RFC 8785 section 3.2.3: UTF-16 order puts the emoji before U+FB33
+    #[test]
+    fn test_key_order_is_utf16() {
+        let input = r#"{
+            "€": "Euro Sign",
+            "\r": "Carriage Return",
+            "דּ": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "😀": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "ö": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let value: Value = serde_json::from_str(input).unwrap();
+        let canonical = to_string(&value);
+        let positions: Vec<usize> = [
+            "Carriage Return",
+            "One",
+            "Control",
+            "Latin Small Letter O With Diaeresis",
+            "Euro Sign",
+            "Emoji: Grinning Face",
+            "Hebrew Letter Dalet With Dagesh",
+        ]
+        .iter()
+        .map(|name| canonical.find(name).unwrap())
+        .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", canonical);
+    }
+
+    #[test]
+    fn test_hash_ignores_key_order() {
+        let a: Value =
+            serde_json::from_str(r#"{"b": [1, {"y": 2, "x": 1.5}], "a": "t", "c": null}"#).unwrap();
+        let b: Value =
+            serde_json::from_str(r#"{"c": null, "a": "t", "b": [1, {"x": 1.50, "y": 2.0}]}"#)
+                .unwrap();
+        assert_eq!(sha256_hex(&a), sha256_hex(&b));
+        assert_eq!(sha256_hex(&a).len(), 64);
+        // Array order is content
+        let c: Value =
+            serde_json::from_str(r#"{"a": "t", "b": [{"x": 1.5, "y": 2}, 1], "c": null}"#).unwrap();
+        assert_ne!(sha256_hex(&a), sha256_hex(&c));
+    }
+
+    #[test]
+    fn test_content_hash_leaves_out_volatile_fields() {
+        let poll = |nanos: u32, hash: &str| {
+            json!({
+                "seconds": 1_700_000_000,
+                "nanosecond": nanos,
+                "unix": {"seconds": 1_700_000_000, "nanos": nanos},
+                "content_hash": hash,
+            })
+        };
+        let volatile = ["nanosecond", "unix.nanos"];
+        let first = content_hash(&poll(5, ""), &volatile);
+        assert_eq!(first, content_hash(&poll(999, "stale"), &volatile));
+        assert_ne!(first, content_hash(&poll(5, ""), &["nanosecond"]));
+
+        let mut later = poll(5, "");
+        later["seconds"] = json!(1_700_000_001);
+        assert_ne!(first, content_hash(&later, &volatile));
+    }
+}
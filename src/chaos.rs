@@ -0,0 +1,534 @@
+// Test-only clock anomalies
+//
+// `CHAOS_MODE` makes the server report deliberately wrong times so that
+// clients and monitors can be exercised against clock trouble without
+// breaking a real host clock. It is refused unless `ALLOW_CHAOS=true` is set
+// as well. One anomaly is active at a time:
+//
+//   offset:<duration>            fixed shift, e.g. offset:-2s
+//   drift:<ppm>                  runs fast (or slow when negative) by N ppm
+//   jitter:<duration>            gaussian noise with this standard deviation
+//   backward:<step>/<period>     jumps back `step` every `period`
+//   freeze                       time stops when the server starts
+//
+// Durations are milliseconds, or a number with ms, s, m or h. Whatever the
+// anomaly, a reported time never falls more than `CHAOS_MAX_REGRESSION_MS`
+// behind the latest one reported before it. Every reading of "now" goes
+// through `now()`, so all tools and endpoints see the same fake clock; tool
+// results carry a CHAOS_ACTIVE warning, HTTP responses an `X-Chaos-Mode`
+// header and `/health` reports degraded.
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+/// How far a reported time may fall behind the latest one, by default
+pub const DEFAULT_MAX_REGRESSION_MS: u64 = 1000;
+
+/// Seed for the jitter generator when `CHAOS_SEED` is unset
+pub const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Largest offset, step or period accepted
+const MAX_ANOMALY_MS: i64 = 365 * 86_400_000;
+
+/// The misbehaviour injected into the clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    Offset { offset_ms: i64 },
+    Drift { ppm: f64 },
+    Jitter { stddev_ms: i64 },
+    BackwardSteps { step_ms: i64, period_ms: i64 },
+    Freeze,
+}
+
+impl Anomaly {
+    /// `offset`, `drift`, `jitter`, `backward` or `freeze`
+    pub fn mode(&self) -> &'static str {
+        match self {
+            Self::Offset { .. } => "offset",
+            Self::Drift { .. } => "drift",
+            Self::Jitter { .. } => "jitter",
+            Self::BackwardSteps { .. } => "backward",
+            Self::Freeze => "freeze",
+        }
+    }
+
+    /// One sentence for get_chaos_status
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Offset { offset_ms } => {
+                format!("every time is shifted by {} ms", offset_ms)
+            }
+            Self::Drift { ppm } => format!(
+                "the clock runs {} by {} ppm since the server started",
+                if *ppm < 0.0 { "slow" } else { "fast" },
+                ppm.abs()
+            ),
+            Self::Jitter { stddev_ms } => format!(
+                "each time has gaussian noise with a standard deviation of {} ms",
+                stddev_ms
+            ),
+            Self::BackwardSteps { step_ms, period_ms } => {
+                format!("the clock steps back {} ms every {} ms", step_ms, period_ms)
+            }
+            Self::Freeze => "time stands still at the moment the server started".to_string(),
+        }
+    }
+}
+
+/// `250`, `250ms`, `-2s`, `5m` or `1h`, in milliseconds
+fn parse_ms(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => text.split_at(at),
+        None => (text, "ms"),
+    };
+    let scale = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+    let value: i64 = number.parse().ok()?;
+    value
+        .checked_mul(scale)
+        .filter(|ms| ms.abs() <= MAX_ANOMALY_MS)
+}
+
+impl FromStr for Anomaly {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let (mode, argument) = text.split_once(':').unwrap_or((text, ""));
+        let invalid = || format!("invalid {} argument '{}'", mode, argument);
+        match mode.to_ascii_lowercase().as_str() {
+            "offset" => Ok(Self::Offset {
+                offset_ms: parse_ms(argument).ok_or_else(invalid)?,
+            }),
+            "drift" => argument
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|ppm| ppm.is_finite() && ppm.abs() <= 1e6)
+                .map(|ppm| Self::Drift { ppm })
+                .ok_or_else(invalid),
+            "jitter" => parse_ms(argument)
+                .filter(|ms| *ms > 0)
+                .map(|stddev_ms| Self::Jitter { stddev_ms })
+                .ok_or_else(invalid),
+            "backward" => {
+                let (step, period) = argument.split_once('/').ok_or_else(invalid)?;
+                match (parse_ms(step), parse_ms(period)) {
+                    (Some(step_ms), Some(period_ms)) if step_ms > 0 && period_ms > 0 => {
+                        Ok(Self::BackwardSteps { step_ms, period_ms })
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+            "freeze" if argument.is_empty() => Ok(Self::Freeze),
+            _ => Err(format!("unknown chaos mode '{}'", text)),
+        }
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offset { offset_ms } => write!(f, "offset:{}ms", offset_ms),
+            Self::Drift { ppm } => write!(f, "drift:{}", ppm),
+            Self::Jitter { stddev_ms } => write!(f, "jitter:{}ms", stddev_ms),
+            Self::BackwardSteps { step_ms, period_ms } => {
+                write!(f, "backward:{}ms/{}ms", step_ms, period_ms)
+            }
+            Self::Freeze => f.write_str("freeze"),
+        }
+    }
+}
+
+impl Serialize for Anomaly {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// CHAOS_MODE, CHAOS_MAX_REGRESSION_MS and CHAOS_SEED
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChaosConfig {
+    pub anomaly: Anomaly,
+    pub max_regression_ms: u64,
+    pub seed: u64,
+}
+
+/// What the fake clock carries from one reading to the next
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosState {
+    /// Real time of the first reading; drift, steps and freeze count from here
+    pub started: Option<DateTime<Utc>>,
+    /// Latest fake time reported so far
+    pub latest: Option<DateTime<Utc>>,
+    /// xorshift64* state for jitter; never zero
+    pub rng: u64,
+}
+
+impl ChaosState {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            started: None,
+            latest: None,
+            rng: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+}
+
+/// Next xorshift64* value as a float in (0, 1]
+fn next_unit(rng: &mut u64) -> f64 {
+    *rng ^= *rng >> 12;
+    *rng ^= *rng << 25;
+    *rng ^= *rng >> 27;
+    let value = rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+    (value as f64 + 1.0) / (1u64 << 53) as f64
+}
+
+/// A standard normal sample (Box-Muller)
+fn next_gaussian(rng: &mut u64) -> f64 {
+    let (u1, u2) = (next_unit(rng), next_unit(rng));
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+fn nanos(value: f64) -> Duration {
+    Duration::nanoseconds(value.round().clamp(i64::MIN as f64, i64::MAX as f64) as i64)
+}
+
+/// The fake time for `real` and the state to pass to the next reading
+pub fn apply(
+    config: &ChaosConfig,
+    state: &ChaosState,
+    real: DateTime<Utc>,
+) -> (DateTime<Utc>, ChaosState) {
+    let mut next = state.clone();
+    let started = *next.started.get_or_insert(real);
+    let elapsed = real - started;
+
+    let fake = match config.anomaly {
+        Anomaly::Offset { offset_ms } => real + Duration::milliseconds(offset_ms),
+        Anomaly::Drift { ppm } => {
+            let elapsed_ns = elapsed.num_nanoseconds().unwrap_or(i64::MAX) as f64;
+            real + nanos(elapsed_ns * ppm / 1e6)
+        }
+        Anomaly::Jitter { stddev_ms } => {
+            real + nanos(next_gaussian(&mut next.rng) * stddev_ms as f64 * 1e6)
+        }
+        Anomaly::BackwardSteps { step_ms, period_ms } => {
+            let steps = elapsed.num_milliseconds().max(0) / period_ms;
+            real - Duration::milliseconds(steps.saturating_mul(step_ms))
+        }
+        Anomaly::Freeze => started,
+    };
+
+    let max_regression =
+        Duration::milliseconds(config.max_regression_ms.min(MAX_ANOMALY_MS as u64) as i64);
+    let floor = next
+        .latest
+        .and_then(|latest| latest.checked_sub_signed(max_regression));
+    let fake = match floor {
+        Some(floor) if fake < floor => floor,
+        _ => fake,
+    };
+    next.latest = Some(next.latest.map_or(fake, |latest| latest.max(fake)));
+    (fake, next)
+}
+
+/// The installed anomaly and its running state
+pub struct Chaos {
+    config: ChaosConfig,
+    state: Mutex<ChaosState>,
+}
+
+/// get_chaos_status
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosStatus {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<&'static str>,
+    /// The anomaly in CHAOS_MODE syntax
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_regression_ms: Option<u64>,
+    /// RFC 3339 real time the anomaly started counting from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<String>,
+    /// Fake minus real time for a reading taken now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skew_ms: Option<f64>,
+    pub note: String,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        let state = Mutex::new(ChaosState::new(config.seed));
+        Self { config, state }
+    }
+
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    /// The fake time for `real`, advancing the shared state
+    pub fn distort(&self, real: DateTime<Utc>) -> DateTime<Utc> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (fake, next) = apply(&self.config, &state, real);
+        *state = next;
+        fake
+    }
+
+    pub fn status(&self) -> ChaosStatus {
+        let real = Utc::now();
+        let fake = self.distort(real);
+        let started = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .started;
+        ChaosStatus {
+            active: true,
+            mode: Some(self.config.anomaly.mode()),
+            anomaly: Some(self.config.anomaly.to_string()),
+            description: Some(self.config.anomaly.describe()),
+            max_regression_ms: Some(self.config.max_regression_ms),
+            started: started.map(|at| at.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            skew_ms: (fake - real)
+                .num_microseconds()
+                .map(|us| us as f64 / 1000.0),
+            note: "CHAOS_MODE is on: every time this server reports is deliberately wrong"
+                .to_string(),
+        }
+    }
+}
+
+/// get_chaos_status with chaos off
+pub fn inactive_status() -> ChaosStatus {
+    ChaosStatus {
+        active: false,
+        mode: None,
+        anomaly: None,
+        description: None,
+        max_regression_ms: None,
+        started: None,
+        skew_ms: None,
+        note: "chaos mode is off; times are real".to_string(),
+    }
+}
+
+static GLOBAL: OnceLock<Chaos> = OnceLock::new();
+
+/// Install the configured anomaly for the whole process
+pub fn init(config: Option<&ChaosConfig>) {
+    if let Some(config) = config {
+        let chaos = GLOBAL.get_or_init(|| Chaos::new(config.clone()));
+        tracing::warn!(
+            event = "chaos.enabled",
+            anomaly = %chaos.config.anomaly,
+            "CHAOS_MODE is on: reported times are deliberately wrong"
+        );
+    }
+}
+
+/// The process-wide anomaly, if chaos mode is on
+pub fn global() -> Option<&'static Chaos> {
+    GLOBAL.get()
+}
+
+pub fn is_active() -> bool {
+    GLOBAL.get().is_some()
+}
+
+/// `real` as the fake clock reports it; unchanged with chaos off
+pub fn distort(real: DateTime<Utc>) -> DateTime<Utc> {
+    match global() {
+        Some(chaos) => chaos.distort(real),
+        None => real,
+    }
+}
+
+/// The current time as every tool and endpoint should see it
+pub fn now() -> DateTime<Utc> {
+    distort(Utc::now())
+}
+
+/// get_chaos_status
+pub fn status() -> ChaosStatus {
+    global().map_or_else(inactive_status, Chaos::status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn config(mode: &str, max_regression_ms: u64) -> ChaosConfig {
+        ChaosConfig {
+            anomaly: mode.parse().unwrap(),
+            max_regression_ms,
+            seed: 42,
+        }
+    }
+
+    /// Fake times for real times `start + offsets[i]` ms, in order
+    fn run(config: &ChaosConfig, offsets: &[i64]) -> Vec<DateTime<Utc>> {
+        let start = at("2024-03-15T10:00:00Z");
+        let mut state = ChaosState::new(config.seed);
+        offsets
+            .iter()
+            .map(|ms| {
+                let (fake, next) = apply(config, &state, start + Duration::milliseconds(*ms));
+                state = next;
+                fake
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_round_trips() {
+        for (text, shown) in [
+            ("offset:-2s", "offset:-2000ms"),
+            ("offset:250", "offset:250ms"),
+            ("drift:50", "drift:50"),
+            ("drift:-12.5", "drift:-12.5"),
+            ("jitter:20ms", "jitter:20ms"),
+            ("backward:500ms/1m", "backward:500ms/60000ms"),
+            ("freeze", "freeze"),
+        ] {
+            let anomaly: Anomaly = text.parse().unwrap();
+            assert_eq!(anomaly.to_string(), shown);
+            assert_eq!(shown.parse::<Anomaly>().unwrap(), anomaly);
+        }
+        for bad in [
+            "",
+            "wobble",
+            "offset",
+            "offset:soon",
+            "drift:fast",
+            "jitter:0",
+            "backward:500ms",
+            "backward:0/1s",
+            "freeze:1s",
+            "offset:1000d",
+        ] {
+            assert!(bad.parse::<Anomaly>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_offset_shifts_every_reading() {
+        let start = at("2024-03-15T10:00:00Z");
+        let fakes = run(&config("offset:-2s", 5000), &[0, 1000, 60_000]);
+        assert_eq!(fakes[0], start - Duration::seconds(2));
+        assert_eq!(fakes[1], start - Duration::seconds(1));
+        assert_eq!(fakes[2], start + Duration::seconds(58));
+    }
+
+    #[test]
+    fn test_drift_grows_with_elapsed_time() {
+        let start = at("2024-03-15T10:00:00Z");
+        let fakes = run(&config("drift:100", 1000), &[0, 1_000_000, 2_000_000]);
+        assert_eq!(fakes[0], start);
+        // 100 ppm of 1000 s is 100 ms
+        assert_eq!(fakes[1], start + Duration::milliseconds(1_000_100));
+        assert_eq!(fakes[2], start + Duration::milliseconds(2_000_200));
+
+        let fakes = run(&config("drift:-100", 1000), &[0, 1_000_000]);
+        assert_eq!(fakes[1], start + Duration::milliseconds(999_900));
+    }
+
+    #[test]
+    fn test_jitter_is_seeded_and_roughly_gaussian() {
+        let config = config("jitter:10ms", u64::MAX);
+        let offsets: Vec<i64> = (0..2000).map(|i| i * 1000).collect();
+        let start = at("2024-03-15T10:00:00Z");
+        let noise: Vec<f64> = run(&config, &offsets)
+            .iter()
+            .zip(&offsets)
+            .map(|(fake, ms)| {
+                (*fake - (start + Duration::milliseconds(*ms)))
+                    .num_microseconds()
+                    .unwrap() as f64
+                    / 1000.0
+            })
+            .collect();
+        let mean = noise.iter().sum::<f64>() / noise.len() as f64;
+        let stddev =
+            (noise.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / noise.len() as f64).sqrt();
+        assert!(mean.abs() < 1.0, "mean {}", mean);
+        assert!((stddev - 10.0).abs() < 1.0, "stddev {}", stddev);
+        // Same seed, same noise
+        assert_eq!(run(&config, &offsets[..10]), run(&config, &offsets[..10]));
+    }
+
+    #[test]
+    fn test_backward_steps_jump_back_each_period() {
+        let start = at("2024-03-15T10:00:00Z");
+        let fakes = run(
+            &config("backward:500ms/10s", 1000),
+            &[0, 9_999, 10_000, 19_999, 20_000],
+        );
+        assert_eq!(fakes[0], start);
+        assert_eq!(fakes[1], start + Duration::milliseconds(9_999));
+        assert_eq!(fakes[2], start + Duration::milliseconds(9_500));
+        assert_eq!(fakes[3], start + Duration::milliseconds(19_499));
+        assert_eq!(fakes[4], start + Duration::milliseconds(19_000));
+    }
+
+    #[test]
+    fn test_backward_steps_never_exceed_max_regression() {
+        // Each step (5 s) is larger than the allowed regression (1 s)
+        let config = config("backward:5s/2s", 1000);
+        let offsets: Vec<i64> = (0..200).map(|i| i * 100).collect();
+        let fakes = run(&config, &offsets);
+        let mut latest = fakes[0];
+        let mut regressed = false;
+        for fake in &fakes[1..] {
+            let behind = latest - *fake;
+            assert!(behind <= Duration::milliseconds(1000), "{:?}", behind);
+            regressed |= behind > Duration::zero();
+            latest = latest.max(*fake);
+        }
+        assert!(regressed, "the clock should still go backwards");
+    }
+
+    #[test]
+    fn test_zero_max_regression_keeps_time_monotonic() {
+        let config = config("jitter:50ms", 0);
+        let offsets: Vec<i64> = (0..500).map(|i| i * 10).collect();
+        let fakes = run(&config, &offsets);
+        assert!(fakes.windows(2).all(|pair| pair[1] >= pair[0]));
+    }
+
+    #[test]
+    fn test_freeze_holds_the_first_reading() {
+        let start = at("2024-03-15T10:00:00Z");
+        let fakes = run(&config("freeze", 1000), &[0, 1000, 3_600_000]);
+        assert!(fakes.iter().all(|fake| *fake == start));
+    }
+
+    #[test]
+    fn test_inactive_status_has_no_anomaly() {
+        let status = serde_json::to_value(inactive_status()).unwrap();
+        assert_eq!(status["active"], false);
+        assert!(status.get("mode").is_none());
+    }
+}
@@ -0,0 +1,188 @@
+// Argument completion for prompts (`completion/complete`)
+//
+// Shared by the rmcp server and the legacy stdio handler. Completion is keyed
+// on the argument name, so any prompt taking `timezone` or `format` gets it.
+
+use crate::time::{StandardFormats, TimezoneConverter};
+
+/// Most values returned for a single completion request
+pub const MAX_COMPLETIONS: usize = 20;
+
+/// Completion values plus how many matched in total
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completion {
+    pub values: Vec<String>,
+    pub total: usize,
+    pub has_more: bool,
+}
+
+impl Completion {
+    fn from_ranked(values: Vec<String>, total: usize) -> Self {
+        let has_more = total > MAX_COMPLETIONS;
+        Self {
+            values: values.into_iter().take(MAX_COMPLETIONS).collect(),
+            total,
+            has_more,
+        }
+    }
+}
+
+/// Complete the value of argument `name`; unknown arguments get no values
+pub fn complete_argument(name: &str, value: &str) -> Completion {
+    match name {
+        "timezone" | "from_timezone" | "to_timezone" => complete_timezone(value),
+        "timezones" => complete_timezone_list(value),
+        "format" => complete_format(value),
+        _ => Completion::default(),
+    }
+}
+
+/// IANA names starting with `partial` (case-insensitive) in alphabetical
+/// order, then city-name and fuzzy matches
+pub fn complete_timezone(partial: &str) -> Completion {
+    let needle = partial.trim().replace(' ', "_").to_lowercase();
+
+    let (prefixed, rest): (Vec<&str>, Vec<&str>) = TimezoneConverter::timezone_names()
+        .iter()
+        .partition(|name| name.to_lowercase().starts_with(&needle));
+    let prefix_matches = prefixed.len();
+    let mut values: Vec<String> = prefixed
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(str::to_string)
+        .collect();
+
+    if prefix_matches < MAX_COMPLETIONS && !needle.is_empty() {
+        // City names next ("tok" -> Asia/Tokyo), then substring and typo matches
+        let cities = rest
+            .into_iter()
+            .filter(|name| {
+                name.rsplit('/')
+                    .next()
+                    .is_some_and(|city| city.to_lowercase().starts_with(&needle))
+            })
+            .map(str::to_string);
+        let fuzzy = TimezoneConverter::suggest_timezones(partial, MAX_COMPLETIONS);
+        for name in cities.chain(fuzzy) {
+            if values.len() == MAX_COMPLETIONS {
+                break;
+            }
+            if !values.contains(&name) {
+                values.push(name);
+            }
+        }
+    }
+
+    let total = prefix_matches.max(values.len());
+    Completion::from_ranked(values, total)
+}
+
+/// Complete the last entry of a comma-separated timezone list, keeping the
+/// entries before it
+pub fn complete_timezone_list(partial: &str) -> Completion {
+    let (head, last) = match partial.rfind(',') {
+        Some(i) => (&partial[..=i], &partial[i + 1..]),
+        None => ("", partial),
+    };
+    let mut completion = complete_timezone(last);
+    for value in &mut completion.values {
+        value.insert_str(0, head);
+    }
+    completion
+}
+
+/// `StandardFormats` whose name or format string starts with `partial`
+pub fn complete_format(partial: &str) -> Completion {
+    let needle = partial.trim().to_lowercase();
+    let values: Vec<String> = StandardFormats::NAMED
+        .iter()
+        .filter(|f| {
+            f.name.to_lowercase().starts_with(&needle)
+                || f.format.to_lowercase().starts_with(&needle)
+        })
+        .map(|f| f.format.to_string())
+        .collect();
+    let total = values.len();
+    Completion::from_ranked(values, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_timezone_prefix_is_capped_and_sorted() {
+        let completion = complete_timezone("Amer");
+        assert_eq!(completion.values.len(), MAX_COMPLETIONS);
+        assert!(completion.has_more);
+        assert!(completion.total > MAX_COMPLETIONS);
+        assert!(completion.values.iter().all(|v| v.starts_with("America/")));
+        let mut sorted = completion.values.clone();
+        sorted.sort();
+        assert_eq!(completion.values, sorted);
+    }
+
+    #[test]
+    fn test_timezone_is_case_insensitive_with_fuzzy_fallback() {
+        let completion = complete_timezone("tok");
+        assert!(!completion.has_more);
+        assert_eq!(
+            completion.values.first().map(String::as_str),
+            Some("Asia/Tokyo")
+        );
+
+        // Exact prefix matches come before fuzzy ones
+        let completion = complete_timezone("europe/lon");
+        assert_eq!(
+            completion.values.first().map(String::as_str),
+            Some("Europe/London")
+        );
+        assert!(
+            completion.values.len() > 1,
+            "fuzzy matches follow the prefix match"
+        );
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_empty_input_lists_first_names() {
+        let completion = complete_timezone("");
+        assert_eq!(completion.values.len(), MAX_COMPLETIONS);
+        assert!(completion.has_more);
+        assert_eq!(completion.total, TimezoneConverter::list_timezones().len());
+        assert_eq!(completion.values[0], "Africa/Abidjan");
+    }
+
+    #[test]
+    fn test_format_completion() {
+        let all = complete_format("");
+        assert_eq!(all.values.len(), StandardFormats::NAMED.len());
+        assert!(!all.has_more);
+
+        let iso = complete_format("iso");
+        assert_eq!(iso.values, [StandardFormats::ISO_8601]);
+
+        let by_format = complete_format("%Y-%m-%d");
+        assert_eq!(
+            by_format.values,
+            [StandardFormats::ISO_8601, StandardFormats::RFC_3339]
+        );
+    }
+
+    #[test]
+    fn test_timezone_list_completes_last_entry() {
+        let completion = complete_argument("timezones", "UTC,Asia/Tok");
+        assert_eq!(
+            completion.values.first().map(String::as_str),
+            Some("UTC,Asia/Tokyo")
+        );
+        assert!(completion.values.iter().all(|v| v.starts_with("UTC,")));
+    }
+
+    #[test]
+    fn test_unknown_argument() {
+        assert_eq!(complete_argument("year", "20"), Completion::default());
+        assert_eq!(complete_argument("timezone", "tok").values[0], "Asia/Tokyo");
+    }
+}
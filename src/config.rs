@@ -0,0 +1,1152 @@
+// Server configuration, read from the environment once at startup
+//
+// `ServerConfig::load()` is the one place that decides what the server runs
+// with: every variable is parsed and validated up front, so a typo fails the
+// start with the variable's name instead of silently falling back to a
+// default. Legacy names are accepted as aliases; when both are set the
+// current name wins. The result is passed explicitly to `run()`,
+// `run_http_api_server()` and the service constructors.
+//
+// Variables (current name first, legacy aliases after it):
+//
+// | Setting                    | Variables                                   | Default     |
+// |----------------------------|---------------------------------------------|-------------|
+// | http.enabled               | ENABLE_HTTP_API, ENABLE_HEALTH_SERVER       | true        |
+// | http.port                  | HTTP_API_PORT, HEALTH_PORT                  | 3000        |
+// | http.bind_addr             | HTTP_API_BIND                               | 0.0.0.0     |
+// | http.cors_allow_origin     | CORS_ALLOW_ORIGIN                           | *           |
+// | http.compression_min_bytes | HTTP_COMPRESSION_MIN_BYTES                  | 1024        |
+// | http.static_max_age_secs   | HTTP_STATIC_MAX_AGE_SECS                    | 3600        |
+// | http.listeners             | HTTP_LISTENERS (addr;auth|noauth, ...)      | bind_addr:port, noauth |
+// | http.allow_partial_bind    | HTTP_LISTENERS_ALLOW_PARTIAL                | false       |
+// | http.metrics_legacy        | METRICS_LEGACY                              | false       |
+// | http.admin_token           | ADMIN_TOKEN (16+ characters)                | none (no /admin routes) |
+// | ntp                        | NTP_SERVERS, ENABLE_PPS, ENABLE_GPS, ...    | see NtpConfig |
+// | ntp.query                  | NTP_QUERY_CONCURRENCY, NTP_QUERY_QUEUE_WAIT_MS | 2, 3000   |
+// | ntp.history                | NTP_HISTORY_INTERVAL_SECS, NTP_HISTORY_CAPACITY, NTP_HISTORY_FILE | 60, 24h of samples, none |
+// | ntp.shm_retry              | NTP_SHM_RETRY_SECS, NTP_SHM_STALE_SECS      | 30, 60      |
+// | ntp.supervisor             | MANAGE_NTPD, NTPD_PATH, NTPD_CONFIG, NTPD_MAX_RESTARTS | off, /usr/sbin/ntpd, /etc/ntpsec/ntp.conf, 5 |
+// | auth.api_keys              | API_KEY_<NAME>, API_KEYS                    | none        |
+// | logging.filter             | RUST_LOG                                    | info        |
+// | logging.audit              | AUDIT_LOG_FILE, AUDIT_LOG_*                 | off         |
+// | logging.startup_banner     | STARTUP_BANNER (structured, pretty)         | structured  |
+// | mcp.request_timeout_secs   | MCP_REQUEST_TIMEOUT_SECS                    | 30          |
+// | mcp.lenient_params         | MCP_LENIENT_PARAMS                          | false       |
+// | mcp.bench_tool             | ENABLE_BENCH_TOOL                           | false       |
+// | mcp.disabled_tools         | DISABLE_NTP_TOOLS, DISABLE_SUBPROCESS, DISABLED_TOOLS | none |
+// | mcp.tool_timeouts          | TOOL_TIMEOUT_<NAME>_MS                      | built-in table |
+// | mcp.rate_limit             | MCP_RATE_LIMIT (per second[,burst] or off)  | 100,200     |
+// | mcp.schedule_max_pending   | SCHEDULE_MAX_PENDING                        | 32          |
+// | default_timezone           | DEFAULT_TIMEZONE                            | none (UTC)  |
+// | as_of_max_skew_secs        | AS_OF_MAX_SKEW_SECS                         | none (unlimited) |
+// | time_source.priority       | TIME_SOURCE_PRIORITY                        | shm,system  |
+// | validity.current_time_ms   | CURRENT_TIME_VALID_MS                       | 1000        |
+// | tzdata.caveat_days         | TZDATA_CAVEAT_DAYS                          | 365         |
+// | intervals.max_years        | MAX_INTERVAL_YEARS                          | 10000       |
+// | holidays.file              | HOLIDAY_CALENDAR_FILE                       | none (built-in US, UK, DE) |
+// | signing.key_file           | SIGNING_KEY_FILE                            | none (per-process key) |
+// | signing.state_file         | SIGNING_STATE_FILE                          | <key_file>.seq |
+// | health.max_offset_ms       | HEALTH_MAX_OFFSET_MS                        | 100         |
+// | health.max_jitter_ms       | HEALTH_MAX_JITTER_MS                        | 10          |
+// | health.require_sync        | HEALTH_REQUIRE_SYNC                         | true        |
+// | health.strict              | HEALTH_STRICT                               | false       |
+// | maintenance                | MAINTENANCE_WINDOW (cron + duration)        | none        |
+// | clock_guard                | ALLOW_IMPLAUSIBLE_CLOCK, CLOCK_MAX_FUTURE_YEARS | false, 20 |
+// | chaos                      | CHAOS_MODE (needs ALLOW_CHAOS=true), CHAOS_MAX_REGRESSION_MS, CHAOS_SEED | off, 1000 |
+// | alert                      | ALERT_WEBHOOK_URL, ALERT_OFFSET_MS, ALERT_COOLDOWN_SECS | off, 100, 300 |
+// | tsa_url                    | TSA_URL (https://, needs the tsa feature)   | none        |
+// | state                      | STATE_FILE, STATE_SAVE_INTERVAL_SECS        | off, 300    |
+// | mode.http_only             | HTTP_API_ONLY, CONTAINER_APP_NAME, KUBERNETES_SERVICE_HOST | false |
+// | mode.container             | /.dockerenv, CONTAINER_APP_NAME, KUBERNETES_SERVICE_HOST, SKIP_NTP_CHECK | detected |
+// | print_config               | PRINT_CONFIG or --print-config              | false       |
+
+#[cfg(all(feature = "ntp", feature = "http-api"))]
+use crate::alert::AlertConfig;
+use crate::attestation::SigningConfig;
+use crate::audit::AuditConfig;
+#[cfg(feature = "auth")]
+use crate::auth::ApiKey;
+use crate::chaos::{Anomaly, ChaosConfig};
+use crate::guard::GuardConfig;
+#[cfg(feature = "http-api")]
+use crate::http::cache::DEFAULT_STATIC_MAX_AGE_SECS;
+#[cfg(feature = "http-api")]
+use crate::http::listener::ListenerConfig;
+#[cfg(feature = "http-api")]
+use crate::http::response::DEFAULT_COMPRESSION_MIN_BYTES;
+use crate::maintenance::MaintenanceWindow;
+use crate::mcp::policy::ToolPolicy;
+use crate::mcp::rate_limit::RateLimit;
+use crate::mcp::timeouts::ToolTimeouts;
+#[cfg(feature = "ntp")]
+use crate::ntp::NtpConfig;
+use crate::persist::PersistConfig;
+use crate::scheduler::DEFAULT_MAX_PENDING;
+use crate::server::limits::DEFAULT_REQUEST_TIMEOUT_SECS;
+use crate::startup::BannerStyle;
+use crate::time::duration::DEFAULT_MAX_INTERVAL_YEARS;
+use crate::time::source::{TimeSource, DEFAULT_TIME_SOURCE_PRIORITY};
+use crate::time::tzdata::DEFAULT_TZDATA_CAVEAT_DAYS;
+use crate::validity::{ValidityConfig, DEFAULT_CURRENT_TIME_VALID_MS};
+use serde::Serialize;
+#[cfg(feature = "http-api")]
+use serde::Serializer;
+use std::collections::BTreeMap;
+#[cfg(feature = "http-api")]
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::warn;
+
+/// Log filter used when `RUST_LOG` is not set
+pub const DEFAULT_LOG_FILTER: &str = "mcp_utc_time_server=info,rmcp=warn";
+
+pub const DEFAULT_HTTP_PORT: u16 = 3000;
+
+#[cfg(feature = "http-api")]
+const REDACTED: &str = "[redacted]";
+
+/// Shortest `ADMIN_TOKEN` accepted
+#[cfg(feature = "http-api")]
+pub const MIN_ADMIN_TOKEN_LEN: usize = 16;
+
+/// A variable that is set but cannot be used
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {variable}={value:?}: expected {expected}")]
+pub struct ConfigError {
+    pub variable: String,
+    pub value: String,
+    pub expected: String,
+}
+
+impl ConfigError {
+    pub fn new(variable: &str, value: &str, expected: impl Into<String>) -> Self {
+        Self {
+            variable: variable.to_string(),
+            value: value.to_string(),
+            expected: expected.into(),
+        }
+    }
+}
+
+/// Snapshot of environment variables; tests build one from pairs
+#[derive(Debug, Clone, Default)]
+pub struct EnvVars(BTreeMap<String, String>);
+
+impl EnvVars {
+    pub fn from_process() -> Self {
+        Self(std::env::vars().collect())
+    }
+
+    pub fn from_pairs<K: Into<String>, V: Into<String>>(
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        Self(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The first of `names` that is set, with its value. `names[0]` is the
+    /// current name; using a later (legacy) one logs a deprecation warning.
+    pub fn lookup<'a>(&'a self, names: &[&'static str]) -> Option<(&'static str, &'a str)> {
+        let (index, name, value) = names
+            .iter()
+            .enumerate()
+            .find_map(|(i, name)| self.0.get(*name).map(|v| (i, *name, v.as_str())))?;
+        if index > 0 {
+            warn!(
+                event = "config.deprecated",
+                "{} is deprecated, use {}", name, names[0]
+            );
+        }
+        Some((name, value))
+    }
+
+    pub fn string(&self, names: &[&'static str]) -> Option<String> {
+        self.lookup(names).map(|(_, v)| v.to_string())
+    }
+
+    /// Parse the first set variable of `names`; `expected` describes valid values
+    pub fn parse<T: FromStr>(
+        &self,
+        names: &[&'static str],
+        expected: &str,
+    ) -> Result<Option<T>, ConfigError> {
+        self.lookup(names)
+            .map(|(name, value)| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConfigError::new(name, value, expected))
+            })
+            .transpose()
+    }
+
+    /// true/false/1/0/yes/no
+    pub fn flag(&self, names: &[&'static str]) -> Result<Option<bool>, ConfigError> {
+        self.lookup(names)
+            .map(|(name, value)| {
+                crate::output::parse_flag(value)
+                    .ok_or_else(|| ConfigError::new(name, value, "true or false"))
+            })
+            .transpose()
+    }
+
+    /// Variables whose name starts with `prefix`, in name order
+    pub fn with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        self.0
+            .range(prefix.to_string()..)
+            .take_while(move |(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[cfg(feature = "http-api")]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HttpConfig {
+    /// Run the HTTP API next to the stdio MCP server
+    pub enabled: bool,
+    pub port: u16,
+    pub bind_addr: IpAddr,
+    /// `*` or a comma-separated list of origins allowed to call the API
+    pub cors_allow_origin: String,
+    /// Responses at least this large are compressed when the client accepts it
+    pub compression_min_bytes: usize,
+    /// `max-age` sent with responses that never change while the process runs
+    pub static_max_age_secs: u64,
+    /// Sockets to serve on; empty means `bind_addr:port` without auth
+    pub listeners: Vec<ListenerConfig>,
+    /// Run with the listeners that bound when some of them could not
+    pub allow_partial_bind: bool,
+    /// `/metrics` also emits the metric names of earlier releases
+    pub metrics_legacy: bool,
+    /// Bearer token of the `/admin/` routes, which do not exist without it;
+    /// serialized as "[redacted]"
+    #[serde(serialize_with = "redact_token")]
+    pub admin_token: Option<String>,
+}
+
+#[cfg(feature = "http-api")]
+fn redact_token<S: Serializer>(token: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    token.as_ref().map(|_| REDACTED).serialize(serializer)
+}
+
+#[cfg(feature = "http-api")]
+impl HttpConfig {
+    /// HTTP_* variables; `;auth` listeners need API keys to check against
+    fn from_vars(env: &EnvVars, has_api_keys: bool) -> Result<Self, ConfigError> {
+        let listeners = match env.lookup(&["HTTP_LISTENERS"]) {
+            Some((name, value)) => {
+                let listeners = ListenerConfig::parse_list(value).map_err(|_| {
+                    ConfigError::new(
+                        name,
+                        value,
+                        "a comma-separated list of address:port[;auth|;noauth]",
+                    )
+                })?;
+                if !has_api_keys && listeners.iter().any(|l| l.auth) {
+                    return Err(ConfigError::new(
+                        name,
+                        value,
+                        if cfg!(feature = "auth") {
+                            "auth listeners only when API keys are configured"
+                        } else {
+                            "noauth listeners only (built without the auth feature)"
+                        },
+                    ));
+                }
+                listeners
+            }
+            None => Vec::new(),
+        };
+
+        let admin_token = match env.lookup(&["ADMIN_TOKEN"]) {
+            Some((name, value)) if value.trim().chars().count() < MIN_ADMIN_TOKEN_LEN => {
+                // Not echoed back: the value is a credential
+                return Err(ConfigError::new(
+                    name,
+                    REDACTED,
+                    format!("at least {} characters", MIN_ADMIN_TOKEN_LEN),
+                ));
+            }
+            Some((_, value)) => Some(value.trim().to_string()),
+            None => None,
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
+            enabled: env
+                .flag(&["ENABLE_HTTP_API", "ENABLE_HEALTH_SERVER"])?
+                .unwrap_or(defaults.enabled),
+            port: env
+                .parse(&["HTTP_API_PORT", "HEALTH_PORT"], "a port number (0-65535)")?
+                .unwrap_or(defaults.port),
+            bind_addr: env
+                .parse(&["HTTP_API_BIND"], "an IP address")?
+                .unwrap_or(defaults.bind_addr),
+            cors_allow_origin: env
+                .string(&["CORS_ALLOW_ORIGIN"])
+                .unwrap_or(defaults.cors_allow_origin),
+            compression_min_bytes: env
+                .parse(&["HTTP_COMPRESSION_MIN_BYTES"], "a byte count")?
+                .unwrap_or(defaults.compression_min_bytes),
+            static_max_age_secs: env
+                .parse(&["HTTP_STATIC_MAX_AGE_SECS"], "a number of seconds")?
+                .unwrap_or(defaults.static_max_age_secs),
+            listeners,
+            allow_partial_bind: env
+                .flag(&["HTTP_LISTENERS_ALLOW_PARTIAL"])?
+                .unwrap_or(defaults.allow_partial_bind),
+            metrics_legacy: env
+                .flag(&["METRICS_LEGACY"])?
+                .unwrap_or(defaults.metrics_legacy),
+            admin_token,
+        })
+    }
+
+    /// The configured listeners, or the single `bind_addr:port` one
+    pub fn effective_listeners(&self) -> Vec<ListenerConfig> {
+        if self.listeners.is_empty() {
+            vec![ListenerConfig {
+                addr: SocketAddr::new(self.bind_addr, self.port),
+                auth: false,
+            }]
+        } else {
+            self.listeners.clone()
+        }
+    }
+}
+
+#[cfg(feature = "http-api")]
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: DEFAULT_HTTP_PORT,
+            bind_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            cors_allow_origin: "*".to_string(),
+            compression_min_bytes: DEFAULT_COMPRESSION_MIN_BYTES,
+            static_max_age_secs: DEFAULT_STATIC_MAX_AGE_SECS,
+            listeners: Vec::new(),
+            allow_partial_bind: false,
+            metrics_legacy: false,
+            admin_token: None,
+        }
+    }
+}
+
+#[cfg(feature = "auth")]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuthConfig {
+    /// Serialized with the key values replaced by "[redacted]"
+    #[serde(serialize_with = "redact_keys")]
+    pub api_keys: Vec<ApiKey>,
+}
+
+#[cfg(feature = "auth")]
+fn redact_keys<S: Serializer>(keys: &[ApiKey], serializer: S) -> Result<S::Ok, S::Error> {
+    let redacted: Vec<ApiKey> = keys
+        .iter()
+        .map(|key| ApiKey {
+            key: REDACTED.to_string(),
+            ..key.clone()
+        })
+        .collect();
+    redacted.serialize(serializer)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingConfig {
+    /// tracing filter directives
+    pub filter: String,
+    pub audit: Option<AuditConfig>,
+    /// How the startup report is written
+    pub startup_banner: BannerStyle,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            filter: DEFAULT_LOG_FILTER.to_string(),
+            audit: None,
+            startup_banner: BannerStyle::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct McpConfig {
+    /// Deadline for a single tool call
+    pub request_timeout_secs: u64,
+    /// Drop unknown tool arguments instead of rejecting the call
+    pub lenient_params: bool,
+    /// Allow run_selftest_benchmark
+    pub bench_tool: bool,
+    /// Tools removed from tools/list and refused when called
+    pub disabled_tools: ToolPolicy,
+    /// Per-tool budgets overriding the built-in table
+    pub tool_timeouts: ToolTimeouts,
+    /// Tool calls each session may make; `None` when off
+    pub rate_limit: Option<RateLimit>,
+    /// Notifications one session may have pending with schedule_notification
+    pub schedule_max_pending: usize,
+}
+
+impl McpConfig {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: DEFAULT_REQUEST_TIMEOUT_SECS,
+            lenient_params: false,
+            bench_tool: false,
+            disabled_tools: ToolPolicy::default(),
+            tool_timeouts: ToolTimeouts::default(),
+            rate_limit: Some(RateLimit::default()),
+            schedule_max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeSourceConfig {
+    /// Sources asked for the current time, first usable one wins
+    pub priority: Vec<TimeSource>,
+}
+
+impl Default for TimeSourceConfig {
+    fn default() -> Self {
+        Self {
+            priority: DEFAULT_TIME_SOURCE_PRIORITY.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TzdataConfig {
+    /// Timezone results further ahead than this carry `tzdata_caveat`
+    pub caveat_days: u32,
+}
+
+impl Default for TzdataConfig {
+    fn default() -> Self {
+        Self {
+            caveat_days: DEFAULT_TZDATA_CAVEAT_DAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IntervalConfig {
+    /// Longer intervals are refused as a likely unit mix-up
+    pub max_years: u32,
+}
+
+impl Default for IntervalConfig {
+    fn default() -> Self {
+        Self {
+            max_years: DEFAULT_MAX_INTERVAL_YEARS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HolidaysConfig {
+    /// JSON file of extra holiday calendars, read at startup
+    pub file: Option<String>,
+}
+
+/// NTP offset beyond which `/health` reports degraded
+pub const DEFAULT_HEALTH_MAX_OFFSET_MS: f64 = 100.0;
+
+/// NTP system jitter beyond which `/health` reports degraded
+pub const DEFAULT_HEALTH_MAX_JITTER_MS: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthConfig {
+    /// Larger NTP offsets grade the clock degraded
+    pub max_offset_ms: f64,
+    /// Larger system jitter grades the clock degraded
+    pub max_jitter_ms: f64,
+    /// An unsynchronized clock is unhealthy rather than degraded
+    pub require_sync: bool,
+    /// `/health` returns 503 when unhealthy instead of always 200
+    pub strict: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            max_offset_ms: DEFAULT_HEALTH_MAX_OFFSET_MS,
+            max_jitter_ms: DEFAULT_HEALTH_MAX_JITTER_MS,
+            require_sync: true,
+            strict: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ModeConfig {
+    /// Serve only the HTTP API (no stdin for MCP stdio)
+    pub http_only: bool,
+    /// Running in a container; NTP tools report unavailable
+    pub container: bool,
+    /// What made `container` true: `/.dockerenv` or the variable that is set
+    pub container_signal: Option<&'static str>,
+}
+
+/// Everything the server reads from its environment
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerConfig {
+    #[cfg(feature = "http-api")]
+    pub http: HttpConfig,
+    #[cfg(feature = "ntp")]
+    pub ntp: NtpConfig,
+    #[cfg(feature = "auth")]
+    pub auth: AuthConfig,
+    pub logging: LoggingConfig,
+    pub mcp: McpConfig,
+    /// Timezone used when neither the call nor the session names one
+    pub default_timezone: Option<String>,
+    /// Furthest a client's `as_of` may be from the clock; `None` allows any
+    pub as_of_max_skew_secs: Option<u64>,
+    pub time_source: TimeSourceConfig,
+    /// How long responses say they stay correct
+    pub validity: ValidityConfig,
+    pub tzdata: TzdataConfig,
+    pub intervals: IntervalConfig,
+    pub holidays: HolidaysConfig,
+    /// Key and sequence files for get_signed_time
+    pub signing: SigningConfig,
+    pub health: HealthConfig,
+    /// Recurring window announced to clients, e.g. `0 2 * * SUN 15m`
+    pub maintenance: Option<MaintenanceWindow>,
+    /// When the system clock is too wrong to serve time from
+    pub clock_guard: GuardConfig,
+    /// Test-only clock anomaly; every response says it is on
+    pub chaos: Option<ChaosConfig>,
+    /// Counters and NTP history kept across restarts
+    pub state: Option<PersistConfig>,
+    /// Webhook told about NTP health transitions
+    #[cfg(all(feature = "ntp", feature = "http-api"))]
+    pub alert: Option<AlertConfig>,
+    /// RFC 3161 timestamp authority asked by get_trusted_timestamp
+    pub tsa_url: Option<String>,
+    pub mode: ModeConfig,
+    /// Dump the effective config as JSON and exit
+    pub print_config: bool,
+}
+
+impl ServerConfig {
+    /// Read and validate the process environment
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_vars(&EnvVars::from_process())
+    }
+
+    pub fn from_vars(env: &EnvVars) -> Result<Self, ConfigError> {
+        #[cfg(feature = "auth")]
+        let api_keys = crate::auth::api_key::load_keys(env);
+        #[cfg(feature = "auth")]
+        let has_api_keys = !api_keys.is_empty();
+        #[cfg(all(feature = "http-api", not(feature = "auth")))]
+        let has_api_keys = false;
+        #[cfg(feature = "http-api")]
+        let http = HttpConfig::from_vars(env, has_api_keys)?;
+
+        let filter = env
+            .string(&["RUST_LOG"])
+            .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string());
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&filter) {
+            return Err(ConfigError::new(
+                "RUST_LOG",
+                &filter,
+                format!("tracing filter directives ({})", e),
+            ));
+        }
+
+        let request_timeout_secs = match env.lookup(&["MCP_REQUEST_TIMEOUT_SECS"]) {
+            Some((name, value)) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&secs: &u64| secs > 0)
+                .ok_or_else(|| ConfigError::new(name, value, "a positive number of seconds"))?,
+            None => DEFAULT_REQUEST_TIMEOUT_SECS,
+        };
+
+        let max_interval_years = match env.lookup(&["MAX_INTERVAL_YEARS"]) {
+            Some((name, value)) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&years: &u32| years > 0)
+                .ok_or_else(|| ConfigError::new(name, value, "a positive number of years"))?,
+            None => DEFAULT_MAX_INTERVAL_YEARS,
+        };
+
+        let threshold_ms = |variable: &'static str, default: f64| match env.lookup(&[variable]) {
+            Some((name, value)) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&ms: &f64| ms.is_finite() && ms >= 0.0)
+                .ok_or_else(|| ConfigError::new(name, value, "a non-negative number of ms")),
+            None => Ok(default),
+        };
+        let max_offset_ms = threshold_ms("HEALTH_MAX_OFFSET_MS", DEFAULT_HEALTH_MAX_OFFSET_MS)?;
+        let max_jitter_ms = threshold_ms("HEALTH_MAX_JITTER_MS", DEFAULT_HEALTH_MAX_JITTER_MS)?;
+
+        let default_timezone = match env.lookup(&["DEFAULT_TIMEZONE"]) {
+            Some((name, value)) => Some(
+                crate::time::TimezoneConverter::resolve_name(value)
+                    .ok_or_else(|| ConfigError::new(name, value, "an IANA timezone name"))?,
+            ),
+            None => None,
+        };
+
+        let time_source = match env.lookup(&["TIME_SOURCE_PRIORITY"]) {
+            Some((name, value)) => {
+                let priority = TimeSource::parse_priority(value).map_err(|_| {
+                    ConfigError::new(
+                        name,
+                        value,
+                        "a comma-separated list of shm, ntp, gps, ptp, system",
+                    )
+                })?;
+                if !cfg!(feature = "ntp") && priority.iter().any(|&s| s != TimeSource::System) {
+                    return Err(ConfigError::new(
+                        name,
+                        value,
+                        "system (built without the ntp feature)",
+                    ));
+                }
+                TimeSourceConfig { priority }
+            }
+            None => TimeSourceConfig::default(),
+        };
+
+        let chaos = match env.lookup(&["CHAOS_MODE"]) {
+            Some((name, value)) => {
+                let anomaly: Anomaly = value.parse().map_err(|_| {
+                    ConfigError::new(
+                        name,
+                        value,
+                        "offset:<duration>, drift:<ppm>, jitter:<duration>, backward:<step>/<period> or freeze",
+                    )
+                })?;
+                if !env.flag(&["ALLOW_CHAOS"])?.unwrap_or(false) {
+                    return Err(ConfigError::new(
+                        name,
+                        value,
+                        "ALLOW_CHAOS=true as well; chaos mode is for testing only",
+                    ));
+                }
+                Some(ChaosConfig {
+                    anomaly,
+                    max_regression_ms: env
+                        .parse(&["CHAOS_MAX_REGRESSION_MS"], "a number of milliseconds")?
+                        .unwrap_or(crate::chaos::DEFAULT_MAX_REGRESSION_MS),
+                    seed: env
+                        .parse(&["CHAOS_SEED"], "an unsigned integer")?
+                        .unwrap_or(crate::chaos::DEFAULT_SEED),
+                })
+            }
+            None => None,
+        };
+
+        #[cfg(all(feature = "ntp", feature = "http-api"))]
+        let alert = match env
+            .string(&["ALERT_WEBHOOK_URL"])
+            .filter(|url| !url.is_empty())
+        {
+            Some(url) => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(ConfigError::new(
+                        "ALERT_WEBHOOK_URL",
+                        &url,
+                        "an http:// or https:// URL",
+                    ));
+                }
+                Some(AlertConfig {
+                    url,
+                    offset_ms: threshold_ms(
+                        "ALERT_OFFSET_MS",
+                        crate::alert::DEFAULT_ALERT_OFFSET_MS,
+                    )?,
+                    cooldown_secs: env
+                        .parse(&["ALERT_COOLDOWN_SECS"], "a number of seconds")?
+                        .unwrap_or(crate::alert::DEFAULT_ALERT_COOLDOWN_SECS),
+                })
+            }
+            None => None,
+        };
+
+        let tsa_url = match env.string(&["TSA_URL"]).filter(|url| !url.is_empty()) {
+            Some(url) if !cfg!(feature = "tsa") => {
+                return Err(ConfigError::new(
+                    "TSA_URL",
+                    &url,
+                    "unset (built without the tsa feature)",
+                ));
+            }
+            Some(url) if !url.starts_with("https://") => {
+                return Err(ConfigError::new("TSA_URL", &url, "an https:// URL"));
+            }
+            url => url,
+        };
+
+        let state = match env.string(&["STATE_FILE"]).filter(|path| !path.is_empty()) {
+            Some(file) => Some(PersistConfig {
+                file,
+                save_interval_secs: env
+                    .parse(&["STATE_SAVE_INTERVAL_SECS"], "a number of seconds")?
+                    .unwrap_or(crate::persist::DEFAULT_SAVE_INTERVAL_SECS),
+            }),
+            None => None,
+        };
+
+        let container_signal = if std::path::Path::new("/.dockerenv").exists() {
+            Some("/.dockerenv")
+        } else {
+            [
+                "KUBERNETES_SERVICE_HOST",
+                "CONTAINER_APP_NAME",
+                "SKIP_NTP_CHECK",
+            ]
+            .into_iter()
+            .find(|name| env.is_set(name))
+        };
+        let http_only_flag = env.flag(&["HTTP_API_ONLY"])?;
+        if !cfg!(feature = "http-api") && http_only_flag == Some(true) {
+            return Err(ConfigError::new(
+                "HTTP_API_ONLY",
+                &env.string(&["HTTP_API_ONLY"]).unwrap_or_default(),
+                "false (built without the http-api feature)",
+            ));
+        }
+        // Without the HTTP API a container still gets the stdio server
+        let http_only = cfg!(feature = "http-api")
+            && (http_only_flag.unwrap_or(false)
+                || env.is_set("CONTAINER_APP_NAME")
+                || env.is_set("KUBERNETES_SERVICE_HOST"));
+
+        Ok(Self {
+            #[cfg(feature = "http-api")]
+            http,
+            #[cfg(feature = "ntp")]
+            ntp: NtpConfig::from_vars(env)?,
+            #[cfg(feature = "auth")]
+            auth: AuthConfig { api_keys },
+            logging: LoggingConfig {
+                filter,
+                audit: AuditConfig::from_vars(env)?,
+                startup_banner: env
+                    .parse(&["STARTUP_BANNER"], "structured or pretty")?
+                    .unwrap_or_default(),
+            },
+            mcp: McpConfig {
+                request_timeout_secs,
+                lenient_params: env.flag(&["MCP_LENIENT_PARAMS"])?.unwrap_or(false),
+                bench_tool: env.flag(&["ENABLE_BENCH_TOOL"])?.unwrap_or(false),
+                disabled_tools: ToolPolicy::from_vars(env)?,
+                tool_timeouts: ToolTimeouts::from_vars(env)?,
+                rate_limit: RateLimit::from_vars(env)?,
+                schedule_max_pending: env
+                    .parse(&["SCHEDULE_MAX_PENDING"], "a number of notifications")?
+                    .unwrap_or(DEFAULT_MAX_PENDING),
+            },
+            default_timezone,
+            as_of_max_skew_secs: env.parse(&["AS_OF_MAX_SKEW_SECS"], "a number of seconds")?,
+            time_source,
+            validity: ValidityConfig {
+                current_time_ms: env
+                    .parse(&["CURRENT_TIME_VALID_MS"], "a number of milliseconds")?
+                    .unwrap_or(DEFAULT_CURRENT_TIME_VALID_MS),
+            },
+            tzdata: TzdataConfig {
+                caveat_days: env
+                    .parse(&["TZDATA_CAVEAT_DAYS"], "a number of days")?
+                    .unwrap_or(DEFAULT_TZDATA_CAVEAT_DAYS),
+            },
+            intervals: IntervalConfig {
+                max_years: max_interval_years,
+            },
+            holidays: HolidaysConfig {
+                file: env
+                    .string(&["HOLIDAY_CALENDAR_FILE"])
+                    .filter(|path| !path.is_empty()),
+            },
+            signing: SigningConfig::from_vars(env)?,
+            health: HealthConfig {
+                max_offset_ms,
+                max_jitter_ms,
+                require_sync: env.flag(&["HEALTH_REQUIRE_SYNC"])?.unwrap_or(true),
+                strict: env.flag(&["HEALTH_STRICT"])?.unwrap_or(false),
+            },
+            maintenance: env.parse(
+                &["MAINTENANCE_WINDOW"],
+                "a cron expression and a duration, e.g. '0 2 * * SUN 15m'",
+            )?,
+            clock_guard: GuardConfig {
+                allow_implausible: env.flag(&["ALLOW_IMPLAUSIBLE_CLOCK"])?.unwrap_or(false),
+                max_future_years: env
+                    .parse(&["CLOCK_MAX_FUTURE_YEARS"], "a number of years")?
+                    .unwrap_or(crate::guard::DEFAULT_MAX_FUTURE_YEARS),
+            },
+            chaos,
+            state,
+            #[cfg(all(feature = "ntp", feature = "http-api"))]
+            alert,
+            tsa_url,
+            mode: ModeConfig {
+                http_only,
+                container: container_signal.is_some(),
+                container_signal,
+            },
+            print_config: env.flag(&["PRINT_CONFIG"])?.unwrap_or(false),
+        })
+    }
+
+    /// Pretty JSON of the effective config; API keys are redacted
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(pairs: &[(&str, &str)]) -> Result<ServerConfig, ConfigError> {
+        ServerConfig::from_vars(&EnvVars::from_pairs(pairs.iter().copied()))
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = load(&[]).unwrap();
+        #[cfg(feature = "http-api")]
+        {
+            assert!(config.http.enabled);
+            assert_eq!(config.http.port, DEFAULT_HTTP_PORT);
+            assert_eq!(config.http.bind_addr.to_string(), "0.0.0.0");
+        }
+        assert_eq!(config.logging.filter, DEFAULT_LOG_FILTER);
+        assert!(config.logging.audit.is_none());
+        assert!(config.default_timezone.is_none());
+        assert!(!config.mode.http_only);
+        assert_eq!(config.time_source.priority, DEFAULT_TIME_SOURCE_PRIORITY);
+        assert_eq!(config.tzdata.caveat_days, DEFAULT_TZDATA_CAVEAT_DAYS);
+        assert_eq!(config.intervals.max_years, DEFAULT_MAX_INTERVAL_YEARS);
+        assert_eq!(config.validity, ValidityConfig::default());
+        assert!(config.holidays.file.is_none());
+        assert_eq!(config.signing, SigningConfig::default());
+        assert_eq!(config.health, HealthConfig::default());
+        assert!(config.maintenance.is_none());
+        assert_eq!(config.clock_guard, GuardConfig::default());
+        assert!(config.chaos.is_none());
+        assert!(config.state.is_none());
+        #[cfg(all(feature = "ntp", feature = "http-api"))]
+        assert!(config.alert.is_none());
+        assert_eq!(config.mcp.disabled_tools, ToolPolicy::default());
+        assert_eq!(config.mcp.schedule_max_pending, DEFAULT_MAX_PENDING);
+        #[cfg(feature = "ntp")]
+        {
+            assert_eq!(config.ntp.history.interval_secs, 60);
+            assert_eq!(config.ntp.history.capacity, 1440);
+        }
+    }
+
+    #[cfg(feature = "http-api")]
+    #[test]
+    fn test_current_name_wins_over_legacy_alias() {
+        let config = load(&[("HEALTH_PORT", "8081")]).unwrap();
+        assert_eq!(config.http.port, 8081);
+        let config = load(&[("HEALTH_PORT", "8081"), ("HTTP_API_PORT", "8082")]).unwrap();
+        assert_eq!(config.http.port, 8082);
+
+        let config = load(&[("ENABLE_HEALTH_SERVER", "false")]).unwrap();
+        assert!(!config.http.enabled);
+        let config = load(&[
+            ("ENABLE_HEALTH_SERVER", "false"),
+            ("ENABLE_HTTP_API", "true"),
+        ])
+        .unwrap();
+        assert!(config.http.enabled);
+    }
+
+    #[test]
+    fn test_invalid_values_name_the_variable() {
+        #[cfg(feature = "http-api")]
+        {
+            let error = load(&[("HEALTH_PORT", "http")]).unwrap_err();
+            assert_eq!(error.variable, "HEALTH_PORT");
+            assert_eq!(error.value, "http");
+        }
+
+        let mut invalid = vec![
+            ("MCP_REQUEST_TIMEOUT_SECS", "0"),
+            ("MCP_LENIENT_PARAMS", "maybe"),
+            ("ENABLE_BENCH_TOOL", "sometimes"),
+            ("CURRENT_TIME_VALID_MS", "soon"),
+            ("MAINTENANCE_WINDOW", "0 2 * * SUN"),
+            ("CHAOS_MODE", "wobble"),
+            ("ALLOW_IMPLAUSIBLE_CLOCK", "sometimes"),
+            ("CLOCK_MAX_FUTURE_YEARS", "forever"),
+            ("DISABLE_NTP_TOOLS", "maybe"),
+            ("DISABLED_TOOLS", "get_tiem"),
+            ("DEFAULT_TIMEZONE", "Mars/Olympus"),
+            ("TIME_SOURCE_PRIORITY", "shm,sundial"),
+            ("TZDATA_CAVEAT_DAYS", "a year"),
+            ("MAX_INTERVAL_YEARS", "0"),
+            ("SCHEDULE_MAX_PENDING", "lots"),
+            ("AUDIT_LOG_MAX_BYTES", "-1"),
+            ("HEALTH_MAX_OFFSET_MS", "-5"),
+            ("HEALTH_MAX_JITTER_MS", "NaN"),
+            ("HEALTH_STRICT", "maybe"),
+        ];
+        if cfg!(feature = "http-api") {
+            invalid.extend([
+                ("ENABLE_HTTP_API", "sometimes"),
+                ("HTTP_API_BIND", "localhost:80"),
+                ("HTTP_LISTENERS", "127.0.0.1:3000;tls"),
+                ("HTTP_LISTENERS_ALLOW_PARTIAL", "sometimes"),
+            ]);
+        } else {
+            invalid.push(("HTTP_API_ONLY", "true"));
+        }
+        if cfg!(feature = "ntp") {
+            invalid.extend([
+                ("LOCAL_STRATUM", "high"),
+                ("NTP_QUERY_CONCURRENCY", "0"),
+                ("NTP_HISTORY_INTERVAL_SECS", "0"),
+                ("NTP_HISTORY_CAPACITY", "all"),
+            ]);
+        } else {
+            invalid.push(("TIME_SOURCE_PRIORITY", "gps,system"));
+        }
+        for (name, value) in invalid {
+            let error = load(&[(name, value), ("AUDIT_LOG_FILE", "/tmp/audit.jsonl")]).unwrap_err();
+            assert_eq!(error.variable, name);
+            assert!(error.to_string().starts_with(&format!("invalid {}=", name)));
+        }
+    }
+
+    #[cfg(all(feature = "ntp", feature = "http-api"))]
+    #[test]
+    fn test_alert_webhook() {
+        let config = load(&[
+            ("ALERT_WEBHOOK_URL", "https://hooks.example.com/time"),
+            ("ALERT_OFFSET_MS", "25"),
+        ])
+        .unwrap();
+        let alert = config.alert.unwrap();
+        assert_eq!(alert.offset_ms, 25.0);
+        assert_eq!(
+            alert.cooldown_secs,
+            crate::alert::DEFAULT_ALERT_COOLDOWN_SECS
+        );
+        for (name, value) in [
+            ("ALERT_WEBHOOK_URL", "hooks.example.com"),
+            ("ALERT_OFFSET_MS", "-1"),
+            ("ALERT_COOLDOWN_SECS", "5m"),
+        ] {
+            let error = load(&[
+                ("ALERT_WEBHOOK_URL", "http://localhost:9000"),
+                (name, value),
+            ])
+            .unwrap_err();
+            assert_eq!(error.variable, name);
+        }
+    }
+
+    #[test]
+    fn test_tsa_url() {
+        assert!(load(&[]).unwrap().tsa_url.is_none());
+        let error = load(&[("TSA_URL", "http://timestamp.example.com")]).unwrap_err();
+        assert_eq!(error.variable, "TSA_URL");
+        let config = load(&[("TSA_URL", "https://timestamp.example.com/tsr")]);
+        if cfg!(feature = "tsa") {
+            assert_eq!(
+                config.unwrap().tsa_url.as_deref(),
+                Some("https://timestamp.example.com/tsr")
+            );
+        } else {
+            assert!(config.unwrap_err().to_string().contains("tsa feature"));
+        }
+    }
+
+    #[test]
+    fn test_state_file() {
+        let config = load(&[("STATE_FILE", "/var/lib/time/state.json")]).unwrap();
+        let state = config.state.unwrap();
+        assert_eq!(state.file, "/var/lib/time/state.json");
+        assert_eq!(
+            state.save_interval_secs,
+            crate::persist::DEFAULT_SAVE_INTERVAL_SECS
+        );
+        let error = load(&[
+            ("STATE_FILE", "state.json"),
+            ("STATE_SAVE_INTERVAL_SECS", "hourly"),
+        ])
+        .unwrap_err();
+        assert_eq!(error.variable, "STATE_SAVE_INTERVAL_SECS");
+        assert!(load(&[("STATE_FILE", "")]).unwrap().state.is_none());
+    }
+
+    #[test]
+    fn test_chaos_mode_needs_allow_chaos() {
+        let error = load(&[("CHAOS_MODE", "drift:50")]).unwrap_err();
+        assert_eq!(error.variable, "CHAOS_MODE");
+        assert!(error.expected.contains("ALLOW_CHAOS"));
+        let error = load(&[("CHAOS_MODE", "freeze"), ("ALLOW_CHAOS", "false")]).unwrap_err();
+        assert_eq!(error.variable, "CHAOS_MODE");
+
+        let config = load(&[
+            ("CHAOS_MODE", "backward:2s/1m"),
+            ("ALLOW_CHAOS", "true"),
+            ("CHAOS_MAX_REGRESSION_MS", "500"),
+        ])
+        .unwrap();
+        let chaos = config.chaos.unwrap();
+        assert_eq!(
+            chaos.anomaly,
+            Anomaly::BackwardSteps {
+                step_ms: 2000,
+                period_ms: 60_000
+            }
+        );
+        assert_eq!(chaos.max_regression_ms, 500);
+        assert_eq!(chaos.seed, crate::chaos::DEFAULT_SEED);
+    }
+
+    #[cfg(all(feature = "http-api", feature = "ntp"))]
+    #[test]
+    fn test_values_are_normalized() {
+        let config = load(&[
+            ("DEFAULT_TIMEZONE", "australia/sydney"),
+            ("HTTP_API_BIND", "127.0.0.1"),
+            ("HTTP_API_ONLY", "1"),
+            ("TIME_SOURCE_PRIORITY", "GPS, shm,system"),
+        ])
+        .unwrap();
+        assert_eq!(config.default_timezone.as_deref(), Some("Australia/Sydney"));
+        assert_eq!(config.http.bind_addr.to_string(), "127.0.0.1");
+        assert!(config.mode.http_only);
+        assert_eq!(
+            config.time_source.priority,
+            [TimeSource::Gps, TimeSource::Shm, TimeSource::System]
+        );
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_ntp_history_capacity_follows_interval() {
+        let config = load(&[("NTP_HISTORY_INTERVAL_SECS", "300")]).unwrap();
+        assert_eq!(config.ntp.history.capacity, 288);
+        let config = load(&[
+            ("NTP_HISTORY_INTERVAL_SECS", "300"),
+            ("NTP_HISTORY_CAPACITY", "10"),
+            ("NTP_HISTORY_FILE", "/var/lib/mcp/ntp-history.jsonl"),
+        ])
+        .unwrap();
+        assert_eq!(config.ntp.history.capacity, 10);
+        assert_eq!(
+            config.ntp.history.file.as_deref(),
+            Some("/var/lib/mcp/ntp-history.jsonl")
+        );
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_ntp_shm_retry() {
+        let config = load(&[]).unwrap();
+        assert_eq!(config.ntp.shm_retry.retry_secs, 30);
+        assert_eq!(config.ntp.shm_retry.stale_after_secs, 60);
+        let config = load(&[("NTP_SHM_RETRY_SECS", "5"), ("NTP_SHM_STALE_SECS", "10")]).unwrap();
+        assert_eq!(config.ntp.shm_retry.retry_secs, 5);
+        assert_eq!(config.ntp.shm_retry.stale_after_secs, 10);
+        let error = load(&[("NTP_SHM_RETRY_SECS", "0")]).unwrap_err();
+        assert_eq!(error.variable, "NTP_SHM_RETRY_SECS");
+    }
+
+    #[cfg(feature = "auth")]
+    #[test]
+    fn test_http_listeners() {
+        let config = load(&[("HTTP_API_BIND", "127.0.0.1"), ("HTTP_API_PORT", "8080")]).unwrap();
+        assert_eq!(
+            config.http.effective_listeners(),
+            [ListenerConfig::parse("127.0.0.1:8080;noauth").unwrap()]
+        );
+
+        let config = load(&[
+            ("HTTP_LISTENERS", "127.0.0.1:3000;noauth,[::]:8443;auth"),
+            ("HTTP_LISTENERS_ALLOW_PARTIAL", "true"),
+            ("API_KEY_CI", "secret-ci-key"),
+        ])
+        .unwrap();
+        let listeners = config.http.effective_listeners();
+        assert_eq!(listeners.len(), 2);
+        assert!(!listeners[0].auth && listeners[1].auth);
+        assert!(config.http.allow_partial_bind);
+
+        // An auth listener without keys would refuse every request
+        let error = load(&[("HTTP_LISTENERS", "[::]:8443;auth")]).unwrap_err();
+        assert_eq!(error.variable, "HTTP_LISTENERS");
+    }
+
+    #[cfg(feature = "http-api")]
+    #[test]
+    fn test_admin_token() {
+        assert!(load(&[]).unwrap().http.admin_token.is_none());
+
+        let config = load(&[("ADMIN_TOKEN", "correct-horse-battery")]).unwrap();
+        assert_eq!(
+            config.http.admin_token.as_deref(),
+            Some("correct-horse-battery")
+        );
+        let json = config.to_json();
+        assert!(!json.contains("correct-horse-battery"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["http"]["admin_token"], REDACTED);
+
+        let error = load(&[("ADMIN_TOKEN", "short")]).unwrap_err();
+        assert_eq!(error.variable, "ADMIN_TOKEN");
+        assert!(!error.to_string().contains("short\""));
+    }
+
+    #[cfg(feature = "auth")]
+    #[test]
+    fn test_print_config_redacts_api_keys() {
+        let config = load(&[
+            ("API_KEY_CI", "secret-ci-key"),
+            ("API_KEYS", "legacy-one,legacy-two"),
+            ("PRINT_CONFIG", "true"),
+        ])
+        .unwrap();
+        assert!(config.print_config);
+        assert_eq!(config.auth.api_keys.len(), 3);
+
+        let json = config.to_json();
+        assert!(!json.contains("secret-ci-key"));
+        assert!(!json.contains("legacy-one"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["auth"]["api_keys"][0]["key"], REDACTED);
+        assert_eq!(value["auth"]["api_keys"][0]["name"], "Key CI");
+        assert_eq!(value["http"]["port"], DEFAULT_HTTP_PORT);
+    }
+}
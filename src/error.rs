@@ -1,7 +1,333 @@
 // Custom error types for MCP time server
+//
+// `TimeServerError` is what the time, NTP and auth code returns. Each variant
+// carries a stable machine-readable code and maps to one JSON-RPC error code
+// and one HTTP status, so the rmcp server, the legacy stdio handler and the
+// HTTP API all report the same failure the same way.
 
+use serde_json::{json, Value};
 use thiserror::Error;
 
+/// JSON-RPC "Invalid params"
+pub const JSONRPC_INVALID_PARAMS: i32 = -32602;
+/// JSON-RPC "Internal error"
+pub const JSONRPC_INTERNAL_ERROR: i32 = -32603;
+/// Server-defined: the caller is not allowed to make this request
+pub const JSONRPC_UNAUTHORIZED: i32 = -32001;
+/// Server-defined: NTP status or offset could not be obtained
+pub const JSONRPC_NTP_UNAVAILABLE: i32 = -32002;
+/// Server-defined: a bounded resource is saturated; retry later
+pub const JSONRPC_BUSY: i32 = -32003;
+/// Server-defined: the tool is switched off by server policy
+pub const JSONRPC_TOOL_DISABLED: i32 = -32004;
+/// Server-defined: the tool ran past its time budget
+pub const JSONRPC_DEADLINE_EXCEEDED: i32 = -32000;
+/// Server-defined: the system clock is implausible, so no time is served
+pub const JSONRPC_CLOCK_IMPLAUSIBLE: i32 = -32005;
+/// Server-defined: the session called tools faster than its rate limit
+pub const JSONRPC_RATE_LIMITED: i32 = -32006;
+/// MCP: a request other than `initialize` arrived before the handshake
+/// finished (the legacy stdio handler only; shares its value with
+/// `JSONRPC_NTP_UNAVAILABLE`)
+pub const JSONRPC_NOT_INITIALIZED: i32 = -32002;
+
+/// Suggestions attached to an `InvalidTimezone` error
+const TIMEZONE_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum TimeServerError {
+    #[error("Invalid timezone: {name}")]
+    InvalidTimezone {
+        name: String,
+        /// Close IANA names, best first
+        suggestions: Vec<String>,
+    },
+
+    #[error("Invalid format string '{spec}'{}", offset.map(|o| format!(" at byte {}", o)).unwrap_or_default())]
+    InvalidFormat {
+        spec: String,
+        /// Byte offset of the first unsupported specifier
+        offset: Option<usize>,
+    },
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    #[error("Timestamp {value} is outside the supported range (years 1 to 9999 UTC)")]
+    TimestampOutOfRange {
+        /// The value as given, in its own unit
+        value: String,
+    },
+
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    /// One of several inputs failed; `field` names it
+    #[error("{field}: {reason}")]
+    InvalidValue {
+        field: String,
+        reason: String,
+        /// Code of the underlying error ("invalid_timestamp", ...)
+        cause: &'static str,
+    },
+
+    /// Usually a timestamp in the wrong unit, e.g. milliseconds read as seconds
+    #[error("Interval of {years} years exceeds the limit of {max_years} years; check that both ends are in the same unit (milliseconds read as seconds?)")]
+    IntervalTooLong { years: u64, max_years: u32 },
+
+    #[error("NTP unavailable: {reason}")]
+    NtpUnavailable { reason: String },
+
+    #[error("Server busy, try again in {retry_after_ms} ms")]
+    Busy { retry_after_ms: u64 },
+
+    #[error("Rate limit exceeded, try again in {retry_after_ms} ms")]
+    RateLimited { retry_after_ms: u64 },
+
+    #[error("Clock error: {0}")]
+    ClockError(String),
+
+    #[error("Clock implausible: {reason}")]
+    ClockImplausible { reason: String },
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Unknown parameter '{field}' for {tool}{}", suggestion.as_ref().map(|s| format!("; did you mean '{}'?", s)).unwrap_or_default())]
+    UnknownField {
+        tool: String,
+        field: String,
+        suggestion: Option<String>,
+    },
+
+    #[error("Signing unavailable: {0}")]
+    SigningUnavailable(String),
+
+    #[error("Tool '{tool}' is disabled by server policy")]
+    ToolDisabled { tool: String },
+
+    #[error("Tool '{tool}' exceeded its {budget_ms} ms budget")]
+    DeadlineExceeded { tool: String, budget_ms: u64 },
+
+    #[error("API version '{requested}' is not supported; use one of {}", supported.join(", "))]
+    UnsupportedApiVersion {
+        requested: String,
+        supported: Vec<String>,
+    },
+
+    #[error("{feature} support is not compiled in; rebuild with --features {feature}")]
+    NotCompiledIn { feature: String },
+
+    #[error("{operation} is not supported on {platform}")]
+    NotSupported { operation: String, platform: String },
+
+    #[error("Timestamp authority unavailable: {reason}")]
+    TsaUnavailable { reason: String },
+
+    #[error("Timestamp authority refused the request ({status}){}", text.as_ref().map(|t| format!(": {}", t)).unwrap_or_default())]
+    TsaRejected {
+        status: String,
+        text: Option<String>,
+        failures: Vec<String>,
+    },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl TimeServerError {
+    /// `InvalidTimezone` with suggestions for `name`
+    pub fn invalid_timezone(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let suggestions =
+            crate::time::TimezoneConverter::suggest_timezones(&name, TIMEZONE_SUGGESTIONS);
+        Self::InvalidTimezone { name, suggestions }
+    }
+
+    /// `InvalidValue` naming `field`, wrapping the error it produced
+    pub fn for_value(field: impl Into<String>, error: TimeServerError) -> Self {
+        Self::InvalidValue {
+            field: field.into(),
+            reason: error.to_string(),
+            cause: error.code(),
+        }
+    }
+
+    /// `NotSupported` for `operation` on the OS this was built for
+    pub fn not_supported(operation: impl Into<String>) -> Self {
+        Self::NotSupported {
+            operation: operation.into(),
+            platform: std::env::consts::OS.to_string(),
+        }
+    }
+
+    /// `TimestampOutOfRange` for `value`
+    pub fn out_of_range(value: impl Into<String>) -> Self {
+        Self::TimestampOutOfRange {
+            value: value.into(),
+        }
+    }
+
+    /// Stable machine-readable code ("invalid_timezone", ...)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidTimezone { .. } => "invalid_timezone",
+            Self::InvalidFormat { .. } => "invalid_format",
+            Self::InvalidTimestamp(_) => "invalid_timestamp",
+            Self::TimestampOutOfRange { .. } => "timestamp_out_of_range",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::InvalidValue { .. } => "invalid_value",
+            Self::IntervalTooLong { .. } => "interval_too_long",
+            Self::NtpUnavailable { .. } => "ntp_unavailable",
+            Self::Busy { .. } => "busy",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::ClockError(_) => "clock_error",
+            Self::ClockImplausible { .. } => "clock_implausible",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::UnknownField { .. } => "unknown_field",
+            Self::SigningUnavailable(_) => "signing_unavailable",
+            Self::ToolDisabled { .. } => "tool_disabled",
+            Self::DeadlineExceeded { .. } => "deadline_exceeded",
+            Self::UnsupportedApiVersion { .. } => "unsupported_api_version",
+            Self::NotCompiledIn { .. } => "not_compiled_in",
+            Self::NotSupported { .. } => "not_supported",
+            Self::TsaUnavailable { .. } => "tsa_unavailable",
+            Self::TsaRejected { .. } => "tsa_rejected",
+            Self::Io(_) => "io_error",
+        }
+    }
+
+    pub fn jsonrpc_code(&self) -> i32 {
+        match self {
+            Self::InvalidTimezone { .. }
+            | Self::InvalidFormat { .. }
+            | Self::InvalidTimestamp(_)
+            | Self::TimestampOutOfRange { .. }
+            | Self::InvalidArgument(_)
+            | Self::InvalidValue { .. }
+            | Self::IntervalTooLong { .. }
+            | Self::UnknownField { .. }
+            | Self::UnsupportedApiVersion { .. } => JSONRPC_INVALID_PARAMS,
+            Self::NtpUnavailable { .. } => JSONRPC_NTP_UNAVAILABLE,
+            Self::Busy { .. } => JSONRPC_BUSY,
+            Self::RateLimited { .. } => JSONRPC_RATE_LIMITED,
+            Self::Unauthorized(_) => JSONRPC_UNAUTHORIZED,
+            Self::ToolDisabled { .. } => JSONRPC_TOOL_DISABLED,
+            Self::DeadlineExceeded { .. } => JSONRPC_DEADLINE_EXCEEDED,
+            Self::ClockImplausible { .. } => JSONRPC_CLOCK_IMPLAUSIBLE,
+            Self::ClockError(_)
+            | Self::SigningUnavailable(_)
+            | Self::NotCompiledIn { .. }
+            | Self::NotSupported { .. }
+            | Self::TsaUnavailable { .. }
+            | Self::TsaRejected { .. }
+            | Self::Io(_) => JSONRPC_INTERNAL_ERROR,
+        }
+    }
+
+    /// HTTP status code and reason phrase
+    pub fn http_status(&self) -> (u16, &'static str) {
+        match self {
+            Self::InvalidTimezone { .. } => (404, "Not Found"),
+            Self::InvalidFormat { .. }
+            | Self::InvalidTimestamp(_)
+            | Self::TimestampOutOfRange { .. }
+            | Self::InvalidArgument(_)
+            | Self::InvalidValue { .. }
+            | Self::IntervalTooLong { .. }
+            | Self::UnknownField { .. } => (400, "Bad Request"),
+            Self::Unauthorized(_) => (401, "Unauthorized"),
+            Self::ToolDisabled { .. } => (403, "Forbidden"),
+            Self::UnsupportedApiVersion { .. } => (406, "Not Acceptable"),
+            Self::RateLimited { .. } => (429, "Too Many Requests"),
+            Self::NtpUnavailable { .. }
+            | Self::Busy { .. }
+            | Self::SigningUnavailable(_)
+            | Self::TsaUnavailable { .. }
+            | Self::ClockImplausible { .. } => (503, "Service Unavailable"),
+            Self::TsaRejected { .. } => (502, "Bad Gateway"),
+            Self::NotCompiledIn { .. } | Self::NotSupported { .. } => (501, "Not Implemented"),
+            Self::DeadlineExceeded { .. } => (504, "Gateway Timeout"),
+            Self::ClockError(_) | Self::Io(_) => (500, "Internal Server Error"),
+        }
+    }
+
+    /// Variant-specific fields, `null` when there are none
+    pub fn details(&self) -> Value {
+        match self {
+            Self::InvalidTimezone { name, suggestions } => {
+                json!({"timezone": name, "suggestions": suggestions})
+            }
+            Self::InvalidFormat { spec, offset } => json!({"format": spec, "offset": offset}),
+            Self::TimestampOutOfRange { value } => json!({
+                "value": value,
+                "min_unix_seconds": crate::time::unix::MIN_SECONDS,
+                "max_unix_seconds": crate::time::unix::MAX_SECONDS,
+            }),
+            Self::InvalidValue { field, cause, .. } => json!({"field": field, "cause": cause}),
+            Self::IntervalTooLong { years, max_years } => {
+                json!({"years": years, "max_years": max_years})
+            }
+            Self::NtpUnavailable { reason }
+            | Self::ClockImplausible { reason }
+            | Self::TsaUnavailable { reason } => json!({"reason": reason}),
+            Self::TsaRejected {
+                status,
+                text,
+                failures,
+            } => json!({"status": status, "status_text": text, "failure_info": failures}),
+            Self::Busy { retry_after_ms } | Self::RateLimited { retry_after_ms } => {
+                json!({"retry_after_ms": retry_after_ms})
+            }
+            Self::ToolDisabled { tool } => json!({"tool": tool}),
+            Self::DeadlineExceeded { tool, budget_ms } => {
+                json!({"tool": tool, "budget_ms": budget_ms})
+            }
+            Self::NotCompiledIn { feature } => json!({"feature": feature}),
+            Self::NotSupported {
+                operation,
+                platform,
+            } => json!({"operation": operation, "platform": platform}),
+            Self::UnsupportedApiVersion {
+                requested,
+                supported,
+            } => json!({"requested": requested, "supported": supported}),
+            Self::UnknownField {
+                tool,
+                field,
+                suggestion,
+            } => json!({"tool": tool, "field": field, "suggestion": suggestion}),
+            _ => Value::Null,
+        }
+    }
+
+    /// `{code, message, details}` body shared by HTTP responses and JSON-RPC error data
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "details": self.details(),
+        })
+    }
+}
+
+impl From<TimeServerError> for rmcp::ErrorData {
+    fn from(error: TimeServerError) -> Self {
+        rmcp::ErrorData::new(
+            rmcp::model::ErrorCode(error.jsonrpc_code()),
+            error.to_string(),
+            Some(error.to_json()),
+        )
+    }
+}
+
+/// Lets callers still on `Result<_, String>` keep using `?`
+impl From<TimeServerError> for String {
+    fn from(error: TimeServerError) -> Self {
+        error.to_string()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum McpError {
     #[error("Invalid request: {0}")]
@@ -25,6 +351,18 @@ pub enum McpError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    #[error("Server not initialized")]
+    NotInitialized,
+
+    #[error("Unsupported protocol version: {requested}")]
+    UnsupportedProtocolVersion {
+        requested: String,
+        supported: Vec<&'static str>,
+    },
+
+    #[error(transparent)]
+    Time(#[from] TimeServerError),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -38,11 +376,169 @@ impl McpError {
             McpError::ParseError(_) => -32700,
             McpError::InvalidRequest(_) => -32600,
             McpError::MethodNotFound(_) => -32601,
-            McpError::InvalidParams(_) => -32602,
-            McpError::InternalError(_) => -32603,
+            McpError::InvalidParams(_) => JSONRPC_INVALID_PARAMS,
+            McpError::InternalError(_) => JSONRPC_INTERNAL_ERROR,
+            McpError::NotInitialized => JSONRPC_NOT_INITIALIZED,
+            McpError::UnsupportedProtocolVersion { .. } => JSONRPC_INVALID_PARAMS,
+            McpError::Time(e) => e.jsonrpc_code(),
             _ => -32000,
         }
     }
+
+    /// JSON-RPC `error.data`, set for errors from the time layer and for
+    /// version negotiation failures
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            McpError::Time(e) => Some(e.to_json()),
+            McpError::UnsupportedProtocolVersion {
+                requested,
+                supported,
+            } => Some(json!({"requested": requested, "supported": supported})),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, McpError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variants() -> Vec<TimeServerError> {
+        vec![
+            TimeServerError::invalid_timezone("Europe/Londn"),
+            TimeServerError::InvalidFormat {
+                spec: "%Y-%Q".into(),
+                offset: Some(3),
+            },
+            TimeServerError::InvalidTimestamp("out of range".into()),
+            TimeServerError::out_of_range("253402300800"),
+            TimeServerError::InvalidArgument("bad".into()),
+            TimeServerError::for_value("b", TimeServerError::InvalidTimestamp("bad".into())),
+            TimeServerError::IntervalTooLong {
+                years: 54_000,
+                max_years: 10_000,
+            },
+            TimeServerError::NtpUnavailable {
+                reason: "ntpq failed".into(),
+            },
+            TimeServerError::Busy {
+                retry_after_ms: 3000,
+            },
+            TimeServerError::RateLimited { retry_after_ms: 10 },
+            TimeServerError::ClockError("clock_gettime failed".into()),
+            TimeServerError::ClockImplausible {
+                reason: "system clock reads 1971-03-04T05:06:07Z".into(),
+            },
+            TimeServerError::Unauthorized("missing API key".into()),
+            TimeServerError::UnknownField {
+                tool: "get_time".into(),
+                field: "time_zone".into(),
+                suggestion: Some("timezone".into()),
+            },
+            TimeServerError::SigningUnavailable("key file unreadable".into()),
+            TimeServerError::ToolDisabled {
+                tool: "get_ntp_peers".into(),
+            },
+            TimeServerError::DeadlineExceeded {
+                tool: "get_ntp_peers".into(),
+                budget_ms: 10000,
+            },
+            TimeServerError::UnsupportedApiVersion {
+                requested: "3".into(),
+                supported: vec!["1".into(), "2".into()],
+            },
+            TimeServerError::NotCompiledIn {
+                feature: "geo".into(),
+            },
+            TimeServerError::NotSupported {
+                operation: "NTP shared memory".into(),
+                platform: "windows".into(),
+            },
+            TimeServerError::TsaUnavailable {
+                reason: "connection refused".into(),
+            },
+            TimeServerError::TsaRejected {
+                status: "rejection".into(),
+                text: Some("unsupported policy".into()),
+                failures: vec!["unaccepted_policy".into()],
+            },
+            TimeServerError::Io(std::io::Error::other("disk")),
+        ]
+    }
+
+    #[test]
+    fn test_each_variant_maps_to_jsonrpc_and_http() {
+        let expected = [
+            ("invalid_timezone", -32602, 404),
+            ("invalid_format", -32602, 400),
+            ("invalid_timestamp", -32602, 400),
+            ("timestamp_out_of_range", -32602, 400),
+            ("invalid_argument", -32602, 400),
+            ("invalid_value", -32602, 400),
+            ("interval_too_long", -32602, 400),
+            ("ntp_unavailable", -32002, 503),
+            ("busy", -32003, 503),
+            ("rate_limited", -32006, 429),
+            ("clock_error", -32603, 500),
+            ("clock_implausible", -32005, 503),
+            ("unauthorized", -32001, 401),
+            ("unknown_field", -32602, 400),
+            ("signing_unavailable", -32603, 503),
+            ("tool_disabled", -32004, 403),
+            ("deadline_exceeded", -32000, 504),
+            ("unsupported_api_version", -32602, 406),
+            ("not_compiled_in", -32603, 501),
+            ("not_supported", -32603, 501),
+            ("tsa_unavailable", -32603, 503),
+            ("tsa_rejected", -32603, 502),
+            ("io_error", -32603, 500),
+        ];
+        for (error, (code, jsonrpc, status)) in variants().into_iter().zip(expected) {
+            assert_eq!(error.code(), code);
+            assert_eq!(error.jsonrpc_code(), jsonrpc, "{}", code);
+            assert_eq!(error.http_status().0, status, "{}", code);
+
+            let body = error.to_json();
+            assert_eq!(body["code"], code);
+            assert_eq!(body["message"], error.to_string());
+
+            let message = error.to_string();
+            let rpc: rmcp::ErrorData = error.into();
+            assert_eq!(rpc.code.0, jsonrpc);
+            assert_eq!(rpc.message, message);
+            assert_eq!(rpc.data.unwrap()["code"], code);
+        }
+    }
+
+    #[test]
+    fn test_details() {
+        let error = TimeServerError::invalid_timezone("Europe/Londn");
+        assert_eq!(error.details()["timezone"], "Europe/Londn");
+        assert_eq!(error.details()["suggestions"][0], "Europe/London");
+
+        let error = TimeServerError::InvalidFormat {
+            spec: "%Y-%Q".into(),
+            offset: Some(3),
+        };
+        assert_eq!(error.to_string(), "Invalid format string '%Y-%Q' at byte 3");
+        assert_eq!(error.details(), json!({"format": "%Y-%Q", "offset": 3}));
+
+        assert_eq!(
+            TimeServerError::ClockError("x".into()).details(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_legacy_error_keeps_code_and_data() {
+        let error = McpError::from(TimeServerError::NtpUnavailable {
+            reason: "down".into(),
+        });
+        assert_eq!(error.code(), -32002);
+        assert_eq!(error.to_string(), "NTP unavailable: down");
+        assert_eq!(error.data().unwrap()["details"]["reason"], "down");
+        assert_eq!(McpError::InvalidParams("x".into()).data(), None);
+    }
+}
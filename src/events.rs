@@ -0,0 +1,278 @@
+// In-process bus for server diagnostics
+//
+// Subsystems publish a `ServerEvent` once (NTP losing sync, a clock step,
+// repeated auth failures). `publish` writes it to tracing and broadcasts it;
+// each MCP session subscribes and forwards what passes its filter as
+// `notifications/message`. The filter applies the level set with
+// `logging/setLevel` and lets through at most one event of a kind per minute,
+// so a flapping source cannot flood a client.
+
+use chrono::{SecondsFormat, Utc};
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Minimum gap between two notifications of the same kind to one client
+pub const NOTIFY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Level a session starts at, before any `logging/setLevel`
+pub const DEFAULT_NOTIFY_LEVEL: LoggingLevel = LoggingLevel::Info;
+
+/// Events a slow subscriber may fall behind by before it skips ahead
+const BUS_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerEvent {
+    /// Stable identifier and rate-limit key ("ntp.sync_lost"); the part
+    /// before the first dot is the MCP logger name
+    pub kind: &'static str,
+    #[serde(skip)]
+    pub level: LoggingLevel,
+    pub message: String,
+    /// RFC 3339, UTC
+    pub at: String,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl ServerEvent {
+    pub fn new(kind: &'static str, level: LoggingLevel, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            level,
+            message: message.into(),
+            at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            details: Value::Null,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// "ntp" for "ntp.sync_lost"
+    pub fn logger(&self) -> &'static str {
+        self.kind.split('.').next().unwrap_or(self.kind)
+    }
+
+    pub fn to_notification(&self) -> LoggingMessageNotificationParam {
+        LoggingMessageNotificationParam {
+            level: self.level,
+            logger: Some(self.logger().to_string()),
+            data: serde_json::to_value(self).unwrap_or_default(),
+        }
+    }
+}
+
+/// Position of `level` in RFC 5424 severity order, debug lowest
+pub fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+pub struct EventBus {
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(BUS_CAPACITY).0,
+        }
+    }
+
+    /// Log `event` and hand it to every current subscriber
+    pub fn publish(&self, event: ServerEvent) {
+        let kind = event.kind;
+        match severity(event.level) {
+            0 => tracing::debug!(event = kind, details = %event.details, "{}", event.message),
+            1 | 2 => tracing::info!(event = kind, details = %event.details, "{}", event.message),
+            3 => tracing::warn!(event = kind, details = %event.details, "{}", event.message),
+            _ => tracing::error!(event = kind, details = %event.details, "{}", event.message),
+        }
+        // No subscribers is not an error: the event has been logged
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// The process-wide bus
+pub fn global() -> &'static EventBus {
+    static GLOBAL: OnceLock<EventBus> = OnceLock::new();
+    GLOBAL.get_or_init(EventBus::new)
+}
+
+/// Publish on the process-wide bus
+pub fn publish(event: ServerEvent) {
+    global().publish(event);
+}
+
+/// One subscriber's level threshold and per-kind rate limit
+#[derive(Debug)]
+pub struct NotificationFilter {
+    level: LoggingLevel,
+    interval: Duration,
+    last_sent: HashMap<&'static str, Instant>,
+}
+
+impl Default for NotificationFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_NOTIFY_LEVEL, NOTIFY_INTERVAL)
+    }
+}
+
+impl NotificationFilter {
+    pub fn new(level: LoggingLevel, interval: Duration) -> Self {
+        Self {
+            level,
+            interval,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    pub fn level(&self) -> LoggingLevel {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: LoggingLevel) {
+        self.level = level;
+    }
+
+    /// Whether to forward `event` at `now`; an admitted event starts the
+    /// interval for its kind
+    pub fn admit(&mut self, event: &ServerEvent, now: Instant) -> bool {
+        if severity(event.level) < severity(self.level) {
+            return false;
+        }
+        match self.last_sent.get(event.kind) {
+            Some(&sent) if now.saturating_duration_since(sent) < self.interval => false,
+            _ => {
+                self.last_sent.insert(event.kind, now);
+                true
+            }
+        }
+    }
+}
+
+/// Fires once each time `threshold` failures fall within `window`
+#[derive(Debug)]
+pub struct FailureThreshold {
+    threshold: usize,
+    window: Duration,
+    failures: Vec<Instant>,
+}
+
+impl FailureThreshold {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            window,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Record a failure at `now`; returns the count when it reaches the
+    /// threshold, then starts counting afresh
+    pub fn record(&mut self, now: Instant) -> Option<usize> {
+        self.failures
+            .retain(|&at| now.saturating_duration_since(at) < self.window);
+        self.failures.push(now);
+        if self.failures.len() < self.threshold {
+            return None;
+        }
+        let count = self.failures.len();
+        self.failures.clear();
+        Some(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: &'static str, level: LoggingLevel) -> ServerEvent {
+        ServerEvent::new(kind, level, format!("{} happened", kind))
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_events() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.publish(
+            event("ntp.sync_lost", LoggingLevel::Warning)
+                .with_details(serde_json::json!({"stratum": 16})),
+        );
+
+        for receiver in [&mut first, &mut second] {
+            let received = receiver.recv().await.unwrap();
+            assert_eq!(received.kind, "ntp.sync_lost");
+            let notification = received.to_notification();
+            assert_eq!(notification.level, LoggingLevel::Warning);
+            assert_eq!(notification.logger.as_deref(), Some("ntp"));
+            assert_eq!(notification.data["kind"], "ntp.sync_lost");
+            assert_eq!(notification.data["message"], "ntp.sync_lost happened");
+            assert_eq!(notification.data["details"]["stratum"], 16);
+            assert!(notification.data["at"].as_str().unwrap().ends_with('Z'));
+            assert!(notification.data.get("level").is_none());
+        }
+    }
+
+    #[test]
+    fn test_filter_limits_each_kind_to_one_per_interval() {
+        let mut filter = NotificationFilter::default();
+        let start = Instant::now();
+        let step = event("clock.step", LoggingLevel::Warning);
+
+        assert!(filter.admit(&step, start));
+        assert!(!filter.admit(&step, start + Duration::from_secs(59)));
+        assert!(filter.admit(&event("ntp.sync_lost", LoggingLevel::Warning), start));
+        assert!(filter.admit(&step, start + NOTIFY_INTERVAL));
+    }
+
+    #[test]
+    fn test_filter_applies_level() {
+        let mut filter = NotificationFilter::default();
+        let now = Instant::now();
+        assert!(!filter.admit(&event("a.debug", LoggingLevel::Debug), now));
+        assert!(filter.admit(&event("a.notice", LoggingLevel::Notice), now));
+
+        filter.set_level(LoggingLevel::Error);
+        assert!(!filter.admit(&event("a.warning", LoggingLevel::Warning), now));
+        assert!(filter.admit(&event("a.critical", LoggingLevel::Critical), now));
+    }
+
+    #[test]
+    fn test_failure_threshold_counts_within_window() {
+        let mut failures = FailureThreshold::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(failures.record(start), None);
+        assert_eq!(failures.record(start + Duration::from_secs(30)), None);
+        // The first failure has aged out
+        assert_eq!(failures.record(start + Duration::from_secs(61)), None);
+        assert_eq!(failures.record(start + Duration::from_secs(62)), Some(3));
+        assert_eq!(failures.record(start + Duration::from_secs(63)), None);
+    }
+}
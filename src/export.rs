@@ -0,0 +1,430 @@
+// CSV and iCalendar renderings of tool results
+//
+// Reporting pipelines want tables and calendars rather than JSON. A result
+// row implements `CsvRecord` to become one CSV line (RFC 4180: a header row,
+// CRLF line ends, fields quoted when they hold a comma, quote or line break).
+// A result that is an instant or a stretch of time implements `CalendarItem`
+// to become a VEVENT. Events name their zone with `DTSTART;TZID=` and no
+// VTIMEZONE block, which calendar clients resolve from their own tz
+// database; a local time that occurs twice in the zone is written in UTC
+// instead, since a TZID time means the first occurrence. Lines are folded at
+// 75 octets as RFC 5545 requires.
+
+use crate::error::TimeServerError;
+use crate::time::timezone::{OffsetSegment, OffsetTransition, TransitionKind};
+use crate::time::WorldClockRow;
+use chrono::{DateTime, LocalResult, TimeZone, Utc};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// Longest iCalendar content line, in octets, before folding
+const ICS_LINE_OCTETS: usize = 75;
+
+/// PRODID of generated calendars
+const ICS_PRODID: &str = "-//mcp-utc-time-server//export//EN";
+
+/// How a tool returns its result
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Csv,
+    /// iCalendar; only for results that are instants or periods
+    Ics,
+}
+
+impl OutputFormat {
+    /// `json`, `csv` or `ics`, as in an HTTP `?format=`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "ics" | "ical" => Some(Self::Ics),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8; header=present",
+            Self::Ics => "text/calendar; charset=utf-8",
+        }
+    }
+
+    /// The error for a result that has no calendar form
+    pub fn not_calendar(what: &str) -> TimeServerError {
+        TimeServerError::InvalidArgument(format!(
+            "output_format 'ics' needs instants or periods; {} supports json and csv",
+            what
+        ))
+    }
+}
+
+/// A result row with a fixed set of columns
+pub trait CsvRecord {
+    const HEADER: &'static [&'static str];
+
+    /// One value per [`Self::HEADER`] column
+    fn fields(&self) -> Vec<String>;
+}
+
+/// `records` as CSV with a header row
+pub fn to_csv<R: CsvRecord>(records: &[R]) -> String {
+    let mut csv = String::new();
+    let mut push_row = |fields: &mut dyn Iterator<Item = Cow<'_, str>>| {
+        let row: Vec<Cow<'_, str>> = fields.collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    };
+    push_row(&mut R::HEADER.iter().map(|name| csv_field(name)));
+    for record in records {
+        let fields = record.fields();
+        push_row(&mut fields.iter().map(|field| csv_field(field)));
+    }
+    csv
+}
+
+/// One field, quoted when it holds a delimiter, quote or line break
+fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// A result that is an instant or a period in one timezone
+pub trait CalendarItem {
+    /// Unix seconds
+    fn start(&self) -> i64;
+    /// Unix seconds, exclusive; `None` for an instant
+    fn end(&self) -> Option<i64>;
+    fn summary(&self, timezone: &str) -> String;
+}
+
+/// `items` of `timezone` as an iCalendar with one VEVENT each, stamped `stamp`
+pub fn to_ics<I: CalendarItem>(
+    items: &[I],
+    timezone: &str,
+    stamp: DateTime<Utc>,
+) -> Result<String, TimeServerError> {
+    let tz: Tz = timezone
+        .parse()
+        .map_err(|_| TimeServerError::invalid_timezone(timezone))?;
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", ICS_PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for item in items {
+        let start = ics_date_time(tz, item.start())?;
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}-{}@mcp-utc-time-server",
+            item.start(),
+            item.end().unwrap_or(item.start()),
+            tz.name().replace('/', "-")
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        lines.push(format!("DTSTART{}", start));
+        if let Some(end) = item.end() {
+            lines.push(format!("DTEND{}", ics_date_time(tz, end)?));
+        }
+        lines.push(format!("SUMMARY:{}", ics_text(&item.summary(tz.name()))));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    Ok(lines.iter().map(|line| fold_line(line)).collect())
+}
+
+/// `;TZID=Zone:local` for `timestamp`, or `:utcZ` when that local time is
+/// ambiguous in the zone
+fn ics_date_time(tz: Tz, timestamp: i64) -> Result<String, TimeServerError> {
+    let utc = DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+        TimeServerError::InvalidTimestamp(format!("{} is out of range", timestamp))
+    })?;
+    let local = utc.with_timezone(&tz).naive_local();
+    Ok(match tz.from_local_datetime(&local) {
+        LocalResult::Single(_) => {
+            format!(";TZID={}:{}", tz.name(), local.format("%Y%m%dT%H%M%S"))
+        }
+        _ => format!(":{}", utc.format("%Y%m%dT%H%M%SZ")),
+    })
+}
+
+/// Escape a TEXT value (RFC 5545 §3.3.11)
+fn ics_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `line` with CRLF, split into 75-octet pieces joined by CRLF and a space,
+/// never inside a UTF-8 sequence
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > ICS_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space counts toward the continuation line
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// `+05:30` style offset
+fn offset_text(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.unsigned_abs() / 60;
+    format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+impl CsvRecord for WorldClockRow {
+    const HEADER: &'static [&'static str] = &[
+        "timezone",
+        "local_time",
+        "date",
+        "offset_seconds",
+        "utc_offset",
+        "day_difference",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.timezone.clone(),
+            self.local_time.clone(),
+            self.date.clone(),
+            self.offset_seconds.to_string(),
+            self.utc_offset.clone(),
+            self.day_difference.to_string(),
+        ]
+    }
+}
+
+impl CsvRecord for OffsetTransition {
+    const HEADER: &'static [&'static str] = &[
+        "utc",
+        "unix_timestamp",
+        "local_before",
+        "local_after",
+        "offset_before_seconds",
+        "offset_after_seconds",
+        "change_minutes",
+        "kind",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.utc.clone(),
+            self.unix_timestamp.to_string(),
+            self.local_before.clone(),
+            self.local_after.clone(),
+            self.offset_before_seconds.to_string(),
+            self.offset_after_seconds.to_string(),
+            self.change_minutes.to_string(),
+            match self.kind {
+                TransitionKind::Gap => "gap",
+                TransitionKind::Overlap => "overlap",
+            }
+            .to_string(),
+        ]
+    }
+}
+
+impl CalendarItem for OffsetTransition {
+    fn start(&self) -> i64 {
+        self.unix_timestamp
+    }
+
+    fn end(&self) -> Option<i64> {
+        None
+    }
+
+    fn summary(&self, timezone: &str) -> String {
+        format!(
+            "{}: UTC{} -> UTC{}",
+            timezone,
+            offset_text(self.offset_before_seconds),
+            offset_text(self.offset_after_seconds)
+        )
+    }
+}
+
+impl CsvRecord for OffsetSegment {
+    const HEADER: &'static [&'static str] = &[
+        "from_utc",
+        "to_utc",
+        "from_unix",
+        "to_unix",
+        "offset_seconds",
+        "abbreviation",
+        "is_dst",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.from_utc.clone(),
+            self.to_utc.clone(),
+            self.from_unix.to_string(),
+            self.to_unix.to_string(),
+            self.offset_seconds.to_string(),
+            self.abbreviation.clone(),
+            self.is_dst.to_string(),
+        ]
+    }
+}
+
+impl CalendarItem for OffsetSegment {
+    fn start(&self) -> i64 {
+        self.from_unix
+    }
+
+    fn end(&self) -> Option<i64> {
+        Some(self.to_unix)
+    }
+
+    fn summary(&self, timezone: &str) -> String {
+        format!(
+            "{}: {} (UTC{}{})",
+            timezone,
+            self.abbreviation,
+            offset_text(self.offset_seconds),
+            if self.is_dst { ", DST" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::TimezoneConverter;
+    use chrono::NaiveDateTime;
+    use icalendar::{Calendar, CalendarDateTime, Component, DatePerhapsTime};
+
+    fn stamp() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    /// `ics` read back with the icalendar crate, after checking the
+    /// framing it is lenient about: CRLF endings and lines of at most 75 octets
+    fn parse_ics(ics: &str) -> Calendar {
+        assert!(ics.ends_with("\r\n"));
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= ICS_LINE_OCTETS, "{:?}", line);
+            assert!(!line.contains('\n'), "{:?}", line);
+        }
+        ics.parse().expect("icalendar rejected the export")
+    }
+
+    fn naive(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_csv_quotes_delimiters_and_quotes() {
+        let row = WorldClockRow {
+            timezone: "Custom, \"Zone\"".to_string(),
+            local_time: "12:00:00".to_string(),
+            date: "2024-01-01".to_string(),
+            offset_seconds: -12600,
+            utc_offset: "-03:30".to_string(),
+            day_difference: 0,
+        };
+        assert_eq!(
+            to_csv(&[row]),
+            "timezone,local_time,date,offset_seconds,utc_offset,day_difference\r\n\
+             \"Custom, \"\"Zone\"\"\",12:00:00,2024-01-01,-12600,-03:30,0\r\n"
+        );
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_ics_events_for_transitions_and_segments() {
+        let transitions = TimezoneConverter::dst_transitions("Europe/London", 2024).unwrap();
+        let calendar = parse_ics(&to_ics(&transitions, "Europe/London", stamp()).unwrap());
+        let events: Vec<_> = calendar.events().collect();
+        assert_eq!(events.len(), 2);
+        // Spring forward: 02:00 BST is unambiguous
+        assert_eq!(
+            events[0].get_start(),
+            Some(DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone {
+                date_time: naive("2024-03-31T02:00:00"),
+                tzid: "Europe/London".to_string(),
+            }))
+        );
+        assert_eq!(
+            events[0].get_summary(),
+            Some("Europe/London: UTC+00:00 -> UTC+01:00")
+        );
+        // Fall back: 01:00 local happens twice, so the instant is given in UTC
+        assert_eq!(
+            events[1].get_start(),
+            Some(DatePerhapsTime::DateTime(CalendarDateTime::Utc(
+                naive("2024-10-27T01:00:00").and_utc()
+            )))
+        );
+        assert_eq!(
+            events[1].get_summary(),
+            Some("Europe/London: UTC+01:00 -> UTC+00:00")
+        );
+
+        let segments =
+            TimezoneConverter::offset_timeline("Asia/Kolkata", 1_700_000_000, 1_700_086_400)
+                .unwrap();
+        let calendar = parse_ics(&to_ics(&segments, "Asia/Kolkata", stamp()).unwrap());
+        let events: Vec<_> = calendar.events().collect();
+        assert_eq!(events.len(), 1);
+        let local = |text: &str| {
+            Some(DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone {
+                date_time: naive(text),
+                tzid: "Asia/Kolkata".to_string(),
+            }))
+        };
+        assert_eq!(events[0].get_start(), local("2023-11-15T03:43:20"));
+        assert_eq!(events[0].get_end(), local("2023-11-16T03:43:20"));
+        assert_eq!(
+            events[0].get_summary(),
+            Some("Asia/Kolkata: IST (UTC+05:30)")
+        );
+    }
+
+    #[test]
+    fn test_long_lines_fold_at_75_octets() {
+        let line = format!("SUMMARY:{}", "Zürich ".repeat(20));
+        let folded = fold_line(&line);
+        assert!(folded
+            .split("\r\n")
+            .all(|piece| piece.len() <= ICS_LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+        assert_eq!(ics_text("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_output_format_names() {
+        assert_eq!(OutputFormat::parse("CSV"), Some(OutputFormat::Csv));
+        assert_eq!(OutputFormat::parse("ics"), Some(OutputFormat::Ics));
+        assert_eq!(OutputFormat::parse("xml"), None);
+        assert_eq!(OutputFormat::default(), OutputFormat::Json);
+    }
+}
@@ -0,0 +1,309 @@
+// Refusing to serve time from a clock that cannot be right
+//
+// A host that boots with a dead RTC comes up in 1970 (or 2106), and every
+// agent that trusts this server would be handed that date. The guard compares
+// the system clock against the build timestamp build.rs embeds: a clock
+// earlier than the build (less a day of slack for builders and hosts that
+// disagree) or more than CLOCK_MAX_FUTURE_YEARS past it is implausible.
+// While it is, tools and HTTP routes that return the time fail with
+// `clock_implausible`; the diagnostic tools in `DIAGNOSTIC_TOOLS`, `/health`
+// and `/ready` keep answering and say why. The verdict is taken afresh on
+// every check, so the guard lifts as soon as the clock is stepped into range,
+// or while the latest NTP status refresh reports the clock synchronized.
+// `ALLOW_IMPLAUSIBLE_CLOCK=true` turns the refusal off for testing; `/health`
+// still reports the clock, as degraded.
+
+use crate::error::TimeServerError;
+use chrono::{DateTime, Months, SecondsFormat, TimeZone, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tracing::{info, warn};
+
+/// Years past the build time a clock may read before it is implausible
+pub const DEFAULT_MAX_FUTURE_YEARS: u32 = 20;
+
+/// How far before the build time the clock may read
+const BUILD_TIME_SLACK: chrono::Duration = chrono::Duration::days(1);
+
+/// Tools that keep answering while the clock is implausible, so operators
+/// can find out why
+pub const DIAGNOSTIC_TOOLS: &[&str] = &[
+    "get_ntp_status",
+    "get_ntp_peers",
+    "get_ntp_history",
+    "get_ptp_status",
+    "get_pps_status",
+    "get_gps_status",
+    "check_time_sanity",
+    "get_server_info",
+    "get_process_stats",
+    "get_data_versions",
+    "get_chaos_status",
+    "get_examples",
+];
+
+/// ALLOW_IMPLAUSIBLE_CLOCK and CLOCK_MAX_FUTURE_YEARS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GuardConfig {
+    /// Serve time even from an implausible clock
+    pub allow_implausible: bool,
+    /// Readings further past the build time are implausible
+    pub max_future_years: u32,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            allow_implausible: false,
+            max_future_years: DEFAULT_MAX_FUTURE_YEARS,
+        }
+    }
+}
+
+/// Why `now` cannot be the real time for a binary built at `build_time`
+pub fn implausibility(
+    now: DateTime<Utc>,
+    build_time: DateTime<Utc>,
+    max_future_years: u32,
+) -> Option<String> {
+    if now < build_time - BUILD_TIME_SLACK {
+        return Some(format!(
+            "system clock reads {}, before this server was built ({})",
+            rfc3339(now),
+            rfc3339(build_time)
+        ));
+    }
+    let latest = build_time.checked_add_months(Months::new(max_future_years.saturating_mul(12)))?;
+    (now > latest).then(|| {
+        format!(
+            "system clock reads {}, more than {} years after this server was built ({})",
+            rfc3339(now),
+            max_future_years,
+            rfc3339(build_time)
+        )
+    })
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// What the guard makes of the clock right now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GuardStatus {
+    /// Time-returning tools and routes answer
+    pub serving: bool,
+    /// Why the clock is implausible; null when it is not
+    pub reason: Option<String>,
+    pub clock: String,
+    pub build_time: String,
+    /// The latest NTP status refresh found the clock synchronized
+    pub ntp_confirmed: bool,
+    /// ALLOW_IMPLAUSIBLE_CLOCK is set
+    pub overridden: bool,
+}
+
+impl GuardStatus {
+    pub fn plausible(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// The guard for one process (or one test server)
+#[derive(Debug)]
+pub struct ClockGuard {
+    config: GuardConfig,
+    build_time: DateTime<Utc>,
+    /// Reads the system clock; tests substitute a fixed one
+    clock: fn() -> DateTime<Utc>,
+    ntp_confirmed: AtomicBool,
+    /// Whether the last check refused, to log transitions once
+    refusing: AtomicBool,
+}
+
+impl ClockGuard {
+    /// A guard on the system clock against the embedded build time
+    pub fn new(config: GuardConfig) -> Self {
+        Self {
+            config,
+            build_time: crate::info::build_time().unwrap_or_else(|| {
+                // Builds without a timestamp still rule out a clock at the epoch
+                Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+            }),
+            clock: Utc::now,
+            ntp_confirmed: AtomicBool::new(false),
+            refusing: AtomicBool::new(false),
+        }
+    }
+
+    /// Read the time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: fn() -> DateTime<Utc>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Judge against `build_time` instead of the embedded one
+    pub fn with_build_time(mut self, build_time: DateTime<Utc>) -> Self {
+        self.build_time = build_time;
+        self
+    }
+
+    /// Record an NTP status refresh; a synchronized clock lifts the guard
+    /// until a refresh says otherwise
+    pub fn observe_ntp(&self, synced: bool) {
+        self.ntp_confirmed.store(synced, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> GuardStatus {
+        let now = (self.clock)();
+        let reason = implausibility(now, self.build_time, self.config.max_future_years);
+        let ntp_confirmed = self.ntp_confirmed.load(Ordering::Relaxed);
+        let serving = reason.is_none() || ntp_confirmed || self.config.allow_implausible;
+        if self.refusing.swap(!serving, Ordering::Relaxed) == serving {
+            match &reason {
+                Some(reason) if !serving => {
+                    warn!(event = "clock.implausible", reason = %reason, "refusing to serve time")
+                }
+                _ => info!(
+                    event = "clock.plausible",
+                    ntp_confirmed, "serving time again"
+                ),
+            }
+        }
+        GuardStatus {
+            serving,
+            reason,
+            clock: rfc3339(now),
+            build_time: rfc3339(self.build_time),
+            ntp_confirmed,
+            overridden: self.config.allow_implausible,
+        }
+    }
+
+    /// `ClockImplausible` unless time may be served
+    pub fn check(&self) -> Result<(), TimeServerError> {
+        match self.status() {
+            GuardStatus {
+                serving: false,
+                reason: Some(reason),
+                ..
+            } => Err(TimeServerError::ClockImplausible { reason }),
+            _ => Ok(()),
+        }
+    }
+
+    /// `check` for every tool but the diagnostic ones
+    pub fn check_tool(&self, tool: &str) -> Result<(), TimeServerError> {
+        if DIAGNOSTIC_TOOLS.contains(&tool) {
+            return Ok(());
+        }
+        self.check()
+    }
+}
+
+static GLOBAL: OnceLock<Arc<ClockGuard>> = OnceLock::new();
+
+/// Install the configured guard for the whole process
+pub fn init(config: &GuardConfig) {
+    let _ = GLOBAL.set(Arc::new(ClockGuard::new(config.clone())));
+}
+
+/// The process-wide guard, with the defaults if `init` was not called
+pub fn global() -> Arc<ClockGuard> {
+    GLOBAL
+        .get_or_init(|| Arc::new(ClockGuard::new(GuardConfig::default())))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn in_1971() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(1971, 3, 4, 5, 6, 7).unwrap()
+    }
+
+    fn guard(config: GuardConfig) -> ClockGuard {
+        ClockGuard::new(config)
+            .with_build_time(built())
+            .with_clock(in_1971)
+    }
+
+    #[test]
+    fn test_implausibility() {
+        let build = built();
+        assert!(implausibility(build, build, 20).is_none());
+        // Hosts a little behind the builder are fine
+        assert!(implausibility(build - chrono::Duration::hours(3), build, 20).is_none());
+        assert_eq!(
+            implausibility(in_1971(), build, 20).unwrap(),
+            "system clock reads 1971-03-04T05:06:07Z, before this server was built (2025-06-01T00:00:00Z)"
+        );
+        let far = Utc.with_ymd_and_hms(2106, 2, 7, 6, 28, 15).unwrap();
+        assert!(implausibility(far, build, 20)
+            .unwrap()
+            .contains("more than 20 years after"));
+        assert!(implausibility(far, build, 100).is_none());
+    }
+
+    #[test]
+    fn test_1971_refuses_time_but_not_diagnostics() {
+        let guard = guard(GuardConfig::default());
+        let status = guard.status();
+        assert!(!status.serving);
+        assert!(!status.plausible());
+        assert_eq!(status.clock, "1971-03-04T05:06:07Z");
+
+        let error = guard.check_tool("get_time").unwrap_err();
+        assert_eq!(error.code(), "clock_implausible");
+        assert!(error.to_string().contains("before this server was built"));
+        assert!(guard.check_tool("get_ntp_status").is_ok());
+    }
+
+    #[test]
+    fn test_override_and_ntp_confirmation_lift_the_guard() {
+        let guard = guard(GuardConfig {
+            allow_implausible: true,
+            ..Default::default()
+        });
+        assert!(guard.check_tool("get_time").is_ok());
+        assert!(guard.status().overridden);
+        assert!(!guard.status().plausible());
+
+        let guard = self::guard(GuardConfig::default());
+        guard.observe_ntp(true);
+        assert!(guard.check_tool("get_time").is_ok());
+        guard.observe_ntp(false);
+        assert!(guard.check_tool("get_time").is_err());
+    }
+
+    static STEPPED: AtomicBool = AtomicBool::new(false);
+
+    /// 1971 until `STEPPED`, then a day after the build
+    fn stepping_clock() -> DateTime<Utc> {
+        if STEPPED.load(Ordering::Relaxed) {
+            built() + chrono::Duration::days(1)
+        } else {
+            in_1971()
+        }
+    }
+
+    #[test]
+    fn test_clock_moving_into_range() {
+        let guard = ClockGuard::new(GuardConfig::default())
+            .with_build_time(built())
+            .with_clock(stepping_clock);
+        assert!(guard.check_tool("get_time").is_err());
+        STEPPED.store(true, Ordering::Relaxed);
+        assert!(guard.check_tool("get_time").is_ok());
+        assert!(guard.status().plausible());
+
+        // The real clock is after any build of this crate
+        assert!(ClockGuard::new(GuardConfig::default()).check().is_ok());
+    }
+}
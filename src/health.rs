@@ -0,0 +1,636 @@
+// Clock health grading shared by `/health` and the get_ntp_status tool
+//
+// `assess` turns an NTP status (or the reason there is none) into a
+// three-state verdict with one entry per check: synchronization, offset
+// against HEALTH_MAX_OFFSET_MS, system jitter against HEALTH_MAX_JITTER_MS
+// (when ntpd reports it), and whether a refclock sample is present in NTP
+// shared memory. The overall status is the worst check, so the tool and
+// the endpoint grade the same status the same way. While CHAOS_MODE is on,
+// `/health` also carries a degraded "chaos" check. On a host with PTP
+// hardware a recent PTP status adds a "ptp" check, and a locked PTP slave
+// stands in for NTP synchronization, since phc2sys rather than ntpd
+// disciplines the clock there. A system clock the guard finds implausible
+// (before the build, or decades past it) adds a "clock" check. When the
+// server runs ntpd itself (MANAGE_NTPD) an "ntpd" check is degraded while
+// the daemon is down awaiting a restart and unhealthy once restarts stop.
+// Builds without the `ntp` feature grade only the clock guard and chaos
+// checks.
+
+use crate::chaos::ChaosConfig;
+use crate::config::HealthConfig;
+use crate::guard::GuardStatus;
+#[cfg(feature = "ntp")]
+use crate::ntp::supervisor::SupervisorStatus;
+#[cfg(feature = "ntp")]
+use crate::ntp::{NtpStatus, NtpSyncedClock};
+#[cfg(feature = "ntp")]
+use crate::ptp::PtpStatus;
+use serde::Serialize;
+#[cfg(feature = "ntp")]
+use std::sync::Mutex;
+#[cfg(feature = "ntp")]
+use std::time::{Duration, Instant};
+
+/// How long `/health` reuses an NTP status before asking ntpd again
+#[cfg(feature = "ntp")]
+const STATUS_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub detail: String,
+}
+
+impl HealthCheck {
+    fn new(name: &'static str, status: HealthStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    /// Worst status of the checks
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// Add `check`; the overall status becomes the worse of the two
+    pub fn push(&mut self, check: HealthCheck) {
+        self.status = self.status.max(check.status);
+        self.checks.push(check);
+    }
+
+    /// Grade `ptp` alongside NTP; a no-op on hosts without PTP
+    #[cfg(feature = "ntp")]
+    pub fn fold_ptp(&mut self, ptp: &PtpStatus, config: &HealthConfig) {
+        if !ptp.available {
+            return;
+        }
+        let check = ptp_check(ptp, config);
+        if ptp.synced {
+            // ntpd is not expected to run where PTP disciplines the clock
+            if let Some(sync) = self.checks.iter_mut().find(|c| c.name == "sync") {
+                if sync.status != HealthStatus::Healthy {
+                    *sync = HealthCheck::new("sync", HealthStatus::Healthy, "disciplined by PTP");
+                }
+            }
+            self.status = self
+                .checks
+                .iter()
+                .map(|c| c.status)
+                .max()
+                .unwrap_or(HealthStatus::Healthy);
+        }
+        self.push(check);
+    }
+}
+
+/// Offset from the grandmaster against HEALTH_MAX_OFFSET_MS; degraded when
+/// there is PTP hardware but no locked port
+#[cfg(feature = "ntp")]
+pub fn ptp_check(ptp: &PtpStatus, config: &HealthConfig) -> HealthCheck {
+    match (ptp.synced, ptp.master_offset_ns) {
+        (true, Some(offset_ns)) => {
+            let within = offset_ns.unsigned_abs() as f64 <= config.max_offset_ms * 1e6;
+            HealthCheck::new(
+                "ptp",
+                if within {
+                    HealthStatus::Healthy
+                } else {
+                    HealthStatus::Degraded
+                },
+                format!(
+                    "locked to {}, master offset {} ns ({} {} ms)",
+                    ptp.gm_identity.as_deref().unwrap_or("grandmaster"),
+                    offset_ns,
+                    if within { "within" } else { "exceeds" },
+                    config.max_offset_ms
+                ),
+            )
+        }
+        _ if !ptp.pmc_available => HealthCheck::new(
+            "ptp",
+            HealthStatus::Degraded,
+            "PTP hardware present but pmc is not installed",
+        ),
+        _ => HealthCheck::new(
+            "ptp",
+            HealthStatus::Degraded,
+            format!(
+                "not locked (port state {}, grandmaster {})",
+                ptp.port_state.as_deref().unwrap_or("unknown"),
+                match ptp.gm_present {
+                    Some(true) => "present",
+                    Some(false) => "absent",
+                    None => "unknown",
+                }
+            ),
+        ),
+    }
+}
+
+/// Unhealthy while the clock guard refuses to serve time, degraded while an
+/// implausible clock is served anyway; `None` for a plausible clock
+pub fn clock_guard_check(guard: &GuardStatus) -> Option<HealthCheck> {
+    let reason = guard.reason.as_deref()?;
+    Some(if !guard.serving {
+        HealthCheck::new("clock", HealthStatus::Unhealthy, reason)
+    } else if guard.overridden {
+        HealthCheck::new(
+            "clock",
+            HealthStatus::Degraded,
+            format!("{} (served anyway: ALLOW_IMPLAUSIBLE_CLOCK)", reason),
+        )
+    } else {
+        HealthCheck::new(
+            "clock",
+            HealthStatus::Degraded,
+            format!("{} (served: NTP reports it synchronized)", reason),
+        )
+    })
+}
+
+/// Unhealthy once the managed ntpd is no longer restarted, degraded while it
+/// is down awaiting a restart; `None` while it runs
+#[cfg(feature = "ntp")]
+pub fn ntpd_check(supervisor: &SupervisorStatus) -> Option<HealthCheck> {
+    if supervisor.ntpd_running {
+        return None;
+    }
+    let exit = supervisor
+        .last_exit
+        .as_ref()
+        .map(|exit| match (&exit.error, exit.code, exit.signal) {
+            (Some(error), _, _) => format!(": {}", error),
+            (None, Some(code), _) => format!(" with status {}", code),
+            (None, None, Some(signal)) => format!(" on signal {}", signal),
+            (None, None, None) => String::new(),
+        })
+        .unwrap_or_default();
+    Some(if supervisor.gave_up {
+        HealthCheck::new(
+            "ntpd",
+            HealthStatus::Unhealthy,
+            format!(
+                "managed ntpd exited{} and is no longer restarted ({} restarts)",
+                exit, supervisor.restarts
+            ),
+        )
+    } else {
+        HealthCheck::new(
+            "ntpd",
+            HealthStatus::Degraded,
+            format!("managed ntpd is not running{}; restarting", exit),
+        )
+    })
+}
+
+/// Degraded while CHAOS_MODE distorts the reported time
+pub fn chaos_check(chaos: &ChaosConfig) -> HealthCheck {
+    HealthCheck::new(
+        "chaos",
+        HealthStatus::Degraded,
+        format!("CHAOS_MODE {}: {}", chaos.anomaly, chaos.anomaly.describe()),
+    )
+}
+
+/// Grade `ntp`, or the reason no status could be read. In a container the
+/// status is assumed from the host rather than measured, which is at best
+/// degraded.
+#[cfg(feature = "ntp")]
+pub fn assess(
+    ntp: Result<&NtpStatus, &str>,
+    container: bool,
+    config: &HealthConfig,
+) -> HealthReport {
+    let unsynced = if config.require_sync {
+        HealthStatus::Unhealthy
+    } else {
+        HealthStatus::Degraded
+    };
+
+    let mut checks = Vec::with_capacity(4);
+    match ntp {
+        Err(reason) => checks.push(HealthCheck::new("sync", unsynced, reason)),
+        Ok(status) => {
+            checks.push(if container {
+                HealthCheck::new(
+                    "sync",
+                    HealthStatus::Degraded,
+                    "container: synchronization assumed from the host, not verified",
+                )
+            } else if status.synced {
+                HealthCheck::new(
+                    "sync",
+                    HealthStatus::Healthy,
+                    format!("synchronized at stratum {}", status.stratum),
+                )
+            } else {
+                HealthCheck::new("sync", unsynced, "not synchronized")
+            });
+
+            if status.synced {
+                let within = status.offset_ms.abs() <= config.max_offset_ms;
+                checks.push(HealthCheck::new(
+                    "offset",
+                    if within {
+                        HealthStatus::Healthy
+                    } else {
+                        HealthStatus::Degraded
+                    },
+                    format!(
+                        "{:.3} ms ({} {} ms)",
+                        status.offset_ms,
+                        if within { "within" } else { "exceeds" },
+                        config.max_offset_ms
+                    ),
+                ));
+            }
+
+            if let Some(jitter_ms) = status.sys_jitter_ms.filter(|_| status.synced) {
+                let within = jitter_ms <= config.max_jitter_ms;
+                checks.push(HealthCheck::new(
+                    "jitter",
+                    if within {
+                        HealthStatus::Healthy
+                    } else {
+                        HealthStatus::Degraded
+                    },
+                    format!(
+                        "{:.3} ms ({} {} ms)",
+                        jitter_ms,
+                        if within { "within" } else { "exceeds" },
+                        config.max_jitter_ms
+                    ),
+                ));
+            }
+
+            checks.push(if status.shm_valid {
+                HealthCheck::new("shm", HealthStatus::Healthy, "refclock sample present")
+            } else {
+                HealthCheck::new("shm", HealthStatus::Degraded, "no valid refclock sample")
+            });
+        }
+    }
+
+    HealthReport {
+        status: checks
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(HealthStatus::Healthy),
+        checks,
+    }
+}
+
+/// Grades for `/health`, reusing the NTP status for a few seconds so a busy
+/// probe does not run ntpq on every request
+#[derive(Debug)]
+pub struct HealthProbe {
+    config: HealthConfig,
+    #[cfg(feature = "ntp")]
+    container: bool,
+    /// Graded instead of querying the clock (tests)
+    #[cfg(feature = "ntp")]
+    fixed: Option<NtpStatus>,
+    #[cfg(feature = "ntp")]
+    last: Mutex<Option<(Instant, Result<NtpStatus, String>)>>,
+}
+
+impl HealthProbe {
+    #[cfg_attr(not(feature = "ntp"), allow(unused_variables))]
+    pub fn new(config: HealthConfig, container: bool) -> Self {
+        Self {
+            config,
+            #[cfg(feature = "ntp")]
+            container,
+            #[cfg(feature = "ntp")]
+            fixed: None,
+            #[cfg(feature = "ntp")]
+            last: Mutex::new(None),
+        }
+    }
+
+    /// A probe that always grades `status`
+    #[cfg(feature = "ntp")]
+    pub fn with_status(config: HealthConfig, container: bool, status: NtpStatus) -> Self {
+        Self {
+            fixed: Some(status),
+            ..Self::new(config, container)
+        }
+    }
+
+    pub fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
+    /// The graded status and the status it was graded from
+    #[cfg(feature = "ntp")]
+    pub async fn report(
+        &self,
+        clock: &NtpSyncedClock,
+    ) -> (HealthReport, Result<NtpStatus, String>) {
+        let status = match &self.fixed {
+            Some(status) => Ok(status.clone()),
+            None => self.status(clock).await,
+        };
+        let mut report = assess(
+            status.as_ref().map_err(String::as_str),
+            self.container,
+            &self.config,
+        );
+        if let Some((_, ptp)) = crate::ptp::latest(Instant::now()) {
+            report.fold_ptp(&ptp, &self.config);
+        }
+        if let Some(check) =
+            crate::ntp::supervisor::global().and_then(|supervisor| ntpd_check(&supervisor.status()))
+        {
+            report.push(check);
+        }
+        if let Some(chaos) = crate::chaos::global() {
+            report.push(chaos_check(chaos.config()));
+        }
+        (report, status)
+    }
+
+    /// The chaos check alone: a build without NTP has no sync to grade
+    #[cfg(not(feature = "ntp"))]
+    pub fn report(&self) -> HealthReport {
+        let mut report = HealthReport {
+            status: HealthStatus::Healthy,
+            checks: Vec::new(),
+        };
+        if let Some(chaos) = crate::chaos::global() {
+            report.push(chaos_check(chaos.config()));
+        }
+        report
+    }
+
+    #[cfg(feature = "ntp")]
+    async fn status(&self, clock: &NtpSyncedClock) -> Result<NtpStatus, String> {
+        if let Some((at, status)) = self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+        {
+            if at.elapsed() < STATUS_CACHE_TTL {
+                return status.clone();
+            }
+        }
+        let status = clock.get_status_async().await.map_err(|e| e.to_string());
+        *self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            Some((Instant::now(), status.clone()));
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "ntp")]
+    fn status(synced: bool, offset_ms: f64, shm_valid: bool) -> NtpStatus {
+        NtpStatus {
+            synced,
+            offset_ms,
+            stratum: if synced { 2 } else { 16 },
+            precision: -20,
+            root_delay: 0.0,
+            root_dispersion: 0.0,
+            shm_valid,
+            pps_enabled: false,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "ntp")]
+    fn check(report: &HealthReport, name: &str) -> HealthStatus {
+        report
+            .checks
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap()
+            .status
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_grades() {
+        let config = HealthConfig::default();
+        let healthy = assess(Ok(&status(true, 1.5, true)), false, &config);
+        assert_eq!(healthy.status, HealthStatus::Healthy);
+        assert_eq!(healthy.checks.len(), 3);
+
+        let no_shm = assess(Ok(&status(true, 1.5, false)), false, &config);
+        assert_eq!(no_shm.status, HealthStatus::Degraded);
+        assert_eq!(check(&no_shm, "shm"), HealthStatus::Degraded);
+
+        let far = assess(Ok(&status(true, -250.0, true)), false, &config);
+        assert_eq!(far.status, HealthStatus::Degraded);
+        assert!(far.checks[1].detail.contains("exceeds 100 ms"));
+
+        let unsynced = assess(Ok(&status(false, 0.0, true)), false, &config);
+        assert_eq!(unsynced.status, HealthStatus::Unhealthy);
+        assert!(unsynced.checks.iter().all(|c| c.name != "offset"));
+
+        let missing = assess(Err("ntpq not found"), false, &config);
+        assert_eq!(missing.status, HealthStatus::Unhealthy);
+        assert_eq!(missing.checks[0].detail, "ntpq not found");
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_chaos_degrades_a_healthy_report() {
+        let config = HealthConfig::default();
+        let mut report = assess(Ok(&status(true, 1.5, true)), false, &config);
+        report.push(chaos_check(&ChaosConfig {
+            anomaly: crate::chaos::Anomaly::Freeze,
+            max_regression_ms: 1000,
+            seed: 1,
+        }));
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(check(&report, "chaos"), HealthStatus::Degraded);
+
+        let mut unsynced = assess(Ok(&status(false, 0.0, true)), false, &config);
+        unsynced.push(chaos_check(&ChaosConfig {
+            anomaly: crate::chaos::Anomaly::Drift { ppm: 50.0 },
+            max_regression_ms: 1000,
+            seed: 1,
+        }));
+        assert_eq!(unsynced.status, HealthStatus::Unhealthy);
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_thresholds_are_configurable() {
+        let config = HealthConfig {
+            max_offset_ms: 500.0,
+            require_sync: false,
+            ..HealthConfig::default()
+        };
+        let far = assess(Ok(&status(true, -250.0, true)), false, &config);
+        assert_eq!(far.status, HealthStatus::Healthy);
+        let unsynced = assess(Ok(&status(false, 0.0, true)), false, &config);
+        assert_eq!(unsynced.status, HealthStatus::Degraded);
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_jitter_is_graded_when_reported() {
+        let config = HealthConfig::default();
+        let jittery = NtpStatus {
+            sys_jitter_ms: Some(25.0),
+            ..status(true, 1.5, true)
+        };
+        let report = assess(Ok(&jittery), false, &config);
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(check(&report, "jitter"), HealthStatus::Degraded);
+        assert!(report.checks[2].detail.contains("exceeds 10 ms"));
+
+        let steady = NtpStatus {
+            sys_jitter_ms: Some(0.2),
+            ..status(true, 1.5, true)
+        };
+        let report = assess(Ok(&steady), false, &config);
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.checks.len(), 4);
+
+        // No jitter from ntpd: no check, as before
+        let report = assess(Ok(&status(true, 1.5, true)), false, &config);
+        assert!(report.checks.iter().all(|c| c.name != "jitter"));
+    }
+
+    #[cfg(feature = "ntp")]
+    fn ptp(synced: bool, master_offset_ns: i64) -> PtpStatus {
+        PtpStatus {
+            available: true,
+            synced,
+            pmc_available: true,
+            master_offset_ns: Some(master_offset_ns),
+            gm_present: Some(synced),
+            gm_identity: Some("001122.fffe.334455".into()),
+            port_state: Some(if synced { "SLAVE" } else { "LISTENING" }.into()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_locked_ptp_stands_in_for_ntp() {
+        let config = HealthConfig::default();
+        let mut report = assess(Err("ntpq command not found"), false, &config);
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        report.fold_ptp(&ptp(true, -40), &config);
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.checks[0].detail, "disciplined by PTP");
+        assert!(report.checks[1].detail.contains("master offset -40 ns"));
+
+        let mut far = assess(Err("ntpq command not found"), false, &config);
+        far.fold_ptp(&ptp(true, 250_000_000), &config);
+        assert_eq!(far.status, HealthStatus::Degraded);
+        assert_eq!(check(&far, "ptp"), HealthStatus::Degraded);
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_unlocked_or_absent_ptp() {
+        let config = HealthConfig::default();
+        let mut report = assess(Ok(&status(true, 1.5, true)), false, &config);
+        report.fold_ptp(&ptp(false, 0), &config);
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.checks.last().unwrap().detail.contains("LISTENING"));
+
+        let mut report = assess(Err("ntpq command not found"), false, &config);
+        report.fold_ptp(&PtpStatus::default(), &config);
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert!(report.checks.iter().all(|c| c.name != "ptp"));
+    }
+
+    #[test]
+    fn test_implausible_clock() {
+        use crate::guard::{ClockGuard, GuardConfig};
+        fn in_1971() -> chrono::DateTime<chrono::Utc> {
+            "1971-03-04T05:06:07Z".parse().unwrap()
+        }
+
+        let plausible = ClockGuard::new(GuardConfig::default());
+        assert!(clock_guard_check(&plausible.status()).is_none());
+
+        let guard = ClockGuard::new(GuardConfig::default()).with_clock(in_1971);
+        let check = clock_guard_check(&guard.status()).unwrap();
+        assert_eq!(check.status, HealthStatus::Unhealthy);
+        assert!(check.detail.starts_with("system clock reads 1971"));
+
+        guard.observe_ntp(true);
+        let check = clock_guard_check(&guard.status()).unwrap();
+        assert_eq!(check.status, HealthStatus::Degraded);
+        assert!(check
+            .detail
+            .ends_with("(served: NTP reports it synchronized)"));
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_managed_ntpd() {
+        use crate::ntp::supervisor::ChildExit;
+
+        let mut supervisor = SupervisorStatus {
+            ntpd_running: true,
+            ..Default::default()
+        };
+        assert!(ntpd_check(&supervisor).is_none());
+
+        supervisor.ntpd_running = false;
+        supervisor.restarts = 5;
+        supervisor.last_exit = Some(ChildExit {
+            at: "2026-01-01T00:00:00Z".to_string(),
+            code: Some(1),
+            signal: None,
+            error: None,
+            ran_secs: 0.5,
+        });
+        let check = ntpd_check(&supervisor).unwrap();
+        assert_eq!(check.status, HealthStatus::Degraded);
+        assert_eq!(
+            check.detail,
+            "managed ntpd is not running with status 1; restarting"
+        );
+
+        supervisor.gave_up = true;
+        let check = ntpd_check(&supervisor).unwrap();
+        assert_eq!(check.status, HealthStatus::Unhealthy);
+        assert!(check.detail.ends_with("no longer restarted (5 restarts)"));
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_container_is_degraded() {
+        let report = assess(
+            Ok(&status(true, 0.0, false)),
+            true,
+            &HealthConfig::default(),
+        );
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(check(&report, "sync"), HealthStatus::Degraded);
+        assert_eq!(check(&report, "offset"), HealthStatus::Healthy);
+    }
+}
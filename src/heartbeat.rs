@@ -0,0 +1,234 @@
+// Per-session heartbeat notifications
+//
+// subscribe_heartbeat starts a task that sends `notifications/message`
+// (logger "heartbeat") every interval with the current time and the NTP sync
+// flag the last status refresh left on the clock, so a client gets a liveness
+// and clock signal without polling. A session has at most one heartbeat:
+// subscribing again replaces it. The task stops on unsubscribe_heartbeat,
+// when a notification cannot be delivered, or when the last `Heartbeat`
+// handle (the session's server) is dropped. A guard held by the task keeps
+// `ServerStats::heartbeat_tasks` exact on every one of those paths.
+
+use crate::state::ServerState;
+use crate::stats::ServerStats;
+use crate::time::TimeSource;
+use chrono::{SecondsFormat, Utc};
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use rmcp::service::Peer;
+use rmcp::RoleServer;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::debug;
+
+/// Shortest interval a client may ask for
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest interval a client may ask for
+pub const MAX_INTERVAL: Duration = Duration::from_secs(300);
+
+/// The data of one heartbeat notification
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Beat {
+    /// 1 for the first beat of a subscription
+    pub sequence: u64,
+    /// RFC 3339, UTC, milliseconds
+    pub utc: String,
+    pub seconds: i64,
+    pub nanos: u32,
+    pub source: TimeSource,
+    /// From the last NTP status refresh; null until one has been read
+    pub ntp_synced: Option<bool>,
+    pub interval_seconds: f64,
+}
+
+/// The running heartbeat, as subscribe_heartbeat reports it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeartbeatStatus {
+    pub interval_seconds: f64,
+    /// RFC 3339, UTC
+    pub started_at: String,
+}
+
+/// Cheap-to-clone handle to a session's heartbeat; the task only holds the
+/// receiving end of its stop channel, so dropping the last handle stops it
+#[derive(Clone)]
+pub struct Heartbeat {
+    /// Stop channel of the running task; dropping the sender stops it
+    running: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    min_interval: Duration,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new(MIN_INTERVAL)
+    }
+}
+
+impl Heartbeat {
+    /// Accept intervals from `min_interval` up to [`MAX_INTERVAL`]
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            running: Arc::default(),
+            min_interval,
+        }
+    }
+
+    /// `seconds` as an interval, or why it is refused
+    pub fn interval(&self, seconds: f64) -> Result<Duration, String> {
+        let allowed = self.min_interval.as_secs_f64()..=MAX_INTERVAL.as_secs_f64();
+        if !seconds.is_finite() || !allowed.contains(&seconds) {
+            return Err(format!(
+                "'interval_seconds' must be between {} and {}",
+                allowed.start(),
+                allowed.end()
+            ));
+        }
+        Ok(Duration::from_secs_f64(seconds))
+    }
+
+    /// Start beating every `interval` to `peer`, replacing any running
+    /// heartbeat; returns the new status and whether one was replaced
+    pub fn start(
+        &self,
+        interval: Duration,
+        peer: Peer<RoleServer>,
+        state: Arc<ServerState>,
+        stats: Arc<ServerStats>,
+    ) -> (HeartbeatStatus, bool) {
+        let status = HeartbeatStatus {
+            interval_seconds: interval.as_secs_f64(),
+            started_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+        let (stop, stopped) = oneshot::channel();
+        let guard = TaskGuard::new(stats);
+        tokio::spawn(run(interval, peer, state, stopped, guard));
+
+        let previous = self.lock().replace(stop);
+        (status, previous.is_some())
+    }
+
+    /// Stop the running heartbeat; false if there was none
+    pub fn stop(&self) -> bool {
+        self.lock().take().is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<oneshot::Sender<()>>> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Counts a heartbeat task in `ServerStats` for as long as the task lives
+struct TaskGuard(Arc<ServerStats>);
+
+impl TaskGuard {
+    fn new(stats: Arc<ServerStats>) -> Self {
+        stats.record_heartbeat_started();
+        Self(stats)
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.record_heartbeat_stopped();
+    }
+}
+
+/// The beat numbered `sequence`; `None` when no time source can be read
+pub fn beat(state: &ServerState, sequence: u64, interval: Duration) -> Option<Beat> {
+    let now = match state.time_sources().now() {
+        Ok(now) => now,
+        Err(e) => {
+            debug!(event = "heartbeat.no_time", error = %e);
+            return None;
+        }
+    };
+    #[cfg(feature = "ntp")]
+    let ntp_synced = state
+        .ntp_clock()
+        .latest_status()
+        .map(|status| status.synced);
+    #[cfg(not(feature = "ntp"))]
+    let ntp_synced = None;
+    Some(Beat {
+        sequence,
+        utc: now
+            .time
+            .to_datetime()
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        seconds: now.time.seconds,
+        nanos: now.time.nanos,
+        source: now.source,
+        ntp_synced,
+        interval_seconds: interval.as_secs_f64(),
+    })
+}
+
+async fn run(
+    interval: Duration,
+    peer: Peer<RoleServer>,
+    state: Arc<ServerState>,
+    mut stopped: oneshot::Receiver<()>,
+    _guard: TaskGuard,
+) {
+    let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+    // A slow client delays the next beat rather than receiving a burst
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut sequence = 0;
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            _ = ticks.tick() => {}
+        }
+        sequence += 1;
+        let Some(beat) = beat(&state, sequence, interval) else {
+            continue;
+        };
+        let param = LoggingMessageNotificationParam {
+            level: LoggingLevel::Info,
+            logger: Some("heartbeat".into()),
+            data: serde_json::to_value(&beat).unwrap_or_default(),
+        };
+        if let Err(e) = peer.notify_logging_message(param).await {
+            debug!(event = "heartbeat.deliver_failed", error = %e);
+            break;
+        }
+    }
+    debug!(event = "heartbeat.stop", beats = sequence);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_bounds() {
+        let heartbeat = Heartbeat::default();
+        assert_eq!(heartbeat.interval(1.0), Ok(Duration::from_secs(1)));
+        assert_eq!(heartbeat.interval(300.0), Ok(MAX_INTERVAL));
+        for refused in [0.5, 300.5, f64::NAN, f64::INFINITY, -1.0] {
+            let error = heartbeat.interval(refused).unwrap_err();
+            assert!(error.contains("between 1 and 300"), "{}", error);
+        }
+        assert!(Heartbeat::new(Duration::from_millis(50))
+            .interval(0.1)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_beat_is_compact_time() {
+        let state = ServerState::new();
+        let beat = beat(&state, 3, Duration::from_secs(5)).unwrap();
+        assert_eq!(beat.sequence, 3);
+        assert!(beat.utc.ends_with('Z'));
+        assert_eq!(beat.utc.len(), "2024-01-01T00:00:00.000Z".len());
+        assert_eq!(beat.interval_seconds, 5.0);
+        let data = serde_json::to_value(&beat).unwrap();
+        assert!(data["ntp_synced"].is_null());
+        assert!(data["source"].is_string());
+    }
+}
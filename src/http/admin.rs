@@ -0,0 +1,254 @@
+// Admin routes: the effective config, the API keys and the log filter at runtime
+//
+// They exist only when ADMIN_TOKEN is set; without it every `/admin/` path
+// answers 404 like any unknown route. The token is its own credential, sent
+// as `Authorization: Bearer`, and never an API key: listeners with and
+// without `;auth` check it the same way. Both sides are hashed before they
+// are compared, so the comparison takes the same time whatever is presented.
+//
+// | Route           | Method | Effect                                                 |
+// |-----------------|--------|--------------------------------------------------------|
+// | /admin/config   | GET    | the effective ServerConfig, secrets redacted           |
+// | /admin/keys     | GET    | key names and rate limits, without the keys            |
+// | /admin/keys     | POST   | read API_KEY_* and API_KEYS again, as SIGHUP does      |
+// | /admin/loglevel | GET    | the current tracing filter                             |
+// | /admin/loglevel | PUT    | body is the new filter, e.g. "mcp_utc_time_server=debug" |
+
+use super::request::{HttpRequest, Method};
+use super::response::HttpResponse;
+use super::{error_response, method_not_allowed, not_found, pretty_param, HttpSettings};
+use crate::auth::SharedApiKeys;
+use crate::config::ServerConfig;
+use crate::error::TimeServerError;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+pub const ADMIN_ROUTE_PREFIX: &str = "/admin/";
+pub const CONFIG_ROUTE: &str = "/admin/config";
+pub const KEYS_ROUTE: &str = "/admin/keys";
+pub const LOGLEVEL_ROUTE: &str = "/admin/loglevel";
+
+/// Swaps the filter of the subscriber `main` installs
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// What the admin routes work with; there is none without ADMIN_TOKEN
+#[derive(Clone)]
+pub struct AdminSettings {
+    token: String,
+    /// The config as serialized at startup, with its secrets redacted
+    config: Arc<Value>,
+    /// None until [`HttpSettings::with_log_filter`]
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl std::fmt::Debug for AdminSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminSettings")
+            .field("token", &"[redacted]")
+            .field("log_filter", &self.log_filter.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AdminSettings {
+    pub fn from_config(config: &ServerConfig) -> Option<Self> {
+        Some(Self {
+            token: config.http.admin_token.clone()?,
+            config: Arc::new(serde_json::to_value(config).unwrap_or_default()),
+            log_filter: None,
+        })
+    }
+
+    pub(super) fn set_log_filter(&mut self, handle: LogFilterHandle) {
+        self.log_filter = Some(handle);
+    }
+
+    /// `presented` is the admin token
+    fn accepts(&self, presented: &str) -> bool {
+        let (presented, expected) = (Sha256::digest(presented), Sha256::digest(&self.token));
+        presented
+            .iter()
+            .zip(expected.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+/// Answer a request for an `/admin/` path
+pub(super) fn respond(request: &HttpRequest, settings: &HttpSettings) -> HttpResponse {
+    let pretty = pretty_param(request);
+    let Some(admin) = &settings.admin else {
+        return not_found(&request.path, pretty);
+    };
+    let token = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| admin.accepts(token)) {
+        warn!(event = "http.admin.unauthorized", path = %request.path);
+        let error = TimeServerError::Unauthorized(match token {
+            Some(_) => "invalid admin token".to_string(),
+            None => "admin token required (Authorization: Bearer)".to_string(),
+        });
+        return error_response(&error, json!({}), pretty).with_header("WWW-Authenticate", "Bearer");
+    }
+
+    match (request.path.as_str(), &request.method) {
+        (CONFIG_ROUTE, Method::Get | Method::Head) => {
+            HttpResponse::json_as(200, "OK", admin.config.as_ref(), pretty)
+        }
+        (CONFIG_ROUTE, _) => method_not_allowed(request, "GET, HEAD"),
+        (KEYS_ROUTE, Method::Get | Method::Head) => {
+            HttpResponse::json_as(200, "OK", &keys_body(&settings.api_keys), pretty)
+        }
+        (KEYS_ROUTE, Method::Post) => {
+            let count = reload_api_keys(&settings.api_keys);
+            info!(event = "http.admin.keys_reloaded", count = count);
+            let mut body = keys_body(&settings.api_keys);
+            body["reloaded"] = json!(true);
+            HttpResponse::json_as(200, "OK", &body, pretty)
+        }
+        (KEYS_ROUTE, _) => method_not_allowed(request, "GET, HEAD, POST"),
+        (LOGLEVEL_ROUTE, method) => match (method, &admin.log_filter) {
+            (Method::Get | Method::Head | Method::Put, None) => {
+                let error = TimeServerError::NotSupported {
+                    operation: "changing the log filter".to_string(),
+                    platform: "a subscriber without a reload layer".to_string(),
+                };
+                error_response(&error, json!({}), pretty)
+            }
+            (Method::Get | Method::Head, Some(handle)) => {
+                match handle.with_current(|f| f.to_string()) {
+                    Ok(filter) => {
+                        HttpResponse::json_as(200, "OK", &json!({ "filter": filter }), pretty)
+                    }
+                    Err(e) => error_response(
+                        &TimeServerError::Io(std::io::Error::other(e)),
+                        json!({}),
+                        pretty,
+                    ),
+                }
+            }
+            (Method::Put, Some(handle)) => match set_log_filter(handle, &request.body) {
+                Ok(filter) => {
+                    HttpResponse::json_as(200, "OK", &json!({ "filter": filter }), pretty)
+                }
+                Err(error) => error_response(&error, json!({}), pretty),
+            },
+            _ => method_not_allowed(request, "GET, HEAD, PUT"),
+        },
+        _ => not_found(&request.path, pretty),
+    }
+}
+
+/// `{keys: [{name, rate_limit}], count}`
+fn keys_body(keys: &SharedApiKeys) -> Value {
+    let summaries = keys.summaries();
+    json!({
+        "count": summaries.len(),
+        "keys": summaries,
+    })
+}
+
+/// Apply the tracing filter in `body`; returns it as applied
+fn set_log_filter(handle: &LogFilterHandle, body: &str) -> Result<String, TimeServerError> {
+    let directives = body.trim();
+    if directives.is_empty() {
+        return Err(TimeServerError::InvalidArgument(
+            "the body must be a tracing filter, e.g. \"mcp_utc_time_server=debug\"".to_string(),
+        ));
+    }
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| TimeServerError::InvalidArgument(format!("invalid log filter: {}", e)))?;
+    let applied = filter.to_string();
+    handle
+        .reload(filter)
+        .map_err(|e| TimeServerError::Io(std::io::Error::other(e)))?;
+    info!(event = "http.admin.log_filter", filter = %applied);
+    Ok(applied)
+}
+
+/// Read the API keys from the environment again; returns how many there are
+pub fn reload_api_keys(keys: &SharedApiKeys) -> usize {
+    keys.replace(env_api_keys())
+}
+
+#[cfg(feature = "auth")]
+fn env_api_keys() -> Vec<crate::auth::ApiKey> {
+    crate::auth::api_key::load_keys(&crate::config::EnvVars::from_process())
+}
+
+/// A build without the auth feature has no keys to reload
+#[cfg(not(feature = "auth"))]
+fn env_api_keys() -> Vec<crate::auth::ApiKey> {
+    Vec::new()
+}
+
+/// Reload `keys` on every SIGHUP, for as long as the process runs
+#[cfg(unix)]
+pub fn reload_keys_on_sighup(keys: SharedApiKeys) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!("SIGHUP handler not installed: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            let count = reload_api_keys(&keys);
+            info!(
+                event = "auth.keys_reloaded",
+                count = count,
+                "SIGHUP received"
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admin(token: &str) -> AdminSettings {
+        let mut config = ServerConfig::default();
+        config.http.admin_token = Some(token.to_string());
+        AdminSettings::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_token_must_match_exactly() {
+        let admin = admin("correct-horse-battery");
+        assert!(admin.accepts("correct-horse-battery"));
+        assert!(!admin.accepts("correct-horse-batter"));
+        assert!(!admin.accepts("correct-horse-battery "));
+        assert!(!admin.accepts(""));
+        assert!(!format!("{:?}", admin).contains("correct-horse"));
+
+        assert!(AdminSettings::from_config(&ServerConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_config_snapshot_is_redacted() {
+        let admin = admin("correct-horse-battery");
+        assert_eq!(admin.config["http"]["admin_token"], "[redacted]");
+    }
+
+    #[test]
+    fn test_log_filter_is_validated() {
+        let (_layer, handle) = reload::Layer::<EnvFilter, Registry>::new(EnvFilter::new("warn"));
+        assert!(set_log_filter(&handle, "  ").is_err());
+        assert!(set_log_filter(&handle, "mcp_utc_time_server=loud").is_err());
+        assert_eq!(
+            set_log_filter(&handle, "mcp_utc_time_server=debug\n").unwrap(),
+            "mcp_utc_time_server=debug"
+        );
+        assert_eq!(
+            handle.with_current(|f| f.to_string()).unwrap(),
+            "mcp_utc_time_server=debug"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+// Several API calls in one request
+//
+// `POST /api/batch` takes `{"operations": [...]}` and answers with one entry
+// per operation, in request order: `{"ok": body}` with the body the single
+// endpoint would send (in the requested API version, without its own
+// `api_version`), or `{"error": {code, message}}`, so one bad operation does
+// not fail the others. Operations are read into the same request types the
+// single endpoints and tools use and run concurrently. A body that is not
+// `{"operations": [...]}`, or that holds more than `MAX_BATCH_OPERATIONS`, is
+// refused with 400.
+
+use super::request::HttpRequest;
+use super::response::HttpResponse;
+use super::versions::{ApiVersion, Shape};
+use super::{error_response, zone_reading};
+use crate::chaos;
+use crate::error::TimeServerError;
+use crate::server_sdk::{ConvertTimeParams, TimeServer};
+use crate::time::utc::EnhancedTimeResponse;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+pub const BATCH_ROUTE: &str = "/api/batch";
+
+/// Most operations one batch may hold
+pub const MAX_BATCH_OPERATIONS: usize = 20;
+
+/// Methods `/api/batch` accepts
+pub const ALLOWED_METHODS: &str = "POST, OPTIONS";
+
+/// One operation, named by its `op` field
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Operation {
+    /// `GET /api/time`
+    Time,
+    /// `GET /api/time/timezone/{timezone}`
+    Timezone { timezone: String },
+    /// The `convert_time` tool
+    Convert(ConvertTimeParams),
+}
+
+impl Operation {
+    fn run(self, server: &TimeServer, version: ApiVersion) -> Result<Value, TimeServerError> {
+        let body = match self {
+            Operation::Time => {
+                let now = chaos::now();
+                let reading = server
+                    .validity()
+                    .current_time(now)
+                    .attach(EnhancedTimeResponse::at(now));
+                version.to_value(Shape::TimeReading, &reading)
+            }
+            Operation::Timezone { timezone } => version.to_value(
+                Shape::TimeReading,
+                &zone_reading(&timezone, server.validity())?,
+            ),
+            Operation::Convert(params) => version.to_value(Shape::Stable, &server.convert(params)?),
+        };
+        body.map_err(|e| TimeServerError::Io(e.into()))
+    }
+}
+
+/// The operations in `body`, each parsed on its own so a bad one only fails itself
+fn parse(body: &str) -> Result<Vec<Result<Operation, TimeServerError>>, TimeServerError> {
+    #[derive(Deserialize)]
+    struct Batch {
+        operations: Vec<Value>,
+    }
+
+    let batch: Batch = serde_json::from_str(body).map_err(|e| {
+        TimeServerError::InvalidArgument(format!("body must be {{\"operations\": [...]}}: {}", e))
+    })?;
+    if batch.operations.len() > MAX_BATCH_OPERATIONS {
+        return Err(TimeServerError::InvalidArgument(format!(
+            "{} operations; a batch holds at most {}",
+            batch.operations.len(),
+            MAX_BATCH_OPERATIONS
+        )));
+    }
+    Ok(batch
+        .operations
+        .into_iter()
+        .map(|operation| {
+            serde_json::from_value(operation)
+                .map_err(|e| TimeServerError::InvalidArgument(e.to_string()))
+        })
+        .collect())
+}
+
+fn outcome(result: Result<Value, TimeServerError>) -> Value {
+    match result {
+        Ok(body) => json!({"ok": body}),
+        Err(error) => json!({"error": {"code": error.code(), "message": error.to_string()}}),
+    }
+}
+
+/// Answer `POST /api/batch`
+pub async fn respond(
+    request: &HttpRequest,
+    server: &TimeServer,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    let version = match ApiVersion::requested(request) {
+        Ok(version) => version,
+        Err(error) => return error_response(&error, json!({}), pretty),
+    };
+    let operations = match parse(&request.body) {
+        Ok(operations) => operations,
+        Err(error) => return error_response(&error, json!({}), pretty),
+    };
+
+    let tasks = operations.into_iter().map(|operation| {
+        let server = server.clone();
+        tokio::spawn(async move { operation.and_then(|operation| operation.run(&server, version)) })
+    });
+    let results: Vec<Value> = futures::future::join_all(tasks)
+        .await
+        .into_iter()
+        .map(|joined| {
+            outcome(joined.unwrap_or_else(|e| {
+                Err(TimeServerError::ClockError(format!(
+                    "operation failed: {}",
+                    e
+                )))
+            }))
+        })
+        .collect();
+
+    let body = json!({"count": results.len(), "results": results});
+    version.json(200, "OK", Shape::Stable, &body, pretty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keeps_bad_operations_in_place() {
+        let operations = parse(
+            r#"{"operations": [
+                {"op": "time"},
+                {"op": "sundial"},
+                {"op": "timezone", "timezone": "Asia/Tokyo"},
+                {"op": "convert", "timestamp": 0, "to_timezone": "UTC", "colour": "red"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(matches!(operations[0], Ok(Operation::Time)));
+        assert!(operations[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("sundial"));
+        assert!(matches!(operations[2], Ok(Operation::Timezone { .. })));
+        assert!(operations[3]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("colour"));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_and_oversized_batches() {
+        for body in ["", "[]", r#"{"ops": []}"#, r#"{"operations": {}}"#] {
+            assert_eq!(
+                parse(body).unwrap_err().code(),
+                "invalid_argument",
+                "{}",
+                body
+            );
+        }
+        let full = json!({"operations": vec![json!({"op": "time"}); MAX_BATCH_OPERATIONS]});
+        assert_eq!(
+            parse(&full.to_string()).unwrap().len(),
+            MAX_BATCH_OPERATIONS
+        );
+        let over = json!({"operations": vec![json!({"op": "time"}); MAX_BATCH_OPERATIONS + 1]});
+        assert!(parse(&over.to_string()).is_err());
+    }
+}
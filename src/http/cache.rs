@@ -0,0 +1,113 @@
+// Conditional request support (ETag / If-None-Match) for bodies that never
+// change during the lifetime of the process
+
+use super::response::HttpResponse;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+/// Default `max-age` for static responses, in seconds
+pub const DEFAULT_STATIC_MAX_AGE_SECS: u64 = 3600;
+
+/// A pre-serialized response body with its validators
+#[derive(Debug, Clone)]
+pub struct CachedBody {
+    pub body: Vec<u8>,
+    /// Strong ETag, including the surrounding quotes
+    pub etag: String,
+    /// HTTP-date the body was built
+    pub last_modified: String,
+}
+
+impl CachedBody {
+    /// Serialize `value` once and derive its validators
+    pub fn json(value: &impl serde::Serialize) -> Self {
+        Self::json_as(value, None)
+    }
+
+    /// As [`CachedBody::json`], choosing pretty or compact output
+    pub fn json_as(value: &impl serde::Serialize, pretty: Option<bool>) -> Self {
+        let body = crate::output::to_json(value, pretty).unwrap_or_else(|_| "{}".to_string());
+        Self::new(body.into_bytes())
+    }
+
+    /// Derive the validators of an already serialized JSON body
+    pub fn new(body: Vec<u8>) -> Self {
+        Self {
+            etag: strong_etag(&body),
+            last_modified: Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            body,
+        }
+    }
+
+    /// Build a 200 response, or 304 when the client's `If-None-Match` matches
+    pub fn respond(&self, if_none_match: Option<&str>, max_age_secs: u64) -> HttpResponse {
+        let response = if if_none_match.is_some_and(|header| etag_matches(header, &self.etag)) {
+            HttpResponse::new(304, "Not Modified")
+        } else {
+            HttpResponse::new(200, "OK")
+                .with_header("Content-Type", "application/json")
+                .with_body(self.body.clone())
+        };
+
+        response
+            .with_header("ETag", self.etag.clone())
+            .with_header("Last-Modified", self.last_modified.clone())
+            .with_header("Cache-Control", format!("public, max-age={}", max_age_secs))
+    }
+}
+
+/// Quoted hex SHA-256 prefix of the body
+pub fn strong_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// Evaluate an `If-None-Match` header (list of tags or `*`) against `etag`.
+/// Uses weak comparison as RFC 9110 requires for If-None-Match.
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strong_etag_is_stable_and_quoted() {
+        let a = strong_etag(b"hello");
+        assert_eq!(a, strong_etag(b"hello"));
+        assert_ne!(a, strong_etag(b"hello!"));
+        assert!(a.starts_with('"') && a.ends_with('"'));
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = "\"abc\"";
+        assert!(etag_matches("\"abc\"", etag));
+        assert!(etag_matches("\"x\", \"abc\"", etag));
+        assert!(etag_matches("W/\"abc\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(!etag_matches("\"abd\"", etag));
+        assert!(!etag_matches("", etag));
+    }
+
+    #[test]
+    fn test_respond_not_modified() {
+        let cached = CachedBody::json(&serde_json::json!({"a": 1}));
+
+        let fresh = cached.respond(None, 60);
+        assert_eq!(fresh.status, 200);
+        assert_eq!(fresh.body, cached.body);
+        assert_eq!(fresh.header("Cache-Control"), Some("public, max-age=60"));
+
+        let not_modified = cached.respond(Some(&cached.etag), 60);
+        assert_eq!(not_modified.status, 304);
+        assert!(not_modified.body.is_empty());
+        assert_eq!(not_modified.header("ETag"), Some(cached.etag.as_str()));
+    }
+}
@@ -0,0 +1,84 @@
+// Cross-origin resource sharing: response headers and preflight handling
+
+use super::response::HttpResponse;
+
+/// Methods every route but `/api/batch` accepts
+pub const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+/// Request headers browsers may send on cross-origin calls
+pub const ALLOWED_HEADERS: &str =
+    "Content-Type, Authorization, X-API-Key, If-None-Match, Accept-Version";
+
+/// How long browsers may cache a preflight result
+pub const PREFLIGHT_MAX_AGE_SECS: u64 = 86400;
+
+/// `Access-Control-Allow-Origin` for a request.
+///
+/// `configured` is either `*` or a comma-separated list of exact origins; a
+/// listed origin is echoed back, anything else gets no CORS header at all.
+pub fn allow_origin(configured: &str, request_origin: Option<&str>) -> Option<String> {
+    let configured = configured.trim();
+    if configured == "*" {
+        return Some("*".to_string());
+    }
+
+    let origin = request_origin?;
+    configured
+        .split(',')
+        .map(str::trim)
+        .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        .then(|| origin.to_string())
+}
+
+/// Attach the CORS origin headers to a response
+pub fn apply(
+    mut response: HttpResponse,
+    configured: &str,
+    request_origin: Option<&str>,
+) -> HttpResponse {
+    if let Some(origin) = allow_origin(configured, request_origin) {
+        response = response.with_header("Access-Control-Allow-Origin", origin);
+    }
+    // With an explicit allow-list the header depends on the request
+    if configured.trim() != "*" {
+        response = response.with_header("Vary", "Origin");
+    }
+    response
+}
+
+/// 204 answer to an `OPTIONS` preflight for a route accepting `methods`
+pub fn preflight(methods: &str) -> HttpResponse {
+    HttpResponse::new(204, "No Content")
+        .with_header("Access-Control-Allow-Methods", methods)
+        .with_header("Access-Control-Allow-Headers", ALLOWED_HEADERS)
+        .with_header("Access-Control-Max-Age", PREFLIGHT_MAX_AGE_SECS.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_origin() {
+        assert_eq!(allow_origin("*", None).as_deref(), Some("*"));
+        assert_eq!(
+            allow_origin("*", Some("https://example.com")).as_deref(),
+            Some("*")
+        );
+    }
+
+    #[test]
+    fn test_origin_allow_list() {
+        let configured = "https://a.example, https://b.example";
+        assert_eq!(
+            allow_origin(configured, Some("https://b.example")).as_deref(),
+            Some("https://b.example")
+        );
+        assert_eq!(allow_origin(configured, Some("https://evil.example")), None);
+        assert_eq!(allow_origin(configured, None), None);
+
+        let response = apply(HttpResponse::new(200, "OK"), configured, None);
+        assert_eq!(response.header("Access-Control-Allow-Origin"), None);
+        assert_eq!(response.header("Vary"), Some("Origin"));
+    }
+}
@@ -0,0 +1,192 @@
+// HTTP listeners: one or more bind addresses serving the same router
+//
+// `HTTP_LISTENERS=127.0.0.1:3000;noauth,[::]:8443;auth` opens one socket per
+// entry. Each listener decides for itself whether an API key is required, so
+// an internal port can stay open while an external one enforces auth. A
+// listener that cannot be bound is reported on its own; unless partial
+// binding is allowed, any failure stops the start.
+
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ListenerConfig {
+    pub addr: SocketAddr,
+    /// Requests need a valid API key (except `/health` and `/ready`)
+    pub auth: bool,
+}
+
+impl ListenerConfig {
+    /// `addr[;auth|;noauth]`; without a flag the listener is open
+    pub fn parse(entry: &str) -> Result<Self, String> {
+        let (addr, flag) = match entry.trim().split_once(';') {
+            Some((addr, flag)) => (addr.trim(), Some(flag.trim())),
+            None => (entry.trim(), None),
+        };
+        let addr = addr
+            .parse()
+            .map_err(|_| format!("'{}' is not an address:port", addr))?;
+        let auth = match flag.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("noauth") => false,
+            Some("auth") => true,
+            Some(other) => return Err(format!("'{}' is not auth or noauth", other)),
+        };
+        Ok(Self { addr, auth })
+    }
+
+    /// A comma-separated list of entries
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        list.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+impl fmt::Display for ListenerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = if self.auth { "auth" } else { "noauth" };
+        write!(f, "{};{}", self.addr, flag)
+    }
+}
+
+/// Listeners that could not be bound, each with its error
+#[derive(Debug)]
+pub struct BindError {
+    pub failures: Vec<(ListenerConfig, std::io::Error)>,
+    /// How many listeners were bound
+    pub bound: usize,
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to bind HTTP listener")?;
+        for (i, (listener, error)) in self.failures.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{} ({})", sep, listener, error)?;
+        }
+        if self.bound > 0 {
+            write!(
+                f,
+                "; {} bound (set HTTP_LISTENERS_ALLOW_PARTIAL=true to run with them)",
+                self.bound
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BindError {}
+
+/// Bind every listener; fails when none binds, or when any fails and
+/// `allow_partial` is false
+pub async fn bind_all(
+    configs: &[ListenerConfig],
+    allow_partial: bool,
+) -> Result<Vec<(ListenerConfig, TcpListener)>, BindError> {
+    let mut bound = Vec::with_capacity(configs.len());
+    let mut failures = Vec::new();
+    for &config in configs {
+        match TcpListener::bind(config.addr).await {
+            Ok(listener) => bound.push((config, listener)),
+            Err(error) => {
+                warn!(event = "http.bind_failed", listener = %config, error = %error);
+                failures.push((config, error));
+            }
+        }
+    }
+    if bound.is_empty() || (!failures.is_empty() && !allow_partial) {
+        return Err(BindError {
+            bound: bound.len(),
+            failures,
+        });
+    }
+    Ok(bound)
+}
+
+/// Running listeners; `shutdown` stops accepting on all of them
+#[derive(Debug)]
+pub struct HttpServer {
+    listeners: Vec<ListenerConfig>,
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub(super) fn new(
+        listeners: Vec<ListenerConfig>,
+        shutdown: watch::Sender<bool>,
+        tasks: Vec<JoinHandle<()>>,
+    ) -> Self {
+        Self {
+            listeners,
+            shutdown,
+            tasks,
+        }
+    }
+
+    /// Bound addresses (with the actual port for port 0) and their auth flags
+    pub fn listeners(&self) -> &[ListenerConfig] {
+        &self.listeners
+    }
+
+    /// Stop accepting on every listener and wait for the accept loops to end.
+    /// Connections already accepted are answered.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        self.wait().await;
+    }
+
+    /// Wait until every accept loop has ended
+    pub async fn wait(self) {
+        for task in self.tasks {
+            let _ = task.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listener_list() {
+        let listeners =
+            ListenerConfig::parse_list("127.0.0.1:3000;noauth, [::]:8443;AUTH,0.0.0.0:80").unwrap();
+        assert_eq!(listeners.len(), 3);
+        assert_eq!(listeners[0].to_string(), "127.0.0.1:3000;noauth");
+        assert_eq!(listeners[1].to_string(), "[::]:8443;auth");
+        assert!(listeners[1].addr.is_ipv6());
+        assert!(!listeners[2].auth);
+
+        assert!(ListenerConfig::parse("localhost:80").is_err());
+        assert!(ListenerConfig::parse("127.0.0.1:80;maybe").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_failures_are_reported_per_listener() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let configs = [
+            ListenerConfig::parse("127.0.0.1:0").unwrap(),
+            ListenerConfig {
+                addr: taken.local_addr().unwrap(),
+                auth: true,
+            },
+        ];
+
+        let error = bind_all(&configs, false).await.unwrap_err();
+        assert_eq!(error.bound, 1);
+        assert_eq!(error.failures.len(), 1);
+        assert_eq!(error.failures[0].0, configs[1]);
+        assert!(error.to_string().contains(&configs[1].to_string()));
+
+        let bound = bind_all(&configs, true).await.unwrap();
+        assert_eq!(bound.len(), 1);
+        assert!(bind_all(&configs[1..], true).await.is_err());
+    }
+}
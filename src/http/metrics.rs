@@ -0,0 +1,296 @@
+// Prometheus metrics for `/metrics`
+//
+// Every scrape fills a `Registry` from values already in memory: one reading
+// of the time sources for the time and the source that gave it, the NTP
+// status the last refresh left on the clock, the SHM segment and the request
+// counters. Nothing here runs ntpq, so scraping more often does not query
+// ntpd more often; the NTP metrics are absent until a first status has been
+// read. `encode` writes text format 0.0.4 with each family's HELP and TYPE
+// once, followed by its samples.
+
+use crate::state::ServerState;
+use crate::stats::ServerStats;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Gauge,
+    Counter,
+}
+
+impl MetricType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Gauge => "gauge",
+            Self::Counter => "counter",
+        }
+    }
+}
+
+/// One metric name with its help text, type and samples
+#[derive(Debug)]
+pub struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricType,
+    samples: Vec<(Vec<(&'static str, String)>, f64)>,
+}
+
+impl Family {
+    /// Add a sample with `labels`
+    pub fn sample(&mut self, labels: &[(&'static str, &str)], value: f64) -> &mut Self {
+        let labels = labels
+            .iter()
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect();
+        self.samples.push((labels, value));
+        self
+    }
+}
+
+/// Metric families in the order they are registered
+#[derive(Debug, Default)]
+pub struct Registry {
+    families: Vec<Family>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `name`; samples are added to the returned family
+    pub fn family(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        kind: MetricType,
+    ) -> &mut Family {
+        debug_assert!(
+            self.families.iter().all(|f| f.name != name),
+            "{} registered twice",
+            name
+        );
+        self.families.push(Family {
+            name,
+            help,
+            kind,
+            samples: Vec::new(),
+        });
+        self.families.last_mut().expect("just pushed")
+    }
+
+    /// An unlabelled gauge
+    pub fn gauge(&mut self, name: &'static str, help: &'static str, value: f64) {
+        self.family(name, help, MetricType::Gauge)
+            .sample(&[], value);
+    }
+
+    /// An unlabelled counter
+    pub fn counter(&mut self, name: &'static str, help: &'static str, value: f64) {
+        self.family(name, help, MetricType::Counter)
+            .sample(&[], value);
+    }
+
+    /// Text exposition format
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# HELP {} {}", family.name, escape_help(family.help));
+            let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind.as_str());
+            for (labels, value) in &family.samples {
+                out.push_str(family.name);
+                if !labels.is_empty() {
+                    let labels: Vec<String> = labels
+                        .iter()
+                        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                        .collect();
+                    let _ = write!(out, "{{{}}}", labels.join(","));
+                }
+                let _ = writeln!(out, " {}", format_value(*value));
+            }
+        }
+        out
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label(value: &str) -> String {
+    escape_help(value).replace('"', "\\\"")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn flag(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// The metrics for one scrape; `legacy` adds the names of earlier releases
+pub fn collect(state: &ServerState, stats: &ServerStats, legacy: bool) -> Registry {
+    let mut registry = Registry::new();
+
+    // One reading, so the time and its source cannot disagree
+    if let Ok(now) = state.time_sources().now() {
+        registry.gauge(
+            "mcp_time_seconds",
+            "Current Unix time in seconds, with the fraction",
+            now.time.seconds as f64 + f64::from(now.time.nanos) / 1e9,
+        );
+        if legacy {
+            registry.gauge(
+                "mcp_time_nanos",
+                "Nanoseconds within the second of mcp_time_seconds (deprecated)",
+                f64::from(now.time.nanos),
+            );
+        }
+        let sources = registry.family(
+            "mcp_clock_source",
+            "Configured time sources; 1 for the one that gave mcp_time_seconds",
+            MetricType::Gauge,
+        );
+        for source in state.time_sources().priority() {
+            sources.sample(&[("source", source.as_str())], flag(source == now.source));
+        }
+    }
+
+    #[cfg(feature = "ntp")]
+    ntp_metrics(&mut registry, state.ntp_clock());
+
+    registry.counter(
+        "mcp_rate_limited_total",
+        "MCP tool calls refused by a session rate limit",
+        stats.rate_limited() as f64,
+    );
+    registry
+}
+
+#[cfg(feature = "ntp")]
+fn ntp_metrics(registry: &mut Registry, clock: &crate::ntp::NtpSyncedClock) {
+    registry.gauge(
+        "mcp_shm_valid",
+        "1 when the NTP shared memory segment holds a valid sample",
+        flag(clock.shm().is_some_and(|shm| shm.is_valid())),
+    );
+    let Some(status) = clock.latest_status() else {
+        return;
+    };
+    registry.gauge(
+        "mcp_ntp_offset_milliseconds",
+        "Offset of the system clock from NTP time at the last status refresh",
+        status.offset_ms,
+    );
+    registry.gauge(
+        "mcp_ntp_stratum",
+        "NTP stratum at the last status refresh (16 when unsynchronized)",
+        f64::from(status.stratum),
+    );
+    registry.gauge(
+        "mcp_ntp_synced",
+        "1 when the last status refresh found the clock synchronized",
+        flag(status.synced),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus_parse::{Scrape, Value};
+
+    fn parse(text: &str) -> Scrape {
+        Scrape::parse(text.lines().map(|line| Ok(line.to_string()))).unwrap()
+    }
+
+    #[test]
+    fn test_encode_escapes_and_parses() {
+        let mut registry = Registry::new();
+        registry.gauge("a_gauge", "A gauge\nover two lines", 1.5);
+        registry.counter("a_total", "A counter", 3.0);
+        registry
+            .family("a_labelled", "Labels", MetricType::Gauge)
+            .sample(&[("name", "say \"hi\"\\")], 1.0)
+            .sample(&[("name", "plain")], f64::NAN);
+        let text = registry.encode();
+        assert!(text.contains("# HELP a_gauge A gauge\\nover two lines\n"));
+        assert!(text.contains("a_labelled{name=\"say \\\"hi\\\"\\\\\"} 1\n"));
+        assert!(text.contains("a_labelled{name=\"plain\"} NaN\n"));
+
+        let scrape = parse(&text);
+        assert_eq!(scrape.samples.len(), 4);
+        assert!(matches!(scrape.samples[0].value, Value::Gauge(v) if v == 1.5));
+        assert!(matches!(scrape.samples[1].value, Value::Counter(v) if v == 3.0));
+        assert_eq!(scrape.samples[3].labels.get("name"), Some("plain"));
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_collect_reads_cached_status() {
+        use crate::ntp::{NtpStatus, NtpSyncedClock};
+        use std::sync::Arc;
+
+        let clock = Arc::new(NtpSyncedClock::new());
+        let state = ServerState::with_ntp_clock(clock.clone());
+        let stats = ServerStats::new();
+
+        let scrape = parse(&collect(&state, &stats, false).encode());
+        let names: Vec<&str> = scrape.samples.iter().map(|s| s.metric.as_str()).collect();
+        assert!(names.contains(&"mcp_time_seconds"));
+        assert!(names.contains(&"mcp_shm_valid"));
+        // No status read yet, and collecting does not read one
+        assert!(!names.contains(&"mcp_ntp_synced"));
+        assert!(!names.contains(&"mcp_time_nanos"));
+        assert!(clock.latest_status().is_none());
+
+        clock.set_latest_status(NtpStatus {
+            synced: true,
+            offset_ms: -0.25,
+            stratum: 2,
+            ..Default::default()
+        });
+        let scrape = parse(&collect(&state, &stats, true).encode());
+        let value = |name: &str| {
+            scrape
+                .samples
+                .iter()
+                .find(|s| s.metric == name)
+                .map(|s| s.value.clone())
+        };
+        assert!(
+            matches!(value("mcp_ntp_offset_milliseconds"), Some(Value::Gauge(v)) if v == -0.25)
+        );
+        assert!(matches!(value("mcp_ntp_stratum"), Some(Value::Gauge(v)) if v == 2.0));
+        assert!(matches!(value("mcp_ntp_synced"), Some(Value::Gauge(v)) if v == 1.0));
+        assert!(matches!(value("mcp_time_nanos"), Some(Value::Gauge(v)) if v < 1e9));
+        assert!(matches!(
+            value("mcp_rate_limited_total"),
+            Some(Value::Counter(_))
+        ));
+
+        let sources: Vec<_> = scrape
+            .samples
+            .iter()
+            .filter(|s| s.metric == "mcp_clock_source")
+            .collect();
+        assert_eq!(sources.len(), state.time_sources().priority().len());
+        let active: Vec<_> = sources
+            .iter()
+            .filter(|s| matches!(s.value, Value::Gauge(v) if v == 1.0))
+            .collect();
+        assert_eq!(active.len(), 1);
+        assert!(active[0].labels.get("source").is_some());
+    }
+}
@@ -0,0 +1,965 @@
+// HTTP API server for health checks and REST time queries
+//
+// A deliberately small HTTP/1.1 implementation on raw tokio sockets: one
+// request per connection, JSON responses, no external web framework.
+
+pub mod admin;
+pub mod batch;
+pub mod cache;
+pub mod cors;
+pub mod listener;
+pub mod metrics;
+pub mod request;
+pub mod response;
+pub mod stream;
+pub mod versions;
+
+use anyhow::Result;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::audit::{AuditEvent, AuditLogger};
+use crate::auth::{ApiKeyValidator, AuthFailureMonitor, SharedApiKeys};
+use crate::chaos;
+use crate::config::ServerConfig;
+use crate::error::TimeServerError;
+use crate::export::{self, OutputFormat};
+use crate::guard::ClockGuard;
+use crate::health::{clock_guard_check, HealthProbe, HealthReport, HealthStatus};
+use crate::maintenance::MaintenanceWindow;
+use crate::sanity::{CachedSanity, CheckStatus, SanityConfig};
+use crate::server_sdk::TimeServer;
+use crate::time::utc::EnhancedTimeResponse;
+use crate::time::{StrftimeFormatter, TimezoneConverter, TimezoneListQuery, TimezonePage};
+use crate::validity::{ValidityConfig, ZoneReading};
+use cache::{CachedBody, DEFAULT_STATIC_MAX_AGE_SECS};
+pub use listener::{HttpServer, ListenerConfig};
+use request::{percent_decode, HttpRequest, Method, ReadError};
+use response::{HttpResponse, DEFAULT_COMPRESSION_MIN_BYTES};
+use versions::{ApiVersion, Shape};
+
+/// Settings for the HTTP API server, read once at startup
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    /// Responses at least this large are compressed when the client accepts it
+    pub compression_min_bytes: usize,
+    /// `max-age` sent with responses that never change while the process runs
+    pub static_max_age_secs: u64,
+    /// `*` or a comma-separated list of origins allowed to call the API
+    pub cors_allow_origin: String,
+    /// Known API keys, used to name the caller in audit records; reloaded
+    /// by `POST /admin/keys` and SIGHUP
+    pub api_keys: SharedApiKeys,
+    /// Unknown keys presented; too many in a minute raise an `auth.failures` event
+    pub auth_failures: Arc<AuthFailureMonitor>,
+    pub audit: Option<AuditLogger>,
+    /// Time sanity checks; in strict mode a failure makes `/ready` return 503
+    pub sanity: Arc<CachedSanity>,
+    /// Grades the clock for `/health`; 503 when unhealthy in strict mode
+    pub health: Arc<HealthProbe>,
+    /// Announced in `/health` while open or about to open
+    pub maintenance: Option<MaintenanceWindow>,
+    /// Refuses time routes while the system clock is implausible
+    pub clock_guard: Arc<ClockGuard>,
+    /// `/metrics` also emits `mcp_time_nanos` (METRICS_LEGACY)
+    pub metrics_legacy: bool,
+    /// The `/admin/` routes; None (and 404) without ADMIN_TOKEN
+    pub admin: Option<admin::AdminSettings>,
+}
+
+impl HttpSettings {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            compression_min_bytes: config.http.compression_min_bytes,
+            static_max_age_secs: config.http.static_max_age_secs,
+            cors_allow_origin: config.http.cors_allow_origin.clone(),
+            api_keys: SharedApiKeys::new(ApiKeyValidator::from_api_keys(api_keys(config))),
+            auth_failures: Arc::default(),
+            audit: crate::audit::global().cloned(),
+            sanity: Arc::new(CachedSanity::new(SanityConfig::global().clone())),
+            health: Arc::new(HealthProbe::new(
+                config.health.clone(),
+                config.mode.container,
+            )),
+            maintenance: config.maintenance.clone(),
+            clock_guard: crate::guard::global(),
+            metrics_legacy: config.http.metrics_legacy,
+            admin: admin::AdminSettings::from_config(config),
+        }
+    }
+
+    /// Let `PUT /admin/loglevel` change the filter `handle` controls
+    pub fn with_log_filter(mut self, handle: admin::LogFilterHandle) -> Self {
+        if let Some(admin) = &mut self.admin {
+            admin.set_log_filter(handle);
+        }
+        self
+    }
+}
+
+/// Keys from the API_KEY_* variables; a build without the auth feature reads none
+#[cfg(feature = "auth")]
+fn api_keys(config: &ServerConfig) -> Vec<crate::auth::ApiKey> {
+    config.auth.api_keys.clone()
+}
+
+#[cfg(not(feature = "auth"))]
+fn api_keys(_config: &ServerConfig) -> Vec<crate::auth::ApiKey> {
+    Vec::new()
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            compression_min_bytes: DEFAULT_COMPRESSION_MIN_BYTES,
+            static_max_age_secs: DEFAULT_STATIC_MAX_AGE_SECS,
+            cors_allow_origin: "*".into(),
+            api_keys: SharedApiKeys::new(ApiKeyValidator::from_keys(Vec::new())),
+            auth_failures: Arc::default(),
+            audit: None,
+            sanity: Arc::new(CachedSanity::new(SanityConfig::default())),
+            health: Arc::new(HealthProbe::new(Default::default(), false)),
+            maintenance: None,
+            clock_guard: crate::guard::global(),
+            metrics_legacy: false,
+            admin: None,
+        }
+    }
+}
+
+const TIMEZONE_ROUTE_PREFIX: &str = "/api/time/timezone/";
+
+/// `/api/timezone/{tz}/timeline`
+const TIMELINE_ROUTE_PREFIX: &str = "/api/timezone/";
+const TIMELINE_ROUTE_SUFFIX: &str = "/timeline";
+
+/// Probes answered without an API key on listeners that require one
+const AUTH_EXEMPT_ROUTES: &[&str] = &["/health", "/ready"];
+
+/// `/api/` routes that keep answering while the clock guard refuses time
+const GUARD_EXEMPT_ROUTES: &[&str] = &["/api/info", "/api/ntp/status", "/api/signing-key"];
+
+/// Fixed-path routes; the timezone prefix route is matched separately
+const ROUTES: &[&str] = &[
+    "/health",
+    "/ready",
+    "/metrics",
+    "/api/time",
+    "/api/time/stream",
+    "/api/unix",
+    "/api/nanos",
+    "/api/timezones",
+    "/api/timezone/locate",
+    "/api/format/explain",
+    "/api/ntp/status",
+    "/api/info",
+    "/api/signing-key",
+    batch::BATCH_ROUTE,
+];
+
+fn is_known_route(path: &str) -> bool {
+    path == "/"
+        || ROUTES.contains(&path)
+        || path.starts_with(TIMEZONE_ROUTE_PREFIX)
+        || timeline_zone(path).is_some()
+}
+
+/// The still-encoded zone of a timeline path
+fn timeline_zone(path: &str) -> Option<&str> {
+    path.strip_prefix(TIMELINE_ROUTE_PREFIX)?
+        .strip_suffix(TIMELINE_ROUTE_SUFFIX)
+}
+
+/// The full timezone list, serialized once per process in each version and style
+fn timezones_body(version: ApiVersion, pretty: bool) -> &'static CachedBody {
+    static BODIES: [std::sync::OnceLock<CachedBody>; 2 * ApiVersion::SUPPORTED.len()] =
+        [const { std::sync::OnceLock::new() }; 2 * ApiVersion::SUPPORTED.len()];
+    BODIES[version as usize * 2 + pretty as usize]
+        .get_or_init(|| timezones_page(&TimezoneListQuery::default(), version, pretty))
+}
+
+fn timezones_page(query: &TimezoneListQuery, version: ApiVersion, pretty: bool) -> CachedBody {
+    render_page(
+        &TimezoneConverter::list_timezones_page(query),
+        version,
+        pretty,
+    )
+}
+
+fn render_page(page: &TimezonePage, version: ApiVersion, pretty: bool) -> CachedBody {
+    let json = version
+        .render(Shape::Stable, page, pretty)
+        .unwrap_or_else(|_| "{}".to_string());
+    CachedBody::new(json.into_bytes())
+}
+
+/// `?pretty=false` for compact JSON; absent or unparseable uses `JSON_PRETTY`
+fn pretty_param(request: &HttpRequest) -> Option<bool> {
+    request
+        .query_param("pretty")
+        .and_then(crate::output::parse_flag)
+}
+
+/// Parse `/api/timezones` query parameters; the error names the bad parameter
+fn timezone_list_query(request: &HttpRequest) -> Result<TimezoneListQuery, TimeServerError> {
+    let number = |name: &str| -> Result<Option<usize>, TimeServerError> {
+        request
+            .query_param(name)
+            .map(|v| {
+                v.parse().map_err(|_| {
+                    TimeServerError::InvalidArgument(format!(
+                        "'{}' must be a non-negative integer",
+                        name
+                    ))
+                })
+            })
+            .transpose()
+    };
+
+    Ok(TimezoneListQuery {
+        offset: number("offset")?,
+        limit: number("limit")?,
+        prefix: request.query_param("prefix").map(str::to_string),
+        region: request.query_param("region").map(str::to_string),
+        grouped: matches!(request.query_param("grouped"), Some("true" | "1" | "")),
+        detail: matches!(request.query_param("detail"), Some("true" | "1" | "")),
+    })
+}
+
+/// Run HTTP API server for health checks and time queries
+/// This provides a REST API at /health, /api/time, /api/unix, etc.
+pub async fn run_http_api_server(config: &ServerConfig) -> Result<()> {
+    run_http_api_server_with(config, HttpSettings::from_config(config)).await
+}
+
+/// Run the HTTP API server with `settings` instead of those read from `config`
+pub async fn run_http_api_server_with(config: &ServerConfig, settings: HttpSettings) -> Result<()> {
+    start_http_api(config, settings).await?.wait().await;
+    Ok(())
+}
+
+/// Bind every configured listener and serve on each of them until
+/// [`HttpServer::shutdown`]. Listeners share the router and differ only in
+/// whether they require an API key.
+pub async fn start_http_api(config: &ServerConfig, settings: HttpSettings) -> Result<HttpServer> {
+    let bound = listener::bind_all(
+        &config.http.effective_listeners(),
+        config.http.allow_partial_bind,
+    )
+    .await?;
+
+    let server = TimeServer::with_config(config).with_clock_guard(settings.clock_guard.clone());
+    let (shutdown, stop) = tokio::sync::watch::channel(false);
+    let mut listeners = Vec::with_capacity(bound.len());
+    let mut tasks = Vec::with_capacity(bound.len());
+    for (listener_config, socket) in bound {
+        let local = ListenerConfig {
+            addr: socket.local_addr()?,
+            ..listener_config
+        };
+        info!(
+            event = "http.server.start",
+            addr = %local.addr,
+            port = local.addr.port(),
+            auth = local.auth,
+            "HTTP API server listening"
+        );
+        if local.auth && !settings.api_keys.has_keys() {
+            warn!(event = "http.auth_without_keys", listener = %local);
+        }
+        listeners.push(local);
+        tasks.push(tokio::spawn(accept_loop(
+            socket,
+            local.auth,
+            server.clone(),
+            settings.clone(),
+            stop.clone(),
+        )));
+    }
+
+    crate::info::record_http_api(crate::info::HttpApiInfo {
+        port: listeners[0].addr.port(),
+        auth_enabled: settings.api_keys.has_keys(),
+        api_key_count: settings.api_keys.key_count(),
+    });
+    timezones_body(ApiVersion::default(), crate::output::pretty_default()); // Serialize static bodies before accepting traffic
+
+    Ok(HttpServer::new(listeners, shutdown, tasks))
+}
+
+/// Accept connections on `listener` until `stop` turns true
+async fn accept_loop(
+    listener: tokio::net::TcpListener,
+    auth_required: bool,
+    server: TimeServer,
+    settings: HttpSettings,
+    mut stop: tokio::sync::watch::Receiver<bool>,
+) {
+    use tokio::io::AsyncWriteExt;
+
+    loop {
+        let (mut socket, peer_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    debug!(event = "http.accept_error", error = %e);
+                    continue;
+                }
+            },
+            _ = stop.wait_for(|stopped| *stopped) => break,
+        };
+        let server_clone = server.clone();
+        let settings = settings.clone();
+
+        tokio::spawn(async move {
+            match tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                request::read_request(&mut socket),
+            )
+            .await
+            {
+                Ok(Ok(raw)) => {
+                    let request = String::from_utf8_lossy(&raw);
+                    debug!(event = "http.request", peer = %peer_addr, request = %request.lines().next().unwrap_or(""));
+
+                    // A stream owns the socket until it ends; it never goes through the
+                    // router, except to be refused there while the clock is implausible
+                    if let Some((request, params)) = stream::streaming_request(&request) {
+                        if (!auth_required || is_authorized(&request, &settings))
+                            && settings.clock_guard.check().is_ok()
+                        {
+                            serve_stream(socket, request, params, &server_clone, &settings).await;
+                            return;
+                        }
+                    }
+
+                    let response =
+                        handle_http_request(&request, &server_clone, &settings, auth_required)
+                            .await;
+
+                    if let Err(e) = socket.write_all(&response.to_bytes()).await {
+                        debug!(event = "http.write_error", error = %e, peer = %peer_addr);
+                    }
+                }
+                Ok(Err(ReadError::Empty)) => {
+                    debug!(event = "http.empty_request", peer = %peer_addr);
+                }
+                Ok(Err(ReadError::TooLarge)) => {
+                    debug!(event = "http.request_too_large", peer = %peer_addr);
+                    let response = HttpResponse::new(413, "Payload Too Large");
+                    let _ = socket.write_all(&response.to_bytes()).await;
+                }
+                Ok(Err(ReadError::Io(e))) => {
+                    debug!(event = "http.read_error", error = %e, peer = %peer_addr);
+                }
+                Err(_) => {
+                    debug!(event = "http.timeout", peer = %peer_addr);
+                    let response = HttpResponse::new(408, "Request Timeout");
+                    let _ = socket.write_all(&response.to_bytes()).await;
+                }
+            }
+
+            let _ = socket.shutdown().await;
+        });
+    }
+    debug!(event = "http.server.stop", addr = ?listener.local_addr().ok());
+}
+
+/// Write `/api/time/stream` samples from the configured time sources
+async fn serve_stream(
+    mut socket: tokio::net::TcpStream,
+    request: HttpRequest,
+    params: stream::StreamParams,
+    server: &TimeServer,
+    settings: &HttpSettings,
+) {
+    let started = std::time::Instant::now();
+    let head = cors::apply(
+        stream::stream_head(),
+        &settings.cors_allow_origin,
+        request.header("origin"),
+    );
+    let state = server.state();
+    let result = stream::serve(&mut socket, head.clone(), params, || {
+        state.time_sources().now()
+    })
+    .await;
+    match result {
+        Ok(sent) => debug!(
+            event = "http.stream.end",
+            sent = sent,
+            requested = params.count
+        ),
+        Err(e) => debug!(event = "http.stream.write_error", error = %e),
+    }
+    if let Some(audit) = &settings.audit {
+        audit.record(audit_event(&request, &head, settings, started));
+    }
+    let _ = tokio::io::AsyncWriteExt::shutdown(&mut socket).await;
+}
+
+async fn handle_http_request(
+    raw: &str,
+    server: &TimeServer,
+    settings: &HttpSettings,
+    auth_required: bool,
+) -> HttpResponse {
+    let started = std::time::Instant::now();
+    crate::stats::global().record_http_request();
+    let request = match HttpRequest::parse(raw) {
+        Some(request) => request,
+        None => return HttpResponse::new(400, "Bad Request"),
+    };
+
+    let path = request.path.as_str();
+    let is_admin = path.starts_with(admin::ADMIN_ROUTE_PREFIX);
+    let mut response = match &request.method {
+        // Preflights carry no credentials, so they are answered before auth
+        Method::Options if path == batch::BATCH_ROUTE => cors::preflight(batch::ALLOWED_METHODS),
+        Method::Options if is_known_route(path) || path.starts_with("/api/") => {
+            cors::preflight(cors::ALLOWED_METHODS)
+        }
+        // The admin token, not an API key, guards these on every listener
+        _ if is_admin => admin::respond(&request, settings),
+        _ if auth_required
+            && !AUTH_EXEMPT_ROUTES.contains(&path)
+            && !is_authorized(&request, settings) =>
+        {
+            let error = TimeServerError::Unauthorized(match presented_key(&request) {
+                Some(_) => "invalid API key".to_string(),
+                None => "API key required (X-API-Key or Authorization: Bearer)".to_string(),
+            });
+            error_response(&error, json!({}), pretty_param(&request))
+                .with_header("WWW-Authenticate", "Bearer")
+        }
+        Method::Post if path == batch::BATCH_ROUTE => match settings.clock_guard.check() {
+            Ok(()) => batch::respond(&request, server, pretty_param(&request)).await,
+            Err(error) => error_response(&error, json!({}), pretty_param(&request)),
+        },
+        _ if path == batch::BATCH_ROUTE => method_not_allowed(&request, batch::ALLOWED_METHODS),
+        Method::Get | Method::Head => route(&request, server, settings).await,
+        _ if is_known_route(path) => method_not_allowed(&request, cors::ALLOWED_METHODS),
+        _ => not_found(path, pretty_param(&request)),
+    };
+
+    response = cors::apply(
+        response,
+        &settings.cors_allow_origin,
+        request.header("origin"),
+    );
+
+    // Anything not explicitly cacheable is time-dependent and must never be stored
+    if response.header("Cache-Control").is_none() {
+        response = response.with_header("Cache-Control", "no-store");
+    }
+
+    // Chaos mode is flagged on every response, whatever its body
+    if let Some(chaos) = chaos::global() {
+        response = response.with_header("X-Chaos-Mode", chaos.config().anomaly.to_string());
+    }
+
+    let response = response.negotiate_encoding(
+        request.header("accept-encoding"),
+        settings.compression_min_bytes,
+    );
+
+    if let Some(key) = presented_key(&request).filter(|_| !is_admin) {
+        if settings.api_keys.has_keys() && !settings.api_keys.validate(key) {
+            settings.auth_failures.record("http");
+        }
+    }
+
+    if let Some(audit) = &settings.audit {
+        if (path.starts_with("/api/") || is_admin) && request.method != Method::Options {
+            audit.record(audit_event(&request, &response, settings, started));
+        }
+    }
+
+    // HEAD gets exactly the GET headers, including the encoded Content-Length
+    if request.method == Method::Head {
+        response.without_body()
+    } else {
+        response
+    }
+}
+
+async fn route(
+    request: &HttpRequest,
+    server: &TimeServer,
+    settings: &HttpSettings,
+) -> HttpResponse {
+    let path = request.path.as_str();
+    let pretty = pretty_param(request);
+    let version = if path.starts_with("/api/") && path != stream::STREAM_ROUTE {
+        match ApiVersion::requested(request) {
+            Ok(version) => version,
+            Err(error) => return error_response(&error, json!({}), pretty),
+        }
+    } else {
+        ApiVersion::default()
+    };
+    if path.starts_with("/api/") && !GUARD_EXEMPT_ROUTES.contains(&path) {
+        if let Err(error) = settings.clock_guard.check() {
+            return error_response(&error, json!({}), pretty);
+        }
+    }
+
+    match path {
+        "/health" | "/" => {
+            let (mut report, ntp_status) = graded_health(server, settings).await;
+            if let Some(check) = clock_guard_check(&settings.clock_guard.status()) {
+                report.push(check);
+            }
+
+            let now = chaos::now();
+            let mut health = json!({
+                "status": report.status,
+                "version": env!("CARGO_PKG_VERSION"),
+                "service": "mcp-utc-time-server",
+                "timestamp": now.to_rfc3339(),
+                "ntp": ntp_status,
+                "checks": report.checks
+            });
+            if let Some(notice) = settings.maintenance.as_ref().and_then(|w| w.notice(now)) {
+                health["maintenance"] = json!(notice);
+            }
+
+            if report.status == HealthStatus::Unhealthy && settings.health.config().strict {
+                HttpResponse::json_as(503, "Service Unavailable", &health, pretty)
+            } else {
+                HttpResponse::json_as(200, "OK", &health, pretty)
+            }
+        }
+        "/ready" => {
+            let guard = settings.clock_guard.status();
+            if !guard.serving {
+                let body = json!({"status": "not_ready", "clock": guard});
+                return HttpResponse::json_as(503, "Service Unavailable", &body, pretty);
+            }
+            if !settings.sanity.config().strict {
+                return HttpResponse::json_as(200, "OK", &json!({"status": "ready"}), pretty);
+            }
+            let report = settings.sanity.report().await;
+            if report.status == CheckStatus::Fail {
+                let body = json!({"status": "not_ready", "time_sanity": report});
+                HttpResponse::json_as(503, "Service Unavailable", &body, pretty)
+            } else {
+                let body = json!({"status": "ready", "time_sanity": report});
+                HttpResponse::json_as(200, "OK", &body, pretty)
+            }
+        }
+        "/metrics" => {
+            let registry = metrics::collect(
+                server.state(),
+                crate::stats::global(),
+                settings.metrics_legacy,
+            );
+            HttpResponse::text(200, "OK", &registry.encode(), "text/plain; version=0.0.4")
+        }
+        "/api/time" => {
+            let now = chaos::now();
+            let response = server
+                .validity()
+                .current_time(now)
+                .attach(EnhancedTimeResponse::at(now));
+            version.json(200, "OK", Shape::TimeReading, &response, pretty)
+        }
+        // GET with valid parameters is streamed before routing; HEAD gets the headers
+        stream::STREAM_ROUTE => match stream::StreamParams::from_request(request) {
+            Ok(_) => stream::stream_head(),
+            Err(error) => error_response(&error, json!({}), pretty),
+        },
+        "/api/unix" => {
+            let now = chaos::now();
+            let response = server
+                .validity()
+                .current_time(now)
+                .attach(crate::time::UnixTime::from_datetime(now));
+            version.json(200, "OK", Shape::Stable, &response, pretty)
+        }
+        "/api/nanos" => {
+            let reading = crate::time::UnixTime::now().nanos_reading();
+            version.json(200, "OK", Shape::Stable, &reading, pretty)
+        }
+        "/api/timezones" => {
+            let query = match timezone_list_query(request) {
+                Ok(query) => query,
+                Err(error) => return error_response(&error, json!({}), pretty),
+            };
+            let if_none_match = request.header("if-none-match");
+            let pretty = pretty.unwrap_or_else(crate::output::pretty_default);
+            let response = if query == TimezoneListQuery::default() {
+                timezones_body(version, pretty).respond(if_none_match, settings.static_max_age_secs)
+            } else {
+                let page = TimezoneConverter::list_timezones_page(&query);
+                // Detailed offsets are only good until the listing is recomputed
+                let max_age = page
+                    .detail_expires_at()
+                    .map_or(settings.static_max_age_secs, |expires_at| {
+                        (expires_at - chaos::now()).num_seconds().max(0) as u64
+                    });
+                render_page(&page, version, pretty).respond(if_none_match, max_age)
+            };
+            response.with_versioning(version)
+        }
+        "/api/timezone/locate" => match locate_query(request) {
+            Ok(located) => version.json(200, "OK", Shape::Stable, &located, pretty),
+            Err(error) => error_response(&error, json!({}), pretty),
+        },
+        "/api/format/explain" => match request.query_param("format") {
+            Some(format) => version.json(
+                200,
+                "OK",
+                Shape::Stable,
+                &StrftimeFormatter::explain(format, &chaos::now()),
+                pretty,
+            ),
+            None => error_response(
+                &TimeServerError::InvalidArgument("'format' query parameter is required".into()),
+                json!({}),
+                pretty,
+            ),
+        },
+        path if path.starts_with(TIMEZONE_ROUTE_PREFIX) => timezone_response(
+            &path[TIMEZONE_ROUTE_PREFIX.len()..],
+            server.validity(),
+            version,
+            pretty,
+        ),
+        path if timeline_zone(path).is_some() => timeline_response(
+            timeline_zone(path).unwrap_or_default(),
+            request,
+            version,
+            pretty,
+        ),
+        "/api/info" => version.json(
+            200,
+            "OK",
+            Shape::Stable,
+            &crate::info::collect(server.disabled_tools()),
+            pretty,
+        ),
+        "/api/signing-key" => match crate::attestation::global() {
+            Ok(signer) => version.json(200, "OK", Shape::Stable, &signer.key_info(), pretty),
+            Err(error) => error_response(&error, json!({}), pretty),
+        },
+        "/api/ntp/status" => ntp_status_response(server, settings, version, pretty).await,
+        _ => not_found(path, pretty),
+    }
+}
+
+/// The `/health` grade and its `ntp` object
+#[cfg(feature = "ntp")]
+async fn graded_health(
+    server: &TimeServer,
+    settings: &HttpSettings,
+) -> (HealthReport, serde_json::Value) {
+    use crate::ntp::NtpSyncedClock;
+
+    let (report, status) = settings.health.report(server.state().ntp_clock()).await;
+    // A container's status is assumed, not measured, so it confirms nothing
+    settings.clock_guard.observe_ntp(
+        !NtpSyncedClock::is_container_environment() && status.as_ref().is_ok_and(|s| s.synced),
+    );
+    let ntp_status = match status {
+        Ok(s) => json!({
+            "synced": s.synced,
+            "offset_ms": s.offset_ms,
+            "stratum": s.stratum,
+            "shm_valid": s.shm_valid,
+            "pps_enabled": s.pps_enabled
+        }),
+        Err(_) => json!({"available": false}),
+    };
+    (report, ntp_status)
+}
+
+/// Without the ntp feature there is no sync to grade
+#[cfg(not(feature = "ntp"))]
+async fn graded_health(
+    _server: &TimeServer,
+    settings: &HttpSettings,
+) -> (HealthReport, serde_json::Value) {
+    (settings.health.report(), json!({"available": false}))
+}
+
+#[cfg(feature = "ntp")]
+async fn ntp_status_response(
+    server: &TimeServer,
+    settings: &HttpSettings,
+    version: ApiVersion,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    use crate::ntp::NtpSyncedClock;
+    use crate::warnings::{Warning, Warnings};
+
+    let ntp_clock = server.state().ntp_clock();
+    if NtpSyncedClock::is_container_environment() {
+        let result = json!({
+            "available": false,
+            "message": "NTP not available in container environment",
+            "container_mode": true
+        });
+        let mut warnings = Warnings::new();
+        warnings.push(Warning::ntp_container_assumed());
+        let validity = server.ntp_validity();
+        let cache_control = validity.cache_control();
+        let body = warnings.attach(validity.attach(result));
+        version
+            .json(200, "OK", Shape::Stable, &body, pretty)
+            .with_header("Cache-Control", cache_control)
+    } else {
+        match ntp_clock.get_status_async().await {
+            Ok(status) => {
+                crate::ntp::correction::global().record(&status, std::time::Instant::now());
+                settings.clock_guard.observe_ntp(status.synced);
+                let mut result = json!(status);
+                result["available"] = json!(true);
+                let validity = server.ntp_validity();
+                let cache_control = validity.cache_control();
+                version
+                    .json(200, "OK", Shape::Stable, &validity.attach(result), pretty)
+                    .with_header("Cache-Control", cache_control)
+            }
+            Err(e) => error_response(&e, json!({"available": false}), pretty),
+        }
+    }
+}
+
+#[cfg(not(feature = "ntp"))]
+async fn ntp_status_response(
+    _server: &TimeServer,
+    _settings: &HttpSettings,
+    _version: ApiVersion,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    let error = TimeServerError::NotCompiledIn {
+        feature: "ntp".to_string(),
+    };
+    error_response(&error, json!({"available": false}), pretty)
+}
+
+fn audit_event(
+    request: &HttpRequest,
+    response: &HttpResponse,
+    settings: &HttpSettings,
+    started: std::time::Instant,
+) -> AuditEvent {
+    let arguments = (!request.query.is_empty()).then(|| {
+        request
+            .query
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    });
+    let key_name = presented_key(request).and_then(|key| settings.api_keys.key_name(key));
+
+    AuditEvent::new(
+        "http",
+        format!("{} {}", request.method.as_str(), request.path),
+        started,
+    )
+    .with_arguments(arguments)
+    .with_status(response.status)
+    .with_api_key(key_name)
+}
+
+/// A known API key was presented
+fn is_authorized(request: &HttpRequest, settings: &HttpSettings) -> bool {
+    presented_key(request).is_some_and(|key| settings.api_keys.validate(key))
+}
+
+/// The API key sent as `X-API-Key` or a bearer token
+fn presented_key(request: &HttpRequest) -> Option<&str> {
+    request.header("x-api-key").or_else(|| {
+        request
+            .header("authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+    })
+}
+
+/// `{error, code, message, details}` with the status for `error`; keys in
+/// `extra` are added at the top level
+fn error_response(
+    error: &TimeServerError,
+    extra: serde_json::Value,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    let (status, reason) = error.http_status();
+    let mut body = error.to_json();
+    body["error"] = json!(error.to_string());
+    if let (Some(body), serde_json::Value::Object(extra)) = (body.as_object_mut(), extra) {
+        body.extend(extra);
+    }
+    HttpResponse::json_as(status, reason, &body, pretty)
+}
+
+fn method_not_allowed(request: &HttpRequest, allowed: &'static str) -> HttpResponse {
+    let error = json!({
+        "error": "Method Not Allowed",
+        "method": request.method.as_str(),
+        "path": request.path,
+    });
+    HttpResponse::json_as(405, "Method Not Allowed", &error, pretty_param(request))
+        .with_header("Allow", allowed)
+}
+
+fn not_found(path: &str, pretty: Option<bool>) -> HttpResponse {
+    let endpoints: Vec<&str> = ROUTES
+        .iter()
+        .copied()
+        .chain(["/api/time/timezone/:tz", "/api/timezone/:tz/timeline"])
+        .collect();
+    let error = json!({
+        "error": "Not Found",
+        "path": path,
+        "available_endpoints": endpoints,
+    });
+    HttpResponse::json_as(404, "Not Found", &error, pretty)
+}
+
+/// The current time in `name`, resolved as [`TimezoneConverter::resolve_name`] does
+fn zone_reading(
+    name: &str,
+    validity: &ValidityConfig,
+) -> Result<ZoneReading<EnhancedTimeResponse>, TimeServerError> {
+    let now = chaos::now();
+    let tz = TimezoneConverter::resolve_name(name)
+        .ok_or_else(|| TimeServerError::invalid_timezone(name))?;
+    let time = EnhancedTimeResponse::at_timezone(now, &tz)?;
+    validity.zone_reading(time, &tz, now)
+}
+
+/// Handle `/api/time/timezone/{tz}` where `raw_tz` is the still-encoded remainder of the path
+fn timezone_response(
+    raw_tz: &str,
+    validity: &ValidityConfig,
+    version: ApiVersion,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    let decoded = match percent_decode(raw_tz) {
+        Some(decoded) => decoded,
+        None => {
+            let error = TimeServerError::InvalidArgument(format!(
+                "Malformed timezone path segment: {}",
+                raw_tz
+            ));
+            let extra = json!({
+                "timezone": raw_tz,
+                "hint": "Percent-encode the IANA name, e.g. America%2FNew_York",
+            });
+            return error_response(&error, extra, pretty);
+        }
+    };
+
+    if decoded.trim().trim_matches('/').is_empty() {
+        let error = TimeServerError::InvalidArgument("Missing timezone".to_string());
+        let extra = json!({
+            "timezone": decoded,
+            "hint": "Use /api/time/timezone/{IANA name}, e.g. /api/time/timezone/Europe/London",
+        });
+        return error_response(&error, extra, pretty);
+    }
+
+    match zone_reading(&decoded, validity) {
+        Ok(response) => version.json(200, "OK", Shape::TimeReading, &response, pretty),
+        Err(error) => {
+            let suggestions = match &error {
+                TimeServerError::InvalidTimezone { suggestions, .. } => suggestions.clone(),
+                _ => Vec::new(),
+            };
+            let hint = if suggestions.is_empty() {
+                "See /api/timezones for the list of valid IANA names".to_string()
+            } else {
+                format!("Did you mean {}?", suggestions.join(", "))
+            };
+            let extra = json!({
+                "timezone": decoded,
+                "hint": hint,
+                "suggestions": suggestions,
+            });
+            error_response(&error, extra, pretty)
+        }
+    }
+}
+
+/// Handle `/api/timezone/{tz}/timeline?start=&end=`
+/// `?lat=&lon=` for `/api/timezone/locate`
+fn locate_query(
+    request: &HttpRequest,
+) -> Result<crate::time::locate::LocatedTimezone, TimeServerError> {
+    let degrees = |name: &str| -> Result<f64, TimeServerError> {
+        request
+            .query_param(name)
+            .ok_or_else(|| {
+                TimeServerError::InvalidArgument(format!("'{}' query parameter is required", name))
+            })?
+            .parse()
+            .map_err(|_| {
+                TimeServerError::InvalidArgument(format!("'{}' must be a number of degrees", name))
+            })
+    };
+    crate::time::locate::locate(degrees("lat")?, degrees("lon")?, chaos::now())
+}
+
+fn timeline_response(
+    raw_tz: &str,
+    request: &HttpRequest,
+    version: ApiVersion,
+    pretty: Option<bool>,
+) -> HttpResponse {
+    let timestamp = |name: &str| -> Result<i64, TimeServerError> {
+        request
+            .query_param(name)
+            .ok_or_else(|| {
+                TimeServerError::InvalidArgument(format!("'{}' query parameter is required", name))
+            })?
+            .parse()
+            .map_err(|_| {
+                TimeServerError::InvalidArgument(format!("'{}' must be a Unix timestamp", name))
+            })
+    };
+    let zone = percent_decode(raw_tz).unwrap_or_else(|| raw_tz.to_string());
+    let format = match request.query_param("format") {
+        None => Ok(OutputFormat::Json),
+        Some(format) => OutputFormat::parse(format).ok_or_else(|| {
+            TimeServerError::InvalidArgument("'format' must be json, csv or ics".to_string())
+        }),
+    };
+
+    let timeline = format.and_then(|format| {
+        let tz = TimezoneConverter::resolve_name(&zone)
+            .ok_or_else(|| TimeServerError::invalid_timezone(zone.as_str()))?;
+        let (start, end) = (timestamp("start")?, timestamp("end")?);
+        let segments = TimezoneConverter::offset_timeline(&tz, start, end)?;
+        Ok((format, tz, start, end, segments))
+    });
+
+    match timeline {
+        Ok((OutputFormat::Json, tz, start, end, segments)) => {
+            let body = json!({
+                "timezone": tz,
+                "start": start,
+                "end": end,
+                "count": segments.len(),
+                "segments": segments,
+            });
+            version.json(200, "OK", Shape::Stable, &body, pretty)
+        }
+        Ok((OutputFormat::Csv, _, _, _, segments)) => HttpResponse::text(
+            200,
+            "OK",
+            &export::to_csv(&segments),
+            OutputFormat::Csv.content_type(),
+        ),
+        Ok((OutputFormat::Ics, tz, _, _, segments)) => {
+            match export::to_ics(&segments, &tz, chaos::now()) {
+                Ok(ics) => HttpResponse::text(200, "OK", &ics, OutputFormat::Ics.content_type()),
+                Err(error) => error_response(&error, json!({}), pretty),
+            }
+        }
+        Err(error) => error_response(&error, json!({}), pretty),
+    }
+}
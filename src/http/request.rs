@@ -0,0 +1,291 @@
+// Minimal HTTP/1.1 request parsing for the hand-rolled API server
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Largest request, headers and body together, the server reads
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Request method; anything we never route is kept verbatim for 405 responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Options,
+    Post,
+    Put,
+    Other(String),
+}
+
+impl Method {
+    /// Methods are case-sensitive (RFC 9110 §9.1)
+    pub fn parse(token: &str) -> Self {
+        match token {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "OPTIONS" => Method::Options,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            other => Method::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Other(other) => other,
+        }
+    }
+}
+
+/// A parsed HTTP request line, headers and body
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: Method,
+    /// Request path with the query string and fragment removed (still percent-encoded)
+    pub path: String,
+    /// Decoded query parameters in request order
+    pub query: Vec<(String, String)>,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+    /// Everything after the blank line; empty for most requests
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// Parse the request line and headers of a raw HTTP request
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut lines = raw.lines();
+        let mut parts = lines.next()?.split_whitespace();
+        let method = Method::parse(parts.next()?);
+        let target = parts.next()?;
+
+        let target = target.split('#').next().unwrap_or("");
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, Vec::new()),
+        };
+
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let body = raw
+            .split_once("\r\n\r\n")
+            .or_else(|| raw.split_once("\n\n"))
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+
+        Some(Self {
+            method,
+            path: path.to_string(),
+            query,
+            headers,
+            body,
+        })
+    }
+
+    /// First value of a query parameter
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// First value of a header (case-insensitive name)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Why `read_request` gave up
+#[derive(Debug)]
+pub enum ReadError {
+    /// The peer closed before sending anything
+    Empty,
+    /// Over `MAX_REQUEST_BYTES`, or a Content-Length that would be
+    TooLarge,
+    Io(std::io::Error),
+}
+
+/// Read one request: up to the end of the headers, then as much body as
+/// Content-Length announces
+pub async fn read_request<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, ReadError> {
+    let mut buf = Vec::with_capacity(8192);
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut chunk).await.map_err(ReadError::Io)?;
+        if n == 0 {
+            return if buf.is_empty() {
+                Err(ReadError::Empty)
+            } else {
+                Ok(buf)
+            };
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.len() > MAX_REQUEST_BYTES {
+            return Err(ReadError::TooLarge);
+        }
+        if let Some(header_end) = header_end(&buf) {
+            let head = String::from_utf8_lossy(&buf[..header_end]);
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            if header_end.saturating_add(length) > MAX_REQUEST_BYTES {
+                return Err(ReadError::TooLarge);
+            }
+            if buf.len() >= header_end + length {
+                return Ok(buf);
+            }
+        }
+    }
+}
+
+/// Offset just past the blank line ending the headers
+fn header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| i + 4)
+        .or_else(|| buf.windows(2).position(|w| w == b"\n\n").map(|i| i + 2))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode =
+                |s: &str| percent_decode(&s.replace('+', " ")).unwrap_or_else(|| s.to_string());
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes; returns None for malformed escapes or invalid UTF-8
+pub fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            let hex = std::str::from_utf8(hex).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line_and_query() {
+        let raw = "GET /api/time/timezone/Asia/Tokyo?pretty=false&x=a%20b HTTP/1.1\r\n\
+                   Host: localhost\r\n\
+                   Accept-Encoding: gzip\r\n\r\n";
+        let request = HttpRequest::parse(raw).unwrap();
+
+        assert_eq!(request.method, Method::Get);
+        assert_eq!(request.path, "/api/time/timezone/Asia/Tokyo");
+        assert_eq!(request.query_param("pretty"), Some("false"));
+        assert_eq!(request.query_param("x"), Some("a b"));
+        assert_eq!(request.header("accept-encoding"), Some("gzip"));
+        assert_eq!(request.header("Host"), Some("localhost"));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("America%2FNew_York").as_deref(),
+            Some("America/New_York")
+        );
+        assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+        assert_eq!(percent_decode("bad%2"), None);
+        assert_eq!(percent_decode("bad%zz"), None);
+        assert_eq!(percent_decode("%ff%fe"), None);
+    }
+
+    #[test]
+    fn test_method_parsing() {
+        let head = HttpRequest::parse("HEAD /api/time HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(head.method, Method::Head);
+
+        let post = HttpRequest::parse("POST /api/batch HTTP/1.1\r\n\r\n{}").unwrap();
+        assert_eq!(post.method, Method::Post);
+        assert_eq!(post.body, "{}");
+
+        let put = HttpRequest::parse("PUT /admin/loglevel HTTP/1.1\r\n\r\ndebug").unwrap();
+        assert_eq!(put.method, Method::Put);
+        assert_eq!(put.body, "debug");
+
+        let delete = HttpRequest::parse("DELETE /api/time HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(delete.method, Method::Other("DELETE".to_string()));
+        assert_eq!(delete.method.as_str(), "DELETE");
+
+        // Methods are case-sensitive
+        assert_eq!(Method::parse("get"), Method::Other("get".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_read_request_waits_for_the_body() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            client
+                .write_all(b"POST /api/batch HTTP/1.1\r\nContent-Length: 11\r\n\r\n{\"oper")
+                .await
+                .unwrap();
+            tokio::task::yield_now().await;
+            client.write_all(b"ations\"}").await.unwrap();
+            client
+        });
+        let raw = read_request(&mut server).await.unwrap();
+        let request = HttpRequest::parse(&String::from_utf8(raw).unwrap()).unwrap();
+        assert_eq!(request.body, "{\"operations\"}");
+        drop(writer.await.unwrap());
+
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        tokio::io::AsyncWriteExt::write_all(
+            &mut client,
+            format!(
+                "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_REQUEST_BYTES
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            read_request(&mut server).await,
+            Err(ReadError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_malformed_request_line() {
+        assert!(HttpRequest::parse("").is_none());
+        assert!(HttpRequest::parse("GET\r\n\r\n").is_none());
+    }
+}
@@ -0,0 +1,282 @@
+// HTTP response building, serialization and content encoding
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::Write;
+
+/// Default minimum body size (bytes) before compression is considered
+pub const DEFAULT_COMPRESSION_MIN_BYTES: usize = 1024;
+
+/// An HTTP response before it is written to the socket
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, status_text: &'static str) -> Self {
+        Self {
+            status,
+            status_text,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// JSON response in the server's default style (`JSON_PRETTY`)
+    pub fn json(status: u16, status_text: &'static str, body: &impl serde::Serialize) -> Self {
+        Self::json_as(status, status_text, body, None)
+    }
+
+    /// JSON response, pretty-printed unless `pretty` or the server default says otherwise
+    pub fn json_as(
+        status: u16,
+        status_text: &'static str,
+        body: &impl serde::Serialize,
+        pretty: Option<bool>,
+    ) -> Self {
+        let json = crate::output::to_json(body, pretty).unwrap_or_else(|_| "{}".to_string());
+        Self::new(status, status_text)
+            .with_header("Content-Type", "application/json")
+            .with_body(json.into_bytes())
+    }
+
+    pub fn text(status: u16, status_text: &'static str, body: &str, content_type: &str) -> Self {
+        Self::new(status, status_text)
+            .with_header("Content-Type", content_type)
+            .with_body(body.as_bytes().to_vec())
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Compress the body if the client accepts an encoding we support and the
+    /// body is at least `min_bytes` long
+    pub fn negotiate_encoding(mut self, accept_encoding: Option<&str>, min_bytes: usize) -> Self {
+        if self.body.is_empty() {
+            return self;
+        }
+        // The representation depends on Accept-Encoding whether or not we compress
+        self = self.with_header("Vary", "Accept-Encoding");
+
+        if self.body.len() < min_bytes || self.header("Content-Encoding").is_some() {
+            return self;
+        }
+
+        let encoding = match accept_encoding.and_then(preferred_encoding) {
+            Some(encoding) => encoding,
+            None => return self,
+        };
+
+        let compressed = match encoding {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&self.body).and_then(|_| encoder.finish())
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&self.body).and_then(|_| encoder.finish())
+            }
+        };
+
+        match compressed {
+            Ok(body) => {
+                self.body = body;
+                self.with_header("Content-Encoding", encoding.as_str())
+            }
+            Err(_) => self,
+        }
+    }
+
+    /// Drop the body for a HEAD response, keeping the Content-Length the
+    /// equivalent GET would have sent
+    pub fn without_body(mut self) -> Self {
+        if self.header("Transfer-Encoding").is_some() {
+            return self;
+        }
+        let length = self.body.len();
+        self.body.clear();
+        self.with_header("Content-Length", length.to_string())
+    }
+
+    /// Serialize status line, headers and body for the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut response = self.clone();
+        // A chunked body carries its own framing
+        if self.header("Content-Length").is_none() && self.header("Transfer-Encoding").is_none() {
+            response = response.with_header("Content-Length", self.body.len().to_string());
+        }
+        let mut bytes = response.head_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// Status line and headers only, for a body written separately (chunked)
+    pub fn head_bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, self.status_text);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        head.into_bytes()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Pick the best supported encoding from an Accept-Encoding header,
+/// honoring q-values (q=0 means "not acceptable") and preferring gzip on ties
+pub fn preferred_encoding(accept_encoding: &str) -> Option<ContentEncoding> {
+    let mut best: Option<(f32, ContentEncoding)> = None;
+
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        let candidates: &[ContentEncoding] = match name.as_str() {
+            "gzip" | "x-gzip" => &[ContentEncoding::Gzip],
+            "deflate" => &[ContentEncoding::Deflate],
+            "*" => &[ContentEncoding::Gzip],
+            _ => &[],
+        };
+
+        for &encoding in candidates {
+            if q <= 0.0 {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((best_q, best_enc)) => {
+                    q > best_q
+                        || (q == best_q
+                            && best_enc != ContentEncoding::Gzip
+                            && encoding == ContentEncoding::Gzip)
+                }
+            };
+            if better {
+                best = Some((q, encoding));
+            }
+        }
+    }
+
+    best.map(|(_, encoding)| encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use std::io::Read;
+
+    #[test]
+    fn test_preferred_encoding() {
+        assert_eq!(preferred_encoding("gzip"), Some(ContentEncoding::Gzip));
+        assert_eq!(
+            preferred_encoding("deflate"),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(
+            preferred_encoding("deflate, gzip"),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            preferred_encoding("gzip;q=0.5, deflate"),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(preferred_encoding("gzip;q=0"), None);
+        assert_eq!(preferred_encoding("br, identity"), None);
+        assert_eq!(preferred_encoding("*"), Some(ContentEncoding::Gzip));
+    }
+
+    #[test]
+    fn test_negotiate_encoding_round_trip() {
+        let body = json_body(4096);
+        let response = HttpResponse::json(200, "OK", &body);
+        let original = response.body.clone();
+
+        let gzipped = response.clone().negotiate_encoding(Some("gzip"), 1024);
+        assert_eq!(gzipped.header("Content-Encoding"), Some("gzip"));
+        assert_eq!(gzipped.header("Vary"), Some("Accept-Encoding"));
+        let mut decoded = Vec::new();
+        GzDecoder::new(&gzipped.body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, original);
+
+        let deflated = response.negotiate_encoding(Some("deflate"), 1024);
+        assert_eq!(deflated.header("Content-Encoding"), Some("deflate"));
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(&deflated.body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_small_or_unrequested_bodies_stay_identity() {
+        let small =
+            HttpResponse::json(200, "OK", &json_body(1)).negotiate_encoding(Some("gzip"), 1024);
+        assert_eq!(small.header("Content-Encoding"), None);
+
+        let large = HttpResponse::json(200, "OK", &json_body(4096)).negotiate_encoding(None, 1024);
+        assert_eq!(large.header("Content-Encoding"), None);
+        assert_eq!(large.header("Vary"), Some("Accept-Encoding"));
+    }
+
+    #[test]
+    fn test_to_bytes_sets_content_length() {
+        let response = HttpResponse::text(200, "OK", "hello", "text/plain");
+        let wire = String::from_utf8(response.to_bytes()).unwrap();
+        assert!(wire.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(wire.contains("Content-Length: 5\r\n"));
+        assert!(wire.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn test_without_body_keeps_content_length() {
+        let response = HttpResponse::text(200, "OK", "hello", "text/plain").without_body();
+        let wire = String::from_utf8(response.to_bytes()).unwrap();
+        assert!(wire.contains("Content-Length: 5\r\n"));
+        assert_eq!(wire.matches("Content-Length").count(), 1);
+        assert!(wire.ends_with("\r\n\r\n"));
+    }
+
+    fn json_body(entries: usize) -> serde_json::Value {
+        let zones: Vec<String> = (0..entries).map(|i| format!("Region/City_{}", i)).collect();
+        serde_json::json!({ "timezones": zones })
+    }
+}
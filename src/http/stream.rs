@@ -0,0 +1,279 @@
+// NDJSON time sample stream: GET /api/time/stream
+//
+// Writes one compact JSON object per tick as a chunk of a chunked response,
+// flushing after each, until `count` samples are sent or the client goes
+// away. The socket is read while waiting for the next tick, so a closed
+// connection ends the stream at once instead of at the next failed write.
+
+use super::request::{HttpRequest, Method};
+use super::response::HttpResponse;
+use crate::error::TimeServerError;
+use crate::time::{SourcedTime, TimeSource};
+use chrono::SecondsFormat;
+use serde::Serialize;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::MissedTickBehavior;
+use tracing::debug;
+
+pub const STREAM_ROUTE: &str = "/api/time/stream";
+
+pub const DEFAULT_STREAM_INTERVAL_MS: u64 = 1000;
+pub const MIN_STREAM_INTERVAL_MS: u64 = 50;
+pub const MAX_STREAM_INTERVAL_MS: u64 = 60_000;
+pub const DEFAULT_STREAM_COUNT: u64 = 60;
+pub const MAX_STREAM_COUNT: u64 = 10_000;
+
+/// Streams currently being written
+static ACTIVE_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn active_streams() -> usize {
+    ACTIVE_STREAMS.load(Ordering::SeqCst)
+}
+
+/// Counts a stream as active for as long as it is held
+struct ActiveStream;
+
+impl ActiveStream {
+    fn enter() -> Self {
+        ACTIVE_STREAMS.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        ACTIVE_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// `interval_ms` clamped to 50 ms..60 s, `count` capped at 10000
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamParams {
+    pub interval: Duration,
+    pub count: u64,
+}
+
+impl Default for StreamParams {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(DEFAULT_STREAM_INTERVAL_MS),
+            count: DEFAULT_STREAM_COUNT,
+        }
+    }
+}
+
+impl StreamParams {
+    /// Read `interval_ms` and `count`; the error names the bad parameter
+    pub fn from_request(request: &HttpRequest) -> Result<Self, TimeServerError> {
+        let number = |name: &str, default: u64| -> Result<u64, TimeServerError> {
+            request.query_param(name).map_or(Ok(default), |v| {
+                v.parse().map_err(|_| {
+                    TimeServerError::InvalidArgument(format!(
+                        "'{}' must be a non-negative integer",
+                        name
+                    ))
+                })
+            })
+        };
+
+        let interval_ms = number("interval_ms", DEFAULT_STREAM_INTERVAL_MS)?
+            .clamp(MIN_STREAM_INTERVAL_MS, MAX_STREAM_INTERVAL_MS);
+        Ok(Self {
+            interval: Duration::from_millis(interval_ms),
+            count: number("count", DEFAULT_STREAM_COUNT)?.min(MAX_STREAM_COUNT),
+        })
+    }
+}
+
+/// The request, if it is a GET of the stream route with valid parameters.
+/// Anything else, including invalid parameters, goes through the router.
+pub fn streaming_request(raw: &str) -> Option<(HttpRequest, StreamParams)> {
+    let request = HttpRequest::parse(raw)?;
+    if request.method != Method::Get || request.path != STREAM_ROUTE {
+        return None;
+    }
+    let params = StreamParams::from_request(&request).ok()?;
+    Some((request, params))
+}
+
+/// Status line and headers of a stream; also the HEAD response
+pub fn stream_head() -> HttpResponse {
+    HttpResponse::new(200, "OK")
+        .with_header("Content-Type", "application/x-ndjson")
+        .with_header("Transfer-Encoding", "chunked")
+        .with_header("Cache-Control", "no-store")
+}
+
+/// One line of the stream
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeSample {
+    pub seq: u64,
+    pub unix_seconds: i64,
+    /// Nanoseconds within the second
+    pub nanos: u32,
+    pub rfc3339: String,
+    pub source: TimeSource,
+}
+
+impl TimeSample {
+    pub fn new(seq: u64, sourced: &SourcedTime) -> Self {
+        Self {
+            seq,
+            unix_seconds: sourced.time.seconds,
+            nanos: sourced.time.nanos,
+            rfc3339: sourced
+                .to_datetime()
+                .to_rfc3339_opts(SecondsFormat::Nanos, true),
+            source: sourced.source,
+        }
+    }
+}
+
+/// Write `head` and then up to `params.count` samples from `clock`, one per
+/// interval. Returns the number of samples sent; stops early, without an
+/// error, when the peer closes its side of the connection.
+pub async fn serve<S, F>(
+    socket: S,
+    head: HttpResponse,
+    params: StreamParams,
+    mut clock: F,
+) -> io::Result<u64>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: FnMut() -> Result<SourcedTime, TimeServerError>,
+{
+    let _active = ActiveStream::enter();
+    let (mut reader, mut writer) = tokio::io::split(socket);
+    writer.write_all(&head.head_bytes()).await?;
+    writer.flush().await?;
+
+    let mut ticks = tokio::time::interval(params.interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut probe = [0u8; 256];
+
+    for seq in 0..params.count {
+        // Anything the client sends after the request is ignored; EOF or an
+        // error means it has gone
+        loop {
+            tokio::select! {
+                read = reader.read(&mut probe) => match read {
+                    Ok(0) | Err(_) => {
+                        debug!(event = "http.stream.disconnect", sent = seq);
+                        return Ok(seq);
+                    }
+                    Ok(_) => {}
+                },
+                _ = ticks.tick() => break,
+            }
+        }
+
+        let line = match clock() {
+            Ok(sourced) => serde_json::to_string(&TimeSample::new(seq, &sourced)),
+            Err(error) => {
+                let mut body = error.to_json();
+                body["seq"] = seq.into();
+                body["error"] = error.to_string().into();
+                serde_json::to_string(&body)
+            }
+        }
+        .map_err(io::Error::other)?;
+
+        let chunk = format!("{:x}\r\n{}\n\r\n", line.len() + 1, line);
+        writer.write_all(chunk.as_bytes()).await?;
+        writer.flush().await?;
+    }
+
+    writer.write_all(b"0\r\n\r\n").await?;
+    writer.flush().await?;
+    Ok(params.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{TimeQuality, UnixTime};
+
+    fn request(query: &str) -> HttpRequest {
+        HttpRequest::parse(&format!("GET {}?{} HTTP/1.1\r\n\r\n", STREAM_ROUTE, query)).unwrap()
+    }
+
+    fn system_clock() -> Result<SourcedTime, TimeServerError> {
+        Ok(SourcedTime {
+            time: UnixTime::now(),
+            source: TimeSource::System,
+            quality: TimeQuality::LIVE,
+        })
+    }
+
+    #[test]
+    fn test_params_are_clamped() {
+        let params = StreamParams::from_request(&request("interval_ms=1&count=999999")).unwrap();
+        assert_eq!(
+            params.interval,
+            Duration::from_millis(MIN_STREAM_INTERVAL_MS)
+        );
+        assert_eq!(params.count, MAX_STREAM_COUNT);
+
+        let params = StreamParams::from_request(&request("")).unwrap();
+        assert_eq!(params, StreamParams::default());
+
+        let error = StreamParams::from_request(&request("count=-1")).unwrap_err();
+        assert!(error.to_string().contains("'count'"));
+        assert!(streaming_request("GET /api/time/stream?interval_ms=x HTTP/1.1\r\n\r\n").is_none());
+        assert!(streaming_request("HEAD /api/time/stream HTTP/1.1\r\n\r\n").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_writes_chunked_ndjson() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let params = StreamParams {
+            interval: Duration::from_millis(MIN_STREAM_INTERVAL_MS),
+            count: 3,
+        };
+        let sent = tokio::spawn(serve(server, stream_head(), params, system_clock));
+
+        let mut wire = String::new();
+        let mut client = client;
+        client.read_to_string(&mut wire).await.unwrap();
+        assert_eq!(sent.await.unwrap().unwrap(), 3);
+
+        let (head, body) = wire.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("Transfer-Encoding: chunked"));
+        assert!(!head.contains("Content-Length"));
+        assert!(body.ends_with("0\r\n\r\n"));
+        let seqs: Vec<u64> = body
+            .split("\r\n")
+            .filter(|part| part.starts_with('{'))
+            .map(|line| {
+                let sample: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+                assert_eq!(sample["source"], "system");
+                sample["seq"].as_u64().unwrap()
+            })
+            .collect();
+        assert_eq!(seqs, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_stops_stream() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let params = StreamParams {
+            interval: Duration::from_millis(MIN_STREAM_INTERVAL_MS),
+            count: MAX_STREAM_COUNT,
+        };
+        let stream = tokio::spawn(serve(server, stream_head(), params, system_clock));
+
+        let mut buf = [0u8; 512];
+        assert!(client.read(&mut buf).await.unwrap() > 0);
+        drop(client);
+
+        let sent = tokio::time::timeout(Duration::from_secs(1), stream)
+            .await
+            .expect("stream kept running after disconnect")
+            .unwrap()
+            .unwrap();
+        assert!(sent < 5, "{}", sent);
+    }
+}
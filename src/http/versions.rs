@@ -0,0 +1,314 @@
+// Versions of the HTTP API's JSON shapes
+//
+// Every successful `/api/*` JSON body carries `api_version`. A client asks
+// for a version with `?v=2` or an `Accept-Version: 2` header (the query wins,
+// and `v2` works as well as `2`); without either it gets version 1. Version 1
+// is frozen: its body is exactly what the server sent before versioning, with
+// `api_version` appended as the last field. Version 2 may diverge: time
+// readings (`/api/time` and `/api/time/timezone/:tz`) drop the top-level
+// `seconds`, `milliseconds`, `microseconds` and `nanos_since_epoch` that
+// repeat `unix`, rename `offset` to `offset_seconds`, and replace the
+// Sunday-based `week_of_year` with `iso_week` and `iso_week_year`; and every
+// body writes nanosecond counts past 2^53 as strings, as the MCP tools do.
+// A version the server does not know is refused with 406 and the supported
+// list. Error bodies are the same in every version and the event stream is
+// not versioned.
+
+use super::request::HttpRequest;
+use super::response::HttpResponse;
+use crate::error::TimeServerError;
+use crate::output::{append_fields, to_json, with_nanos_as_numbers};
+use chrono::{DateTime, Datelike, Timelike};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Response header naming the version of the body
+pub const API_VERSION_HEADER: &str = "API-Version";
+
+/// A version of the JSON shapes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+/// How a body changes between versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// The same in every version
+    Stable,
+    /// An `EnhancedTimeResponse`, possibly with validity fields alongside
+    TimeReading,
+}
+
+impl ApiVersion {
+    /// Oldest first
+    pub const SUPPORTED: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "1",
+            ApiVersion::V2 => "2",
+        }
+    }
+
+    /// `1`, `v1`, `2` or `v2`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let number = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        Self::SUPPORTED.into_iter().find(|v| v.as_str() == number)
+    }
+
+    /// The version `request` asks for with `?v=` or `Accept-Version`
+    pub fn requested(request: &HttpRequest) -> Result<Self, TimeServerError> {
+        let Some(requested) = request
+            .query_param("v")
+            .or_else(|| request.header("accept-version"))
+        else {
+            return Ok(Self::default());
+        };
+        Self::parse(requested).ok_or_else(|| TimeServerError::UnsupportedApiVersion {
+            requested: requested.to_string(),
+            supported: Self::SUPPORTED
+                .iter()
+                .map(|v| v.as_str().to_string())
+                .collect(),
+        })
+    }
+
+    /// `body` in this version's shape, with `api_version`
+    pub fn render(
+        self,
+        shape: Shape,
+        body: &impl Serialize,
+        pretty: bool,
+    ) -> Result<String, serde_json::Error> {
+        let marker = json!({"api_version": self.as_str()});
+        match (self, shape) {
+            (ApiVersion::V2, Shape::TimeReading) => {
+                let mut value = serde_json::to_value(body)?;
+                if let Some(fields) = value.as_object_mut() {
+                    time_reading_v2(fields);
+                    fields.insert("api_version".into(), marker["api_version"].clone());
+                }
+                to_json(&value, Some(pretty))
+            }
+            // Serialized as before versioning, so the v1 bytes do not move;
+            // that includes nanosecond counts as numbers
+            (ApiVersion::V1, _) => with_nanos_as_numbers(|| {
+                append_fields(&to_json(body, Some(pretty))?, &marker, pretty)
+            }),
+            (ApiVersion::V2, Shape::Stable) => {
+                append_fields(&to_json(body, Some(pretty))?, &marker, pretty)
+            }
+        }
+    }
+
+    /// `body` in this version's shape without `api_version`, for a body
+    /// nested inside another response
+    pub fn to_value(self, shape: Shape, body: &impl Serialize) -> Result<Value, serde_json::Error> {
+        match (self, shape) {
+            (ApiVersion::V1, _) => with_nanos_as_numbers(|| serde_json::to_value(body)),
+            (ApiVersion::V2, Shape::TimeReading) => {
+                let mut value = serde_json::to_value(body)?;
+                if let Some(fields) = value.as_object_mut() {
+                    time_reading_v2(fields);
+                }
+                Ok(value)
+            }
+            (ApiVersion::V2, Shape::Stable) => serde_json::to_value(body),
+        }
+    }
+
+    /// A JSON response with `body` in this version's shape
+    pub fn json(
+        self,
+        status: u16,
+        status_text: &'static str,
+        shape: Shape,
+        body: &impl Serialize,
+        pretty: Option<bool>,
+    ) -> HttpResponse {
+        let pretty = pretty.unwrap_or_else(crate::output::pretty_default);
+        let json = self
+            .render(shape, body, pretty)
+            .unwrap_or_else(|_| "{}".to_string());
+        HttpResponse::new(status, status_text)
+            .with_header("Content-Type", "application/json")
+            .with_body(json.into_bytes())
+            .with_versioning(self)
+    }
+}
+
+impl HttpResponse {
+    /// Name the body's version and that it depends on the request's
+    pub fn with_versioning(self, version: ApiVersion) -> Self {
+        self.with_header(API_VERSION_HEADER, version.as_str())
+            .with_header("Vary", "Accept-Version")
+    }
+}
+
+/// The version 2 fields of a time reading
+fn time_reading_v2(fields: &mut Map<String, Value>) {
+    for repeated in [
+        "seconds",
+        "milliseconds",
+        "microseconds",
+        "nanos_since_epoch",
+    ] {
+        fields.remove(repeated);
+    }
+    if let Some(offset) = fields.remove("offset") {
+        fields.insert("offset_seconds".into(), offset);
+    }
+    fields.remove("week_of_year");
+
+    // Version 1 gives the UTC calendar fields even for a zoned reading;
+    // version 2 gives those of the local time in `rfc3339`
+    let Some(local) = fields
+        .get("rfc3339")
+        .and_then(Value::as_str)
+        .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+    else {
+        return;
+    };
+    let week = local.iso_week();
+    for (name, value) in [
+        ("year", json!(local.year())),
+        ("month", json!(local.month())),
+        ("day", json!(local.day())),
+        ("hour", json!(local.hour())),
+        ("minute", json!(local.minute())),
+        ("second", json!(local.second())),
+        ("weekday", json!(local.format("%A").to_string())),
+        ("day_of_year", json!(local.ordinal())),
+        ("iso_week", json!(week.week())),
+        ("iso_week_year", json!(week.year())),
+    ] {
+        fields.insert(name.into(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::utc::EnhancedTimeResponse;
+    use crate::time::{TimezoneConverter, UnixTime};
+    use chrono::Utc;
+
+    /// A Monday: week 52 by the Sunday-based count, ISO week 1 of 2025
+    fn instant() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-12-30T23:30:15.123456789Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn request(text: &str) -> HttpRequest {
+        HttpRequest::parse(text).unwrap()
+    }
+
+    /// `body` in version 1 is byte for byte the recorded pretty body, and
+    /// the same value compact
+    fn assert_recorded(golden: &str, body: &impl Serialize, shape: Shape) {
+        let pretty = ApiVersion::V1.render(shape, body, true).unwrap();
+        assert_eq!(pretty, golden.trim_end());
+        let compact = ApiVersion::V1.render(shape, body, false).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&compact).unwrap(),
+            serde_json::from_str::<Value>(golden).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_v1_matches_the_recorded_bodies() {
+        assert_recorded(
+            include_str!("../../tests/golden/api_v1/time.json"),
+            &EnhancedTimeResponse::at(instant()),
+            Shape::TimeReading,
+        );
+        let tokyo = TimezoneConverter::resolve_name("Asia/Tokyo").unwrap();
+        assert_recorded(
+            include_str!("../../tests/golden/api_v1/time_timezone.json"),
+            &EnhancedTimeResponse::at_timezone(instant(), &tokyo).unwrap(),
+            Shape::TimeReading,
+        );
+        assert_recorded(
+            include_str!("../../tests/golden/api_v1/unix.json"),
+            &UnixTime::from_datetime(instant()),
+            Shape::Stable,
+        );
+    }
+
+    #[test]
+    fn test_v2_time_readings_use_local_fields() {
+        let rendered = ApiVersion::V2
+            .render(
+                Shape::TimeReading,
+                &EnhancedTimeResponse::at(instant()),
+                true,
+            )
+            .unwrap();
+        assert_eq!(
+            rendered,
+            include_str!("../../tests/golden/api_v2/time.json").trim_end()
+        );
+
+        let tokyo = TimezoneConverter::resolve_name("Asia/Tokyo").unwrap();
+        let zoned = EnhancedTimeResponse::at_timezone(instant(), &tokyo).unwrap();
+        let rendered = ApiVersion::V2
+            .render(Shape::TimeReading, &zoned, true)
+            .unwrap();
+        assert_eq!(
+            rendered,
+            include_str!("../../tests/golden/api_v2/time_timezone.json").trim_end()
+        );
+        let value: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!((&value["day"], &value["hour"]), (&json!(31), &json!(8)));
+        assert_eq!(
+            (&value["iso_week"], &value["iso_week_year"]),
+            (&json!(1), &json!(2025))
+        );
+        assert!(value.get("week_of_year").is_none());
+
+        // Stable bodies only gain the marker
+        let unix = UnixTime::from_datetime(instant());
+        let v2 = ApiVersion::V2.render(Shape::Stable, &unix, false).unwrap();
+        assert!(v2.ends_with(r#","api_version":"2"}"#));
+    }
+
+    #[test]
+    fn test_requested_version() {
+        let version = |text| ApiVersion::requested(&request(text));
+        assert_eq!(
+            version("GET /api/time HTTP/1.1\r\n\r\n").unwrap(),
+            ApiVersion::V1
+        );
+        assert_eq!(
+            version("GET /api/time?v=v2 HTTP/1.1\r\n\r\n").unwrap(),
+            ApiVersion::V2
+        );
+        assert_eq!(
+            version("GET /api/time HTTP/1.1\r\nAccept-Version: 2\r\n\r\n").unwrap(),
+            ApiVersion::V2
+        );
+        // The query wins over the header
+        assert_eq!(
+            version("GET /api/time?v=1 HTTP/1.1\r\nAccept-Version: 2\r\n\r\n").unwrap(),
+            ApiVersion::V1
+        );
+    }
+
+    #[test]
+    fn test_unknown_version_is_not_acceptable() {
+        let error = ApiVersion::requested(&request(
+            "GET /api/time HTTP/1.1\r\nAccept-Version: 3\r\n\r\n",
+        ))
+        .unwrap_err();
+        assert_eq!(error.http_status(), (406, "Not Acceptable"));
+        assert_eq!(
+            error.details(),
+            json!({"requested": "3", "supported": ["1", "2"]})
+        );
+    }
+}
@@ -0,0 +1,261 @@
+// Build and runtime information for `get_server_info` and `/api/info`
+//
+// Build metadata comes from build.rs. Runtime modes are registered by the
+// components as they start (HTTP listener, stdio transport) or read from
+// their own state (GPS/PPS monitors, audit logger), not re-derived from env.
+
+use crate::mcp::policy::ToolPolicy;
+use crate::time::leap::{self, LeapTableInfo};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// What the HTTP API server started with
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HttpApiInfo {
+    pub port: u16,
+    pub auth_enabled: bool,
+    pub api_key_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub build_timestamp: Option<String>,
+    pub profile: &'static str,
+    pub target: &'static str,
+    /// Cargo features compiled in
+    pub features: Vec<&'static str>,
+    /// IANA time zone database release bundled with chrono-tz
+    pub tzdb_version: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeModes {
+    pub container_mode: bool,
+    pub mcp_stdio: bool,
+    /// `None` when the HTTP API is not running in this process
+    pub http_api: Option<HttpApiInfo>,
+    /// `host_clock` in containers, otherwise `ntpd`; `none` without the ntp feature
+    pub ntp_backend: &'static str,
+    pub pps_monitor: bool,
+    pub gps_refclock: bool,
+    pub audit_log: bool,
+    pub corrected_time_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostInfo {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub family: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfoReport {
+    pub name: &'static str,
+    pub build: BuildInfo,
+    pub modes: RuntimeModes,
+    pub host: HostInfo,
+    /// Tools switched off by server policy
+    pub disabled_tools: ToolPolicy,
+    pub started_at: String,
+    pub uptime_seconds: f64,
+}
+
+/// Reported by `get_data_versions`: how current the bundled time data is
+#[derive(Debug, Clone, Serialize)]
+pub struct DataVersions {
+    /// IANA tz database release compiled into chrono-tz
+    pub tzdata_version: &'static str,
+    /// Timezone results further ahead than this carry `tzdata_caveat`
+    pub tzdata_caveat_days: u32,
+    pub leap_seconds: LeapTableInfo,
+    pub crate_version: &'static str,
+    pub build_timestamp: Option<String>,
+}
+
+static STARTED: OnceLock<(DateTime<Utc>, Instant)> = OnceLock::new();
+static HTTP_API: OnceLock<HttpApiInfo> = OnceLock::new();
+static MCP_STDIO: AtomicBool = AtomicBool::new(false);
+
+/// Fix the process start time; later calls keep the first value
+pub fn mark_started() {
+    STARTED.get_or_init(|| (Utc::now(), Instant::now()));
+}
+
+/// Called by the HTTP API server once it is listening
+pub fn record_http_api(info: HttpApiInfo) {
+    let _ = HTTP_API.set(info);
+}
+
+/// Called when the MCP stdio transport starts
+pub fn record_mcp_stdio() {
+    MCP_STDIO.store(true, Ordering::Relaxed);
+}
+
+/// When this binary was built (`SOURCE_DATE_EPOCH` for reproducible builds)
+pub fn build_time() -> Option<DateTime<Utc>> {
+    env!("BUILD_TIMESTAMP")
+        .parse()
+        .ok()
+        .filter(|&secs: &i64| secs > 0)
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+}
+
+pub fn build_info() -> BuildInfo {
+    let features = [
+        ("http-api", cfg!(feature = "http-api")),
+        ("ntp", cfg!(feature = "ntp")),
+        ("prompts", cfg!(feature = "prompts")),
+        ("timezones-full", cfg!(feature = "timezones-full")),
+        ("timezones-minimal", cfg!(feature = "timezones-minimal")),
+        ("auth", cfg!(feature = "auth")),
+        ("geo", cfg!(feature = "geo")),
+        ("sse-auth", cfg!(feature = "sse-auth")),
+    ]
+    .into_iter()
+    .filter_map(|(name, on)| on.then_some(name))
+    .collect();
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("BUILD_GIT_COMMIT"),
+        build_timestamp: build_time().map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        profile: env!("BUILD_PROFILE"),
+        target: env!("BUILD_TARGET"),
+        features,
+        tzdb_version: chrono_tz::IANA_TZDB_VERSION,
+    }
+}
+
+pub fn data_versions(tzdata_caveat_days: u32) -> DataVersions {
+    let build = build_info();
+    DataVersions {
+        tzdata_version: build.tzdb_version,
+        tzdata_caveat_days,
+        leap_seconds: leap::table_info(Utc::now()),
+        crate_version: build.version,
+        build_timestamp: build.build_timestamp,
+    }
+}
+
+#[cfg(feature = "ntp")]
+pub fn runtime_modes() -> RuntimeModes {
+    let container_mode = crate::ntp::NtpSyncedClock::is_container_environment();
+    RuntimeModes {
+        container_mode,
+        mcp_stdio: MCP_STDIO.load(Ordering::Relaxed),
+        http_api: HTTP_API.get().cloned(),
+        ntp_backend: if container_mode { "host_clock" } else { "ntpd" },
+        pps_monitor: crate::ntp::pps::global().is_some(),
+        gps_refclock: crate::ntp::gps::global().is_some(),
+        audit_log: crate::audit::global().is_some(),
+        corrected_time_default: crate::ntp::correction::enabled_by_default(),
+    }
+}
+
+#[cfg(not(feature = "ntp"))]
+pub fn runtime_modes() -> RuntimeModes {
+    RuntimeModes {
+        container_mode: false,
+        mcp_stdio: MCP_STDIO.load(Ordering::Relaxed),
+        http_api: HTTP_API.get().cloned(),
+        ntp_backend: "none",
+        pps_monitor: false,
+        gps_refclock: false,
+        audit_log: crate::audit::global().is_some(),
+        corrected_time_default: false,
+    }
+}
+
+/// When the process started and how long ago
+pub fn uptime() -> (DateTime<Utc>, Duration) {
+    mark_started();
+    let (started_at, started) = STARTED
+        .get()
+        .copied()
+        .unwrap_or((Utc::now(), Instant::now()));
+    (started_at, started.elapsed())
+}
+
+pub fn collect(disabled_tools: &ToolPolicy) -> ServerInfoReport {
+    let (started_at, uptime) = uptime();
+
+    ServerInfoReport {
+        name: env!("CARGO_PKG_NAME"),
+        build: build_info(),
+        modes: runtime_modes(),
+        host: HostInfo {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            family: std::env::consts::FAMILY,
+        },
+        disabled_tools: disabled_tools.clone(),
+        started_at: started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        uptime_seconds: uptime.as_secs_f64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "ntp")]
+    use serial_test::serial;
+
+    #[test]
+    fn test_version_matches_package() {
+        let info = collect(&ToolPolicy::disabling(["get_ntp_peers"]));
+        assert_eq!(info.build.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.build.tzdb_version.len(), 5);
+        assert!(!info.build.git_commit.is_empty());
+        assert!(info.build.build_timestamp.is_some());
+        assert_eq!(info.host.os, std::env::consts::OS);
+        assert_eq!(info.disabled_tools.disabled().count(), 1);
+    }
+
+    #[test]
+    fn test_data_versions() {
+        let versions = data_versions(30);
+        assert_eq!(versions.tzdata_version, chrono_tz::IANA_TZDB_VERSION);
+        assert_eq!(versions.tzdata_caveat_days, 30);
+        assert_eq!(versions.leap_seconds.last_entry, "2017-01-01");
+        assert_eq!(versions.crate_version, env!("CARGO_PKG_VERSION"));
+        assert!(versions.build_timestamp.is_some());
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    #[serial]
+    fn test_container_mode_follows_environment() {
+        std::env::remove_var("CONTAINER_APP_NAME");
+        let before = runtime_modes();
+
+        std::env::set_var("CONTAINER_APP_NAME", "info-test");
+        let inside = runtime_modes();
+        std::env::remove_var("CONTAINER_APP_NAME");
+
+        assert!(inside.container_mode);
+        assert_eq!(inside.ntp_backend, "host_clock");
+        // Other container markers may be present where the tests run
+        if !before.container_mode {
+            assert_eq!(before.ntp_backend, "ntpd");
+        }
+    }
+
+    #[test]
+    fn test_registered_components_are_reported() {
+        record_mcp_stdio();
+        record_http_api(HttpApiInfo {
+            port: 3000,
+            auth_enabled: false,
+            api_key_count: 0,
+        });
+        let modes = runtime_modes();
+        assert!(modes.mcp_stdio);
+        assert_eq!(modes.http_api.map(|h| h.port), Some(3000));
+    }
+}
@@ -1,12 +1,51 @@
 // MCP UTC Time Server Library
 
+#[cfg(all(feature = "ntp", feature = "http-api"))]
+pub mod alert;
+pub mod attestation;
+pub mod audit;
+#[cfg(feature = "http-api")]
 pub mod auth;
+pub mod bench;
+pub mod canonical;
+pub mod chaos;
+pub mod completion;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod guard;
+pub mod health;
+pub mod heartbeat;
+#[cfg(feature = "http-api")]
+pub mod http;
+pub mod info;
+pub mod maintenance;
+pub mod mcp;
+#[cfg(feature = "ntp")]
 pub mod ntp;
+pub mod output;
+pub mod persist;
+pub mod preferences;
+#[cfg(feature = "ntp")]
+pub mod ptp;
+pub mod sanity;
+pub mod scheduler;
+pub mod server;
 pub mod server_sdk;
+pub mod startup;
+pub mod state;
+pub mod stats;
 pub mod time;
+pub mod tsa;
+pub mod validity;
+pub mod warnings;
 
 // Re-export commonly used types
+#[cfg(feature = "http-api")]
 pub use auth::{ApiKey, ApiKeyValidator};
+pub use config::ServerConfig;
+#[cfg(feature = "ntp")]
 pub use ntp::{NtpConfig, NtpStatus, NtpSyncedClock};
 pub use time::utc::EnhancedTimeResponse;
 pub use time::UnixTime;
@@ -1,17 +1,27 @@
 // MCP UTC Time Server - Main entry point
 
 use anyhow::Result;
-use std::env;
+use mcp_utc_time_server::ServerConfig;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize structured logging to stderr without ANSI colors
+    mcp_utc_time_server::info::mark_started();
+
+    // Every setting is validated here; a bad value stops the server before it starts
+    let config = ServerConfig::load()?;
+    if config.print_config || std::env::args().any(|arg| arg == "--print-config") {
+        println!("{}", config.to_json());
+        return Ok(());
+    }
+
+    // Initialize structured logging to stderr without ANSI colors; the
+    // filter sits in a reload layer so PUT /admin/loglevel can replace it
+    let (log_filter, log_filter_handle) = tracing_subscriber::reload::Layer::new(
+        tracing_subscriber::EnvFilter::new(&config.logging.filter),
+    );
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "mcp_utc_time_server=info,rmcp=warn".into()),
-        )
+        .with(log_filter)
         .with(
             tracing_subscriber::fmt::layer()
                 .with_writer(std::io::stderr)
@@ -19,36 +29,215 @@ async fn main() -> Result<()> {
                 .with_target(false) // Disable target module names
                 .compact(), // Use compact format
         )
+        // Recent errors for get_process_stats
+        .with(mcp_utc_time_server::stats::ErrorCapture::global())
         .init();
 
-    // Check if we should run HTTP API server alongside MCP server
-    let enable_http_api = env::var("ENABLE_HTTP_API")
-        .or_else(|_| env::var("ENABLE_HEALTH_SERVER")) // Backward compatibility
-        .unwrap_or_else(|_| "true".into())
-        .parse::<bool>()
-        .unwrap_or(true);
+    // Test-only clock anomalies; CHAOS_MODE was refused above without ALLOW_CHAOS
+    mcp_utc_time_server::chaos::init(config.chaos.as_ref());
+
+    // Time tools refuse to answer while the system clock predates the build
+    mcp_utc_time_server::guard::init(&config.clock_guard);
+
+    // Audit logging is opt-in; a configured but unwritable file is fatal
+    mcp_utc_time_server::audit::init(config.logging.audit.as_ref())?;
+
+    // Built-in holiday calendars plus HOLIDAY_CALENDAR_FILE; a malformed file is fatal
+    mcp_utc_time_server::time::holidays::init(config.holidays.file.as_deref())?;
+
+    // NTP, PPS, GPS and PTP sources; none are compiled into builds without the ntp feature
+    #[cfg(feature = "ntp")]
+    init_ntp(&config);
+
+    // NTP health transitions POSTed to ALERT_WEBHOOK_URL from their own task
+    #[cfg(all(feature = "ntp", feature = "http-api"))]
+    mcp_utc_time_server::alert::init(config.alert.as_ref());
+
+    // Wall-clock steps are published as clock.step events (MCP logging notifications)
+    mcp_utc_time_server::time::step::spawn_watch(
+        mcp_utc_time_server::time::step::CLOCK_STEP_WATCH_INTERVAL,
+    );
+
+    // Signing tools report an unreadable key file; the rest of the server runs without it
+    if let Err(e) = mcp_utc_time_server::attestation::init(&config.signing) {
+        tracing::warn!("Signed time disabled: {}", e);
+    }
+
+    // Shared state, including the time source priority; after GPS so its reader is found
+    mcp_utc_time_server::state::ServerState::init(&config);
+
+    // NTP status history for get_ntp_history; a container has no ntpd to
+    // sample unless the server runs one
+    #[cfg(feature = "ntp")]
+    if !config.mode.container || config.ntp.supervisor.is_some() {
+        mcp_utc_time_server::ntp::history::spawn_sampler(
+            mcp_utc_time_server::state::ServerState::shared().shared_ntp_clock(),
+        );
+    }
+
+    // Counters and history from the previous run, saved again periodically
+    mcp_utc_time_server::persist::init(config.state.as_ref());
+    mcp_utc_time_server::persist::spawn_saver();
+
+    // Opt-in SNTP responder; a configured port that cannot be bound is fatal
+    #[cfg(feature = "ntp")]
+    let sntp = mcp_utc_time_server::ntp::sntp::init_from_env(
+        mcp_utc_time_server::state::ServerState::shared(),
+    )
+    .await?;
+
+    #[cfg(feature = "http-api")]
+    let result = run_with_http(&config, log_filter_handle).await;
+    #[cfg(not(feature = "http-api"))]
+    let result = {
+        // Without the HTTP API nothing changes the log filter
+        drop(log_filter_handle);
+        // MCP stdio server only; this build has no HTTP API
+        mcp_utc_time_server::startup::StartupReport::collect(&config, None)
+            .log(config.logging.startup_banner);
+        tokio::select! {
+            result = mcp_utc_time_server::server_sdk::run(&config) => result,
+            _ = terminated() => Ok(()),
+        }
+    };
+
+    // Requests are no longer accepted; the managed ntpd goes last
+    #[cfg(feature = "ntp")]
+    if let Some(sntp) = sntp {
+        sntp.shutdown().await;
+    }
+    #[cfg(feature = "ntp")]
+    mcp_utc_time_server::ntp::supervisor::shutdown().await;
+
+    mcp_utc_time_server::persist::save_now();
+
+    if let Some(audit) = mcp_utc_time_server::audit::global() {
+        audit.flush().await;
+    }
+
+    result
+}
+
+/// Start the time sources that read ntpd, PPS, GPS and PTP
+#[cfg(feature = "ntp")]
+fn init_ntp(config: &ServerConfig) {
+    // ntpq runs behind a shared semaphore sized from NTP_QUERY_CONCURRENCY
+    mcp_utc_time_server::ntp::query::init(&config.ntp);
+
+    // PPS monitoring reports the device as unavailable rather than failing
+    mcp_utc_time_server::ntp::pps::init(&config.ntp);
+
+    // GPS refclock is optional hardware; run without it if the SHM unit is unavailable
+    if let Err(e) = mcp_utc_time_server::ntp::gps::init(&config.ntp) {
+        tracing::warn!("GPS disabled: {}", e);
+    }
+
+    // PTP status for the ptp time source and health grading; absent linuxptp is not an error
+    mcp_utc_time_server::ptp::init(&config.ntp, &config.time_source.priority);
+
+    // ntpd as a child process (MANAGE_NTPD=true), restarted when it exits
+    mcp_utc_time_server::ntp::supervisor::init(config.ntp.supervisor.as_ref());
+}
 
-    // Check if we're running in container mode (HTTP API only, no stdio)
-    let container_mode = env::var("CONTAINER_APP_NAME").is_ok()
-        || env::var("KUBERNETES_SERVICE_HOST").is_ok()
-        || env::var("HTTP_API_ONLY").is_ok();
+/// Resolves on SIGTERM, so the listeners and the managed ntpd are stopped in
+/// order rather than the process dying mid-request
+#[cfg(unix)]
+async fn terminated() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+            tracing::info!(event = "server.stop", "SIGTERM received; shutting down");
+        }
+        Err(e) => {
+            tracing::warn!("SIGTERM handler not installed: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
 
-    if container_mode {
+/// Without SIGTERM, Ctrl-C (or Ctrl-Break) is the polite way to stop
+#[cfg(not(unix))]
+async fn terminated() {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => tracing::info!(event = "server.stop", "Ctrl-C received; shutting down"),
+        Err(e) => {
+            tracing::warn!("Ctrl-C handler not installed: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// The HTTP API alone (container mode) or next to the MCP stdio server
+#[cfg(feature = "http-api")]
+async fn run_with_http(
+    config: &ServerConfig,
+    log_filter: mcp_utc_time_server::http::admin::LogFilterHandle,
+) -> Result<()> {
+    let mut http = None;
+    let result = if config.mode.http_only {
         // Container mode: run ONLY the HTTP API server (no stdin available for MCP stdio)
         tracing::info!("Running in container mode - HTTP API server only");
-        mcp_utc_time_server::server_sdk::run_http_api_server().await
-    } else if enable_http_api {
-        // Local mode: run both HTTP API server and MCP stdio server
-        tokio::spawn(async {
-            if let Err(e) = mcp_utc_time_server::server_sdk::run_http_api_server().await {
-                eprintln!("HTTP API server error: {}", e);
+        let settings = http_settings(config, log_filter);
+        match mcp_utc_time_server::http::start_http_api(config, settings).await {
+            Ok(server) => {
+                report_startup(config, Some(&server));
+                terminated().await;
+                server.shutdown().await;
+                Ok(())
             }
-        });
+            Err(e) => Err(e),
+        }
+    } else if config.http.enabled {
+        // Local mode: run both HTTP API server and MCP stdio server
+        let settings = http_settings(config, log_filter);
+        match mcp_utc_time_server::http::start_http_api(config, settings).await {
+            Ok(server) => http = Some(server),
+            Err(e) => eprintln!("HTTP API server error: {}", e),
+        }
+        report_startup(config, http.as_ref());
 
         // Run the MCP server with official SDK (STDIO transport)
-        mcp_utc_time_server::server_sdk::run().await
+        tokio::select! {
+            result = mcp_utc_time_server::server_sdk::run(config) => result,
+            _ = terminated() => Ok(()),
+        }
     } else {
         // MCP stdio server only
-        mcp_utc_time_server::server_sdk::run().await
+        report_startup(config, None);
+        tokio::select! {
+            result = mcp_utc_time_server::server_sdk::run(config) => result,
+            _ = terminated() => Ok(()),
+        }
+    };
+
+    if let Some(http) = http {
+        http.shutdown().await;
     }
+
+    result
+}
+
+/// HTTP settings from `config` whose log filter the admin routes can change;
+/// SIGHUP reloads their API keys
+#[cfg(feature = "http-api")]
+fn http_settings(
+    config: &ServerConfig,
+    log_filter: mcp_utc_time_server::http::admin::LogFilterHandle,
+) -> mcp_utc_time_server::http::HttpSettings {
+    let settings =
+        mcp_utc_time_server::http::HttpSettings::from_config(config).with_log_filter(log_filter);
+    #[cfg(unix)]
+    mcp_utc_time_server::http::admin::reload_keys_on_sighup(settings.api_keys.clone());
+    settings
+}
+
+/// One `server.startup` event saying what actually came up
+#[cfg(feature = "http-api")]
+fn report_startup(
+    config: &ServerConfig,
+    http: Option<&mcp_utc_time_server::http::listener::HttpServer>,
+) {
+    mcp_utc_time_server::startup::StartupReport::collect(config, http)
+        .log(config.logging.startup_banner);
 }
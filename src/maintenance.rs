@@ -0,0 +1,246 @@
+// Declared maintenance windows of the server itself
+//
+// Operators restart hosts on a schedule; `MAINTENANCE_WINDOW` tells clients
+// when, as a cron expression followed by a duration, e.g. `0 2 * * SUN 15m`
+// for Sundays 02:00-02:15 UTC. The schedule is listed by the
+// get_maintenance_window tool, `/health` carries a `maintenance` field while
+// a window is open or starts within ten minutes, and tool results get a
+// MAINTENANCE_ACTIVE warning during one. The server keeps serving either way.
+// With the variable unset none of this appears in any output.
+
+use crate::time::cron::CronSchedule;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// How far ahead `/health` announces the next window
+pub const UPCOMING_NOTICE_SECS: i64 = 600;
+
+/// Longest window accepted
+const MAX_WINDOW_SECS: i64 = 7 * 86400;
+
+/// A recurring window: starts on `schedule`, lasts `duration`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    schedule: CronSchedule,
+    duration: Duration,
+}
+
+/// One occurrence of a window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceInterval {
+    /// RFC 3339, UTC
+    pub start: String,
+    pub end: String,
+}
+
+/// A window that is open now or about to be
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceNotice {
+    pub active: bool,
+    pub start: String,
+    pub end: String,
+    /// Seconds until `start`, for an upcoming window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starts_in_seconds: Option<i64>,
+}
+
+impl MaintenanceWindow {
+    pub fn schedule(&self) -> &CronSchedule {
+        &self.schedule
+    }
+
+    pub fn duration_seconds(&self) -> i64 {
+        self.duration.num_seconds()
+    }
+
+    /// Start and end of the window open at `now`, if any
+    pub fn active_at(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let start = self.schedule.next_after(now - self.duration)?;
+        (start <= now).then(|| (start, start + self.duration))
+    }
+
+    /// The window open at `now` followed by the next ones, `count` in all
+    pub fn occurrences(&self, now: DateTime<Utc>, count: usize) -> Vec<MaintenanceInterval> {
+        let mut start = self
+            .active_at(now)
+            .map(|(start, _)| start)
+            .or_else(|| self.schedule.next_after(now));
+        let mut intervals = Vec::with_capacity(count);
+        while let Some(at) = start.filter(|_| intervals.len() < count) {
+            intervals.push(self.interval(at));
+            start = self.schedule.next_after(at);
+        }
+        intervals
+    }
+
+    /// What `/health` reports at `now`: the open window, or one starting
+    /// within [`UPCOMING_NOTICE_SECS`]
+    pub fn notice(&self, now: DateTime<Utc>) -> Option<MaintenanceNotice> {
+        if let Some((start, _)) = self.active_at(now) {
+            let MaintenanceInterval { start, end } = self.interval(start);
+            return Some(MaintenanceNotice {
+                active: true,
+                start,
+                end,
+                starts_in_seconds: None,
+            });
+        }
+        let start = self.schedule.next_after(now)?;
+        let starts_in = (start - now).num_seconds();
+        (starts_in <= UPCOMING_NOTICE_SECS).then(|| {
+            let MaintenanceInterval { start, end } = self.interval(start);
+            MaintenanceNotice {
+                active: false,
+                start,
+                end,
+                starts_in_seconds: Some(starts_in),
+            }
+        })
+    }
+
+    fn interval(&self, start: DateTime<Utc>) -> MaintenanceInterval {
+        MaintenanceInterval {
+            start: start.to_rfc3339_opts(SecondsFormat::Secs, true),
+            end: (start + self.duration).to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+/// `90s`, `15m`, `2h`, `1d` or a sum such as `1h30m`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = 0i64;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        let value: i64 = digits.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        digits.clear();
+    }
+    (digits.is_empty() && total > 0 && total <= MAX_WINDOW_SECS).then(|| Duration::seconds(total))
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let (cron, duration) = text
+            .rsplit_once(char::is_whitespace)
+            .ok_or_else(|| "expected a cron expression followed by a duration".to_string())?;
+        let duration = parse_duration(duration).ok_or_else(|| {
+            format!(
+                "invalid duration '{}' (e.g. 15m, 1h30m; at most 7d)",
+                duration
+            )
+        })?;
+        let schedule: CronSchedule = cron
+            .parse()
+            .map_err(|e: crate::time::cron::CronError| e.0)?;
+        Ok(Self { schedule, duration })
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.duration.num_seconds();
+        let (value, unit) = match seconds {
+            s if s % 86400 == 0 => (s / 86400, "d"),
+            s if s % 3600 == 0 => (s / 3600, "h"),
+            s if s % 60 == 0 => (s / 60, "m"),
+            s => (s, "s"),
+        };
+        write!(f, "{} {}{}", self.schedule, value, unit)
+    }
+}
+
+impl Serialize for MaintenanceWindow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn sunday_window() -> MaintenanceWindow {
+        "0 2 * * SUN 15m".parse().unwrap()
+    }
+
+    #[test]
+    fn test_occurrences_cross_a_month_boundary() {
+        let window = sunday_window();
+        let starts: Vec<String> = window
+            .occurrences(at("2025-01-20T00:00:00Z"), 3)
+            .into_iter()
+            .map(|i| format!("{} {}", i.start, i.end))
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                "2025-01-26T02:00:00Z 2025-01-26T02:15:00Z",
+                "2025-02-02T02:00:00Z 2025-02-02T02:15:00Z",
+                "2025-02-09T02:00:00Z 2025-02-09T02:15:00Z",
+            ]
+        );
+        // The window open now comes first
+        let open = window.occurrences(at("2025-01-26T02:10:00Z"), 1);
+        assert_eq!(open[0].start, "2025-01-26T02:00:00Z");
+    }
+
+    #[test]
+    fn test_notice_inside_near_and_outside_the_window() {
+        let window = sunday_window();
+
+        let inside = window.notice(at("2025-01-26T02:05:00Z")).unwrap();
+        assert!(inside.active);
+        assert_eq!(inside.end, "2025-01-26T02:15:00Z");
+        assert!(inside.starts_in_seconds.is_none());
+
+        let near = window.notice(at("2025-01-26T01:52:00Z")).unwrap();
+        assert!(!near.active);
+        assert_eq!(near.starts_in_seconds, Some(480));
+
+        // The end is exclusive
+        assert!(window.notice(at("2025-01-26T02:15:00Z")).is_none());
+        assert!(window.notice(at("2025-01-26T01:49:59Z")).is_none());
+        assert!(window.active_at(at("2025-01-26T01:59:59Z")).is_none());
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(sunday_window().to_string(), "0 2 * * SUN 15m");
+        assert_eq!(sunday_window().duration_seconds(), 900);
+        let window: MaintenanceWindow = "@daily 1h30m".parse().unwrap();
+        assert_eq!(window.duration_seconds(), 5400);
+        assert_eq!(window.to_string(), "@daily 90m");
+
+        for bad in [
+            "0 2 * * SUN",
+            "0 2 * * SUN 0m",
+            "0 2 * * SUN 8d",
+            "0 2 * * 15m",
+            "15m",
+        ] {
+            assert!(bad.parse::<MaintenanceWindow>().is_err(), "{}", bad);
+        }
+    }
+}
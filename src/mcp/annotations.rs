@@ -0,0 +1,189 @@
+// Tool annotations: the hints clients use to decide which calls to confirm
+//
+// Every tool has an entry in `TOOL_HINTS`. The rmcp server attaches them to
+// tools/list as `annotations` (readOnlyHint, destructiveHint, idempotentHint,
+// openWorldHint) and the legacy handler reads the same table, so the two
+// transports cannot disagree. Almost every tool only reads the clock, tz data
+// or ntpd: read-only and idempotent. The scheduling, heartbeat and session
+// tools change this session's state, and get_signed_time advances its
+// sequence file. `open_world` marks the tools that reach past this host (a
+// TSA, an HTTPS Date header). Listing a tool without an entry fails a debug
+// assertion instead of going out with the spec's permissive defaults.
+
+use rmcp::model::ToolAnnotations;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolHints {
+    pub read_only: bool,
+    /// Meaningful only when not read-only: false for tools that only add
+    pub destructive: bool,
+    /// Meaningful only when not read-only: calling again with the same
+    /// arguments changes nothing further
+    pub idempotent: bool,
+    pub open_world: bool,
+}
+
+impl ToolHints {
+    pub fn annotations(self) -> ToolAnnotations {
+        ToolAnnotations::new()
+            .read_only(self.read_only)
+            .destructive(self.destructive)
+            .idempotent(self.idempotent)
+            .open_world(self.open_world)
+    }
+}
+
+/// Reads and changes nothing outside this host
+const READ: ToolHints = ToolHints {
+    read_only: true,
+    destructive: false,
+    idempotent: true,
+    open_world: false,
+};
+
+/// Reads from a host other than this one
+const READ_REMOTE: ToolHints = ToolHints {
+    open_world: true,
+    ..READ
+};
+
+/// Adds session state on every call
+const ADDS: ToolHints = ToolHints {
+    read_only: false,
+    destructive: false,
+    idempotent: false,
+    open_world: false,
+};
+
+/// Sets session state to what the arguments say
+const SETS: ToolHints = ToolHints {
+    idempotent: true,
+    ..ADDS
+};
+
+/// Removes session state
+const REMOVES: ToolHints = ToolHints {
+    destructive: true,
+    ..SETS
+};
+
+pub const TOOL_HINTS: &[(&str, ToolHints)] = &[
+    ("get_time", READ),
+    ("get_unix_time", READ),
+    ("get_nanos", READ),
+    ("get_time_formatted", READ),
+    ("explain_format", READ),
+    ("build_format", READ),
+    ("convert_epoch", READ),
+    ("get_time_with_timezone", READ),
+    ("list_timezones", READ),
+    ("get_timezones_if_changed", READ),
+    ("abbreviation_lookup", READ),
+    ("convert_time", READ),
+    ("convert_calendar", READ),
+    ("business_time", READ),
+    ("get_week_number", READ),
+    ("parse_time", READ),
+    ("parse_natural_time", READ),
+    ("email_date", READ),
+    ("timestamp_card", READ),
+    ("format_duration", READ),
+    ("compare_times", READ),
+    ("timezone_for_location", READ),
+    ("get_host_timezone", READ),
+    ("world_clock", READ),
+    ("get_dst_transitions", READ),
+    ("get_offset_timeline", READ),
+    ("offset_difference", READ),
+    ("local_to_utc", READ),
+    ("find_overlap", READ),
+    ("assert_time_window", READ),
+    ("schedule_notification", ADDS),
+    ("list_scheduled", READ),
+    ("cancel_scheduled", REMOVES),
+    ("subscribe_heartbeat", SETS),
+    ("unsubscribe_heartbeat", REMOVES),
+    ("set_preferences", SETS),
+    ("get_preferences", READ),
+    ("clear_preferences", REMOVES),
+    ("get_server_info", READ),
+    ("get_data_versions", READ),
+    ("get_clock_resolution", READ),
+    ("get_examples", READ),
+    // SANITY_HTTP_CHECK compares against an HTTPS Date header
+    ("check_time_sanity", READ_REMOTE),
+    ("get_maintenance_window", READ),
+    ("get_chaos_status", READ),
+    ("run_selftest_benchmark", READ),
+    ("get_process_stats", READ),
+    // Every call takes the next sequence number
+    ("get_signed_time", ADDS),
+    ("get_signing_key", READ),
+    ("verify_signed_time", READ),
+    ("get_trusted_timestamp", READ_REMOTE),
+    ("get_ntp_status", READ),
+    ("get_ntp_peers", READ),
+    ("get_ntp_history", READ),
+    ("get_pps_status", READ),
+    ("get_ptp_status", READ),
+    ("get_gps_status", READ),
+];
+
+/// `tool`'s entry in `TOOL_HINTS`
+pub fn hints(tool: &str) -> Option<ToolHints> {
+    TOOL_HINTS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map(|&(_, hints)| hints)
+}
+
+/// The annotations tools/list carries for `tool`
+pub fn annotations(tool: &str) -> Option<ToolAnnotations> {
+    let hints = hints(tool);
+    debug_assert!(hints.is_some(), "tool {} has no entry in TOOL_HINTS", tool);
+    hints.map(ToolHints::annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server_sdk::TimeServer;
+
+    #[test]
+    fn test_every_tool_has_hints() {
+        let tools = TimeServer::tool_names();
+        for tool in &tools {
+            assert!(hints(tool).is_some(), "no hints for {}", tool);
+        }
+        if cfg!(feature = "ntp") {
+            for (name, _) in TOOL_HINTS {
+                assert!(tools.iter().any(|tool| tool == name), "{} is no tool", name);
+            }
+        }
+        let mut names: Vec<_> = TOOL_HINTS.iter().map(|(name, _)| name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), TOOL_HINTS.len());
+    }
+
+    #[test]
+    fn test_annotations_follow_the_spec() {
+        let value = serde_json::to_value(annotations("cancel_scheduled")).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "readOnlyHint": false,
+                "destructiveHint": true,
+                "idempotentHint": true,
+                "openWorldHint": false,
+            })
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "tool get_moon_phase has no entry in TOOL_HINTS")]
+    fn test_tool_without_hints_fails_assertion() {
+        annotations("get_moon_phase");
+    }
+}
@@ -0,0 +1,116 @@
+// Opt-in `content_hash` on tool results, for clients that poll and dedup
+//
+// Every tool takes `hash: true`. Its result then carries `content_hash`: the
+// lowercase hex SHA-256 of the result's canonical JSON (see `canonical`),
+// leaving out the hash itself and the tool's fields in `VOLATILE_FIELDS`.
+// Those are the sub-second readings and expiry times that differ on every
+// call, so get_time with precision=seconds hashes the same for a whole second.
+// The argument is taken off before the tool runs, and tools/list adds it to
+// every input schema.
+
+use crate::canonical::{self, CONTENT_HASH_FIELD};
+use crate::error::TimeServerError;
+use serde_json::{json, Map, Value};
+
+/// Argument that asks for the hash
+pub const HASH_PARAM: &str = "hash";
+
+/// Fields of the current-time responses that change within a second
+const CURRENT_TIME: &[&str] = &[
+    "unix.nanos",
+    "unix.nanos_since_epoch",
+    "nanos_since_epoch",
+    "nanosecond",
+    "microseconds",
+    "milliseconds",
+    "reference_time",
+    "expires_at",
+];
+
+/// Dotted paths each tool's hash leaves out; other tools hash everything
+pub const VOLATILE_FIELDS: &[(&str, &[&str])] = &[
+    ("get_time", CURRENT_TIME),
+    ("get_time_with_timezone", CURRENT_TIME),
+    (
+        "get_unix_time",
+        &["nanos", "nanos_since_epoch", "expires_at"],
+    ),
+    ("get_nanos", &["nanoseconds", "subsec_nanos"]),
+];
+
+pub fn volatile_fields(tool: &str) -> &'static [&'static str] {
+    VOLATILE_FIELDS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map_or(&[], |&(_, fields)| fields)
+}
+
+/// Take `hash` out of `arguments`; whether it asked for the hash
+pub fn take_flag(arguments: Option<&mut Map<String, Value>>) -> Result<bool, TimeServerError> {
+    match arguments.and_then(|arguments| arguments.remove(HASH_PARAM)) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(hash)) => Ok(hash),
+        Some(other) => Err(TimeServerError::InvalidArgument(format!(
+            "{} must be true or false, not {}",
+            HASH_PARAM, other
+        ))),
+    }
+}
+
+/// Set `body`'s `content_hash` as `tool`'s result
+pub fn add(tool: &str, body: &mut Map<String, Value>) {
+    let hash = canonical::content_hash(&Value::Object(body.clone()), volatile_fields(tool));
+    body.insert(CONTENT_HASH_FIELD.into(), hash.into());
+}
+
+/// The `hash` property tools/list adds to each input schema
+pub fn property_schema() -> Value {
+    json!({
+        "type": "boolean",
+        "description": "Add content_hash: SHA-256 of the canonical (RFC 8785) result without its volatile fields, equal across polls with the same content"
+    })
+}
+
+/// `schema` (an object schema) with the `hash` property
+pub fn with_hash_property(schema: &mut Map<String, Value>) {
+    if let Value::Object(properties) = schema
+        .entry("properties")
+        .or_insert_with(|| Value::Object(Map::new()))
+    {
+        properties.insert(HASH_PARAM.into(), property_schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_is_taken_off() {
+        let mut arguments = json!({"timezone": "UTC", "hash": true});
+        let arguments = arguments.as_object_mut().unwrap();
+        assert!(take_flag(Some(arguments)).unwrap());
+        assert!(!arguments.contains_key("hash"));
+        assert!(!take_flag(Some(arguments)).unwrap());
+        assert!(!take_flag(None).unwrap());
+
+        let mut bad = json!({"hash": "yes"});
+        let error = take_flag(bad.as_object_mut()).unwrap_err();
+        assert_eq!(error.code(), "invalid_argument");
+    }
+
+    #[test]
+    fn test_hash_replaces_an_old_one() {
+        let mut body = json!({"seconds": 1, "nanosecond": 5})
+            .as_object()
+            .cloned()
+            .unwrap();
+        add("get_time", &mut body);
+        let first = body[CONTENT_HASH_FIELD].clone();
+        body.insert("nanosecond".into(), json!(6));
+        add("get_time", &mut body);
+        assert_eq!(body[CONTENT_HASH_FIELD], first);
+        add("get_week_number", &mut body);
+        assert_ne!(body[CONTENT_HASH_FIELD], first);
+    }
+}
@@ -0,0 +1,1851 @@
+// Example calls for every tool, served by get_examples
+//
+// Each entry is a real call captured from the running server: the arguments
+// sent and the response (or JSON-RPC error) that came back. The end-to-end
+// suite replays every example against a live server and fails when a
+// response no longer has the recorded shape, so the catalog cannot drift
+// from what the tools return. Values are only illustrative; shapes are
+// checked with [`shape_mismatch`].
+
+use crate::error::TimeServerError;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Where an example's response shape holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Host {
+    Any,
+    /// Captured in container mode; bare metal with ntpd answers differently
+    Container,
+    /// Needs a build with the `geo` feature; without it the tool fails
+    Geo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The tool's JSON output
+    Response(&'static str),
+    /// The JSON-RPC error object: code, message, data
+    Error(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolExample {
+    pub tool: &'static str,
+    pub summary: &'static str,
+    /// JSON object sent as the call's `arguments`
+    pub arguments: &'static str,
+    pub outcome: Outcome,
+    pub host: Host,
+}
+
+impl ToolExample {
+    pub fn arguments(&self) -> Value {
+        serde_json::from_str(self.arguments).expect("example arguments are JSON")
+    }
+
+    /// The recorded response or error object
+    pub fn expected(&self) -> Value {
+        let (Outcome::Response(text) | Outcome::Error(text)) = self.outcome;
+        serde_json::from_str(text).expect("example outcome is JSON")
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self.outcome, Outcome::Error(_))
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut entry = json!({
+            "tool": self.tool,
+            "summary": self.summary,
+            "arguments": self.arguments(),
+        });
+        let key = if self.is_error() { "error" } else { "response" };
+        entry[key] = self.expected();
+        if self.host != Host::Any {
+            entry["host"] = json!(self.host);
+        }
+        entry
+    }
+}
+
+/// Examples of `tool`, or all of them
+pub fn examples(tool: Option<&str>) -> Vec<&'static ToolExample> {
+    EXAMPLES
+        .iter()
+        .filter(|example| tool.is_none_or(|tool| example.tool == tool))
+        .collect()
+}
+
+/// The get_examples result
+pub fn catalog(tool: Option<&str>) -> Result<Value, TimeServerError> {
+    let examples = examples(tool);
+    if examples.is_empty() {
+        return Err(TimeServerError::InvalidArgument(format!(
+            "no examples for tool '{}'",
+            tool.unwrap_or_default()
+        )));
+    }
+    Ok(json!({
+        "count": examples.len(),
+        "examples": examples.iter().map(|e| e.to_json()).collect::<Vec<_>>(),
+    }))
+}
+
+/// Arguments of the successful examples of `tool`, for its input schema
+pub fn argument_examples(tool: &str) -> Vec<Value> {
+    examples(Some(tool))
+        .into_iter()
+        .filter(|example| !example.is_error())
+        .map(ToolExample::arguments)
+        .collect()
+}
+
+/// Where `actual` differs in shape from `expected`, as a JSON path.
+/// Objects must have the same keys and values the same JSON type; a null on
+/// either side, an empty object or an empty array in `expected` matches
+/// anything, and arrays are compared element by element as far as both go.
+pub fn shape_mismatch(expected: &Value, actual: &Value) -> Option<String> {
+    mismatch_at("$", expected, actual)
+}
+
+fn mismatch_at(path: &str, expected: &Value, actual: &Value) -> Option<String> {
+    match (expected, actual) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Object(expected), Value::Object(actual)) => {
+            if expected.is_empty() {
+                return None;
+            }
+            if let Some(key) = expected.keys().find(|key| !actual.contains_key(*key)) {
+                return Some(format!("{}.{} is missing", path, key));
+            }
+            if let Some(key) = actual.keys().find(|key| !expected.contains_key(*key)) {
+                return Some(format!("{}.{} is not in the example", path, key));
+            }
+            expected.iter().find_map(|(key, value)| {
+                mismatch_at(&format!("{}.{}", path, key), value, &actual[key])
+            })
+        }
+        (Value::Array(expected), Value::Array(actual)) => expected
+            .iter()
+            .zip(actual)
+            .enumerate()
+            .find_map(|(i, (e, a))| mismatch_at(&format!("{}[{}]", path, i), e, a)),
+        (Value::Bool(_), Value::Bool(_))
+        | (Value::Number(_), Value::Number(_))
+        | (Value::String(_), Value::String(_)) => None,
+        _ => Some(format!(
+            "{} is {} in the example but {}",
+            path,
+            type_name(expected),
+            type_name(actual)
+        )),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// In the order a client would try them; session tools come last because
+/// their preferences change later responses
+pub const EXAMPLES: &[ToolExample] = &[
+    ToolExample {
+        tool: "get_time",
+        summary: "Current time in a timezone, to the millisecond",
+        arguments: r#"{"precision": "millis", "timezone": "Australia/Melbourne"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "ctime": "Sat Oct 17 07:37:43 2026",
+            "custom_formats": {
+                "apache_log": "17/Oct/2026:07:37:43 +0000",
+                "syslog": "Oct 17 07:37:43",
+                "unix_date": "Sat Oct 17 07:37:43 UTC 2026",
+                "unix_timestamp": "1792222663"
+            },
+            "day": 17,
+            "day_of_year": 290,
+            "expires_at": "2026-10-17T07:37:44.157Z",
+            "hour": 7,
+            "iso8601": "2026-10-17T18:37:43.157+11:00",
+            "microseconds": 1792222663157628,
+            "milliseconds": 1792222663157,
+            "minute": 37,
+            "month": 10,
+            "nanos_since_epoch": "1792222663157628123",
+            "nanosecond": 157628123,
+            "offset": 39600,
+            "rfc2822": "Sat, 17 Oct 2026 18:37:43 +1100",
+            "rfc3339": "2026-10-17T18:37:43.157+11:00",
+            "rfc9557": "2026-10-17T18:37:43.157+11:00[Australia/Melbourne]",
+            "second": 43,
+            "seconds": 1792222663,
+            "source": "system",
+            "timezone": "Australia/Melbourne",
+            "unix": {
+                "nanos": 157628123,
+                "nanos_since_epoch": "1792222663157628123",
+                "seconds": 1792222663
+            },
+            "valid_for_ms": 1000,
+            "week_of_year": 41,
+            "weekday": "Saturday",
+            "year": 2026
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_unix_time",
+        summary: "Current Unix time with nanoseconds",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "expires_at": "2026-10-17T07:37:44.158Z",
+            "nanos": 158684004,
+            "nanos_since_epoch": "1792222663158684004",
+            "seconds": 1792222663,
+            "source": "system",
+            "valid_for_ms": 1000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_nanos",
+        summary: "Nanoseconds since the Unix epoch",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "nanoseconds": "1792222663159131824",
+            "seconds": 1792222663,
+            "subsec_nanos": 159131824
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_time_formatted",
+        summary: "Current time with a strftime format",
+        arguments: r#"{"format": "%Y-%m-%d %H:%M:%S %Z", "timezone": "Europe/London"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "format": "%Y-%m-%d %H:%M:%S %Z",
+            "formatted": "2026-10-17 08:37:43 BST",
+            "timezone": "Europe/London",
+            "unix_nanos": 159485624,
+            "unix_seconds": 1792222663
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_time_formatted",
+        summary: "Format a pinned instant instead of now, for calls that must agree",
+        arguments: r#"{"format": "%A %H:%M", "timezone": "Asia/Tokyo", "as_of": "2026-10-17T07:37:43Z"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "format": "%A %H:%M",
+            "formatted": "Saturday 16:37",
+            "reference_source": "client",
+            "reference_time": "2026-10-17T07:37:43Z",
+            "timezone": "Asia/Tokyo",
+            "unix_nanos": 0,
+            "unix_seconds": 1792222663
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_time_formatted",
+        summary: "Error: unknown directive %Q",
+        arguments: r#"{"format": "%Y-%m-%d %Q"}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32602,
+            "data": {
+                "code": "invalid_format",
+                "details": {
+                    "format": "%Y-%m-%d %Q",
+                    "offset": 9
+                },
+                "message": "Invalid format string '%Y-%m-%d %Q' at byte 9"
+            },
+            "message": "Invalid format string '%Y-%m-%d %Q' at byte 9"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "explain_format",
+        summary: "What each directive of a strftime format means",
+        arguments: r#"{"format": "%d/%m/%Y %H:%M"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "directives": [
+                {
+                    "directive": "%d",
+                    "example": "02",
+                    "meaning": "day of month (01-31)",
+                    "offset": 0
+                },
+                {
+                    "directive": "%m",
+                    "example": "01",
+                    "meaning": "month (01-12)",
+                    "offset": 3
+                },
+                {
+                    "directive": "%Y",
+                    "example": "2006",
+                    "meaning": "year, at least 4 digits (2006)",
+                    "offset": 6
+                },
+                {
+                    "directive": "%H",
+                    "example": "15",
+                    "meaning": "hour, 24-hour clock (00-23)",
+                    "offset": 9
+                },
+                {
+                    "directive": "%M",
+                    "example": "04",
+                    "meaning": "minute (00-59)",
+                    "offset": 12
+                }
+            ],
+            "format": "%d/%m/%Y %H:%M",
+            "reference_time": "2006-01-02T15:04:05.123456789Z",
+            "rendered_now": "17/10/2026 07:37",
+            "rendered_reference": "02/01/2006 15:04",
+            "unknown": []
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "build_format",
+        summary: "A strftime format from a description made of token names",
+        arguments: r#"{"description": "year-month-day hour:minute with timezone, 24h"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "example": "2006-01-02 15:04 UTC",
+            "format": "%Y-%m-%d %H:%M %Z",
+            "parts": [
+                {
+                    "directive": "%Y",
+                    "kind": "token",
+                    "token": "year"
+                },
+                {
+                    "kind": "literal",
+                    "text": "-"
+                },
+                {
+                    "directive": "%m",
+                    "kind": "token",
+                    "token": "month"
+                },
+                {
+                    "kind": "literal",
+                    "text": "-"
+                },
+                {
+                    "directive": "%d",
+                    "kind": "token",
+                    "token": "day"
+                },
+                {
+                    "kind": "literal",
+                    "text": " "
+                },
+                {
+                    "directive": "%H",
+                    "kind": "token",
+                    "token": "hour24"
+                },
+                {
+                    "kind": "literal",
+                    "text": ":"
+                },
+                {
+                    "directive": "%M",
+                    "kind": "token",
+                    "token": "minute"
+                },
+                {
+                    "kind": "literal",
+                    "text": " "
+                },
+                {
+                    "directive": "%Z",
+                    "kind": "token",
+                    "token": "tz_abbr"
+                }
+            ],
+            "reference_time": "2006-01-02T15:04:05.123456789Z",
+            "unknown": []
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "convert_epoch",
+        summary: "An NTP timestamp in every supported epoch",
+        arguments: r#"{"epoch": "ntp", "value": 3913056000}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "epochs": [
+                {
+                    "epoch": "unix",
+                    "exact": "1704067200",
+                    "leap_seconds": "ignored: every day is 86400 s and a leap second repeats the last one",
+                    "origin": "1970-01-01T00:00:00Z",
+                    "unit": "seconds",
+                    "value": 1704067200
+                },
+                {
+                    "epoch": "filetime",
+                    "exact": "133485408000000000",
+                    "leap_seconds": "ignored: converted like Unix time (Windows leap second support is off by default)",
+                    "origin": "1601-01-01T00:00:00Z",
+                    "unit": "100ns_ticks",
+                    "value": 133485408000000000
+                },
+                {
+                    "epoch": "dotnet_ticks",
+                    "exact": "638396640000000000",
+                    "leap_seconds": "ignored: DateTime has no leap seconds",
+                    "origin": "0001-01-01T00:00:00Z",
+                    "unit": "100ns_ticks",
+                    "value": 638396640000000000
+                },
+                {
+                    "epoch": "ntp",
+                    "exact": "3913056000",
+                    "leap_seconds": "ignored: NTP seconds repeat across a leap second like Unix time",
+                    "ntp_era": 0,
+                    "ntp_era_seconds": 3913056000,
+                    "origin": "1900-01-01T00:00:00Z",
+                    "unit": "seconds",
+                    "value": 3913056000
+                },
+                {
+                    "epoch": "excel",
+                    "exact": "45292",
+                    "leap_seconds": "ignored: every day is 86400 s; serials before 61 are off by one day because Excel treats 1900 as a leap year",
+                    "origin": "1899-12-30T00:00:00Z",
+                    "unit": "days",
+                    "value": 45292
+                },
+                {
+                    "epoch": "cocoa",
+                    "exact": "725760000",
+                    "leap_seconds": "ignored: converted like Unix time",
+                    "origin": "2001-01-01T00:00:00Z",
+                    "unit": "seconds",
+                    "value": 725760000
+                },
+                {
+                    "epoch": "gps",
+                    "exact": "1388102418",
+                    "leap_seconds": "counted: GPS time runs ahead of UTC by the leap seconds since 1980 (TAI - UTC - 19)",
+                    "origin": "1980-01-06T00:00:00Z",
+                    "unit": "seconds",
+                    "value": 1388102418
+                }
+            ],
+            "unix_nanos": "1704067200000000000",
+            "unix_timestamp": 1704067200,
+            "utc": "2024-01-01T00:00:00Z"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_time_with_timezone",
+        summary: "Current time in a timezone",
+        arguments: r#"{"timezone": "Asia/Tokyo"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "ctime": "Sat Oct 17 07:37:43 2026",
+            "custom_formats": {
+                "apache_log": "17/Oct/2026:07:37:43 +0000",
+                "syslog": "Oct 17 07:37:43",
+                "unix_date": "Sat Oct 17 07:37:43 UTC 2026",
+                "unix_timestamp": "1792222663"
+            },
+            "day": 17,
+            "day_of_year": 290,
+            "expires_at": "2026-10-17T07:37:44.158Z",
+            "hour": 7,
+            "iso8601": "2026-10-17T16:37:43.161409414+09:00",
+            "microseconds": 1792222663161409,
+            "milliseconds": 1792222663161,
+            "minute": 37,
+            "month": 10,
+            "nanos_since_epoch": "1792222663161409414",
+            "nanosecond": 161409414,
+            "offset": 32400,
+            "offset_validity": {
+                "expires_at": "2027-10-17T07:37:43.158Z",
+                "valid_for_ms": 31536000000
+            },
+            "rfc2822": "Sat, 17 Oct 2026 16:37:43 +0900",
+            "rfc3339": "2026-10-17T16:37:43.161409414+09:00",
+            "rfc9557": "2026-10-17T16:37:43.161409414+09:00[Asia/Tokyo]",
+            "second": 43,
+            "seconds": 1792222663,
+            "timezone": "Asia/Tokyo",
+            "unix": {
+                "nanos": 161409414,
+                "nanos_since_epoch": "1792222663161409414",
+                "seconds": 1792222663
+            },
+            "valid_for_ms": 1000,
+            "week_of_year": 41,
+            "weekday": "Saturday",
+            "year": 2026
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_time_with_timezone",
+        summary: "Error: unknown timezone",
+        arguments: r#"{"timezone": "Mars/Olympus_Mons"}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32602,
+            "data": {
+                "code": "invalid_timezone",
+                "details": {
+                    "suggestions": [],
+                    "timezone": "Mars/Olympus_Mons"
+                },
+                "message": "Invalid timezone: Mars/Olympus_Mons"
+            },
+            "message": "Invalid timezone: Mars/Olympus_Mons"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "list_timezones",
+        summary: "First page of zones under a prefix",
+        arguments: r#"{"limit": 3, "prefix": "Australia/"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "checksum": "12dcc29201f28098",
+            "count": 3,
+            "expires_at": "2027-10-17T07:37:43.160Z",
+            "has_more": true,
+            "offset": 0,
+            "timezones": [
+                "Australia/ACT",
+                "Australia/Adelaide",
+                "Australia/Brisbane"
+            ],
+            "total": 23,
+            "valid_for_ms": 31536000000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_timezones_if_changed",
+        summary: "A stale checksum gets the full list (shortened here)",
+        arguments: r#"{"checksum": "0000000000000000"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "checksum": "12dcc29201f28098",
+            "count": 597,
+            "expires_at": "2027-10-17T07:37:43.160Z",
+            "has_more": false,
+            "offset": 0,
+            "timezones": [
+                "Africa/Abidjan",
+                "Africa/Accra",
+                "Africa/Addis_Ababa"
+            ],
+            "total": 597,
+            "unchanged": false,
+            "valid_for_ms": 31536000000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "abbreviation_lookup",
+        summary: "Zones using an abbreviation on a date",
+        arguments: r#"{"abbreviation": "AEST", "timestamp": 1704067200}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "abbreviation": "AEST",
+            "date": "2024-01-01",
+            "offsets": [
+                {
+                    "is_dst": false,
+                    "offset_seconds": 36000,
+                    "utc_offset": "+10:00",
+                    "zone_count": 3,
+                    "zones": [
+                        "Australia/Brisbane",
+                        "Australia/Lindeman",
+                        "Australia/Queensland"
+                    ]
+                }
+            ],
+            "unique": true
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "convert_time",
+        summary: "A Unix timestamp in another timezone",
+        arguments: r#"{"timestamp": 1704067200, "to_timezone": "America/New_York"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "converted": {
+                "abbreviation": "EST",
+                "formatted": "2023-12-31T19:00:00-05:00",
+                "is_dst": false,
+                "offset": -18000,
+                "rfc9557": "2023-12-31T19:00:00-05:00[America/New_York]",
+                "timestamp": 1704067200,
+                "timezone": "America/New_York"
+            },
+            "note": "timestamp is absolute Unix time; the timezones only change how it is shown",
+            "original": {
+                "formatted": "2024-01-01T00:00:00+00:00",
+                "offset": 0,
+                "timestamp": 1704067200,
+                "timezone": "UTC"
+            }
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "convert_calendar",
+        summary: "A Gregorian instant as a Hebrew date",
+        arguments: r#"{"calendar": "hebrew", "timestamp": 1704067200}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "date": {
+                "calendar": "hebrew",
+                "day": 20,
+                "era": "AM",
+                "formatted": "20 Tevet 5784 AM",
+                "gregorian": "2024-01-01",
+                "leap_year": true,
+                "month": 10,
+                "month_name": "Tevet",
+                "year": 5784
+            },
+            "timezone": "UTC",
+            "unix_timestamp": 1704067200,
+            "utc": "2024-01-01T00:00:00+00:00"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "business_time",
+        summary: "Whether a date is a business day in a region",
+        arguments: r#"{"date": "2024-12-25", "region": "UK"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "date": "2024-12-25",
+            "holidays": [
+                {
+                    "date": "2024-12-25",
+                    "name": "Christmas Day",
+                    "observed": "2024-12-25"
+                }
+            ],
+            "is_business_day": false,
+            "is_holiday": true,
+            "is_weekend": false,
+            "next_business_day": "2024-12-27",
+            "region": "UK",
+            "weekday": "Wednesday"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_week_number",
+        summary: "A New Year date numbered five ways",
+        arguments: r#"{"date": "2021-01-01"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "date": "2021-01-01",
+            "iso_week": "2020-W53",
+            "selected": {
+                "convention": "iso",
+                "first_day": "2020-12-28",
+                "last_day": "2021-01-03",
+                "week": 53,
+                "week_year": 2020
+            },
+            "weekday": "Friday",
+            "weeks": {
+                "iso": {
+                    "week": 53,
+                    "week_year": 2020
+                },
+                "monday": {
+                    "week": 0,
+                    "week_year": 2021
+                },
+                "saturday": {
+                    "week": 1,
+                    "week_year": 2021
+                },
+                "simple": {
+                    "week": 1,
+                    "week_year": 2021
+                },
+                "sunday": {
+                    "week": 0,
+                    "week_year": 2021
+                }
+            }
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "parse_time",
+        summary: "An RFC 9557 string whose offset and zone disagree",
+        arguments: r#"{"input": "2024-03-10T02:30:00-05:00[America/New_York]"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "calendar": null,
+            "consistent": false,
+            "critical": false,
+            "input": "2024-03-10T02:30:00-05:00[America/New_York]",
+            "nanos": 0,
+            "offset_seconds": -18000,
+            "resolved_by": "offset",
+            "rfc3339": "2024-03-10T02:30:00-05:00",
+            "rfc9557": "2024-03-10T02:30:00-05:00",
+            "timezone": "America/New_York",
+            "unix_timestamp": 1710055800,
+            "utc": "2024-03-10T07:30:00Z"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "parse_natural_time",
+        summary: "A time in words, relative to a fixed reference",
+        arguments: r#"{"input": "tomorrow at 3pm", "reference_timestamp": 1710497730, "timezone": "America/New_York"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "input": "tomorrow at 3pm",
+            "local_status": "unique",
+            "reference": "2024-03-15T06:15:30-04:00",
+            "rfc3339": "2024-03-16T15:00:00-04:00",
+            "timezone": "America/New_York",
+            "trace": [
+                {
+                    "kind": "day",
+                    "meaning": "2024-03-16 (the day after the reference day)",
+                    "text": "tomorrow"
+                },
+                {
+                    "kind": "filler",
+                    "meaning": "ignored",
+                    "text": "at"
+                },
+                {
+                    "kind": "time",
+                    "meaning": "15:00:00",
+                    "text": "3pm"
+                }
+            ],
+            "unix_timestamp": 1710615600,
+            "utc": "2024-03-16T19:00:00Z"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "email_date",
+        summary: "Parse a Date header with an obsolete zone name",
+        arguments: r#"{"input": "Mon, 3 Mar 2025 7:05:09 EST (Eastern)", "mode": "parse"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "normalized": "Mon, 03 Mar 2025 07:05:09 -0500",
+            "notes": [
+                "comments removed",
+                "one-digit time field",
+                "obsolete zone EST read as -0500"
+            ],
+            "offset": "-05:00",
+            "offset_seconds": -18000,
+            "offset_unknown": false,
+            "unix_seconds": 1741003509,
+            "utc": "2025-03-03T12:05:09Z"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "email_date",
+        summary: "Generate a Date header in a timezone",
+        arguments: r#"{"mode": "generate", "timestamp": 1057049557, "timezone": "Australia/Sydney"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "date": "Tue, 01 Jul 2003 18:52:37 +1000",
+            "header": "Date: Tue, 01 Jul 2003 18:52:37 +1000",
+            "timezone": "Australia/Sydney",
+            "unix_seconds": 1057049557
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "timestamp_card",
+        summary: "A timestamp three days ahead, with two local times",
+        arguments: r#"{"timestamp": 1792481863, "timezones": "America/New_York,Asia/Tokyo"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "iso_week": "2026-W43",
+            "reference": "2026-10-17T07:37:43Z",
+            "relative": "in 3 days",
+            "text": "UTC         2026-10-20T07:37:43Z\nRelative    in 3 days\nWeekday     Tuesday\nISO week    2026-W43\nUnix        1792481863\nLocal       America/New_York  2026-10-20 03:37:43  -04:00\n            Asia/Tokyo        2026-10-20 16:37:43  +09:00\n",
+            "unix_timestamp": 1792481863,
+            "utc": "2026-10-20T07:37:43Z",
+            "weekday": "Tuesday",
+            "zones": [
+                {
+                    "date": "2026-10-20",
+                    "day_difference": 0,
+                    "local_time": "03:37:43",
+                    "offset_seconds": -14400,
+                    "timezone": "America/New_York",
+                    "utc_offset": "-04:00"
+                },
+                {
+                    "date": "2026-10-20",
+                    "day_difference": 0,
+                    "local_time": "16:37:43",
+                    "offset_seconds": 32400,
+                    "timezone": "Asia/Tokyo",
+                    "utc_offset": "+09:00"
+                }
+            ]
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "format_duration",
+        summary: "A computed number of seconds in words",
+        arguments: r#"{"value": 184200, "style": "human_long"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "formatted": "2 days, 3 hours, 10 minutes",
+            "style": "human_long",
+            "total_seconds": 184200.0
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "timezone_for_location",
+        summary: "Coordinates in Tokyo",
+        arguments: r#"{"latitude": 35.69, "longitude": 139.69}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "abbreviation": "JST",
+            "approximate": false,
+            "candidates": [],
+            "is_dst": false,
+            "latitude": 35.69,
+            "longitude": 139.69,
+            "offset_seconds": 32400,
+            "timezone": "Asia/Tokyo",
+            "utc_offset": "+09:00"
+        }"#,
+        ),
+        host: Host::Geo,
+    },
+    ToolExample {
+        tool: "get_host_timezone",
+        summary: "A host with /etc/localtime linked to Europe/Berlin",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "checked": [
+                {
+                    "outcome": "not set",
+                    "source": "tz_env"
+                },
+                {
+                    "outcome": "Europe/Berlin",
+                    "source": "localtime"
+                }
+            ],
+            "determined": true,
+            "is_dst": false,
+            "locale": "en_US.UTF-8",
+            "offset_seconds": 3600,
+            "source": "localtime",
+            "timezone": "Europe/Berlin",
+            "utc_offset": "+01:00"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "compare_times",
+        summary: "Unix seconds against an RFC 2822 date",
+        arguments: r#"{"a": 1710496800, "b": "Fri, 15 Mar 2024 11:30:00 +0100"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "a": {
+                "input": "1710496800",
+                "representation": "unix_seconds",
+                "unix_nanos": "1710496800000000000",
+                "utc": "2024-03-15T10:00:00Z"
+            },
+            "b": {
+                "input": "Fri, 15 Mar 2024 11:30:00 +0100",
+                "representation": "rfc2822",
+                "unix_nanos": "1710498600000000000",
+                "utc": "2024-03-15T10:30:00Z"
+            },
+            "difference": "30m",
+            "difference_nanos": 1800000000000,
+            "difference_seconds": 1800.0,
+            "earlier": "a"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "world_clock",
+        summary: "Current time in several zones",
+        arguments: r#"{"timezones": "UTC,Asia/Kolkata"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "reference_source": "server",
+            "reference_time": "2026-10-17T07:37:43.159485624Z",
+            "rows": [
+                {
+                    "date": "2026-10-17",
+                    "day_difference": 0,
+                    "local_time": "07:37:43",
+                    "offset_seconds": 0,
+                    "timezone": "UTC",
+                    "utc_offset": "+00:00"
+                },
+                {
+                    "date": "2026-10-17",
+                    "day_difference": 0,
+                    "local_time": "13:07:43",
+                    "offset_seconds": 19800,
+                    "timezone": "Asia/Kolkata",
+                    "utc_offset": "+05:30"
+                }
+            ],
+            "table": "Zone          Local time  Date        UTC offset  Day\n------------  ----------  ----------  ----------  ---\nUTC           07:37:43    2026-10-17  +00:00      0\nAsia/Kolkata  13:07:43    2026-10-17  +05:30      0\n",
+            "unix_timestamp": 1792222663,
+            "utc": "2026-10-17T07:37:43Z"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_dst_transitions",
+        summary: "DST changes of a zone in one year",
+        arguments: r#"{"timezone": "Europe/Berlin", "year": 2024}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "count": 2,
+            "has_dst": true,
+            "timezone": "Europe/Berlin",
+            "transitions": [
+                {
+                    "change_minutes": 60,
+                    "kind": "gap",
+                    "local_after": "2024-03-31T03:00:00",
+                    "local_before": "2024-03-31T02:00:00",
+                    "offset_after_seconds": 7200,
+                    "offset_before_seconds": 3600,
+                    "unix_timestamp": 1711846800,
+                    "utc": "2024-03-31T01:00:00+00:00"
+                },
+                {
+                    "change_minutes": -60,
+                    "kind": "overlap",
+                    "local_after": "2024-10-27T02:00:00",
+                    "local_before": "2024-10-27T03:00:00",
+                    "offset_after_seconds": 3600,
+                    "offset_before_seconds": 7200,
+                    "unix_timestamp": 1729990800,
+                    "utc": "2024-10-27T01:00:00+00:00"
+                }
+            ],
+            "year": 2024
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_offset_timeline",
+        summary: "Offsets of a zone over a range, including a permanent change",
+        arguments: r#"{"end": 1420070400, "start": 1388534400, "timezone": "Europe/Moscow"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "count": 2,
+            "end": 1420070400,
+            "segments": [
+                {
+                    "abbreviation": "MSK",
+                    "from_unix": 1388534400,
+                    "from_utc": "2014-01-01T00:00:00+00:00",
+                    "is_dst": false,
+                    "offset_seconds": 14400,
+                    "to_unix": 1414274400,
+                    "to_utc": "2014-10-25T22:00:00+00:00"
+                },
+                {
+                    "abbreviation": "MSK",
+                    "from_unix": 1414274400,
+                    "from_utc": "2014-10-25T22:00:00+00:00",
+                    "is_dst": false,
+                    "offset_seconds": 10800,
+                    "to_unix": 1420070400,
+                    "to_utc": "2015-01-01T00:00:00+00:00"
+                }
+            ],
+            "start": 1388534400,
+            "timezone": "Europe/Moscow"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "offset_difference",
+        summary: "London to Sydney as both switch DST a week apart",
+        arguments: r#"{"date": "2024-03-30", "from_timezone": "Europe/London", "to_timezone": "Australia/Sydney"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "changes": [
+                {
+                    "date": "2024-03-31",
+                    "difference_minutes": 600,
+                    "from_offset_seconds": 3600,
+                    "to_offset_seconds": 39600
+                },
+                {
+                    "date": "2024-04-07",
+                    "difference_minutes": 540,
+                    "from_offset_seconds": 3600,
+                    "to_offset_seconds": 36000
+                }
+            ],
+            "changes_in_window": true,
+            "date": "2024-03-30",
+            "difference_minutes": 660,
+            "from_offset_seconds": 0,
+            "from_timezone": "Europe/London",
+            "to_offset_seconds": 39600,
+            "to_timezone": "Australia/Sydney",
+            "window_days": 14
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "local_to_utc",
+        summary: "An ambiguous local time resolved to the later instant",
+        arguments: r#"{"ambiguity": "later", "local_datetime": "2024-11-03T01:30:00", "timezone": "America/New_York"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "candidates": [
+                {
+                    "offset_seconds": -14400,
+                    "unix_timestamp": 1730611800,
+                    "utc": "2024-11-03T05:30:00+00:00"
+                },
+                {
+                    "offset_seconds": -18000,
+                    "unix_timestamp": 1730615400,
+                    "utc": "2024-11-03T06:30:00+00:00"
+                }
+            ],
+            "local_datetime": "2024-11-03T01:30:00",
+            "offset_seconds": -18000,
+            "policy_applied": "later",
+            "status": "ambiguous",
+            "timezone": "America/New_York",
+            "unix_timestamp": 1730615400,
+            "utc": "2024-11-03T06:30:00+00:00"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "find_overlap",
+        summary: "Common working hours in New York and London",
+        arguments: r#"{"date": "2024-06-12", "min_minutes": 60, "timezones": ["America/New_York", "Europe/London"]}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "date": "2024-06-12",
+            "min_slot_minutes": 60,
+            "near_miss": null,
+            "overlap": true,
+            "reference_timezone": "America/New_York",
+            "slots": [
+                {
+                    "end_utc": "2024-06-12T16:00:00+00:00",
+                    "local": [
+                        {
+                            "end": "2024-06-12T12:00:00-04:00",
+                            "out_of_hours_minutes": 0,
+                            "start": "2024-06-12T09:00:00-04:00",
+                            "timezone": "America/New_York",
+                            "working_hours": "09:00-17:00"
+                        },
+                        {
+                            "end": "2024-06-12T17:00:00+01:00",
+                            "out_of_hours_minutes": 0,
+                            "start": "2024-06-12T14:00:00+01:00",
+                            "timezone": "Europe/London",
+                            "working_hours": "09:00-17:00"
+                        }
+                    ],
+                    "minutes": 180,
+                    "start_utc": "2024-06-12T13:00:00+00:00"
+                }
+            ]
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "assert_time_window",
+        summary: "Is it business hours in Berlin?",
+        arguments: r#"{"daily": "09:00-17:00", "timestamp": "2024-06-14T10:00:00Z", "timezone": "Europe/Berlin"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "current_window_end": "2024-06-14T15:00:00Z",
+            "current_window_start": "2024-06-14T07:00:00Z",
+            "inside": true,
+            "next_window_end": "2024-06-15T15:00:00Z",
+            "next_window_start": "2024-06-15T07:00:00Z",
+            "reference_source": "client",
+            "reference_time": "2024-06-14T10:00:00Z",
+            "seconds_until_change": 18000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "assert_time_window",
+        summary: "A cron window needs a duration",
+        arguments: r#"{"cron": "0 9 * * MON-FRI"}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32602,
+            "data": {
+                "code": "invalid_argument",
+                "details": null,
+                "message": "give one window: 'start' and 'end', 'daily', or 'cron' with 'duration'"
+            },
+            "message": "give one window: 'start' and 'end', 'daily', or 'cron' with 'duration'"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "schedule_notification",
+        summary: "Notify in five minutes",
+        arguments: r#"{"delay_seconds": 300, "label": "stand-up"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "created_at": "2026-10-17T07:37:43.177Z",
+            "fire_at": "2026-10-17T07:42:43.177Z",
+            "id": 1,
+            "label": "stand-up"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "list_scheduled",
+        summary: "Pending notifications of this session",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "count": 1,
+            "max_pending": 32,
+            "scheduled": [
+                {
+                    "created_at": "2026-10-17T07:37:43.177Z",
+                    "fire_at": "2026-10-17T07:42:43.177Z",
+                    "id": 1,
+                    "label": "stand-up"
+                }
+            ]
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "cancel_scheduled",
+        summary: "Cancel a pending notification",
+        arguments: r#"{"id": 1}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "cancelled": true,
+            "id": 1
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "subscribe_heartbeat",
+        summary: "A heartbeat notification every 30 seconds",
+        arguments: r#"{"interval_seconds": 30}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "interval_seconds": 30.0,
+            "replaced": false,
+            "started_at": "2026-10-17T07:37:43.177Z",
+            "subscribed": true
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "subscribe_heartbeat",
+        summary: "Intervals outside 1 to 300 seconds are refused",
+        arguments: r#"{"interval_seconds": 0.5}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32602,
+            "message": "'interval_seconds' must be between 1 and 300"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "unsubscribe_heartbeat",
+        summary: "Stop the heartbeat",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "unsubscribed": true
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_server_info",
+        summary: "Build, modes and uptime of the server",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "build": {
+                "build_timestamp": "2026-10-17T07:37:07Z",
+                "features": [],
+                "git_commit": "46f3779a4336",
+                "profile": "debug",
+                "target": "x86_64-unknown-linux-gnu",
+                "tzdb_version": "2024a",
+                "version": "0.1.0"
+            },
+            "host": {
+                "arch": "x86_64",
+                "family": "unix",
+                "os": "linux"
+            },
+            "disabled_tools": [],
+            "modes": {
+                "audit_log": false,
+                "container_mode": true,
+                "corrected_time_default": false,
+                "gps_refclock": false,
+                "http_api": null,
+                "mcp_stdio": false,
+                "ntp_backend": "host_clock",
+                "pps_monitor": false
+            },
+            "name": "mcp-utc-time-server",
+            "started_at": "2026-10-17T07:37:43.178Z",
+            "uptime_seconds": 1.8627e-05
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_process_stats",
+        summary: "Uptime, request counts and memory of the server process",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "heartbeat_tasks": 0,
+            "http_requests": 0,
+            "last_error": null,
+            "mcp_requests": {
+                "by_tool": null,
+                "rate_limited": 0,
+                "total": 12
+            },
+            "memory": {
+                "rss_bytes": 9437184
+            },
+            "pid": 4127,
+            "started_at": "2026-10-17T07:37:43.178Z",
+            "state_file_age_seconds": null,
+            "state_loaded": false,
+            "tokio_tasks": 6,
+            "uptime_seconds": 41.52
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_data_versions",
+        summary: "tzdata and leap second table versions",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "build_timestamp": "2026-10-17T07:37:07Z",
+            "crate_version": "0.1.0",
+            "leap_seconds": {
+                "entries": 28,
+                "expired": false,
+                "expires": "2026-12-28",
+                "last_entry": "2017-01-01",
+                "tai_utc_offset": 37
+            },
+            "tzdata_caveat_days": 365,
+            "tzdata_version": "2024a"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_clock_resolution",
+        summary: "Kernel clock resolution and 1000 back-to-back reads",
+        arguments: r#"{"samples": 1000}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "measured": {
+                "max_delta_ns": 1523,
+                "min_delta_ns": 20,
+                "now_cost_ns": 24.6,
+                "p50_delta_ns": 22,
+                "p90_delta_ns": 25,
+                "p99_delta_ns": 41,
+                "samples": 1000,
+                "zero_deltas": 0
+            },
+            "monotonic_res_ns": 1,
+            "realtime_res_ns": 1,
+            "timer_slack_ns": 50000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "check_time_sanity",
+        summary: "Clock cross-checks, skipping the kernel TAI and network ones",
+        arguments: r#"{"skip": ["tai", "http"]}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "checked_at": "2026-10-17T07:37:43.179Z",
+            "checks": [
+                {
+                    "check": "tai",
+                    "message": "skipped by request",
+                    "status": "skip",
+                    "values": {}
+                },
+                {
+                    "check": "filesystem",
+                    "message": "file timestamps agree with the clock",
+                    "status": "pass",
+                    "values": {
+                        "clock_after": "2026-10-17T07:37:43.179696468Z",
+                        "clock_before": "2026-10-17T07:37:43.179629819Z",
+                        "file_modified": "2026-10-17T07:37:43.176395984Z",
+                        "skew_seconds": -0.003233835
+                    }
+                },
+                {
+                    "check": "timezone",
+                    "message": "host timezone is UTC",
+                    "status": "pass",
+                    "values": {
+                        "local_offset_seconds": 0,
+                        "localtime": "/usr/share/zoneinfo/Etc/UTC",
+                        "tz": null
+                    }
+                },
+                {
+                    "check": "http",
+                    "message": "skipped by request",
+                    "status": "skip",
+                    "values": {}
+                }
+            ],
+            "status": "pass"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "run_selftest_benchmark",
+        summary: "Without ENABLE_BENCH_TOOL the benchmark is refused",
+        arguments: r#"{"iterations": 100, "tool": "get_unix_time"}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32001,
+            "data": {
+                "code": "unauthorized",
+                "details": null,
+                "message": "Unauthorized: run_selftest_benchmark is disabled; the server enables it with ENABLE_BENCH_TOOL=true"
+            },
+            "message": "Unauthorized: run_selftest_benchmark is disabled; the server enables it with ENABLE_BENCH_TOOL=true"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_maintenance_window",
+        summary: "MAINTENANCE_WINDOW='0 2 * * SUN 15m', outside a window",
+        arguments: r#"{"count": 2}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "schedule": "0 2 * * SUN",
+            "duration_seconds": 900,
+            "active": false,
+            "windows": [
+                {"start": "2024-03-17T02:00:00Z", "end": "2024-03-17T02:15:00Z"},
+                {"start": "2024-03-24T02:00:00Z", "end": "2024-03-24T02:15:00Z"}
+            ]
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_chaos_status",
+        summary: "Chaos mode off, as in any normal deployment",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "active": false,
+            "note": "chaos mode is off; times are real"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_signed_time",
+        summary: "Signed time in a container, where NTP quality is unavailable",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "algorithm": "ed25519",
+            "canonical": "{\"ntp\":{\"available\":false,\"synced\":false},\"sequence\":42,\"time\":{\"nanos\":204118563,\"nanos_since_epoch\":\"1792222663204118563\",\"seconds\":1792222663}}",
+            "ntp": {
+                "available": false,
+                "synced": false
+            },
+            "public_key": "0d7550754e0800a5d237eef5826035766b9b3e5a15868a940ab289958788e3b0",
+            "sequence": 42,
+            "signature": "7f657410ba04335c2cb1516b3bed4a0fac715bfbc35aa9514bf4184031611f8a1a345e237969abef550df3f0af74f732afbb15dae91689a50203622fa3dba803",
+            "time": {
+                "nanos": 204118563,
+                "nanos_since_epoch": "1792222663204118563",
+                "seconds": 1792222663
+            }
+        }"#,
+        ),
+        host: Host::Container,
+    },
+    ToolExample {
+        tool: "get_signing_key",
+        summary: "Per-process key (no SIGNING_KEY_FILE)",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "algorithm": "ed25519",
+            "persistent": false,
+            "public_key": "0d7550754e0800a5d237eef5826035766b9b3e5a15868a940ab289958788e3b0"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "verify_signed_time",
+        summary: "Check an attestation against the key it names",
+        arguments: r#"{"attestation": {"algorithm": "ed25519", "canonical": "{\"ntp\":{\"available\":false,\"synced\":false},\"sequence\":42,\"time\":{\"nanos\":204118563,\"nanos_since_epoch\":\"1792222663204118563\",\"seconds\":1792222663}}", "ntp": {"available": false, "synced": false}, "public_key": "0d7550754e0800a5d237eef5826035766b9b3e5a15868a940ab289958788e3b0", "sequence": 42, "signature": "7f657410ba04335c2cb1516b3bed4a0fac715bfbc35aa9514bf4184031611f8a1a345e237969abef550df3f0af74f732afbb15dae91689a50203622fa3dba803", "time": {"nanos": 204118563, "nanos_since_epoch": "1792222663204118563", "seconds": 1792222663}}}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "public_key": "0d7550754e0800a5d237eef5826035766b9b3e5a15868a940ab289958788e3b0",
+            "sequence": 42,
+            "signed_at": "2026-10-17T07:37:43.204118563Z",
+            "valid": true
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_trusted_timestamp",
+        summary: "A digest that is not SHA-256",
+        arguments: r#"{"digest": "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"}"#,
+        outcome: Outcome::Error(
+            r#"{
+            "code": -32602,
+            "data": {
+                "code": "invalid_value",
+                "details": {
+                    "cause": "invalid_argument",
+                    "field": "digest"
+                },
+                "message": "digest: a SHA-256 digest is 32 bytes (64 hex digits or 44 base64 characters), got 30"
+            },
+            "message": "digest: a SHA-256 digest is 32 bytes (64 hex digits or 44 base64 characters), got 30"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_ntp_status",
+        summary: "NTP status in a container",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "available": false,
+            "checks": [
+                {
+                    "detail": "container: synchronization assumed from the host, not verified",
+                    "name": "sync",
+                    "status": "degraded"
+                },
+                {
+                    "detail": "0.000 ms (within 100 ms)",
+                    "name": "offset",
+                    "status": "healthy"
+                },
+                {
+                    "detail": "no valid refclock sample",
+                    "name": "shm",
+                    "status": "degraded"
+                }
+            ],
+            "container_mode": true,
+            "expires_at": "2026-10-17T07:37:43.201Z",
+            "health": "degraded",
+            "managed": false,
+            "message": "NTP not available in container environment. Container uses host system time.",
+            "shm_interface": "not_available",
+            "synced": false,
+            "valid_for_ms": 0,
+            "warnings": [
+                {
+                    "code": "NTP_CONTAINER_ASSUMED",
+                    "message": "running in a container; the host clock is assumed to be NTP-synchronized"
+                }
+            ]
+        }"#,
+        ),
+        host: Host::Container,
+    },
+    ToolExample {
+        tool: "get_ntp_peers",
+        summary: "NTP peers in a container",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "available": false,
+            "container_mode": true,
+            "message": "NTP peers not available in container environment",
+            "peers": []
+        }"#,
+        ),
+        host: Host::Container,
+    },
+    ToolExample {
+        tool: "get_ntp_history",
+        summary: "Hourly offset buckets for the last day, recorded on a host running ntpd",
+        arguments: r#"{"resolution": 3600, "since": "24h"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "buckets": [
+                {
+                    "max_offset_ms": 0.412,
+                    "mean_offset_ms": 0.087,
+                    "min_offset_ms": -0.301,
+                    "samples": 60,
+                    "start": 1718186400,
+                    "synced_percent": 100.0
+                }
+            ],
+            "capacity": 1440,
+            "file": null,
+            "interval_secs": 60,
+            "recording": true,
+            "resolution": 3600,
+            "samples": null,
+            "since": 1718103600,
+            "summary": {
+                "from": 1718103640,
+                "max_offset_ms": 1.254,
+                "p50_offset_ms": 0.118,
+                "p95_offset_ms": 0.506,
+                "samples": 1440,
+                "synced_percent": 100.0,
+                "to": 1718189980
+            }
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_pps_status",
+        summary: "PPS monitor without a device configured",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "assert_sequence": null,
+            "available": false,
+            "clear_sequence": null,
+            "device": null,
+            "enabled": false,
+            "error": null,
+            "jitter": null,
+            "last_clear": null,
+            "last_pulse": null,
+            "missed_pulses": 0,
+            "pulses_observed": 0,
+            "receiving": false
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_gps_status",
+        summary: "GPS receiver without a device configured",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "checksum_errors": 0,
+            "connected": false,
+            "device": null,
+            "enabled": false,
+            "fix": "no_fix",
+            "last_error": null,
+            "last_gps_time": null,
+            "last_sentence_at": null,
+            "malformed": 0,
+            "samples_published": 0,
+            "satellites_used": null,
+            "sentences": 0,
+            "shm_unit": null
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_ptp_status",
+        summary: "A host locked to a PTP grandmaster",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "available": true,
+            "check": {
+                "detail": "locked to 001122.fffe.334455, master offset -8 ns (within 100 ms)",
+                "name": "ptp",
+                "status": "healthy"
+            },
+            "devices": [
+                {
+                    "clock_name": "e1000e PTP",
+                    "device": "/dev/ptp0"
+                }
+            ],
+            "expires_at": "2026-10-17T07:38:47.158Z",
+            "gm_identity": "001122.fffe.334455",
+            "gm_present": true,
+            "master_offset_ns": -8,
+            "notes": [],
+            "phc_offset": {
+                "device": "/dev/ptp0",
+                "offset_ns": 37000000021
+            },
+            "pmc_available": true,
+            "port_state": "SLAVE",
+            "ports": [
+                {
+                    "port_identity": "90e2ba.fffe.2a5b9c-1",
+                    "port_state": "SLAVE"
+                }
+            ],
+            "synced": true,
+            "valid_for_ms": 64000
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_examples",
+        summary: "Examples for one tool",
+        arguments: r#"{"tool": "get_nanos"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "count": 1,
+            "examples": [
+                {
+                    "tool": "get_nanos",
+                    "summary": "Nanoseconds since the Unix epoch",
+                    "arguments": {},
+                    "response": {
+                        "nanoseconds": "1792222663159131824",
+                        "seconds": 1792222663,
+                        "subsec_nanos": 159131824
+                    }
+                }
+            ]
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "set_preferences",
+        summary: "Session defaults for timezone and precision",
+        arguments: r#"{"precision": "millis", "timezone": "Australia/Melbourne"}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "precision": "millis",
+            "timezone": "Australia/Melbourne"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "get_preferences",
+        summary: "Session defaults currently set",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "precision": "millis",
+            "timezone": "Australia/Melbourne"
+        }"#,
+        ),
+        host: Host::Any,
+    },
+    ToolExample {
+        tool: "clear_preferences",
+        summary: "Drop the session defaults",
+        arguments: r#"{}"#,
+        outcome: Outcome::Response(
+            r#"{
+            "cleared": true
+        }"#,
+        ),
+        host: Host::Any,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples_are_json() {
+        for example in EXAMPLES {
+            assert!(example.arguments().is_object(), "{}", example.tool);
+            let expected = example.expected();
+            assert!(expected.is_object(), "{}", example.tool);
+            if example.is_error() {
+                assert!(expected["code"].is_i64(), "{}", example.tool);
+                assert!(expected["message"].is_string(), "{}", example.tool);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_examples_cover_bad_input() {
+        let errors: Vec<_> = EXAMPLES.iter().filter(|e| e.is_error()).collect();
+        let code = |tool: &str| {
+            errors
+                .iter()
+                .find(|e| e.tool == tool)
+                .map(|e| e.expected()["data"]["code"].clone())
+        };
+        assert_eq!(
+            code("get_time_with_timezone"),
+            Some(json!("invalid_timezone"))
+        );
+        assert_eq!(code("get_time_formatted"), Some(json!("invalid_format")));
+    }
+
+    #[test]
+    fn test_get_examples_example_matches_catalog() {
+        let example = examples(Some("get_examples"))[0];
+        let tool = example.arguments()["tool"].as_str().unwrap().to_string();
+        assert_eq!(catalog(Some(&tool)).unwrap(), example.expected());
+        assert!(catalog(Some("get_weather")).is_err());
+        assert_eq!(catalog(None).unwrap()["count"], EXAMPLES.len());
+    }
+
+    #[test]
+    fn test_shape_mismatch() {
+        let expected = json!({"a": 1, "b": {"c": "x"}, "d": [{"e": true}], "f": null, "g": {}});
+        let same = json!({"a": 2.5, "b": {"c": "y"}, "d": [], "f": [1], "g": {"h": 1}});
+        assert_eq!(shape_mismatch(&expected, &same), None);
+
+        let cases = [
+            (
+                json!({"a": "1"}),
+                "$.a is a number in the example but a string",
+            ),
+            (
+                json!({"b": {"c": "x", "z": 1}}),
+                "$.b.z is not in the example",
+            ),
+            (json!({"b": {}}), "$.b.c is missing"),
+            (
+                json!({"d": [{"e": 0}]}),
+                "$.d[0].e is a boolean in the example but a number",
+            ),
+        ];
+        for (change, error) in cases {
+            let mut actual = same.clone();
+            for (key, value) in change.as_object().unwrap() {
+                actual[key] = value.clone();
+            }
+            assert_eq!(shape_mismatch(&expected, &actual).as_deref(), Some(error));
+        }
+    }
+}
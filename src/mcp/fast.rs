@@ -0,0 +1,202 @@
+// Hand-written JSON for get_unix_time
+//
+// Agents poll get_unix_time at high rates, and rendering its small body
+// through serde_json's pretty printer cost more than reading the clock. The
+// compact body is written here field by field into a buffer kept per
+// thread, so a call allocates only the String handed to the MCP result. The
+// bytes match `serde_json::to_string` of the serde response exactly: same
+// fields and order, and nanosecond counts as numbers or strings by the same
+// rule. Corrected readings, which carry extra fields and warnings, and pretty
+// output still go through serde.
+
+use crate::time::source::TimeSource;
+use crate::time::unix::nanos_as_number;
+use crate::time::UnixTime;
+use crate::validity::Validity;
+use chrono::{DateTime, Datelike, SecondsFormat, Timelike, Utc};
+use std::cell::RefCell;
+
+/// Longer than any body written here, so the buffer never grows
+const BUFFER_CAPACITY: usize = 192;
+
+thread_local! {
+    static BUFFER: RefCell<String> = RefCell::new(String::with_capacity(BUFFER_CAPACITY));
+}
+
+/// Compact get_unix_time body for an uncorrected reading taken at `now`
+pub fn unix_time_json(
+    time: &UnixTime,
+    source: TimeSource,
+    valid_for_ms: u64,
+    now: DateTime<Utc>,
+) -> String {
+    BUFFER.with(|buffer| {
+        let mut out = buffer.borrow_mut();
+        out.clear();
+        write_unix_time(&mut out, time, source, valid_for_ms, now);
+        out.as_str().to_owned()
+    })
+}
+
+fn write_unix_time(
+    out: &mut String,
+    time: &UnixTime,
+    source: TimeSource,
+    valid_for_ms: u64,
+    now: DateTime<Utc>,
+) {
+    out.push_str("{\"seconds\":");
+    push_int(out, time.seconds.into());
+    out.push_str(",\"nanos\":");
+    push_int(out, time.nanos.into());
+    out.push_str(",\"nanos_since_epoch\":");
+    match nanos_as_number(time.nanos_since_epoch) {
+        Some(number) => push_int(out, number.into()),
+        None => {
+            out.push('"');
+            push_int(out, time.nanos_since_epoch);
+            out.push('"');
+        }
+    }
+    out.push_str(",\"source\":\"");
+    out.push_str(source.as_str());
+    out.push_str("\",\"valid_for_ms\":");
+    push_int(out, valid_for_ms.into());
+    out.push_str(",\"expires_at\":\"");
+    let expires_at = Validity::expiry(now, valid_for_ms);
+    if (0..=9999).contains(&expires_at.year()) && expires_at.nanosecond() < 1_000_000_000 {
+        // What to_rfc3339_opts(Millis, true) writes, without its String
+        push_padded(out, expires_at.year() as u32, 4);
+        out.push('-');
+        push_padded(out, expires_at.month(), 2);
+        out.push('-');
+        push_padded(out, expires_at.day(), 2);
+        out.push('T');
+        push_padded(out, expires_at.hour(), 2);
+        out.push(':');
+        push_padded(out, expires_at.minute(), 2);
+        out.push(':');
+        push_padded(out, expires_at.second(), 2);
+        out.push('.');
+        push_padded(out, expires_at.timestamp_subsec_millis(), 3);
+        out.push('Z');
+    } else {
+        // Widened years and leap seconds; never reached with a sane window
+        out.push_str(&expires_at.to_rfc3339_opts(SecondsFormat::Millis, true));
+    }
+    out.push_str("\"}");
+}
+
+/// Decimal digits of `value`, as itoa writes them (core::fmt is slower)
+fn push_int(out: &mut String, value: i128) {
+    if value < 0 {
+        out.push('-');
+    }
+    let mut digits = [0u8; 40];
+    let mut at = digits.len();
+    // 128-bit division is slow; nanosecond counts fit in 64 bits until 2554
+    match u64::try_from(value.unsigned_abs()) {
+        Ok(mut rest) => loop {
+            at -= 1;
+            digits[at] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        },
+        Err(_) => {
+            let mut rest = value.unsigned_abs();
+            loop {
+                at -= 1;
+                digits[at] = b'0' + (rest % 10) as u8;
+                rest /= 10;
+                if rest == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    out.extend(digits[at..].iter().map(|&d| d as char));
+}
+
+/// `value` zero-padded to `width` digits
+fn push_padded(out: &mut String, value: u32, width: usize) {
+    let mut digits = [b'0'; 10];
+    let mut at = digits.len();
+    let mut rest = value;
+    while rest > 0 {
+        at -= 1;
+        digits[at] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+    }
+    let at = at.min(digits.len() - width);
+    out.extend(digits[at..].iter().map(|&d| d as char));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::correction::Corrected;
+    use crate::validity::ValidityConfig;
+    use crate::warnings::Warnings;
+    use std::time::Duration;
+
+    /// The body get_unix_time built with serde before the fast path
+    fn serde_body(
+        time: &UnixTime,
+        source: TimeSource,
+        valid_for_ms: u64,
+        now: DateTime<Utc>,
+    ) -> String {
+        let validity = Validity::for_duration(now, Duration::from_millis(valid_for_ms));
+        let response = Warnings::new().attach(validity.attach(Corrected {
+            time: time.clone(),
+            source,
+            correction: None,
+        }));
+        serde_json::to_string(&response).unwrap()
+    }
+
+    #[test]
+    fn test_matches_serde_output() {
+        let now = DateTime::from_timestamp(1_792_222_663, 158_684_004).unwrap();
+        let times = [
+            UnixTime::from_datetime(now),
+            UnixTime::from_parts(0, 0).unwrap(),
+            // Nanoseconds still exact as a JSON number
+            UnixTime::from_parts(9_007_199, 254_740_991).unwrap(),
+            UnixTime::from_parts(-1, 999_999_999).unwrap(),
+            UnixTime::from_parts(crate::time::unix::MIN_SECONDS, 0).unwrap(),
+            // Past i64 nanoseconds (2262)
+            UnixTime::from_parts(crate::time::unix::MAX_SECONDS, 999_999_999).unwrap(),
+        ];
+        let windows = [
+            0,
+            ValidityConfig::default().current_time_ms,
+            crate::validity::INDEFINITE_VALID_MS,
+            u64::MAX,
+        ];
+        for time in &times {
+            for source in TimeSource::ALL {
+                for valid_for_ms in windows {
+                    let fast = unix_time_json(time, source, valid_for_ms, now);
+                    assert_eq!(fast, serde_body(time, source, valid_for_ms, now));
+                    let parsed: serde_json::Value = serde_json::from_str(&fast).unwrap();
+                    assert_eq!(parsed["seconds"], time.seconds);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_buffer_is_reused() {
+        let now = Utc::now();
+        let time = UnixTime::from_datetime(now);
+        let first = unix_time_json(&time, TimeSource::System, 1000, now);
+        let capacity = BUFFER.with(|buffer| buffer.borrow().capacity());
+        let second = unix_time_json(&time, TimeSource::Shm, 1000, now);
+        assert_eq!(capacity, BUFFER_CAPACITY);
+        assert_eq!(BUFFER.with(|buffer| buffer.borrow().capacity()), capacity);
+        assert_eq!(first.replace("system", "shm"), second);
+    }
+}
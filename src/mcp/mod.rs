@@ -1,2 +1,10 @@
+pub mod annotations;
+pub mod content_hash;
+pub mod examples;
+pub mod fast;
+pub mod params;
+pub mod policy;
+pub mod rate_limit;
+pub mod timeouts;
 pub mod transport;
 pub mod types;
@@ -0,0 +1,142 @@
+// Tool argument names checked against the declared input schema
+//
+// serde ignores fields it does not know, so a typo such as `time_zone`
+// silently falls back to the default and the caller gets UTC without
+// knowing why. Both MCP servers check argument names against the tool's
+// input schema before dispatch and reject unknown ones, suggesting the
+// closest declared name. `MCP_LENIENT_PARAMS=true` restores the old
+// behavior: unknown arguments are dropped and logged at debug level.
+
+use crate::error::TimeServerError;
+use serde_json::{Map, Value};
+use tracing::debug;
+
+/// Suggestions further than this many edits away are not offered
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// `MCP_LENIENT_PARAMS`, for the legacy handler that reads the environment
+/// directly (the rmcp server takes it from `ServerConfig`)
+pub fn lenient_from_env() -> bool {
+    std::env::var("MCP_LENIENT_PARAMS")
+        .ok()
+        .and_then(|v| crate::output::parse_flag(&v))
+        .unwrap_or(false)
+}
+
+/// Property names declared by an input schema
+pub fn schema_fields(schema: &Map<String, Value>) -> Vec<&str> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Reject (or, when `lenient`, remove) argument names not in `known`
+pub fn check_arguments(
+    tool: &str,
+    arguments: &mut Map<String, Value>,
+    known: &[&str],
+    lenient: bool,
+) -> Result<(), TimeServerError> {
+    let unknown: Vec<String> = arguments
+        .keys()
+        .filter(|name| !known.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    if !lenient {
+        let field = unknown.into_iter().next().unwrap_or_default();
+        return Err(TimeServerError::UnknownField {
+            tool: tool.to_string(),
+            suggestion: closest(&field, known).map(str::to_string),
+            field,
+        });
+    }
+    for name in unknown {
+        debug!(event = "tool.unknown_argument_dropped", tool = %tool, argument = %name);
+        arguments.remove(&name);
+    }
+    Ok(())
+}
+
+/// The declared name fewest edits away from `name`, if any is close
+pub fn closest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    let name = name.to_ascii_lowercase();
+    known
+        .iter()
+        .map(|&candidate| (levenshtein(&name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance (insertions, deletions, substitutions) between two strings
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(i + 1);
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("time_zone", "timezone"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("format", "format"), 0);
+    }
+
+    #[test]
+    fn test_unknown_argument_is_rejected_with_suggestion() {
+        let known = ["timezone", "precision", "pretty"];
+        let mut arguments = json!({"time_zone": "Asia/Tokyo"})
+            .as_object()
+            .unwrap()
+            .clone();
+
+        let error = check_arguments("get_time", &mut arguments, &known, false).unwrap_err();
+        assert_eq!(error.code(), "unknown_field");
+        assert_eq!(
+            error.details(),
+            json!({"tool": "get_time", "field": "time_zone", "suggestion": "timezone"})
+        );
+        assert!(error.to_string().contains("did you mean 'timezone'"));
+
+        assert_eq!(closest("Precison", &known), Some("precision"));
+        assert_eq!(closest("calendar", &known), None);
+    }
+
+    #[test]
+    fn test_lenient_mode_drops_unknown_arguments() {
+        let known = ["timezone"];
+        let mut arguments = json!({"timezone": "UTC", "tz": "Asia/Tokyo"})
+            .as_object()
+            .unwrap()
+            .clone();
+        check_arguments("get_time", &mut arguments, &known, true).unwrap();
+        assert_eq!(Value::Object(arguments), json!({"timezone": "UTC"}));
+    }
+
+    #[test]
+    fn test_schema_fields() {
+        let schema = json!({"type": "object", "properties": {"a": {}, "b": {}}});
+        assert_eq!(schema_fields(schema.as_object().unwrap()), ["a", "b"]);
+        assert!(schema_fields(json!({"type": "object"}).as_object().unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+// Which tools the server offers
+//
+// Some deployments cannot or must not run everything: a locked-down
+// container has no ntpq, and a sandbox may forbid spawning processes at all.
+// `DISABLE_NTP_TOOLS` drops the NTP and reference clock tools,
+// `DISABLE_SUBPROCESS` the ones that run a subprocess, and `DISABLED_TOOLS`
+// names individual tools. Disabled tools are removed from the routers when
+// the server is built, so they do not appear in tools/list; a client that
+// calls one anyway gets a `tool_disabled` error naming it.
+
+use crate::config::{ConfigError, EnvVars};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Tools that read ntpd, its shared memory, a reference clock or ptp4l
+pub const NTP_TOOLS: &[&str] = &[
+    "get_ntp_status",
+    "get_ntp_peers",
+    "get_ntp_history",
+    "get_pps_status",
+    "get_gps_status",
+    "get_ptp_status",
+];
+
+/// Tools that run `ntpq` or the linuxptp tools; get_signed_time asks ntpq
+/// for the NTP quality it signs
+pub const SUBPROCESS_TOOLS: &[&str] = &[
+    "get_ntp_status",
+    "get_ntp_peers",
+    "get_signed_time",
+    "get_ptp_status",
+];
+
+/// The set of disabled tools; serializes as a sorted list of names
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ToolPolicy {
+    disabled: BTreeSet<String>,
+}
+
+impl ToolPolicy {
+    /// A policy that disables exactly `tools`
+    pub fn disabling<I, S>(tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            disabled: tools.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Read `DISABLE_NTP_TOOLS`, `DISABLE_SUBPROCESS` and `DISABLED_TOOLS`.
+    /// Names in `DISABLED_TOOLS` must be tools of this server.
+    pub fn from_vars(env: &EnvVars) -> Result<Self, ConfigError> {
+        let mut disabled = BTreeSet::new();
+        if env.flag(&["DISABLE_NTP_TOOLS"])?.unwrap_or(false) {
+            disabled.extend(NTP_TOOLS.iter().map(|t| t.to_string()));
+        }
+        if env.flag(&["DISABLE_SUBPROCESS"])?.unwrap_or(false) {
+            disabled.extend(SUBPROCESS_TOOLS.iter().map(|t| t.to_string()));
+        }
+        if let Some((name, value)) = env.lookup(&["DISABLED_TOOLS"]) {
+            let known = crate::server_sdk::TimeServer::tool_names();
+            for tool in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                if !known.iter().any(|k| k == tool) {
+                    return Err(ConfigError::new(
+                        name,
+                        value,
+                        format!(
+                            "a comma-separated list of tool names ('{}' is not one)",
+                            tool
+                        ),
+                    ));
+                }
+                disabled.insert(tool.to_string());
+            }
+        }
+        Ok(Self { disabled })
+    }
+
+    /// The policy of the process environment, for the legacy handler that
+    /// reads it directly; an invalid value was already rejected at startup,
+    /// so it disables nothing here
+    pub fn from_env() -> Self {
+        Self::from_vars(&EnvVars::from_process()).unwrap_or_default()
+    }
+
+    pub fn is_disabled(&self, tool: &str) -> bool {
+        self.disabled.contains(tool)
+    }
+
+    /// Disabled tool names, sorted
+    pub fn disabled(&self) -> impl Iterator<Item = &str> {
+        self.disabled.iter().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(pairs: &[(&str, &str)]) -> Result<ToolPolicy, ConfigError> {
+        ToolPolicy::from_vars(&EnvVars::from_pairs(pairs.iter().copied()))
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_flags_and_list_combine() {
+        assert_eq!(policy(&[]).unwrap(), ToolPolicy::default());
+
+        let ntp = policy(&[("DISABLE_NTP_TOOLS", "true")]).unwrap();
+        assert!(ntp.is_disabled("get_pps_status"));
+        assert!(!ntp.is_disabled("get_signed_time"));
+
+        let combined = policy(&[
+            ("DISABLE_SUBPROCESS", "1"),
+            ("DISABLED_TOOLS", " get_time , get_ntp_peers,"),
+        ])
+        .unwrap();
+        assert_eq!(
+            combined.disabled().collect::<Vec<_>>(),
+            [
+                "get_ntp_peers",
+                "get_ntp_status",
+                "get_ptp_status",
+                "get_signed_time",
+                "get_time"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_tool_is_rejected() {
+        let error = policy(&[("DISABLED_TOOLS", "get_ntp_peer")]).unwrap_err();
+        assert_eq!(error.variable, "DISABLED_TOOLS");
+        assert!(error.expected.contains("get_ntp_peer"));
+    }
+}
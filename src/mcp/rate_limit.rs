@@ -0,0 +1,211 @@
+// How often one session may call tools
+//
+// An agent stuck in a loop can call get_time thousands of times a second and
+// starve the NTP refresh of the runtime. Each session (one `TimeServer`; the
+// stdio server has exactly one) gets a token bucket holding `burst` calls and
+// refilled at `per_second`; a call that finds it empty is refused at once with
+// `rate_limited` and the milliseconds until the next token, rather than
+// queued. The bucket is refilled from a monotonic `Instant` on each call, so
+// the happy path is an uncontended lock and some arithmetic. `MCP_RATE_LIMIT`
+// sets `<per second>[,<burst>]`, or `off`.
+
+use crate::config::{ConfigError, EnvVars};
+use crate::error::TimeServerError;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::debug;
+
+/// Calls per second a session is refilled with by default
+pub const DEFAULT_PER_SECOND: u32 = 100;
+/// Calls a session may make back to back by default
+pub const DEFAULT_BURST: u32 = 200;
+
+/// Refill rate and bucket size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_second: DEFAULT_PER_SECOND,
+            burst: DEFAULT_BURST,
+        }
+    }
+}
+
+impl RateLimit {
+    /// Read `MCP_RATE_LIMIT`; `None` when it is `off`. Without a burst the
+    /// bucket holds two seconds of calls.
+    pub fn from_vars(env: &EnvVars) -> Result<Option<Self>, ConfigError> {
+        let Some((name, value)) = env.lookup(&["MCP_RATE_LIMIT"]) else {
+            return Ok(Some(Self::default()));
+        };
+        Self::parse(value)
+            .ok_or_else(|| ConfigError::new(name, value, "<calls per second>[,<burst>] or off"))
+    }
+
+    fn parse(value: &str) -> Option<Option<Self>> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("off") {
+            return Some(None);
+        }
+        let (per_second, burst) = match value.split_once(',') {
+            Some((rate, burst)) => (rate.trim().parse().ok()?, burst.trim().parse().ok()?),
+            None => {
+                let rate: u32 = value.parse().ok()?;
+                (rate, rate.saturating_mul(2))
+            }
+        };
+        (per_second > 0 && burst > 0).then_some(Some(Self { per_second, burst }))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// One session's token bucket
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+    /// Reads the monotonic clock; tests substitute their own
+    clock: fn() -> Instant,
+}
+
+impl RateLimiter {
+    /// A full bucket for `limit`
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                refilled: Instant::now(),
+            }),
+            clock: Instant::now,
+        }
+    }
+
+    /// Read the time from `clock` instead of `Instant::now`; the bucket
+    /// starts full at its current reading
+    pub fn with_clock(mut self, clock: fn() -> Instant) -> Self {
+        self.clock = clock;
+        self.bucket
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .refilled = clock();
+        self
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take a token for one call to `tool`, or fail with `rate_limited`
+    pub fn admit(&self, tool: &str) -> Result<(), TimeServerError> {
+        self.take((self.clock)()).map_err(|retry_after_ms| {
+            debug!(event = "tool.rate_limited", tool = %tool, retry_after_ms);
+            TimeServerError::RateLimited { retry_after_ms }
+        })
+    }
+
+    /// Take a token at `now`; `Err` carries the milliseconds until one is back
+    fn take(&self, now: Instant) -> Result<(), u64> {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        let per_second = f64::from(self.limit.per_second);
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(f64::from(self.limit.burst));
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait_ms = (1.0 - bucket.tokens) / per_second * 1000.0;
+            Err((wait_ms.ceil() as u64).max(1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limit(pairs: &[(&str, &str)]) -> Result<Option<RateLimit>, ConfigError> {
+        RateLimit::from_vars(&EnvVars::from_pairs(pairs.iter().copied()))
+    }
+
+    #[test]
+    fn test_from_vars() {
+        assert_eq!(limit(&[]).unwrap(), Some(RateLimit::default()));
+        assert_eq!(limit(&[("MCP_RATE_LIMIT", "off")]).unwrap(), None);
+        assert_eq!(
+            limit(&[("MCP_RATE_LIMIT", "5")]).unwrap(),
+            Some(RateLimit {
+                per_second: 5,
+                burst: 10
+            })
+        );
+        assert_eq!(
+            limit(&[("MCP_RATE_LIMIT", " 20, 1 ")]).unwrap(),
+            Some(RateLimit {
+                per_second: 20,
+                burst: 1
+            })
+        );
+        for bad in ["", "0", "10,0", "fast", "1.5", "10,", "-1"] {
+            let error = limit(&[("MCP_RATE_LIMIT", bad)]).unwrap_err();
+            assert!(error.to_string().contains("MCP_RATE_LIMIT"), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(RateLimit {
+            per_second: 4,
+            burst: 3,
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.take(start).is_ok());
+        }
+        assert_eq!(limiter.take(start), Err(250));
+        // Half a token in, half the wait remains
+        assert_eq!(limiter.take(start + Duration::from_millis(125)), Err(125));
+        assert!(limiter.take(start + Duration::from_millis(250)).is_ok());
+        assert!(limiter.take(start + Duration::from_millis(250)).is_err());
+
+        // A long pause refills no more than the burst
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.take(later).is_ok());
+        }
+        assert!(limiter.take(later).is_err());
+    }
+
+    #[test]
+    fn test_admit_reports_retry_after() {
+        let limiter = RateLimiter::new(RateLimit {
+            per_second: 1000,
+            burst: 1,
+        })
+        .with_clock(|| {
+            static FROZEN: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+            *FROZEN.get_or_init(Instant::now)
+        });
+        assert!(limiter.admit("get_time").is_ok());
+        match limiter.admit("get_time") {
+            Err(TimeServerError::RateLimited { retry_after_ms }) => assert_eq!(retry_after_ms, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
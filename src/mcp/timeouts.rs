@@ -0,0 +1,199 @@
+// How long each tool may run
+//
+// Tools differ by orders of magnitude: get_unix_time answers in microseconds,
+// get_ntp_peers waits on ntpq. Each tool has a budget from `TOOL_BUDGETS_MS`
+// (or `DEFAULT_BUDGET_MS`), capped by the server-wide deadline
+// (`MCP_REQUEST_TIMEOUT_SECS`); `TOOL_TIMEOUT_<NAME>_MS` sets one tool's
+// budget outright, above the cap if need be. The rmcp server runs every call
+// through `enforce`, which fails with `deadline_exceeded` naming the budget,
+// and tools/list carries each budget as `x-timeout-ms` in the input schema so
+// clients can set their own timeouts to match.
+
+use crate::config::{ConfigError, EnvVars};
+use crate::error::TimeServerError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Budget of a tool `TOOL_BUDGETS_MS` does not list
+pub const DEFAULT_BUDGET_MS: u64 = 2_000;
+
+/// Tools that wait on a subprocess, the kernel or many other calls
+pub const TOOL_BUDGETS_MS: &[(&str, u64)] = &[
+    ("get_ntp_status", 10_000),
+    ("get_ntp_peers", 10_000),
+    ("get_signed_time", 10_000),
+    ("get_trusted_timestamp", 15_000),
+    ("get_ptp_status", 10_000),
+    ("check_time_sanity", 10_000),
+    ("get_clock_resolution", 10_000),
+    ("get_host_timezone", 5_000),
+    ("list_timezones", 5_000),
+    ("get_timezones_if_changed", 5_000),
+    ("run_selftest_benchmark", 30_000),
+];
+
+/// Schema keyword tools/list carries the budget under
+pub const SCHEMA_KEY: &str = "x-timeout-ms";
+
+const ENV_PREFIX: &str = "TOOL_TIMEOUT_";
+const ENV_SUFFIX: &str = "_MS";
+
+/// Budgets set with `TOOL_TIMEOUT_<NAME>_MS`, in milliseconds by tool name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ToolTimeouts {
+    overrides: BTreeMap<String, u64>,
+}
+
+impl ToolTimeouts {
+    /// Read every `TOOL_TIMEOUT_<NAME>_MS`; `<NAME>` is a tool of this server
+    /// in upper case and the value a positive number of milliseconds
+    pub fn from_vars(env: &EnvVars) -> Result<Self, ConfigError> {
+        let known = crate::server_sdk::TimeServer::tool_names();
+        let mut overrides = BTreeMap::new();
+        for (variable, value) in env.with_prefix(ENV_PREFIX) {
+            let tool = variable
+                .strip_prefix(ENV_PREFIX)
+                .and_then(|rest| rest.strip_suffix(ENV_SUFFIX))
+                .map(str::to_lowercase)
+                .filter(|tool| known.contains(tool))
+                .ok_or_else(|| ConfigError::new(variable, value, "TOOL_TIMEOUT_<tool name>_MS"))?;
+            let ms = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&ms: &u64| ms > 0)
+                .ok_or_else(|| {
+                    ConfigError::new(variable, value, "a positive number of milliseconds")
+                })?;
+            overrides.insert(tool, ms);
+        }
+        Ok(Self { overrides })
+    }
+
+    /// Set `tool`'s budget as `TOOL_TIMEOUT_<NAME>_MS` would
+    pub fn with_override(mut self, tool: impl Into<String>, budget: Duration) -> Self {
+        self.overrides
+            .insert(tool.into(), budget.as_millis() as u64);
+        self
+    }
+
+    /// How long `tool` may run when no call may run longer than `ceiling`
+    pub fn budget(&self, tool: &str, ceiling: Duration) -> Duration {
+        if let Some(&ms) = self.overrides.get(tool) {
+            return Duration::from_millis(ms);
+        }
+        let ms = TOOL_BUDGETS_MS
+            .iter()
+            .find(|(name, _)| *name == tool)
+            .map_or(DEFAULT_BUDGET_MS, |&(_, ms)| ms);
+        Duration::from_millis(ms).min(ceiling)
+    }
+}
+
+/// Run `call` for `tool`, failing with `DeadlineExceeded` once `budget` is spent
+pub async fn enforce<T, E>(
+    tool: &str,
+    budget: Duration,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    E: From<TimeServerError>,
+{
+    tokio::time::timeout(budget, call)
+        .await
+        .unwrap_or_else(|_| {
+            warn!(event = "tool.timeout", tool = %tool, budget = ?budget);
+            Err(TimeServerError::DeadlineExceeded {
+                tool: tool.to_string(),
+                budget_ms: budget.as_millis() as u64,
+            }
+            .into())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const CEILING: Duration = Duration::from_secs(30);
+
+    fn timeouts(pairs: &[(&str, &str)]) -> Result<ToolTimeouts, ConfigError> {
+        ToolTimeouts::from_vars(&EnvVars::from_pairs(pairs.iter().copied()))
+    }
+
+    #[test]
+    fn test_table_default_and_ceiling() {
+        let timeouts = ToolTimeouts::default();
+        assert_eq!(
+            timeouts.budget("get_unix_time", CEILING),
+            Duration::from_millis(DEFAULT_BUDGET_MS)
+        );
+        assert_eq!(
+            timeouts.budget("get_ntp_peers", CEILING),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            timeouts.budget("get_ntp_peers", Duration::from_secs(3)),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_env_override_changes_the_budget() {
+        let timeouts = timeouts(&[
+            ("TOOL_TIMEOUT_GET_NTP_PEERS_MS", "45000"),
+            ("TOOL_TIMEOUT_GET_UNIX_TIME_MS", " 50 "),
+        ])
+        .unwrap();
+        // An explicit budget is not capped
+        assert_eq!(
+            timeouts.budget("get_ntp_peers", CEILING),
+            Duration::from_secs(45)
+        );
+        assert_eq!(
+            timeouts.budget("get_unix_time", CEILING),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            serde_json::to_value(&timeouts).unwrap(),
+            serde_json::json!({"get_ntp_peers": 45000, "get_unix_time": 50})
+        );
+    }
+
+    #[test]
+    fn test_bad_overrides_are_rejected() {
+        let error = timeouts(&[("TOOL_TIMEOUT_GET_WEATHER_MS", "100")]).unwrap_err();
+        assert_eq!(error.variable, "TOOL_TIMEOUT_GET_WEATHER_MS");
+        let error = timeouts(&[("TOOL_TIMEOUT_GET_TIME_MS", "0")]).unwrap_err();
+        assert!(error.expected.contains("milliseconds"));
+        assert!(timeouts(&[("TOOL_TIMEOUT_GET_TIME", "100")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_call_fails_within_its_budget() {
+        let budget = Duration::from_millis(50);
+        let started = Instant::now();
+        let result: Result<(), TimeServerError> = enforce("get_ntp_peers", budget, async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert!(started.elapsed() < budget + Duration::from_millis(500));
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), "deadline_exceeded");
+        assert_eq!(
+            error.to_string(),
+            "Tool 'get_ntp_peers' exceeded its 50 ms budget"
+        );
+        assert_eq!(error.details()["budget_ms"], 50);
+
+        let fast: Result<u8, TimeServerError> = enforce("get_time", budget, async { Ok(7) }).await;
+        assert_eq!(fast.unwrap(), 7);
+    }
+}
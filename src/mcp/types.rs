@@ -1,5 +1,6 @@
 // MCP protocol types for time server
 
+use rmcp::model::ToolAnnotations;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -56,6 +57,10 @@ pub struct ServerCapabilities {
     pub prompts: Option<PromptsCapability>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resources: Option<ResourcesCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +91,9 @@ pub struct ToolDefinition {
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none", rename = "inputSchema")]
     pub input_schema: Option<Value>,
+    /// From [`crate::mcp::annotations::TOOL_HINTS`], as the rmcp server lists them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -196,4 +204,12 @@ impl McpResponse {
             id,
         }
     }
+
+    /// Attach `error.data`; no-op on success responses
+    pub fn with_error_data(mut self, data: Option<Value>) -> Self {
+        if let Some(error) = self.error.as_mut() {
+            error.data = data;
+        }
+        self
+    }
 }
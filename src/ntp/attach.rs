@@ -0,0 +1,313 @@
+// Attaching the NTP shared memory segment again while the server runs
+//
+// ntpd often starts after this server (at boot, or in a container whose
+// daemon comes up second) and recreates its segments when it restarts, so one
+// attach at startup is not enough. While no segment is attached, or the one
+// attached has held no valid sample for `NTP_SHM_STALE_SECS`, the status
+// sampler attaches the unit again: at most once per `NTP_SHM_RETRY_SECS`,
+// plus up to a quarter of that as jitter. The new handle replaces the old one
+// in a single swap, so a reader sees one or the other, and nothing on a
+// request path ever attaches.
+
+use super::shm::NtpShmInterface;
+use crate::error::TimeServerError;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SHM_RETRY_SECS: u64 = 30;
+pub const DEFAULT_SHM_STALE_SECS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShmRetryConfig {
+    /// Least time between two attach attempts
+    pub retry_secs: u64,
+    /// How long an attached segment may go without a valid sample before it
+    /// is attached again
+    pub stale_after_secs: u64,
+}
+
+impl Default for ShmRetryConfig {
+    fn default() -> Self {
+        Self {
+            retry_secs: DEFAULT_SHM_RETRY_SECS,
+            stale_after_secs: DEFAULT_SHM_STALE_SECS,
+        }
+    }
+}
+
+/// Attaches the segment; called once at startup and for every retry
+pub type ShmConnector = Arc<dyn Fn() -> Result<NtpShmInterface, TimeServerError> + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct AttachState {
+    attempts: u64,
+    last_error: Option<String>,
+    /// No attempt before this
+    next_attempt: Option<Instant>,
+    /// When the attached segment was first seen without a valid sample
+    invalid_since: Option<Instant>,
+}
+
+/// The SHM handle the clock reads, and what it takes to replace it
+pub struct ShmAttacher {
+    handle: RwLock<Option<NtpShmInterface>>,
+    /// None for a handle given from outside, which is never replaced
+    connect: Option<ShmConnector>,
+    config: ShmRetryConfig,
+    state: Mutex<AttachState>,
+}
+
+impl std::fmt::Debug for ShmAttacher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShmAttacher")
+            .field("handle", &self.current())
+            .field("config", &self.config)
+            .field("state", &*self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ShmAttacher {
+    /// Attach with `connect` now, and again whenever a retry is due
+    pub fn connect(connect: ShmConnector, config: ShmRetryConfig) -> Self {
+        let attacher = Self {
+            handle: RwLock::default(),
+            connect: Some(connect),
+            config,
+            state: Mutex::default(),
+        };
+        attacher.attempt(Instant::now());
+        attacher
+    }
+
+    /// Start from `shm`, the result of a first call to `connect`
+    pub fn attached(shm: NtpShmInterface, connect: ShmConnector, config: ShmRetryConfig) -> Self {
+        let retry = Duration::from_secs(config.retry_secs);
+        Self {
+            handle: RwLock::new(Some(shm)),
+            connect: Some(connect),
+            config,
+            state: Mutex::new(AttachState {
+                attempts: 1,
+                next_attempt: Some(Instant::now() + retry),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Always `shm`; nothing is retried
+    pub fn fixed(shm: Option<NtpShmInterface>) -> Self {
+        Self {
+            handle: RwLock::new(shm),
+            connect: None,
+            config: ShmRetryConfig::default(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// The handle attached now; cheap, it shares the attachment
+    pub fn current(&self) -> Option<NtpShmInterface> {
+        self.handle
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Attach attempts so far, including the first
+    pub fn attempts(&self) -> u64 {
+        self.state().attempts
+    }
+
+    /// Why the latest attempt failed; None once one succeeds
+    pub fn last_error(&self) -> Option<String> {
+        self.state().last_error.clone()
+    }
+
+    /// Attach again if the segment is missing or stale and a retry is due;
+    /// returns whether an attempt was made
+    pub fn refresh(&self) -> bool {
+        self.refresh_at(Instant::now())
+    }
+
+    pub(crate) fn refresh_at(&self, now: Instant) -> bool {
+        if self.connect.is_none() {
+            return false;
+        }
+        let healthy = self.current().map(|shm| shm.read_sample().is_some());
+        {
+            let mut state = self.state();
+            match healthy {
+                Some(true) => {
+                    state.invalid_since = None;
+                    return false;
+                }
+                Some(false) => {
+                    let since = *state.invalid_since.get_or_insert(now);
+                    if now.duration_since(since) < Duration::from_secs(self.config.stale_after_secs)
+                    {
+                        return false;
+                    }
+                }
+                None => {}
+            }
+            if state.next_attempt.is_some_and(|next| now < next) {
+                return false;
+            }
+        }
+        self.attempt(now);
+        true
+    }
+
+    fn attempt(&self, now: Instant) {
+        let Some(connect) = &self.connect else {
+            return;
+        };
+        let result = connect();
+        let retry = Duration::from_secs(self.config.retry_secs);
+        let mut state = self.state();
+        state.attempts += 1;
+        state.next_attempt = Some(now + retry + jitter(retry / 4));
+        match result {
+            Ok(shm) => {
+                tracing::info!(
+                    event = "ntp.shm.attached",
+                    unit = shm.unit(),
+                    attempts = state.attempts
+                );
+                *self
+                    .handle
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(shm);
+                state.last_error = None;
+                state.invalid_since = None;
+            }
+            Err(e) => {
+                tracing::debug!(
+                    event = "ntp.shm.attach_failed",
+                    attempts = state.attempts,
+                    error = %e
+                );
+                state.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, AttachState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Anywhere from zero to `max`, so servers started together do not retry in step
+fn jitter(max: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    max.mul_f64((random % 1024) as f64 / 1024.0)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::ntp::shm::ShmSample;
+
+    const RETRY: Duration = Duration::from_secs(DEFAULT_SHM_RETRY_SECS);
+
+    /// A key no ntpd uses, different for every test that asks
+    fn test_key(n: i32) -> libc::key_t {
+        0x4d430000 | ((std::process::id() as i32 & 0xfff) << 4) | n
+    }
+
+    fn opener(key: libc::key_t) -> ShmConnector {
+        Arc::new(move || NtpShmInterface::open(key, 0))
+    }
+
+    fn sample(n: i64) -> ShmSample {
+        ShmSample {
+            clock_sec: n,
+            clock_nsec: 0,
+            receive_sec: n,
+            receive_nsec: 0,
+            leap: 0,
+            precision: -20,
+        }
+    }
+
+    #[test]
+    fn test_segment_created_later_is_picked_up() {
+        let key = test_key(1);
+        let attacher = ShmAttacher::connect(opener(key), ShmRetryConfig::default());
+        let start = Instant::now();
+        assert!(attacher.current().is_none());
+        assert_eq!(attacher.attempts(), 1);
+        assert!(attacher.last_error().is_some());
+
+        let writer = NtpShmInterface::create(key, 0, true).unwrap();
+        writer.write_sample(&sample(1_700_000_000));
+
+        // Within the retry interval nothing is tried, however often asked
+        for secs in 0..RETRY.as_secs() {
+            assert!(!attacher.refresh_at(start + Duration::from_secs(secs)));
+        }
+        assert_eq!(attacher.attempts(), 1);
+
+        // Past the interval and its jitter the segment is found
+        assert!(attacher.refresh_at(start + RETRY + RETRY / 4 + Duration::from_millis(1)));
+        assert_eq!(attacher.attempts(), 2);
+        assert_eq!(attacher.last_error(), None);
+        let shm = attacher.current().unwrap();
+        assert_eq!(shm.read_sample().unwrap().clock_sec, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_failed_attempts_are_rate_limited() {
+        let attacher = ShmAttacher::connect(opener(test_key(2)), ShmRetryConfig::default());
+        let start = Instant::now();
+        let mut now = start;
+        while now < start + RETRY * 10 {
+            attacher.refresh_at(now);
+            now += Duration::from_secs(1);
+        }
+        // One attempt at startup, then one per interval at most
+        let attempts = attacher.attempts();
+        assert!((8..=11).contains(&attempts), "{} attempts", attempts);
+        assert!(attacher.current().is_none());
+    }
+
+    #[test]
+    fn test_stale_segment_is_replaced() {
+        let key = test_key(3);
+        let first = NtpShmInterface::create(key, 0, true).unwrap();
+        let attacher = ShmAttacher::connect(opener(key), ShmRetryConfig::default());
+        let start = Instant::now();
+        assert!(attacher.current().is_some());
+
+        // ntpd restarts: the old segment goes, a new one takes the key
+        drop(first);
+        let second = NtpShmInterface::create(key, 0, true).unwrap();
+        second.write_sample(&sample(42));
+
+        let stale_after = Duration::from_secs(DEFAULT_SHM_STALE_SECS);
+        assert!(!attacher.refresh_at(start + RETRY * 2));
+        assert!(!attacher.refresh_at(start + RETRY * 2 + stale_after / 2));
+        assert!(attacher.refresh_at(start + RETRY * 2 + stale_after));
+        assert_eq!(
+            attacher.current().unwrap().read_sample().unwrap().clock_sec,
+            42
+        );
+
+        // A valid sample needs nothing further
+        assert!(!attacher.refresh_at(start + RETRY * 100));
+        assert_eq!(attacher.attempts(), 2);
+    }
+
+    #[test]
+    fn test_fixed_handle_is_never_replaced() {
+        let attacher = ShmAttacher::fixed(None);
+        assert!(!attacher.refresh_at(Instant::now() + RETRY * 10));
+        assert_eq!(attacher.attempts(), 0);
+    }
+}
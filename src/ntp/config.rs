@@ -1,4 +1,10 @@
 // NTP Configuration
+use super::attach::ShmRetryConfig;
+use super::history::{NtpHistoryConfig, DEFAULT_NTP_HISTORY_INTERVAL_SECS};
+use super::query::NtpQueryConfig;
+use super::supervisor::SupervisorConfig;
+use crate::config::{ConfigError, EnvVars};
+use crate::ptp::PtpConfig;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,9 +12,24 @@ pub struct NtpConfig {
     pub servers: Vec<NtpServer>,
     pub pps: Option<PpsConfig>,
     pub gps: Option<GpsConfig>,
+    /// PTP status monitoring (ENABLE_PTP=yes)
+    #[serde(default)]
+    pub ptp: Option<PtpConfig>,
     pub stratum: Option<u8>,
     pub drift_file: String,
     pub stats_dir: String,
+    /// Limits on concurrent `ntpq` runs
+    #[serde(default)]
+    pub query: NtpQueryConfig,
+    /// Status samples kept for get_ntp_history
+    #[serde(default)]
+    pub history: NtpHistoryConfig,
+    /// ntpd run as a child process (MANAGE_NTPD=true)
+    #[serde(default)]
+    pub supervisor: Option<SupervisorConfig>,
+    /// When a missing or stale SHM segment is attached again
+    #[serde(default)]
+    pub shm_retry: ShmRetryConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +53,14 @@ pub struct GpsConfig {
     pub enabled: bool,
     pub device: String,
     pub baud_rate: u32,
+    /// SHM unit the GPS time is published to (ntpd creates units 0-1
+    /// root-only, so 2-3 are the usable ones for an unprivileged writer)
+    #[serde(default = "default_gps_shm_unit")]
+    pub shm_unit: u8,
+}
+
+fn default_gps_shm_unit() -> u8 {
+    2
 }
 
 impl Default for NtpConfig {
@@ -55,19 +84,34 @@ impl Default for NtpConfig {
             ],
             pps: None,
             gps: None,
+            ptp: None,
             stratum: Some(10),
             drift_file: "/var/lib/ntp/ntp.drift".to_string(),
             stats_dir: "/var/log/ntpstats".to_string(),
+            query: NtpQueryConfig::default(),
+            history: NtpHistoryConfig::default(),
+            supervisor: None,
+            shm_retry: ShmRetryConfig::default(),
         }
     }
 }
 
 impl NtpConfig {
+    /// Read the process environment; unusable values fall back to defaults
     pub fn from_env() -> Self {
+        Self::from_vars(&EnvVars::from_process()).unwrap_or_else(|e| {
+            tracing::warn!("{}; using NTP defaults", e);
+            Self::default()
+        })
+    }
+
+    /// `NTP_SERVERS`, `ENABLE_PPS`/`PPS_*`, `ENABLE_GPS`/`GPS_*`, `ENABLE_PTP`, `LOCAL_STRATUM`,
+    /// `NTP_QUERY_*`, `NTP_HISTORY_*`, `NTP_SHM_*` and `MANAGE_NTPD`/`NTPD_*`
+    pub fn from_vars(env: &EnvVars) -> Result<Self, ConfigError> {
         let mut config = Self::default();
 
         // Parse NTP servers from environment
-        if let Ok(servers) = std::env::var("NTP_SERVERS") {
+        if let Some(servers) = env.string(&["NTP_SERVERS"]) {
             config.servers = servers
                 .split(',')
                 .map(|s| NtpServer {
@@ -81,36 +125,87 @@ impl NtpConfig {
         }
 
         // Parse PPS configuration
-        if let Ok(pps_enabled) = std::env::var("ENABLE_PPS") {
-            if pps_enabled == "yes" {
-                config.pps = Some(PpsConfig {
-                    enabled: true,
-                    device: "/dev/pps0".to_string(),
-                    gpio_pin: std::env::var("PPS_GPIO").ok().and_then(|s| s.parse().ok()),
-                });
-            }
+        if env.string(&["ENABLE_PPS"]).as_deref() == Some("yes") {
+            config.pps = Some(PpsConfig {
+                enabled: true,
+                device: env
+                    .string(&["PPS_DEVICE"])
+                    .unwrap_or_else(|| "/dev/pps0".to_string()),
+                gpio_pin: env.parse(&["PPS_GPIO"], "a GPIO pin number")?,
+            });
         }
 
         // Parse GPS configuration
-        if let Ok(gps_enabled) = std::env::var("ENABLE_GPS") {
-            if gps_enabled == "yes" {
-                config.gps = Some(GpsConfig {
-                    enabled: true,
-                    device: std::env::var("GPS_DEVICE")
-                        .unwrap_or_else(|_| "/dev/ttyAMA0".to_string()),
-                    baud_rate: std::env::var("GPS_BAUD")
-                        .ok()
-                        .and_then(|s| s.parse().ok())
-                        .unwrap_or(9600),
-                });
-            }
+        if env.string(&["ENABLE_GPS"]).as_deref() == Some("yes") {
+            config.gps = Some(GpsConfig {
+                enabled: true,
+                device: env
+                    .string(&["GPS_DEVICE"])
+                    .unwrap_or_else(|| "/dev/ttyAMA0".to_string()),
+                baud_rate: env.parse(&["GPS_BAUD"], "a baud rate")?.unwrap_or(9600),
+                shm_unit: env
+                    .parse(&["GPS_SHM_UNIT"], "an SHM unit (0-255)")?
+                    .unwrap_or_else(default_gps_shm_unit),
+            });
+        }
+
+        if env.string(&["ENABLE_PTP"]).as_deref() == Some("yes") {
+            config.ptp = Some(PtpConfig { enabled: true });
         }
 
         // Parse stratum
-        if let Ok(stratum) = std::env::var("LOCAL_STRATUM") {
-            config.stratum = stratum.parse().ok();
+        if let Some(stratum) = env.parse(&["LOCAL_STRATUM"], "a stratum (0-15)")? {
+            config.stratum = Some(stratum);
+        }
+
+        if let Some((name, value)) = env.lookup(&["NTP_QUERY_CONCURRENCY"]) {
+            config.query.concurrency = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n: &usize| n > 0)
+                .ok_or_else(|| ConfigError::new(name, value, "a positive number of processes"))?;
+        }
+        if let Some(wait) = env.parse(&["NTP_QUERY_QUEUE_WAIT_MS"], "a number of milliseconds")? {
+            config.query.queue_wait_ms = wait;
+        }
+
+        let interval = match env.lookup(&["NTP_HISTORY_INTERVAL_SECS"]) {
+            Some((name, value)) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&secs: &u64| secs > 0)
+                .ok_or_else(|| ConfigError::new(name, value, "a positive number of seconds"))?,
+            None => DEFAULT_NTP_HISTORY_INTERVAL_SECS,
+        };
+        config.history = NtpHistoryConfig::with_interval(interval);
+        if let Some((name, value)) = env.lookup(&["NTP_HISTORY_CAPACITY"]) {
+            config.history.capacity = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n: &usize| n > 0)
+                .ok_or_else(|| ConfigError::new(name, value, "a positive number of samples"))?;
+        }
+        config.history.file = env
+            .string(&["NTP_HISTORY_FILE"])
+            .filter(|path| !path.is_empty());
+        config.supervisor = SupervisorConfig::from_vars(env)?;
+        for (var, secs) in [
+            ("NTP_SHM_RETRY_SECS", &mut config.shm_retry.retry_secs),
+            ("NTP_SHM_STALE_SECS", &mut config.shm_retry.stale_after_secs),
+        ] {
+            if let Some((name, value)) = env.lookup(&[var]) {
+                *secs = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&secs: &u64| secs > 0)
+                    .ok_or_else(|| ConfigError::new(name, value, "a positive number of seconds"))?;
+            }
         }
 
-        config
+        Ok(config)
     }
 }
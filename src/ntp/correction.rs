@@ -0,0 +1,263 @@
+// NTP offset correction for returned timestamps
+//
+// ntpq reports `offset` as reference minus local (theta in RFC 5905): positive
+// means the system clock is behind the reference, so the corrected time is
+// `system + offset`. Measurements older than the configured age are not used.
+
+use super::{NtpStatus, NtpSyncedClock};
+pub use crate::time::correction::{Corrected, Correction};
+#[cfg(test)]
+use crate::time::TimeSource;
+use crate::warnings::WarningCode;
+use chrono::{DateTime, Utc};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Offsets older than this are considered stale and not applied
+pub const DEFAULT_MAX_OFFSET_AGE_SECS: u64 = 300;
+
+/// Re-query ntpd once the cached offset is this old
+const REFRESH_INTERVAL: Duration = Duration::from_secs(64);
+
+/// A measured offset and when it was taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OffsetSample {
+    /// Reference minus local, in milliseconds
+    pub offset_ms: f64,
+    pub measured_at: Instant,
+}
+
+/// Shift `time` by an offset given in (fractional) milliseconds
+pub fn apply_offset(time: DateTime<Utc>, offset_ms: f64) -> DateTime<Utc> {
+    time + chrono::Duration::nanoseconds((offset_ms * 1e6).round() as i64)
+}
+
+/// Most recent NTP offset, shared by every session
+#[derive(Debug)]
+pub struct OffsetCache {
+    sample: Mutex<Option<OffsetSample>>,
+    max_age: Duration,
+}
+
+impl OffsetCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            sample: Mutex::new(None),
+            max_age,
+        }
+    }
+
+    /// Staleness limit from `NTP_OFFSET_MAX_AGE_SECS`
+    pub fn from_env() -> Self {
+        let secs = std::env::var("NTP_OFFSET_MAX_AGE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_OFFSET_AGE_SECS);
+        Self::new(Duration::from_secs(secs))
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn sample(&self) -> Option<OffsetSample> {
+        *self
+            .sample
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Store the offset from `status`; unsynchronized readings are ignored
+    pub fn record(&self, status: &NtpStatus, measured_at: Instant) {
+        if !status.synced {
+            return;
+        }
+        *self
+            .sample
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(OffsetSample {
+            offset_ms: status.offset_ms,
+            measured_at,
+        });
+    }
+
+    /// Apply the cached offset to `time` if it is fresh enough at `now`
+    pub fn correct(&self, time: DateTime<Utc>, now: Instant) -> (DateTime<Utc>, Correction) {
+        let Some(sample) = self.sample() else {
+            return (
+                time,
+                Correction::skipped(None, "no NTP offset measurement available".to_string())
+                    .fell_back(WarningCode::OffsetUnavailable),
+            );
+        };
+
+        let age = now.saturating_duration_since(sample.measured_at);
+        let age_s = Some(age.as_secs_f64());
+        if age > self.max_age {
+            let note = format!(
+                "NTP offset is stale ({:.0}s old, limit {}s)",
+                age.as_secs_f64(),
+                self.max_age.as_secs()
+            );
+            return (
+                time,
+                Correction::skipped(age_s, note).fell_back(WarningCode::OffsetStale),
+            );
+        }
+
+        (
+            apply_offset(time, sample.offset_ms),
+            Correction {
+                corrected: true,
+                correction_applied_ms: sample.offset_ms,
+                offset_age_s: age_s,
+                correction_note: None,
+                fallback: None,
+            },
+        )
+    }
+
+    /// Time left at `now` before the cached offset is due for a refresh;
+    /// zero when there is none
+    pub fn refresh_due_in(&self, now: Instant) -> Duration {
+        self.sample()
+            .map(|s| REFRESH_INTERVAL.saturating_sub(now.saturating_duration_since(s.measured_at)))
+            .unwrap_or_default()
+    }
+
+    /// Query ntpd through `clock` when the cached offset is missing or due for refresh
+    pub async fn refresh_if_stale(&self, clock: &NtpSyncedClock) {
+        let due = self
+            .sample()
+            .is_none_or(|s| s.measured_at.elapsed() >= REFRESH_INTERVAL);
+        if !due || NtpSyncedClock::is_container_environment() {
+            return;
+        }
+
+        match clock.get_status_async().await {
+            Ok(status) => self.record(&status, Instant::now()),
+            Err(e) => debug!(event = "ntp.offset_refresh_failed", error = %e),
+        }
+    }
+}
+
+static GLOBAL: OnceLock<OffsetCache> = OnceLock::new();
+
+/// The process-wide offset cache
+pub fn global() -> &'static OffsetCache {
+    GLOBAL.get_or_init(OffsetCache::from_env)
+}
+
+/// Whether tools correct by default (`CORRECTED_TIME_DEFAULT=true`)
+pub fn enabled_by_default() -> bool {
+    std::env::var("CORRECTED_TIME_DEFAULT")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1" || v.eq_ignore_ascii_case("yes"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(synced: bool, offset_ms: f64) -> NtpStatus {
+        NtpStatus {
+            synced,
+            offset_ms,
+            stratum: 2,
+            precision: -20,
+            root_delay: 0.0,
+            root_dispersion: 0.0,
+            shm_valid: false,
+            pps_enabled: false,
+            ..Default::default()
+        }
+    }
+
+    fn time(secs: i64, nanos: u32) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, nanos).unwrap()
+    }
+
+    #[test]
+    fn test_positive_offset_means_clock_is_behind() {
+        let cache = OffsetCache::new(Duration::from_secs(300));
+        let t0 = Instant::now();
+        cache.record(&status(true, 80.0), t0);
+
+        let (corrected, correction) =
+            cache.correct(time(1_000, 950_000_000), t0 + Duration::from_secs(10));
+        assert_eq!(corrected.timestamp(), 1_001);
+        assert_eq!(corrected.timestamp_subsec_nanos(), 30_000_000);
+        assert!(correction.corrected);
+        assert_eq!(correction.correction_applied_ms, 80.0);
+        assert_eq!(correction.offset_age_s, Some(10.0));
+    }
+
+    #[test]
+    fn test_negative_fractional_offset_borrows_a_second() {
+        let cache = OffsetCache::new(Duration::from_secs(300));
+        let t0 = Instant::now();
+        cache.record(&status(true, -80.5), t0);
+
+        let (corrected, _) = cache.correct(time(100, 50_000_000), t0);
+        assert_eq!(corrected.timestamp(), 99);
+        assert_eq!(corrected.timestamp_subsec_nanos(), 969_500_000);
+    }
+
+    #[test]
+    fn test_stale_offset_is_not_applied() {
+        let cache = OffsetCache::new(Duration::from_secs(60));
+        let t0 = Instant::now();
+        cache.record(&status(true, 80.0), t0);
+
+        let (at_limit, correction) = cache.correct(time(1_000, 0), t0 + Duration::from_secs(60));
+        assert!(correction.corrected);
+        assert_eq!(at_limit.timestamp_subsec_millis(), 80);
+
+        let (stale, correction) = cache.correct(time(1_000, 0), t0 + Duration::from_secs(61));
+        assert_eq!(stale, time(1_000, 0));
+        assert!(!correction.corrected);
+        assert_eq!(correction.correction_applied_ms, 0.0);
+        assert_eq!(correction.offset_age_s, Some(61.0));
+        let warning = correction.warning().unwrap();
+        assert_eq!(warning.code, WarningCode::OffsetStale);
+        assert_eq!(warning.field.as_deref(), Some("corrected"));
+        assert!(correction.correction_note.unwrap().contains("stale"));
+    }
+
+    #[test]
+    fn test_refresh_due_in_counts_down_from_the_sample() {
+        let cache = OffsetCache::new(Duration::from_secs(300));
+        let t0 = Instant::now();
+        assert_eq!(cache.refresh_due_in(t0), Duration::ZERO);
+
+        cache.record(&status(true, 1.0), t0);
+        assert_eq!(
+            cache.refresh_due_in(t0 + Duration::from_secs(4)),
+            REFRESH_INTERVAL - Duration::from_secs(4)
+        );
+        assert_eq!(
+            cache.refresh_due_in(t0 + REFRESH_INTERVAL * 2),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_unsynced_or_missing_measurement() {
+        let cache = OffsetCache::new(Duration::from_secs(60));
+        cache.record(&status(false, 500.0), Instant::now());
+        assert_eq!(cache.sample(), None);
+
+        let (unchanged, correction) = cache.correct(time(5, 0), Instant::now());
+        assert_eq!(unchanged, time(5, 0));
+        assert!(!correction.corrected);
+        assert_eq!(correction.offset_age_s, None);
+        assert_eq!(
+            correction.warning().map(|w| w.code),
+            Some(WarningCode::OffsetUnavailable)
+        );
+        assert!(Correction::from_reference(TimeSource::Gps)
+            .warning()
+            .is_none());
+    }
+}
@@ -0,0 +1,818 @@
+// GPS NMEA reader feeding an NTP SHM refclock unit
+//
+// Parsing is pure (`parse_sentence`, `GpsTracker`) so it can be checked against
+// captured sentences. The serial port is any `AsyncBufRead` and the SHM segment
+// is a `RefclockSink`, so the reader loop runs in tests without hardware.
+
+use super::config::{GpsConfig, NtpConfig};
+use super::shm::{NtpShmInterface, ShmSample};
+use crate::error::TimeServerError;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+use std::io;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+use tracing::{debug, info, warn};
+
+/// NMEA 0183 caps sentences at 82 characters; anything far beyond is line noise
+const MAX_SENTENCE_BYTES: u64 = 256;
+
+/// NMEA time arrives a few hundred milliseconds after the second it names
+const NMEA_PRECISION: i32 = -1;
+
+/// Pause before reopening a serial device that failed or went away
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum NmeaError {
+    #[error("sentence does not start with '$'")]
+    MissingStart,
+
+    #[error("missing checksum")]
+    MissingChecksum,
+
+    #[error("checksum mismatch: sentence says {expected:02X}, computed {computed:02X}")]
+    BadChecksum { expected: u8, computed: u8 },
+
+    #[error("malformed {field} field: {value:?}")]
+    Malformed { field: &'static str, value: String },
+
+    #[error("unsupported sentence type {0}")]
+    Unsupported(String),
+}
+
+/// Receiver fix dimension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum FixMode {
+    #[default]
+    #[serde(rename = "no_fix")]
+    NoFix,
+    #[serde(rename = "2d")]
+    Fix2D,
+    #[serde(rename = "3d")]
+    Fix3D,
+}
+
+/// Recommended minimum data: time, date and validity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rmc {
+    pub time: Option<NaiveTime>,
+    pub date: Option<NaiveDate>,
+    /// Status `A`; `V` means the receiver has no usable fix
+    pub valid: bool,
+}
+
+/// Fix data: quality and satellites used
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gga {
+    pub time: Option<NaiveTime>,
+    /// 0 = invalid, 1 = GPS, 2 = DGPS, ...
+    pub quality: u8,
+    pub satellites: Option<u8>,
+    pub altitude_m: Option<f64>,
+}
+
+/// Time and date with a four-digit year
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zda {
+    pub time: Option<NaiveTime>,
+    pub date: Option<NaiveDate>,
+}
+
+/// DOP and active satellites: carries the 2D/3D fix mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gsa {
+    pub mode: FixMode,
+    pub satellites: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NmeaSentence {
+    Rmc(Rmc),
+    Gga(Gga),
+    Zda(Zda),
+    Gsa(Gsa),
+}
+
+impl Rmc {
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        Some(self.date?.and_time(self.time?).and_utc())
+    }
+}
+
+impl Zda {
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        Some(self.date?.and_time(self.time?).and_utc())
+    }
+}
+
+/// Parse one NMEA sentence, validating its checksum
+pub fn parse_sentence(line: &str) -> Result<NmeaSentence, NmeaError> {
+    let line = line.trim();
+    let body = line.strip_prefix('$').ok_or(NmeaError::MissingStart)?;
+    let (body, checksum) = body.rsplit_once('*').ok_or(NmeaError::MissingChecksum)?;
+
+    let expected = match checksum.len() {
+        2 => u8::from_str_radix(checksum, 16).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| malformed("checksum", checksum))?;
+    let computed = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    if expected != computed {
+        return Err(NmeaError::BadChecksum { expected, computed });
+    }
+
+    let mut fields = body.split(',');
+    let address = fields.next().unwrap_or_default();
+    let fields: Vec<&str> = fields.collect();
+    let field = |i: usize| fields.get(i).copied().unwrap_or("");
+
+    // Two-character talker (GP, GN, GL, GA, BD, ...) then the sentence type
+    let kind = match address.len() {
+        5 if !address.starts_with('P') => &address[2..],
+        _ => return Err(NmeaError::Unsupported(address.to_string())),
+    };
+
+    match kind {
+        "RMC" => Ok(NmeaSentence::Rmc(Rmc {
+            time: parse_time(field(0))?,
+            valid: field(1) == "A",
+            date: parse_ddmmyy(field(8))?,
+        })),
+        "GGA" => Ok(NmeaSentence::Gga(Gga {
+            time: parse_time(field(0))?,
+            quality: parse_optional(field(5), "quality")?.unwrap_or(0),
+            satellites: parse_optional(field(6), "satellites")?,
+            altitude_m: parse_optional(field(8), "altitude")?,
+        })),
+        "ZDA" => Ok(NmeaSentence::Zda(Zda {
+            time: parse_time(field(0))?,
+            date: parse_zda_date(field(1), field(2), field(3))?,
+        })),
+        "GSA" => Ok(NmeaSentence::Gsa(Gsa {
+            mode: match field(1) {
+                "2" => FixMode::Fix2D,
+                "3" => FixMode::Fix3D,
+                _ => FixMode::NoFix,
+            },
+            satellites: (2..14).filter(|&i| !field(i).is_empty()).count() as u8,
+        })),
+        other => Err(NmeaError::Unsupported(other.to_string())),
+    }
+}
+
+fn malformed(field: &'static str, value: &str) -> NmeaError {
+    NmeaError::Malformed {
+        field,
+        value: value.to_string(),
+    }
+}
+
+fn parse_optional<T: std::str::FromStr>(
+    value: &str,
+    field: &'static str,
+) -> Result<Option<T>, NmeaError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(|_| malformed(field, value))
+}
+
+/// `hhmmss[.sss]`; a second of 60 is kept as a chrono leap second
+fn parse_time(value: &str) -> Result<Option<NaiveTime>, NmeaError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let err = || malformed("time", value);
+
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if whole.len() != 6 || !whole.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err());
+    }
+    let h: u32 = whole[0..2].parse().map_err(|_| err())?;
+    let m: u32 = whole[2..4].parse().map_err(|_| err())?;
+    let s: u32 = whole[4..6].parse().map_err(|_| err())?;
+
+    let mut nanos = 0u32;
+    if !fraction.is_empty() {
+        if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+        nanos = fraction.parse::<u32>().map_err(|_| err())? * 10u32.pow(9 - fraction.len() as u32);
+    }
+
+    let time = match s {
+        60 => NaiveTime::from_hms_nano_opt(h, m, 59, 1_000_000_000 + nanos),
+        _ => NaiveTime::from_hms_nano_opt(h, m, s, nanos),
+    };
+    time.map(Some).ok_or_else(err)
+}
+
+/// RMC `ddmmyy`; two-digit years below 80 are 20xx
+fn parse_ddmmyy(value: &str) -> Result<Option<NaiveDate>, NmeaError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let err = || malformed("date", value);
+    if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err());
+    }
+    let d: u32 = value[0..2].parse().map_err(|_| err())?;
+    let m: u32 = value[2..4].parse().map_err(|_| err())?;
+    let yy: i32 = value[4..6].parse().map_err(|_| err())?;
+    let year = if yy < 80 { 2000 + yy } else { 1900 + yy };
+    NaiveDate::from_ymd_opt(year, m, d)
+        .map(Some)
+        .ok_or_else(err)
+}
+
+fn parse_zda_date(day: &str, month: &str, year: &str) -> Result<Option<NaiveDate>, NmeaError> {
+    if day.is_empty() || month.is_empty() || year.is_empty() {
+        return Ok(None);
+    }
+    let err = || malformed("date", &format!("{},{},{}", day, month, year));
+    let d: u32 = day.parse().map_err(|_| err())?;
+    let m: u32 = month.parse().map_err(|_| err())?;
+    let y: i32 = year.parse().map_err(|_| err())?;
+    NaiveDate::from_ymd_opt(y, m, d).map(Some).ok_or_else(err)
+}
+
+/// A GPS time paired with the system time it was received at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsSample {
+    pub time: DateTime<Utc>,
+    pub received: DateTime<Utc>,
+}
+
+impl GpsSample {
+    pub fn to_shm(&self) -> ShmSample {
+        ShmSample {
+            clock_sec: self.time.timestamp(),
+            clock_nsec: self.time.timestamp_subsec_nanos(),
+            receive_sec: self.received.timestamp(),
+            receive_nsec: self.received.timestamp_subsec_nanos(),
+            leap: 0,
+            precision: NMEA_PRECISION,
+        }
+    }
+}
+
+/// Folds a stream of sentences into fix state and time samples
+#[derive(Debug, Default)]
+pub struct GpsTracker {
+    fix: FixMode,
+    /// Whether a GSA has been seen; receivers without it get a fix mode
+    /// inferred from GGA
+    has_gsa: bool,
+    satellites: Option<u8>,
+    last_time: Option<DateTime<Utc>>,
+    last_sample: Option<GpsSample>,
+    last_sentence_at: Option<DateTime<Utc>>,
+    sentences: u64,
+    checksum_errors: u64,
+    malformed: u64,
+}
+
+impl GpsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one line; returns a sample when it carries a new, trusted time
+    pub fn ingest(&mut self, line: &str, received: DateTime<Utc>) -> Option<GpsSample> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let sentence = match parse_sentence(line) {
+            Ok(sentence) => sentence,
+            Err(NmeaError::Unsupported(_)) => return None,
+            Err(e) => {
+                debug!(event = "gps.bad_sentence", error = %e);
+                match e {
+                    NmeaError::BadChecksum { .. } => self.checksum_errors += 1,
+                    _ => self.malformed += 1,
+                }
+                return None;
+            }
+        };
+        self.sentences += 1;
+        self.last_sentence_at = Some(received);
+
+        let time = match sentence {
+            NmeaSentence::Rmc(rmc) => {
+                if !rmc.valid {
+                    self.fix = FixMode::NoFix;
+                    return None;
+                }
+                rmc.timestamp()
+            }
+            NmeaSentence::Gga(gga) => {
+                self.satellites = gga.satellites;
+                if gga.quality == 0 {
+                    self.fix = FixMode::NoFix;
+                } else if !self.has_gsa {
+                    self.fix = match gga.altitude_m {
+                        Some(_) => FixMode::Fix3D,
+                        None => FixMode::Fix2D,
+                    };
+                }
+                None
+            }
+            NmeaSentence::Gsa(gsa) => {
+                self.has_gsa = true;
+                self.fix = gsa.mode;
+                None
+            }
+            // ZDA has no validity flag, so only trust it alongside a fix
+            NmeaSentence::Zda(zda) if self.fix != FixMode::NoFix => zda.timestamp(),
+            NmeaSentence::Zda(_) => None,
+        }?;
+
+        // RMC and ZDA usually both name the same second
+        if self.last_time == Some(time) {
+            return None;
+        }
+        self.last_time = Some(time);
+        self.last_sample = Some(GpsSample { time, received });
+        self.last_sample
+    }
+
+    /// The most recent trusted sample
+    pub fn last_sample(&self) -> Option<GpsSample> {
+        self.last_sample
+    }
+
+    pub fn fix(&self) -> FixMode {
+        self.fix
+    }
+}
+
+/// Destination for GPS samples (an NTP SHM unit in production)
+pub trait RefclockSink: Send {
+    fn publish(&mut self, sample: &GpsSample) -> Result<(), TimeServerError>;
+}
+
+impl RefclockSink for NtpShmInterface {
+    fn publish(&mut self, sample: &GpsSample) -> Result<(), TimeServerError> {
+        self.write_sample(&sample.to_shm());
+        Ok(())
+    }
+}
+
+/// Reported by the `get_gps_status` tool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GpsStatus {
+    pub enabled: bool,
+    pub device: Option<String>,
+    pub connected: bool,
+    pub fix: FixMode,
+    pub satellites_used: Option<u8>,
+    /// Most recent time taken from the receiver (RFC 3339)
+    pub last_gps_time: Option<String>,
+    pub last_sentence_at: Option<String>,
+    pub sentences: u64,
+    pub checksum_errors: u64,
+    pub malformed: u64,
+    pub shm_unit: Option<u8>,
+    pub samples_published: u64,
+    pub last_error: Option<String>,
+}
+
+impl GpsStatus {
+    /// Status when GPS support is not enabled
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            device: None,
+            connected: false,
+            fix: FixMode::NoFix,
+            satellites_used: None,
+            last_gps_time: None,
+            last_sentence_at: None,
+            sentences: 0,
+            checksum_errors: 0,
+            malformed: 0,
+            shm_unit: None,
+            samples_published: 0,
+            last_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    tracker: GpsTracker,
+    device: String,
+    shm_unit: Option<u8>,
+    connected: bool,
+    samples_published: u64,
+    last_error: Option<String>,
+}
+
+/// Shared view of the reader task's state
+#[derive(Debug, Clone, Default)]
+pub struct GpsMonitor {
+    state: Arc<Mutex<MonitorState>>,
+}
+
+impl GpsMonitor {
+    pub fn new(device: impl Into<String>, shm_unit: Option<u8>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MonitorState {
+                device: device.into(),
+                shm_unit,
+                ..Default::default()
+            })),
+        }
+    }
+
+    pub fn status(&self) -> GpsStatus {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let tracker = &state.tracker;
+        GpsStatus {
+            enabled: true,
+            device: Some(state.device.clone()),
+            connected: state.connected,
+            fix: tracker.fix,
+            satellites_used: tracker.satellites,
+            last_gps_time: tracker.last_time.map(rfc3339),
+            last_sentence_at: tracker.last_sentence_at.map(rfc3339),
+            sentences: tracker.sentences,
+            checksum_errors: tracker.checksum_errors,
+            malformed: tracker.malformed,
+            shm_unit: state.shm_unit,
+            samples_published: state.samples_published,
+            last_error: state.last_error.clone(),
+        }
+    }
+
+    /// The most recent trusted sample from the receiver
+    pub fn last_sample(&self) -> Option<GpsSample> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .tracker
+            .last_sample()
+    }
+
+    fn ingest(&self, line: &str, received: DateTime<Utc>) -> Option<GpsSample> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .tracker
+            .ingest(line, received)
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .connected = connected;
+    }
+
+    fn record_published(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .samples_published += 1;
+    }
+
+    fn record_error(&self, error: impl Into<String>) {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .last_error = Some(error.into());
+    }
+}
+
+fn rfc3339(dt: DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Read NMEA lines from `source` until EOF, publishing each new time to `sink`
+pub async fn read_nmea<R, S>(mut source: R, sink: &mut S, monitor: &GpsMonitor) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    S: RefclockSink + ?Sized,
+{
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = (&mut source)
+            .take(MAX_SENTENCE_BYTES)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if n == 0 {
+            return Ok(());
+        }
+        let received = Utc::now();
+
+        let text = String::from_utf8_lossy(&line);
+        if let Some(sample) = monitor.ingest(&text, received) {
+            match sink.publish(&sample) {
+                Ok(()) => monitor.record_published(),
+                Err(e) => {
+                    warn!(event = "gps.publish_failed", error = %e);
+                    monitor.record_error(e.to_string());
+                }
+            }
+        }
+    }
+}
+
+fn open_serial(device: &str, baud_rate: u32) -> io::Result<tokio_serial::SerialStream> {
+    use tokio_serial::SerialPortBuilderExt;
+
+    tokio_serial::new(device, baud_rate)
+        .open_native_async()
+        .map_err(io::Error::from)
+}
+
+/// Reopen the serial device whenever it fails, forever
+async fn run_serial(config: GpsConfig, mut shm: NtpShmInterface, monitor: GpsMonitor) {
+    loop {
+        match open_serial(&config.device, config.baud_rate) {
+            Ok(port) => {
+                info!(event = "gps.connected", device = %config.device);
+                monitor.set_connected(true);
+                let result = read_nmea(BufReader::new(port), &mut shm, &monitor).await;
+                monitor.set_connected(false);
+                let error = match result {
+                    Ok(()) => "serial device closed".to_string(),
+                    Err(e) => format!("serial read failed: {}", e),
+                };
+                warn!(event = "gps.disconnected", device = %config.device, error = %error);
+                monitor.record_error(error);
+            }
+            Err(e) => {
+                warn!(event = "gps.open_failed", device = %config.device, error = %e);
+                monitor.record_error(format!("cannot open {}: {}", config.device, e));
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Attach the SHM unit and start reading the GPS in the background
+pub fn spawn(config: GpsConfig) -> Result<GpsMonitor, TimeServerError> {
+    let shm = NtpShmInterface::new(config.shm_unit)?;
+    let monitor = GpsMonitor::new(&config.device, Some(config.shm_unit));
+    info!(
+        event = "gps.start",
+        device = %config.device,
+        baud = config.baud_rate,
+        shm_unit = config.shm_unit
+    );
+    tokio::spawn(run_serial(config, shm, monitor.clone()));
+    Ok(monitor)
+}
+
+static GLOBAL: OnceLock<GpsMonitor> = OnceLock::new();
+
+/// Start the GPS reader when `ENABLE_GPS=yes`
+pub fn init(config: &NtpConfig) -> Result<(), TimeServerError> {
+    match &config.gps {
+        Some(config) if config.enabled => {
+            let _ = GLOBAL.set(spawn(config.clone())?);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The process-wide GPS reader, if enabled
+pub fn global() -> Option<&'static GpsMonitor> {
+    GLOBAL.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Captured receiver output
+    const RMC: &str = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+    const GGA: &str = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+    const ZDA: &str = "$GPZDA,201530.00,04,07,2002,00,00*60";
+    const GSA_3D: &str = "$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39";
+    const GSA_NO_FIX: &str = "$GPGSA,A,1,,,,,,,,,,,,,99.99,99.99,99.99*30";
+    const RMC_NO_FIX: &str = "$GPRMC,,V,,,,,,,,,,N*53";
+    const GGA_NO_FIX: &str = "$GPGGA,,,,,,0,00,99.99,,,,,,*48";
+    const GN_RMC: &str = "$GNRMC,083559.00,A,4717.11437,N,00833.91522,E,0.004,77.52,091202,,,A*49";
+    const GN_GSA_2D: &str = "$GNGSA,A,2,21,15,,,,,,,,,,,1.55,1.01,1.18*13";
+    const GN_RMC_BAD_CHECKSUM: &str =
+        "$GNRMC,083559.00,A,4717.11437,N,00833.91522,E,0.004,77.52,091202,,,A*57";
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_rmc() {
+        let NmeaSentence::Rmc(rmc) = parse_sentence(RMC).unwrap() else {
+            panic!("not RMC");
+        };
+        assert!(rmc.valid);
+        assert_eq!(rmc.timestamp(), Some(at(1994, 3, 23, 12, 35, 19)));
+
+        let NmeaSentence::Rmc(rmc) = parse_sentence(GN_RMC).unwrap() else {
+            panic!("not RMC");
+        };
+        assert_eq!(rmc.timestamp(), Some(at(2002, 12, 9, 8, 35, 59)));
+    }
+
+    #[test]
+    fn test_parse_gga_and_gsa() {
+        let NmeaSentence::Gga(gga) = parse_sentence(GGA).unwrap() else {
+            panic!("not GGA");
+        };
+        assert_eq!(gga.quality, 1);
+        assert_eq!(gga.satellites, Some(8));
+        assert_eq!(gga.altitude_m, Some(545.4));
+
+        assert_eq!(
+            parse_sentence(GSA_3D).unwrap(),
+            NmeaSentence::Gsa(Gsa {
+                mode: FixMode::Fix3D,
+                satellites: 5
+            })
+        );
+        assert_eq!(
+            parse_sentence(GN_GSA_2D).unwrap(),
+            NmeaSentence::Gsa(Gsa {
+                mode: FixMode::Fix2D,
+                satellites: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_zda_fractional_seconds() {
+        let NmeaSentence::Zda(zda) = parse_sentence(ZDA).unwrap() else {
+            panic!("not ZDA");
+        };
+        assert_eq!(zda.timestamp(), Some(at(2002, 7, 4, 20, 15, 30)));
+
+        let line = with_checksum("GPZDA,201530.25,04,07,2002,00,00");
+        let NmeaSentence::Zda(zda) = parse_sentence(&line).unwrap() else {
+            panic!("not ZDA");
+        };
+        assert_eq!(zda.timestamp().unwrap().timestamp_subsec_millis(), 250);
+    }
+
+    #[test]
+    fn test_empty_fields() {
+        let NmeaSentence::Rmc(rmc) = parse_sentence(RMC_NO_FIX).unwrap() else {
+            panic!("not RMC");
+        };
+        assert!(!rmc.valid);
+        assert_eq!(rmc.timestamp(), None);
+
+        let NmeaSentence::Gga(gga) = parse_sentence(GGA_NO_FIX).unwrap() else {
+            panic!("not GGA");
+        };
+        assert_eq!(gga.quality, 0);
+        assert_eq!(gga.time, None);
+        assert_eq!(gga.altitude_m, None);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert_eq!(
+            parse_sentence(GN_RMC_BAD_CHECKSUM),
+            Err(NmeaError::BadChecksum {
+                expected: 0x57,
+                computed: 0x49
+            })
+        );
+        assert_eq!(
+            parse_sentence("$GPZDA,201530.00,04,07,2002,00,00"),
+            Err(NmeaError::MissingChecksum)
+        );
+        assert_eq!(parse_sentence(&RMC[1..]), Err(NmeaError::MissingStart));
+        assert!(matches!(
+            parse_sentence(&with_checksum("GPRMC,1235,A,,,,,,,230394,,")),
+            Err(NmeaError::Malformed { field: "time", .. })
+        ));
+        assert!(matches!(
+            parse_sentence(&with_checksum("GPRMC,123519,A,,,,,,,310294,,")),
+            Err(NmeaError::Malformed { field: "date", .. })
+        ));
+        assert!(matches!(
+            parse_sentence(&with_checksum("GPGSV,3,1,11")),
+            Err(NmeaError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_leap_second() {
+        let line = with_checksum("GPZDA,235960.00,31,12,2016,00,00");
+        let NmeaSentence::Zda(zda) = parse_sentence(&line).unwrap() else {
+            panic!("not ZDA");
+        };
+        let time = zda.timestamp().unwrap();
+        assert_eq!(time.timestamp(), at(2016, 12, 31, 23, 59, 59).timestamp());
+        assert_eq!(time.timestamp_subsec_nanos(), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_tracker_fix_and_samples() {
+        let now = Utc::now();
+        let mut tracker = GpsTracker::new();
+
+        assert_eq!(tracker.ingest(GGA_NO_FIX, now), None);
+        assert_eq!(tracker.ingest(RMC_NO_FIX, now), None);
+        assert_eq!(tracker.ingest(GSA_NO_FIX, now), None);
+        assert_eq!(tracker.fix(), FixMode::NoFix);
+        // ZDA without a fix is not trusted
+        assert_eq!(tracker.ingest(ZDA, now), None);
+
+        assert_eq!(tracker.ingest(GSA_3D, now), None);
+        assert_eq!(tracker.fix(), FixMode::Fix3D);
+        let sample = tracker.ingest(RMC, now).unwrap();
+        assert_eq!(sample.time, at(1994, 3, 23, 12, 35, 19));
+        assert_eq!(sample.received, now);
+        // The same second again yields nothing new
+        assert_eq!(tracker.ingest(RMC, now), None);
+
+        assert!(tracker.ingest(GN_RMC_BAD_CHECKSUM, now).is_none());
+        assert_eq!(tracker.checksum_errors, 1);
+        assert_eq!(tracker.sentences, 7);
+    }
+
+    #[test]
+    fn test_tracker_infers_fix_without_gsa() {
+        let mut tracker = GpsTracker::new();
+        tracker.ingest(GGA, Utc::now());
+        assert_eq!(tracker.fix(), FixMode::Fix3D);
+        assert_eq!(tracker.satellites, Some(8));
+        tracker.ingest(GGA_NO_FIX, Utc::now());
+        assert_eq!(tracker.fix(), FixMode::NoFix);
+    }
+
+    impl RefclockSink for Vec<GpsSample> {
+        fn publish(&mut self, sample: &GpsSample) -> Result<(), TimeServerError> {
+            self.push(*sample);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_nmea_publishes_samples() {
+        let noise = "x".repeat(1000);
+        let input = [
+            GGA_NO_FIX, RMC_NO_FIX, &noise, GSA_3D, GGA, RMC, ZDA, GN_RMC,
+        ]
+        .join("\r\n");
+
+        let monitor = GpsMonitor::new("/dev/test", Some(2));
+        let mut sink = Vec::new();
+        read_nmea(input.as_bytes(), &mut sink, &monitor)
+            .await
+            .unwrap();
+
+        let times: Vec<_> = sink.iter().map(|s| s.time).collect();
+        assert_eq!(
+            times,
+            [
+                at(1994, 3, 23, 12, 35, 19),
+                at(2002, 7, 4, 20, 15, 30),
+                at(2002, 12, 9, 8, 35, 59)
+            ]
+        );
+
+        let status = monitor.status();
+        assert_eq!(status.fix, FixMode::Fix3D);
+        assert_eq!(status.satellites_used, Some(8));
+        assert_eq!(status.samples_published, 3);
+        assert_eq!(status.malformed, 4, "noise is split into oversized chunks");
+        assert_eq!(
+            status.last_gps_time.as_deref(),
+            Some("2002-12-09T08:35:59.000Z")
+        );
+    }
+
+    #[test]
+    fn test_sample_to_shm() {
+        let sample = GpsSample {
+            time: at(2024, 1, 1, 0, 0, 0),
+            received: at(2024, 1, 1, 0, 0, 0) + chrono::Duration::milliseconds(350),
+        };
+        let shm = sample.to_shm();
+        assert_eq!(shm.clock_sec, 1_704_067_200);
+        assert_eq!(shm.clock_nsec, 0);
+        assert_eq!(shm.receive_sec, 1_704_067_200);
+        assert_eq!(shm.receive_nsec, 350_000_000);
+        assert_eq!(shm.precision, NMEA_PRECISION);
+    }
+
+    fn with_checksum(body: &str) -> String {
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        format!("${}*{:02X}", body, checksum)
+    }
+}
@@ -0,0 +1,492 @@
+// NTP status history
+//
+// Every status read from ntpd is appended to a bounded ring, at most one per
+// `NTP_HISTORY_INTERVAL_SECS`; a sampler task reads the status on that
+// interval so the ring fills even when nobody asks. The default capacity is
+// 24 hours of samples. With `NTP_HISTORY_FILE` each sample is also appended
+// as a JSON line and the newest lines are loaded at startup, so history
+// survives restarts; the file is rewritten from the ring once it holds twice
+// the capacity.
+//
+// `downsample` and `summarize` are pure functions over samples.
+
+use super::{NtpStatus, NtpSyncedClock};
+use crate::error::TimeServerError;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+pub const DEFAULT_NTP_HISTORY_INTERVAL_SECS: u64 = 60;
+
+/// Span the ring covers when no capacity is configured
+const DEFAULT_HISTORY_SPAN_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NtpHistoryConfig {
+    /// Shortest spacing between samples, and the sampler's period
+    pub interval_secs: u64,
+    /// Samples kept; 24 hours at `interval_secs` by default
+    pub capacity: usize,
+    /// JSON lines file the samples are appended to and reloaded from
+    pub file: Option<String>,
+}
+
+impl NtpHistoryConfig {
+    pub fn with_interval(interval_secs: u64) -> Self {
+        Self {
+            interval_secs,
+            capacity: (DEFAULT_HISTORY_SPAN_SECS / interval_secs.max(1)).max(1) as usize,
+            file: None,
+        }
+    }
+}
+
+impl Default for NtpHistoryConfig {
+    fn default() -> Self {
+        Self::with_interval(DEFAULT_NTP_HISTORY_INTERVAL_SECS)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NtpSample {
+    /// Unix seconds
+    pub timestamp: i64,
+    pub offset_ms: f64,
+    pub stratum: u8,
+    pub synced: bool,
+}
+
+impl NtpSample {
+    pub fn of(status: &NtpStatus, timestamp: i64) -> Self {
+        Self {
+            timestamp,
+            offset_ms: status.offset_ms,
+            stratum: status.stratum,
+            synced: status.synced,
+        }
+    }
+}
+
+/// Offset range and sync share of the samples in `[start, start + resolution)`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryBucket {
+    pub start: i64,
+    pub samples: usize,
+    pub min_offset_ms: f64,
+    pub max_offset_ms: f64,
+    pub mean_offset_ms: f64,
+    pub synced_percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistorySummary {
+    pub samples: usize,
+    pub from: i64,
+    pub to: i64,
+    /// Percentiles and maximum of the absolute offset
+    pub p50_offset_ms: f64,
+    pub p95_offset_ms: f64,
+    pub max_offset_ms: f64,
+    /// Samples are evenly spaced, so this is also the share of time
+    pub synced_percent: f64,
+}
+
+/// Samples grouped into buckets of `resolution_secs` aligned to the Unix
+/// epoch; empty buckets are left out
+pub fn downsample(samples: &[NtpSample], resolution_secs: u64) -> Vec<HistoryBucket> {
+    let resolution = resolution_secs.max(1) as i64;
+    let mut buckets: Vec<(HistoryBucket, usize)> = Vec::new();
+    for sample in samples {
+        let start = sample.timestamp.div_euclid(resolution) * resolution;
+        match buckets.last_mut() {
+            Some((bucket, synced)) if bucket.start == start => {
+                bucket.samples += 1;
+                bucket.min_offset_ms = bucket.min_offset_ms.min(sample.offset_ms);
+                bucket.max_offset_ms = bucket.max_offset_ms.max(sample.offset_ms);
+                bucket.mean_offset_ms += sample.offset_ms;
+                *synced += sample.synced as usize;
+            }
+            _ => buckets.push((
+                HistoryBucket {
+                    start,
+                    samples: 1,
+                    min_offset_ms: sample.offset_ms,
+                    max_offset_ms: sample.offset_ms,
+                    mean_offset_ms: sample.offset_ms,
+                    synced_percent: 0.0,
+                },
+                sample.synced as usize,
+            )),
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|(mut bucket, synced)| {
+            bucket.mean_offset_ms /= bucket.samples as f64;
+            bucket.synced_percent = percent(synced, bucket.samples);
+            bucket
+        })
+        .collect()
+}
+
+/// Offset percentiles (nearest rank) and the synced share; `None` without samples
+pub fn summarize(samples: &[NtpSample]) -> Option<HistorySummary> {
+    let (first, last) = (samples.first()?, samples.last()?);
+    let mut offsets: Vec<f64> = samples.iter().map(|s| s.offset_ms.abs()).collect();
+    offsets.sort_by(f64::total_cmp);
+    let rank =
+        |p: f64| offsets[((p * offsets.len() as f64).ceil() as usize).clamp(1, offsets.len()) - 1];
+    Some(HistorySummary {
+        samples: samples.len(),
+        from: first.timestamp,
+        to: last.timestamp,
+        p50_offset_ms: rank(0.50),
+        p95_offset_ms: rank(0.95),
+        max_offset_ms: offsets[offsets.len() - 1],
+        synced_percent: percent(samples.iter().filter(|s| s.synced).count(), samples.len()),
+    })
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    (part as f64 * 1000.0 / whole as f64).round() / 10.0
+}
+
+/// `since` as Unix seconds: RFC 3339, or a span back from `now` such as
+/// `90m`, `24h` or `7d`
+pub fn parse_since(text: &str, now: i64) -> Result<i64, TimeServerError> {
+    let text = text.trim();
+    if let Ok(at) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Ok(at.timestamp());
+    }
+    let unit = match text.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 3600,
+        Some('d') => 86400,
+        _ => 0,
+    };
+    text[..text.len().saturating_sub(1)]
+        .parse::<i64>()
+        .ok()
+        .filter(|n| unit > 0 && *n >= 0)
+        .and_then(|n| n.checked_mul(unit))
+        .map(|span| now.saturating_sub(span))
+        .ok_or_else(|| {
+            TimeServerError::InvalidArgument(format!(
+                "'since' must be RFC 3339 or a span like '30m', '24h' or '7d', got '{}'",
+                text
+            ))
+        })
+}
+
+#[derive(Debug)]
+struct HistoryFile {
+    path: PathBuf,
+    /// Lines in the file, to know when to compact it
+    lines: usize,
+}
+
+/// Bounded, optionally persisted ring of NTP samples
+#[derive(Debug)]
+pub struct NtpHistory {
+    config: NtpHistoryConfig,
+    samples: Mutex<VecDeque<NtpSample>>,
+    file: Option<Mutex<HistoryFile>>,
+}
+
+impl NtpHistory {
+    /// An empty ring, filled from `config.file` when it exists
+    pub fn new(config: NtpHistoryConfig) -> Self {
+        let capacity = config.capacity.max(1);
+        let mut samples = VecDeque::with_capacity(capacity);
+        let file = config.file.as_ref().map(|path| {
+            let path = PathBuf::from(path);
+            let lines = match File::open(&path) {
+                Ok(file) => load(BufReader::new(file), &mut samples, capacity),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+                Err(e) => {
+                    warn!(event = "ntp.history.load_failed", path = %path.display(), error = %e);
+                    0
+                }
+            };
+            Mutex::new(HistoryFile { path, lines })
+        });
+        Self {
+            config,
+            samples: Mutex::new(samples),
+            file,
+        }
+    }
+
+    pub fn config(&self) -> &NtpHistoryConfig {
+        &self.config
+    }
+
+    /// Append `sample` unless it is less than the interval after the newest
+    /// one; returns whether it was kept
+    pub fn record(&self, sample: NtpSample) -> bool {
+        {
+            let mut samples = self
+                .samples
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if samples.back().is_some_and(|last| {
+                sample.timestamp < last.timestamp + self.config.interval_secs as i64
+            }) {
+                return false;
+            }
+            if samples.len() >= self.config.capacity.max(1) {
+                samples.pop_front();
+            }
+            samples.push_back(sample);
+        }
+        if let Some(file) = &self.file {
+            self.persist(
+                &mut file.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+                &sample,
+            );
+        }
+        true
+    }
+
+    /// Samples at or after `since`, oldest first
+    pub fn since(&self, since: i64) -> Vec<NtpSample> {
+        let samples = self
+            .samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let start = samples.partition_point(|s| s.timestamp < since);
+        samples.range(start..).copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Merge `saved` samples into the ring, keeping time order and the
+    /// newest `capacity`; samples already present are skipped
+    pub fn restore(&self, saved: &[NtpSample]) {
+        let mut samples = self
+            .samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut merged: Vec<NtpSample> = samples.iter().chain(saved).copied().collect();
+        merged.sort_by_key(|s| s.timestamp);
+        merged.dedup_by_key(|s| s.timestamp);
+        let skip = merged.len().saturating_sub(self.config.capacity.max(1));
+        *samples = merged.into_iter().skip(skip).collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn persist(&self, file: &mut HistoryFile, sample: &NtpSample) {
+        let result = if file.lines >= self.config.capacity.max(1) * 2 {
+            // Rewrite from the ring, which already holds `sample`
+            let samples: Vec<NtpSample> = self
+                .samples
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .iter()
+                .copied()
+                .collect();
+            file.lines = samples.len();
+            write_lines(&file.path, &samples, false)
+        } else {
+            file.lines += 1;
+            write_lines(&file.path, std::slice::from_ref(sample), true)
+        };
+        if let Err(e) = result {
+            warn!(event = "ntp.history.write_failed", path = %file.path.display(), error = %e);
+        }
+    }
+}
+
+/// The newest `capacity` parseable lines into `samples`; returns the line count
+fn load(reader: impl BufRead, samples: &mut VecDeque<NtpSample>, capacity: usize) -> usize {
+    let mut lines = 0;
+    for line in reader.lines().map_while(Result::ok) {
+        lines += 1;
+        if let Ok(sample) = serde_json::from_str::<NtpSample>(&line) {
+            if samples
+                .back()
+                .is_some_and(|last| sample.timestamp <= last.timestamp)
+            {
+                continue;
+            }
+            if samples.len() >= capacity {
+                samples.pop_front();
+            }
+            samples.push_back(sample);
+        }
+    }
+    lines
+}
+
+fn write_lines(path: &PathBuf, samples: &[NtpSample], append: bool) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)?;
+    let mut buf = Vec::new();
+    for sample in samples {
+        serde_json::to_writer(&mut buf, sample)?;
+        buf.push(b'\n');
+    }
+    file.write_all(&buf)
+}
+
+/// Read the NTP status every interval so the history fills without callers,
+/// attaching SHM again first when it is missing or stale
+pub fn spawn_sampler(clock: Arc<NtpSyncedClock>) -> Option<tokio::task::JoinHandle<()>> {
+    let interval = clock.history()?.config().interval_secs.max(1);
+    Some(tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticks.tick().await;
+            clock.refresh_shm();
+            if let Err(e) = clock.get_status_async().await {
+                tracing::debug!(event = "ntp.history.sample_failed", error = %e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: i64, offset_ms: f64, synced: bool) -> NtpSample {
+        NtpSample {
+            timestamp,
+            offset_ms,
+            stratum: 2,
+            synced,
+        }
+    }
+
+    #[test]
+    fn test_downsample() {
+        let samples = [
+            sample(0, 1.0, true),
+            sample(60, -3.0, true),
+            sample(120, 2.0, false),
+            sample(300, 10.0, true),
+        ];
+        let buckets = downsample(&samples, 180);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, 0);
+        assert_eq!(buckets[0].samples, 3);
+        assert_eq!(buckets[0].min_offset_ms, -3.0);
+        assert_eq!(buckets[0].max_offset_ms, 2.0);
+        assert_eq!(buckets[0].mean_offset_ms, 0.0);
+        assert_eq!(buckets[0].synced_percent, 66.7);
+        assert_eq!(buckets[1].start, 180);
+        assert_eq!(buckets[1].mean_offset_ms, 10.0);
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&[]), None);
+        let samples: Vec<NtpSample> = (1..=20)
+            .map(|i| {
+                sample(
+                    i * 60,
+                    if i % 2 == 0 { i as f64 } else { -(i as f64) },
+                    i != 7,
+                )
+            })
+            .collect();
+        let summary = summarize(&samples).unwrap();
+        assert_eq!(summary.samples, 20);
+        assert_eq!(summary.from, 60);
+        assert_eq!(summary.to, 1200);
+        assert_eq!(summary.p50_offset_ms, 10.0);
+        assert_eq!(summary.p95_offset_ms, 19.0);
+        assert_eq!(summary.max_offset_ms, 20.0);
+        assert_eq!(summary.synced_percent, 95.0);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("24h", 100_000).unwrap(), 100_000 - 86_400);
+        assert_eq!(parse_since("90m", 10_000).unwrap(), 10_000 - 5_400);
+        assert_eq!(parse_since("1970-01-02T00:00:00Z", 0).unwrap(), 86_400);
+        for bad in ["", "h", "yesterday", "-5m", "3w"] {
+            assert!(parse_since(bad, 0).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_ring_is_bounded_and_spaced() {
+        let history = NtpHistory::new(NtpHistoryConfig {
+            interval_secs: 60,
+            capacity: 3,
+            file: None,
+        });
+        assert!(history.record(sample(0, 1.0, true)));
+        assert!(!history.record(sample(30, 2.0, true)));
+        for i in 1..=4 {
+            assert!(history.record(sample(i * 60, i as f64, true)));
+        }
+        assert_eq!(history.len(), 3);
+        let kept: Vec<i64> = history.since(0).iter().map(|s| s.timestamp).collect();
+        assert_eq!(kept, [120, 180, 240]);
+        assert_eq!(history.since(200).len(), 1);
+    }
+
+    #[test]
+    fn test_restore_merges_saved_samples() {
+        let history = NtpHistory::new(NtpHistoryConfig {
+            interval_secs: 60,
+            capacity: 3,
+            file: None,
+        });
+        history.record(sample(300, 5.0, true));
+        history.restore(&[
+            sample(60, 1.0, true),
+            sample(120, 2.0, true),
+            sample(300, 9.0, true),
+        ]);
+        let kept: Vec<i64> = history.since(0).iter().map(|s| s.timestamp).collect();
+        assert_eq!(kept, [60, 120, 300]);
+        assert_eq!(history.since(300)[0].offset_ms, 5.0);
+    }
+
+    #[test]
+    fn test_history_survives_restart() {
+        let path = std::env::temp_dir().join(format!("ntp-history-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = NtpHistoryConfig {
+            interval_secs: 1,
+            capacity: 4,
+            file: Some(path.to_string_lossy().into_owned()),
+        };
+
+        let history = NtpHistory::new(config.clone());
+        for i in 0..10 {
+            history.record(sample(i, i as f64, true));
+        }
+        drop(history);
+        // Compacted once it reached twice the capacity
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines <= 8, "{} lines", lines);
+
+        let reloaded = NtpHistory::new(config);
+        let kept: Vec<i64> = reloaded.since(0).iter().map(|s| s.timestamp).collect();
+        assert_eq!(kept, [6, 7, 8, 9]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
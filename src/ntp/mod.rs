@@ -1,6 +1,20 @@
 // NTP Integration Module
+pub mod attach;
 pub mod config;
+pub mod correction;
+pub mod gps;
+pub mod history;
+pub mod ntpq;
+pub mod packet;
+pub mod platform;
+pub mod pps;
+pub mod query;
+pub mod shm;
+pub mod sntp;
+pub mod supervisor;
 pub mod sync;
 
+pub use attach::{ShmAttacher, ShmRetryConfig};
 pub use config::NtpConfig;
+pub use history::{NtpHistory, NtpHistoryConfig};
 pub use sync::{NtpStatus, NtpSyncedClock};
@@ -0,0 +1,325 @@
+// Parsers for `ntpq -c rv` and `ntpq -c kerninfo` output
+//
+// Classic ntpd and NTPsec print the same system variables with a few
+// different spellings (`jitter` vs `sys_jitter`, `noise`/`stability` vs
+// `clk_jitter`/`clk_wander`) and different reftime and unit styles. Both
+// are read into one shape here; anything missing stays `None`, so a daemon
+// that prints less just reports less.
+
+use super::packet::NtpTimestamp;
+use chrono::SecondsFormat;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// System variables from `ntpq -c rv`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemVariables {
+    pub offset_ms: Option<f64>,
+    pub stratum: Option<u8>,
+    pub precision: Option<i8>,
+    pub root_delay: Option<f64>,
+    pub root_dispersion: Option<f64>,
+    pub frequency_ppm: Option<f64>,
+    pub sys_jitter_ms: Option<f64>,
+    pub clk_jitter_ms: Option<f64>,
+    pub clk_wander_ppm: Option<f64>,
+    pub refid: Option<String>,
+    /// RFC 3339, UTC
+    pub reftime: Option<String>,
+}
+
+/// Kernel time discipline state from `ntpq -c kerninfo`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct KernelInfo {
+    pub pll_offset_ms: Option<f64>,
+    pub pll_frequency_ppm: Option<f64>,
+    pub maximum_error_ms: Option<f64>,
+    pub estimated_error_ms: Option<f64>,
+    pub time_constant: Option<i64>,
+    /// Kernel status flags, e.g. ["pll", "nano"]
+    pub status: Vec<String>,
+}
+
+/// `timex` status bits (STA_*) and their ntpq names
+const KERNEL_STATUS_FLAGS: [(u32, &str); 16] = [
+    (0x0001, "pll"),
+    (0x0002, "ppsfreq"),
+    (0x0004, "ppstime"),
+    (0x0008, "fll"),
+    (0x0010, "ins"),
+    (0x0020, "del"),
+    (0x0040, "unsync"),
+    (0x0080, "freqhold"),
+    (0x0100, "ppssignal"),
+    (0x0200, "ppsjitter"),
+    (0x0400, "ppswander"),
+    (0x0800, "ppserror"),
+    (0x1000, "clockerr"),
+    (0x2000, "nano"),
+    (0x4000, "mode=fll"),
+    (0x8000, "clk=b"),
+];
+
+/// `name=value` pairs of `ntpq -c rv`, in order. Values may themselves
+/// contain commas (classic ntpd's reftime, the status word), so a segment
+/// without `=` belongs to the pair before it.
+pub fn variables(output: &str) -> Vec<(&str, String)> {
+    let mut pairs: Vec<(&str, String)> = Vec::new();
+    for segment in output.split(',') {
+        let segment = segment.trim();
+        if segment.is_empty() {
+            continue;
+        }
+        match segment.split_once('=') {
+            Some((name, value)) if is_variable_name(name) => {
+                pairs.push((name, value.trim().trim_matches('"').to_string()))
+            }
+            _ => {
+                if let Some((_, value)) = pairs.last_mut() {
+                    value.push_str(", ");
+                    value.push_str(segment);
+                }
+            }
+        }
+    }
+    pairs
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn parse_rv(output: &str) -> SystemVariables {
+    let mut vars = SystemVariables::default();
+    for (name, value) in variables(output) {
+        let number = || leading_number(&value);
+        match name {
+            "offset" => vars.offset_ms = number(),
+            "stratum" => vars.stratum = value.parse().ok(),
+            "precision" => vars.precision = value.parse().ok(),
+            "rootdelay" => vars.root_delay = number(),
+            "rootdisp" => vars.root_dispersion = number(),
+            "frequency" => vars.frequency_ppm = number(),
+            // ntpd before 4.2.6 printed `jitter`, `noise` and `stability`
+            "sys_jitter" | "jitter" => vars.sys_jitter_ms = number(),
+            "clk_jitter" | "noise" => vars.clk_jitter_ms = number(),
+            "clk_wander" | "stability" => vars.clk_wander_ppm = number(),
+            "refid" => vars.refid = Some(value).filter(|v| !v.is_empty()),
+            "reftime" => vars.reftime = parse_reftime(&value),
+            _ => {}
+        }
+    }
+    vars
+}
+
+/// `ee7da447.1f7ced92  Sat, Oct 17 2026  7:37:43.123` (ntpd) or
+/// `ee7da447.1f7ced92 2026-10-17T07:37:43.123Z` (NTPsec): the hex NTP
+/// timestamp is read and the human part ignored. All zeros means never.
+fn parse_reftime(value: &str) -> Option<String> {
+    let hex = value.split_whitespace().next()?;
+    let (seconds, fraction) = hex.split_once('.')?;
+    let seconds = u32::from_str_radix(seconds, 16).ok()?;
+    let fraction = u32::from_str_radix(fraction, 16).ok()?;
+    if seconds == 0 && fraction == 0 {
+        return None;
+    }
+    let timestamp = NtpTimestamp(((seconds as u64) << 32) | fraction as u64);
+    Some(
+        timestamp
+            .to_datetime()
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+    )
+}
+
+/// Parse `ntpq -c kerninfo`; None when it reports nothing (no kernel
+/// discipline, or the query was refused)
+pub fn parse_kerninfo(output: &str) -> Option<KernelInfo> {
+    let mut info = KernelInfo::default();
+    let mut found = false;
+    for line in output.lines() {
+        let Some((label, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match label.trim() {
+            "pll offset" => info.pll_offset_ms = milliseconds(value),
+            "pll frequency" => info.pll_frequency_ppm = leading_number(value),
+            "maximum error" => info.maximum_error_ms = milliseconds(value),
+            "estimated error" => info.estimated_error_ms = milliseconds(value),
+            "pll time constant" => info.time_constant = value.parse().ok(),
+            "kernel status" => info.status = kernel_status(value),
+            _ => continue,
+        }
+        found = true;
+    }
+    found.then_some(info)
+}
+
+/// Status flags, whether ntpq printed them as words or as the raw word
+fn kernel_status(value: &str) -> Vec<String> {
+    let raw = value
+        .strip_prefix("0x")
+        .map(|hex| u32::from_str_radix(hex, 16))
+        .unwrap_or_else(|| value.parse());
+    match raw {
+        Ok(bits) => KERNEL_STATUS_FLAGS
+            .iter()
+            .filter(|(bit, _)| bits & bit != 0)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+        Err(_) => value.split_whitespace().map(str::to_string).collect(),
+    }
+}
+
+/// A kerninfo time in ms. ntpq prints milliseconds; NTPsec names the unit
+fn milliseconds(value: &str) -> Option<f64> {
+    let number = leading_number(value)?;
+    let scale = match value.split_whitespace().nth(1) {
+        Some("s") => 1000.0,
+        Some("us") | Some("µs") => 0.001,
+        Some("ns") => 0.000_001,
+        _ => 1.0,
+    };
+    Some(number * scale)
+}
+
+/// The number at the start of `value`, ignoring a unit after it
+fn leading_number(value: &str) -> Option<f64> {
+    value.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from ntpd 4.2.8p15 on Debian 12
+    const NTPD_RV: &str = "associd=0 status=0615 leap_none, sync_ntp, 1 event, clock_sync,
+version=\"ntpd 4.2.8p15@1.3728-o Wed Sep 23 11:46:38 UTC 2020 (1)\",
+processor=\"x86_64\", system=\"Linux/6.1.0-13-amd64\", leap=00, stratum=2,
+precision=-23, rootdelay=1.297, rootdisp=21.430, refid=192.0.2.10,
+reftime=ee7da447.1f7ced92  Sat, Oct 17 2026  7:37:43.123,
+clock=ee7da454.8d4fe1a2  Sat, Oct 17 2026  7:37:56.552, peer=51542, tc=10,
+mintc=3, offset=-0.128412, frequency=-12.345, sys_jitter=0.234511,
+clk_jitter=0.061, clk_wander=0.004, tai=37, leapsec=201701010000,
+expire=202706280000";
+
+    // Captured from NTPsec 1.2.2 on Fedora 39
+    const NTPSEC_RV: &str = "associd=0 status=0618 leap_none, sync_ntp, 1 event, no_sys_peer,
+version=\"ntpd ntpsec-1.2.2 2023-05-24T14:12:41Z\", processor=\"x86_64\",
+system=\"Linux/6.5.6-300.fc39.x86_64\", leap=00, stratum=1, precision=-24,
+rootdelay=0.000000, rootdisp=0.452103, rootdist=0.455, refid=PPS,
+reftime=ee7da447.1f7ced92 2026-10-17T07:37:43.123Z,
+clock=ee7da454.8d4fe1a2 2026-10-17T07:37:56.552Z, peer=41216, tc=4, mintc=0,
+offset=0.000412, frequency=3.116, sys_jitter=0.000954, clk_jitter=0.000391,
+clk_wander=0.000110, tai=37, leapsec=201701010000, expire=202706280000,
+mintc=0";
+
+    // ntpd 4.2.4 spelling
+    const OLD_NTPD_RV: &str = "status=0644 leap_none, sync_ntp, 4 events, event_peer/strat_chg,
+stratum=3, refid=10.0.0.1, reftime=00000000.00000000  Thu, Feb  7 2036  6:28:16.000,
+offset=1.250, frequency=4.512, jitter=0.402, noise=0.115, stability=0.008";
+
+    const NTPD_KERNINFO: &str = "associd=0 status=0615 leap_none, sync_ntp, 1 event, clock_sync,
+pll offset:            -0.128
+pll frequency:         -12.345
+maximum error:         0.016
+estimated error:       0.000109
+kernel status:         pll nano
+pll time constant:     10
+precision:             1e-06
+frequency tolerance:   500
+pps frequency:         0
+pps stability:         0
+pps jitter:            0
+calibration interval   0
+calibration cycles:    0
+jitter exceeded:       0
+stability exceeded:    0
+calibration errors:    0";
+
+    const NTPSEC_KERNINFO: &str = "associd=0 status=0618 leap_none, sync_ntp, 1 event, no_sys_peer,
+pll offset:           0.000412 ms
+pll frequency:        3.116 ppm
+maximum error:        0.0025 s
+estimated error:      1 us
+kernel status:        0x2107
+pll time constant:    4
+precision:            1e-06 ms
+frequency tolerance:  500 ppm";
+
+    const KERNINFO_REFUSED: &str = "***Server reports a bad format request\n";
+
+    #[test]
+    fn test_parse_ntpd_rv() {
+        let vars = parse_rv(NTPD_RV);
+        assert_eq!(vars.offset_ms, Some(-0.128412));
+        assert_eq!(vars.stratum, Some(2));
+        assert_eq!(vars.precision, Some(-23));
+        assert_eq!(vars.root_delay, Some(1.297));
+        assert_eq!(vars.root_dispersion, Some(21.43));
+        assert_eq!(vars.frequency_ppm, Some(-12.345));
+        assert_eq!(vars.sys_jitter_ms, Some(0.234511));
+        assert_eq!(vars.clk_jitter_ms, Some(0.061));
+        assert_eq!(vars.clk_wander_ppm, Some(0.004));
+        assert_eq!(vars.refid.as_deref(), Some("192.0.2.10"));
+        // The comma inside the human-readable reftime does not split it
+        assert_eq!(vars.reftime.as_deref(), Some("2026-10-17T07:37:43.123Z"));
+    }
+
+    #[test]
+    fn test_parse_ntpsec_rv() {
+        let vars = parse_rv(NTPSEC_RV);
+        assert_eq!(vars.stratum, Some(1));
+        assert_eq!(vars.precision, Some(-24));
+        assert_eq!(vars.offset_ms, Some(0.000412));
+        assert_eq!(vars.frequency_ppm, Some(3.116));
+        assert_eq!(vars.sys_jitter_ms, Some(0.000954));
+        assert_eq!(vars.clk_jitter_ms, Some(0.000391));
+        assert_eq!(vars.clk_wander_ppm, Some(0.00011));
+        assert_eq!(vars.refid.as_deref(), Some("PPS"));
+        assert_eq!(vars.reftime.as_deref(), Some("2026-10-17T07:37:43.123Z"));
+    }
+
+    #[test]
+    fn test_parse_legacy_spellings_and_missing_fields() {
+        let vars = parse_rv(OLD_NTPD_RV);
+        assert_eq!(vars.sys_jitter_ms, Some(0.402));
+        assert_eq!(vars.clk_jitter_ms, Some(0.115));
+        assert_eq!(vars.clk_wander_ppm, Some(0.008));
+        assert_eq!(vars.reftime, None);
+        assert_eq!(vars.precision, None);
+
+        assert_eq!(parse_rv(""), SystemVariables::default());
+    }
+
+    #[test]
+    fn test_parse_ntpd_kerninfo() {
+        let info = parse_kerninfo(NTPD_KERNINFO).unwrap();
+        assert_eq!(info.pll_offset_ms, Some(-0.128));
+        assert_eq!(info.pll_frequency_ppm, Some(-12.345));
+        assert_eq!(info.maximum_error_ms, Some(0.016));
+        assert_eq!(info.estimated_error_ms, Some(0.000109));
+        assert_eq!(info.time_constant, Some(10));
+        assert_eq!(info.status, ["pll", "nano"]);
+    }
+
+    #[test]
+    fn test_parse_ntpsec_kerninfo_units_and_status_word() {
+        let info = parse_kerninfo(NTPSEC_KERNINFO).unwrap();
+        assert_eq!(info.pll_offset_ms, Some(0.000412));
+        assert_eq!(info.pll_frequency_ppm, Some(3.116));
+        assert_eq!(info.maximum_error_ms, Some(2.5));
+        assert_eq!(info.estimated_error_ms, Some(0.001));
+        assert_eq!(info.time_constant, Some(4));
+        assert_eq!(
+            info.status,
+            ["pll", "ppsfreq", "ppstime", "ppssignal", "nano"]
+        );
+    }
+
+    #[test]
+    fn test_kerninfo_unavailable() {
+        assert_eq!(parse_kerninfo(KERNINFO_REFUSED), None);
+        assert_eq!(parse_kerninfo(""), None);
+    }
+}
@@ -0,0 +1,250 @@
+// NTPv4 packet encoding (RFC 5905 section 7.3)
+//
+// Only the 48-byte header is handled; extension fields and the MAC of
+// authenticated packets are ignored on decode and never produced.
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// Size of the fixed NTP header
+pub const PACKET_LEN: usize = 48;
+
+/// Seconds from the NTP epoch (1900-01-01) to the Unix epoch
+pub const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// Leap indicator: no warning
+pub const LEAP_NONE: u8 = 0;
+/// Leap indicator: clock not synchronized
+pub const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+/// Association mode of a client request
+pub const MODE_CLIENT: u8 = 3;
+/// Association mode of a server reply
+pub const MODE_SERVER: u8 = 4;
+
+/// Stratum reported by an unsynchronized server
+pub const STRATUM_UNSYNCHRONIZED: u8 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PacketError {
+    #[error("packet is {0} bytes, shorter than the {PACKET_LEN}-byte NTP header")]
+    TooShort(usize),
+
+    #[error("unsupported NTP version {0}")]
+    UnsupportedVersion(u8),
+}
+
+/// 64-bit NTP timestamp: seconds since 1900 and a 32-bit binary fraction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    pub const ZERO: Self = Self(0);
+
+    pub fn seconds(self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    pub fn fraction(self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Convert to UTC. Era 0 ends in 2036; as in RFC 4330, seconds with the
+    /// high bit clear are taken to be in era 1 (from 2036-02-07)
+    pub fn to_datetime(self) -> DateTime<Utc> {
+        let mut secs = self.seconds() as i64;
+        if secs & 0x8000_0000 == 0 {
+            secs += 1 << 32;
+        }
+        let nanos = ((self.fraction() as u64 * 1_000_000_000) >> 32) as u32;
+        DateTime::from_timestamp(secs - NTP_UNIX_OFFSET, nanos).unwrap_or_default()
+    }
+}
+
+impl From<DateTime<Utc>> for NtpTimestamp {
+    fn from(time: DateTime<Utc>) -> Self {
+        // Wraps modulo 2^32 seconds, which is what the era rule above undoes
+        let secs = (time.timestamp() + NTP_UNIX_OFFSET) as u32 as u64;
+        let nanos = time.timestamp_subsec_nanos().min(999_999_999) as u64;
+        let fraction = ((nanos << 32) + 500_000_000) / 1_000_000_000;
+        Self((secs << 32) | fraction.min(u32::MAX as u64))
+    }
+}
+
+/// Seconds as an NTP short format value (16.16 fixed point)
+pub fn short_format(seconds: f64) -> u32 {
+    (seconds.max(0.0) * 65_536.0).round().min(u32::MAX as f64) as u32
+}
+
+/// NTP packet header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NtpPacket {
+    pub leap: u8,
+    pub version: u8,
+    pub mode: u8,
+    pub stratum: u8,
+    /// Poll interval, log2 seconds
+    pub poll: i8,
+    /// Clock precision, log2 seconds
+    pub precision: i8,
+    pub root_delay: u32,
+    pub root_dispersion: u32,
+    /// Source identifier: four ASCII characters at stratum 0-1, an upstream
+    /// address above, or a kiss code when unsynchronized
+    pub reference_id: [u8; 4],
+    pub reference: NtpTimestamp,
+    pub origin: NtpTimestamp,
+    pub receive: NtpTimestamp,
+    pub transmit: NtpTimestamp,
+}
+
+impl Default for NtpPacket {
+    fn default() -> Self {
+        Self {
+            leap: LEAP_NONE,
+            version: 4,
+            mode: MODE_CLIENT,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            root_delay: 0,
+            root_dispersion: 0,
+            reference_id: [0; 4],
+            reference: NtpTimestamp::ZERO,
+            origin: NtpTimestamp::ZERO,
+            receive: NtpTimestamp::ZERO,
+            transmit: NtpTimestamp::ZERO,
+        }
+    }
+}
+
+impl NtpPacket {
+    /// A client request carrying `transmit` as its transmit timestamp
+    pub fn client(transmit: NtpTimestamp) -> Self {
+        Self {
+            transmit,
+            ..Self::default()
+        }
+    }
+
+    pub fn encode(&self) -> [u8; PACKET_LEN] {
+        let mut buf = [0u8; PACKET_LEN];
+        buf[0] = (self.leap & 0x3) << 6 | (self.version & 0x7) << 3 | (self.mode & 0x7);
+        buf[1] = self.stratum;
+        buf[2] = self.poll as u8;
+        buf[3] = self.precision as u8;
+        buf[4..8].copy_from_slice(&self.root_delay.to_be_bytes());
+        buf[8..12].copy_from_slice(&self.root_dispersion.to_be_bytes());
+        buf[12..16].copy_from_slice(&self.reference_id);
+        buf[16..24].copy_from_slice(&self.reference.0.to_be_bytes());
+        buf[24..32].copy_from_slice(&self.origin.0.to_be_bytes());
+        buf[32..40].copy_from_slice(&self.receive.0.to_be_bytes());
+        buf[40..48].copy_from_slice(&self.transmit.0.to_be_bytes());
+        buf
+    }
+
+    /// Decode the header of an NTPv1-v4 packet
+    pub fn decode(buf: &[u8]) -> Result<Self, PacketError> {
+        if buf.len() < PACKET_LEN {
+            return Err(PacketError::TooShort(buf.len()));
+        }
+        let version = (buf[0] >> 3) & 0x7;
+        if !(1..=4).contains(&version) {
+            return Err(PacketError::UnsupportedVersion(version));
+        }
+        let u32_at = |i: usize| u32::from_be_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+        let ts_at = |i: usize| NtpTimestamp((u32_at(i) as u64) << 32 | u32_at(i + 4) as u64);
+        Ok(Self {
+            leap: buf[0] >> 6,
+            version,
+            mode: buf[0] & 0x7,
+            stratum: buf[1],
+            poll: buf[2] as i8,
+            precision: buf[3] as i8,
+            root_delay: u32_at(4),
+            root_dispersion: u32_at(8),
+            reference_id: [buf[12], buf[13], buf[14], buf[15]],
+            reference: ts_at(16),
+            origin: ts_at(24),
+            receive: ts_at(32),
+            transmit: ts_at(40),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_conversion() {
+        let unix_epoch = DateTime::from_timestamp(0, 0).unwrap();
+        let ts = NtpTimestamp::from(unix_epoch);
+        assert_eq!(ts.seconds() as i64, NTP_UNIX_OFFSET);
+        assert_eq!(ts.fraction(), 0);
+        assert_eq!(ts.to_datetime(), unix_epoch);
+
+        let half = DateTime::from_timestamp(1_700_000_000, 500_000_000).unwrap();
+        let ts = NtpTimestamp::from(half);
+        assert_eq!(ts.fraction(), 1 << 31);
+        assert_eq!(ts.to_datetime(), half);
+
+        // Sub-nanosecond rounding in the fraction must not drift the round trip
+        let odd = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        let back = NtpTimestamp::from(odd).to_datetime();
+        assert!((back - odd).num_nanoseconds().unwrap().abs() <= 1);
+    }
+
+    #[test]
+    fn test_timestamp_after_era_rollover() {
+        // 2040 lies in NTP era 1, where the 32-bit seconds field has wrapped
+        let time = DateTime::from_timestamp(2_208_988_800, 0).unwrap();
+        let ts = NtpTimestamp::from(time);
+        assert!(ts.seconds() < 0x8000_0000);
+        assert_eq!(ts.to_datetime(), time);
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let packet = NtpPacket {
+            leap: LEAP_UNSYNCHRONIZED,
+            version: 4,
+            mode: MODE_SERVER,
+            stratum: 2,
+            poll: 6,
+            precision: -20,
+            root_delay: short_format(0.015),
+            root_dispersion: short_format(0.25),
+            reference_id: *b"GPS\0",
+            reference: NtpTimestamp(0x1111_2222_3333_4444),
+            origin: NtpTimestamp(0xE000_0000_8000_0000),
+            receive: NtpTimestamp(0xE000_0001_0000_0001),
+            transmit: NtpTimestamp(0xE000_0001_0000_0002),
+        };
+        let bytes = packet.encode();
+        assert_eq!(bytes[0], 0b11_100_100);
+        assert_eq!(bytes[3], 0xEC);
+        assert_eq!(&bytes[8..12], &[0x00, 0x00, 0x40, 0x00]);
+        assert_eq!(NtpPacket::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_packets() {
+        assert_eq!(
+            NtpPacket::decode(&[0u8; 47]),
+            Err(PacketError::TooShort(47))
+        );
+
+        let mut bytes = NtpPacket::default().encode();
+        bytes[0] = 5 << 3 | MODE_CLIENT;
+        assert_eq!(
+            NtpPacket::decode(&bytes),
+            Err(PacketError::UnsupportedVersion(5))
+        );
+
+        // Extension fields after the header are ignored
+        let mut long = NtpPacket::client(NtpTimestamp(42)).encode().to_vec();
+        long.extend_from_slice(&[0u8; 20]);
+        assert_eq!(NtpPacket::decode(&long).unwrap().transmit, NtpTimestamp(42));
+    }
+}
@@ -0,0 +1,249 @@
+// Where NTP status comes from on each platform
+//
+// ntpd and NTPsec are asked with `ntpq`, and refclock samples travel through
+// SysV shared memory; both exist only on Unix. Windows keeps time with its
+// own service, so there `w32tm /query /status /verbose` is read instead, as
+// a best effort: the labels are localized, and a non-English system reports
+// less. On any other target the NTP tools answer that the platform is not
+// supported rather than spawning commands that cannot exist.
+
+use super::NtpStatus;
+use crate::error::TimeServerError;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Arguments of the w32tm query
+pub const W32TM_ARGS: &[&str] = &["/query", "/status", "/verbose"];
+
+/// What the NTP status tools read on this platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusBackend {
+    /// `ntpq -c rv` against ntpd or NTPsec
+    Ntpq,
+    /// The Windows Time service
+    W32tm,
+    Unsupported,
+}
+
+impl StatusBackend {
+    /// The backend of the target this was built for
+    pub const fn current() -> Self {
+        if cfg!(unix) {
+            Self::Ntpq
+        } else if cfg!(windows) {
+            Self::W32tm
+        } else {
+            Self::Unsupported
+        }
+    }
+}
+
+/// get_ntp_status where no backend exists: a result, not an error, so
+/// clients can tell "cannot know here" from "failed to ask"
+pub fn unsupported_status() -> Value {
+    let error = TimeServerError::not_supported("NTP status");
+    json!({
+        "available": false,
+        "supported": false,
+        "platform": std::env::consts::OS,
+        "message": error.to_string(),
+        "code": error.code(),
+        "synced": false,
+        "shm_interface": "not_supported",
+    })
+}
+
+/// Fields of `w32tm /query /status /verbose`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct W32tmStatus {
+    /// 3 while the service is not synchronized
+    pub leap_indicator: Option<u8>,
+    pub stratum: Option<u8>,
+    pub precision: Option<i8>,
+    pub root_delay_ms: Option<f64>,
+    pub root_dispersion_ms: Option<f64>,
+    /// The time source, e.g. "time.windows.com" or "Local CMOS Clock"
+    pub source: Option<String>,
+    pub phase_offset_ms: Option<f64>,
+}
+
+impl W32tmStatus {
+    pub fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+        for line in output.lines() {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            let number = || leading_number(value);
+            match name.trim() {
+                "Leap Indicator" => status.leap_indicator = number().map(|n| n as u8),
+                "Stratum" => status.stratum = number().map(|n| n as u8),
+                "Precision" => status.precision = number().map(|n| n as i8),
+                "Root Delay" => status.root_delay_ms = seconds_as_ms(value),
+                "Root Dispersion" => status.root_dispersion_ms = seconds_as_ms(value),
+                "Phase Offset" => status.phase_offset_ms = seconds_as_ms(value),
+                // "time.windows.com,0x9": the flags after the comma are dropped
+                "Source" => {
+                    status.source = value
+                        .split(',')
+                        .next()
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                }
+                _ => {}
+            }
+        }
+        status
+    }
+
+    /// Synchronized to an NTP source rather than free-running
+    pub fn synced(&self) -> bool {
+        let free_running = self
+            .source
+            .as_deref()
+            .is_some_and(|s| s == "Local CMOS Clock" || s == "Free-running System Clock");
+        self.leap_indicator.is_some_and(|leap| leap != 3)
+            && self
+                .stratum
+                .is_some_and(|stratum| (1..16).contains(&stratum))
+            && !free_running
+    }
+
+    pub fn to_status(&self) -> NtpStatus {
+        NtpStatus {
+            synced: self.synced(),
+            offset_ms: self.phase_offset_ms.unwrap_or(0.0),
+            stratum: self.stratum.filter(|&s| s > 0).unwrap_or(16),
+            precision: self.precision.unwrap_or(0),
+            root_delay: self.root_delay_ms.unwrap_or(0.0),
+            root_dispersion: self.root_dispersion_ms.unwrap_or(0.0),
+            refid: self.source.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn leading_number(value: &str) -> Option<f64> {
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].parse().ok()
+}
+
+/// "0.0312500s" as milliseconds
+fn seconds_as_ms(value: &str) -> Option<f64> {
+    value
+        .strip_suffix('s')
+        .and_then(|seconds| seconds.trim().parse::<f64>().ok())
+        .map(|seconds| seconds * 1000.0)
+}
+
+/// Ask the Windows Time service
+#[cfg(windows)]
+pub async fn w32tm_status() -> Result<NtpStatus, TimeServerError> {
+    use super::query::{CommandRunner, TokioRunner, NTP_COMMAND_TIMEOUT};
+
+    let unavailable = |reason: String| TimeServerError::NtpUnavailable { reason };
+    let output = tokio::time::timeout(NTP_COMMAND_TIMEOUT, TokioRunner.run("w32tm", W32TM_ARGS))
+        .await
+        .map_err(|_| unavailable("w32tm timed out".into()))?
+        .map_err(|e| unavailable(format!("w32tm: {}", e)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        // w32tm prints its errors on stdout, e.g. when the service is stopped
+        return Err(unavailable(format!("w32tm failed: {}", stdout.trim())));
+    }
+    Ok(W32tmStatus::parse(&stdout).to_status())
+}
+
+/// Only Windows has w32tm
+#[cfg(not(windows))]
+pub async fn w32tm_status() -> Result<NtpStatus, TimeServerError> {
+    Err(TimeServerError::not_supported("w32tm"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNCED: &str = "Leap Indicator: 0(no warning)\r
+Stratum: 4 (secondary reference - syncd by (S)NTP)\r
+Precision: -23 (119.209ns per tick)\r
+Root Delay: 0.0312500s\r
+Root Dispersion: 7.8287669s\r
+ReferenceId: 0x14654A6C (source IP:  20.101.57.9)\r
+Last Successful Sync Time: 10/17/2026 7:37:43 AM\r
+Source: time.windows.com,0x9\r
+Poll Interval: 10 (1024s)\r
+\r
+Phase Offset: -0.0008474s\r
+ClockRate: 0.0156250s\r
+State Machine: 2 (Sync)\r
+";
+
+    const FREE_RUNNING: &str = "Leap Indicator: 3(not synchronized)
+Stratum: 0 (unspecified)
+Precision: -23 (119.209ns per tick)
+Root Delay: 0.0000000s
+Root Dispersion: 0.0000000s
+ReferenceId: 0x00000000 (unspecified)
+Last Successful Sync Time: unspecified
+Source: Local CMOS Clock
+Poll Interval: 6 (64s)
+";
+
+    #[test]
+    fn test_parses_w32tm() {
+        let status = W32tmStatus::parse(SYNCED);
+        assert_eq!(status.leap_indicator, Some(0));
+        assert_eq!(status.stratum, Some(4));
+        assert_eq!(status.precision, Some(-23));
+        assert_eq!(status.root_delay_ms, Some(31.25));
+        assert_eq!(status.source.as_deref(), Some("time.windows.com"));
+        assert!((status.phase_offset_ms.unwrap() + 0.8474).abs() < 1e-9);
+        assert!(status.synced());
+
+        let status = status.to_status();
+        assert!(status.synced);
+        assert_eq!(status.stratum, 4);
+        assert_eq!(status.refid.as_deref(), Some("time.windows.com"));
+        assert!(!status.shm_valid);
+    }
+
+    #[test]
+    fn test_free_running_w32tm_is_not_synced() {
+        let status = W32tmStatus::parse(FREE_RUNNING);
+        assert_eq!(status.leap_indicator, Some(3));
+        assert!(!status.synced());
+        assert_eq!(status.to_status().stratum, 16);
+
+        // Localized output reads as nothing known, not as synced
+        assert!(!W32tmStatus::parse("Indicateur de saut : 0").synced());
+    }
+
+    #[test]
+    fn test_backend_matches_target() {
+        let backend = StatusBackend::current();
+        if cfg!(unix) {
+            assert_eq!(backend, StatusBackend::Ntpq);
+        } else if cfg!(windows) {
+            assert_eq!(backend, StatusBackend::W32tm);
+        } else {
+            assert_eq!(backend, StatusBackend::Unsupported);
+        }
+        let status = unsupported_status();
+        assert_eq!(status["code"], "not_supported");
+        assert_eq!(status["platform"], std::env::consts::OS);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_w32tm_only_on_windows() {
+        let error = w32tm_status().await.unwrap_err();
+        assert_eq!(error.code(), "not_supported");
+    }
+}
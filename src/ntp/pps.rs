@@ -0,0 +1,515 @@
+// PPS (pulse-per-second) device monitoring
+//
+// Polls `/dev/ppsN` with the PPS_FETCH ioctl (time_pps_fetch with a zero
+// timeout), tracks assert/clear events and measures pulse-interval jitter.
+// The ioctl sits behind `PpsSource` so the tracker runs in tests without a
+// device.
+
+use super::config::{NtpConfig, PpsConfig};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Pulses kept for jitter statistics
+pub const JITTER_WINDOW: usize = 64;
+
+/// Without a new pulse for this long the signal is considered lost
+const PULSE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// Several polls per pulse so consecutive sequence numbers are seen
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Pause before reopening a device that failed or is missing
+const REOPEN_DELAY: Duration = Duration::from_secs(5);
+
+/// One PPS_FETCH result; times are `(seconds, nanoseconds)` since the epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PpsFetch {
+    pub assert_sequence: u32,
+    pub assert_time: (i64, u32),
+    pub clear_sequence: u32,
+    pub clear_time: (i64, u32),
+}
+
+/// Anything that can report the latest PPS event (a device or a test mock)
+pub trait PpsSource: Send {
+    fn fetch(&mut self) -> io::Result<PpsFetch>;
+}
+
+/// A `/dev/ppsN` character device
+pub struct PpsDevice {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    file: std::fs::File,
+}
+
+impl PpsDevice {
+    #[cfg(target_os = "linux")]
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            file: std::fs::File::open(path)?,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn open(_path: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "PPS devices are only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod ioctl {
+    // Layouts from <linux/pps.h>
+
+    #[repr(C)]
+    #[derive(Default, Clone, Copy)]
+    pub struct PpsKtime {
+        pub sec: i64,
+        pub nsec: i32,
+        pub flags: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default, Clone, Copy)]
+    pub struct PpsKinfo {
+        pub assert_sequence: u32,
+        pub clear_sequence: u32,
+        pub assert_tu: PpsKtime,
+        pub clear_tu: PpsKtime,
+        pub current_mode: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Default, Clone, Copy)]
+    pub struct PpsFdata {
+        pub info: PpsKinfo,
+        pub timeout: PpsKtime,
+    }
+
+    /// `_IOWR('p', 0xa4, struct pps_fdata *)`: the size field is that of a
+    /// pointer, as declared in the kernel header
+    pub const PPS_FETCH: u64 = (3 << 30)
+        | ((std::mem::size_of::<*const PpsFdata>() as u64) << 16)
+        | ((b'p' as u64) << 8)
+        | 0xa4;
+}
+
+#[cfg(target_os = "linux")]
+impl PpsSource for PpsDevice {
+    fn fetch(&mut self) -> io::Result<PpsFetch> {
+        use std::os::fd::AsRawFd;
+
+        // A zero timeout returns the latest event without waiting
+        let mut data = ioctl::PpsFdata::default();
+        let result = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                ioctl::PPS_FETCH as _,
+                &mut data as *mut ioctl::PpsFdata,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let info = data.info;
+        Ok(PpsFetch {
+            assert_sequence: info.assert_sequence,
+            assert_time: (info.assert_tu.sec, info.assert_tu.nsec as u32),
+            clear_sequence: info.clear_sequence,
+            clear_time: (info.clear_tu.sec, info.clear_tu.nsec as u32),
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl PpsSource for PpsDevice {
+    fn fetch(&mut self) -> io::Result<PpsFetch> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "PPS devices are only supported on Linux",
+        ))
+    }
+}
+
+/// Pulse interval statistics over the sliding window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JitterStats {
+    pub samples: usize,
+    pub mean_interval_s: f64,
+    /// RMS deviation of the intervals from their mean
+    pub rms_jitter_us: f64,
+    /// Largest deviation of any interval from the mean
+    pub max_deviation_us: f64,
+}
+
+/// Folds successive fetches into pulse counts and interval jitter
+#[derive(Debug, Default)]
+pub struct PpsTracker {
+    last: Option<PpsFetch>,
+    last_advance: Option<Instant>,
+    pulses_observed: u64,
+    /// Pulses that came and went between two polls
+    missed: u64,
+    /// Assert-to-assert intervals in nanoseconds
+    intervals: VecDeque<i64>,
+}
+
+impl PpsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a fetch taken at `observed`
+    pub fn update(&mut self, fetch: PpsFetch, observed: Instant) {
+        let Some(last) = self.last.replace(fetch) else {
+            // Baseline only: pulses before monitoring started are not counted
+            return;
+        };
+
+        let advanced = fetch.assert_sequence.wrapping_sub(last.assert_sequence);
+        if advanced == 0 {
+            return;
+        }
+        self.pulses_observed += advanced as u64;
+        self.last_advance = Some(observed);
+
+        if advanced == 1 {
+            let interval = nanos(fetch.assert_time) - nanos(last.assert_time);
+            if self.intervals.len() == JITTER_WINDOW {
+                self.intervals.pop_front();
+            }
+            self.intervals.push_back(interval);
+        } else {
+            self.missed += advanced as u64 - 1;
+        }
+    }
+
+    pub fn is_receiving(&self, now: Instant) -> bool {
+        self.last_advance
+            .is_some_and(|at| now.saturating_duration_since(at) < PULSE_TIMEOUT)
+    }
+
+    pub fn jitter(&self) -> Option<JitterStats> {
+        if self.intervals.is_empty() {
+            return None;
+        }
+        let n = self.intervals.len() as f64;
+        let mean = self.intervals.iter().map(|&i| i as f64).sum::<f64>() / n;
+        let deviations = self.intervals.iter().map(|&i| i as f64 - mean);
+        let rms = (deviations.clone().map(|d| d * d).sum::<f64>() / n).sqrt();
+        let max = deviations.map(f64::abs).fold(0.0, f64::max);
+
+        Some(JitterStats {
+            samples: self.intervals.len(),
+            mean_interval_s: mean / 1e9,
+            rms_jitter_us: rms / 1e3,
+            max_deviation_us: max / 1e3,
+        })
+    }
+}
+
+fn nanos((sec, nsec): (i64, u32)) -> i64 {
+    sec.saturating_mul(1_000_000_000)
+        .saturating_add(nsec as i64)
+}
+
+fn rfc3339((sec, nsec): (i64, u32)) -> Option<String> {
+    chrono::DateTime::from_timestamp(sec, nsec)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true))
+}
+
+/// Reported by the `get_pps_status` tool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PpsStatus {
+    pub enabled: bool,
+    /// The device is open and answering PPS_FETCH
+    pub available: bool,
+    pub device: Option<String>,
+    /// A pulse arrived within the last few seconds
+    pub receiving: bool,
+    pub last_pulse: Option<String>,
+    pub last_clear: Option<String>,
+    pub assert_sequence: Option<u32>,
+    pub clear_sequence: Option<u32>,
+    pub pulses_observed: u64,
+    pub missed_pulses: u64,
+    pub jitter: Option<JitterStats>,
+    pub error: Option<String>,
+}
+
+impl PpsStatus {
+    /// Status when PPS monitoring is not enabled
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            available: false,
+            device: None,
+            receiving: false,
+            last_pulse: None,
+            last_clear: None,
+            assert_sequence: None,
+            clear_sequence: None,
+            pulses_observed: 0,
+            missed_pulses: 0,
+            jitter: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    tracker: PpsTracker,
+    device: String,
+    available: bool,
+    error: Option<String>,
+}
+
+/// Shared view of the polling task's state
+#[derive(Debug, Clone, Default)]
+pub struct PpsMonitor {
+    state: Arc<Mutex<MonitorState>>,
+}
+
+impl PpsMonitor {
+    pub fn new(device: impl Into<String>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MonitorState {
+                device: device.into(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    pub fn status(&self) -> PpsStatus {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let tracker = &state.tracker;
+        let last = tracker.last.filter(|f| f.assert_sequence > 0);
+        PpsStatus {
+            enabled: true,
+            available: state.available,
+            device: Some(state.device.clone()),
+            receiving: state.available && tracker.is_receiving(Instant::now()),
+            last_pulse: last.and_then(|f| rfc3339(f.assert_time)),
+            last_clear: last
+                .filter(|f| f.clear_sequence > 0)
+                .and_then(|f| rfc3339(f.clear_time)),
+            assert_sequence: tracker.last.map(|f| f.assert_sequence),
+            clear_sequence: tracker.last.map(|f| f.clear_sequence),
+            pulses_observed: tracker.pulses_observed,
+            missed_pulses: tracker.missed,
+            jitter: tracker.jitter(),
+            error: state.error.clone(),
+        }
+    }
+
+    fn update(&self, fetch: PpsFetch) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.available = true;
+        state.error = None;
+        state.tracker.update(fetch, Instant::now());
+    }
+
+    fn record_error(&self, error: impl Into<String>) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.available = false;
+        state.error = Some(error.into());
+    }
+}
+
+/// Fetch from `source` every `interval` until it fails; returns the error
+pub async fn poll<S: PpsSource>(
+    mut source: S,
+    monitor: &PpsMonitor,
+    interval: Duration,
+) -> io::Error {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match source.fetch() {
+            Ok(fetch) => monitor.update(fetch),
+            Err(e) => return e,
+        }
+    }
+}
+
+/// Reopen the device whenever it fails, forever
+async fn run_device(config: PpsConfig, monitor: PpsMonitor) {
+    loop {
+        let error = match PpsDevice::open(&config.device) {
+            Ok(device) => {
+                info!(event = "pps.open", device = %config.device);
+                format!(
+                    "PPS_FETCH failed: {}",
+                    poll(device, &monitor, POLL_INTERVAL).await
+                )
+            }
+            Err(e) => format!("cannot open {}: {}", config.device, e),
+        };
+        warn!(event = "pps.unavailable", device = %config.device, error = %error);
+        monitor.record_error(error);
+
+        if cfg!(not(target_os = "linux")) {
+            return;
+        }
+        tokio::time::sleep(REOPEN_DELAY).await;
+    }
+}
+
+/// Start monitoring `config.device` in the background
+pub fn spawn(config: PpsConfig) -> PpsMonitor {
+    let monitor = PpsMonitor::new(&config.device);
+    tokio::spawn(run_device(config, monitor.clone()));
+    monitor
+}
+
+static GLOBAL: OnceLock<PpsMonitor> = OnceLock::new();
+
+/// Start PPS monitoring when `ENABLE_PPS=yes`
+pub fn init(config: &NtpConfig) {
+    if let Some(config) = config.pps.clone().filter(|p| p.enabled) {
+        let _ = GLOBAL.set(spawn(config));
+    }
+}
+
+/// The process-wide PPS monitor, if enabled
+pub fn global() -> Option<&'static PpsMonitor> {
+    GLOBAL.get()
+}
+
+/// Whether pulses are currently arriving on the monitored device
+pub fn is_receiving() -> bool {
+    global().is_some_and(|pps| pps.status().receiving)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: i64 = 1_700_000_000;
+
+    fn pulse(seq: u32, offset_ns: i64) -> PpsFetch {
+        let t = BASE * 1_000_000_000 + seq as i64 * 1_000_000_000 + offset_ns;
+        PpsFetch {
+            assert_sequence: seq,
+            assert_time: (t / 1_000_000_000, (t % 1_000_000_000) as u32),
+            clear_sequence: seq,
+            clear_time: (t / 1_000_000_000, 500_000_000),
+        }
+    }
+
+    /// Replays fetches, then reports the device gone
+    struct MockPps(VecDeque<PpsFetch>);
+
+    impl PpsSource for MockPps {
+        fn fetch(&mut self) -> io::Result<PpsFetch> {
+            self.0
+                .pop_front()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn test_jitter_over_window() {
+        let mut tracker = PpsTracker::new();
+        let start = Instant::now();
+        // Alternating +/-2us around the second
+        for seq in 1..=5 {
+            let offset = if seq % 2 == 0 { 2_000 } else { -2_000 };
+            tracker.update(pulse(seq, offset), start);
+        }
+
+        let jitter = tracker.jitter().unwrap();
+        assert_eq!(jitter.samples, 4);
+        assert!((jitter.mean_interval_s - 1.0).abs() < 1e-9);
+        assert!((jitter.rms_jitter_us - 4.0).abs() < 1e-6);
+        assert!((jitter.max_deviation_us - 4.0).abs() < 1e-6);
+        assert_eq!(tracker.pulses_observed, 4);
+    }
+
+    #[test]
+    fn test_repeated_and_missed_pulses() {
+        let mut tracker = PpsTracker::new();
+        let start = Instant::now();
+        tracker.update(pulse(10, 0), start);
+        assert!(!tracker.is_receiving(start), "baseline is not a new pulse");
+
+        // Polled twice within the same second
+        tracker.update(pulse(11, 0), start);
+        tracker.update(pulse(11, 0), start);
+        // Two pulses between polls: counted, but no interval recorded
+        tracker.update(pulse(13, 0), start);
+
+        assert_eq!(tracker.pulses_observed, 3);
+        assert_eq!(tracker.missed, 1);
+        assert_eq!(tracker.jitter().unwrap().samples, 1);
+        assert!(tracker.is_receiving(start + Duration::from_secs(1)));
+        assert!(!tracker.is_receiving(start + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut tracker = PpsTracker::new();
+        let start = Instant::now();
+        for seq in 0..(JITTER_WINDOW as u32 + 10) {
+            tracker.update(pulse(seq, 0), start);
+        }
+        assert_eq!(tracker.jitter().unwrap().samples, JITTER_WINDOW);
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_device_disappears() {
+        let monitor = PpsMonitor::new("/dev/pps-test");
+        let fetches = (1..=3).map(|seq| pulse(seq, 0)).collect();
+
+        let error = poll(MockPps(fetches), &monitor, Duration::from_millis(1)).await;
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let status = monitor.status();
+        assert!(status.available);
+        assert!(status.receiving);
+        assert_eq!(status.assert_sequence, Some(3));
+        assert_eq!(status.pulses_observed, 2);
+        assert_eq!(
+            status.last_pulse.as_deref(),
+            Some("2023-11-14T22:13:23.000000000Z")
+        );
+        assert!(status.jitter.is_some());
+
+        monitor.record_error("gone");
+        let status = monitor.status();
+        assert!(!status.available);
+        assert!(!status.receiving);
+        assert_eq!(status.error.as_deref(), Some("gone"));
+    }
+
+    #[test]
+    fn test_missing_device_is_not_a_panic() {
+        assert!(PpsDevice::open("/dev/pps-does-not-exist").is_err());
+        assert!(!PpsStatus::disabled().available);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pps_fetch_request_number() {
+        // Matches PPS_FETCH from <linux/pps.h> on 64-bit targets
+        if std::mem::size_of::<usize>() == 8 {
+            assert_eq!(ioctl::PPS_FETCH, 0xc008_70a4);
+        }
+    }
+}
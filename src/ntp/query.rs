@@ -0,0 +1,304 @@
+// Bounded execution of external NTP commands
+//
+// Every `ntpq` invocation goes through one `NtpQueryBackend`. The production
+// backend holds a semaphore (`NTP_QUERY_CONCURRENCY`, default 2), so a burst
+// of tool calls queues instead of forking a process per call. A call that
+// waits longer than `NTP_QUERY_QUEUE_WAIT_MS` for a permit gives up with
+// `Busy`; a command that runs longer than two seconds is killed.
+
+use super::NtpConfig;
+use crate::error::TimeServerError;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::process::Output;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+pub const DEFAULT_NTP_QUERY_CONCURRENCY: usize = 2;
+pub const DEFAULT_NTP_QUERY_QUEUE_WAIT_MS: u64 = 3000;
+
+/// Longest a single `ntpq` run may take
+pub const NTP_COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NtpQueryConfig {
+    /// `ntpq` processes allowed to run at once
+    pub concurrency: usize,
+    /// How long a call waits for a free slot before reporting busy
+    pub queue_wait_ms: u64,
+}
+
+impl Default for NtpQueryConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_NTP_QUERY_CONCURRENCY,
+            queue_wait_ms: DEFAULT_NTP_QUERY_QUEUE_WAIT_MS,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NtpQueryError {
+    #[error("ntpq command not found")]
+    NotFound,
+
+    #[error("ntpq command timed out")]
+    TimedOut,
+
+    #[error("NTP queries are busy; waited {waited_ms} ms for a slot")]
+    Busy { waited_ms: u64 },
+
+    #[error("ntpq failed: {0}")]
+    Failed(String),
+}
+
+impl From<NtpQueryError> for TimeServerError {
+    fn from(error: NtpQueryError) -> Self {
+        match error {
+            NtpQueryError::Busy { waited_ms } => TimeServerError::Busy {
+                retry_after_ms: waited_ms,
+            },
+            other => TimeServerError::NtpUnavailable {
+                reason: other.to_string(),
+            },
+        }
+    }
+}
+
+/// Runs `ntpq` and returns its standard output
+pub trait NtpQueryBackend: Send + Sync {
+    fn ntpq<'a>(&'a self, args: &'a [&'a str]) -> BoxFuture<'a, Result<String, NtpQueryError>>;
+}
+
+/// Starts a process and collects its output
+pub trait CommandRunner: Send + Sync {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+    ) -> BoxFuture<'a, io::Result<Output>>;
+}
+
+/// `tokio::process`, killing the child if the caller stops waiting
+pub struct TokioRunner;
+
+impl CommandRunner for TokioRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+    ) -> BoxFuture<'a, io::Result<Output>> {
+        Box::pin(
+            tokio::process::Command::new(program)
+                .args(args)
+                .kill_on_drop(true)
+                .output(),
+        )
+    }
+}
+
+/// The production backend: `ntpq` behind a semaphore, with a queue deadline
+/// and a per-command timeout
+pub struct NtpqBackend<R = TokioRunner> {
+    runner: R,
+    permits: Semaphore,
+    queue_wait: Duration,
+    command_timeout: Duration,
+    /// Commands started, successful or not
+    runs: AtomicU64,
+}
+
+impl NtpqBackend {
+    pub fn new(config: &NtpQueryConfig) -> Self {
+        Self::with_runner(TokioRunner, config)
+    }
+}
+
+impl<R: CommandRunner> NtpqBackend<R> {
+    pub fn with_runner(runner: R, config: &NtpQueryConfig) -> Self {
+        Self {
+            runner,
+            permits: Semaphore::new(config.concurrency.max(1)),
+            queue_wait: Duration::from_millis(config.queue_wait_ms),
+            command_timeout: NTP_COMMAND_TIMEOUT,
+            runs: AtomicU64::new(0),
+        }
+    }
+
+    /// Override the per-command timeout (defaults to [`NTP_COMMAND_TIMEOUT`])
+    pub fn with_command_timeout(mut self, command_timeout: Duration) -> Self {
+        self.command_timeout = command_timeout;
+        self
+    }
+
+    /// `ntpq` processes started through this backend
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    async fn query(&self, args: &[&str]) -> Result<String, NtpQueryError> {
+        let queued = Instant::now();
+        let _permit = match timeout(self.queue_wait, self.permits.acquire()).await {
+            Ok(Ok(permit)) => permit,
+            // The semaphore is never closed; treat it like a full queue
+            Ok(Err(_)) | Err(_) => {
+                return Err(NtpQueryError::Busy {
+                    waited_ms: queued.elapsed().as_millis() as u64,
+                })
+            }
+        };
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        let output = match timeout(self.command_timeout, self.runner.run("ntpq", args)).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(NtpQueryError::NotFound)
+            }
+            Ok(Err(e)) => return Err(NtpQueryError::Failed(e.to_string())),
+            Err(_) => return Err(NtpQueryError::TimedOut),
+        };
+        if !output.status.success() {
+            return Err(NtpQueryError::Failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl<R: CommandRunner> NtpQueryBackend for NtpqBackend<R> {
+    fn ntpq<'a>(&'a self, args: &'a [&'a str]) -> BoxFuture<'a, Result<String, NtpQueryError>> {
+        Box::pin(self.query(args))
+    }
+}
+
+static GLOBAL: OnceLock<NtpqBackend> = OnceLock::new();
+
+/// Size the process-wide backend from `config`; call before the first query
+pub fn init(config: &NtpConfig) {
+    let _ = GLOBAL.set(NtpqBackend::new(&config.query));
+}
+
+/// The process-wide backend, with default limits if `init` was not called
+pub fn global() -> &'static NtpqBackend {
+    GLOBAL.get_or_init(|| NtpqBackend::new(&NtpQueryConfig::default()))
+}
+
+/// The tests build exit statuses from raw Unix wait codes
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Sleeps instead of forking, recording how many runs overlap
+    #[derive(Default)]
+    struct SlowRunner {
+        delay: Duration,
+        running: AtomicUsize,
+        high_water: AtomicUsize,
+        runs: AtomicUsize,
+    }
+
+    impl CommandRunner for SlowRunner {
+        fn run<'a>(
+            &'a self,
+            _program: &'a str,
+            args: &'a [&'a str],
+        ) -> BoxFuture<'a, io::Result<Output>> {
+            Box::pin(async move {
+                let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.high_water.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(self.delay).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                self.runs.fetch_add(1, Ordering::SeqCst);
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: args.join(" ").into_bytes(),
+                    stderr: Vec::new(),
+                })
+            })
+        }
+    }
+
+    fn backend(
+        delay_ms: u64,
+        concurrency: usize,
+        queue_wait_ms: u64,
+    ) -> Arc<NtpqBackend<SlowRunner>> {
+        let runner = SlowRunner {
+            delay: Duration::from_millis(delay_ms),
+            ..Default::default()
+        };
+        Arc::new(NtpqBackend::with_runner(
+            runner,
+            &NtpQueryConfig {
+                concurrency,
+                queue_wait_ms,
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries_never_exceed_permits() {
+        let backend = backend(20, 2, 5_000);
+        let calls: Vec<_> = (0..10)
+            .map(|_| {
+                let backend = backend.clone();
+                tokio::spawn(async move { backend.ntpq(&["-p", "-n"]).await })
+            })
+            .collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap(), "-p -n");
+        }
+        assert_eq!(backend.runner.runs.load(Ordering::SeqCst), 10);
+        assert_eq!(backend.runner.high_water.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_deadline_reports_busy() {
+        let backend = backend(300, 1, 20);
+        let first = {
+            let backend = backend.clone();
+            tokio::spawn(async move { backend.ntpq(&["-c", "rv"]).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let error = backend.ntpq(&["-c", "rv"]).await.unwrap_err();
+        assert!(matches!(error, NtpQueryError::Busy { waited_ms } if waited_ms >= 20));
+        let error = TimeServerError::from(error);
+        assert_eq!(error.code(), "busy");
+
+        assert!(first.await.unwrap().is_ok());
+        assert_eq!(backend.runner.runs.load(Ordering::SeqCst), 1);
+        // The refused call never started a process
+        assert_eq!(backend.runs(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_command_times_out() {
+        let runner = SlowRunner {
+            delay: Duration::from_millis(300),
+            ..Default::default()
+        };
+        let backend = NtpqBackend::with_runner(runner, &NtpQueryConfig::default())
+            .with_command_timeout(Duration::from_millis(20));
+        assert!(matches!(
+            backend.ntpq(&["-p"]).await,
+            Err(NtpQueryError::TimedOut)
+        ));
+        // The permit is released with the abandoned run
+        assert_eq!(backend.permits.available_permits(), 2);
+    }
+}
@@ -0,0 +1,406 @@
+// NTPsec refclock shared memory (the SHM driver, units 0-3)
+//
+// ntpd reads, and gpsd or this server's GPS task writes, one SysV shared
+// memory segment per unit. SysV shm only exists on Unix: elsewhere
+// `NtpShmInterface` keeps its API so callers compile unchanged, but
+// attaching fails with `NotSupported` and the clock uses the system time.
+
+use crate::error::TimeServerError;
+
+/// One refclock sample as published to a SHM unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShmSample {
+    /// Time reported by the reference clock
+    pub clock_sec: i64,
+    pub clock_nsec: u32,
+    /// System time when the reference time was received
+    pub receive_sec: i64,
+    pub receive_nsec: u32,
+    /// NTP leap indicator (0 = none, 1 = insert, 2 = delete, 3 = unsynchronized)
+    pub leap: i32,
+    /// Precision as log2 seconds
+    pub precision: i32,
+}
+
+impl NtpShmInterface {
+    /// Create SHM interface for NTPsec unit 0-3
+    /// Unit 0 corresponds to SHM(0) in ntp.conf, uses key 0x4e545030
+    /// Unit 1 corresponds to SHM(1) in ntp.conf, uses key 0x4e545031, etc.
+    pub fn new(unit: u8) -> Result<Self, TimeServerError> {
+        if unit > 3 {
+            return Err(TimeServerError::InvalidArgument(
+                "SHM unit must be 0-3".to_string(),
+            ));
+        }
+
+        // NTPsec uses magic keys: 0x4e545030 + unit number
+        #[cfg(unix)]
+        let shm = Self::create(0x4e545030 + unit as i32, unit, false);
+        #[cfg(not(unix))]
+        let shm = Err(TimeServerError::not_supported("NTP shared memory"));
+        crate::startup::record_shm_attach(unit, shm.as_ref().map(|_| ()));
+        shm
+    }
+
+    /// Read current time data from shared memory
+    pub fn read_time(&self) -> Option<(i64, u32, bool)> {
+        self.read_sample()
+            .map(|sample| (sample.clock_sec, sample.clock_nsec, true))
+    }
+
+    /// Get the unit number
+    pub fn unit(&self) -> u8 {
+        self.unit
+    }
+}
+
+#[cfg(unix)]
+pub use sysv::{NtpShmInterface, NtpShmTime};
+
+#[cfg(unix)]
+mod sysv {
+    use super::ShmSample;
+    use crate::error::TimeServerError;
+    use libc::{shmat, shmdt, shmget, IPC_CREAT};
+    use std::ptr::{self, addr_of, addr_of_mut, NonNull};
+    use std::sync::atomic::{fence, Ordering};
+    use std::sync::Arc;
+
+    const NTP_SHM_SIZE: usize = 96;
+
+    /// NTPsec shared memory structure for time exchange
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct NtpShmTime {
+        mode: i32,                    // 0: both RW, 1: RW by ntpd, RO by us
+        count: i32,                   // Updated each write
+        clock_time_sec: i64,          // Clock timestamp seconds
+        clock_time_usec: i32,         // Clock timestamp microseconds
+        receive_time_sec: i64,        // When timestamp was received
+        receive_time_usec: i32,       // Receive time microseconds
+        leap: i32,                    // Leap second indicator
+        precision: i32,               // Clock precision (log2 seconds)
+        nsamples: i32,                // Number of samples
+        valid: i32,                   // 0: invalid, 1: valid
+        clock_time_stamp_nsec: u32,   // Nanosecond resolution
+        receive_time_stamp_nsec: u32, // Nanosecond resolution
+        dummy: [i32; 8],              // Reserved for future use
+    }
+
+    /// Attempts at a consistent SHM read before giving up; ntpd or gpsd
+    /// rewriting the segment during every attempt means it is busy, not broken
+    const SHM_READ_ATTEMPTS: usize = 4;
+
+    /// One attachment of an SHM segment, detached exactly once when the last
+    /// handle to it drops
+    ///
+    /// Another process writes the segment at any time, so no Rust reference to
+    /// it is ever formed: every field is read and written with volatile
+    /// accesses through the raw pointer, which the compiler may neither cache
+    /// nor elide. Whether a read is consistent is decided by the `count`
+    /// protocol in [`NtpShmInterface::read_sample`], not by locking.
+    #[derive(Debug)]
+    pub(super) struct ShmMapping {
+        pub(super) shm_id: i32,
+        ptr: NonNull<NtpShmTime>,
+        /// A private segment is removed along with the last attachment
+        private: bool,
+    }
+
+    // SAFETY: the pointer stays valid until `drop`, and all access through it
+    // is volatile; concurrent writers are expected and detected by `count`
+    unsafe impl Send for ShmMapping {}
+    unsafe impl Sync for ShmMapping {}
+
+    impl ShmMapping {
+        /// Attach segment `shm_id`
+        fn attach(shm_id: i32, private: bool) -> Result<Self, TimeServerError> {
+            // SAFETY: shmat maps a segment we own or were granted by key
+            let raw = unsafe { shmat(shm_id, ptr::null(), 0) };
+            if raw as isize == -1 {
+                return Err(TimeServerError::NtpUnavailable {
+                    reason: format!(
+                        "Failed to attach SHM segment: {}",
+                        std::io::Error::last_os_error()
+                    ),
+                });
+            }
+            let ptr = NonNull::new(raw as *mut NtpShmTime).ok_or_else(|| {
+                TimeServerError::NtpUnavailable {
+                    reason: "SHM segment attached at address 0".to_string(),
+                }
+            })?;
+            Ok(Self {
+                shm_id,
+                ptr,
+                private,
+            })
+        }
+
+        fn as_ptr(&self) -> *mut NtpShmTime {
+            self.ptr.as_ptr()
+        }
+    }
+
+    impl Drop for ShmMapping {
+        fn drop(&mut self) {
+            // SAFETY: attached in `attach`, and this is the only detach
+            unsafe {
+                shmdt(self.ptr.as_ptr() as *const libc::c_void);
+                if self.private {
+                    libc::shmctl(self.shm_id, libc::IPC_RMID, ptr::null_mut());
+                }
+            }
+        }
+    }
+
+    /// Read one field of the segment
+    macro_rules! shm_read {
+        ($shm:expr, $field:ident) => {
+            // SAFETY: the mapping is live for as long as `$shm` is
+            unsafe { ptr::read_volatile(addr_of!((*$shm).$field)) }
+        };
+    }
+
+    /// Write one field of the segment
+    macro_rules! shm_write {
+        ($shm:expr, $field:ident, $value:expr) => {
+            // SAFETY: the mapping is live for as long as `$shm` is
+            unsafe { ptr::write_volatile(addr_of_mut!((*$shm).$field), $value) }
+        };
+    }
+
+    /// Shared memory interface to NTPsec
+    ///
+    /// Cloning is cheap and shares the attachment; the segment is detached when
+    /// the last clone drops.
+    #[derive(Debug, Clone)]
+    pub struct NtpShmInterface {
+        pub(super) mapping: Arc<ShmMapping>,
+        pub(super) unit: u8,
+    }
+
+    impl NtpShmInterface {
+        /// A fresh segment nothing else can find by key, removed when the last
+        /// clone drops; for tests and simulated refclocks
+        pub fn private() -> Result<Self, TimeServerError> {
+            Self::create(libc::IPC_PRIVATE, 0, true)
+        }
+
+        pub(crate) fn create(
+            key: libc::key_t,
+            unit: u8,
+            private: bool,
+        ) -> Result<Self, TimeServerError> {
+            Self::get(key, unit, IPC_CREAT | 0o666, private)
+        }
+
+        /// Attach the segment `key` names only if it already exists, as the
+        /// clock finds ntpd's when ntpd created it first
+        #[cfg(test)]
+        pub(crate) fn open(key: libc::key_t, unit: u8) -> Result<Self, TimeServerError> {
+            Self::get(key, unit, 0, false)
+        }
+
+        fn get(
+            key: libc::key_t,
+            unit: u8,
+            flags: libc::c_int,
+            private: bool,
+        ) -> Result<Self, TimeServerError> {
+            // Get or create shared memory segment
+            // SAFETY: shmget has no memory-safety preconditions
+            let shm_id = unsafe { shmget(key, NTP_SHM_SIZE, flags) };
+            if shm_id < 0 {
+                return Err(TimeServerError::NtpUnavailable {
+                    reason: format!(
+                        "Failed to get SHM segment for unit {}: {}",
+                        unit,
+                        std::io::Error::last_os_error()
+                    ),
+                });
+            }
+            let mapping = ShmMapping::attach(shm_id, private)?;
+
+            // Initialize the structure if it's new
+            let shm = mapping.as_ptr();
+            if shm_read!(shm, mode) == 0 && shm_read!(shm, count) == 0 {
+                let initial = NtpShmTime {
+                    mode: 1, // Mode 1: ntpd writes, we read
+                    count: 0,
+                    clock_time_sec: 0,
+                    clock_time_usec: 0,
+                    receive_time_sec: 0,
+                    receive_time_usec: 0,
+                    leap: 0,
+                    precision: -20, // Microsecond precision
+                    nsamples: 0,
+                    valid: 0,
+                    clock_time_stamp_nsec: 0,
+                    receive_time_stamp_nsec: 0,
+                    dummy: [0; 8],
+                };
+                // SAFETY: the segment is at least NTP_SHM_SIZE bytes
+                unsafe { ptr::write_volatile(shm, initial) };
+            }
+
+            Ok(NtpShmInterface {
+                mapping: Arc::new(mapping),
+                unit,
+            })
+        }
+
+        /// Read the full sample, or `None` when it is invalid or was being
+        /// rewritten during every attempt
+        ///
+        /// The writer clears `valid` and bumps `count` before changing the
+        /// fields, and bumps `count` again before setting `valid`. A reader takes
+        /// `count`, the fields, then `valid` and `count` again: a sample whose
+        /// `count` changed, or that was not valid afterwards, may be torn and is
+        /// read again.
+        pub fn read_sample(&self) -> Option<ShmSample> {
+            let shm = self.mapping.as_ptr();
+            for _ in 0..SHM_READ_ATTEMPTS {
+                let count = shm_read!(shm, count);
+                fence(Ordering::SeqCst);
+                let sample = ShmSample {
+                    clock_sec: shm_read!(shm, clock_time_sec),
+                    clock_nsec: shm_read!(shm, clock_time_stamp_nsec),
+                    receive_sec: shm_read!(shm, receive_time_sec),
+                    receive_nsec: shm_read!(shm, receive_time_stamp_nsec),
+                    leap: shm_read!(shm, leap),
+                    precision: shm_read!(shm, precision),
+                };
+                fence(Ordering::SeqCst);
+                let valid = shm_read!(shm, valid) == 1;
+                let unchanged = shm_read!(shm, count) == count;
+                if valid && unchanged {
+                    return Some(sample);
+                }
+                if unchanged {
+                    // Not being rewritten, just invalid
+                    return None;
+                }
+                std::hint::spin_loop();
+            }
+            None
+        }
+
+        /// Publish a sample as the refclock writer (the role gpsd normally plays).
+        ///
+        /// Uses mode 0: `valid` is cleared while the fields change and `count` is
+        /// bumped before and after, so ntpd never consumes a half-written sample.
+        pub fn write_sample(&self, sample: &ShmSample) {
+            let shm = self.mapping.as_ptr();
+            shm_write!(shm, valid, 0);
+            shm_write!(shm, mode, 0);
+            let count = shm_read!(shm, count);
+            shm_write!(shm, count, count.wrapping_add(1));
+            fence(Ordering::SeqCst);
+
+            shm_write!(shm, clock_time_sec, sample.clock_sec);
+            shm_write!(shm, clock_time_usec, (sample.clock_nsec / 1000) as i32);
+            shm_write!(shm, clock_time_stamp_nsec, sample.clock_nsec);
+            shm_write!(shm, receive_time_sec, sample.receive_sec);
+            shm_write!(shm, receive_time_usec, (sample.receive_nsec / 1000) as i32);
+            shm_write!(shm, receive_time_stamp_nsec, sample.receive_nsec);
+            shm_write!(shm, leap, sample.leap);
+            shm_write!(shm, precision, sample.precision);
+
+            fence(Ordering::SeqCst);
+            shm_write!(shm, count, count.wrapping_add(2));
+            shm_write!(shm, valid, 1);
+        }
+
+        /// Check if the shared memory has valid data
+        pub fn is_valid(&self) -> bool {
+            shm_read!(self.mapping.as_ptr(), valid) == 1
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub use unsupported::NtpShmInterface;
+
+/// Without SysV shm no interface is ever attached, so these never run
+#[cfg(not(unix))]
+mod unsupported {
+    use super::ShmSample;
+    use crate::error::TimeServerError;
+
+    #[derive(Debug, Clone)]
+    pub struct NtpShmInterface {
+        pub(super) unit: u8,
+    }
+
+    impl NtpShmInterface {
+        pub fn private() -> Result<Self, TimeServerError> {
+            Err(TimeServerError::not_supported("NTP shared memory"))
+        }
+
+        pub fn read_sample(&self) -> Option<ShmSample> {
+            None
+        }
+
+        pub fn write_sample(&self, _sample: &ShmSample) {}
+
+        pub fn is_valid(&self) -> bool {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn sample(n: i64) -> ShmSample {
+        ShmSample {
+            clock_sec: n,
+            clock_nsec: 250,
+            receive_sec: n,
+            receive_nsec: 500,
+            leap: 0,
+            precision: -20,
+        }
+    }
+
+    #[test]
+    fn test_unit_out_of_range() {
+        let error = NtpShmInterface::new(4).unwrap_err();
+        assert_eq!(error.code(), "invalid_argument");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_last_clone_detaches_and_removes_a_private_segment() {
+        let shm = NtpShmInterface::private().unwrap();
+        let shm_id = shm.mapping.shm_id;
+        let reader = shm.clone();
+        shm.write_sample(&sample(5));
+        drop(shm);
+        assert_eq!(reader.read_time(), Some((5, 250, true)));
+
+        let exists = || {
+            // SAFETY: IPC_STAT only writes into `info`
+            let mut info: libc::shmid_ds = unsafe { std::mem::zeroed() };
+            unsafe { libc::shmctl(shm_id, libc::IPC_STAT, &mut info) == 0 }
+        };
+        assert!(exists());
+        drop(reader);
+        assert!(!exists());
+    }
+
+    #[cfg(not(unix))]
+    #[test]
+    fn test_shm_is_not_supported() {
+        for error in [
+            NtpShmInterface::new(0).unwrap_err(),
+            NtpShmInterface::private().unwrap_err(),
+        ] {
+            assert_eq!(error.code(), "not_supported");
+            assert_eq!(error.details()["platform"], std::env::consts::OS);
+        }
+    }
+}
@@ -0,0 +1,422 @@
+// SNTP responder: answers NTP client packets over UDP
+//
+// Opt-in with `ENABLE_SNTP_SERVER=true`. Replies carry the server's best time
+// (system time plus the cached NTP offset when one is fresh) and the stratum
+// and precision last reported by ntpd. Replies are never larger than the
+// request and each source address is rate limited, so the responder cannot
+// be used to amplify traffic.
+
+use super::correction;
+use super::packet::{
+    short_format, NtpPacket, NtpTimestamp, LEAP_NONE, LEAP_UNSYNCHRONIZED, MODE_CLIENT,
+    MODE_SERVER, STRATUM_UNSYNCHRONIZED,
+};
+use super::sync::{NtpStatus, NtpSyncedClock};
+use crate::error::TimeServerError;
+use crate::state::ServerState;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Unprivileged default; port 123 needs root or CAP_NET_BIND_SERVICE
+pub const DEFAULT_SNTP_PORT: u16 = 1123;
+
+/// Replies per second allowed for one source address
+const DEFAULT_RATE_LIMIT: u32 = 4;
+
+/// How often the stratum and sync state are re-read from ntpd
+const STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(16);
+
+/// Forget idle sources once the table grows past this many entries
+const MAX_TRACKED_SOURCES: usize = 4096;
+
+/// Status served before the first ntpd reading arrives
+fn unsynchronized() -> NtpStatus {
+    NtpStatus {
+        synced: false,
+        offset_ms: 0.0,
+        stratum: STRATUM_UNSYNCHRONIZED,
+        precision: -20,
+        root_delay: 0.0,
+        root_dispersion: 0.0,
+        shm_valid: false,
+        pps_enabled: false,
+        ..Default::default()
+    }
+}
+
+/// Build the reply to `request`, received at `receive` and sent at `transmit`
+pub fn build_reply(
+    request: &NtpPacket,
+    status: &NtpStatus,
+    reference: NtpTimestamp,
+    receive: NtpTimestamp,
+    transmit: NtpTimestamp,
+) -> NtpPacket {
+    let synced = status.synced && (1..STRATUM_UNSYNCHRONIZED).contains(&status.stratum);
+    let (leap, stratum, reference_id, reference) = if synced {
+        (LEAP_NONE, status.stratum, reference_id(status), reference)
+    } else {
+        // Kiss code: the server has not synchronized yet
+        (
+            LEAP_UNSYNCHRONIZED,
+            STRATUM_UNSYNCHRONIZED,
+            *b"INIT",
+            NtpTimestamp::ZERO,
+        )
+    };
+
+    NtpPacket {
+        leap,
+        version: request.version,
+        mode: MODE_SERVER,
+        stratum,
+        poll: request.poll,
+        precision: status.precision,
+        // ntpq reports both in milliseconds
+        root_delay: short_format(status.root_delay / 1000.0),
+        root_dispersion: short_format(status.root_dispersion / 1000.0),
+        reference_id,
+        reference,
+        origin: request.transmit,
+        receive,
+        transmit,
+    }
+}
+
+/// Identify the time source; upstream server addresses are not known here
+fn reference_id(status: &NtpStatus) -> [u8; 4] {
+    if status.pps_enabled {
+        *b"PPS\0"
+    } else if status.shm_valid {
+        *b"SHM\0"
+    } else {
+        *b"LOCL"
+    }
+}
+
+/// Fixed-window limit on replies per source address
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: u32,
+    windows: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Count a request from `source` at `now`; false once it is over the limit
+    pub fn allow(&mut self, source: IpAddr, now: Instant) -> bool {
+        if self.windows.len() >= MAX_TRACKED_SOURCES {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < Duration::from_secs(1));
+        }
+        let (start, count) = self.windows.entry(source).or_insert((now, 0));
+        if now.duration_since(*start) >= Duration::from_secs(1) {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.per_second
+    }
+}
+
+/// Settings read from the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SntpConfig {
+    pub bind: SocketAddr,
+    pub rate_limit: u32,
+}
+
+impl SntpConfig {
+    /// `None` unless `ENABLE_SNTP_SERVER=true`; `SNTP_BIND`, `SNTP_PORT` and
+    /// `SNTP_RATE_LIMIT` (replies per second per source) adjust the defaults
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("ENABLE_SNTP_SERVER")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1" || v.eq_ignore_ascii_case("yes"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let ip = std::env::var("SNTP_BIND")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(IpAddr::from([0, 0, 0, 0]));
+        let port = std::env::var("SNTP_PORT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SNTP_PORT);
+        let rate_limit = std::env::var("SNTP_RATE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RATE_LIMIT);
+        Some(Self {
+            bind: SocketAddr::new(ip, port),
+            rate_limit,
+        })
+    }
+}
+
+/// A bound responder; `spawn` starts serving
+pub struct SntpResponder {
+    socket: UdpSocket,
+    status: watch::Receiver<(NtpStatus, NtpTimestamp)>,
+    limiter: RateLimiter,
+}
+
+impl SntpResponder {
+    /// Bind `addr`; replies use whatever `status` currently holds together
+    /// with the time the status was last confirmed (the reference timestamp)
+    pub async fn bind(
+        addr: SocketAddr,
+        status: watch::Receiver<(NtpStatus, NtpTimestamp)>,
+        rate_limit: u32,
+    ) -> Result<Self, TimeServerError> {
+        let socket = UdpSocket::bind(addr).await?;
+        Ok(Self {
+            socket,
+            status,
+            limiter: RateLimiter::new(rate_limit),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, TimeServerError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn spawn(self) -> SntpHandle {
+        let (tx, rx) = oneshot::channel();
+        let task = tokio::spawn(self.run(rx));
+        SntpHandle {
+            shutdown: Some(tx),
+            responder: Some(task),
+            refresh: None,
+        }
+    }
+
+    async fn run(mut self, mut shutdown: oneshot::Receiver<()>) {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, peer) = tokio::select! {
+                _ = &mut shutdown => break,
+                received = self.socket.recv_from(&mut buf) => match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        // ICMP errors from earlier replies surface here on some platforms
+                        debug!(event = "sntp.recv_failed", error = %e);
+                        continue;
+                    }
+                },
+            };
+            let receive = NtpTimestamp::from(now());
+
+            let request = match NtpPacket::decode(&buf[..len]) {
+                Ok(request) if request.mode == MODE_CLIENT => request,
+                Ok(request) => {
+                    debug!(event = "sntp.ignored", peer = %peer, mode = request.mode);
+                    continue;
+                }
+                Err(e) => {
+                    debug!(event = "sntp.malformed", peer = %peer, error = %e);
+                    continue;
+                }
+            };
+            if !self.limiter.allow(peer.ip(), Instant::now()) {
+                debug!(event = "sntp.rate_limited", peer = %peer);
+                continue;
+            }
+
+            let (status, reference) = self.status.borrow().clone();
+            let reply = build_reply(&request, &status, reference, receive, now().into());
+            if let Err(e) = self.socket.send_to(&reply.encode(), peer).await {
+                debug!(event = "sntp.send_failed", peer = %peer, error = %e);
+            }
+        }
+        debug!(event = "sntp.stop");
+    }
+}
+
+/// Running responder tasks; stopped by `shutdown` or on drop
+pub struct SntpHandle {
+    shutdown: Option<oneshot::Sender<()>>,
+    responder: Option<JoinHandle<()>>,
+    refresh: Option<JoinHandle<()>>,
+}
+
+impl SntpHandle {
+    /// Stop answering and wait for the receive loop to exit
+    pub async fn shutdown(mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        if let Some(task) = self.responder.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for SntpHandle {
+    fn drop(&mut self) {
+        for task in [&self.responder, &self.refresh].into_iter().flatten() {
+            task.abort();
+        }
+    }
+}
+
+/// Corrected time when a fresh offset is cached, system time otherwise
+fn now() -> DateTime<Utc> {
+    correction::global()
+        .correct(crate::chaos::now(), Instant::now())
+        .0
+}
+
+/// Keep `tx` up to date with ntpd's view of the clock
+async fn refresh_status(state: Arc<ServerState>, tx: watch::Sender<(NtpStatus, NtpTimestamp)>) {
+    let mut interval = tokio::time::interval(STATUS_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        match state.ntp_clock().get_status_async().await {
+            Ok(status) => {
+                correction::global().record(&status, Instant::now());
+                crate::guard::global()
+                    .observe_ntp(status.synced && !NtpSyncedClock::is_container_environment());
+                let reference = NtpTimestamp::from(now());
+                if tx.send((status, reference)).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                debug!(event = "sntp.status_failed", error = %e);
+                tx.send_replace((unsynchronized(), NtpTimestamp::ZERO));
+            }
+        }
+    }
+}
+
+/// Start the responder if `ENABLE_SNTP_SERVER=true`, reading ntpd status through `state`
+pub async fn init_from_env(state: Arc<ServerState>) -> Result<Option<SntpHandle>, TimeServerError> {
+    let Some(config) = SntpConfig::from_env() else {
+        return Ok(None);
+    };
+
+    let (tx, rx) = watch::channel((unsynchronized(), NtpTimestamp::ZERO));
+    let responder = match SntpResponder::bind(config.bind, rx, config.rate_limit).await {
+        Ok(responder) => responder,
+        Err(e) => {
+            warn!("SNTP responder could not bind {}: {}", config.bind, e);
+            return Err(e);
+        }
+    };
+    info!(
+        "SNTP responder listening on udp://{} ({} replies/s per source)",
+        responder.local_addr()?,
+        config.rate_limit
+    );
+
+    let mut handle = responder.spawn();
+    handle.refresh = Some(tokio::spawn(refresh_status(state, tx)));
+    Ok(Some(handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced_status() -> NtpStatus {
+        NtpStatus {
+            synced: true,
+            offset_ms: 0.5,
+            stratum: 2,
+            precision: -23,
+            root_delay: 12.5,
+            root_dispersion: 3.0,
+            shm_valid: false,
+            pps_enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reply_mirrors_request() {
+        let request = NtpPacket {
+            version: 3,
+            poll: 6,
+            ..NtpPacket::client(NtpTimestamp(0xE000_0000_0000_0001))
+        };
+        let reply = build_reply(
+            &request,
+            &synced_status(),
+            NtpTimestamp(1),
+            NtpTimestamp(2),
+            NtpTimestamp(3),
+        );
+        assert_eq!(reply.mode, MODE_SERVER);
+        assert_eq!(reply.version, 3);
+        assert_eq!(reply.poll, 6);
+        assert_eq!(reply.origin, request.transmit);
+        assert_eq!(reply.leap, LEAP_NONE);
+        assert_eq!(reply.stratum, 2);
+        assert_eq!(reply.precision, -23);
+        assert_eq!(reply.root_delay, short_format(0.0125));
+        assert_eq!(&reply.reference_id, b"PPS\0");
+        assert_eq!(reply.reference, NtpTimestamp(1));
+    }
+
+    #[test]
+    fn test_unsynchronized_reply() {
+        let status = NtpStatus {
+            synced: false,
+            ..synced_status()
+        };
+        let reply = build_reply(
+            &NtpPacket::client(NtpTimestamp(7)),
+            &status,
+            NtpTimestamp(1),
+            NtpTimestamp(2),
+            NtpTimestamp(3),
+        );
+        assert_eq!(reply.leap, LEAP_UNSYNCHRONIZED);
+        assert_eq!(reply.stratum, STRATUM_UNSYNCHRONIZED);
+        assert_eq!(&reply.reference_id, b"INIT");
+        assert_eq!(reply.reference, NtpTimestamp::ZERO);
+
+        // "Synced" at stratum 16 is still unsynchronized
+        let status = NtpStatus {
+            stratum: 16,
+            ..synced_status()
+        };
+        let reply = build_reply(
+            &NtpPacket::client(NtpTimestamp(7)),
+            &status,
+            NtpTimestamp(1),
+            NtpTimestamp(2),
+            NtpTimestamp(3),
+        );
+        assert_eq!(reply.leap, LEAP_UNSYNCHRONIZED);
+    }
+
+    #[test]
+    fn test_rate_limiter_windows() {
+        let mut limiter = RateLimiter::new(2);
+        let a: IpAddr = [192, 0, 2, 1].into();
+        let b: IpAddr = [192, 0, 2, 2].into();
+        let t0 = Instant::now();
+
+        assert!(limiter.allow(a, t0));
+        assert!(limiter.allow(a, t0));
+        assert!(!limiter.allow(a, t0 + Duration::from_millis(500)));
+        assert!(limiter.allow(b, t0), "limits are per source");
+        assert!(limiter.allow(a, t0 + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,447 @@
+// ntpd as a supervised child process
+//
+// A container image runs one process, so there is no init system to start
+// ntpd and the SHM and ntpq paths have nothing to read. With MANAGE_NTPD=true
+// the server starts the daemon itself (`NTPD_PATH -n -g -c NTPD_CONFIG`, the
+// file scripts/configure-ntp.sh generates) and keeps it running: when it
+// exits it is started again after a backoff that doubles from
+// `INITIAL_BACKOFF` up to `MAX_BACKOFF`, and after NTPD_MAX_RESTARTS
+// consecutive failures it is left stopped and NTP health is unhealthy. A run
+// longer than `STABLE_RUN` resets the count. Each line ntpd writes to stderr
+// is logged with `component = "ntpd"`. `shutdown` sends the child SIGTERM
+// and waits for it, so on SIGTERM the server stops accepting requests, then
+// stops ntpd, then exits.
+
+use crate::config::{ConfigError, EnvVars};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_NTPD_PATH: &str = "/usr/sbin/ntpd";
+/// Where scripts/configure-ntp.sh writes the generated configuration
+pub const DEFAULT_NTPD_CONFIG: &str = "/etc/ntpsec/ntp.conf";
+pub const DEFAULT_MAX_RESTARTS: u32 = 5;
+
+/// Wait before the first restart; doubled for each consecutive failure
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A run at least this long is not counted as a failure
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+/// How long ntpd has to exit after SIGTERM before it is killed
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The daemon to run and how hard to try
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupervisorConfig {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Consecutive failed runs restarted before giving up
+    pub max_restarts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl SupervisorConfig {
+    /// Run `program` in the foreground with `config_file`
+    pub fn ntpd(program: &str, config_file: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: ["-n", "-g", "-c", config_file]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect(),
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            initial_backoff_ms: INITIAL_BACKOFF.as_millis() as u64,
+            max_backoff_ms: MAX_BACKOFF.as_millis() as u64,
+        }
+    }
+
+    /// `MANAGE_NTPD`, `NTPD_PATH`, `NTPD_CONFIG` and `NTPD_MAX_RESTARTS`;
+    /// `None` unless MANAGE_NTPD is true
+    pub fn from_vars(env: &EnvVars) -> Result<Option<Self>, ConfigError> {
+        if env.flag(&["MANAGE_NTPD"])? != Some(true) {
+            return Ok(None);
+        }
+        let program = env
+            .string(&["NTPD_PATH"])
+            .unwrap_or_else(|| DEFAULT_NTPD_PATH.to_string());
+        let config_file = env
+            .string(&["NTPD_CONFIG"])
+            .unwrap_or_else(|| DEFAULT_NTPD_CONFIG.to_string());
+        let mut config = Self::ntpd(&program, &config_file);
+        if let Some(max) = env.parse(&["NTPD_MAX_RESTARTS"], "a number of restarts")? {
+            config.max_restarts = max;
+        }
+        Ok(Some(config))
+    }
+
+    /// Wait before restart number `attempt` (from 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(32);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+/// How a run of the daemon ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChildExit {
+    pub at: String,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    /// Why the daemon could not be started, when it was not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub ran_secs: f64,
+}
+
+impl ChildExit {
+    fn new(status: Option<ExitStatus>, error: Option<String>, ran: Duration) -> Self {
+        Self {
+            at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            code: status.and_then(|s| s.code()),
+            #[cfg(unix)]
+            signal: status.and_then(|s| s.signal()),
+            #[cfg(not(unix))]
+            signal: None,
+            error,
+            ran_secs: ran.as_secs_f64(),
+        }
+    }
+}
+
+/// What get_ntp_status reports about the managed daemon
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupervisorStatus {
+    pub managed: bool,
+    pub ntpd_running: bool,
+    pub ntpd_pid: Option<u32>,
+    /// Restarts since the server started
+    pub restarts: u32,
+    pub last_exit: Option<ChildExit>,
+    /// NTPD_MAX_RESTARTS consecutive failures; ntpd is no longer restarted
+    pub gave_up: bool,
+}
+
+impl Default for SupervisorStatus {
+    fn default() -> Self {
+        Self {
+            managed: true,
+            ntpd_running: false,
+            ntpd_pid: None,
+            restarts: 0,
+            last_exit: None,
+            gave_up: false,
+        }
+    }
+}
+
+/// A running supervision task
+#[derive(Debug)]
+pub struct Supervisor {
+    status: Arc<Mutex<SupervisorStatus>>,
+    stop: watch::Sender<bool>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Supervisor {
+    /// Start the daemon and keep it running
+    pub fn spawn(config: SupervisorConfig) -> Self {
+        let status = Arc::new(Mutex::new(SupervisorStatus::default()));
+        let (stop, stopped) = watch::channel(false);
+        let task = tokio::spawn(supervise(config, status.clone(), stopped));
+        Self {
+            status,
+            stop,
+            task: Mutex::new(Some(task)),
+        }
+    }
+
+    pub fn status(&self) -> SupervisorStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clone()
+    }
+
+    /// Stop the daemon (SIGTERM, then SIGKILL after `STOP_TIMEOUT`) and
+    /// wait for it; it is not restarted afterwards
+    pub async fn shutdown(&self) {
+        let _ = self.stop.send(true);
+        let task = self.task.lock().unwrap_or_else(|p| p.into_inner()).take();
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+}
+
+fn update(status: &Mutex<SupervisorStatus>, change: impl FnOnce(&mut SupervisorStatus)) {
+    change(&mut status.lock().unwrap_or_else(|p| p.into_inner()));
+}
+
+fn start(config: &SupervisorConfig) -> std::io::Result<Child> {
+    Command::new(&config.program)
+        .args(&config.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+/// Log each line the daemon writes to stderr
+async fn forward_stderr(stderr: ChildStderr) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        info!(component = "ntpd", "{}", line);
+    }
+}
+
+/// SIGTERM, then SIGKILL if the child outlives `STOP_TIMEOUT`; without
+/// signals the child is killed straight away
+async fn terminate(child: &mut Child) -> Option<ExitStatus> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) has no memory effects; the pid is our own child,
+        // not yet reaped, so it cannot have been reused
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = child.start_kill();
+    match tokio::time::timeout(STOP_TIMEOUT, child.wait()).await {
+        Ok(status) => status.ok(),
+        Err(_) => {
+            warn!(component = "ntpd", "ntpd ignored SIGTERM; killing it");
+            let _ = child.kill().await;
+            child.try_wait().ok().flatten()
+        }
+    }
+}
+
+/// Resolves once `shutdown` is called (or the supervisor is dropped)
+async fn stopped(stop: &mut watch::Receiver<bool>) {
+    let _ = stop.wait_for(|stop| *stop).await;
+}
+
+async fn supervise(
+    config: SupervisorConfig,
+    status: Arc<Mutex<SupervisorStatus>>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut failures = 0u32;
+    loop {
+        let started = Instant::now();
+        let exit = match start(&config) {
+            Ok(mut child) => {
+                let pid = child.id();
+                info!(component = "ntpd", pid, program = %config.program, "ntpd started");
+                update(&status, |s| {
+                    s.ntpd_running = true;
+                    s.ntpd_pid = pid;
+                });
+                if let Some(stderr) = child.stderr.take() {
+                    tokio::spawn(forward_stderr(stderr));
+                }
+                tokio::select! {
+                    exited = child.wait() => {
+                        ChildExit::new(exited.ok(), None, started.elapsed())
+                    }
+                    _ = stopped(&mut stop) => {
+                        let exited = terminate(&mut child).await;
+                        let exit = ChildExit::new(exited, None, started.elapsed());
+                        info!(component = "ntpd", code = ?exit.code, "ntpd stopped");
+                        update(&status, |s| {
+                            s.ntpd_running = false;
+                            s.ntpd_pid = None;
+                            s.last_exit = Some(exit);
+                        });
+                        return;
+                    }
+                }
+            }
+            Err(e) => ChildExit::new(None, Some(e.to_string()), started.elapsed()),
+        };
+
+        if started.elapsed() >= STABLE_RUN {
+            failures = 0;
+        }
+        failures += 1;
+        warn!(
+            component = "ntpd",
+            code = ?exit.code,
+            signal = ?exit.signal,
+            error = ?exit.error,
+            failures,
+            "ntpd exited"
+        );
+        update(&status, |s| {
+            s.ntpd_running = false;
+            s.ntpd_pid = None;
+            s.last_exit = Some(exit);
+        });
+
+        if failures > config.max_restarts {
+            error!(
+                component = "ntpd",
+                failures, "ntpd keeps exiting; no longer restarting it"
+            );
+            update(&status, |s| s.gave_up = true);
+            return;
+        }
+        let delay = config.backoff(failures);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = stopped(&mut stop) => return,
+        }
+        update(&status, |s| s.restarts += 1);
+    }
+}
+
+static SUPERVISOR: OnceLock<Supervisor> = OnceLock::new();
+
+/// Start supervising ntpd when `config` says to
+pub fn init(config: Option<&SupervisorConfig>) {
+    if let Some(config) = config {
+        SUPERVISOR.get_or_init(|| Supervisor::spawn(config.clone()));
+    }
+}
+
+/// The process-wide supervisor; `None` unless MANAGE_NTPD is on
+pub fn global() -> Option<&'static Supervisor> {
+    SUPERVISOR.get()
+}
+
+/// Stop the managed ntpd, if there is one
+pub async fn shutdown() {
+    if let Some(supervisor) = global() {
+        supervisor.shutdown().await;
+    }
+}
+
+/// The tests run /bin/sh scripts as the daemon
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn script(body: &str, max_restarts: u32) -> SupervisorConfig {
+        SupervisorConfig {
+            program: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), body.to_string()],
+            max_restarts,
+            initial_backoff_ms: 20,
+            max_backoff_ms: 1000,
+        }
+    }
+
+    async fn wait_until(supervisor: &Supervisor, done: impl Fn(&SupervisorStatus) -> bool) {
+        for _ in 0..500 {
+            if done(&supervisor.status()) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out: {:?}", supervisor.status());
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let config = |pairs: &[(&str, &str)]| {
+            SupervisorConfig::from_vars(&EnvVars::from_pairs(pairs.iter().copied()))
+        };
+        assert_eq!(config(&[]).unwrap(), None);
+        assert_eq!(config(&[("MANAGE_NTPD", "false")]).unwrap(), None);
+
+        let managed = config(&[("MANAGE_NTPD", "true")]).unwrap().unwrap();
+        assert_eq!(managed.program, DEFAULT_NTPD_PATH);
+        assert_eq!(managed.args, ["-n", "-g", "-c", DEFAULT_NTPD_CONFIG]);
+        assert_eq!(managed.max_restarts, DEFAULT_MAX_RESTARTS);
+
+        let custom = config(&[
+            ("MANAGE_NTPD", "true"),
+            ("NTPD_PATH", "/usr/local/sbin/ntpd"),
+            ("NTPD_CONFIG", "/etc/ntp.conf"),
+            ("NTPD_MAX_RESTARTS", "0"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(custom.program, "/usr/local/sbin/ntpd");
+        assert_eq!(custom.args[3], "/etc/ntp.conf");
+        assert_eq!(custom.max_restarts, 0);
+
+        assert!(config(&[("MANAGE_NTPD", "true"), ("NTPD_MAX_RESTARTS", "lots")]).is_err());
+    }
+
+    #[test]
+    fn test_backoff_doubles_to_cap() {
+        let config = script("true", 10);
+        let delays: Vec<u128> = (1..=8).map(|n| config.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [20, 40, 80, 160, 320, 640, 1000, 1000]);
+        assert_eq!(config.backoff(u32::MAX).as_millis(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_restarts_with_backoff_then_gives_up() {
+        let started = Instant::now();
+        let supervisor = Supervisor::spawn(script("echo failing >&2; exit 3", 3));
+        wait_until(&supervisor, |s| s.gave_up).await;
+
+        let status = supervisor.status();
+        assert_eq!(status.restarts, 3);
+        assert!(!status.ntpd_running);
+        let exit = status.last_exit.unwrap();
+        assert_eq!(exit.code, Some(3));
+        assert_eq!(exit.signal, None);
+        // 20 + 40 + 80 ms between the four runs
+        assert!(started.elapsed() >= Duration::from_millis(140));
+    }
+
+    #[tokio::test]
+    async fn test_missing_program_counts_as_failure() {
+        let mut config = script("", 1);
+        config.program = "/nonexistent/ntpd".to_string();
+        let supervisor = Supervisor::spawn(config);
+        wait_until(&supervisor, |s| s.gave_up).await;
+        let status = supervisor.status();
+        assert_eq!(status.restarts, 1);
+        assert!(status.last_exit.unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_terminates_without_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("stopped");
+        let body = format!(
+            "trap 'echo term > {}; exit 0' TERM; while :; do sleep 0.02; done",
+            marker.display()
+        );
+        let supervisor = Supervisor::spawn(script(&body, 3));
+        wait_until(&supervisor, |s| s.ntpd_running).await;
+        let pid = supervisor.status().ntpd_pid.unwrap();
+
+        supervisor.shutdown().await;
+        // The child saw SIGTERM and had exited by the time shutdown returned
+        assert_eq!(std::fs::read_to_string(&marker).unwrap().trim(), "term");
+        assert_eq!(unsafe { libc::kill(pid as libc::pid_t, 0) }, -1);
+
+        let status = supervisor.status();
+        assert!(!status.ntpd_running);
+        assert_eq!(status.last_exit.unwrap().code, Some(0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(supervisor.status().restarts, 0);
+        assert!(!supervisor.status().gave_up);
+    }
+}
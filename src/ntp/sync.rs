@@ -1,32 +1,69 @@
-// NTP-synchronized clock access via NTPsec shared memory interface
-use libc::{shmat, shmdt, shmget, IPC_CREAT};
-use std::ptr;
+// NTP-synchronized clock access via NTPsec shared memory interface, with
+// status from ntpq (or w32tm on Windows, see `platform`)
+use super::attach::{ShmAttacher, ShmConnector, ShmRetryConfig};
+use super::history::{NtpHistory, NtpSample};
+use super::ntpq::{self, KernelInfo};
+use super::platform::{self, StatusBackend};
+use super::query::{NtpQueryBackend, NtpQueryError};
+use super::shm::NtpShmInterface;
+use crate::error::TimeServerError;
+use crate::events::ServerEvent;
+use rmcp::model::LoggingLevel;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::process::Command;
-use tokio::time::timeout;
-
-const NTP_SHM_SIZE: usize = 96;
-
-/// NTPsec shared memory structure for time exchange
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-pub struct NtpShmTime {
-    mode: i32,                    // 0: both RW, 1: RW by ntpd, RO by us
-    count: i32,                   // Updated each write
-    clock_time_sec: i64,          // Clock timestamp seconds
-    clock_time_usec: i32,         // Clock timestamp microseconds
-    receive_time_sec: i64,        // When timestamp was received
-    receive_time_usec: i32,       // Receive time microseconds
-    leap: i32,                    // Leap second indicator
-    precision: i32,               // Clock precision (log2 seconds)
-    nsamples: i32,                // Number of samples
-    valid: i32,                   // 0: invalid, 1: valid
-    clock_time_stamp_nsec: u32,   // Nanosecond resolution
-    receive_time_stamp_nsec: u32, // Nanosecond resolution
-    dummy: [i32; 8],              // Reserved for future use
+
+/// Last sync state seen by `get_status_async`, as a `SyncState`
+static LAST_SYNC_STATE: AtomicU8 = AtomicU8::new(SyncState::Unknown as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum SyncState {
+    Unknown = 0,
+    Synced = 1,
+    Unsynced = 2,
+}
+
+impl SyncState {
+    fn of(status: &NtpStatus) -> Self {
+        if status.synced {
+            Self::Synced
+        } else {
+            Self::Unsynced
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// The event for a change from the `previous` state to `status`; the first
+/// status seen is not a change
+fn sync_transition(previous: u8, status: &NtpStatus) -> Option<ServerEvent> {
+    let details = serde_json::json!({
+        "stratum": status.stratum,
+        "offset_ms": status.offset_ms,
+        "shm_valid": status.shm_valid,
+    });
+    match (previous, SyncState::of(status)) {
+        (p, SyncState::Unsynced) if p == SyncState::Synced as u8 => Some(
+            ServerEvent::new(
+                "ntp.sync_lost",
+                LoggingLevel::Warning,
+                "NTP lost synchronization",
+            )
+            .with_details(details),
+        ),
+        (p, SyncState::Synced) if p == SyncState::Unsynced as u8 => Some(
+            ServerEvent::new("ntp.sync_restored", LoggingLevel::Info, "NTP synchronized")
+                .with_details(details),
+        ),
+        _ => None,
+    }
+}
+
+/// ntpd's synchronization state; the frequency, jitter, reference and
+/// kernel fields are left out when ntpd did not report them
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
 pub struct NtpStatus {
     pub synced: bool,
     pub offset_ms: f64,
@@ -36,145 +73,141 @@ pub struct NtpStatus {
     pub root_dispersion: f64,
     pub shm_valid: bool,
     pub pps_enabled: bool,
+    /// Clock frequency error (ppm)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_ppm: Option<f64>,
+    /// Combined system jitter (ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sys_jitter_ms: Option<f64>,
+    /// Jitter of the local clock (ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clk_jitter_ms: Option<f64>,
+    /// Frequency wander of the local clock (ppm)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clk_wander_ppm: Option<f64>,
+    /// Reference ID of the system peer (an address or refclock name)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refid: Option<String>,
+    /// When the system clock was last set or corrected (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reftime: Option<String>,
+    /// Kernel time discipline, from `ntpq -c kerninfo`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<KernelInfo>,
+    /// SHM attach attempts since startup, including the first
+    pub shm_attach_attempts: u64,
+    /// Why the latest SHM attach attempt failed; absent once one succeeds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shm_last_error: Option<String>,
 }
 
-/// Shared memory interface to NTPsec
-pub struct NtpShmInterface {
-    #[allow(dead_code)] // Used in Drop implementation
-    shm_id: i32,
-    shm_ptr: *mut NtpShmTime,
-    unit: u8,
+/// The realtime clock through `SystemTime`, for targets without clock_gettime
+#[cfg_attr(unix, allow(dead_code))]
+fn system_time_now() -> Result<(i64, u32), std::io::Error> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(std::io::Error::other)?;
+    Ok((now.as_secs() as i64, now.subsec_nanos()))
 }
 
-impl NtpShmInterface {
-    /// Create SHM interface for NTPsec unit 0-3
-    /// Unit 0 corresponds to SHM(0) in ntp.conf, uses key 0x4e545030
-    /// Unit 1 corresponds to SHM(1) in ntp.conf, uses key 0x4e545031, etc.
-    pub fn new(unit: u8) -> Result<Self, String> {
-        if unit > 3 {
-            return Err("SHM unit must be 0-3".to_string());
-        }
-
-        // NTPsec uses magic keys: 0x4e545030 + unit number
-        let key = 0x4e545030 + unit as i32;
-
-        unsafe {
-            // Get or create shared memory segment
-            let shm_id = shmget(key, NTP_SHM_SIZE, IPC_CREAT | 0o666);
-            if shm_id < 0 {
-                return Err(format!(
-                    "Failed to create SHM segment for unit {}: {}",
-                    unit,
-                    std::io::Error::last_os_error()
-                ));
-            }
-
-            // Attach to shared memory
-            let shm_ptr = shmat(shm_id, ptr::null(), 0) as *mut NtpShmTime;
-            if shm_ptr as isize == -1 {
-                return Err(format!(
-                    "Failed to attach SHM segment: {}",
-                    std::io::Error::last_os_error()
-                ));
-            }
-
-            // Initialize the structure if it's new
-            let shm = &mut *shm_ptr;
-            if shm.mode == 0 && shm.count == 0 {
-                *shm = NtpShmTime {
-                    mode: 1, // Mode 1: ntpd writes, we read
-                    count: 0,
-                    clock_time_sec: 0,
-                    clock_time_usec: 0,
-                    receive_time_sec: 0,
-                    receive_time_usec: 0,
-                    leap: 0,
-                    precision: -20, // Microsecond precision
-                    nsamples: 0,
-                    valid: 0,
-                    clock_time_stamp_nsec: 0,
-                    receive_time_stamp_nsec: 0,
-                    dummy: [0; 8],
-                };
-            }
+/// Cloning shares the SHM attachment, the history and the latest status
+#[derive(Clone)]
+pub struct NtpSyncedClock {
+    /// Attached again by `refresh_shm` while missing or stale
+    shm: Arc<ShmAttacher>,
+    /// Statuses read from ntpd are recorded here
+    history: Option<Arc<NtpHistory>>,
+    /// The status `get_status_async` returned last, for readers that must
+    /// not run ntpq
+    latest: Arc<Mutex<Option<NtpStatus>>>,
+}
 
-            Ok(NtpShmInterface {
-                shm_id,
-                shm_ptr,
-                unit,
-            })
+impl NtpSyncedClock {
+    /// Check if running in a container environment without its own ntpd;
+    /// a container whose ntpd this server runs (MANAGE_NTPD) reads it as
+    /// any host would
+    pub fn is_container_environment() -> bool {
+        if super::supervisor::global().is_some() {
+            return false;
         }
+        std::path::Path::new("/.dockerenv").exists()
+            || std::env::var("KUBERNETES_SERVICE_HOST").is_ok()
+            || std::env::var("CONTAINER_APP_NAME").is_ok()
+            || std::env::var("SKIP_NTP_CHECK").is_ok()
     }
 
-    /// Read current time data from shared memory
-    pub fn read_time(&self) -> Option<(i64, u32, bool)> {
-        unsafe {
-            let shm = &*self.shm_ptr;
-
-            if shm.valid == 0 {
-                return None;
-            }
+    /// Create a new NTP synced clock with optional SHM interface
+    pub fn new() -> Self {
+        Self::with_shm_retry(ShmRetryConfig::default())
+    }
 
-            // Memory barrier to ensure reads are consistent
-            std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    /// Try SHM(0), and again on the terms of `retry` while it is missing or
+    /// stale; a failure is kept for the startup report and the status
+    pub fn with_shm_retry(retry: ShmRetryConfig) -> Self {
+        Self::with_attacher(ShmAttacher::connect(unit_connector(0), retry))
+    }
 
-            Some((
-                shm.clock_time_sec,
-                shm.clock_time_stamp_nsec,
-                shm.valid == 1,
-            ))
-        }
+    /// Create with specific SHM unit
+    pub fn with_shm_unit(unit: u8) -> Result<Self, TimeServerError> {
+        let shm = NtpShmInterface::new(unit)?;
+        Ok(Self::with_attacher(ShmAttacher::attached(
+            shm,
+            unit_connector(unit),
+            ShmRetryConfig::default(),
+        )))
     }
 
-    /// Get the unit number
-    pub fn unit(&self) -> u8 {
-        self.unit
+    /// Read time from `shm` instead of attaching a unit
+    pub fn with_shm(shm: NtpShmInterface) -> Self {
+        Self::with_attacher(ShmAttacher::fixed(Some(shm)))
     }
 
-    /// Check if the shared memory has valid data
-    pub fn is_valid(&self) -> bool {
-        unsafe {
-            let shm = &*self.shm_ptr;
-            shm.valid == 1
+    pub fn with_attacher(shm: ShmAttacher) -> Self {
+        Self {
+            shm: Arc::new(shm),
+            history: None,
+            latest: Arc::default(),
         }
     }
-}
 
-impl Drop for NtpShmInterface {
-    fn drop(&mut self) {
-        unsafe {
-            shmdt(self.shm_ptr as *const libc::c_void);
-        }
+    /// Record every status read from ntpd in `history`
+    pub fn with_history(mut self, history: Arc<NtpHistory>) -> Self {
+        self.history = Some(history);
+        self
     }
-}
 
-unsafe impl Send for NtpShmInterface {}
-unsafe impl Sync for NtpShmInterface {}
+    /// The attached SHM unit, if any
+    pub fn shm(&self) -> Option<NtpShmInterface> {
+        self.shm.current()
+    }
 
-pub struct NtpSyncedClock {
-    shm: Option<NtpShmInterface>,
-}
+    pub fn shm_attacher(&self) -> &ShmAttacher {
+        &self.shm
+    }
 
-impl NtpSyncedClock {
-    /// Check if running in a container environment
-    pub fn is_container_environment() -> bool {
-        std::path::Path::new("/.dockerenv").exists()
-            || std::env::var("KUBERNETES_SERVICE_HOST").is_ok()
-            || std::env::var("CONTAINER_APP_NAME").is_ok()
-            || std::env::var("SKIP_NTP_CHECK").is_ok()
+    /// Attach SHM again if it is missing or stale and a retry is due; for
+    /// the background sampler, never a request
+    pub fn refresh_shm(&self) -> bool {
+        self.shm.refresh()
     }
 
-    /// Create a new NTP synced clock with optional SHM interface
-    pub fn new() -> Self {
-        // Try to connect to SHM(0) by default
-        let shm = NtpShmInterface::new(0).ok();
-        Self { shm }
+    pub fn history(&self) -> Option<&Arc<NtpHistory>> {
+        self.history.as_ref()
     }
 
-    /// Create with specific SHM unit
-    pub fn with_shm_unit(unit: u8) -> Result<Self, String> {
-        let shm = NtpShmInterface::new(unit)?;
-        Ok(Self { shm: Some(shm) })
+    pub(crate) fn set_latest_status(&self, status: NtpStatus) {
+        *self
+            .latest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(status);
+    }
+
+    /// The status read last, without asking ntpd again
+    pub fn latest_status(&self) -> Option<NtpStatus> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
     }
 
     /// Get high-precision system time using clock_gettime
@@ -199,17 +232,13 @@ impl NtpSyncedClock {
 
         #[cfg(not(unix))]
         {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-            Ok((now.as_secs() as i64, now.subsec_nanos()))
+            system_time_now()
         }
     }
 
     /// Get time from SHM if available, otherwise fallback to system time
     pub fn now_synced(&self) -> Result<(i64, u32), std::io::Error> {
-        if let Some(ref shm) = self.shm {
+        if let Some(shm) = self.shm() {
             if let Some((secs, nanos, _)) = shm.read_time() {
                 return Ok((secs, nanos));
             }
@@ -220,7 +249,7 @@ impl NtpSyncedClock {
     }
 
     /// Wait for NTP synchronization
-    pub async fn wait_for_sync(timeout_duration: Duration) -> Result<(), String> {
+    pub async fn wait_for_sync(timeout_duration: Duration) -> Result<(), TimeServerError> {
         let start = tokio::time::Instant::now();
 
         loop {
@@ -229,7 +258,9 @@ impl NtpSyncedClock {
             }
 
             if start.elapsed() > timeout_duration {
-                return Err("NTP sync timeout".to_string());
+                return Err(TimeServerError::NtpUnavailable {
+                    reason: "NTP sync timeout".to_string(),
+                });
             }
 
             tokio::time::sleep(Duration::from_secs(1)).await;
@@ -237,35 +268,35 @@ impl NtpSyncedClock {
     }
 
     /// Check NTP synchronization status (async, container-aware)
-    pub async fn is_synced_async() -> Result<bool, String> {
+    pub async fn is_synced_async() -> Result<bool, TimeServerError> {
         // In containers, skip NTP check
         if Self::is_container_environment() {
             tracing::debug!("Container environment detected, skipping NTP check");
             return Ok(false);
         }
+        match StatusBackend::current() {
+            StatusBackend::Ntpq => {}
+            StatusBackend::W32tm => {
+                return Ok(platform::w32tm_status()
+                    .await
+                    .is_ok_and(|status| status.synced))
+            }
+            StatusBackend::Unsupported => return Err(TimeServerError::not_supported("NTP status")),
+        }
 
-        // Add timeout to prevent indefinite hangs
-        let result = timeout(
-            Duration::from_secs(2),
-            Command::new("ntpq").args(["-p", "-n"]).output(),
-        )
-        .await;
-
-        let output = match result {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+        let stdout = match super::query::global().ntpq(&["-p", "-n"]).await {
+            Ok(stdout) => stdout,
+            Err(NtpQueryError::NotFound) => {
                 tracing::warn!("ntpq not found, assuming not synced");
                 return Ok(false);
             }
-            Ok(Err(e)) => return Err(format!("Failed to check NTP status: {}", e)),
-            Err(_) => {
-                tracing::warn!("ntpq command timed out");
+            Err(e @ NtpQueryError::Busy { .. }) => return Err(e.into()),
+            Err(e) => {
+                tracing::warn!("{}, assuming not synced", e);
                 return Ok(false);
             }
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
         // Look for synchronized peer (marked with *)
         Ok(stdout.lines().any(|line| line.starts_with('*')))
     }
@@ -277,10 +308,33 @@ impl NtpSyncedClock {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(Self::is_synced_async())
         })
+        .map_err(String::from)
+    }
+
+    /// Get NTP status information (async, container-aware). A change
+    /// between synced and unsynced is published on the event bus, and a
+    /// status read from ntpd is added to the history and checked for
+    /// webhook alerts.
+    pub async fn get_status_async(&self) -> Result<NtpStatus, TimeServerError> {
+        let mut status = self.query_status().await?;
+        status.shm_attach_attempts = self.shm.attempts();
+        status.shm_last_error = self.shm.last_error();
+        if let Some(history) = self.history.as_ref() {
+            if !Self::is_container_environment() {
+                history.record(NtpSample::of(&status, chrono::Utc::now().timestamp()));
+            }
+        }
+        let previous = LAST_SYNC_STATE.swap(SyncState::of(&status) as u8, Ordering::Relaxed);
+        if let Some(event) = sync_transition(previous, &status) {
+            crate::events::publish(event);
+        }
+        #[cfg(feature = "http-api")]
+        crate::alert::observe(&status);
+        self.set_latest_status(status.clone());
+        Ok(status)
     }
 
-    /// Get NTP status information (async, container-aware)
-    pub async fn get_status_async(&self) -> Result<NtpStatus, String> {
+    async fn query_status(&self) -> Result<NtpStatus, TimeServerError> {
         // In container environment, return minimal status
         if Self::is_container_environment() {
             tracing::debug!("Container environment: returning degraded NTP status");
@@ -293,89 +347,67 @@ impl NtpSyncedClock {
                 root_dispersion: 0.0,
                 shm_valid: false,
                 pps_enabled: false,
+                ..Default::default()
             });
         }
+        match StatusBackend::current() {
+            StatusBackend::Ntpq => {}
+            StatusBackend::W32tm => return platform::w32tm_status().await,
+            StatusBackend::Unsupported => return Err(TimeServerError::not_supported("NTP status")),
+        }
 
-        // Add timeout for ntpq command
-        let result = timeout(
-            Duration::from_secs(2),
-            Command::new("ntpq").args(["-c", "rv"]).output(),
-        )
-        .await;
-
-        let output = match result {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
-                tracing::warn!("ntpq not found, returning degraded status");
-                return Ok(NtpStatus {
-                    synced: false,
-                    offset_ms: 0.0,
-                    stratum: 16,
-                    precision: 0,
-                    root_delay: 0.0,
-                    root_dispersion: 0.0,
-                    shm_valid: self.shm.as_ref().map(|s| s.is_valid()).unwrap_or(false),
-                    pps_enabled: false,
-                });
-            }
-            Ok(Err(e)) => return Err(format!("Failed to get NTP status: {}", e)),
-            Err(_) => {
-                tracing::warn!("ntpq command timed out, returning degraded status");
-                return Ok(NtpStatus {
-                    synced: false,
-                    offset_ms: 0.0,
-                    stratum: 16,
-                    precision: 0,
-                    root_delay: 0.0,
-                    root_dispersion: 0.0,
-                    shm_valid: self.shm.as_ref().map(|s| s.is_valid()).unwrap_or(false),
-                    pps_enabled: false,
-                });
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        let shm_valid = self.shm.as_ref().map(|s| s.is_valid()).unwrap_or(false);
-        let pps_enabled = stdout.contains("pps") || stdout.contains("PPS");
-
-        let mut status = NtpStatus {
-            synced: Self::is_synced_async().await.unwrap_or(false),
+        let degraded = NtpStatus {
+            synced: false,
             offset_ms: 0.0,
             stratum: 16,
             precision: 0,
             root_delay: 0.0,
             root_dispersion: 0.0,
-            shm_valid,
-            pps_enabled,
+            shm_valid: self.shm().is_some_and(|s| s.is_valid()),
+            pps_enabled: super::pps::is_receiving(),
+            ..Default::default()
+        };
+        let stdout = match super::query::global().ntpq(&["-c", "rv"]).await {
+            Ok(stdout) => stdout,
+            Err(NtpQueryError::NotFound) => {
+                tracing::warn!("ntpq not found, returning degraded status");
+                return Ok(degraded);
+            }
+            Err(e @ NtpQueryError::Busy { .. }) => return Err(e.into()),
+            Err(e) => {
+                tracing::warn!("{}, returning degraded status", e);
+                return Ok(degraded);
+            }
         };
 
-        // Parse NTP variables
-        for part in stdout.split(',') {
-            let part = part.trim();
-
-            if part.starts_with("offset=") {
-                if let Some(val) = part.strip_prefix("offset=") {
-                    status.offset_ms = val.parse().unwrap_or(0.0);
-                }
-            } else if part.starts_with("stratum=") {
-                if let Some(val) = part.strip_prefix("stratum=") {
-                    status.stratum = val.parse().unwrap_or(16);
-                }
-            } else if part.starts_with("precision=") {
-                if let Some(val) = part.strip_prefix("precision=") {
-                    status.precision = val.parse().unwrap_or(0);
-                }
-            } else if part.starts_with("rootdelay=") {
-                if let Some(val) = part.strip_prefix("rootdelay=") {
-                    status.root_delay = val.parse().unwrap_or(0.0);
-                }
-            } else if part.starts_with("rootdisp=") {
-                if let Some(val) = part.strip_prefix("rootdisp=") {
-                    status.root_dispersion = val.parse().unwrap_or(0.0);
-                }
+        let vars = ntpq::parse_rv(&stdout);
+        // Not every daemon allows kerninfo, and a status without it is still useful
+        let kernel = match super::query::global().ntpq(&["-c", "kerninfo"]).await {
+            Ok(stdout) => ntpq::parse_kerninfo(&stdout),
+            Err(e) => {
+                tracing::debug!("kerninfo unavailable: {}", e);
+                None
             }
-        }
+        };
+
+        let status = NtpStatus {
+            synced: Self::is_synced_async().await.unwrap_or(false),
+            offset_ms: vars.offset_ms.unwrap_or(0.0),
+            stratum: vars.stratum.unwrap_or(16),
+            precision: vars.precision.unwrap_or(0),
+            root_delay: vars.root_delay.unwrap_or(0.0),
+            root_dispersion: vars.root_dispersion.unwrap_or(0.0),
+            shm_valid: self.shm().is_some_and(|s| s.is_valid()),
+            pps_enabled: super::pps::is_receiving(),
+            frequency_ppm: vars.frequency_ppm,
+            sys_jitter_ms: vars.sys_jitter_ms,
+            clk_jitter_ms: vars.clk_jitter_ms,
+            clk_wander_ppm: vars.clk_wander_ppm,
+            refid: vars.refid,
+            reftime: vars.reftime,
+            kernel,
+            ..Default::default()
+        };
 
         Ok(status)
     }
@@ -386,10 +418,11 @@ impl NtpSyncedClock {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(self.get_status_async())
         })
+        .map_err(String::from)
     }
 
     /// Get NTP offset in microseconds (async)
-    pub async fn get_offset_us_async(&self) -> Result<i64, String> {
+    pub async fn get_offset_us_async(&self) -> Result<i64, TimeServerError> {
         let status = self.get_status_async().await?;
         Ok((status.offset_ms * 1000.0) as i64)
     }
@@ -400,9 +433,15 @@ impl NtpSyncedClock {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(self.get_offset_us_async())
         })
+        .map_err(String::from)
     }
 }
 
+/// Attaches SHM unit `unit` by its ntpd key
+fn unit_connector(unit: u8) -> ShmConnector {
+    Arc::new(move || NtpShmInterface::new(unit))
+}
+
 impl Default for NtpSyncedClock {
     fn default() -> Self {
         Self::new()
@@ -412,6 +451,8 @@ impl Default for NtpSyncedClock {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(unix)]
+    use crate::ntp::shm::ShmSample;
 
     #[test]
     fn test_get_time() {
@@ -422,4 +463,131 @@ mod tests {
         assert!(secs > 0);
         assert!(nanos < 1_000_000_000);
     }
+
+    #[test]
+    fn test_system_time_fallback_agrees_with_now() {
+        let (fallback, _) = system_time_now().unwrap();
+        let (secs, _) = NtpSyncedClock::now().unwrap();
+        assert!((secs - fallback).abs() <= 1, "{} vs {}", secs, fallback);
+    }
+
+    /// A sample whose fields all derive from `n`, so a torn read shows
+    #[cfg(unix)]
+    fn numbered_sample(n: i64) -> ShmSample {
+        ShmSample {
+            clock_sec: n,
+            clock_nsec: (n * 7919 % 1_000_000_000) as u32,
+            receive_sec: n + 1,
+            receive_nsec: (n * 104_729 % 1_000_000_000) as u32,
+            leap: (n % 3) as i32,
+            precision: -((n % 30) as i32),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_reads_never_see_a_torn_sample() {
+        const WRITES: i64 = 200_000;
+        let shm = NtpShmInterface::private().unwrap();
+        let clock = NtpSyncedClock::with_shm(shm.clone());
+        assert!(
+            clock.now_synced().unwrap().0 > WRITES,
+            "empty segment falls back"
+        );
+
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let clock = clock.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    let mut consistent = 0u64;
+                    while !done.load(Ordering::Relaxed) {
+                        if let Some(sample) = clock.shm().unwrap().read_sample() {
+                            assert_eq!(sample, numbered_sample(sample.clock_sec));
+                            consistent += 1;
+                        }
+                        let (secs, nanos) = clock.now_synced().unwrap();
+                        // Larger values are the system clock fallback
+                        if secs <= WRITES {
+                            assert_eq!(nanos, numbered_sample(secs).clock_nsec);
+                        }
+                    }
+                    consistent
+                })
+            })
+            .collect();
+
+        for n in 1..=WRITES {
+            shm.write_sample(&numbered_sample(n));
+        }
+        done.store(true, Ordering::Relaxed);
+        let consistent: u64 = readers.into_iter().map(|r| r.join().unwrap()).sum();
+        assert!(consistent > 0);
+        assert_eq!(clock.now_synced().unwrap().0, WRITES);
+    }
+
+    #[test]
+    fn test_sync_transitions_publish_events() {
+        let status = |synced| NtpStatus {
+            synced,
+            offset_ms: 0.5,
+            stratum: if synced { 2 } else { 16 },
+            precision: -20,
+            root_delay: 0.0,
+            root_dispersion: 0.0,
+            shm_valid: false,
+            pps_enabled: false,
+            ..Default::default()
+        };
+
+        let lost = sync_transition(SyncState::Synced as u8, &status(false)).unwrap();
+        assert_eq!(lost.kind, "ntp.sync_lost");
+        assert_eq!(lost.level, LoggingLevel::Warning);
+        assert_eq!(lost.details["stratum"], 16);
+        let restored = sync_transition(SyncState::Unsynced as u8, &status(true)).unwrap();
+        assert_eq!(restored.kind, "ntp.sync_restored");
+
+        assert!(sync_transition(SyncState::Unknown as u8, &status(false)).is_none());
+        assert!(sync_transition(SyncState::Synced as u8, &status(true)).is_none());
+    }
+
+    #[test]
+    fn test_status_leaves_out_unreported_fields() {
+        let fields = |status: &NtpStatus| {
+            let value = serde_json::to_value(status).unwrap();
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let reported = [
+            "offset_ms",
+            "pps_enabled",
+            "precision",
+            "root_delay",
+            "root_dispersion",
+            "shm_attach_attempts",
+            "shm_valid",
+            "stratum",
+            "synced",
+        ];
+        assert_eq!(fields(&NtpStatus::default()), reported);
+
+        let status = NtpStatus {
+            sys_jitter_ms: Some(0.25),
+            refid: Some("PPS".into()),
+            kernel: ntpq::parse_kerninfo("kernel status: pll nano"),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(fields(&status).len(), reported.len() + 3);
+        assert_eq!(value["refid"], "PPS");
+        assert_eq!(
+            value["kernel"]["status"],
+            serde_json::json!(["pll", "nano"])
+        );
+    }
 }
@@ -0,0 +1,151 @@
+// JSON rendering shared by MCP tool results and the HTTP API
+//
+// Pretty output is the default (`JSON_PRETTY=true`); machine consumers can
+// ask for compact output per request, which roughly halves the payload.
+// get_unix_time, called at high rates, is compact unless `pretty` or an
+// explicit `JSON_PRETTY=true` asks otherwise (see `mcp::fast`).
+// Objects come out in a stable order: structs in declaration order, maps
+// (serde_json `Map` and the `BTreeMap`s in responses) sorted by key.
+
+use serde::Serialize;
+use std::cell::Cell;
+use std::sync::OnceLock;
+
+/// Server-wide default from `JSON_PRETTY` (true unless set to false/0/no)
+pub fn pretty_default() -> bool {
+    pretty_setting().unwrap_or(true)
+}
+
+/// `JSON_PRETTY` as set, for tools that are compact unless it asks otherwise
+pub fn pretty_setting() -> Option<bool> {
+    static PRETTY: OnceLock<Option<bool>> = OnceLock::new();
+    *PRETTY.get_or_init(|| {
+        std::env::var("JSON_PRETTY")
+            .ok()
+            .and_then(|v| parse_flag(&v))
+    })
+}
+
+thread_local! {
+    static NANOS_AS_NUMBERS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether nanosecond counts beyond 2^53 stay JSON numbers: in
+/// [`with_nanos_as_numbers`], or server-wide with `JSON_NANOS_AS_NUMBER`
+pub fn nanos_as_numbers() -> bool {
+    static NUMBERS: OnceLock<bool> = OnceLock::new();
+    NANOS_AS_NUMBERS.get()
+        || *NUMBERS.get_or_init(|| {
+            std::env::var("JSON_NANOS_AS_NUMBER")
+                .ok()
+                .and_then(|v| parse_flag(&v))
+                .unwrap_or(false)
+        })
+}
+
+/// Run `render` with nanosecond counts serialized as numbers, for bodies
+/// whose shape is frozen
+pub fn with_nanos_as_numbers<T>(render: impl FnOnce() -> T) -> T {
+    let before = NANOS_AS_NUMBERS.replace(true);
+    let result = render();
+    NANOS_AS_NUMBERS.set(before);
+    result
+}
+
+/// Parse a boolean flag value; an empty value (`?pretty`) means true
+pub fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Serialize `value`, pretty unless `pretty` (or the server default) says otherwise
+pub fn to_json<T: Serialize + ?Sized>(
+    value: &T,
+    pretty: Option<bool>,
+) -> Result<String, serde_json::Error> {
+    if pretty.unwrap_or_else(pretty_default) {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// Append the fields of `extra` (an object) to `object`, JSON already
+/// rendered by [`to_json`] with the same `pretty`, without re-serializing it
+pub fn append_fields<T: Serialize + ?Sized>(
+    object: &str,
+    extra: &T,
+    pretty: bool,
+) -> Result<String, serde_json::Error> {
+    let extra = to_json(extra, Some(pretty))?;
+    let body = object
+        .trim_end()
+        .strip_suffix('}')
+        .unwrap_or(object)
+        .trim_end();
+    let fields = extra.trim().trim_start_matches('{');
+    if body.ends_with('{') {
+        return Ok(format!("{}{}", body, fields));
+    }
+    if fields.trim() == "}" {
+        return Ok(object.to_string());
+    }
+    Ok(format!("{},{}", body, fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::utc::EnhancedTimeResponse;
+
+    #[test]
+    fn test_compact_output_matches_pretty() {
+        let response = EnhancedTimeResponse::now();
+        let pretty = to_json(&response, Some(true)).unwrap();
+        let compact = to_json(&response, Some(false)).unwrap();
+
+        assert!(pretty.contains('\n'));
+        assert!(!compact.contains('\n'));
+        assert!(compact.len() < pretty.len());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&compact).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_append_fields_matches_merged_value() {
+        let extra = serde_json::json!({"valid_for_ms": 5});
+        for pretty in [true, false] {
+            let body = to_json(
+                &serde_json::json!({"count": 1, "names": ["UTC"]}),
+                Some(pretty),
+            )
+            .unwrap();
+            let merged = append_fields(&body, &extra, pretty).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&merged).unwrap(),
+                serde_json::json!({"count": 1, "names": ["UTC"], "valid_for_ms": 5})
+            );
+            assert_eq!(merged.contains('\n'), pretty);
+
+            let empty = append_fields("{}", &extra, pretty).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&empty).unwrap(),
+                extra
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert_eq!(parse_flag("false"), Some(false));
+        assert_eq!(parse_flag("0"), Some(false));
+        assert_eq!(parse_flag("TRUE"), Some(true));
+        assert_eq!(parse_flag(""), Some(true));
+        assert_eq!(parse_flag("maybe"), None);
+    }
+}
@@ -0,0 +1,386 @@
+// Counters and NTP history that survive a restart
+//
+// With `STATE_FILE` set, the request counters and the NTP history ring are
+// written there as one JSON document every `STATE_SAVE_INTERVAL_SECS` and at
+// shutdown, and read back at startup so the numbers continue where the last
+// process left off. A save writes `<file>.tmp`, syncs it and renames it over
+// the file, so a crash mid-save leaves the previous state intact. A file that
+// does not parse, or carries another `schema_version`, is moved aside to
+// `<file>.corrupt-<unix seconds>` and the server starts fresh. Builds without
+// the `ntp` feature keep no history and save an empty one.
+
+#[cfg(feature = "ntp")]
+use crate::ntp::history::{NtpHistory, NtpSample};
+use crate::stats::{ServerStats, StatsSnapshot};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Saved NTP history, read but not kept without the ntp feature
+#[cfg(not(feature = "ntp"))]
+type NtpSample = serde_json::Value;
+
+/// Bumped whenever `Snapshot` changes incompatibly
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How often the state is saved when `STATE_SAVE_INTERVAL_SECS` is unset
+pub const DEFAULT_SAVE_INTERVAL_SECS: u64 = 300;
+
+/// STATE_FILE and STATE_SAVE_INTERVAL_SECS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PersistConfig {
+    pub file: String,
+    pub save_interval_secs: u64,
+}
+
+/// The document kept in `STATE_FILE`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub schema_version: u32,
+    /// Unix seconds
+    pub saved_at: i64,
+    pub stats: StatsSnapshot,
+    pub ntp_history: Vec<NtpSample>,
+}
+
+/// What startup found in `STATE_FILE`
+#[derive(Debug)]
+pub enum Loaded {
+    /// No file yet: first run
+    Missing,
+    Restored(Snapshot),
+    /// Unusable file, moved to `backup` unless that failed too
+    Discarded {
+        reason: String,
+        backup: Option<PathBuf>,
+    },
+}
+
+/// Read `path`, moving it aside when it cannot be used
+pub fn load(path: &Path) -> Loaded {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Loaded::Missing,
+        Err(e) => return discard(path, e.to_string()),
+    };
+    let value: serde_json::Value = match serde_json::from_str(&text) {
+        Ok(value) => value,
+        Err(e) => return discard(path, format!("not valid JSON: {}", e)),
+    };
+    match value.get("schema_version").and_then(|v| v.as_u64()) {
+        Some(version) if version == u64::from(SCHEMA_VERSION) => {}
+        Some(version) => {
+            return discard(
+                path,
+                format!("schema_version {}, expected {}", version, SCHEMA_VERSION),
+            )
+        }
+        None => return discard(path, "no schema_version".to_string()),
+    }
+    match serde_json::from_value(value) {
+        Ok(snapshot) => Loaded::Restored(snapshot),
+        Err(e) => discard(path, e.to_string()),
+    }
+}
+
+fn discard(path: &Path, reason: String) -> Loaded {
+    let backup = sibling(path, &format!("corrupt-{}", Utc::now().timestamp()));
+    let backup = match std::fs::rename(path, &backup) {
+        Ok(()) => Some(backup),
+        Err(e) => {
+            warn!(event = "state.backup_failed", path = %path.display(), error = %e);
+            None
+        }
+    };
+    warn!(
+        event = "state.discarded",
+        path = %path.display(),
+        reason = %reason,
+        backup = ?backup,
+        "Starting with fresh counters"
+    );
+    Loaded::Discarded { reason, backup }
+}
+
+/// `<path>.<suffix>`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Replace `path` with `snapshot` atomically
+pub fn save(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let temp = write_temp(path, snapshot)?;
+    commit(&temp, path)
+}
+
+/// First half of a save: the whole document, synced, next to `path`
+fn write_temp(path: &Path, snapshot: &Snapshot) -> io::Result<PathBuf> {
+    let temp = sibling(path, "tmp");
+    let mut file = std::fs::File::create(&temp)?;
+    serde_json::to_writer(&mut file, snapshot)?;
+    file.write_all(b"\n")?;
+    file.sync_all()?;
+    Ok(temp)
+}
+
+/// Second half: the rename that makes the new document visible
+fn commit(temp: &Path, path: &Path) -> io::Result<()> {
+    std::fs::rename(temp, path)
+}
+
+/// Saves the live counters and history to one file
+#[derive(Debug)]
+pub struct StateStore {
+    config: PersistConfig,
+    stats: Arc<ServerStats>,
+    #[cfg(feature = "ntp")]
+    history: Option<Arc<NtpHistory>>,
+    loaded: bool,
+}
+
+impl StateStore {
+    /// Load `config.file` into `stats` and `history`
+    pub fn open(
+        config: PersistConfig,
+        stats: Arc<ServerStats>,
+        #[cfg(feature = "ntp")] history: Option<Arc<NtpHistory>>,
+    ) -> Self {
+        let loaded = match load(Path::new(&config.file)) {
+            Loaded::Restored(snapshot) => {
+                stats.restore(&snapshot.stats);
+                #[cfg(feature = "ntp")]
+                if let Some(history) = &history {
+                    history.restore(&snapshot.ntp_history);
+                }
+                info!(
+                    event = "state.loaded",
+                    path = %config.file,
+                    saved_at = snapshot.saved_at,
+                    mcp_requests = snapshot.stats.mcp_requests
+                );
+                true
+            }
+            Loaded::Missing | Loaded::Discarded { .. } => false,
+        };
+        Self {
+            config,
+            stats,
+            #[cfg(feature = "ntp")]
+            history,
+            loaded,
+        }
+    }
+
+    /// Whether startup continued from a saved file
+    pub fn loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn config(&self) -> &PersistConfig {
+        &self.config
+    }
+
+    /// Time since the file was last written, None before the first save
+    pub fn file_age(&self) -> Option<Duration> {
+        let modified = std::fs::metadata(&self.config.file).ok()?.modified().ok()?;
+        Some(
+            SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            schema_version: SCHEMA_VERSION,
+            saved_at: Utc::now().timestamp(),
+            stats: self.stats.snapshot(),
+            #[cfg(feature = "ntp")]
+            ntp_history: self
+                .history
+                .as_ref()
+                .map(|history| history.since(i64::MIN))
+                .unwrap_or_default(),
+            #[cfg(not(feature = "ntp"))]
+            ntp_history: Vec::new(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        save(Path::new(&self.config.file), &self.snapshot())
+    }
+
+    /// Save, logging instead of failing
+    pub fn save_logged(&self) {
+        if let Err(e) = self.save() {
+            warn!(event = "state.save_failed", path = %self.config.file, error = %e);
+        }
+    }
+}
+
+static GLOBAL: OnceLock<StateStore> = OnceLock::new();
+
+/// Load the state file into the global counters and NTP history; call after
+/// `ServerState::init`
+pub fn init(config: Option<&PersistConfig>) {
+    let Some(config) = config else { return };
+    let _ = GLOBAL.set(StateStore::open(
+        config.clone(),
+        crate::stats::global().clone(),
+        #[cfg(feature = "ntp")]
+        crate::state::ServerState::shared()
+            .ntp_clock()
+            .history()
+            .cloned(),
+    ));
+}
+
+pub fn global() -> Option<&'static StateStore> {
+    GLOBAL.get()
+}
+
+/// Save every `save_interval_secs` until the process exits
+pub fn spawn_saver() -> Option<tokio::task::JoinHandle<()>> {
+    let store = global()?;
+    let interval = Duration::from_secs(store.config.save_interval_secs.max(1));
+    Some(tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        // The first tick is immediate; there is nothing new to save yet
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            store.save_logged();
+        }
+    }))
+}
+
+/// Final save at shutdown
+pub fn save_now() {
+    if let Some(store) = global() {
+        store.save_logged();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "ntp")]
+    use crate::ntp::history::NtpHistoryConfig;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("persist-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("state.json")
+    }
+
+    #[cfg(feature = "ntp")]
+    fn history() -> Arc<NtpHistory> {
+        Arc::new(NtpHistory::new(NtpHistoryConfig {
+            interval_secs: 60,
+            capacity: 10,
+            file: None,
+        }))
+    }
+
+    fn store(path: &Path) -> StateStore {
+        StateStore::open(
+            PersistConfig {
+                file: path.to_string_lossy().into_owned(),
+                save_interval_secs: DEFAULT_SAVE_INTERVAL_SECS,
+            },
+            Arc::new(ServerStats::new()),
+            #[cfg(feature = "ntp")]
+            Some(history()),
+        )
+    }
+
+    fn snapshot(mcp_requests: u64) -> Snapshot {
+        Snapshot {
+            schema_version: SCHEMA_VERSION,
+            saved_at: 1_700_000_000,
+            stats: StatsSnapshot {
+                mcp_requests,
+                ..StatsSnapshot::default()
+            },
+            ntp_history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("round-trip");
+        let first = store(&path);
+        assert!(!first.loaded());
+        assert!(first.file_age().is_none());
+        first.stats.record_tool_call("get_time");
+        first.stats.record_tool_call("get_time");
+        #[cfg(feature = "ntp")]
+        first.history.as_ref().unwrap().record(NtpSample {
+            timestamp: 1_700_000_000,
+            offset_ms: 1.5,
+            stratum: 2,
+            synced: true,
+        });
+        first.save().unwrap();
+
+        let second = store(&path);
+        assert!(second.loaded());
+        assert!(second.file_age().is_some());
+        second.stats.record_tool_call("get_time");
+        assert_eq!(second.stats.tool_calls()["get_time"], 3);
+        assert_eq!(second.stats.mcp_requests(), first.stats.mcp_requests() + 1);
+        #[cfg(feature = "ntp")]
+        assert_eq!(second.history.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_file_is_backed_up() {
+        let path = temp_path("corrupt");
+        std::fs::write(&path, "{\"schema_version\": 1, \"stats\": ").unwrap();
+        let Loaded::Discarded { backup, .. } = load(&path) else {
+            panic!("a truncated file must be discarded");
+        };
+        let backup = backup.unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&backup).unwrap(),
+            "{\"schema_version\": 1, \"stats\": "
+        );
+
+        let mut future = serde_json::to_value(snapshot(5)).unwrap();
+        future["schema_version"] = (SCHEMA_VERSION + 1).into();
+        std::fs::write(&path, future.to_string()).unwrap();
+        let fresh = store(&path);
+        assert!(!fresh.loaded());
+        assert_eq!(fresh.stats.mcp_requests(), 0);
+    }
+
+    #[test]
+    fn test_interrupted_save_keeps_the_old_file() {
+        let path = temp_path("atomic");
+        save(&path, &snapshot(1)).unwrap();
+
+        // Crash after writing the new document, before the rename
+        let temp = write_temp(&path, &snapshot(2)).unwrap();
+        let Loaded::Restored(seen) = load(&path) else {
+            panic!("the previous save must still load");
+        };
+        assert_eq!(seen.stats.mcp_requests, 1);
+
+        // The next save replaces the leftover temp file
+        save(&path, &snapshot(3)).unwrap();
+        assert!(!temp.exists());
+        let Loaded::Restored(seen) = load(&path) else {
+            panic!("the new save must load");
+        };
+        assert_eq!(seen.stats.mcp_requests, 3);
+    }
+}
@@ -0,0 +1,361 @@
+// Per-session defaults for timezone, format, locale and precision
+//
+// Each MCP session gets a `Session` handle when its `TimeServer` is created;
+// preferences live in one process-wide `PreferenceStore` keyed by session and
+// are removed when the last handle for that session is dropped. The store is
+// capped and evicts the least recently used entry, so transports whose
+// sessions never end explicitly (API-key keyed HTTP clients) stay bounded.
+
+use crate::error::TimeServerError;
+use crate::time::TimezoneConverter;
+use chrono::SecondsFormat;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::debug;
+
+/// Default cap on stored sessions
+pub const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+/// Sub-second digits in RFC 3339 output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl From<Precision> for SecondsFormat {
+    fn from(precision: Precision) -> Self {
+        match precision {
+            Precision::Seconds => SecondsFormat::Secs,
+            Precision::Millis => SecondsFormat::Millis,
+            Precision::Micros => SecondsFormat::Micros,
+            Precision::Nanos => SecondsFormat::Nanos,
+        }
+    }
+}
+
+/// Defaults used when a tool parameter is omitted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Preferences {
+    /// IANA timezone (e.g., 'Australia/Melbourne')
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// strftime format for get_time_formatted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// BCP 47 language tag (e.g., 'en-AU'); stored and reported, output is not localized yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Sub-second digits for get_time: seconds, millis, micros or nanos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<Precision>,
+}
+
+impl Preferences {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check every field, canonicalizing the timezone name
+    pub fn validated(mut self) -> Result<Self, TimeServerError> {
+        if let Some(tz) = &self.timezone {
+            let resolved = TimezoneConverter::resolve_name(tz)
+                .ok_or_else(|| TimeServerError::invalid_timezone(tz.as_str()))?;
+            self.timezone = Some(resolved);
+        }
+        if let Some(format) = &self.format {
+            crate::time::StrftimeFormatter::validate(format)?;
+        }
+        if let Some(locale) = &self.locale {
+            if !is_language_tag(locale) {
+                return Err(TimeServerError::InvalidArgument(format!(
+                    "'{}' is not a BCP 47 language tag (e.g., 'en-AU')",
+                    locale
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Fields of `update` that are set replace the current ones
+    pub fn merge(&mut self, update: Preferences) {
+        if update.timezone.is_some() {
+            self.timezone = update.timezone;
+        }
+        if update.format.is_some() {
+            self.format = update.format;
+        }
+        if update.locale.is_some() {
+            self.locale = update.locale;
+        }
+        if update.precision.is_some() {
+            self.precision = update.precision;
+        }
+    }
+}
+
+/// Shape check only: a 2-3 letter language followed by 1-8 character subtags
+fn is_language_tag(tag: &str) -> bool {
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|p| (1..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Who the preferences belong to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SessionKey {
+    /// One MCP connection
+    Mcp(u64),
+    /// HTTP clients, identified by API key name
+    ApiKey(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    preferences: Preferences,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<SessionKey, Entry>,
+    clock: u64,
+}
+
+/// Preferences of every live session, least recently used evicted first
+#[derive(Debug)]
+pub struct PreferenceStore {
+    inner: Mutex<Inner>,
+    max_sessions: usize,
+}
+
+impl PreferenceStore {
+    pub fn new(max_sessions: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            max_sessions: max_sessions.max(1),
+        }
+    }
+
+    /// Cap from `PREFERENCES_MAX_SESSIONS`
+    pub fn from_env() -> Self {
+        let max = std::env::var("PREFERENCES_MAX_SESSIONS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_SESSIONS);
+        Self::new(max)
+    }
+
+    pub fn get(&self, key: &SessionKey) -> Preferences {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.clock += 1;
+        let now = inner.clock;
+        match inner.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = now;
+                entry.preferences.clone()
+            }
+            None => Preferences::default(),
+        }
+    }
+
+    /// Merge `update` into the session's preferences and return the result
+    pub fn update(&self, key: SessionKey, update: Preferences) -> Preferences {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.clock += 1;
+        let now = inner.clock;
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.max_sessions {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                debug!(event = "preferences.evict", session = ?oldest);
+                inner.entries.remove(&oldest);
+            }
+        }
+
+        let entry = inner.entries.entry(key).or_insert_with(|| Entry {
+            preferences: Preferences::default(),
+            last_used: now,
+        });
+        entry.preferences.merge(update);
+        entry.last_used = now;
+        entry.preferences.clone()
+    }
+
+    pub fn remove(&self, key: &SessionKey) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries
+            .remove(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+static GLOBAL: OnceLock<Arc<PreferenceStore>> = OnceLock::new();
+
+/// The process-wide store
+pub fn global() -> &'static Arc<PreferenceStore> {
+    GLOBAL.get_or_init(|| Arc::new(PreferenceStore::from_env()))
+}
+
+/// One MCP session's view of the store; dropping the last clone forgets
+/// the session's preferences
+#[derive(Debug, Clone)]
+pub struct Session(Arc<SessionGuard>);
+
+#[derive(Debug)]
+struct SessionGuard {
+    key: SessionKey,
+    store: Arc<PreferenceStore>,
+}
+
+impl Session {
+    /// A new MCP session in `store`
+    pub fn new(store: Arc<PreferenceStore>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let key = SessionKey::Mcp(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        Self(Arc::new(SessionGuard { key, store }))
+    }
+
+    pub fn key(&self) -> &SessionKey {
+        &self.0.key
+    }
+
+    pub fn preferences(&self) -> Preferences {
+        self.0.store.get(&self.0.key)
+    }
+
+    pub fn update(&self, update: Preferences) -> Preferences {
+        self.0.store.update(self.0.key.clone(), update)
+    }
+
+    pub fn clear(&self) {
+        self.0.store.remove(&self.0.key);
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.store.remove(&self.key);
+    }
+}
+
+/// A response plus the names of the preferences that filled in omitted parameters
+#[derive(Debug, Serialize)]
+pub struct WithDefaults<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub defaults_applied: Vec<&'static str>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timezone(tz: &str) -> Preferences {
+        Preferences {
+            timezone: Some(tz.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_merges_fields() {
+        let store = Arc::new(PreferenceStore::new(8));
+        let session = Session::new(store);
+        session.update(timezone("Australia/Melbourne"));
+        let merged = session.update(Preferences {
+            precision: Some(Precision::Millis),
+            ..Default::default()
+        });
+        assert_eq!(merged.timezone.as_deref(), Some("Australia/Melbourne"));
+        assert_eq!(merged.precision, Some(Precision::Millis));
+        assert_eq!(session.preferences(), merged);
+
+        session.clear();
+        assert!(session.preferences().is_empty());
+    }
+
+    #[test]
+    fn test_sessions_are_isolated_and_dropped() {
+        let store = Arc::new(PreferenceStore::new(8));
+        let a = Session::new(store.clone());
+        let b = Session::new(store.clone());
+        a.update(timezone("Europe/Paris"));
+        assert!(b.preferences().is_empty());
+
+        let a_clone = a.clone();
+        drop(a);
+        assert_eq!(store.len(), 1, "a clone keeps the session alive");
+        drop(a_clone);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let store = PreferenceStore::new(2);
+        let key = |name: &str| SessionKey::ApiKey(name.to_string());
+        store.update(key("a"), timezone("UTC"));
+        store.update(key("b"), timezone("UTC"));
+        store.get(&key("a"));
+        store.update(key("c"), timezone("UTC"));
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get(&key("b")).is_empty());
+        assert!(!store.get(&key("a")).is_empty());
+        assert!(!store.get(&key("c")).is_empty());
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_validation() {
+        let prefs = timezone("australia/melbourne").validated().unwrap();
+        assert_eq!(prefs.timezone.as_deref(), Some("Australia/Melbourne"));
+        assert!(timezone("Mars/Olympus").validated().is_err());
+
+        let bad_format = Preferences {
+            format: Some("%Q".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_format.validated().is_err());
+
+        let locale = |tag: &str| Preferences {
+            locale: Some(tag.to_string()),
+            ..Default::default()
+        };
+        assert!(locale("en-AU").validated().is_ok());
+        assert!(locale("zh-Hant-TW").validated().is_ok());
+        assert!(locale("english").validated().is_err());
+    }
+}
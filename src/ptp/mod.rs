@@ -0,0 +1,444 @@
+// Precision Time Protocol status (linuxptp)
+//
+// Hosts disciplined by ptp4l and phc2sys have no NTP daemon to ask, so the
+// NTP tools call them unsynchronized however good the clock is. `PtpProbe`
+// lists the PTP hardware clocks in sysfs (`/sys/class/ptp/ptpN/clock_name`),
+// asks ptp4l for TIME_STATUS_NP and PORT_DATA_SET through `pmc`, and reads
+// the PHC-to-system offset with `phc_ctl <device> cmp`. The commands run
+// through the same `CommandRunner` as ntpq, so tests feed captured output;
+// a missing binary or an unreadable device becomes a note, not an error.
+// The latest status is kept for the `ptp` time source and the health
+// grading; with ENABLE_PTP=yes (or `ptp` in TIME_SOURCE_PRIORITY) a task
+// refreshes it every `MONITOR_INTERVAL`.
+
+pub mod pmc;
+
+use crate::ntp::query::{CommandRunner, TokioRunner, NTP_COMMAND_TIMEOUT};
+use crate::ntp::NtpConfig;
+use crate::time::TimeSource;
+use pmc::PortData;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::{debug, info};
+
+/// Where the kernel lists PTP hardware clocks
+pub const SYSFS_PTP_CLASS: &str = "/sys/class/ptp";
+
+/// How often the monitor asks ptp4l again
+pub const MONITOR_INTERVAL: Duration = Duration::from_secs(16);
+
+/// A recorded status older than this is not used for time or grading
+pub const MAX_STATUS_AGE: Duration = Duration::from_secs(60);
+
+/// Port state of a port that follows a grandmaster
+pub const SLAVE_STATE: &str = "SLAVE";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PtpConfig {
+    pub enabled: bool,
+}
+
+/// A PTP hardware clock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PtpDevice {
+    /// "/dev/ptp0"
+    pub device: String,
+    /// Driver-given name from sysfs ("e1000e PTP"), if readable
+    pub clock_name: Option<String>,
+}
+
+/// System clock relative to a PHC, from `phc_ctl`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PhcOffset {
+    pub device: String,
+    /// PHC minus CLOCK_REALTIME; includes the TAI-UTC offset when the PHC runs on TAI
+    pub offset_ns: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PtpStatus {
+    /// A PTP hardware clock exists or ptp4l answered
+    pub available: bool,
+    /// ptp4l sees a grandmaster and a port follows it
+    pub synced: bool,
+    pub devices: Vec<PtpDevice>,
+    /// The `pmc` binary could be run
+    pub pmc_available: bool,
+    pub master_offset_ns: Option<i64>,
+    pub gm_present: Option<bool>,
+    pub gm_identity: Option<String>,
+    /// State of the port following the grandmaster, else of the first port
+    pub port_state: Option<String>,
+    pub ports: Vec<PortData>,
+    pub phc_offset: Option<PhcOffset>,
+    /// What could not be read, and why
+    pub notes: Vec<String>,
+}
+
+/// The PTP clocks listed under `class_dir`, in device order
+pub fn list_devices(class_dir: &Path) -> Vec<PtpDevice> {
+    let Ok(entries) = std::fs::read_dir(class_dir) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<(u32, PtpDevice)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let index = name.strip_prefix("ptp")?.parse().ok()?;
+            let clock_name = std::fs::read_to_string(entry.path().join("clock_name"))
+                .ok()
+                .map(|name| name.trim().to_string());
+            Some((
+                index,
+                PtpDevice {
+                    device: format!("/dev/{}", name),
+                    clock_name,
+                },
+            ))
+        })
+        .collect();
+    devices.sort_by_key(|(index, _)| *index);
+    devices.into_iter().map(|(_, device)| device).collect()
+}
+
+enum CommandError {
+    NotFound,
+    Failed(String),
+}
+
+/// Reads PTP status from sysfs and the linuxptp tools
+pub struct PtpProbe<R = TokioRunner> {
+    runner: R,
+    class_dir: PathBuf,
+    command_timeout: Duration,
+}
+
+impl PtpProbe {
+    pub fn new() -> Self {
+        Self::with_runner(TokioRunner, SYSFS_PTP_CLASS)
+    }
+}
+
+impl Default for PtpProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: CommandRunner> PtpProbe<R> {
+    /// Run commands through `runner` and list clocks under `class_dir`
+    pub fn with_runner(runner: R, class_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            runner,
+            class_dir: class_dir.into(),
+            command_timeout: NTP_COMMAND_TIMEOUT,
+        }
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> Result<String, CommandError> {
+        let output = match timeout(self.command_timeout, self.runner.run(program, args)).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(CommandError::NotFound)
+            }
+            Ok(Err(e)) => return Err(CommandError::Failed(e.to_string())),
+            Err(_) => return Err(CommandError::Failed("timed out".to_string())),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(CommandError::Failed(if stderr.is_empty() {
+                format!("exited with {}", output.status)
+            } else {
+                stderr
+            }));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// `pmc` output for `request`; `None` once pmc turned out to be missing
+    async fn pmc(&self, request: &str, status: &mut PtpStatus) -> Option<String> {
+        match self.run("pmc", &["-u", "-b", "0", request]).await {
+            Ok(output) => {
+                status.pmc_available = true;
+                Some(output)
+            }
+            Err(CommandError::NotFound) => {
+                status
+                    .notes
+                    .push("pmc is not installed (linuxptp); ptp4l was not asked".to_string());
+                None
+            }
+            Err(CommandError::Failed(reason)) => {
+                status.pmc_available = true;
+                status.notes.push(format!("pmc {}: {}", request, reason));
+                None
+            }
+        }
+    }
+
+    /// Everything that can be read right now
+    pub async fn status(&self) -> PtpStatus {
+        let mut status = PtpStatus {
+            devices: list_devices(&self.class_dir),
+            ..Default::default()
+        };
+        if status.devices.is_empty() {
+            status.notes.push(format!(
+                "no PTP hardware clocks in {}",
+                self.class_dir.display()
+            ));
+        }
+
+        if let Some(output) = self.pmc("GET TIME_STATUS_NP", &mut status).await {
+            match pmc::parse_time_status(&output) {
+                Ok(time) => {
+                    status.master_offset_ns = Some(time.master_offset_ns);
+                    status.gm_present = Some(time.gm_present);
+                    status.gm_identity = Some(time.gm_identity);
+                }
+                Err(e) => status.notes.push(e),
+            }
+            if let Some(output) = self.pmc("GET PORT_DATA_SET", &mut status).await {
+                match pmc::parse_port_data(&output) {
+                    Ok(ports) => status.ports = ports,
+                    Err(e) => status.notes.push(e),
+                }
+            }
+        }
+        status.port_state = status
+            .ports
+            .iter()
+            .find(|port| port.port_state == SLAVE_STATE)
+            .or(status.ports.first())
+            .map(|port| port.port_state.clone());
+
+        if let Some(device) = status.devices.first().map(|d| d.device.clone()) {
+            match self.run("phc_ctl", &[&device, "cmp"]).await {
+                Ok(output) => match pmc::parse_phc_offset(&output) {
+                    Ok(offset_ns) => status.phc_offset = Some(PhcOffset { device, offset_ns }),
+                    Err(e) => status.notes.push(e),
+                },
+                Err(CommandError::NotFound) => status
+                    .notes
+                    .push("phc_ctl is not installed; no PHC-to-system offset".to_string()),
+                Err(CommandError::Failed(reason)) => {
+                    status.notes.push(format!("phc_ctl {}: {}", device, reason))
+                }
+            }
+        }
+
+        status.available = !status.devices.is_empty() || status.gm_present.is_some();
+        status.synced =
+            status.gm_present == Some(true) && status.port_state.as_deref() == Some(SLAVE_STATE);
+        status
+    }
+}
+
+/// The most recent status and when it was read
+static LATEST: Mutex<Option<(Instant, PtpStatus)>> = Mutex::new(None);
+
+static PROBE: OnceLock<PtpProbe> = OnceLock::new();
+
+/// Keep `status` as the latest, read at `at`
+pub fn record(status: &PtpStatus, at: Instant) {
+    *LATEST.lock().unwrap_or_else(|p| p.into_inner()) = Some((at, status.clone()));
+}
+
+/// The latest status and its age, if it is younger than `MAX_STATUS_AGE` at `now`
+pub fn latest(now: Instant) -> Option<(Duration, PtpStatus)> {
+    let latest = LATEST.lock().unwrap_or_else(|p| p.into_inner());
+    let (at, status) = latest.as_ref()?;
+    let age = now.saturating_duration_since(*at);
+    (age <= MAX_STATUS_AGE).then(|| (age, status.clone()))
+}
+
+/// Read the status with the process-wide probe and record it
+pub async fn refresh() -> PtpStatus {
+    let status = PROBE.get_or_init(PtpProbe::new).status().await;
+    record(&status, Instant::now());
+    status
+}
+
+/// Start the monitor when ENABLE_PTP=yes or the time source priority names ptp
+pub fn init(config: &NtpConfig, priority: &[TimeSource]) {
+    let enabled = config.ptp.as_ref().is_some_and(|ptp| ptp.enabled);
+    if !enabled && !priority.contains(&TimeSource::Ptp) {
+        return;
+    }
+    info!("PTP monitor started");
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+        loop {
+            interval.tick().await;
+            let status = refresh().await;
+            debug!(
+                event = "ptp.status",
+                synced = status.synced,
+                master_offset_ns = ?status.master_offset_ns
+            );
+        }
+    });
+}
+
+/// The tests build exit statuses from raw Unix wait codes
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::ntp::query::BoxFuture;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use tempfile::TempDir;
+
+    /// Answers from captured output; programs without an entry are "not installed"
+    struct Fixture(Vec<(&'static str, &'static str)>);
+
+    impl CommandRunner for Fixture {
+        fn run<'a>(
+            &'a self,
+            program: &'a str,
+            args: &'a [&'a str],
+        ) -> BoxFuture<'a, io::Result<Output>> {
+            let command = format!("{} {}", program, args.join(" "));
+            let reply = self
+                .0
+                .iter()
+                .find(|(prefix, _)| command.starts_with(prefix))
+                .map(|(_, stdout)| *stdout);
+            Box::pin(async move {
+                let stdout = reply.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: stdout.as_bytes().to_vec(),
+                    stderr: Vec::new(),
+                })
+            })
+        }
+    }
+
+    const TIME_STATUS: &str = "sending: GET TIME_STATUS_NP
+\t90e2ba.fffe.2a5b9c-0 seq 0 RESPONSE MANAGEMENT TIME_STATUS_NP
+\t\tmaster_offset              -8
+\t\tgmPresent                  true
+\t\tgmIdentity                 001122.fffe.334455
+";
+
+    const PORT_DATA: &str = "sending: GET PORT_DATA_SET
+\t90e2ba.fffe.2a5b9c-1 seq 0 RESPONSE MANAGEMENT PORT_DATA_SET
+\t\tportIdentity            90e2ba.fffe.2a5b9c-1
+\t\tportState               SLAVE
+";
+
+    fn sysfs(clocks: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (name, clock_name) in clocks {
+            std::fs::create_dir(dir.path().join(name)).unwrap();
+            std::fs::write(dir.path().join(name).join("clock_name"), clock_name).unwrap();
+        }
+        // Not a PTP clock
+        std::fs::create_dir(dir.path().join("power")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_devices_are_listed_in_order() {
+        let dir = sysfs(&[("ptp10", "ice PTP\n"), ("ptp2", "e1000e PTP\n")]);
+        let devices = list_devices(dir.path());
+        assert_eq!(
+            devices,
+            [
+                PtpDevice {
+                    device: "/dev/ptp2".into(),
+                    clock_name: Some("e1000e PTP".into())
+                },
+                PtpDevice {
+                    device: "/dev/ptp10".into(),
+                    clock_name: Some("ice PTP".into())
+                },
+            ]
+        );
+        assert!(list_devices(&dir.path().join("missing")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_locked_host() {
+        let dir = sysfs(&[("ptp0", "e1000e PTP")]);
+        let probe = PtpProbe::with_runner(
+            Fixture(vec![
+                ("pmc -u -b 0 GET TIME_STATUS_NP", TIME_STATUS),
+                ("pmc -u -b 0 GET PORT_DATA_SET", PORT_DATA),
+                (
+                    "phc_ctl /dev/ptp0 cmp",
+                    "phc_ctl[88.1]: offset from CLOCK_REALTIME is 37000000021ns\n",
+                ),
+            ]),
+            dir.path(),
+        );
+        let status = probe.status().await;
+        assert!(status.available);
+        assert!(status.synced);
+        assert!(status.pmc_available);
+        assert_eq!(status.master_offset_ns, Some(-8));
+        assert_eq!(status.gm_identity.as_deref(), Some("001122.fffe.334455"));
+        assert_eq!(status.port_state.as_deref(), Some("SLAVE"));
+        assert_eq!(
+            status.phc_offset,
+            Some(PhcOffset {
+                device: "/dev/ptp0".into(),
+                offset_ns: 37_000_000_021
+            })
+        );
+        assert!(status.notes.is_empty(), "{:?}", status.notes);
+    }
+
+    #[tokio::test]
+    async fn test_devices_without_pmc_degrade_to_a_device_list() {
+        let dir = sysfs(&[("ptp0", "e1000e PTP")]);
+        let status = PtpProbe::with_runner(Fixture(vec![]), dir.path())
+            .status()
+            .await;
+        assert!(status.available);
+        assert!(!status.synced);
+        assert!(!status.pmc_available);
+        assert_eq!(status.devices.len(), 1);
+        assert_eq!(status.master_offset_ns, None);
+        assert_eq!(status.port_state, None);
+        assert_eq!(
+            status.notes,
+            [
+                "pmc is not installed (linuxptp); ptp4l was not asked",
+                "phc_ctl is not installed; no PHC-to-system offset"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nothing_present() {
+        let dir = sysfs(&[]);
+        let status = PtpProbe::with_runner(
+            Fixture(vec![("pmc", "sending: GET TIME_STATUS_NP\n")]),
+            dir.path(),
+        )
+        .status()
+        .await;
+        assert!(!status.available);
+        assert!(status.pmc_available);
+        assert!(status.notes[0].starts_with("no PTP hardware clocks"));
+        assert_eq!(status.notes[1], "no TIME_STATUS_NP response from ptp4l");
+    }
+
+    #[test]
+    fn test_latest_expires() {
+        let at = Instant::now();
+        let status = PtpStatus {
+            synced: true,
+            ..Default::default()
+        };
+        record(&status, at);
+        assert_eq!(latest(at).unwrap().1, status);
+        assert!(latest(at + MAX_STATUS_AGE + Duration::from_secs(1)).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+// Parsing `pmc` management responses
+//
+// `pmc -u -b 0 'GET ...'` prints the request it sent, then one block per
+// responding port: a header line containing `RESPONSE MANAGEMENT <ID>` and
+// indented `name value` lines. When ptp4l is not running pmc still exits 0
+// after printing only the "sending:" line, so a missing header is an error.
+
+use serde::Serialize;
+
+/// The fields of `TIME_STATUS_NP` this server reports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeStatus {
+    /// Offset from the grandmaster in nanoseconds, as ptp4l last measured it
+    pub master_offset_ns: i64,
+    pub gm_present: bool,
+    pub gm_identity: String,
+}
+
+/// One port from `PORT_DATA_SET`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortData {
+    pub port_identity: String,
+    /// "SLAVE", "MASTER", "LISTENING", "FAULTY", ...
+    pub port_state: String,
+}
+
+/// `name value` pairs of every `RESPONSE MANAGEMENT <id>` block in `output`
+fn response_blocks<'a>(output: &'a str, id: &str) -> Result<Vec<Vec<(&'a str, &'a str)>>, String> {
+    let mut blocks: Vec<Vec<(&str, &str)>> = Vec::new();
+    let mut inside = false;
+    for line in output.lines() {
+        if line.contains("RESPONSE MANAGEMENT") {
+            inside = line.split_whitespace().any(|word| word == id);
+            if inside {
+                blocks.push(Vec::new());
+            }
+            continue;
+        }
+        if !inside || line.trim_start().starts_with("sending:") {
+            continue;
+        }
+        if let (Some(block), Some((name, value))) = (
+            blocks.last_mut(),
+            line.trim().split_once(char::is_whitespace),
+        ) {
+            block.push((name, value.trim()));
+        }
+    }
+    if blocks.is_empty() {
+        return Err(format!("no {} response from ptp4l", id));
+    }
+    Ok(blocks)
+}
+
+fn field<'a>(block: &[(&str, &'a str)], name: &str) -> Result<&'a str, String> {
+    block
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| format!("{} missing from pmc output", name))
+}
+
+/// The first `TIME_STATUS_NP` response in `output`
+pub fn parse_time_status(output: &str) -> Result<TimeStatus, String> {
+    let blocks = response_blocks(output, "TIME_STATUS_NP")?;
+    let block = &blocks[0];
+    let master_offset = field(block, "master_offset")?;
+    let gm_present = field(block, "gmPresent")?;
+    Ok(TimeStatus {
+        master_offset_ns: master_offset
+            .parse()
+            .map_err(|_| format!("master_offset '{}' is not a number", master_offset))?,
+        gm_present: match gm_present {
+            "true" => true,
+            "false" => false,
+            other => return Err(format!("gmPresent '{}' is not true or false", other)),
+        },
+        gm_identity: field(block, "gmIdentity")?.to_string(),
+    })
+}
+
+/// Every port in the `PORT_DATA_SET` responses in `output`
+pub fn parse_port_data(output: &str) -> Result<Vec<PortData>, String> {
+    response_blocks(output, "PORT_DATA_SET")?
+        .iter()
+        .map(|block| {
+            Ok(PortData {
+                port_identity: field(block, "portIdentity")?.to_string(),
+                port_state: field(block, "portState")?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The offset in `phc_ctl <device> cmp` output, in nanoseconds
+pub fn parse_phc_offset(output: &str) -> Result<i64, String> {
+    const MARKER: &str = "offset from CLOCK_REALTIME is ";
+    output
+        .lines()
+        .find_map(|line| {
+            let (_, rest) = line.split_once(MARKER)?;
+            rest.trim().trim_end_matches("ns").parse().ok()
+        })
+        .ok_or_else(|| "no offset in phc_ctl output".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from linuxptp 3.1 on a slave port
+    const TIME_STATUS: &str = "sending: GET TIME_STATUS_NP
+\t90e2ba.fffe.2a5b9c-0 seq 0 RESPONSE MANAGEMENT TIME_STATUS_NP
+\t\tmaster_offset              -12
+\t\tingress_time               1700000000123456789
+\t\tcumulativeScaledRateOffset +0.000000000
+\t\tscaledLastGmPhaseChange    0
+\t\tgmTimeBaseIndicator        0
+\t\tlastGmPhaseChange          0x0000'0000000000000000.0000
+\t\tgmPresent                  true
+\t\tgmIdentity                 001122.fffe.334455
+";
+
+    const PORT_DATA: &str = "sending: GET PORT_DATA_SET
+\t90e2ba.fffe.2a5b9c-1 seq 0 RESPONSE MANAGEMENT PORT_DATA_SET
+\t\tportIdentity            90e2ba.fffe.2a5b9c-1
+\t\tportState               SLAVE
+\t\tlogMinDelayReqInterval  0
+\t\tpeerMeanPathDelay       0
+\t\tlogAnnounceInterval     1
+\t\tversionNumber           2
+\t90e2ba.fffe.2a5b9c-2 seq 0 RESPONSE MANAGEMENT PORT_DATA_SET
+\t\tportIdentity            90e2ba.fffe.2a5b9c-2
+\t\tportState               LISTENING
+\t\tlogMinDelayReqInterval  0
+";
+
+    #[test]
+    fn test_time_status() {
+        assert_eq!(
+            parse_time_status(TIME_STATUS).unwrap(),
+            TimeStatus {
+                master_offset_ns: -12,
+                gm_present: true,
+                gm_identity: "001122.fffe.334455".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_port_data_lists_every_port() {
+        let ports = parse_port_data(PORT_DATA).unwrap();
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].port_identity, "90e2ba.fffe.2a5b9c-1");
+        assert_eq!(ports[0].port_state, "SLAVE");
+        assert_eq!(ports[1].port_state, "LISTENING");
+    }
+
+    #[test]
+    fn test_no_response_when_ptp4l_is_down() {
+        let error = parse_time_status("sending: GET TIME_STATUS_NP\n").unwrap_err();
+        assert_eq!(error, "no TIME_STATUS_NP response from ptp4l");
+        // A response to another request does not count
+        assert!(parse_port_data(TIME_STATUS).is_err());
+
+        let truncated = TIME_STATUS.replace("\t\tgmPresent                  true\n", "");
+        assert_eq!(
+            parse_time_status(&truncated).unwrap_err(),
+            "gmPresent missing from pmc output"
+        );
+    }
+
+    #[test]
+    fn test_phc_offset() {
+        let output = "phc_ctl[5211.914]: offset from CLOCK_REALTIME is 36999999858ns\n";
+        assert_eq!(parse_phc_offset(output).unwrap(), 36_999_999_858);
+        assert_eq!(
+            parse_phc_offset("phc_ctl[1.0]: offset from CLOCK_REALTIME is -42ns").unwrap(),
+            -42
+        );
+        assert!(parse_phc_offset("phc_ctl: failed to open /dev/ptp0").is_err());
+    }
+}
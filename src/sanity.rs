@@ -0,0 +1,641 @@
+// Cheap cross-checks of whether the local clock can be trusted
+//
+// Containers have no NTP daemon to ask, so instead of assuming the host clock
+// is fine the server compares it against independent references: the kernel
+// TAI clock, the filesystem's idea of "now", the configured local timezone
+// and (opt-in) the Date header of a well-known HTTPS host. Each check splits
+// into a measurement and a pure `evaluate_*` function so thresholds can be
+// tested with injected values.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::debug;
+
+/// TAI - UTC since 2017-01-01
+pub const TAI_UTC_OFFSET_SECONDS: i64 = 37;
+
+/// Host whose Date header is compared when `SANITY_HTTP_CHECK=true`
+pub const DEFAULT_HTTP_CHECK_URL: &str = "https://www.cloudflare.com";
+
+/// File timestamp skew that is reported as a warning / failure
+const FILESYSTEM_WARN_SECS: f64 = 2.0;
+const FILESYSTEM_FAIL_SECS: f64 = 60.0;
+
+/// Date header skew beyond its own uncertainty reported as a warning / failure
+const HTTP_WARN_SECS: f64 = 1.0;
+const HTTP_FAIL_SECS: f64 = 10.0;
+
+#[cfg(feature = "http-api")]
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `/ready` reuses a report in strict mode
+const READY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SanityCheck {
+    /// CLOCK_TAI minus CLOCK_REALTIME against the leap second offset
+    Tai,
+    /// Modification time of a freshly written temp file against the clock
+    Filesystem,
+    /// TZ and /etc/localtime against the server's UTC-only output
+    Timezone,
+    /// Date header of an HTTPS HEAD request against the clock
+    Http,
+}
+
+impl SanityCheck {
+    pub const ALL: [SanityCheck; 4] = [Self::Tai, Self::Filesystem, Self::Timezone, Self::Http];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "tai" => Some(Self::Tai),
+            "filesystem" | "fs" => Some(Self::Filesystem),
+            "timezone" | "tz" => Some(Self::Timezone),
+            "http" => Some(Self::Http),
+            _ => None,
+        }
+    }
+}
+
+/// Ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Skip,
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub check: SanityCheck,
+    pub status: CheckStatus,
+    pub message: String,
+    /// The raw measurements behind `status`
+    pub values: serde_json::Value,
+}
+
+impl CheckResult {
+    fn new(
+        check: SanityCheck,
+        status: CheckStatus,
+        message: impl Into<String>,
+        values: serde_json::Value,
+    ) -> Self {
+        Self {
+            check,
+            status,
+            message: message.into(),
+            values,
+        }
+    }
+
+    fn skipped(check: SanityCheck, reason: &str) -> Self {
+        Self::new(check, CheckStatus::Skip, reason, json!({}))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanityReport {
+    /// Worst status of the checks that ran
+    pub status: CheckStatus,
+    pub checked_at: String,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SanityReport {
+    pub fn from_results(checks: Vec<CheckResult>) -> Self {
+        Self {
+            status: checks
+                .iter()
+                .map(|c| c.status)
+                .max()
+                .unwrap_or(CheckStatus::Skip),
+            checked_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            checks,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SanityConfig {
+    /// Checks never run (`SANITY_SKIP_CHECKS=tai,filesystem`)
+    pub skip: Vec<SanityCheck>,
+    /// Run the HTTPS Date check (`SANITY_HTTP_CHECK`)
+    pub http_check: bool,
+    /// URL for the Date check (`SANITY_HTTP_URL`)
+    pub http_url: String,
+    /// A failing report makes `/ready` return 503 (`TIME_SANITY_STRICT`)
+    pub strict: bool,
+}
+
+impl Default for SanityConfig {
+    fn default() -> Self {
+        Self {
+            skip: Vec::new(),
+            http_check: false,
+            http_url: DEFAULT_HTTP_CHECK_URL.to_string(),
+            strict: false,
+        }
+    }
+}
+
+impl SanityConfig {
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| crate::output::parse_flag(&v))
+                .unwrap_or(false)
+        };
+        Self {
+            skip: std::env::var("SANITY_SKIP_CHECKS")
+                .map(|list| list.split(',').filter_map(SanityCheck::parse).collect())
+                .unwrap_or_default(),
+            http_check: flag("SANITY_HTTP_CHECK"),
+            http_url: std::env::var("SANITY_HTTP_URL")
+                .unwrap_or_else(|_| DEFAULT_HTTP_CHECK_URL.to_string()),
+            strict: flag("TIME_SANITY_STRICT"),
+        }
+    }
+
+    /// Settings read from the environment once per process
+    pub fn global() -> &'static Self {
+        static CONFIG: OnceLock<SanityConfig> = OnceLock::new();
+        CONFIG.get_or_init(Self::from_env)
+    }
+}
+
+/// Run every check not skipped by `config` or `skip`
+pub async fn run_checks(config: &SanityConfig, skip: &[SanityCheck]) -> SanityReport {
+    let mut results = Vec::with_capacity(SanityCheck::ALL.len());
+    for check in SanityCheck::ALL {
+        let result = if config.skip.contains(&check) || skip.contains(&check) {
+            CheckResult::skipped(check, "skipped by request")
+        } else {
+            match check {
+                SanityCheck::Tai => check_tai(),
+                SanityCheck::Filesystem => check_filesystem(),
+                SanityCheck::Timezone => check_timezone(),
+                SanityCheck::Http if !config.http_check => {
+                    CheckResult::skipped(check, "disabled (set SANITY_HTTP_CHECK=true)")
+                }
+                #[cfg(feature = "http-api")]
+                SanityCheck::Http => check_http(&config.http_url).await,
+                #[cfg(not(feature = "http-api"))]
+                SanityCheck::Http => {
+                    CheckResult::skipped(check, "built without the http-api feature")
+                }
+            }
+        };
+        debug!(event = "sanity.check", check = ?result.check, status = ?result.status);
+        results.push(result);
+    }
+    SanityReport::from_results(results)
+}
+
+/// The last report for a config, rerun at most once a minute (for `/ready`)
+#[derive(Debug)]
+pub struct CachedSanity {
+    config: SanityConfig,
+    last: Mutex<Option<(Instant, SanityReport)>>,
+}
+
+impl CachedSanity {
+    pub fn new(config: SanityConfig) -> Self {
+        Self {
+            config,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &SanityConfig {
+        &self.config
+    }
+
+    pub async fn report(&self) -> SanityReport {
+        if let Some((at, report)) = self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+        {
+            if at.elapsed() < READY_CACHE_TTL {
+                return report.clone();
+            }
+        }
+        let report = run_checks(&self.config, &[]).await;
+        *self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            Some((Instant::now(), report.clone()));
+        report
+    }
+}
+
+fn nanos_to_seconds(nanos: i128) -> f64 {
+    nanos as f64 / 1e9
+}
+
+#[cfg(target_os = "linux")]
+fn clock_nanos(clock: libc::clockid_t) -> Option<i128> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_gettime only writes to the timespec we pass
+    let rc = unsafe { libc::clock_gettime(clock, &mut ts) };
+    (rc == 0).then(|| ts.tv_sec as i128 * 1_000_000_000 + ts.tv_nsec as i128)
+}
+
+#[cfg(target_os = "linux")]
+fn check_tai() -> CheckResult {
+    match (
+        clock_nanos(libc::CLOCK_REALTIME),
+        clock_nanos(libc::CLOCK_TAI),
+    ) {
+        (Some(realtime), Some(tai)) => evaluate_tai(realtime, tai, TAI_UTC_OFFSET_SECONDS),
+        _ => CheckResult::skipped(SanityCheck::Tai, "CLOCK_TAI is not readable"),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_tai() -> CheckResult {
+    CheckResult::skipped(SanityCheck::Tai, "CLOCK_TAI is only available on Linux")
+}
+
+/// Compare the TAI-REALTIME difference with the expected leap offset
+pub fn evaluate_tai(realtime_nanos: i128, tai_nanos: i128, expected: i64) -> CheckResult {
+    let difference = nanos_to_seconds(tai_nanos - realtime_nanos);
+    let whole = difference.round() as i64;
+    let values = json!({
+        "realtime_ns": realtime_nanos.to_string(),
+        "tai_ns": tai_nanos.to_string(),
+        "difference_seconds": difference,
+        "expected_seconds": expected,
+    });
+    let (status, message) = if whole == expected {
+        (
+            CheckStatus::Pass,
+            "CLOCK_TAI matches the leap second offset".to_string(),
+        )
+    } else if whole == 0 {
+        (
+            CheckStatus::Warn,
+            "kernel TAI offset is not set (CLOCK_TAI equals CLOCK_REALTIME)".to_string(),
+        )
+    } else {
+        (
+            CheckStatus::Fail,
+            format!(
+                "CLOCK_TAI is {}s ahead of CLOCK_REALTIME, expected {}s",
+                whole, expected
+            ),
+        )
+    };
+    CheckResult::new(SanityCheck::Tai, status, message, values)
+}
+
+fn check_filesystem() -> CheckResult {
+    let path = std::env::temp_dir().join(format!(
+        "mcp-time-sanity-{}-{}",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let before = SystemTime::now();
+    let written = std::fs::write(&path, b"sanity").and_then(|_| std::fs::metadata(&path));
+    let after = SystemTime::now();
+    let _ = std::fs::remove_file(&path);
+
+    match written.and_then(|meta| meta.modified()) {
+        Ok(modified) => evaluate_filesystem(before.into(), after.into(), modified.into()),
+        Err(e) => CheckResult::skipped(
+            SanityCheck::Filesystem,
+            &format!("could not write a temp file: {}", e),
+        ),
+    }
+}
+
+/// How far a file's mtime lies outside the `before..=after` window
+pub fn evaluate_filesystem(
+    before: DateTime<Utc>,
+    after: DateTime<Utc>,
+    modified: DateTime<Utc>,
+) -> CheckResult {
+    let skew = if modified < before {
+        modified - before
+    } else if modified > after {
+        modified - after
+    } else {
+        chrono::Duration::zero()
+    };
+    let skew_seconds = nanos_to_seconds(skew.num_nanoseconds().unwrap_or(i64::MAX) as i128);
+    let values = json!({
+        "clock_before": before.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        "clock_after": after.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        "file_modified": modified.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        "skew_seconds": skew_seconds,
+    });
+    let status = match skew_seconds.abs() {
+        s if s > FILESYSTEM_FAIL_SECS => CheckStatus::Fail,
+        s if s > FILESYSTEM_WARN_SECS => CheckStatus::Warn,
+        _ => CheckStatus::Pass,
+    };
+    let message = if status == CheckStatus::Pass {
+        "file timestamps agree with the clock".to_string()
+    } else {
+        format!("file timestamps are {:+.3}s off the clock", skew_seconds)
+    };
+    CheckResult::new(SanityCheck::Filesystem, status, message, values)
+}
+
+fn check_timezone() -> CheckResult {
+    use chrono::Offset;
+
+    let tz = std::env::var("TZ").ok();
+    let localtime = std::fs::read_link("/etc/localtime")
+        .ok()
+        .map(|target| target.to_string_lossy().into_owned());
+    let local_offset = chrono::Local::now().offset().fix().local_minus_utc();
+    evaluate_timezone(tz.as_deref(), localtime.as_deref(), local_offset)
+}
+
+/// Zone name from a TZ value or /etc/localtime target
+fn zone_name(value: &str) -> &str {
+    let value = value.trim_start_matches(':');
+    match value.find("zoneinfo/") {
+        Some(i) => {
+            let name = &value[i + "zoneinfo/".len()..];
+            name.strip_prefix("posix/")
+                .or_else(|| name.strip_prefix("right/"))
+                .unwrap_or(name)
+        }
+        None => value,
+    }
+}
+
+fn is_utc_zone(name: &str) -> bool {
+    matches!(
+        name.to_ascii_uppercase().as_str(),
+        "UTC"
+            | "UTC0"
+            | "UCT"
+            | "GMT"
+            | "GMT0"
+            | "ZULU"
+            | "UNIVERSAL"
+            | "ETC/UTC"
+            | "ETC/UCT"
+            | "ETC/GMT"
+            | "ETC/GMT0"
+            | "ETC/ZULU"
+            | "ETC/UNIVERSAL"
+    )
+}
+
+/// Flag a host-local timezone that is not UTC
+pub fn evaluate_timezone(
+    tz: Option<&str>,
+    localtime_target: Option<&str>,
+    local_offset_seconds: i32,
+) -> CheckResult {
+    let tz_zone = tz.map(zone_name).filter(|z| !z.is_empty());
+    let localtime_zone = localtime_target.map(zone_name);
+    let values = json!({
+        "tz": tz,
+        "localtime": localtime_target,
+        "local_offset_seconds": local_offset_seconds,
+    });
+
+    let mut problems = Vec::new();
+    if let Some(zone) = tz_zone.filter(|z| !is_utc_zone(z)) {
+        problems.push(format!("TZ is '{}'", zone));
+    }
+    if let Some(zone) = localtime_zone.filter(|z| !is_utc_zone(z)) {
+        problems.push(format!("/etc/localtime is '{}'", zone));
+    }
+    if local_offset_seconds != 0 {
+        problems.push(format!(
+            "local time is {:+}s from UTC",
+            local_offset_seconds
+        ));
+    }
+
+    if problems.is_empty() {
+        CheckResult::new(
+            SanityCheck::Timezone,
+            CheckStatus::Pass,
+            "host timezone is UTC",
+            values,
+        )
+    } else {
+        CheckResult::new(
+            SanityCheck::Timezone,
+            CheckStatus::Warn,
+            format!(
+                "{}; responses stay UTC but host tools will disagree",
+                problems.join(", ")
+            ),
+            values,
+        )
+    }
+}
+
+#[cfg(feature = "http-api")]
+async fn check_http(url: &str) -> CheckResult {
+    let client = match reqwest::Client::builder().timeout(HTTP_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return http_error(url, &e.to_string()),
+    };
+    let sent = Utc::now();
+    let response = client.head(url).send().await;
+    let received = Utc::now();
+
+    match response {
+        Ok(response) => match response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(date) => evaluate_http(url, sent, received, date),
+            None => http_error(url, "response has no Date header"),
+        },
+        Err(e) => http_error(url, &e.to_string()),
+    }
+}
+
+fn http_error(url: &str, error: &str) -> CheckResult {
+    CheckResult::new(
+        SanityCheck::Http,
+        CheckStatus::Warn,
+        format!("could not read the Date header: {}", error),
+        json!({"url": url}),
+    )
+}
+
+/// Compare a Date header with the local clock.
+///
+/// The header has whole-second resolution and was generated somewhere within
+/// the round trip, so it is compared with the midpoint and only skew beyond
+/// half the round trip plus half a second counts.
+pub fn evaluate_http(
+    url: &str,
+    sent: DateTime<Utc>,
+    received: DateTime<Utc>,
+    date_header: &str,
+) -> CheckResult {
+    let remote = match DateTime::parse_from_rfc2822(date_header) {
+        Ok(remote) => remote.with_timezone(&Utc),
+        Err(e) => {
+            return http_error(url, &format!("unparseable Date '{}': {}", date_header, e));
+        }
+    };
+    let round_trip =
+        nanos_to_seconds((received - sent).num_nanoseconds().unwrap_or_default() as i128);
+    let midpoint = sent + (received - sent) / 2;
+    // Date truncates, so the server's clock read was in [remote, remote + 1s)
+    let offset =
+        nanos_to_seconds((midpoint - remote).num_nanoseconds().unwrap_or(i64::MAX) as i128) - 0.5;
+    let uncertainty = round_trip / 2.0 + 0.5;
+    let excess = (offset.abs() - uncertainty).max(0.0);
+
+    let values = json!({
+        "url": url,
+        "date_header": date_header,
+        "round_trip_seconds": round_trip,
+        "offset_seconds": offset,
+        "uncertainty_seconds": uncertainty,
+    });
+    let status = match excess {
+        e if e > HTTP_FAIL_SECS => CheckStatus::Fail,
+        e if e > HTTP_WARN_SECS => CheckStatus::Warn,
+        _ => CheckStatus::Pass,
+    };
+    let message = if status == CheckStatus::Pass {
+        format!("clock agrees with {} within {:.1}s", url, uncertainty)
+    } else {
+        format!(
+            "clock is {:+.1}s from {} (±{:.1}s)",
+            offset, url, uncertainty
+        )
+    };
+    CheckResult::new(SanityCheck::Http, status, message, values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: i128 = 1_000_000_000;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_tai_offset() {
+        let now = 1_700_000_000 * SECOND;
+        assert_eq!(
+            evaluate_tai(now, now + 37 * SECOND, 37).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(evaluate_tai(now, now + 1000, 37).status, CheckStatus::Warn);
+        let stale = evaluate_tai(now, now + 36 * SECOND, 37);
+        assert_eq!(stale.status, CheckStatus::Fail);
+        assert_eq!(stale.values["difference_seconds"], 36.0);
+    }
+
+    #[test]
+    fn test_filesystem_skew() {
+        let before = at("2024-06-01T12:00:00Z");
+        let after = at("2024-06-01T12:00:00.010Z");
+        let check = |modified| evaluate_filesystem(before, after, at(modified));
+
+        assert_eq!(check("2024-06-01T12:00:00.005Z").status, CheckStatus::Pass);
+        // Coarse filesystem clocks lag slightly behind
+        assert_eq!(check("2024-06-01T11:59:59.996Z").status, CheckStatus::Pass);
+        let warn = check("2024-06-01T12:00:05.010Z");
+        assert_eq!(warn.status, CheckStatus::Warn);
+        assert_eq!(warn.values["skew_seconds"], 5.0);
+        assert_eq!(check("2024-06-01T11:58:00Z").status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_timezone_settings() {
+        let pass = evaluate_timezone(None, Some("/usr/share/zoneinfo/Etc/UTC"), 0);
+        assert_eq!(pass.status, CheckStatus::Pass);
+        assert_eq!(
+            evaluate_timezone(Some(":UTC"), None, 0).status,
+            CheckStatus::Pass
+        );
+
+        let warn = evaluate_timezone(
+            Some("Europe/Berlin"),
+            Some("../usr/share/zoneinfo/posix/Australia/Sydney"),
+            3600,
+        );
+        assert_eq!(warn.status, CheckStatus::Warn);
+        assert!(warn.message.contains("TZ is 'Europe/Berlin'"));
+        assert!(warn.message.contains("'Australia/Sydney'"));
+        assert!(warn.message.contains("+3600s"));
+    }
+
+    #[test]
+    fn test_http_date_accounts_for_round_trip() {
+        let url = "https://example.com";
+        let sent = at("2024-06-01T12:00:00Z");
+        let received = at("2024-06-01T12:00:02Z");
+        let date = "Sat, 01 Jun 2024 12:00:01 GMT";
+
+        let pass = evaluate_http(url, sent, received, date);
+        assert_eq!(pass.status, CheckStatus::Pass);
+        assert_eq!(pass.values["round_trip_seconds"], 2.0);
+        assert_eq!(pass.values["uncertainty_seconds"], 1.5);
+
+        // 3.5s behind the remote clock is 2s beyond the 1.5s uncertainty
+        let late = "Sat, 01 Jun 2024 12:00:04 GMT";
+        assert_eq!(
+            evaluate_http(url, sent, received, late).status,
+            CheckStatus::Warn
+        );
+        let far = "Sat, 01 Jun 2024 12:05:00 GMT";
+        let fail = evaluate_http(url, sent, received, far);
+        assert_eq!(fail.status, CheckStatus::Fail);
+        assert!(fail.values["offset_seconds"].as_f64().unwrap() < -290.0);
+
+        let garbled = evaluate_http(url, sent, received, "yesterday");
+        assert_eq!(garbled.status, CheckStatus::Warn);
+    }
+
+    #[tokio::test]
+    async fn test_report_skips_and_takes_worst_status() {
+        let config = SanityConfig {
+            skip: vec![SanityCheck::Tai],
+            ..Default::default()
+        };
+        let report = run_checks(&config, &[SanityCheck::Timezone]).await;
+        let status = |check| {
+            report
+                .checks
+                .iter()
+                .find(|c| c.check == check)
+                .unwrap()
+                .status
+        };
+        assert_eq!(status(SanityCheck::Tai), CheckStatus::Skip);
+        assert_eq!(status(SanityCheck::Timezone), CheckStatus::Skip);
+        assert_eq!(status(SanityCheck::Http), CheckStatus::Skip);
+        assert_ne!(status(SanityCheck::Filesystem), CheckStatus::Skip);
+        assert_eq!(report.status, status(SanityCheck::Filesystem));
+
+        let all_skipped = run_checks(&config, &SanityCheck::ALL).await;
+        assert_eq!(all_skipped.status, CheckStatus::Skip);
+    }
+}
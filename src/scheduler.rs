@@ -0,0 +1,389 @@
+// One-shot notification scheduler
+//
+// A single tokio task sleeps until the earliest deadline in a binary heap and
+// hands due entries to their sink. Entries live only in memory; dropping the
+// last `Scheduler` handle stops the task and discards anything pending.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Default cap on pending notifications per scheduler (i.e. per session)
+pub const DEFAULT_MAX_PENDING: usize = 32;
+
+/// Furthest ahead a notification may be scheduled
+pub const MAX_SCHEDULE_AHEAD_SECS: i64 = 30 * 24 * 3600;
+
+/// A pending notification as reported by `list_scheduled`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledEntry {
+    pub id: u64,
+    pub label: String,
+    /// Requested fire time (RFC 3339, UTC)
+    pub fire_at: String,
+    pub created_at: String,
+}
+
+/// Delivered when a scheduled time arrives
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FiredNotification {
+    pub id: u64,
+    pub label: String,
+    pub requested_at: String,
+    pub fired_at: String,
+    /// `fired_at - requested_at` in milliseconds
+    pub drift_ms: f64,
+}
+
+/// Where fired notifications go (an MCP peer, an SSE stream, a test channel)
+pub trait NotificationSink: Send + Sync {
+    fn deliver(&self, notification: FiredNotification);
+}
+
+impl NotificationSink for mpsc::UnboundedSender<FiredNotification> {
+    fn deliver(&self, notification: FiredNotification) {
+        let _ = self.send(notification);
+    }
+}
+
+struct Pending {
+    label: String,
+    fire_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    sink: Arc<dyn NotificationSink>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Min-heap of (deadline, id); cancelled ids are skipped lazily
+    heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    pending: HashMap<u64, Pending>,
+    next_id: u64,
+    task_started: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    max_pending: usize,
+}
+
+/// Cheap-to-clone handle; the task stops when the last handle is dropped
+#[derive(Clone)]
+pub struct Scheduler {
+    shared: Arc<Shared>,
+    /// Wakes the task when a registration may have moved the next deadline
+    wake: Arc<Notify>,
+    /// Dropping the sender (with the last handle) stops the task, which only
+    /// holds a weak reference to the shared state
+    shutdown: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+}
+
+impl Scheduler {
+    pub fn new(max_pending: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+                max_pending,
+            }),
+            wake: Arc::new(Notify::new()),
+            shutdown: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Register a notification for `fire_at`; returns its id
+    pub fn schedule(
+        &self,
+        fire_at: DateTime<Utc>,
+        label: impl Into<String>,
+        sink: Arc<dyn NotificationSink>,
+    ) -> Result<ScheduledEntry, String> {
+        let now = Utc::now();
+        let ahead = fire_at - now;
+        if ahead.num_seconds() > MAX_SCHEDULE_AHEAD_SECS {
+            return Err(format!(
+                "Cannot schedule more than {} days ahead",
+                MAX_SCHEDULE_AHEAD_SECS / 86400
+            ));
+        }
+        let deadline = Instant::now() + ahead.to_std().unwrap_or_default();
+
+        let entry = {
+            let mut state = self
+                .shared
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if state.pending.len() >= self.shared.max_pending {
+                return Err(format!(
+                    "Too many pending notifications (limit {})",
+                    self.shared.max_pending
+                ));
+            }
+
+            state.next_id += 1;
+            let id = state.next_id;
+            let pending = Pending {
+                label: label.into(),
+                fire_at,
+                created_at: now,
+                sink,
+            };
+            let entry = pending.entry(id);
+            state.pending.insert(id, pending);
+            state.heap.push(Reverse((deadline, id)));
+
+            if !state.task_started {
+                state.task_started = true;
+                self.spawn_task();
+            }
+            entry
+        };
+
+        self.wake.notify_one();
+        Ok(entry)
+    }
+
+    /// Remove a pending notification; false if it already fired or never existed
+    pub fn cancel(&self, id: u64) -> bool {
+        // The heap entry stays and is skipped when it comes due
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pending
+            .remove(&id)
+            .is_some()
+    }
+
+    /// Pending notifications, soonest first
+    pub fn list(&self) -> Vec<ScheduledEntry> {
+        let state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut entries: Vec<(DateTime<Utc>, ScheduledEntry)> = state
+            .pending
+            .iter()
+            .map(|(id, p)| (p.fire_at, p.entry(*id)))
+            .collect();
+        entries.sort_by_key(|(fire_at, entry)| (*fire_at, entry.id));
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.shared.max_pending
+    }
+
+    fn spawn_task(&self) {
+        let (tx, rx) = oneshot::channel();
+        *self
+            .shutdown
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(tx);
+        tokio::spawn(run(Arc::downgrade(&self.shared), self.wake.clone(), rx));
+    }
+}
+
+impl Pending {
+    fn entry(&self, id: u64) -> ScheduledEntry {
+        ScheduledEntry {
+            id,
+            label: self.label.clone(),
+            fire_at: rfc3339(self.fire_at),
+            created_at: rfc3339(self.created_at),
+        }
+    }
+}
+
+fn rfc3339(dt: DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+async fn run(
+    shared: std::sync::Weak<Shared>,
+    wake: Arc<Notify>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        let Some((due, next_deadline)) = shared.upgrade().map(|strong| take_due(&strong)) else {
+            break;
+        };
+
+        for (id, pending) in due {
+            let fired_at = Utc::now();
+            let drift = fired_at - pending.fire_at;
+            debug!(event = "scheduler.fire", id = id, label = %pending.label);
+            pending.sink.deliver(FiredNotification {
+                id,
+                label: pending.label,
+                requested_at: rfc3339(pending.fire_at),
+                fired_at: rfc3339(fired_at),
+                drift_ms: drift.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0,
+            });
+        }
+
+        // Wait for the next deadline, a new registration, or shutdown
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = wake.notified() => {}
+            _ = sleep_until(next_deadline) => {}
+        }
+    }
+
+    if let Some(shared) = shared.upgrade() {
+        let mut state = shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.pending.clear();
+        state.heap.clear();
+    }
+    debug!(event = "scheduler.stop");
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Pop every due entry; returns them with the next pending deadline
+fn take_due(shared: &Shared) -> (Vec<(u64, Pending)>, Option<Instant>) {
+    let mut state = shared
+        .state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let now = Instant::now();
+    let mut due = Vec::new();
+
+    while let Some(Reverse((deadline, id))) = state.heap.peek().copied() {
+        if !state.pending.contains_key(&id) {
+            state.heap.pop(); // cancelled
+            continue;
+        }
+        if deadline > now {
+            return (due, Some(deadline));
+        }
+        state.heap.pop();
+        if let Some(pending) = state.pending.remove(&id) {
+            due.push((id, pending));
+        }
+    }
+    (due, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn channel_sink() -> (
+        Arc<dyn NotificationSink>,
+        mpsc::UnboundedReceiver<FiredNotification>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Arc::new(tx), rx)
+    }
+
+    #[tokio::test]
+    async fn test_fires_within_tolerance() {
+        let scheduler = Scheduler::new(4);
+        let (sink, mut rx) = channel_sink();
+
+        let fire_at = Utc::now() + chrono::Duration::milliseconds(100);
+        let entry = scheduler.schedule(fire_at, "tea", sink).unwrap();
+        assert_eq!(scheduler.list().len(), 1);
+
+        let fired = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("notification did not fire")
+            .unwrap();
+        assert_eq!(fired.id, entry.id);
+        assert_eq!(fired.label, "tea");
+        assert!(fired.drift_ms >= 0.0, "fired early: {}", fired.drift_ms);
+        assert!(fired.drift_ms < 250.0, "fired late: {}", fired.drift_ms);
+        assert!(scheduler.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_firing() {
+        let scheduler = Scheduler::new(4);
+        let (sink, mut rx) = channel_sink();
+
+        let soon = Utc::now() + chrono::Duration::milliseconds(100);
+        let cancelled = scheduler.schedule(soon, "cancel me", sink.clone()).unwrap();
+        let kept = scheduler
+            .schedule(soon + chrono::Duration::milliseconds(50), "keep", sink)
+            .unwrap();
+
+        assert!(scheduler.cancel(cancelled.id));
+        assert!(!scheduler.cancel(cancelled.id));
+
+        let fired = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fired.id, kept.id);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(rx.try_recv().is_err(), "cancelled notification fired");
+    }
+
+    #[tokio::test]
+    async fn test_ordering_and_limit() {
+        let scheduler = Scheduler::new(2);
+        let (sink, _rx) = channel_sink();
+        let now = Utc::now();
+
+        scheduler
+            .schedule(now + chrono::Duration::hours(2), "later", sink.clone())
+            .unwrap();
+        scheduler
+            .schedule(now + chrono::Duration::hours(1), "sooner", sink.clone())
+            .unwrap();
+        let labels: Vec<String> = scheduler.list().into_iter().map(|e| e.label).collect();
+        assert_eq!(labels, ["sooner", "later"]);
+
+        let err = scheduler
+            .schedule(now + chrono::Duration::hours(3), "third", sink.clone())
+            .unwrap_err();
+        assert!(err.contains("limit 2"));
+
+        assert!(scheduler
+            .schedule(now + chrono::Duration::days(60), "far", sink)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_registrations() {
+        let scheduler = Scheduler::new(64);
+        let (sink, mut rx) = channel_sink();
+
+        let handles: Vec<_> = (0..32)
+            .map(|i| {
+                let scheduler = scheduler.clone();
+                let sink = sink.clone();
+                tokio::spawn(async move {
+                    let at = Utc::now() + chrono::Duration::milliseconds(20 + i);
+                    scheduler.schedule(at, format!("n{}", i), sink).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        for _ in 0..32 {
+            tokio::time::timeout(Duration::from_secs(2), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+}
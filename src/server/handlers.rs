@@ -1,37 +1,172 @@
 // MCP request handlers for time operations
 
-use crate::error::{McpError, Result};
+use crate::error::{McpError, Result, TimeServerError};
+use crate::mcp::annotations;
+use crate::mcp::content_hash;
+use crate::mcp::params;
+use crate::mcp::policy::ToolPolicy;
 use crate::mcp::types::{
-    McpRequest, McpResponse, PromptArgument, PromptDefinition, PromptsCapability,
-    ServerCapabilities, ToolDefinition, ToolsCapability,
+    ClientInfo, InitializeParams, McpRequest, McpResponse, PromptArgument, PromptDefinition,
+    PromptsCapability, ServerCapabilities, ToolDefinition, ToolsCapability,
 };
+use crate::time::compare::{self, RangeBounds, TimeInput, TimeValue};
+use crate::time::duration::IntervalRules;
+use crate::time::timezone::CONVERSION_NOTE;
 use crate::time::utc::EnhancedTimeResponse;
-use crate::time::{TimezoneConverter, UnixTime};
-use chrono::{Offset, TimeZone, Utc};
+use crate::time::{AmbiguityPolicy, GapPolicy, TimezoneConverter, UnixTime};
+use chrono::Offset;
+use rmcp::model::LoggingLevel;
 use serde_json::{json, Value};
-use tracing::{debug, error};
+use std::sync::Mutex;
+use tracing::{debug, error, info};
+
+/// A snake_case enum argument, its default when absent
+fn optional_enum<T: Default + serde::de::DeserializeOwned>(
+    params: &Value,
+    name: &str,
+) -> Result<T> {
+    match &params[name] {
+        Value::Null => Ok(T::default()),
+        value => serde_json::from_value(value.clone())
+            .map_err(|_| McpError::InvalidParams(format!("invalid {}: {}", name, value))),
+    }
+}
+
+/// Per-request override of `JSON_PRETTY`, accepted by every tool
+const PRETTY_PARAM: &str = "pretty";
+
+/// `schema` (an object schema) with the `pretty` property
+fn with_pretty_property(schema: &mut serde_json::Map<String, Value>) {
+    if let Value::Object(properties) = schema
+        .entry("properties")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()))
+    {
+        properties.insert(
+            PRETTY_PARAM.into(),
+            json!({
+                "type": "boolean",
+                "description": "Pretty-print the JSON result (defaults to JSON_PRETTY)"
+            }),
+        );
+    }
+}
+
+/// Take `pretty` out of `arguments`; None leaves it to `JSON_PRETTY`
+fn take_pretty(arguments: Option<&mut serde_json::Map<String, Value>>) -> Result<Option<bool>> {
+    match arguments.and_then(|arguments| arguments.remove(PRETTY_PARAM)) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(pretty)) => Ok(Some(pretty)),
+        Some(other) => Err(McpError::InvalidParams(format!(
+            "{} must be true or false, not {}",
+            PRETTY_PARAM, other
+        ))),
+    }
+}
+
+/// Protocol versions this handler speaks, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Progress of the initialize handshake
+#[derive(Debug, Default)]
+struct Handshake {
+    /// Set by `initialize`
+    client: Option<ClientInfo>,
+    protocol_version: Option<&'static str>,
+    /// Set by `notifications/initialized`; other requests wait for it
+    initialized: bool,
+}
 
-pub struct TimeHandler;
+#[derive(Default)]
+pub struct TimeHandler {
+    /// Level from logging/setLevel. This transport only answers requests,
+    /// so nothing is sent at it yet; it is kept for parity with the rmcp server.
+    log_level: Mutex<Option<LoggingLevel>>,
+    handshake: Mutex<Handshake>,
+    /// Drop unknown tool arguments instead of rejecting the call
+    lenient_params: bool,
+    /// Tools left out of tools/list and refused when called
+    disabled_tools: ToolPolicy,
+}
+
+/// The newest supported version not newer than `requested`. Versions are
+/// dates, so a client ahead of this server gets the newest one.
+pub fn negotiate_protocol_version(requested: &str) -> Option<&'static str> {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .copied()
+        .find(|version| *version <= requested)
+}
 
 impl TimeHandler {
     pub fn new() -> Self {
-        Self
+        Self {
+            lenient_params: params::lenient_from_env(),
+            disabled_tools: ToolPolicy::from_env(),
+            ..Self::default()
+        }
+    }
+
+    /// Drop unknown tool arguments instead of rejecting the call
+    /// (defaults to `MCP_LENIENT_PARAMS`)
+    pub fn with_lenient_params(mut self, lenient: bool) -> Self {
+        self.lenient_params = lenient;
+        self
+    }
+
+    /// Refuse the tools `policy` disables (defaults to `DISABLE_NTP_TOOLS`,
+    /// `DISABLE_SUBPROCESS` and `DISABLED_TOOLS`)
+    pub fn with_disabled_tools(mut self, policy: ToolPolicy) -> Self {
+        self.disabled_tools = policy;
+        self
+    }
+
+    /// Level most recently set with logging/setLevel
+    pub fn log_level(&self) -> Option<LoggingLevel> {
+        *self.log_level.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Client that completed `initialize`, as sent in `clientInfo`
+    pub fn client_info(&self) -> Option<ClientInfo> {
+        self.handshake().client.clone()
+    }
+
+    /// Version agreed in `initialize`
+    pub fn protocol_version(&self) -> Option<&'static str> {
+        self.handshake().protocol_version
+    }
+
+    fn handshake(&self) -> std::sync::MutexGuard<'_, Handshake> {
+        self.handshake.lock().unwrap_or_else(|p| p.into_inner())
     }
 
     pub async fn handle_request(&self, request: McpRequest) -> McpResponse {
         // Handle notifications (no response needed, but we shouldn't error)
         if request.method.starts_with("notifications/") {
             debug!("Received notification: {}", request.method);
+            if request.method == "notifications/initialized" {
+                let mut handshake = self.handshake();
+                handshake.initialized = handshake.client.is_some();
+            }
             // Notifications don't get responses, but we return success for logging
             return McpResponse::success(json!({}), request.id);
         }
 
+        if request.method != "initialize" && !self.handshake().initialized {
+            return McpResponse::error(
+                McpError::NotInitialized.code(),
+                McpError::NotInitialized.to_string(),
+                request.id,
+            );
+        }
+
         let result = match request.method.as_str() {
             "initialize" => self.handle_initialize(request.params).await,
             "tools/list" => self.list_tools(request.params).await,
             "tools/call" => self.call_tool(request.params).await,
             "prompts/list" => self.list_prompts(request.params).await,
             "prompts/get" => self.get_prompt(request.params).await,
+            "completion/complete" => self.complete(request.params).await,
+            "logging/setLevel" => self.set_level(request.params),
             // Legacy direct methods (for backward compatibility)
             "time/get" => self.get_time(request.params).await,
             "time/get_with_format" => self.get_time_formatted(request.params).await,
@@ -46,14 +181,46 @@ impl TimeHandler {
         match result {
             Ok(value) => McpResponse::success(value, request.id),
             Err(e) => {
-                error!("Request error: {}", e);
-                McpResponse::error(e.code(), e.to_string(), request.id)
+                let client = self.client_info().map(|c| c.name).unwrap_or_default();
+                error!(client = %client, "Request error: {}", e);
+                McpResponse::error(e.code(), e.to_string(), request.id).with_error_data(e.data())
             }
         }
     }
 
-    async fn handle_initialize(&self, _params: Value) -> Result<Value> {
+    async fn handle_initialize(&self, params: Value) -> Result<Value> {
         debug!("Handling initialize request");
+        let params: InitializeParams = serde_json::from_value(params).map_err(|e| {
+            McpError::InvalidParams(format!(
+                "initialize needs protocolVersion and clientInfo {{name, version}}: {}",
+                e
+            ))
+        })?;
+        let protocol_version =
+            negotiate_protocol_version(&params.protocol_version).ok_or_else(|| {
+                McpError::UnsupportedProtocolVersion {
+                    requested: params.protocol_version.clone(),
+                    supported: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
+                }
+            })?;
+
+        {
+            let mut handshake = self.handshake();
+            if handshake.client.is_some() {
+                return Err(McpError::InvalidRequest(
+                    "initialize may only be sent once per session".to_string(),
+                ));
+            }
+            info!(
+                client = %params.client_info.name,
+                client_version = %params.client_info.version,
+                requested = %params.protocol_version,
+                protocol_version,
+                "Client initialized"
+            );
+            handshake.client = Some(params.client_info);
+            handshake.protocol_version = Some(protocol_version);
+        }
 
         let capabilities = ServerCapabilities {
             tools: Some(ToolsCapability {
@@ -63,10 +230,12 @@ impl TimeHandler {
                 list_changed: Some(false),
             }),
             resources: None, // Not implementing resources for this time server
+            completions: Some(json!({})),
+            logging: Some(json!({})),
         };
 
         Ok(json!({
-            "protocolVersion": "2025-06-18",
+            "protocolVersion": protocol_version,
             "serverInfo": {
                 "name": "mcp-utc-time-server",
                 "version": "0.1.0"
@@ -75,6 +244,39 @@ impl TimeHandler {
         }))
     }
 
+    fn set_level(&self, params: Value) -> Result<Value> {
+        let level: LoggingLevel = serde_json::from_value(params["level"].clone()).map_err(|_| {
+            McpError::InvalidParams(
+                "level must be one of debug, info, notice, warning, error, critical, alert, emergency"
+                    .to_string(),
+            )
+        })?;
+        debug!("Logging level set to {:?}", level);
+        *self.log_level.lock().unwrap_or_else(|p| p.into_inner()) = Some(level);
+        Ok(json!({}))
+    }
+
+    async fn complete(&self, params: Value) -> Result<Value> {
+        let argument = &params["argument"];
+        let name = argument["name"]
+            .as_str()
+            .ok_or_else(|| McpError::InvalidParams("Missing argument.name".to_string()))?;
+        let value = argument["value"].as_str().unwrap_or("");
+        debug!("Completing {} = {:?}", name, value);
+
+        let completion = match params["ref"]["type"].as_str() {
+            Some("ref/prompt") => crate::completion::complete_argument(name, value),
+            _ => Default::default(),
+        };
+        Ok(json!({
+            "completion": {
+                "values": completion.values,
+                "total": completion.total,
+                "hasMore": completion.has_more
+            }
+        }))
+    }
+
     async fn list_tools(&self, _params: Value) -> Result<Value> {
         debug!("Listing tools");
         Ok(json!({
@@ -89,18 +291,21 @@ impl TimeHandler {
                 title: Some("Get Current Time".to_string()),
                 description: "Get current UTC time with full Unix/POSIX details".to_string(),
                 input_schema: None,
+                annotations: None,
             },
             ToolDefinition {
                 name: "get_unix_time".to_string(),
                 title: Some("Get Unix Time".to_string()),
                 description: "Get Unix epoch time with nanosecond precision".to_string(),
                 input_schema: None,
+                annotations: None,
             },
             ToolDefinition {
                 name: "get_nanos".to_string(),
                 title: Some("Get Nanoseconds".to_string()),
                 description: "Get nanoseconds since Unix epoch".to_string(),
                 input_schema: None,
+                annotations: None,
             },
             ToolDefinition {
                 name: "get_time_formatted".to_string(),
@@ -112,10 +317,15 @@ impl TimeHandler {
                         "format": {
                             "type": "string",
                             "description": "strftime format string (e.g., '%Y-%m-%d %H:%M:%S')"
+                        },
+                        "timezone": {
+                            "type": "string",
+                            "description": "IANA timezone to format in (defaults to UTC); %Z gives its abbreviation and %z its offset"
                         }
                     },
                     "required": ["format"]
                 })),
+                annotations: None,
             },
             ToolDefinition {
                 name: "get_time_with_timezone".to_string(),
@@ -131,12 +341,14 @@ impl TimeHandler {
                     },
                     "required": ["timezone"]
                 })),
+                annotations: None,
             },
             ToolDefinition {
                 name: "list_timezones".to_string(),
                 title: Some("List Timezones".to_string()),
                 description: "List all available IANA timezones".to_string(),
                 input_schema: None,
+                annotations: None,
             },
             ToolDefinition {
                 name: "convert_time".to_string(),
@@ -147,21 +359,84 @@ impl TimeHandler {
                     "properties": {
                         "timestamp": {
                             "type": "number",
-                            "description": "Unix timestamp in seconds"
+                            "description": "Unix timestamp in seconds (absolute)"
+                        },
+                        "local_datetime": {
+                            "type": "string",
+                            "description": "Wall-clock time in from_timezone instead of a timestamp"
                         },
                         "from_timezone": {
                             "type": "string",
-                            "description": "Source timezone (optional, defaults to UTC)"
+                            "description": "IANA timezone of the original (optional, defaults to UTC)"
+                        },
+                        "ambiguity": {
+                            "type": "string",
+                            "enum": ["earlier", "later", "reject"],
+                            "description": "Policy for a local_datetime that occurs twice (defaults to earlier)"
+                        },
+                        "nonexistent": {
+                            "type": "string",
+                            "enum": ["reject", "shift_forward"],
+                            "description": "Policy for a skipped local_datetime (defaults to reject)"
                         },
                         "to_timezone": {
                             "type": "string",
                             "description": "Target timezone"
                         }
                     },
-                    "required": ["timestamp", "to_timezone"]
+                    "required": ["to_timezone"]
+                })),
+                annotations: None,
+            },
+            ToolDefinition {
+                name: "compare_times".to_string(),
+                title: Some("Compare Times".to_string()),
+                description: "Order two times given in any mix of Unix seconds/ms, RFC 3339, RFC 2822 or a custom format; with c, check whether a lies between b and c".to_string(),
+                input_schema: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "a": {
+                            "description": "The time in question: a number, a string, or {value, representation, format}"
+                        },
+                        "b": {
+                            "description": "The time to compare a with; with c, one end of the range"
+                        },
+                        "c": {
+                            "description": "Other end of the range (optional)"
+                        },
+                        "bounds": {
+                            "type": "string",
+                            "enum": ["inclusive", "exclusive", "half_open"],
+                            "description": "Which range ends count as inside (defaults to inclusive)"
+                        },
+                        "order_policy": {
+                            "type": "string",
+                            "enum": ["strict", "signed", "absolute"],
+                            "description": "When b is before a: signed (default) gives a negative difference, absolute swaps them and sets inputs_swapped, strict fails"
+                        }
+                    },
+                    "required": ["a", "b"]
                 })),
+                annotations: None,
             },
         ]
+        .into_iter()
+        .filter(|tool| !self.disabled_tools.is_disabled(&tool.name))
+        .map(|tool| {
+            let mut schema = match tool.input_schema {
+                Some(Value::Object(schema)) => schema,
+                _ => serde_json::Map::from_iter([("type".into(), json!("object"))]),
+            };
+            content_hash::with_hash_property(&mut schema);
+            with_pretty_property(&mut schema);
+            ToolDefinition {
+                // The same hints the rmcp server lists
+                annotations: annotations::annotations(&tool.name),
+                input_schema: Some(Value::Object(schema)),
+                ..tool
+            }
+        })
+        .collect()
     }
 
     async fn get_time(&self, _params: Value) -> Result<Value> {
@@ -175,18 +450,22 @@ impl TimeHandler {
             .as_str()
             .ok_or_else(|| McpError::InvalidParams("format required".to_string()))?;
 
+        let timezone = params["timezone"].as_str();
+
         debug!("Getting time with format: {}", format);
         let response = EnhancedTimeResponse::now();
-        let formatted = response
-            .format_custom(format)
-            .map_err(|e| McpError::TimeError(e.to_string()))?;
+        let formatted = response.format_custom(format, timezone)?;
 
-        Ok(json!({
+        let mut result = json!({
             "formatted": formatted,
             "format": format,
             "unix_seconds": response.unix.seconds,
             "unix_nanos": response.unix.nanos,
-        }))
+        });
+        if let Some(tz) = timezone {
+            result["timezone"] = json!(tz);
+        }
+        Ok(result)
     }
 
     async fn get_time_with_tz(&self, params: Value) -> Result<Value> {
@@ -195,8 +474,7 @@ impl TimeHandler {
             .ok_or_else(|| McpError::InvalidParams("timezone required".to_string()))?;
 
         debug!("Getting time for timezone: {}", timezone);
-        let response = EnhancedTimeResponse::with_timezone(timezone)
-            .map_err(|e| McpError::InvalidParams(e))?;
+        let response = EnhancedTimeResponse::with_timezone(timezone)?;
 
         Ok(serde_json::to_value(response)?)
     }
@@ -210,16 +488,12 @@ impl TimeHandler {
     async fn get_nanos(&self, _params: Value) -> Result<Value> {
         debug!("Getting nanoseconds");
         let unix_time = UnixTime::now();
-        Ok(json!({
-            "nanoseconds": unix_time.nanos_since_epoch,
-            "seconds": unix_time.seconds,
-            "subsec_nanos": unix_time.nanos,
-        }))
+        Ok(serde_json::to_value(unix_time.nanos_reading())?)
     }
 
     async fn list_timezones(&self, _params: Value) -> Result<Value> {
         debug!("Listing timezones");
-        let timezones = TimezoneConverter::list_timezones();
+        let timezones = TimezoneConverter::timezone_names();
         Ok(json!({
             "timezones": timezones,
             "count": timezones.len(),
@@ -227,32 +501,36 @@ impl TimeHandler {
     }
 
     async fn convert_time(&self, params: Value) -> Result<Value> {
-        let timestamp = params["timestamp"]
-            .as_i64()
-            .ok_or_else(|| McpError::InvalidParams("timestamp required".to_string()))?;
+        let timestamp = match &params["timestamp"] {
+            Value::Null => None,
+            value => Some(
+                value
+                    .as_i64()
+                    .ok_or_else(|| McpError::InvalidParams("Invalid timestamp".to_string()))?,
+            ),
+        };
+        let ambiguity: AmbiguityPolicy = optional_enum(&params, "ambiguity")?;
+        let gap: GapPolicy = optional_enum(&params, "nonexistent")?;
 
-        let from_tz = params["from_timezone"].as_str().unwrap_or("UTC");
+        let from_tz = params["from_timezone"].as_str();
         let to_tz = params["to_timezone"]
             .as_str()
             .ok_or_else(|| McpError::InvalidParams("to_timezone required".to_string()))?;
 
-        debug!("Converting time from {} to {}", from_tz, to_tz);
-
-        // Perform conversion
-        let utc = Utc
-            .timestamp_opt(timestamp, 0)
-            .single()
-            .ok_or_else(|| McpError::InvalidParams("Invalid timestamp".to_string()))?;
+        debug!("Converting time from {:?} to {}", from_tz, to_tz);
 
-        let converted =
-            TimezoneConverter::convert_to_tz(utc, to_tz).map_err(|e| McpError::InvalidParams(e))?;
+        let (utc, original) = TimezoneConverter::conversion_origin(
+            timestamp,
+            params["local_datetime"].as_str(),
+            from_tz,
+            ambiguity,
+            gap,
+        )?;
+        let converted = TimezoneConverter::convert_to_tz(utc, to_tz)?;
 
         Ok(json!({
-            "original": {
-                "timestamp": timestamp,
-                "timezone": from_tz,
-                "formatted": utc.to_rfc3339(),
-            },
+            "original": original,
+            "note": CONVERSION_NOTE,
             "converted": {
                 "timestamp": converted.timestamp(),
                 "timezone": to_tz,
@@ -262,17 +540,65 @@ impl TimeHandler {
         }))
     }
 
+    async fn compare_times(&self, params: Value) -> Result<Value> {
+        let value = |name: &str| -> Result<Option<TimeValue>> {
+            match &params[name] {
+                Value::Null => Ok(None),
+                value => serde_json::from_value::<TimeInput>(value.clone())
+                    .map(|input| Some(input.into()))
+                    .map_err(|_| {
+                        McpError::InvalidParams(format!(
+                            "{}: expected a number, a string or {{value, representation, format}}",
+                            name
+                        ))
+                    }),
+            }
+        };
+        let required = |name: &str| {
+            value(name)?.ok_or_else(|| McpError::InvalidParams(format!("{} required", name)))
+        };
+        let (a, b, c) = (required("a")?, required("b")?, value("c")?);
+        let bounds: RangeBounds = optional_enum(&params, "bounds")?;
+        let rules = IntervalRules {
+            policy: optional_enum(&params, "order_policy")?,
+            ..IntervalRules::default()
+        };
+        let comparison = compare::compare(&a, &b, c.as_ref(), bounds, rules)?;
+        Ok(serde_json::to_value(comparison)?)
+    }
+
     async fn call_tool(&self, params: Value) -> Result<Value> {
         let name = params["name"]
             .as_str()
             .ok_or_else(|| McpError::InvalidParams("tool name required".to_string()))?;
 
-        let arguments = params.get("arguments").unwrap_or(&Value::Null).clone();
+        let mut arguments = params.get("arguments").unwrap_or(&Value::Null).clone();
+        let hash = content_hash::take_flag(arguments.as_object_mut())?;
+        let pretty = take_pretty(arguments.as_object_mut())?;
 
         debug!("Calling tool: {}", name);
 
+        if self.disabled_tools.is_disabled(name) {
+            return Err(TimeServerError::ToolDisabled { tool: name.into() }.into());
+        }
+        crate::guard::global().check_tool(name)?;
+
+        // Argument names must be declared in the tool's input schema
+        let definition = self
+            .get_tool_definitions()
+            .into_iter()
+            .find(|t| t.name == name);
+        if let (Some(definition), Some(supplied)) = (definition, arguments.as_object_mut()) {
+            let schema = definition.input_schema.unwrap_or_default();
+            let known = schema
+                .as_object()
+                .map(params::schema_fields)
+                .unwrap_or_default();
+            params::check_arguments(name, supplied, &known, self.lenient_params)?;
+        }
+
         // Call the appropriate tool based on name
-        let result = match name {
+        let mut result = match name {
             "get_time" => self.get_time(Value::Null).await?,
             "get_unix_time" => self.get_unix_time(Value::Null).await?,
             "get_nanos" => self.get_nanos(Value::Null).await?,
@@ -280,6 +606,7 @@ impl TimeHandler {
             "get_time_with_timezone" => self.get_time_with_tz(arguments).await?,
             "list_timezones" => self.list_timezones(Value::Null).await?,
             "convert_time" => self.convert_time(arguments).await?,
+            "compare_times" => self.compare_times(arguments).await?,
             _ => {
                 return Ok(json!({
                     "content": [{
@@ -291,11 +618,15 @@ impl TimeHandler {
             }
         };
 
+        if let (true, Some(body)) = (hash, result.as_object_mut()) {
+            content_hash::add(name, body);
+        }
+
         // Convert result to MCP tool call format
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": serde_json::to_string_pretty(&result)?
+                "text": crate::output::to_json(&result, pretty)?
             }],
             "isError": false
         }))
@@ -367,7 +698,7 @@ impl TimeHandler {
                         "content": {
                             "type": "text",
                             "text": format!("Here is the current UTC time:\n\n{}",
-                                serde_json::to_string_pretty(&time_data)?)
+                                crate::output::to_json(&time_data, None)?)
                         }
                     }]
                 }))
@@ -387,7 +718,7 @@ impl TimeHandler {
                         "content": {
                             "type": "text",
                             "text": format!("Here is the current time in {}:\n\n{}",
-                                timezone, serde_json::to_string_pretty(&time_data)?)
+                                timezone, crate::output::to_json(&time_data, None)?)
                         }
                     }]
                 }))
@@ -405,7 +736,7 @@ impl TimeHandler {
                         "content": {
                             "type": "text",
                             "text": format!("Here is the current time formatted as '{}':\n\n{}",
-                                format, serde_json::to_string_pretty(&time_data)?)
+                                format, crate::output::to_json(&time_data, None)?)
                         }
                     }]
                 }))
@@ -419,7 +750,7 @@ impl TimeHandler {
                         "content": {
                             "type": "text",
                             "text": format!("Here is the current Unix timestamp:\n\n{}",
-                                serde_json::to_string_pretty(&time_data)?)
+                                crate::output::to_json(&time_data, None)?)
                         }
                     }]
                 }))
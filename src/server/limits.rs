@@ -0,0 +1,80 @@
+// Resource limits for the stdio server
+
+use std::time::Duration;
+
+/// Longest accepted request line (1 MiB)
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Deadline for a single request
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Requests handled at the same time
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Responses waiting for stdout before new requests stop being accepted
+pub const DEFAULT_WRITE_QUEUE: usize = 16;
+
+/// Per-request limits applied by the stdio loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StdioLimits {
+    /// Lines longer than this are rejected with -32600 and skipped
+    pub max_line_bytes: usize,
+    /// Requests still running after this are answered with -32000
+    pub request_timeout: Duration,
+    /// Requests handled at the same time; further lines wait to be read
+    pub concurrency: usize,
+    /// Responses, written or still being computed, that may wait for
+    /// stdout; when all are taken no new request is accepted
+    pub write_queue: usize,
+    /// Answer in request order rather than as each request finishes
+    pub ordered: bool,
+}
+
+impl StdioLimits {
+    /// Read `MCP_MAX_LINE_BYTES`, `MCP_REQUEST_TIMEOUT_SECS`,
+    /// `MCP_STDIO_CONCURRENCY`, `MCP_STDIO_WRITE_QUEUE` and `MCP_STDIO_ORDERED`
+    pub fn from_env() -> Self {
+        let count = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &usize| n > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            max_line_bytes: std::env::var("MCP_MAX_LINE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_LINE_BYTES),
+            request_timeout: request_timeout_from_env(),
+            concurrency: count("MCP_STDIO_CONCURRENCY", DEFAULT_CONCURRENCY),
+            write_queue: count("MCP_STDIO_WRITE_QUEUE", DEFAULT_WRITE_QUEUE),
+            ordered: std::env::var("MCP_STDIO_ORDERED")
+                .ok()
+                .and_then(|v| crate::output::parse_flag(&v))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl Default for StdioLimits {
+    fn default() -> Self {
+        Self {
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            concurrency: DEFAULT_CONCURRENCY,
+            write_queue: DEFAULT_WRITE_QUEUE,
+            ordered: false,
+        }
+    }
+}
+
+/// Request deadline from `MCP_REQUEST_TIMEOUT_SECS`, shared with the rmcp server
+pub fn request_timeout_from_env() -> Duration {
+    let secs = std::env::var("MCP_REQUEST_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&secs: &u64| secs > 0)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
@@ -1,95 +1,1189 @@
 // MCP server using STDIO transport
 
 pub mod handlers;
+pub mod limits;
 pub mod protocol;
 
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::audit::{AuditEvent, AuditLogger};
 use crate::error::Result;
-use crate::mcp::types::{McpRequest, McpResponse};
+use crate::mcp::types::{ClientInfo, McpRequest, McpResponse};
+use futures::stream::{FuturesOrdered, FuturesUnordered, StreamExt};
 use handlers::TimeHandler;
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tracing::{debug, error, info};
+use tokio::io::{self, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{self, OwnedPermit};
+use tracing::{debug, error, info, warn};
+
+pub use limits::StdioLimits;
+
+/// Produces a response for each parsed JSON-RPC request
+pub trait RequestHandler {
+    fn handle_request(&self, request: McpRequest) -> impl Future<Output = McpResponse>;
+
+    /// Client that sent `initialize`, for the audit trail
+    fn client_info(&self) -> Option<ClientInfo> {
+        None
+    }
+}
+
+impl RequestHandler for TimeHandler {
+    fn handle_request(&self, request: McpRequest) -> impl Future<Output = McpResponse> {
+        TimeHandler::handle_request(self, request)
+    }
+
+    fn client_info(&self) -> Option<ClientInfo> {
+        TimeHandler::client_info(self)
+    }
+}
 
-pub struct McpServer {
-    handler: TimeHandler,
+pub struct McpServer<H = TimeHandler> {
+    handler: H,
+    limits: StdioLimits,
+    audit: Option<AuditLogger>,
 }
 
-impl McpServer {
+impl McpServer<TimeHandler> {
     pub fn new() -> Self {
+        let server = Self::with_handler(TimeHandler::new(), StdioLimits::from_env());
+        match crate::audit::global() {
+            Some(logger) => server.with_audit_logger(logger.clone()),
+            None => server,
+        }
+    }
+}
+
+impl Default for McpServer<TimeHandler> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: RequestHandler> McpServer<H> {
+    pub fn with_handler(handler: H, limits: StdioLimits) -> Self {
         Self {
-            handler: TimeHandler::new(),
+            handler,
+            limits,
+            audit: None,
         }
     }
 
+    /// Record every `tools/call` to `logger`
+    pub fn with_audit_logger(mut self, logger: AuditLogger) -> Self {
+        self.audit = Some(logger);
+        self
+    }
+
     pub async fn run(&mut self) -> Result<()> {
+        self.serve(io::stdin(), io::stdout()).await
+    }
+
+    /// Serve newline-delimited JSON-RPC from `reader` until EOF
+    ///
+    /// Three loops run side by side: one reads lines, one runs up to
+    /// `concurrency` requests at a time, and one writes finished responses
+    /// whole, one per line. A request is only accepted once a slot in the
+    /// write queue is reserved for its response, so a client that stops
+    /// reading stdout stops the server reading stdin instead of growing a
+    /// buffer. Responses go out as requests finish unless `ordered` is set;
+    /// `initialize` and notifications wait for earlier requests and run alone.
+    pub async fn serve<R, W>(&mut self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
         info!("MCP UTC Time Server started");
 
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
-        let mut reader = BufReader::new(stdin);
-        let mut line = String::new();
+        let (line_sender, lines) = mpsc::channel(1);
+        let (response_sender, responses) = mpsc::channel(self.limits.write_queue.max(1));
+        tokio::join!(
+            read_lines(reader, self.limits.max_line_bytes, line_sender),
+            self.run_requests(lines, response_sender),
+            write_responses(writer, responses),
+        );
 
-        loop {
-            line.clear();
+        info!("MCP UTC Time Server stopped");
+        Ok(())
+    }
 
-            match reader.read_line(&mut line).await {
-                Ok(0) => {
-                    debug!("EOF received, shutting down");
-                    break;
+    /// Accept lines while there is room, and queue each response
+    async fn run_requests(
+        &self,
+        mut lines: mpsc::Receiver<Incoming>,
+        responses: mpsc::Sender<McpResponse>,
+    ) {
+        let mut in_flight = InFlight::new(self.limits.ordered);
+        let mut permit = None;
+        loop {
+            tokio::select! {
+                biased;
+                Some((slot, response)) = in_flight.next(), if !in_flight.is_empty() => {
+                    slot.send(response);
                 }
-                Ok(_) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
-                        continue;
+                reserved = responses.clone().reserve_owned(),
+                    if permit.is_none() && in_flight.len() < self.limits.concurrency.max(1) =>
+                {
+                    match reserved {
+                        Ok(reserved) => permit = Some(reserved),
+                        // The writer has stopped
+                        Err(_) => break,
                     }
-
-                    debug!("Received request: {}", trimmed);
-
-                    match serde_json::from_str::<McpRequest>(trimmed) {
-                        Ok(request) => {
-                            let response = self.handler.handle_request(request).await;
-
-                            match serde_json::to_string(&response) {
-                                Ok(response_str) => {
-                                    debug!("Sending response: {}", response_str);
-                                    if let Err(e) = stdout.write_all(response_str.as_bytes()).await
-                                    {
-                                        error!("Failed to write response: {}", e);
-                                        break;
-                                    }
-                                    if let Err(e) = stdout.write_all(b"\n").await {
-                                        error!("Failed to write newline: {}", e);
-                                        break;
-                                    }
-                                    if let Err(e) = stdout.flush().await {
-                                        error!("Failed to flush stdout: {}", e);
-                                        break;
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to serialize response: {}", e);
-                                }
-                            }
+                }
+                incoming = lines.recv(), if permit.is_some() => {
+                    let Some(incoming) = incoming else { break };
+                    let slot = permit.take().expect("guarded by the branch condition");
+                    let request = match self.parse(incoming) {
+                        Parsed::Request(request) => request,
+                        Parsed::Response(response) => {
+                            slot.send(response);
+                            continue;
                         }
-                        Err(e) => {
-                            error!("Failed to parse request: {}", e);
-                            let error_response =
-                                McpResponse::error(-32700, format!("Parse error: {}", e), None);
-                            if let Ok(response_str) = serde_json::to_string(&error_response) {
-                                let _ = stdout.write_all(response_str.as_bytes()).await;
-                                let _ = stdout.write_all(b"\n").await;
-                                let _ = stdout.flush().await;
-                            }
+                        Parsed::Nothing => {
+                            permit = Some(slot);
+                            continue;
                         }
+                    };
+                    if is_lifecycle(&request.method) {
+                        while let Some((earlier, response)) = in_flight.next().await {
+                            earlier.send(response);
+                        }
+                        slot.send(self.dispatch(request).await);
+                    } else {
+                        in_flight.push(Box::pin(async move { (slot, self.dispatch(request).await) }));
                     }
                 }
-                Err(e) => {
-                    error!("Error reading from stdin: {}", e);
-                    break;
+            }
+        }
+
+        while let Some((slot, response)) = in_flight.next().await {
+            slot.send(response);
+        }
+    }
+
+    /// The request on `incoming`, or the error response for a bad line
+    fn parse(&self, incoming: Incoming) -> Parsed {
+        let line = match incoming {
+            Incoming::TooLong => {
+                warn!(
+                    max_bytes = self.limits.max_line_bytes,
+                    "Request line too long, skipped"
+                );
+                return Parsed::Response(McpResponse::error(
+                    -32600,
+                    format!(
+                        "Invalid request: exceeds maximum size of {} bytes",
+                        self.limits.max_line_bytes
+                    ),
+                    None,
+                ));
+            }
+            Incoming::Line(line) => line,
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Parsed::Nothing;
+        }
+
+        debug!("Received request: {}", trimmed);
+
+        match serde_json::from_str::<McpRequest>(trimmed) {
+            Ok(request) => Parsed::Request(request),
+            Err(e) => {
+                error!("Failed to parse request: {}", e);
+                Parsed::Response(McpResponse::error(
+                    -32700,
+                    format!("Parse error: {}", e),
+                    None,
+                ))
+            }
+        }
+    }
+
+    /// Run one request under the configured deadline
+    async fn dispatch(&self, request: McpRequest) -> McpResponse {
+        let started = std::time::Instant::now();
+        let id = request.id.clone();
+        let method = request.method.clone();
+        let tool_call = (method == "tools/call").then(|| {
+            (
+                request.params["name"].as_str().unwrap_or("").to_string(),
+                request.params.get("arguments").cloned(),
+            )
+        });
+
+        let response = match tokio::time::timeout(
+            self.limits.request_timeout,
+            self.handler.handle_request(request),
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(_) => {
+                warn!(method = %method, timeout = ?self.limits.request_timeout, "Request timed out");
+                McpResponse::error(-32000, "request timed out".to_string(), id)
+            }
+        };
+
+        if let (Some(audit), Some((tool, arguments))) = (&self.audit, tool_call) {
+            let mut event = AuditEvent::new("stdio", tool, started).with_arguments(arguments);
+            if let Some(client) = self.handler.client_info() {
+                event = event.with_client(&client.name, &client.version);
+            }
+            audit.record(match &response.error {
+                Some(error) => event.with_error(error.message.clone()),
+                None => event,
+            });
+        }
+
+        response
+    }
+}
+
+/// `initialize` and notifications change the handshake, so they run with
+/// no other request in flight
+fn is_lifecycle(method: &str) -> bool {
+    method == "initialize" || method.starts_with("notifications/")
+}
+
+/// A line from the client
+enum Incoming {
+    Line(String),
+    /// A line over the size limit, already skipped
+    TooLong,
+}
+
+enum Parsed {
+    Request(McpRequest),
+    /// An error answered without running a handler
+    Response(McpResponse),
+    /// A blank line
+    Nothing,
+}
+
+/// A response with the write-queue slot reserved for it
+type Reply = (OwnedPermit<McpResponse>, McpResponse);
+
+type ReplyFuture<'a> = Pin<Box<dyn Future<Output = Reply> + 'a>>;
+
+/// Requests being handled, yielded as they finish or in arrival order
+enum InFlight<'a> {
+    Unordered(FuturesUnordered<ReplyFuture<'a>>),
+    Ordered(FuturesOrdered<ReplyFuture<'a>>),
+}
+
+impl<'a> InFlight<'a> {
+    fn new(ordered: bool) -> Self {
+        if ordered {
+            Self::Ordered(FuturesOrdered::new())
+        } else {
+            Self::Unordered(FuturesUnordered::new())
+        }
+    }
+
+    fn push(&mut self, future: ReplyFuture<'a>) {
+        match self {
+            Self::Unordered(futures) => futures.push(future),
+            Self::Ordered(futures) => futures.push_back(future),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Unordered(futures) => futures.len(),
+            Self::Ordered(futures) => futures.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn next(&mut self) -> Option<Reply> {
+        match self {
+            Self::Unordered(futures) => futures.next().await,
+            Self::Ordered(futures) => futures.next().await,
+        }
+    }
+}
+
+/// Send each line of `reader` until EOF, a read error, or the receiver
+/// going away
+async fn read_lines<R: AsyncRead + Unpin>(
+    reader: R,
+    max_line_bytes: usize,
+    lines: mpsc::Sender<Incoming>,
+) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        let read = tokio::select! {
+            read = read_bounded_line(&mut reader, &mut line, max_line_bytes) => read,
+            _ = lines.closed() => return,
+        };
+        let incoming = match read {
+            Ok(LineRead::Eof) => {
+                debug!("EOF received, shutting down");
+                return;
+            }
+            Ok(LineRead::TooLong) => Incoming::TooLong,
+            Ok(LineRead::Line) => Incoming::Line(String::from_utf8_lossy(&line).into_owned()),
+            Err(e) => {
+                error!("Error reading from stdin: {}", e);
+                return;
+            }
+        };
+        if lines.send(incoming).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Write each queued response as one line, until the queue closes or a
+/// write fails
+async fn write_responses<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut responses: mpsc::Receiver<McpResponse>,
+) {
+    while let Some(response) = responses.recv().await {
+        if let Err(e) = write_response(&mut writer, &response).await {
+            error!("Failed to write response: {}", e);
+            return;
+        }
+    }
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    response: &McpResponse,
+) -> std::io::Result<()> {
+    let response_str = serde_json::to_string(response)?;
+    debug!("Sending response: {}", response_str);
+    writer.write_all(response_str.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+enum LineRead {
+    Eof,
+    Line,
+    /// The line exceeded the limit; it has been consumed up to the next newline
+    TooLong,
+}
+
+/// Read one line into `line` without buffering more than `max_bytes` of it.
+/// Oversized lines are drained up to the newline so the next read starts on
+/// a fresh request.
+async fn read_bounded_line<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+    line: &mut Vec<u8>,
+    max_bytes: usize,
+) -> std::io::Result<LineRead> {
+    line.clear();
+    let mut too_long = false;
+    let mut read_any = false;
+
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(match (read_any, too_long) {
+                (false, _) => LineRead::Eof,
+                (true, true) => LineRead::TooLong,
+                (true, false) => LineRead::Line,
+            });
+        }
+        read_any = true;
+
+        let (chunk, found_newline) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..i], true),
+            None => (available, false),
+        };
+
+        if !too_long {
+            if line.len() + chunk.len() > max_bytes {
+                too_long = true;
+                line.clear();
+            } else {
+                line.extend_from_slice(chunk);
+            }
+        }
+
+        let consumed = chunk.len() + usize::from(found_newline);
+        reader.consume(consumed);
+
+        if found_newline {
+            return Ok(if too_long {
+                LineRead::TooLong
+            } else {
+                LineRead::Line
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::SUPPORTED_PROTOCOL_VERSIONS;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    /// Answers immediately, except for `slow` which sleeps for a second
+    struct MockHandler;
+
+    impl RequestHandler for MockHandler {
+        async fn handle_request(&self, request: McpRequest) -> McpResponse {
+            if request.method == "slow" {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            McpResponse::success(json!({"method": request.method}), request.id)
+        }
+    }
+
+    fn limits(max_line_bytes: usize, timeout_ms: u64) -> StdioLimits {
+        StdioLimits {
+            max_line_bytes,
+            request_timeout: Duration::from_millis(timeout_ms),
+            ..StdioLimits::default()
+        }
+    }
+
+    async fn serve_lines(server: &mut McpServer<MockHandler>, input: &[u8]) -> Vec<Value> {
+        let mut output = Vec::new();
+        server.serve(input, &mut output).await.unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    fn request(method: &str, id: i64) -> String {
+        format!(
+            "{}\n",
+            json!({"jsonrpc": "2.0", "method": method, "params": {}, "id": id})
+        )
+    }
+
+    fn initialize_request(protocol_version: &str, id: i64) -> McpRequest {
+        serde_json::from_value(json!({
+            "jsonrpc": "2.0", "method": "initialize", "id": id,
+            "params": {
+                "protocolVersion": protocol_version,
+                "capabilities": {},
+                "clientInfo": {"name": "test-client", "version": "1.0"}
+            }
+        }))
+        .unwrap()
+    }
+
+    fn initialized_notification() -> McpRequest {
+        serde_json::from_value(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .unwrap()
+    }
+
+    /// `initialize` and `notifications/initialized` as stdio lines
+    fn handshake_lines() -> String {
+        [
+            initialize_request("2025-06-18", 0),
+            initialized_notification(),
+        ]
+        .iter()
+        .map(|r| format!("{}\n", serde_json::to_string(r).unwrap()))
+        .collect()
+    }
+
+    async fn initialized_handler() -> TimeHandler {
+        let handler = TimeHandler::new();
+        let response = handler
+            .handle_request(initialize_request("2025-06-18", 0))
+            .await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+        handler.handle_request(initialized_notification()).await;
+        handler
+    }
+
+    #[tokio::test]
+    async fn test_oversized_line_is_rejected_and_loop_resyncs() {
+        let mut server = McpServer::with_handler(MockHandler, StdioLimits::default());
+
+        let mut input = vec![b'x'; 2 * 1024 * 1024];
+        input.push(b'\n');
+        input.extend_from_slice(request("ping", 2).as_bytes());
+
+        let responses = serve_lines(&mut server, &input).await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["error"]["code"], -32600);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["result"]["method"], "ping");
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_leaves_loop_healthy() {
+        let mut server = McpServer::with_handler(MockHandler, limits(1024, 50));
+
+        let input = format!("{}{}", request("slow", 1), request("ping", 2));
+        let responses = serve_lines(&mut server, input.as_bytes()).await;
+
+        // The ping does not wait for the slow request
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 2);
+        assert_eq!(responses[0]["result"]["method"], "ping");
+        assert_eq!(responses[1]["id"], 1);
+        assert_eq!(responses[1]["error"]["code"], -32000);
+        assert_eq!(responses[1]["error"]["message"], "request timed out");
+    }
+
+    /// Write `input` to a server over in-memory pipes and read `count`
+    /// response lines, in the order they arrive
+    async fn serve_duplex(limits: StdioLimits, input: String, count: usize) -> Vec<Value> {
+        let (client, server_end) = tokio::io::duplex(4096);
+        let (server_reader, server_writer) = tokio::io::split(server_end);
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut server = McpServer::with_handler(MockHandler, limits);
+
+        let client = async move {
+            client_writer.write_all(input.as_bytes()).await.unwrap();
+            let mut lines = BufReader::new(client_reader).lines();
+            let mut responses = Vec::new();
+            while responses.len() < count {
+                let line = lines.next_line().await.unwrap().unwrap();
+                responses.push(serde_json::from_str::<Value>(&line).unwrap());
+            }
+            // Closing stdin stops the server
+            client_writer.shutdown().await.unwrap();
+            responses
+        };
+        let (served, responses) = tokio::join!(server.serve(server_reader, server_writer), client);
+        served.unwrap();
+        responses
+    }
+
+    fn ids(responses: &[Value]) -> Vec<i64> {
+        responses
+            .iter()
+            .map(|r| r["id"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fast_request_overtakes_slow_one() {
+        let input = format!(
+            "{}{}{}",
+            request("slow", 1),
+            request("ping", 2),
+            request("ping", 3)
+        );
+        let started = std::time::Instant::now();
+        let responses = serve_duplex(StdioLimits::default(), input, 3).await;
+        assert_eq!(ids(&responses), [2, 3, 1]);
+        assert_eq!(responses[2]["result"]["method"], "slow");
+        // The slow request ran alongside the others rather than before them
+        assert!(started.elapsed() < Duration::from_millis(1900));
+    }
+
+    #[tokio::test]
+    async fn test_ordered_mode_keeps_request_order() {
+        let limits = StdioLimits {
+            ordered: true,
+            ..StdioLimits::default()
+        };
+        let input = format!(
+            "{}{}{}{}",
+            request("slow", 1),
+            request("ping", 2),
+            request("slow", 3),
+            request("ping", 4)
+        );
+        let started = std::time::Instant::now();
+        let responses = serve_duplex(limits, input, 4).await;
+        assert_eq!(ids(&responses), [1, 2, 3, 4]);
+        // Both slow requests still ran at the same time
+        assert!(started.elapsed() < Duration::from_millis(1900));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_queues_requests() {
+        let limits = StdioLimits {
+            concurrency: 1,
+            ..StdioLimits::default()
+        };
+        let input = format!("{}{}", request("slow", 1), request("ping", 2));
+        let responses = serve_duplex(limits, input, 2).await;
+        assert_eq!(ids(&responses), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_full_write_queue_stops_reading_requests() {
+        let limits = StdioLimits {
+            write_queue: 1,
+            ..StdioLimits::default()
+        };
+        // Small pipes in both directions, and a client that does not read
+        let (client, server_end) = tokio::io::duplex(256);
+        let (server_reader, server_writer) = tokio::io::split(server_end);
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut server = McpServer::with_handler(MockHandler, limits);
+        let input: String = (0..200).map(|id| request("ping", id)).collect();
+
+        let client = async move {
+            let writing = async {
+                client_writer.write_all(input.as_bytes()).await.unwrap();
+                client_writer.shutdown().await.unwrap();
+            };
+            tokio::pin!(writing);
+            let stalled = tokio::time::timeout(Duration::from_millis(300), &mut writing).await;
+            assert!(stalled.is_err(), "server kept reading with stdout full");
+
+            // Reading the responses lets the rest of the input through
+            let reading = async {
+                let mut lines = BufReader::new(client_reader).lines();
+                let mut count = 0;
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    let response: Value = serde_json::from_str(&line).unwrap();
+                    assert_eq!(response["result"]["method"], "ping");
+                    count += 1;
                 }
+                count
+            };
+            let (count, ()) = tokio::join!(reading, writing);
+            count
+        };
+        let (served, count) = tokio::join!(server.serve(server_reader, server_writer), client);
+        served.unwrap();
+        assert_eq!(count, 200);
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_are_audited() {
+        use crate::audit::AuditConfig;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let logger = AuditLogger::start(AuditConfig::new(&path)).unwrap();
+        let mut server = McpServer::with_handler(TimeHandler::new(), StdioLimits::default())
+            .with_audit_logger(logger.clone());
+
+        let call = |id: i64, name: &str, arguments: Value| {
+            format!(
+                "{}\n",
+                json!({"jsonrpc": "2.0", "method": "tools/call", "id": id,
+                       "params": {"name": name, "arguments": arguments}})
+            )
+        };
+        let input = [
+            handshake_lines(),
+            call(1, "get_time", json!({})),
+            request("tools/list", 2),
+            call(
+                3,
+                "get_time_with_timezone",
+                json!({"timezone": "Europe/London"}),
+            ),
+            call(4, "get_time_with_timezone", json!({"timezone": "Nowhere"})),
+        ]
+        .concat();
+
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+        logger.flush().await;
+
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            lines.len(),
+            3,
+            "one line per tool call, none for tools/list"
+        );
+        assert_eq!(lines[0]["tool"], "get_time");
+        assert_eq!(lines[0]["transport"], "stdio");
+        assert_eq!(lines[0]["client"], "test-client/1.0");
+        assert_eq!(lines[1]["arguments"]["timezone"], "Europe/London");
+        assert_eq!(lines[1]["outcome"], "ok");
+        assert_eq!(lines[2]["outcome"], "error");
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[tokio::test]
+    async fn test_completion_complete() {
+        let handler = TimeHandler::new();
+        let init = handler
+            .handle_request(initialize_request("2025-06-18", 1))
+            .await;
+        handler.handle_request(initialized_notification()).await;
+        let capabilities = &init.result.unwrap()["capabilities"];
+        assert!(capabilities["completions"].is_object());
+        assert!(capabilities["logging"].is_object());
+
+        let request = json!({
+            "jsonrpc": "2.0", "method": "completion/complete", "id": 2,
+            "params": {
+                "ref": {"type": "ref/prompt", "name": "time_in"},
+                "argument": {"name": "timezone", "value": "amer"}
             }
+        });
+        let response = handler
+            .handle_request(serde_json::from_value(request).unwrap())
+            .await;
+        let completion = &response.result.unwrap()["completion"];
+        assert_eq!(completion["values"].as_array().unwrap().len(), 20);
+        assert_eq!(completion["values"][0], "America/Adak");
+        assert_eq!(completion["hasMore"], true);
+    }
+
+    #[tokio::test]
+    async fn test_logging_set_level() {
+        let handler = initialized_handler().await;
+        let call = |level: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "logging/setLevel", "id": 1,
+                "params": {"level": level}
+            }))
+            .unwrap()
+        };
+
+        let response = handler.handle_request(call(json!("warning"))).await;
+        assert_eq!(response.result, Some(json!({})));
+        assert_eq!(
+            handler.log_level(),
+            Some(rmcp::model::LoggingLevel::Warning)
+        );
+
+        let response = handler.handle_request(call(json!("loud"))).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+        assert_eq!(
+            handler.log_level(),
+            Some(rmcp::model::LoggingLevel::Warning)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_arguments_are_rejected_unless_lenient() {
+        let call = |arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": "get_time_with_timezone", "arguments": arguments}
+            }))
+            .unwrap()
+        };
+
+        let handler = initialized_handler().await;
+        let response = handler
+            .handle_request(call(json!({"timezone": "UTC", "time_zone": "Asia/Tokyo"})))
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(
+            error.message.contains("did you mean 'timezone'"),
+            "{}",
+            error.message
+        );
+        let data = error.data.unwrap();
+        assert_eq!(data["code"], "unknown_field");
+        assert_eq!(data["details"]["field"], "time_zone");
+
+        let response = handler
+            .handle_request(call(json!({"timezone": "Asia/Tokyo"})))
+            .await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+
+        let lenient = TimeHandler::new().with_lenient_params(true);
+        lenient
+            .handle_request(initialize_request("2025-06-18", 0))
+            .await;
+        lenient.handle_request(initialized_notification()).await;
+        let response = lenient
+            .handle_request(call(json!({"timezone": "UTC", "time_zone": "Asia/Tokyo"})))
+            .await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_tools_are_hidden_and_refused() {
+        let handler = TimeHandler::new()
+            .with_disabled_tools(crate::mcp::policy::ToolPolicy::disabling(["get_nanos"]));
+        handler
+            .handle_request(initialize_request("2025-06-18", 0))
+            .await;
+        handler.handle_request(initialized_notification()).await;
+        let request = |method: &str, params: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": method, "id": 1, "params": params
+            }))
+            .unwrap()
+        };
+
+        let listed = handler
+            .handle_request(request("tools/list", json!({})))
+            .await
+            .result
+            .unwrap();
+        let names: Vec<&str> = listed["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert!(!names.contains(&"get_nanos"));
+        assert!(names.contains(&"get_time"));
+
+        let response = handler
+            .handle_request(request("tools/call", json!({"name": "get_nanos"})))
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, crate::error::JSONRPC_TOOL_DISABLED);
+        assert_eq!(error.data.unwrap()["details"]["tool"], "get_nanos");
+
+        let response = handler
+            .handle_request(request("tools/call", json!({"name": "get_time"})))
+            .await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+    }
+
+    #[tokio::test]
+    async fn test_tools_list_carries_annotations() {
+        use crate::mcp::annotations::hints;
+        use rmcp::model::ToolAnnotations;
+
+        let handler = initialized_handler().await;
+        let request = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "method": "tools/list", "id": 1
+        }))
+        .unwrap();
+        let listed = handler.handle_request(request).await.result.unwrap();
+        let tools = listed["tools"].as_array().unwrap();
+        assert!(!tools.is_empty());
+        for tool in tools {
+            let name = tool["name"].as_str().unwrap();
+            let annotations: ToolAnnotations =
+                serde_json::from_value(tool["annotations"].clone()).unwrap();
+            assert_eq!(annotations, hints(name).unwrap().annotations(), "{}", name);
+            assert_eq!(annotations.read_only_hint, Some(true), "{}", name);
+            assert_eq!(annotations.idempotent_hint, Some(true), "{}", name);
+            assert_eq!(annotations.open_world_hint, Some(false), "{}", name);
         }
+    }
 
-        info!("MCP UTC Time Server stopped");
-        Ok(())
+    #[tokio::test]
+    async fn test_content_hash_on_request() {
+        let handler = initialized_handler().await;
+        let call = |arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": "get_time_with_timezone", "arguments": arguments}
+            }))
+            .unwrap()
+        };
+        let output = |response: McpResponse| -> Value {
+            let result = response.result.unwrap();
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap()
+        };
+
+        let hashed = output(
+            handler
+                .handle_request(call(json!({"timezone": "Asia/Tokyo", "hash": true})))
+                .await,
+        );
+        assert_eq!(
+            crate::canonical::content_hash(
+                &hashed,
+                crate::mcp::content_hash::volatile_fields("get_time_with_timezone")
+            ),
+            hashed["content_hash"].as_str().unwrap()
+        );
+        let plain = output(
+            handler
+                .handle_request(call(json!({"timezone": "Asia/Tokyo"})))
+                .await,
+        );
+        assert!(plain.get("content_hash").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pretty_override() {
+        let handler = initialized_handler().await;
+        let call = |name: &str, arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": name, "arguments": arguments}
+            }))
+            .unwrap()
+        };
+        let text = |response: McpResponse| -> String {
+            let result = response.result.unwrap();
+            result["content"][0]["text"].as_str().unwrap().to_string()
+        };
+
+        let arguments = json!({"to_timezone": "Asia/Tokyo", "timestamp": 1710497730});
+        let mut compact = arguments.clone();
+        compact["pretty"] = json!(false);
+        let compact = text(handler.handle_request(call("convert_time", compact)).await);
+        assert!(!compact.contains('\n'), "{}", compact);
+        let mut pretty = arguments;
+        pretty["pretty"] = json!(true);
+        let pretty = text(handler.handle_request(call("convert_time", pretty)).await);
+        assert!(pretty.contains('\n'), "{}", pretty);
+        assert_eq!(
+            serde_json::from_str::<Value>(&compact).unwrap(),
+            serde_json::from_str::<Value>(&pretty).unwrap()
+        );
+
+        // Tools without other arguments take it too
+        let unix = text(
+            handler
+                .handle_request(call("get_unix_time", json!({"pretty": false})))
+                .await,
+        );
+        assert!(!unix.contains('\n'), "{}", unix);
+
+        let response = handler
+            .handle_request(call("get_time", json!({"pretty": "no"})))
+            .await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let response = handler
+            .handle_request(
+                serde_json::from_value(json!({"jsonrpc": "2.0", "method": "tools/list", "id": 2}))
+                    .unwrap(),
+            )
+            .await;
+        for tool in response.result.unwrap()["tools"].as_array().unwrap() {
+            assert_eq!(
+                tool["inputSchema"]["properties"]["pretty"]["type"], "boolean",
+                "{}",
+                tool["name"]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_time_honors_from_timezone() {
+        let handler = initialized_handler().await;
+        let convert = |arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": "convert_time", "arguments": arguments}
+            }))
+            .unwrap()
+        };
+        let output = |response: McpResponse| -> Value {
+            let result = response.result.unwrap();
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap()
+        };
+
+        let response = handler
+            .handle_request(convert(json!({
+                "timestamp": 1704067200,
+                "from_timezone": "America/Chicago",
+                "to_timezone": "Europe/Paris"
+            })))
+            .await;
+        let result = output(response);
+        assert_eq!(result["original"]["formatted"], "2023-12-31T18:00:00-06:00");
+        assert_eq!(
+            result["converted"]["formatted"],
+            "2024-01-01T01:00:00+01:00"
+        );
+
+        let response = handler
+            .handle_request(convert(json!({
+                "local_datetime": "2024-03-10T02:30:00",
+                "from_timezone": "America/Chicago",
+                "to_timezone": "UTC",
+                "nonexistent": "shift_forward"
+            })))
+            .await;
+        let result = output(response);
+        assert_eq!(result["original"]["status"], "nonexistent");
+        assert_eq!(
+            result["converted"]["formatted"],
+            "2024-03-10T08:30:00+00:00"
+        );
+
+        let response = handler
+            .handle_request(convert(json!({
+                "timestamp": 0,
+                "from_timezone": "Chicago",
+                "to_timezone": "UTC"
+            })))
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.data.unwrap()["code"], "invalid_timezone");
+    }
+
+    #[tokio::test]
+    async fn test_compare_times() {
+        let handler = initialized_handler().await;
+        let compare = |arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": "compare_times", "arguments": arguments}
+            }))
+            .unwrap()
+        };
+
+        let response = handler
+            .handle_request(compare(json!({
+                "a": 1710496800,
+                "b": "Fri, 15 Mar 2024 11:00:00 +0100",
+                "c": {"value": "2024-03-15 11:00", "format": "%Y-%m-%d %H:%M"},
+                "bounds": "half_open"
+            })))
+            .await;
+        let result = response.result.unwrap();
+        let output: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(output["earlier"], "equal");
+        assert_eq!(output["difference_nanos"], 0);
+        assert_eq!(output["range"]["position"], "at_start");
+        assert_eq!(output["range"]["within"], true);
+
+        let response = handler
+            .handle_request(compare(json!({"a": 0, "b": "yesterday-ish"})))
+            .await;
+        let data = response.error.unwrap().data.unwrap();
+        assert_eq!(data["code"], "invalid_value");
+        assert_eq!(data["details"]["field"], "b");
+    }
+
+    #[tokio::test]
+    async fn test_time_formatted_in_a_timezone() {
+        let handler = initialized_handler().await;
+        let format = |arguments: Value| {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "tools/call", "id": 1,
+                "params": {"name": "get_time_formatted", "arguments": arguments}
+            }))
+            .unwrap()
+        };
+
+        let response = handler
+            .handle_request(format(
+                json!({"format": "%Z %z", "timezone": "Asia/Kolkata"}),
+            ))
+            .await;
+        let result = response.result.unwrap();
+        let output: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(output["formatted"], "IST +0530");
+        assert_eq!(output["timezone"], "Asia/Kolkata");
+
+        let response = handler
+            .handle_request(format(json!({"format": "%Z", "timezone": "Kolkata"})))
+            .await;
+        assert_eq!(
+            response.error.unwrap().data.unwrap()["code"],
+            "invalid_timezone"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_line_at_limit_and_unterminated_last_line() {
+        let line = request("ping", 1);
+        let limit = line.trim_end().len();
+        let mut server = McpServer::with_handler(MockHandler, limits(limit, 1000));
+
+        let input = format!("{}{}", line, request("ping", 2).trim_end());
+        let responses = serve_lines(&mut server, input.as_bytes()).await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"]["method"], "ping");
+        assert_eq!(responses[1]["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_negotiates_protocol_version() {
+        for (requested, agreed) in [
+            ("2025-06-18", "2025-06-18"),
+            ("2024-11-05", "2024-11-05"),
+            // Unknown versions get the newest one that is not newer
+            ("2025-04-01", "2025-03-26"),
+            ("2030-01-01", "2025-06-18"),
+        ] {
+            let handler = TimeHandler::new();
+            let response = handler
+                .handle_request(initialize_request(requested, 1))
+                .await;
+            assert_eq!(response.result.unwrap()["protocolVersion"], agreed);
+            assert_eq!(handler.protocol_version(), Some(agreed));
+            assert_eq!(handler.client_info().unwrap().name, "test-client");
+        }
+
+        // Older than anything supported
+        let handler = TimeHandler::new();
+        let error = handler
+            .handle_request(initialize_request("2024-01-01", 1))
+            .await
+            .error
+            .unwrap();
+        assert_eq!(error.code, -32602);
+        let data = error.data.unwrap();
+        assert_eq!(data["requested"], "2024-01-01");
+        assert_eq!(data["supported"][0], SUPPORTED_PROTOCOL_VERSIONS[0]);
+        assert!(handler.client_info().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_initialize_requires_params() {
+        let handler = TimeHandler::new();
+        for params in [
+            json!(null),
+            json!({}),
+            json!({"protocolVersion": "2025-06-18"}),
+        ] {
+            let request = serde_json::from_value(json!({
+                "jsonrpc": "2.0", "method": "initialize", "id": 1, "params": params
+            }))
+            .unwrap();
+            let error = handler.handle_request(request).await.error.unwrap();
+            assert_eq!(error.code, -32602);
+        }
+        // A failed initialize can be retried
+        let response = handler
+            .handle_request(initialize_request("2025-06-18", 2))
+            .await;
+        assert!(response.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requests_before_initialized_are_rejected() {
+        let handler = TimeHandler::new();
+        let list = || {
+            serde_json::from_value::<McpRequest>(
+                json!({"jsonrpc": "2.0", "method": "tools/list", "id": 5}),
+            )
+            .unwrap()
+        };
+
+        let error = handler.handle_request(list()).await.error.unwrap();
+        assert_eq!(error.code, -32002);
+        assert_eq!(error.message, "Server not initialized");
+
+        // Initialized only once the client confirms
+        handler
+            .handle_request(initialize_request("2025-06-18", 1))
+            .await;
+        assert_eq!(
+            handler.handle_request(list()).await.error.unwrap().code,
+            -32002
+        );
+
+        handler.handle_request(initialized_notification()).await;
+        let response = handler.handle_request(list()).await;
+        assert!(response.result.unwrap()["tools"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_second_initialize_is_rejected() {
+        let handler = initialized_handler().await;
+        let error = handler
+            .handle_request(initialize_request("2024-11-05", 2))
+            .await
+            .error
+            .unwrap();
+        assert_eq!(error.code, -32600);
+        assert_eq!(handler.protocol_version(), Some("2025-06-18"));
     }
 }
@@ -24,6 +24,12 @@ impl UtcTimeRequest {
     }
 }
 
+impl Default for UtcTimeRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UtcTimeResponse {
     pub fn new(utc_time: String) -> Self {
         UtcTimeResponse {
@@ -31,4 +37,4 @@ impl UtcTimeResponse {
             utc_time,
         }
     }
-}
\ No newline at end of file
+}
@@ -4,297 +4,3200 @@
 // time and timezone services following MCP 2025-06-18 specification.
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "prompts")]
+use rmcp::{handler::server::router::prompt::PromptRouter, prompt, prompt_handler, prompt_router};
 use rmcp::{
-    handler::server::{
-        router::{prompt::PromptRouter, tool::ToolRouter},
-        wrapper::Parameters,
-    },
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext, wrapper::Parameters},
     model::*,
-    prompt, prompt_handler, prompt_router,
-    service::RequestContext,
-    tool, tool_handler, tool_router,
+    service::{NotificationContext, RequestContext},
+    tool, tool_router,
     transport::stdio,
-    ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
+    ErrorData as McpError, Peer, RoleServer, ServerHandler, ServiceExt,
 };
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tracing::{debug, info};
 
+use crate::attestation;
+use crate::audit::{AuditEvent, AuditLogger};
+use crate::chaos;
+#[cfg(feature = "ntp")]
+use crate::config::HealthConfig;
+use crate::config::ServerConfig;
+use crate::error::TimeServerError;
+use crate::events::NotificationFilter;
+use crate::export::{self, OutputFormat};
+use crate::guard::ClockGuard;
+#[cfg(feature = "ntp")]
+use crate::health::{HealthCheck, HealthReport};
+use crate::heartbeat::Heartbeat;
+use crate::maintenance::MaintenanceWindow;
+use crate::mcp::annotations;
+use crate::mcp::content_hash;
+use crate::mcp::fast;
+use crate::mcp::policy::ToolPolicy;
+use crate::mcp::rate_limit::RateLimiter;
+use crate::mcp::timeouts::{self, ToolTimeouts};
+#[cfg(feature = "ntp")]
+use crate::ntp::correction;
+use crate::preferences::{Precision, Preferences, Session, WithDefaults};
+use crate::sanity::{SanityCheck, SanityConfig};
+use crate::scheduler::{self, FiredNotification, NotificationSink, Scheduler};
+use crate::state::ServerState;
+use crate::stats::ServerStats;
+use crate::time::abbreviations;
+use crate::time::calendar::{self, WeekConvention};
+use crate::time::calendars::{self, Calendar};
+use crate::time::card::{self, TimestampCard};
+use crate::time::compare::{self, RangeBounds, TimeInput};
+use crate::time::correction::{Corrected, Correction};
+use crate::time::duration::{
+    self, DurationStyle, DurationUnit, Interval, IntervalRules, OrderPolicy,
+};
+use crate::time::epochs::{self, Epoch};
+use crate::time::explain::{self, Explain, Explained};
+use crate::time::formats::{format_email_date, parse_email_date, FormatBuild, FormatDialect};
+use crate::time::holidays::{self, HolidayCalendar, RuleCalendar};
+use crate::time::host::{self, HostSources};
+use crate::time::natural;
+use crate::time::reference::{self, ReferenceSource, ReferenceTime};
+use crate::time::resolution;
+use crate::time::timezone::{Conversion, ConvertedTime, CONVERSION_NOTE};
+use crate::time::tzdata::{tzdata_caveat, WithCaveat};
+use crate::time::unix;
 use crate::time::utc::EnhancedTimeResponse;
-use crate::time::{TimezoneConverter, UnixTime};
+use crate::time::{
+    parse_rfc9557, world_clock, AmbiguityPolicy, ConflictPolicy, GapPolicy, NumberSystem,
+    StrftimeFormatter, TimeSource, TimezoneConverter, TimezoneListQuery, UnixTime, WorldClock,
+};
+use crate::tsa;
+use crate::validity::{Validity, ValidityConfig};
+use crate::warnings::{Warning, WarningCode, Warnings, WithWarnings};
 
 // Parameter types for tools and prompts
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CurrentTimeParams {
+    /// Apply the measured NTP offset (defaults to CORRECTED_TIME_DEFAULT)
+    #[serde(default)]
+    corrected: Option<bool>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GetTimeParams {
+    /// IANA timezone to report the time in (defaults to the session preference, else UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Sub-second digits in iso8601/rfc3339/rfc9557: seconds, millis, micros or nanos
+    #[serde(default)]
+    precision: Option<Precision>,
+    /// Apply the measured NTP offset (defaults to CORRECTED_TIME_DEFAULT)
+    #[serde(default)]
+    corrected: Option<bool>,
+    /// Append [u-ca=iso8601] to the rfc9557 field
+    #[serde(default)]
+    calendar_annotation: Option<bool>,
+    /// Instant to compute from instead of now, as Unix seconds or RFC 3339;
+    /// echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 struct FormatParams {
+    /// strftime format (defaults to the session preference)
+    #[serde(default)]
+    format: Option<String>,
+    /// IANA timezone to format in (defaults to the session preference, else
+    /// UTC). %z is its offset and %Z its abbreviation ("JST"), or the offset
+    /// ("+04") for zones without one
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Digit set for directive output: latn, arab, arabext or deva (defaults
+    /// to the session locale's -u-nu- extension, else latn)
+    #[serde(default)]
+    number_system: Option<NumberSystem>,
+    /// Syntax of format: chrono (default) or gnu, as for GNU date (%N,
+    /// %3N; a leading '+' is ignored)
+    #[serde(default)]
+    dialect: Option<FormatDialect>,
+    /// Instant to compute from instead of now, as Unix seconds or RFC 3339;
+    /// echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ExplainFormatParams {
+    /// strftime format to explain (e.g., '%Y-%m-%d %H:%M')
     format: String,
+    /// IANA timezone for the current-time rendering (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Syntax of format: chrono (default) or gnu, as for GNU date
+    #[serde(default)]
+    dialect: Option<FormatDialect>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 struct TimezoneParams {
     timezone: String,
+    /// Instant to compute from instead of now, as Unix seconds or RFC 3339;
+    /// echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
 }
 
+#[cfg(feature = "prompts")]
 #[derive(Debug, Deserialize, JsonSchema)]
-struct ConvertTimeParams {
-    timestamp: i64,
-    to_timezone: String,
+struct TimezonePromptParams {
+    /// IANA timezone (defaults to the session preference)
     #[serde(default)]
-    from_timezone: Option<String>,
+    timezone: Option<String>,
 }
 
-/// Time server implementing MCP protocol
-#[derive(Clone)]
-pub struct TimeServer {
-    tool_router: ToolRouter<Self>,
-    prompt_router: PromptRouter<Self>,
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct BuildFormatParams {
+    /// Description made of token names, e.g. 'year-month-day hour:minute
+    /// with timezone, 24h'; the text between tokens is kept
+    #[serde(default)]
+    description: Option<String>,
+    /// Token names in order, e.g. ["year", "month_name_short", "day"];
+    /// items without letters or digits are literal separators
+    #[serde(default)]
+    tokens: Option<Vec<String>>,
+    /// Existing strftime format to split back into tokens
+    #[serde(default)]
+    format: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
 }
 
-impl TimeServer {
-    pub fn new() -> Self {
+#[cfg(feature = "prompts")]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct BuildFormatPromptParams {
+    /// Format described with token names, e.g. 'day/month/year hour:minute'
+    description: String,
+}
+
+#[cfg(feature = "prompts")]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FormatPromptParams {
+    /// strftime format (defaults to the session preference)
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SetPreferencesParams {
+    #[serde(flatten)]
+    preferences: Preferences,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ListTimezonesParams {
+    /// Number of matching names to skip
+    #[serde(default)]
+    offset: Option<usize>,
+    /// Page size (default 100 when filtering or paging, max 600)
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Case-insensitive name prefix (e.g., 'America/Argentina')
+    #[serde(default)]
+    prefix: Option<String>,
+    /// Case-insensitive region (e.g., 'Europe')
+    #[serde(default)]
+    region: Option<String>,
+    /// Return a map of region to city names instead of a flat list
+    #[serde(default)]
+    grouped: bool,
+    /// Return 'zones' with each name's UTC offset, DST flag and local hour,
+    /// all computed at 'captured_at' (recomputed at most once a minute)
+    #[serde(default)]
+    detail: bool,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+impl From<ListTimezonesParams> for TimezoneListQuery {
+    fn from(params: ListTimezonesParams) -> Self {
         Self {
-            tool_router: Self::tool_router(),
-            prompt_router: Self::prompt_router(),
+            offset: params.offset,
+            limit: params.limit,
+            prefix: params.prefix,
+            region: params.region,
+            grouped: params.grouped,
+            detail: params.detail,
         }
     }
+}
 
-    /// Check if NTP tools are available (not in container)
-    fn is_ntp_available() -> bool {
-        use crate::ntp::NtpSyncedClock;
-        !NtpSyncedClock::is_container_environment()
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TimezonesIfChangedParams {
+    /// The checksum of the list the client already has, from list_timezones
+    /// or an earlier call
+    #[serde(default)]
+    checksum: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
 }
 
-impl Default for TimeServer {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConvertTimeParams {
+    /// Unix seconds; absolute, so from_timezone only changes how it is shown
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// Wall-clock time in from_timezone instead of a timestamp (e.g., '2024-11-03T01:30:00')
+    #[serde(default)]
+    local_datetime: Option<String>,
+    to_timezone: String,
+    /// IANA timezone of the original (defaults to UTC)
+    #[serde(default)]
+    from_timezone: Option<String>,
+    /// Policy for a local_datetime that occurs twice (defaults to 'earlier')
+    #[serde(default)]
+    ambiguity: Option<AmbiguityPolicy>,
+    /// Policy for a local_datetime skipped by a forward transition (defaults to 'reject')
+    #[serde(default)]
+    nonexistent: Option<GapPolicy>,
+    /// Append [u-ca=iso8601] to the rfc9557 field
+    #[serde(default)]
+    calendar_annotation: Option<bool>,
+    /// Add an explanation of each step of the conversion (defaults to false)
+    #[serde(default)]
+    explain: Option<bool>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
 }
 
-// Tool implementations using macros
-#[tool_router]
-impl TimeServer {
-    /// Get current UTC time with full Unix/POSIX details
-    #[tool(description = "Get current UTC time with full Unix/POSIX details")]
-    async fn get_time(&self) -> Result<CallToolResult, McpError> {
-        debug!("Tool: get_time");
-        let response = EnhancedTimeResponse::now();
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&response)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
-    }
+/// A JSON number, or a string for values past f64 precision
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(serde_json::Number),
+    Text(String),
+}
 
-    /// Get Unix epoch time with nanosecond precision
-    #[tool(description = "Get Unix epoch time with nanosecond precision")]
-    async fn get_unix_time(&self) -> Result<CallToolResult, McpError> {
-        debug!("Tool: get_unix_time");
-        let unix_time = UnixTime::now();
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&unix_time)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+impl NumberOrText {
+    fn to_text(&self) -> String {
+        match self {
+            Self::Number(number) => number.to_string(),
+            Self::Text(text) => text.clone(),
+        }
     }
+}
 
-    /// Get nanoseconds since Unix epoch
-    #[tool(description = "Get nanoseconds since Unix epoch")]
-    async fn get_nanos(&self) -> Result<CallToolResult, McpError> {
-        debug!("Tool: get_nanos");
-        let unix_time = UnixTime::now();
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ConvertEpochParams {
+    /// Count in the source epoch's unit; pass 18-digit tick counts as a string to keep them exact
+    value: NumberOrText,
+    /// Source epoch: unix, filetime, dotnet_ticks, ntp, excel, cocoa or gps
+    epoch: Epoch,
+    /// NTP era for a 32-bit NTP seconds value (era 1 starts 2036-02-07); omit for era-extended seconds
+    #[serde(default)]
+    ntp_era: Option<i64>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct AbbreviationLookupParams {
+    /// Timezone abbreviation, case-insensitive (e.g., 'CEST', 'IST')
+    abbreviation: String,
+    /// Unix timestamp whose UTC date to look up (defaults to now), for historical abbreviations
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ConvertCalendarParams {
+    /// Calendar: 'hebrew', 'islamic' (tabular), 'persian' or 'japanese'
+    calendar: Calendar,
+    /// Unix timestamp to convert (defaults to now); ignored when 'year' is given
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// IANA timezone whose local date is used (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Year in the calendar, to convert a calendar date to Unix time instead (needs 'month' and 'day')
+    #[serde(default)]
+    year: Option<i64>,
+    /// Month for reverse conversion (Hebrew months count from Nisan = 1; Tishri is 7, Adar II 13)
+    #[serde(default)]
+    month: Option<u32>,
+    /// Day of the month for reverse conversion
+    #[serde(default)]
+    day: Option<u32>,
+    /// Japanese era for reverse conversion ('Reiwa', 'Heisei', '令和', ...)
+    #[serde(default)]
+    era: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct BusinessTimeParams {
+    /// Date to check (YYYY-MM-DD); defaults to today in 'timezone'
+    #[serde(default)]
+    date: Option<String>,
+    /// IANA timezone for 'today' (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Holiday calendar: 'US', 'UK', 'DE' or a region from HOLIDAY_CALENDAR_FILE; without it only weekends are days off
+    #[serde(default)]
+    region: Option<String>,
+    /// Instant whose date is 'today' instead of now, as Unix seconds or RFC
+    /// 3339; echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WeekNumberParams {
+    /// Date (YYYY-MM-DD); defaults to today in 'timezone'
+    #[serde(default)]
+    date: Option<String>,
+    /// Unix timestamp instead of a date, taken as a date in 'timezone'
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// IANA timezone for 'today' and 'timestamp' (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Convention whose week range is returned: 'iso' (default), 'sunday', 'monday', 'saturday' or 'simple'
+    #[serde(default)]
+    convention: Option<WeekConvention>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ParseTimeParams {
+    /// RFC 3339 or RFC 9557 timestamp (e.g., '2024-03-15T10:00:00+09:00[Asia/Tokyo]')
+    input: String,
+    /// When the offset and zone annotation disagree: 'offset' (default), 'zone' or 'reject'
+    #[serde(default)]
+    conflict: Option<ConflictPolicy>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+/// What email_date does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum EmailDateMode {
+    /// Read a Date header value
+    Parse,
+    /// Produce a compliant Date header value
+    Generate,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct EmailDateParams {
+    /// 'parse' a Date header or 'generate' one
+    mode: EmailDateMode,
+    /// Date header value to parse, e.g. 'Tue, 1 Jul 2003 10:52:37 +0200' (parse)
+    #[serde(default)]
+    input: Option<String>,
+    /// Unix seconds to render (generate; defaults to now)
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// IANA timezone whose offset the header carries (generate; defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WorldClockParams {
+    /// Comma-separated IANA timezones (defaults to WORLD_CLOCK_DEFAULT_ZONES)
+    #[serde(default)]
+    timezones: Option<String>,
+    /// json (default) or csv, one row per zone
+    #[serde(default)]
+    output_format: Option<OutputFormat>,
+    /// Instant to compute from instead of now, as Unix seconds or RFC 3339;
+    /// echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TimestampCardParams {
+    /// Unix seconds or RFC 3339, e.g. 1710497730 or '2024-03-15T10:15:30Z'
+    timestamp: NumberOrText,
+    /// Comma-separated IANA timezones to show the local time in (default none)
+    #[serde(default)]
+    timezones: Option<String>,
+    /// Instant "in 3 days" and "2 hours ago" are measured from instead of
+    /// now, as Unix seconds or RFC 3339; echoed as reference_time
+    #[serde(default)]
+    as_of: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FormatDurationParams {
+    /// Length of the duration in 'unit'; negative for a span backwards
+    value: f64,
+    /// seconds (default), ms or ns
+    #[serde(default)]
+    unit: Option<DurationUnit>,
+    /// iso8601, clock, human_long (default), human_short or largest_unit
+    #[serde(default)]
+    style: Option<DurationStyle>,
+    /// Keep at most this many units, e.g. 2 turns '1 day, 5 minutes, 7 seconds' into '1 day, 5 minutes'
+    #[serde(default)]
+    max_units: Option<usize>,
+    /// Also write zero units below the largest one, e.g. '1 day, 0 hours, 5 minutes'
+    #[serde(default)]
+    include_zero: Option<bool>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CompareTimesParams {
+    /// The time in question: a number, a string, or {value, representation, format}
+    a: TimeInput,
+    /// The time to compare 'a' with; with 'c', one end of the range
+    b: TimeInput,
+    /// Other end of the range; the result then says whether 'a' lies between 'b' and 'c'
+    #[serde(default)]
+    c: Option<TimeInput>,
+    /// Which range ends count as inside: inclusive (default), exclusive or half_open ([b, c))
+    #[serde(default)]
+    bounds: Option<RangeBounds>,
+    /// When 'b' is before 'a': signed (default) gives a negative difference, absolute swaps them and sets inputs_swapped, strict fails
+    #[serde(default)]
+    order_policy: Option<OrderPolicy>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct HostTimezoneParams {
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LocationParams {
+    /// Degrees north, -90 to 90
+    latitude: f64,
+    /// Degrees east, -180 to 180
+    longitude: f64,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[cfg(feature = "prompts")]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CardPromptParams {
+    /// Unix seconds or RFC 3339
+    timestamp: String,
+    /// Comma-separated IANA timezones to show the local time in
+    #[serde(default)]
+    timezones: Option<String>,
+}
+
+#[cfg(feature = "prompts")]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WorldClockPromptParams {
+    /// Comma-separated IANA timezones (defaults to WORLD_CLOCK_DEFAULT_ZONES)
+    #[serde(default)]
+    timezones: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TimeSanityParams {
+    /// Checks to leave out: tai, filesystem, timezone, http
+    #[serde(default)]
+    skip: Option<Vec<SanityCheck>>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GetExamplesParams {
+    /// Only this tool's examples (defaults to every tool)
+    #[serde(default)]
+    tool: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ClockResolutionParams {
+    /// Back-to-back clock reads to measure (default 10000, max 100000)
+    #[serde(default)]
+    samples: Option<usize>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SelftestBenchmarkParams {
+    /// Tool to call (e.g., 'get_unix_time')
+    tool: String,
+    /// Arguments passed to the tool on every call
+    #[serde(default)]
+    arguments: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Calls to time (default 1000, max 100000)
+    #[serde(default)]
+    iterations: Option<usize>,
+    /// Allow tools that run ntpq or reach outside the process (default false)
+    #[serde(default)]
+    allow_external: Option<bool>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct MaintenanceWindowParams {
+    /// Occurrences to list, starting with any window open now (default 5, max 100)
+    #[serde(default)]
+    count: Option<usize>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DstTransitionsParams {
+    timezone: String,
+    /// Calendar year to scan (defaults to the current year)
+    #[serde(default)]
+    year: Option<i32>,
+    /// json (default), csv, or ics with one event per transition
+    #[serde(default)]
+    output_format: Option<OutputFormat>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct OffsetTimelineParams {
+    timezone: String,
+    /// Unix timestamp where the timeline starts
+    start: i64,
+    /// Unix timestamp where the timeline ends (exclusive; at most ten years after 'start')
+    end: i64,
+    /// When 'end' is before 'start': signed (default) and strict fail, absolute swaps them and sets inputs_swapped
+    #[serde(default)]
+    order_policy: Option<OrderPolicy>,
+    /// json (default), csv, or ics with one event per segment
+    #[serde(default)]
+    output_format: Option<OutputFormat>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct OffsetDifferenceParams {
+    /// IANA timezone the difference is measured from
+    from_timezone: String,
+    /// IANA timezone the difference is measured to; positive when it is ahead
+    to_timezone: String,
+    /// Date (YYYY-MM-DD); defaults to today in 'from_timezone'
+    #[serde(default)]
+    date: Option<String>,
+    /// Last date of a range (YYYY-MM-DD, inclusive): the difference from 'date' to here as segments
+    #[serde(default)]
+    end_date: Option<String>,
+    /// When 'end_date' is before 'date': signed (default) and strict fail, absolute swaps them and sets inputs_swapped
+    #[serde(default)]
+    order_policy: Option<OrderPolicy>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LocalToUtcParams {
+    /// Local date-time without offset (e.g., '2024-11-03T01:30:00')
+    local_datetime: String,
+    timezone: String,
+    /// Policy for local times that occur twice (defaults to 'earlier')
+    #[serde(default)]
+    ambiguity: Option<AmbiguityPolicy>,
+    /// Policy for local times skipped by a forward transition (defaults to 'reject')
+    #[serde(default)]
+    nonexistent: Option<GapPolicy>,
+    /// Add an explanation of each step of the resolution (defaults to false)
+    #[serde(default)]
+    explain: Option<bool>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ParseNaturalTimeParams {
+    /// Time in words, e.g. 'tomorrow at 3pm', 'next friday noon', 'in 45 minutes'
+    input: String,
+    /// Unix seconds the input is relative to (defaults to now)
+    #[serde(default)]
+    reference_timestamp: Option<i64>,
+    /// IANA timezone the days and times of day are in (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[cfg(feature = "ntp")]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct NtpHistoryParams {
+    /// Oldest sample to return: RFC 3339 or a span back from now ('30m', '24h', '7d'); defaults to all kept
+    #[serde(default)]
+    since: Option<String>,
+    /// Bucket length in seconds; buckets give min/max/mean offset instead of raw samples
+    #[serde(default)]
+    resolution: Option<u64>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FindOverlapParams {
+    /// IANA timezones of the participants; the date is a local day in the first one
+    timezones: Vec<String>,
+    /// Date to search (YYYY-MM-DD); defaults to today in the first timezone
+    #[serde(default)]
+    date: Option<String>,
+    /// Working hours for every participant as HH:MM-HH:MM (defaults to '09:00-17:00'); an end before the start is the next day
+    #[serde(default)]
+    working_hours: Option<String>,
+    /// Working hours for individual timezones, e.g. {"Asia/Tokyo": "08:00-16:00"}
+    #[serde(default)]
+    working_hours_by_timezone: Option<std::collections::BTreeMap<String, String>>,
+    /// Shortest useful slot in minutes (defaults to 30)
+    #[serde(default)]
+    min_minutes: Option<u32>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct AssertTimeWindowParams {
+    /// Start of an explicit window (Unix seconds or RFC 3339); give with 'end'
+    #[serde(default)]
+    start: Option<NumberOrText>,
+    /// End of an explicit window, exclusive; give with 'start'
+    #[serde(default)]
+    end: Option<NumberOrText>,
+    /// When 'end' is before 'start': signed (default) and strict fail, absolute swaps them and sets inputs_swapped
+    #[serde(default)]
+    order_policy: Option<OrderPolicy>,
+    /// Daily local window as HH:MM-HH:MM, e.g. '09:00-17:00'; an end at or before the start is the next day
+    #[serde(default)]
+    daily: Option<String>,
+    /// Five-field cron expression for window starts, matched in local time; give with 'duration'
+    #[serde(default)]
+    cron: Option<String>,
+    /// ISO 8601 length of each cron window, e.g. 'PT8H' (at most 31 days)
+    #[serde(default)]
+    duration: Option<String>,
+    /// IANA timezone of 'daily' and 'cron' (defaults to UTC)
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Instant to test (Unix seconds or RFC 3339); defaults to now
+    #[serde(default)]
+    timestamp: Option<NumberOrText>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ScheduleNotificationParams {
+    /// Text echoed back in the notification
+    label: String,
+    /// Fire time in RFC 3339 (e.g., '2025-01-01T09:00:00Z'); alternative to delay_seconds
+    #[serde(default)]
+    at: Option<String>,
+    /// Fire after this many seconds (at most 30 days); alternative to at
+    #[serde(default)]
+    delay_seconds: Option<f64>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CancelScheduledParams {
+    /// Id returned by schedule_notification
+    id: u64,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SubscribeHeartbeatParams {
+    /// Seconds between heartbeats, 1 to 300
+    interval_seconds: f64,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SignedTimeParams {
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TrustedTimestampParams {
+    /// SHA-256 digest of the data to timestamp: 64 hex digits or base64
+    digest: String,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct VerifySignedTimeParams {
+    /// A get_signed_time result, unchanged
+    attestation: serde_json::Value,
+    /// Hex Ed25519 public key to check against (defaults to the attestation's public_key)
+    #[serde(default)]
+    public_key: Option<String>,
+    /// Pretty-print the JSON result (defaults to JSON_PRETTY)
+    #[serde(default)]
+    pretty: Option<bool>,
+}
+
+/// Occurrences get_maintenance_window lists by default, and at most
+const DEFAULT_MAINTENANCE_OCCURRENCES: usize = 5;
+const MAX_MAINTENANCE_OCCURRENCES: usize = 100;
+
+/// Tools by group for the initialize instructions: heading, tools, and a
+/// note after the list. Only tools still on the router are named.
+const TOOL_GROUPS: &[(&str, &[&str], &str)] = &[
+    (
+        "Time Tools",
+        &[
+            "get_time",
+            "get_unix_time",
+            "get_nanos",
+            "get_time_formatted",
+            "explain_format",
+            "build_format",
+            "convert_epoch",
+            "get_time_with_timezone",
+            "list_timezones",
+            "get_timezones_if_changed",
+            "abbreviation_lookup",
+            "convert_time",
+            "convert_calendar",
+            "business_time",
+            "get_week_number",
+            "parse_time",
+            "parse_natural_time",
+            "email_date",
+            "timestamp_card",
+            "format_duration",
+            "compare_times",
+            "timezone_for_location",
+            "get_host_timezone",
+            "world_clock",
+            "get_dst_transitions",
+            "get_offset_timeline",
+            "offset_difference",
+            "local_to_utc",
+            "find_overlap",
+            "assert_time_window",
+        ],
+        "",
+    ),
+    (
+        "Scheduling Tools",
+        &[
+            "schedule_notification",
+            "list_scheduled",
+            "cancel_scheduled",
+        ],
+        " (fire as notifications/message)",
+    ),
+    (
+        "Heartbeat Tools",
+        &["subscribe_heartbeat", "unsubscribe_heartbeat"],
+        " (periodic notifications/message)",
+    ),
+    (
+        "Session Tools",
+        &["set_preferences", "get_preferences", "clear_preferences"],
+        " (defaults for omitted timezone/format/precision)",
+    ),
+    (
+        "Info Tools",
+        &[
+            "get_server_info",
+            "get_data_versions",
+            "get_clock_resolution",
+            "get_examples",
+            "check_time_sanity",
+            "get_maintenance_window",
+            "get_chaos_status",
+            "run_selftest_benchmark",
+            "get_process_stats",
+        ],
+        "",
+    ),
+    (
+        "Attestation Tools",
+        &[
+            "get_signed_time",
+            "get_signing_key",
+            "verify_signed_time",
+            "get_trusted_timestamp",
+        ],
+        " (Ed25519; RFC 3161 from TSA_URL)",
+    ),
+    (
+        "NTP Tools",
+        &[
+            "get_ntp_status",
+            "get_ntp_peers",
+            "get_ntp_history",
+            "get_pps_status",
+            "get_gps_status",
+            "get_ptp_status",
+        ],
+        " (hardware/bare-metal only)",
+    ),
+];
+
+/// Successful tool result holding `value` as JSON text
+fn json_result<T: Serialize>(value: &T, pretty: Option<bool>) -> Result<CallToolResult, McpError> {
+    let text = crate::output::to_json(value, pretty)
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    Ok(CallToolResult::success(vec![Content::text(text)]))
+}
+
+/// A CSV or iCalendar rendering as the tool's text
+fn export_result(text: String) -> Result<CallToolResult, McpError> {
+    Ok(CallToolResult::success(vec![Content::text(text)]))
+}
+
+/// `result` with `edit` applied to each JSON object it holds, keeping its
+/// pretty or compact layout
+fn edit_json_objects(
+    mut result: CallToolResult,
+    edit: impl Fn(&mut serde_json::Map<String, serde_json::Value>),
+) -> CallToolResult {
+    if result.is_error == Some(true) {
+        return result;
+    }
+    for content in &mut result.content {
+        let Some(text) = content.as_text().map(|t| t.text.clone()) else {
+            continue;
+        };
+        let Ok(serde_json::Value::Object(mut body)) = serde_json::from_str(&text) else {
+            continue;
+        };
+        edit(&mut body);
+        if let Ok(text) = crate::output::to_json(&body, Some(text.contains('\n'))) {
+            *content = Content::text(text);
+        }
+    }
+    result
+}
+
+/// `result` with `warning` added to each JSON object it holds
+fn with_warning(result: CallToolResult, warning: &Warning) -> CallToolResult {
+    edit_json_objects(result, |body| {
+        let warnings = body.entry("warnings").or_insert_with(|| json!([]));
+        if let Some(list) = warnings.as_array_mut() {
+            list.push(json!(warning));
+        }
+    })
+}
+
+/// `tool` with the `hash` argument every tool takes
+fn with_hash_param(mut tool: Tool) -> Tool {
+    let mut schema = (*tool.input_schema).clone();
+    content_hash::with_hash_property(&mut schema);
+    tool.input_schema = Arc::new(schema);
+    tool
+}
+
+/// `tool` with its example arguments as JSON Schema `examples`
+fn with_argument_examples(mut tool: Tool) -> Tool {
+    let examples = crate::mcp::examples::argument_examples(&tool.name);
+    if !examples.is_empty() {
+        let mut schema = (*tool.input_schema).clone();
+        schema.insert("examples".into(), examples.into());
+        tool.input_schema = Arc::new(schema);
+    }
+    tool
+}
+
+/// `tool` with its hints from [`annotations::TOOL_HINTS`]
+fn with_annotations(mut tool: Tool) -> Tool {
+    tool.annotations = annotations::annotations(&tool.name);
+    tool
+}
+
+/// The instant `now` in each requested zone, or the configured defaults
+fn world_clock_at(
+    timezones: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<WorldClock, TimeServerError> {
+    let zones = match timezones {
+        Some(list) => world_clock::parse_zone_list(list),
+        None => world_clock::default_zones(),
+    };
+    world_clock::world_clock(now, &zones)
+}
+
+/// The card for `timestamp` seen from `now`, in the listed zones (if any)
+fn timestamp_card_at(
+    timestamp: &str,
+    timezones: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<TimestampCard, TimeServerError> {
+    let at = card::parse_timestamp(timestamp)?;
+    let zones = timezones
+        .map(world_clock::parse_zone_list)
+        .unwrap_or_default();
+    TimestampCard::new(at, now, &zones)
+}
+
+/// `explicit` if given, else the session's `preferred` value (recorded in `applied`)
+fn or_preference<T>(
+    explicit: Option<T>,
+    preferred: Option<T>,
+    name: &'static str,
+    applied: &mut Vec<&'static str>,
+) -> Option<T> {
+    if explicit.is_some() {
+        return explicit;
+    }
+    if preferred.is_some() {
+        applied.push(name);
+    }
+    preferred
+}
+
+/// Error for a tool or prompt called without `name` and no preference to fall back on
+fn missing_argument(name: &str) -> TimeServerError {
+    TimeServerError::InvalidArgument(format!(
+        "'{}' is required unless a default is set with set_preferences",
+        name
+    ))
+}
+
+/// strftime names are English only; warn when a non-English session locale
+/// meets a format that prints day, month or AM/PM names
+fn locale_fallback(locale: Option<&str>, format: &str) -> Option<Warning> {
+    let locale = locale?;
+    let english = locale
+        .split(['-', '_'])
+        .next()
+        .is_some_and(|language| language.eq_ignore_ascii_case("en"));
+    let mut specifiers = format.split('%').skip(1);
+    let names = specifiers.any(|spec| {
+        spec.trim_start_matches(['-', '_', '0', '^', '#'])
+            .starts_with(['a', 'A', 'b', 'B', 'h', 'p', 'P', 'c', 'r', 'x', 'X'])
+    });
+    (!english && names).then(|| {
+        Warning::new(
+            WarningCode::LocaleFallback,
+            format!(
+                "locale '{}' is not supported for names; English was used",
+                locale
+            ),
+        )
+        .with_field("locale")
+    })
+}
+
+/// Delivers fired schedule entries as MCP logging notifications
+struct PeerSink(Peer<RoleServer>);
+
+impl NotificationSink for PeerSink {
+    fn deliver(&self, notification: FiredNotification) {
+        let peer = self.0.clone();
+        tokio::spawn(async move {
+            let param = LoggingMessageNotificationParam {
+                level: LoggingLevel::Info,
+                logger: Some("scheduler".into()),
+                data: serde_json::to_value(&notification).unwrap_or_default(),
+            };
+            if let Err(e) = peer.notify_logging_message(param).await {
+                debug!(event = "scheduler.deliver_failed", error = %e);
+            }
+        });
+    }
+}
+
+/// Time server implementing MCP protocol
+#[derive(Clone)]
+pub struct TimeServer {
+    tool_router: ToolRouter<Self>,
+    #[cfg(feature = "prompts")]
+    prompt_router: PromptRouter<Self>,
+    /// Deadline for a single tool call; caps the budgets in `tool_timeouts`
+    tool_timeout: Duration,
+    /// Per-tool budgets (TOOL_TIMEOUT_<NAME>_MS over the built-in table)
+    tool_timeouts: ToolTimeouts,
+    /// Drop unknown arguments instead of rejecting the call (MCP_LENIENT_PARAMS)
+    lenient_params: bool,
+    /// run_selftest_benchmark may run (ENABLE_BENCH_TOOL)
+    bench_tool: bool,
+    /// Tools taken out of `tool_router` (DISABLE_NTP_TOOLS, DISABLED_TOOLS, ...)
+    disabled_tools: ToolPolicy,
+    /// This session's token bucket (MCP_RATE_LIMIT); `None` when off
+    rate_limiter: Option<Arc<RateLimiter>>,
+    audit: Option<AuditLogger>,
+    /// One-shot notifications registered by this session
+    scheduler: Scheduler,
+    /// This session's subscribe_heartbeat task, if any
+    heartbeat: Heartbeat,
+    /// Defaults set with set_preferences, dropped when the session ends
+    session: Session,
+    /// Server-wide fallback for an omitted timezone (DEFAULT_TIMEZONE)
+    default_timezone: Option<String>,
+    /// Furthest `as_of` may be from the clock (AS_OF_MAX_SKEW_SECS)
+    as_of_max_skew_secs: Option<u64>,
+    /// Timezone results further ahead carry `tzdata_caveat` (TZDATA_CAVEAT_DAYS)
+    tzdata_caveat_days: u32,
+    /// Longest interval a tool takes (MAX_INTERVAL_YEARS)
+    max_interval_years: u32,
+    /// `valid_for_ms` / `expires_at` rules (CURRENT_TIME_VALID_MS)
+    validity: ValidityConfig,
+    /// Thresholds for the `health` grade of get_ntp_status (HEALTH_*)
+    #[cfg(feature = "ntp")]
+    health: HealthConfig,
+    /// Declared maintenance window (MAINTENANCE_WINDOW)
+    maintenance: Option<MaintenanceWindow>,
+    /// RFC 3161 timestamp authority for get_trusted_timestamp (TSA_URL)
+    tsa_url: Option<String>,
+    /// Refuses time tools while the system clock is implausible
+    clock_guard: Arc<ClockGuard>,
+    /// Level from logging/setLevel and the per-kind rate limit for server events
+    log_filter: Arc<Mutex<NotificationFilter>>,
+    state: Arc<ServerState>,
+    /// Request counters and recent errors, shared with the HTTP API
+    stats: Arc<ServerStats>,
+}
+
+impl TimeServer {
+    /// A server with the built-in defaults
+    pub fn new() -> Self {
+        Self::with_config(&ServerConfig::default())
+    }
+
+    pub fn with_config(config: &ServerConfig) -> Self {
+        Self {
+            tool_router: Self::enabled_tools(
+                &config.mcp.disabled_tools,
+                config.maintenance.is_some(),
+            ),
+            #[cfg(feature = "prompts")]
+            prompt_router: Self::prompt_router(),
+            tool_timeout: config.mcp.request_timeout(),
+            tool_timeouts: config.mcp.tool_timeouts.clone(),
+            lenient_params: config.mcp.lenient_params,
+            bench_tool: config.mcp.bench_tool,
+            disabled_tools: config.mcp.disabled_tools.clone(),
+            rate_limiter: config
+                .mcp
+                .rate_limit
+                .map(|limit| Arc::new(RateLimiter::new(limit))),
+            audit: crate::audit::global().cloned(),
+            scheduler: Scheduler::new(config.mcp.schedule_max_pending),
+            heartbeat: Heartbeat::default(),
+            session: Session::new(crate::preferences::global().clone()),
+            default_timezone: config.default_timezone.clone(),
+            as_of_max_skew_secs: config.as_of_max_skew_secs,
+            tzdata_caveat_days: config.tzdata.caveat_days,
+            max_interval_years: config.intervals.max_years,
+            validity: config.validity,
+            #[cfg(feature = "ntp")]
+            health: config.health.clone(),
+            maintenance: config.maintenance.clone(),
+            tsa_url: config.tsa_url.clone(),
+            clock_guard: crate::guard::global(),
+            log_filter: Arc::default(),
+            state: ServerState::shared(),
+            stats: crate::stats::global().clone(),
+        }
+    }
+
+    /// Every tool compiled into this build
+    fn tool_router() -> ToolRouter<Self> {
+        let router = Self::time_tool_router();
+        #[cfg(feature = "ntp")]
+        let router = router + Self::ntp_tool_router();
+        router
+    }
+
+    /// Names of every tool this server implements, disabled or not
+    pub fn tool_names() -> Vec<String> {
+        Self::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.into_owned())
+            .collect()
+    }
+
+    /// The tool router without the tools `policy` disables
+    fn enabled_tools(policy: &ToolPolicy, maintenance: bool) -> ToolRouter<Self> {
+        let mut router = Self::tool_router();
+        for tool in policy.disabled() {
+            router.remove_route(tool);
+        }
+        // Without MAINTENANCE_WINDOW there is nothing for it to report
+        if !maintenance {
+            router.remove_route("get_maintenance_window");
+        }
+        // Without the tsa feature there is no client to ask a TSA with
+        if !cfg!(feature = "tsa") {
+            router.remove_route("get_trusted_timestamp");
+        }
+        router
+    }
+
+    /// One line per tool group, naming only the tools on the router
+    fn tool_groups(&self) -> String {
+        TOOL_GROUPS
+            .iter()
+            .filter_map(|(heading, tools, note)| {
+                let listed: Vec<&str> = tools
+                    .iter()
+                    .copied()
+                    .filter(|tool| self.tool_router.has_route(tool))
+                    .collect();
+                (!listed.is_empty()).then(|| format!("{}: {}{}", heading, listed.join(", "), note))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Use `state` instead of the process-wide [`ServerState::shared`]
+    pub fn with_state(mut self, state: Arc<ServerState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Count requests in `stats` instead of the process-wide [`ServerStats`]
+    pub fn with_stats(mut self, stats: Arc<ServerStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Accept heartbeat intervals down to `min` (defaults to one second)
+    pub fn with_min_heartbeat_interval(mut self, min: Duration) -> Self {
+        self.heartbeat = Heartbeat::new(min);
+        self
+    }
+
+    pub fn state(&self) -> &Arc<ServerState> {
+        &self.state
+    }
+
+    /// Validity rules, shared with the HTTP API
+    pub fn validity(&self) -> &ValidityConfig {
+        &self.validity
+    }
+
+    /// This session's preferences
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Session preferences over the server-wide defaults
+    fn preferences(&self) -> Preferences {
+        let mut preferences = Preferences {
+            timezone: self.default_timezone.clone(),
+            ..Default::default()
+        };
+        preferences.merge(self.session.preferences());
+        preferences
+    }
+
+    /// Record every tool call to `logger` (defaults to the process-wide audit log)
+    pub fn with_audit_logger(mut self, logger: AuditLogger) -> Self {
+        self.audit = Some(logger);
+        self
+    }
+
+    /// Override the tool-call deadline (defaults to `MCP_REQUEST_TIMEOUT_SECS`)
+    pub fn with_tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = timeout;
+        self
+    }
+
+    /// Use `guard` instead of the process-wide [`crate::guard::global`]
+    pub fn with_clock_guard(mut self, guard: Arc<ClockGuard>) -> Self {
+        self.clock_guard = guard;
+        self
+    }
+
+    pub fn clock_guard(&self) -> &Arc<ClockGuard> {
+        &self.clock_guard
+    }
+
+    /// Limit this session's tool calls with `limiter` (defaults to
+    /// `MCP_RATE_LIMIT`); `None` turns the limit off
+    pub fn with_rate_limiter(mut self, limiter: Option<RateLimiter>) -> Self {
+        self.rate_limiter = limiter.map(Arc::new);
+        self
+    }
+
+    /// Per-tool budgets (defaults to `TOOL_TIMEOUT_<NAME>_MS`)
+    pub fn with_tool_timeouts(mut self, timeouts: ToolTimeouts) -> Self {
+        self.tool_timeouts = timeouts;
+        self
+    }
+
+    /// How long `tool` may run
+    pub fn tool_budget(&self, tool: &str) -> Duration {
+        self.tool_timeouts.budget(tool, self.tool_timeout)
+    }
+
+    /// `tool` with its budget in the input schema, for tools/list
+    fn with_budget(&self, mut tool: Tool) -> Tool {
+        let budget = self.tool_budget(&tool.name).as_millis() as u64;
+        let mut schema = (*tool.input_schema).clone();
+        schema.insert(timeouts::SCHEMA_KEY.into(), budget.into());
+        tool.input_schema = Arc::new(schema);
+        tool
+    }
+
+    /// Drop unknown tool arguments instead of rejecting the call
+    /// (defaults to `MCP_LENIENT_PARAMS`)
+    pub fn with_lenient_params(mut self, lenient: bool) -> Self {
+        self.lenient_params = lenient;
+        self
+    }
+
+    /// Allow run_selftest_benchmark (defaults to `ENABLE_BENCH_TOOL`)
+    pub fn with_bench_tool(mut self, enabled: bool) -> Self {
+        self.bench_tool = enabled;
+        self
+    }
+
+    /// Offer every tool except those `policy` disables (defaults to
+    /// `DISABLE_NTP_TOOLS`, `DISABLE_SUBPROCESS` and `DISABLED_TOOLS`)
+    pub fn with_disabled_tools(mut self, policy: ToolPolicy) -> Self {
+        self.tool_router = Self::enabled_tools(&policy, self.maintenance.is_some());
+        self.disabled_tools = policy;
+        self
+    }
+
+    /// Tools this server refuses to run
+    pub fn disabled_tools(&self) -> &ToolPolicy {
+        &self.disabled_tools
+    }
+
+    /// Forward server events that pass this session's filter to `peer`,
+    /// until the peer goes away
+    fn forward_events(&self, peer: Peer<RoleServer>) {
+        let mut events = crate::events::global().subscribe();
+        let filter = self.log_filter.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!(event = "events.lagged", skipped = skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let admitted = filter
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .admit(&event, std::time::Instant::now());
+                if admitted {
+                    if let Err(e) = peer.notify_logging_message(event.to_notification()).await {
+                        debug!(event = "events.forward_stopped", error = %e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Current time from the highest-priority usable source; with correction
+    /// requested, system time is shifted by the cached NTP offset. A `pinned`
+    /// instant replaces the sources for this call.
+    #[cfg(feature = "ntp")]
+    async fn current_time(
+        &self,
+        corrected: Option<bool>,
+        pinned: Option<DateTime<Utc>>,
+    ) -> Result<(DateTime<Utc>, TimeSource, Option<Correction>), TimeServerError> {
+        if let Some(at) = pinned {
+            return Ok((at, TimeSource::Client, None));
+        }
+        let sources = self.state.time_sources();
+        if !corrected.unwrap_or_else(correction::enabled_by_default) {
+            let reading = sources.now()?;
+            return Ok((reading.to_datetime(), reading.source, None));
+        }
+        let cache = correction::global();
+        cache.refresh_if_stale(self.state.ntp_clock()).await;
+        let reading = sources.now()?;
+        Ok(match reading.source {
+            TimeSource::System | TimeSource::Ntp => {
+                let (now, applied) = cache.correct(chaos::now(), std::time::Instant::now());
+                let source = if applied.corrected {
+                    TimeSource::Ntp
+                } else {
+                    TimeSource::System
+                };
+                (now, source, Some(applied))
+            }
+            source => (
+                reading.to_datetime(),
+                source,
+                Some(Correction::from_reference(source)),
+            ),
+        })
+    }
+
+    /// Current time from the system clock; a requested correction falls back
+    /// with a warning, as it does when no offset has been measured. A
+    /// `pinned` instant replaces the clock for this call.
+    #[cfg(not(feature = "ntp"))]
+    async fn current_time(
+        &self,
+        corrected: Option<bool>,
+        pinned: Option<DateTime<Utc>>,
+    ) -> Result<(DateTime<Utc>, TimeSource, Option<Correction>), TimeServerError> {
+        if let Some(at) = pinned {
+            return Ok((at, TimeSource::Client, None));
+        }
+        let reading = self.state.time_sources().now()?;
+        let correction = corrected.unwrap_or(false).then(|| {
+            Correction::skipped(
+                None,
+                "built without the ntp feature; no offset applied".to_string(),
+            )
+            .fell_back(WarningCode::OffsetUnavailable)
+        });
+        Ok((reading.to_datetime(), reading.source, correction))
+    }
+
+    /// The instant `as_of` names, checked against the clock
+    fn pinned(
+        &self,
+        as_of: Option<&NumberOrText>,
+    ) -> Result<Option<DateTime<Utc>>, TimeServerError> {
+        as_of
+            .map(|as_of| {
+                reference::pinned(&as_of.to_text(), chaos::now(), self.as_of_max_skew_secs)
+            })
+            .transpose()
+    }
+
+    /// `as_of` if given, else the current time, with the reference to echo
+    fn reference_time(
+        &self,
+        as_of: Option<&NumberOrText>,
+    ) -> Result<(DateTime<Utc>, ReferenceTime), TimeServerError> {
+        let (at, source) = match self.pinned(as_of)? {
+            Some(at) => (at, ReferenceSource::Client),
+            None => (chaos::now(), ReferenceSource::Server),
+        };
+        Ok((at, ReferenceTime::new(at, source)))
+    }
+
+    /// `body` with a `tzdata_caveat` (and a TZDATA_FUTURE warning) when `at`
+    /// is far enough ahead
+    fn with_caveat<T>(&self, body: T, at: DateTime<Utc>) -> WithWarnings<WithCaveat<T>> {
+        self.with_caveat_and(body, at, Warnings::new())
+    }
+
+    /// `with_caveat`, after the tool's own `warnings`
+    fn with_caveat_and<T>(
+        &self,
+        body: T,
+        at: DateTime<Utc>,
+        mut warnings: Warnings,
+    ) -> WithWarnings<WithCaveat<T>> {
+        let caveat = tzdata_caveat(at, chaos::now(), self.tzdata_caveat_days);
+        if let Some(caveat) = &caveat {
+            warnings.warn(WarningCode::TzdataFuture, caveat.clone());
+        }
+        warnings.attach(WithCaveat {
+            body,
+            tzdata_caveat: caveat,
+        })
+    }
+
+    /// `normalize_interval` under `policy` and MAX_INTERVAL_YEARS
+    fn interval(
+        &self,
+        ends: (i128, i128),
+        names: (&str, &str),
+        policy: Option<OrderPolicy>,
+    ) -> Result<Interval, TimeServerError> {
+        let rules = IntervalRules {
+            policy: policy.unwrap_or_default(),
+            max_years: self.max_interval_years,
+        };
+        duration::normalize_interval(ends.0, ends.1, names, rules)
+    }
+
+    /// `convert_time` without the MCP wrapping; also serves batch `convert` operations
+    pub(crate) fn convert(
+        &self,
+        params: ConvertTimeParams,
+    ) -> Result<WithWarnings<WithCaveat<Explained<Conversion>>>, TimeServerError> {
+        let to_timezone = params.to_timezone;
+        let mut explain = Explain::new(params.explain.unwrap_or(false));
+
+        let (utc, original) = TimezoneConverter::conversion_origin_explained(
+            params.timestamp,
+            params.local_datetime.as_deref(),
+            params.from_timezone.as_deref(),
+            params.ambiguity.unwrap_or_default(),
+            params.nonexistent.unwrap_or_default(),
+            &mut explain,
+        )?;
+
+        let converted = TimezoneConverter::convert_to_tz(utc, &to_timezone)?;
+        explain.step(|| explain::offset_rule(converted.timezone(), utc));
+        explain.step(|| explain::utc_to_local_arithmetic(utc, converted));
+        let result = Conversion {
+            original,
+            note: CONVERSION_NOTE,
+            converted: ConvertedTime::new(
+                &converted,
+                to_timezone,
+                params.calendar_annotation.unwrap_or(false),
+            )?,
+        };
+        let result = Explained {
+            body: result,
+            explanation: explain.finish(),
+        };
+
+        Ok(self.with_caveat(result, utc))
+    }
+
+    /// NTP status holds until the cached offset is due for a refresh
+    #[cfg(feature = "ntp")]
+    pub fn ntp_validity(&self) -> Validity {
+        let remaining = correction::global().refresh_due_in(std::time::Instant::now());
+        self.validity.cached(chaos::now(), remaining)
+    }
+
+    /// Grade `ntp` as `/health` does, with the latest PTP status folded in
+    #[cfg(feature = "ntp")]
+    fn grade(&self, ntp: Result<&crate::ntp::NtpStatus, &str>, container: bool) -> HealthReport {
+        let mut report = crate::health::assess(ntp, container, &self.health);
+        if let Some((_, ptp)) = crate::ptp::latest(std::time::Instant::now()) {
+            report.fold_ptp(&ptp, &self.health);
+        }
+        if let Some(check) = crate::ntp::supervisor::global()
+            .and_then(|supervisor| crate::health::ntpd_check(&supervisor.status()))
+        {
+            report.push(check);
+        }
+        if let Some(check) = crate::health::clock_guard_check(&self.clock_guard.status()) {
+            report.push(check);
+        }
+        report
+    }
+
+    /// `result` with `managed` and, when the server runs ntpd itself, the
+    /// supervisor's status
+    #[cfg(feature = "ntp")]
+    fn with_managed_ntpd(mut result: serde_json::Value) -> serde_json::Value {
+        let managed = match crate::ntp::supervisor::global() {
+            Some(supervisor) => json!(supervisor.status()),
+            None => json!({"managed": false}),
+        };
+        if let (Some(fields), serde_json::Value::Object(managed)) =
+            (result.as_object_mut(), managed)
+        {
+            fields.extend(managed);
+        }
+        result
+    }
+
+    /// Check if NTP tools are available (compiled in, not in a container)
+    #[cfg(feature = "ntp")]
+    fn is_ntp_available() -> bool {
+        use crate::ntp::NtpSyncedClock;
+        !NtpSyncedClock::is_container_environment()
+    }
+
+    #[cfg(not(feature = "ntp"))]
+    fn is_ntp_available() -> bool {
+        false
+    }
+}
+
+impl Default for TimeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tool implementations using macros
+#[tool_router(router = time_tool_router)]
+impl TimeServer {
+    /// Get current time with full Unix/POSIX details
+    #[tool(
+        description = "Get current time with full Unix/POSIX details, in UTC unless timezone (or a session preference) is given. Set corrected=true to apply the measured NTP offset; precision picks the sub-second digits."
+    )]
+    async fn get_time(
+        &self,
+        Parameters(params): Parameters<GetTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_time");
+        let preferences = self.preferences();
+        let mut applied = Vec::new();
+        let timezone = or_preference(
+            params.timezone,
+            preferences.timezone,
+            "timezone",
+            &mut applied,
+        );
+        let precision = or_preference(
+            params.precision,
+            preferences.precision,
+            "precision",
+            &mut applied,
+        );
+
+        let pinned = self.pinned(params.as_of.as_ref())?;
+        let (now, source, correction) = self.current_time(params.corrected, pinned).await?;
+        let mut time = match &timezone {
+            Some(tz) => EnhancedTimeResponse::at_timezone(now, tz)?,
+            None => EnhancedTimeResponse::at(now),
+        };
+        if params.calendar_annotation.unwrap_or(false) {
+            time = time.with_calendar_annotation();
+        }
+        if let Some(precision) = precision {
+            time = time.with_precision(precision.into());
+        }
+        let warnings: Warnings = correction.iter().filter_map(Correction::warning).collect();
+        let response = warnings.attach(self.validity.current_time(now).attach(WithDefaults {
+            body: ReferenceTime::new(now, source.into()).attach(Corrected {
+                time,
+                source,
+                correction,
+            }),
+            defaults_applied: applied,
+        }));
+        json_result(&response, params.pretty)
+    }
+
+    /// Get Unix epoch time with nanosecond precision
+    #[tool(
+        description = "Get Unix epoch time with nanosecond precision. Set corrected=true to apply the measured NTP offset. Compact JSON unless pretty=true (or JSON_PRETTY=true)."
+    )]
+    async fn get_unix_time(
+        &self,
+        Parameters(params): Parameters<CurrentTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_unix_time");
+        let (now, source, correction) = self.current_time(params.corrected, None).await?;
+        let time = UnixTime::from_datetime(now);
+        // Hot path: compact unless asked, and hand-written when uncorrected
+        let pretty = params.pretty.or_else(crate::output::pretty_setting);
+        if correction.is_none() && pretty != Some(true) {
+            let text = fast::unix_time_json(&time, source, self.validity.current_time_ms, now);
+            return Ok(CallToolResult::success(vec![Content::text(text)]));
+        }
+        let warnings: Warnings = correction.iter().filter_map(Correction::warning).collect();
+        let response = warnings.attach(self.validity.current_time(now).attach(Corrected {
+            time,
+            source,
+            correction,
+        }));
+        json_result(&response, Some(pretty.unwrap_or(false)))
+    }
+
+    /// Get nanoseconds since Unix epoch
+    #[tool(description = "Get nanoseconds since Unix epoch")]
+    async fn get_nanos(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_nanos");
+        json_result(&UnixTime::now().nanos_reading(), None)
+    }
+
+    /// Get time formatted with strftime format string
+    #[tool(
+        description = "Get time formatted with strftime format string (e.g., '%Y-%m-%d %H:%M:%S'), in UTC unless timezone is given. format and timezone default to the session preferences. dialect='gnu' accepts GNU date formats ('+%Y-%m-%dT%H:%M:%S.%N%:z') and reports the chrono_format they were translated to."
+    )]
+    async fn get_time_formatted(
+        &self,
+        Parameters(params): Parameters<FormatParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let preferences = self.preferences();
+        let mut applied = Vec::new();
+        let format = or_preference(params.format, preferences.format, "format", &mut applied)
+            .ok_or_else(|| missing_argument("format"))?;
+        let timezone = or_preference(
+            params.timezone,
+            preferences.timezone,
+            "timezone",
+            &mut applied,
+        );
+        debug!("Tool: get_time_formatted with format: {}", format);
+
+        let number_system = params
+            .number_system
+            .or_else(|| {
+                preferences
+                    .locale
+                    .as_deref()
+                    .and_then(NumberSystem::from_locale)
+            })
+            .unwrap_or_default();
+
+        let dialect = params.dialect.unwrap_or_default();
+        let chrono_format = StrftimeFormatter::to_chrono(&format, dialect)?;
+
+        let (now, reference) = self.reference_time(params.as_of.as_ref())?;
+        let formatted = match (number_system, timezone.as_deref()) {
+            (NumberSystem::Latn, timezone) => {
+                self.state
+                    .format_cache()
+                    .format(now, &chrono_format, timezone)?
+            }
+            (system, Some(tz)) => {
+                system.format(&TimezoneConverter::convert_to_tz(now, tz)?, &chrono_format)?
+            }
+            (system, None) => system.format(&now, &chrono_format)?,
+        };
+
+        let mut result = json!({
+            "formatted": formatted,
+            "format": format,
+            "unix_seconds": now.timestamp(),
+            "unix_nanos": now.timestamp_subsec_nanos(),
+        });
+        if !dialect.is_chrono() {
+            result["dialect"] = json!(dialect);
+            result["chrono_format"] = json!(chrono_format);
+        }
+        if let Some(tz) = timezone {
+            result["timezone"] = json!(tz);
+        }
+        if number_system != NumberSystem::Latn {
+            result["number_system"] = json!(number_system);
+        }
+        let mut warnings = Warnings::new();
+        if let Some(warning) = locale_fallback(preferences.locale.as_deref(), &format) {
+            warnings.push(warning);
+        }
+        let response = warnings.attach(WithDefaults {
+            body: reference.attach(result),
+            defaults_applied: applied,
+        });
+        json_result(&response, params.pretty)
+    }
+
+    /// Get time in specified timezone
+    #[tool(description = "Get time in specified timezone (IANA name like 'America/New_York')")]
+    async fn get_time_with_timezone(
+        &self,
+        Parameters(params): Parameters<TimezoneParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let timezone = params.timezone;
+        debug!("Tool: get_time_with_timezone for {}", timezone);
+        let (now, reference) = self.reference_time(params.as_of.as_ref())?;
+        let time = EnhancedTimeResponse::at_timezone(now, &timezone)?;
+        let response = reference.attach(self.validity.zone_reading(time, &timezone, now)?);
+
+        json_result(&response, params.pretty)
+    }
+
+    /// List IANA timezones, optionally filtered, paged or grouped by region
+    #[tool(
+        description = "List IANA timezones. Without arguments returns every name; use prefix/region to narrow, offset/limit to page, grouped=true for a compact region -> cities map, or detail=true for each zone's current offset, DST flag and local hour"
+    )]
+    async fn list_timezones(
+        &self,
+        Parameters(params): Parameters<ListTimezonesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: list_timezones");
+        let pretty = params.pretty;
+        let query: TimezoneListQuery = params.into();
+        let validity = self.validity.static_data(chaos::now());
+        if query == TimezoneListQuery::default() {
+            let pretty = pretty.unwrap_or_else(crate::output::pretty_default);
+            let listing = self.state.timezone_listing(pretty);
+            let text = crate::output::append_fields(listing, &validity, pretty)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            return Ok(CallToolResult::success(vec![Content::text(text)]));
+        }
+        let result = TimezoneConverter::list_timezones_page(&query);
+        let validity = match result.detail_expires_at() {
+            Some(expires_at) => Validity::until(chaos::now(), expires_at),
+            None => validity,
+        };
+        json_result(&validity.attach(result), pretty)
+    }
+
+    /// The full timezone list, unless the client's copy is current
+    #[tool(
+        description = "Return the full timezone list (as list_timezones without arguments) unless 'checksum' matches the current one, in which case only {unchanged: true, checksum} comes back. The checksum changes only when the server's tz database does, so a client can cache the list across sessions."
+    )]
+    async fn get_timezones_if_changed(
+        &self,
+        Parameters(params): Parameters<TimezonesIfChangedParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_timezones_if_changed");
+        let checksum = TimezoneConverter::timezone_list_checksum();
+        let validity = self.validity.static_data(chaos::now());
+        if params.checksum.as_deref() == Some(checksum) {
+            let result = json!({"unchanged": true, "checksum": checksum});
+            return json_result(&validity.attach(result), params.pretty);
+        }
+        let pretty = params.pretty.unwrap_or_else(crate::output::pretty_default);
+        let listing = self.state.timezone_listing(pretty);
+        let text = crate::output::append_fields(
+            listing,
+            &validity.attach(json!({"unchanged": false})),
+            pretty,
+        )
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Convert timestamp between timezones
+    #[tool(
+        description = "Convert an instant between timezones. Give a Unix 'timestamp' (absolute; 'original' shows it in from_timezone) or a 'local_datetime' wall-clock time in from_timezone, resolved with the ambiguity/nonexistent policies around DST transitions. from_timezone defaults to UTC. 'explain' adds the steps taken: input, offset rules, any DST decision and the arithmetic."
+    )]
+    async fn convert_time(
+        &self,
+        Parameters(params): Parameters<ConvertTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: convert_time from {:?} to {}",
+            params.from_timezone, params.to_timezone
+        );
+        let pretty = params.pretty;
+        json_result(&self.convert(params)?, pretty)
+    }
+
+    /// Convert between Unix time and other epochs
+    #[tool(
+        description = "Convert a value counted from another epoch (Windows FILETIME, .NET ticks, NTP, Excel serial date, Apple Cocoa, GPS or Unix) to the Unix timestamp and the same instant in every supported epoch, with each epoch's unit, origin and leap second handling"
+    )]
+    async fn convert_epoch(
+        &self,
+        Parameters(params): Parameters<ConvertEpochParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let value = params.value.to_text();
+        debug!("Tool: convert_epoch {} {:?}", value, params.epoch);
+        let conversion = epochs::convert_epoch(&value, params.epoch, params.ntp_era)?;
+        json_result(&conversion, params.pretty)
+    }
+
+    /// Map a timezone abbreviation to UTC offsets and zones
+    #[tool(
+        description = "Look up a timezone abbreviation ('CEST', '3pm EST'): the UTC offsets and representative IANA zones using it on a date (defaults to today), with 'unique' false when it is ambiguous and every known meaning for CST, IST and BST"
+    )]
+    async fn abbreviation_lookup(
+        &self,
+        Parameters(params): Parameters<AbbreviationLookupParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: abbreviation_lookup {}", params.abbreviation);
+        let at = match params.timestamp {
+            Some(timestamp) => unix::datetime_at(timestamp)?,
+            None => chaos::now(),
+        };
+        let lookup = abbreviations::lookup_abbreviation(&params.abbreviation, at)?;
+        json_result(&*lookup, params.pretty)
+    }
+
+    /// Business day and public holiday check
+    #[tool(
+        description = "Check whether a date (defaults to today) is a business day: weekend, public holidays in a region's calendar (US federal, UK, DE or custom) with their names and observed dates, and the next business day"
+    )]
+    async fn business_time(
+        &self,
+        Parameters(params): Parameters<BusinessTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: business_time {:?} in {:?}",
+            params.date, params.region
+        );
+        let (now, reference) = self.reference_time(params.as_of.as_ref())?;
+        let date = match &params.date {
+            Some(text) => text.trim().parse::<chrono::NaiveDate>().map_err(|_| {
+                TimeServerError::InvalidArgument(format!(
+                    "'date' must be YYYY-MM-DD, got '{}'",
+                    text
+                ))
+            })?,
+            None => {
+                let timezone = params.timezone.as_deref().unwrap_or("UTC");
+                TimezoneConverter::convert_to_tz(now, timezone)?.date_naive()
+            }
+        };
+
+        let calendar: Arc<dyn HolidayCalendar> = match &params.region {
+            Some(region) => holidays::calendar(region).ok_or_else(|| {
+                TimeServerError::InvalidArgument(format!(
+                    "Unknown holiday region '{}'; available: {}",
+                    region,
+                    holidays::regions().join(", ")
+                ))
+            })?,
+            None => Arc::new(RuleCalendar::new("", Vec::new())),
+        };
+
+        let on_date = calendar.holidays_on(date);
+        let result = json!({
+            "date": date.to_string(),
+            "weekday": date.format("%A").to_string(),
+            "region": params.region.as_ref().map(|_| calendar.region()),
+            "is_weekend": holidays::is_weekend(date),
+            "is_holiday": !on_date.is_empty(),
+            "holidays": on_date,
+            "is_business_day": calendar.is_business_day(date),
+            "next_business_day": calendar.next_business_day(date).map(|d| d.to_string()),
+        });
+        json_result(&reference.attach(result), params.pretty)
+    }
+
+    /// Week number under several conventions
+    #[tool(
+        description = "Week number and week-year of a date (defaults to today) under every common convention side by side: ISO 8601, Sunday-start %U, Monday-start %W, Saturday-start (week holding Jan 1 is week 1) and simple 7-day blocks from Jan 1, plus the first and last day of the week in the selected convention"
+    )]
+    async fn get_week_number(
+        &self,
+        Parameters(params): Parameters<WeekNumberParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: get_week_number {:?} {:?}",
+            params.date, params.timestamp
+        );
+        let timezone = params.timezone.as_deref().unwrap_or("UTC");
+        let date = match (&params.date, params.timestamp) {
+            (Some(text), None) => text.trim().parse::<chrono::NaiveDate>().map_err(|_| {
+                TimeServerError::InvalidArgument(format!(
+                    "'date' must be YYYY-MM-DD, got '{}'",
+                    text
+                ))
+            })?,
+            (None, Some(timestamp)) => {
+                let at = unix::datetime_at(timestamp)?;
+                TimezoneConverter::convert_to_tz(at, timezone)?.date_naive()
+            }
+            (None, None) => TimezoneConverter::convert_to_tz(chaos::now(), timezone)?.date_naive(),
+            (Some(_), Some(_)) => {
+                return Err(TimeServerError::InvalidArgument(
+                    "give 'date' or 'timestamp', not both".to_string(),
+                )
+                .into())
+            }
+        };
+
+        let weeks: serde_json::Map<String, serde_json::Value> = WeekConvention::ALL
+            .iter()
+            .map(|convention| {
+                let week = convention.week_of(date);
+                (
+                    convention.id().to_string(),
+                    json!({"week": week.week, "week_year": week.week_year}),
+                )
+            })
+            .collect();
+        let convention = params.convention.unwrap_or_default();
+        let selected = convention.week_of(date);
+        let result = json!({
+            "date": date.to_string(),
+            "weekday": date.format("%A").to_string(),
+            "iso_week": date.format("%G-W%V").to_string(),
+            "weeks": weeks,
+            "selected": {
+                "convention": convention.id(),
+                "week": selected.week,
+                "week_year": selected.week_year,
+                "first_day": selected.first_day.to_string(),
+                "last_day": selected.last_day.to_string(),
+            },
+        });
+        json_result(&result, params.pretty)
+    }
+
+    /// Convert between Unix time and other calendars
+    #[tool(
+        description = "Convert a Unix timestamp (defaults to now) to a date in the Hebrew, Islamic, Persian (Solar Hijri) or Japanese era calendar: year and era, month number and name, day and a formatted string. Give 'year', 'month' and 'day' (and 'era' for Japanese) to convert a calendar date back to the Unix time its day starts. Islamic dates use the tabular calendar and may differ from observed dates by a day or two."
+    )]
+    async fn convert_calendar(
+        &self,
+        Parameters(params): Parameters<ConvertCalendarParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: convert_calendar {:?}", params.calendar);
+        let timezone = params.timezone.as_deref().unwrap_or("UTC");
+
+        let (unix_timestamp, local_date) = match (params.year, params.month, params.day) {
+            (Some(year), Some(month), Some(day)) => {
+                let date = calendars::to_gregorian(
+                    params.calendar,
+                    params.era.as_deref(),
+                    year,
+                    month,
+                    day,
+                )?;
+                let start = TimezoneConverter::local_to_utc(
+                    &format!("{}T00:00:00", date),
+                    timezone,
+                    AmbiguityPolicy::Earlier,
+                    GapPolicy::ShiftForward,
+                )?;
+                (start.unix_timestamp, date)
+            }
+            (None, None, None) => {
+                let at = match params.timestamp {
+                    Some(timestamp) => unix::datetime_at(timestamp)?,
+                    None => chaos::now(),
+                };
+                let local = TimezoneConverter::convert_to_tz(at, timezone)?;
+                (at.timestamp(), local.date_naive())
+            }
+            _ => {
+                return Err(TimeServerError::InvalidArgument(
+                    "reverse conversion needs all of 'year', 'month' and 'day'".to_string(),
+                )
+                .into())
+            }
+        };
+
+        let date = calendars::from_gregorian(params.calendar, local_date)?;
+        let utc = DateTime::from_timestamp(unix_timestamp, 0).unwrap_or_default();
+        let result = json!({
+            "unix_timestamp": unix_timestamp,
+            "utc": utc.to_rfc3339(),
+            "timezone": timezone,
+            "date": date,
+        });
+        json_result(&result, params.pretty)
+    }
+
+    /// Explain a strftime format
+    #[tool(
+        description = "Explain a strftime format: the meaning of each directive ('%j: day of year (001-366)'), unknown directives listed separately, and the format rendered now and at the reference time 2006-01-02T15:04:05.123456789Z so each number can be matched to its directive. dialect='gnu' reads the format as GNU date does (%N, %3N)"
+    )]
+    async fn explain_format(
+        &self,
+        Parameters(params): Parameters<ExplainFormatParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: explain_format '{}'", params.format);
+        let dialect = params.dialect.unwrap_or_default();
+        let explanation = match &params.timezone {
+            Some(tz) => StrftimeFormatter::explain_dialect(
+                &params.format,
+                dialect,
+                &TimezoneConverter::convert_to_tz(chaos::now(), tz)?,
+            ),
+            None => StrftimeFormatter::explain_dialect(&params.format, dialect, &chaos::now()),
+        };
+        json_result(&explanation, params.pretty)
+    }
+
+    /// Build a strftime format from named tokens
+    #[tool(
+        description = "Build a strftime format without knowing strftime: give 'description' ('year-month-day hour:minute with timezone, 24h'), 'tokens' ([\"year\",\"month_name_short\",\"day\",\"hour24\",\"minute\",\"tz_abbr\"]) or an existing 'format' to split back into tokens. Returns the format, its parts in order, unknown tokens with suggestions, and an example rendered at 2006-01-02T15:04:05.123456789Z"
+    )]
+    async fn build_format(
+        &self,
+        Parameters(params): Parameters<BuildFormatParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: build_format");
+        let build = match (params.description, params.tokens, params.format) {
+            (Some(description), None, None) => FormatBuild::from_description(&description),
+            (None, Some(tokens), None) => FormatBuild::from_tokens(&tokens),
+            (None, None, Some(format)) => FormatBuild::decompose(&format)?,
+            _ => {
+                return Err(TimeServerError::InvalidArgument(
+                    "give exactly one of description, tokens or format".to_string(),
+                )
+                .into())
+            }
+        };
+        json_result(&build, params.pretty)
+    }
+
+    /// Parse an RFC 3339 / RFC 9557 timestamp
+    #[tool(
+        description = "Parse an RFC 3339 or RFC 9557 timestamp such as '2024-03-15T10:00:00+09:00[Asia/Tokyo]'. Checks the offset against the annotated zone at that instant; 'conflict' picks offset-wins, zone-wins or reject. Critical annotations ('[!Zone]') cannot be ignored."
+    )]
+    async fn parse_time(
+        &self,
+        Parameters(params): Parameters<ParseTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: parse_time '{}'", params.input);
+        let parsed = parse_rfc9557(&params.input, params.conflict.unwrap_or_default())?;
+        json_result(&parsed, params.pretty)
+    }
+
+    /// Parse or generate an email Date header
+    #[tool(
+        description = "Email Date headers (RFC 5322). mode 'parse' reads input, including obsolete forms real mail carries (comments, zone names like EST, two-digit years, missing seconds), and returns Unix time, the offset, a normalized header and notes on anything non-standard. mode 'generate' returns a compliant header for timestamp (Unix seconds, default now) in timezone (default UTC)."
+    )]
+    async fn email_date(
+        &self,
+        Parameters(params): Parameters<EmailDateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: email_date {:?}", params.mode);
+        match params.mode {
+            EmailDateMode::Parse => {
+                let input = params.input.ok_or_else(|| missing_argument("input"))?;
+                json_result(&parse_email_date(&input)?, params.pretty)
+            }
+            EmailDateMode::Generate => {
+                let at = match params.timestamp {
+                    Some(seconds) => unix::datetime_at(seconds)?,
+                    None => chaos::now(),
+                };
+                let date = match params.timezone.as_deref() {
+                    Some(tz) => format_email_date(&TimezoneConverter::convert_to_tz(at, tz)?),
+                    None => format_email_date(&at),
+                };
+                let mut result = json!({
+                    "date": date,
+                    "header": format!("Date: {}", date),
+                    "unix_seconds": at.timestamp(),
+                });
+                if let Some(tz) = params.timezone {
+                    result["timezone"] = json!(tz);
+                }
+                json_result(&result, params.pretty)
+            }
+        }
+    }
+
+    /// One instant in several timezones
+    #[tool(
+        description = "Show the current time in several IANA timezones at one shared UTC instant, sorted by UTC offset, with the day difference from UTC and a plain-text table"
+    )]
+    async fn world_clock(
+        &self,
+        Parameters(params): Parameters<WorldClockParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: world_clock {:?}", params.timezones);
+        let (now, reference) = self.reference_time(params.as_of.as_ref())?;
+        let clock = world_clock_at(params.timezones.as_deref(), now)?;
+        match params.output_format.unwrap_or_default() {
+            OutputFormat::Json => {}
+            OutputFormat::Csv => return export_result(export::to_csv(&clock.rows)),
+            OutputFormat::Ics => return Err(OutputFormat::not_calendar("world_clock").into()),
+        }
+        let result = json!({
+            "utc": clock.utc,
+            "unix_timestamp": clock.unix_timestamp,
+            "rows": clock.rows,
+            "table": world_clock::render_table(&clock.rows),
+        });
+
+        json_result(&reference.attach(result), params.pretty)
+    }
+
+    /// Everything about one timestamp, as text and as fields
+    #[tool(
+        description = "Describe one timestamp (Unix seconds or RFC 3339) for a person: UTC RFC 3339, how far away it is ('in 3 days', '2 hours ago'), weekday, ISO week, Unix value and the local time in each of 'timezones' (comma-separated, optional). 'text' is a plain-text block ready to show as is; the other fields hold the same data."
+    )]
+    async fn timestamp_card(
+        &self,
+        Parameters(params): Parameters<TimestampCardParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let timestamp = params.timestamp.to_text();
+        debug!("Tool: timestamp_card {}", timestamp);
+        let (now, reference) = self.reference_time(params.as_of.as_ref())?;
+        let card = timestamp_card_at(&timestamp, params.timezones.as_deref(), now)?;
+        let mut result = json!(card);
+        result["text"] = json!(card.render());
+        json_result(&reference.attach(result), params.pretty)
+    }
+
+    /// Render a number of seconds for people
+    #[tool(
+        description = "Render a duration given as a number (seconds by default, or ms/ns via 'unit') in one of these styles: iso8601 'P2DT3H5M', clock '2d 03:05:00', human_long '2 days, 3 hours, 5 minutes' (default), human_short '2d 3h 5m', largest_unit 'about 2 days'. Days are the largest exact unit, so a year is 'P365D'. Negative durations get a leading minus. 'max_units' drops the smaller units; 'include_zero' keeps zero units."
+    )]
+    async fn format_duration(
+        &self,
+        Parameters(params): Parameters<FormatDurationParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: format_duration {}", params.value);
+        let nanos = params.unit.unwrap_or_default().to_nanos(params.value)?;
+        let style = params.style.unwrap_or_default();
+        let formatted = duration::format(
+            nanos,
+            duration::FormatOptions {
+                style,
+                max_units: params.max_units,
+                include_zero: params.include_zero.unwrap_or(false),
+            },
+        );
+        let result = json!({
+            "formatted": formatted,
+            "style": style,
+            "total_seconds": nanos as f64 / 1e9,
+        });
+        json_result(&result, params.pretty)
+    }
+
+    /// Order two instants, or check one against a range
+    #[tool(
+        description = "Compare times given in any mix of representations: Unix seconds or ms, RFC 3339, RFC 2822, or a custom strftime format per value. Each of a, b and c is a number, a string (read automatically: numbers as Unix seconds, text as RFC 3339 then RFC 2822), or {value, representation: unix_seconds|unix_ms|rfc3339|rfc2822|custom, format}. Returns which is earlier and b minus a; with c, whether a lies in the range between b and c under 'bounds'. A value that cannot be read is named in the error."
+    )]
+    async fn compare_times(
+        &self,
+        Parameters(params): Parameters<CompareTimesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: compare_times");
+        let result = compare::compare(
+            &params.a.into(),
+            &params.b.into(),
+            params.c.map(Into::into).as_ref(),
+            params.bounds.unwrap_or_default(),
+            IntervalRules {
+                policy: params.order_policy.unwrap_or_default(),
+                max_years: self.max_interval_years,
+            },
+        )?;
+        json_result(&result, params.pretty)
+    }
+
+    /// IANA timezone at a latitude/longitude, from embedded outlines
+    #[tool(
+        description = "Find the IANA timezone at a latitude/longitude without any network lookup, with its current UTC offset, abbreviation and DST state. Uses coarse embedded outlines: near a border 'candidates' lists the zones on the other side; a point no outline covers (e.g. at sea) gets Etc/GMT±N from its longitude with approximate=true. Needs a build with the 'geo' feature."
+    )]
+    async fn timezone_for_location(
+        &self,
+        Parameters(params): Parameters<LocationParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: timezone_for_location {} {}",
+            params.latitude, params.longitude
+        );
+        let located = crate::time::locate::locate(params.latitude, params.longitude, chaos::now())?;
+        json_result(&located, params.pretty)
+    }
+
+    /// The timezone the host machine itself is set to (read-only)
+    #[tool(
+        description = "Report the timezone the host machine itself is set to, e.g. for correlating its local log files (read-only; this server still answers in UTC). Checks TZ, the /etc/localtime symlink, /etc/timezone and timedatectl in that order and returns the validated IANA name, the source used, the current offset, the host locale and what each source held; determined=false when none gives a zone, as in many containers."
+    )]
+    async fn get_host_timezone(
+        &self,
+        Parameters(params): Parameters<HostTimezoneParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_host_timezone");
+        let now = chaos::now();
+        let host = tokio::task::spawn_blocking(move || host::detect(&HostSources::system(), now))
+            .await
+            .map_err(|e| TimeServerError::ClockError(format!("host lookup failed: {}", e)))??;
+        json_result(&host, params.pretty)
+    }
+
+    /// List UTC offset transitions of a timezone for a year
+    #[tool(
+        description = "List daylight saving / UTC offset transitions of an IANA timezone for a year (defaults to the current year)"
+    )]
+    async fn get_dst_transitions(
+        &self,
+        Parameters(params): Parameters<DstTransitionsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        use chrono::Datelike;
+
+        let timezone = params.timezone;
+        let year = params.year.unwrap_or_else(|| chaos::now().year());
+        debug!("Tool: get_dst_transitions for {} in {}", timezone, year);
+
+        let transitions = TimezoneConverter::dst_transitions(&timezone, year)?;
+        match params.output_format.unwrap_or_default() {
+            OutputFormat::Json => {}
+            OutputFormat::Csv => return export_result(export::to_csv(&transitions)),
+            OutputFormat::Ics => {
+                return export_result(export::to_ics(&transitions, &timezone, chaos::now())?)
+            }
+        }
+
+        let result = json!({
+            "timezone": timezone,
+            "year": year,
+            "has_dst": TimezoneConverter::observes_dst(&timezone, year)?,
+            "count": transitions.len(),
+            "transitions": transitions,
+        });
+
+        // The year's last instant: later transitions are the least certain
+        let year_end = chrono::NaiveDate::from_ymd_opt(year, 12, 31)
+            .and_then(|date| date.and_hms_opt(23, 59, 59))
+            .map(|dt| dt.and_utc())
+            .unwrap_or_else(chaos::now);
+        json_result(&self.with_caveat(result, year_end), params.pretty)
+    }
+
+    /// Piecewise-constant UTC offset of a timezone over a range
+    #[tool(
+        description = "Get the UTC offset of an IANA timezone over a range (up to ten years) as consecutive segments {from_utc, to_utc, offset_seconds, abbreviation, is_dst}; the first segment starts at 'start' and the last ends at 'end'"
+    )]
+    async fn get_offset_timeline(
+        &self,
+        Parameters(params): Parameters<OffsetTimelineParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: get_offset_timeline for {} from {} to {}",
+            params.timezone, params.start, params.end
+        );
+        let seconds = |secs: i64| i128::from(secs) * unix::NANOS_PER_SECOND;
+        let interval = self.interval(
+            (seconds(params.start), seconds(params.end)),
+            ("start", "end"),
+            params.order_policy,
+        )?;
+        let (start, end) = interval.order(params.start, params.end);
+        let segments = TimezoneConverter::offset_timeline(&params.timezone, start, end)?;
+        match params.output_format.unwrap_or_default() {
+            OutputFormat::Json => {}
+            OutputFormat::Csv => return export_result(export::to_csv(&segments)),
+            OutputFormat::Ics => {
+                return export_result(export::to_ics(&segments, &params.timezone, chaos::now())?)
+            }
+        }
+        let mut result = json!({
+            "timezone": params.timezone,
+            "start": start,
+            "end": end,
+            "count": segments.len(),
+            "segments": segments,
+        });
+        if interval.swapped {
+            result["inputs_swapped"] = json!(true);
+        }
+        let warnings = interval.warning(("start", "end")).into_iter().collect();
+        let end = DateTime::from_timestamp(end, 0).unwrap_or_default();
+        json_result(&self.with_caveat_and(result, end, warnings), params.pretty)
+    }
+
+    /// How far apart two timezones are on a date, and when that changes
+    #[tool(
+        description = "Get the UTC offsets of two IANA timezones on a date (default today) and the difference in minutes ('to' minus 'from'), with every change of the difference within 14 days either side as it moves through either zone's DST dates. With 'end_date', returns the difference from 'date' to 'end_date' (at most ten years) as segments of dates instead. Offsets are taken at local noon of each date."
+    )]
+    async fn offset_difference(
+        &self,
+        Parameters(params): Parameters<OffsetDifferenceParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: offset_difference from {} to {} on {:?}",
+            params.from_timezone, params.to_timezone, params.date
+        );
+        let parse_date = |name: &str, text: &str| {
+            text.trim().parse::<chrono::NaiveDate>().map_err(|_| {
+                TimeServerError::InvalidArgument(format!(
+                    "'{}' must be YYYY-MM-DD, got '{}'",
+                    name, text
+                ))
+            })
+        };
+        let date = match &params.date {
+            Some(text) => parse_date("date", text)?,
+            None => {
+                TimezoneConverter::convert_to_tz(chaos::now(), &params.from_timezone)?.date_naive()
+            }
+        };
+        let noon =
+            |date: chrono::NaiveDate| date.and_hms_opt(12, 0, 0).unwrap_or_default().and_utc();
+
+        let Some(end_date) = &params.end_date else {
+            let difference = TimezoneConverter::offset_difference(
+                &params.from_timezone,
+                &params.to_timezone,
+                date,
+            )?;
+            return json_result(&self.with_caveat(difference, noon(date)), params.pretty);
+        };
+        let end_date = parse_date("end_date", end_date)?;
+        let nanos = |date| unix::UnixTime::from_datetime(noon(date)).nanos_since_epoch;
+        let interval = self.interval(
+            (nanos(date), nanos(end_date)),
+            ("date", "end_date"),
+            params.order_policy,
+        )?;
+        let (date, end_date) = interval.order(date, end_date);
+        let segments = TimezoneConverter::offset_difference_range(
+            &params.from_timezone,
+            &params.to_timezone,
+            date,
+            end_date,
+        )?;
+        let mut result = json!({
+            "from_timezone": params.from_timezone,
+            "to_timezone": params.to_timezone,
+            "date": date.format("%Y-%m-%d").to_string(),
+            "end_date": end_date.format("%Y-%m-%d").to_string(),
+            "count": segments.len(),
+            "segments": segments,
+        });
+        if interval.swapped {
+            result["inputs_swapped"] = json!(true);
+        }
+        let warnings = interval.warning(("date", "end_date")).into_iter().collect();
+        json_result(
+            &self.with_caveat_and(result, noon(end_date), warnings),
+            params.pretty,
+        )
+    }
+
+    /// Resolve a local wall-clock time in a timezone to UTC
+    #[tool(
+        description = "Convert a local date-time (no offset) in an IANA timezone to UTC, with policies for ambiguous and nonexistent times around DST transitions. 'explain' adds the steps taken: input, offset rule, any DST decision and the arithmetic."
+    )]
+    async fn local_to_utc(
+        &self,
+        Parameters(params): Parameters<LocalToUtcParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: local_to_utc for {} in {}",
+            params.local_datetime, params.timezone
+        );
+
+        let mut explain = Explain::new(params.explain.unwrap_or(false));
+        let resolution = TimezoneConverter::local_to_utc_explained(
+            &params.local_datetime,
+            &params.timezone,
+            params.ambiguity.unwrap_or_default(),
+            params.nonexistent.unwrap_or_default(),
+            &mut explain,
+        )?;
+
+        let at = DateTime::from_timestamp(resolution.unix_timestamp, 0).unwrap_or_else(chaos::now);
+        let result = Explained {
+            body: resolution,
+            explanation: explain.finish(),
+        };
+        json_result(&self.with_caveat(result, at), params.pretty)
+    }
+
+    /// Resolve a time given in words
+    #[tool(
+        description = "Resolve a time in words relative to a reference (default now) in a timezone (default UTC): offsets ('in 2 hours', '45 minutes ago'), days ('today', 'tomorrow', 'next friday', 'last monday'), times of day ('3pm', '15:30', 'noon', 'midnight') and combinations ('tomorrow at 3pm'). Returns the Unix timestamp, RFC 3339 and a trace of how each word was read. Ambiguous phrases are rejected with an explanation; local times in a DST gap move forward."
+    )]
+    async fn parse_natural_time(
+        &self,
+        Parameters(params): Parameters<ParseNaturalTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: parse_natural_time '{}'", params.input);
+        let reference = match params.reference_timestamp {
+            Some(seconds) => crate::time::unix::datetime_at(seconds)?,
+            None => chaos::now(),
+        };
+        let resolved = natural::parse_natural_time(
+            &params.input,
+            reference,
+            params.timezone.as_deref().unwrap_or("UTC"),
+        )?;
+        json_result(&resolved, params.pretty)
+    }
+
+    /// Common working hours of participants in several timezones
+    #[tool(
+        description = "Find meeting slots on a date where participants in several IANA timezones are all within working hours (default 09:00-17:00 local, per-timezone overrides allowed). Returns the overlapping UTC intervals of at least 'min_minutes', each shown in every participant's local time, or overlap false with the nearest near-miss: the slot with the fewest out-of-hours minutes in total."
+    )]
+    async fn find_overlap(
+        &self,
+        Parameters(params): Parameters<FindOverlapParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: find_overlap for {:?} on {:?}",
+            params.timezones, params.date
+        );
+        let zone = |name: &str| -> Result<chrono_tz::Tz, TimeServerError> {
+            TimezoneConverter::resolve_name(name)
+                .and_then(|name| name.parse().ok())
+                .ok_or_else(|| TimeServerError::invalid_timezone(name))
+        };
+
+        let default_hours = match &params.working_hours {
+            Some(text) => calendar::WorkingHours::parse(text)?,
+            None => calendar::WorkingHours::default(),
+        };
+        let mut overrides = Vec::new();
+        for (name, text) in params.working_hours_by_timezone.iter().flatten() {
+            overrides.push((zone(name)?, calendar::WorkingHours::parse(text)?));
+        }
+        let participants = params
+            .timezones
+            .iter()
+            .map(|name| {
+                let timezone = zone(name)?;
+                let hours = overrides
+                    .iter()
+                    .find(|(tz, _)| *tz == timezone)
+                    .map_or(default_hours, |(_, hours)| *hours);
+                Ok(calendar::Participant { timezone, hours })
+            })
+            .collect::<Result<Vec<_>, TimeServerError>>()?;
+
+        let date = match (&params.date, participants.first()) {
+            (Some(text), _) => text.trim().parse::<chrono::NaiveDate>().map_err(|_| {
+                TimeServerError::InvalidArgument(format!(
+                    "'date' must be YYYY-MM-DD, got '{}'",
+                    text
+                ))
+            })?,
+            (None, Some(first)) => chaos::now().with_timezone(&first.timezone).date_naive(),
+            (None, None) => chaos::now().date_naive(),
+        };
+
+        let result = calendar::find_overlap(
+            &participants,
+            date,
+            params
+                .min_minutes
+                .unwrap_or(calendar::DEFAULT_MIN_SLOT_MINUTES),
+        )?;
+        let at = date.and_time(chrono::NaiveTime::MIN).and_utc();
+        json_result(&self.with_caveat(result, at), params.pretty)
+    }
+
+    /// Whether an instant falls in a time window, by the server's clock
+    #[tool(
+        description = "Check an instant (default now, by the server's clock) against a time window, for guardrails such as 'only during business hours' or 'valid until T'. Give one window: 'start' and 'end', a 'daily' local range such as '09:00-17:00' (overnight ranges allowed), or a 'cron' expression with a 'duration'; 'daily' and 'cron' are local to 'timezone'. Returns inside, seconds_until_change, and the current and next windows. Local windows keep their wall-clock times across DST, so one spanning a spring-forward gap is an hour shorter."
+    )]
+    async fn assert_time_window(
+        &self,
+        Parameters(params): Parameters<AssertTimeWindowParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: assert_time_window daily={:?} cron={:?}",
+            params.daily, params.cron
+        );
+        let instant = |field: &str, value: &NumberOrText| {
+            card::parse_timestamp(&value.to_text())
+                .map_err(|e| TimeServerError::for_value(field, e))
+        };
+        let timezone = || -> Result<chrono_tz::Tz, TimeServerError> {
+            let name = params.timezone.as_deref().unwrap_or("UTC");
+            TimezoneConverter::resolve_name(name)
+                .and_then(|name| name.parse().ok())
+                .ok_or_else(|| TimeServerError::invalid_timezone(name))
+        };
+
+        let mut swapped = None;
+        let spec = match (
+            (&params.start, &params.end),
+            &params.daily,
+            (&params.cron, &params.duration),
+        ) {
+            ((Some(start), Some(end)), None, (None, None)) => {
+                let (start, end) = (instant("start", start)?, instant("end", end)?);
+                let nanos = |at| unix::UnixTime::from_datetime(at).nanos_since_epoch;
+                let interval = self.interval(
+                    (nanos(start), nanos(end)),
+                    ("start", "end"),
+                    params.order_policy,
+                )?;
+                swapped = interval.warning(("start", "end"));
+                let (start, end) = interval.order(start, end);
+                let window = calendar::Window::new(start, end);
+                if window.end <= window.start {
+                    return Err(TimeServerError::InvalidArgument(
+                        "'end' must be after 'start'".into(),
+                    )
+                    .into());
+                }
+                calendar::WindowSpec::Between(window)
+            }
+            ((None, None), Some(daily), (None, None)) => calendar::WindowSpec::Daily {
+                timezone: timezone()?,
+                hours: calendar::WorkingHours::parse(daily)?,
+            },
+            ((None, None), None, (Some(cron), Some(length))) => {
+                let schedule = cron.parse().map_err(|e: crate::time::cron::CronError| {
+                    TimeServerError::InvalidArgument(format!("'cron': {}", e))
+                })?;
+                let nanos = duration::parse_iso8601(length)?;
+                let max = calendar::MAX_RECURRING_WINDOW_DAYS as i128 * 86_400 * 1_000_000_000;
+                if nanos <= 0 || nanos > max {
+                    return Err(TimeServerError::InvalidArgument(format!(
+                        "'duration' must be positive and at most {} days",
+                        calendar::MAX_RECURRING_WINDOW_DAYS
+                    ))
+                    .into());
+                }
+                calendar::WindowSpec::Recurring {
+                    timezone: timezone()?,
+                    schedule,
+                    duration: chrono::Duration::nanoseconds(nanos as i64),
+                }
+            }
+            _ => {
+                return Err(TimeServerError::InvalidArgument(
+                    "give one window: 'start' and 'end', 'daily', or 'cron' with 'duration'".into(),
+                )
+                .into())
+            }
+        };
+
+        let (at, source) = match &params.timestamp {
+            Some(timestamp) => (instant("timestamp", timestamp)?, ReferenceSource::Client),
+            None => (chaos::now(), ReferenceSource::Server),
+        };
+        let mut result = json!(calendar::window_status(&spec, at).assertion(at));
+        if swapped.is_some() {
+            result["inputs_swapped"] = json!(true);
+        }
+        json_result(
+            &self.with_caveat_and(
+                ReferenceTime::new(at, source).attach(result),
+                at,
+                swapped.into_iter().collect(),
+            ),
+            params.pretty,
+        )
+    }
+
+    /// Register a one-shot notification
+    #[tool(
+        description = "Schedule a one-shot alarm. At the requested time the server sends a notifications/message (logger 'scheduler') with the label, requested time and actual fire time. Give either 'at' (RFC 3339) or 'delay_seconds'."
+    )]
+    async fn schedule_notification(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(params): Parameters<ScheduleNotificationParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: schedule_notification '{}'", params.label);
+
+        let now = chaos::now();
+        let fire_at = match (params.at.as_deref(), params.delay_seconds) {
+            (Some(at), None) => chrono::DateTime::parse_from_rfc3339(at)
+                .map(|dt| dt.with_timezone(&chrono::Utc))
+                .map_err(|e| TimeServerError::InvalidTimestamp(format!("'at': {}", e)))?,
+            (None, Some(delay))
+                if delay.is_finite()
+                    && (0.0..=scheduler::MAX_SCHEDULE_AHEAD_SECS as f64).contains(&delay) =>
+            {
+                chrono::Duration::try_milliseconds((delay * 1000.0) as i64)
+                    .and_then(|delay| now.checked_add_signed(delay))
+                    .ok_or_else(|| TimeServerError::out_of_range(format!("now + {}s", delay)))?
+            }
+            (None, Some(_)) => {
+                return Err(TimeServerError::InvalidArgument(format!(
+                    "'delay_seconds' must be a number from 0 to {} ({} days)",
+                    scheduler::MAX_SCHEDULE_AHEAD_SECS,
+                    scheduler::MAX_SCHEDULE_AHEAD_SECS / 86400
+                ))
+                .into())
+            }
+            _ => {
+                return Err(TimeServerError::InvalidArgument(
+                    "Provide exactly one of 'at' or 'delay_seconds'".into(),
+                )
+                .into())
+            }
+        };
+        if fire_at < now {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "'at' is in the past ({})",
+                fire_at.to_rfc3339()
+            ))
+            .into());
+        }
+
+        let entry = self
+            .scheduler
+            .schedule(fire_at, params.label, std::sync::Arc::new(PeerSink(peer)))
+            .map_err(TimeServerError::InvalidArgument)?;
+
+        json_result(&entry, params.pretty)
+    }
+
+    /// List pending scheduled notifications
+    #[tool(
+        description = "List notifications registered with schedule_notification that have not fired yet"
+    )]
+    async fn list_scheduled(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: list_scheduled");
+        let pending = self.scheduler.list();
+        let result = json!({
+            "count": pending.len(),
+            "max_pending": self.scheduler.max_pending(),
+            "scheduled": pending,
+        });
+        json_result(&result, None)
+    }
+
+    /// Cancel a pending scheduled notification
+    #[tool(description = "Cancel a pending notification by id")]
+    async fn cancel_scheduled(
+        &self,
+        Parameters(params): Parameters<CancelScheduledParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: cancel_scheduled {}", params.id);
+        if !self.scheduler.cancel(params.id) {
+            return Err(McpError::invalid_params(
+                format!("No pending notification with id {}", params.id),
+                None,
+            ));
+        }
+        let result = json!({"id": params.id, "cancelled": true});
+        json_result(&result, params.pretty)
+    }
+
+    /// Start or retime this session's heartbeat
+    #[tool(
+        description = "Send this session a heartbeat every interval_seconds (1 to 300): a notifications/message (logger 'heartbeat') with a sequence number, the current UTC time, its source and the NTP sync flag from the last status refresh. One heartbeat per session; subscribing again replaces the interval. Stops with unsubscribe_heartbeat or when the session ends."
+    )]
+    async fn subscribe_heartbeat(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(params): Parameters<SubscribeHeartbeatParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!(
+            "Tool: subscribe_heartbeat every {}s",
+            params.interval_seconds
+        );
+        let interval = self
+            .heartbeat
+            .interval(params.interval_seconds)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let (status, replaced) =
+            self.heartbeat
+                .start(interval, peer, self.state.clone(), self.stats.clone());
+        let result = json!({
+            "subscribed": true,
+            "interval_seconds": status.interval_seconds,
+            "started_at": status.started_at,
+            "replaced": replaced,
+        });
+        json_result(&result, params.pretty)
+    }
+
+    /// Stop this session's heartbeat
+    #[tool(description = "Stop the heartbeat started with subscribe_heartbeat")]
+    async fn unsubscribe_heartbeat(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: unsubscribe_heartbeat");
+        let stopped = self.heartbeat.stop();
+        json_result(&json!({"unsubscribed": stopped}), None)
+    }
+
+    /// Set session defaults
+    #[tool(
+        description = "Set defaults for this session, used when a tool or prompt omits the parameter: timezone (get_time, get_time_formatted, /time, /time_in), format (get_time_formatted, /format_time), precision (get_time) and locale (stored; formats that print names warn with LOCALE_FALLBACK since names are English). Only the fields given are changed; responses that used a default list it in defaults_applied."
+    )]
+    async fn set_preferences(
+        &self,
+        Parameters(params): Parameters<SetPreferencesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: set_preferences");
+        let update = params.preferences.validated()?;
+        let preferences = self.session.update(update);
+        json_result(&preferences, params.pretty)
+    }
+
+    /// Show session defaults
+    #[tool(description = "Get the defaults set for this session with set_preferences")]
+    async fn get_preferences(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_preferences");
+        json_result(&self.session.preferences(), None)
+    }
+
+    /// Remove session defaults
+    #[tool(description = "Clear every default set for this session with set_preferences")]
+    async fn clear_preferences(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: clear_preferences");
+        self.session.clear();
+        json_result(&json!({"cleared": true}), None)
+    }
+
+    /// Get build and runtime information (read-only)
+    #[tool(
+        description = "Get server build and runtime information (read-only): version, git commit, build time, tz database version, active modes (HTTP API, container, NTP backend, auth), start time and host OS/arch"
+    )]
+    async fn get_server_info(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_server_info");
+        let info = crate::info::collect(&self.disabled_tools);
+        json_result(&info, None)
+    }
+
+    /// Uptime, request counts, memory and the last logged error (read-only)
+    #[tool(
+        description = "Process statistics for debugging an integration (read-only): pid, start time and uptime, MCP tool calls since start (total, per tool, and refused by the rate limit), HTTP requests, resident memory (Linux; 'unavailable' elsewhere), live tokio tasks, and the last error the server logged"
+    )]
+    async fn get_process_stats(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_process_stats");
+        let (started_at, uptime) = crate::info::uptime();
+        let memory = match crate::stats::resident_memory_bytes() {
+            Some(rss) => json!({"rss_bytes": rss}),
+            None => json!("unavailable"),
+        };
+        let state = crate::persist::global();
+        let result = json!({
+            "pid": std::process::id(),
+            "started_at": started_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "uptime_seconds": uptime.as_secs_f64(),
+            "mcp_requests": {
+                "total": self.stats.mcp_requests(),
+                "by_tool": self.stats.tool_calls(),
+                "rate_limited": self.stats.rate_limited(),
+            },
+            "http_requests": self.stats.http_requests(),
+            "memory": memory,
+            "tokio_tasks": crate::stats::tokio_tasks(),
+            "heartbeat_tasks": self.stats.heartbeat_tasks(),
+            "last_error": self.stats.recent_errors().pop(),
+            "state_loaded": state.is_some_and(|state| state.loaded()),
+            "state_file_age_seconds": state
+                .and_then(|state| state.file_age())
+                .map(|age| age.as_secs()),
+        });
+        json_result(&result, None)
+    }
+
+    /// Versions of the bundled timezone and leap second data (read-only)
+    #[tool(
+        description = "Report how current the time data is (read-only): the IANA tzdata release compiled in, the leap second table's last entry and expiry date, the crate version and build date, and how many days ahead timezone results start carrying a tzdata_caveat"
+    )]
+    async fn get_data_versions(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_data_versions");
+        let versions = crate::info::data_versions(self.tzdata_caveat_days);
+        json_result(&versions, None)
+    }
+
+    /// Clock resolution and the measured cost of reading the clock (read-only)
+    #[tool(
+        description = "Report the precision this server can actually deliver (read-only): clock_getres for CLOCK_REALTIME and CLOCK_MONOTONIC, the timer slack, and a measurement of back-to-back clock reads (smallest nonzero step, step percentiles, cost of one read in ns). samples sets the reads taken (default 10000)."
+    )]
+    async fn get_clock_resolution(
+        &self,
+        Parameters(params): Parameters<ClockResolutionParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_clock_resolution");
+        let samples = params.samples.unwrap_or(resolution::DEFAULT_SAMPLES);
+        let report = tokio::task::spawn_blocking(move || resolution::clock_resolution(samples))
+            .await
+            .map_err(|e| TimeServerError::ClockError(format!("measurement failed: {}", e)))?;
+        json_result(&report, params.pretty)
+    }
+
+    /// Server-side latency of another tool (read-only, ENABLE_BENCH_TOOL)
+    #[tool(
+        description = "Measure how fast a tool runs inside this server (read-only; needs ENABLE_BENCH_TOOL=true): calls tool with arguments iterations times (default 1000) in-process and returns min/p50/p95/p99/max and mean latency in ns, errors and calls per second. Tools that run ntpq or reach outside the process need allow_external=true."
+    )]
+    async fn run_selftest_benchmark(
+        &self,
+        Parameters(params): Parameters<SelftestBenchmarkParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        use crate::bench::{LatencyRecorder, DEFAULT_ITERATIONS, EXTERNAL_TOOLS, MAX_ITERATIONS};
+
+        debug!(
+            "Tool: run_selftest_benchmark {} x {:?}",
+            params.tool, params.iterations
+        );
+        if !self.bench_tool {
+            return Err(TimeServerError::Unauthorized(
+                "run_selftest_benchmark is disabled; the server enables it with ENABLE_BENCH_TOOL=true"
+                    .into(),
+            )
+            .into());
+        }
+        let tool = params.tool.as_str();
+        if self.disabled_tools.is_disabled(tool) {
+            return Err(TimeServerError::ToolDisabled { tool: tool.into() }.into());
+        }
+        if tool == "run_selftest_benchmark" || !self.tool_router.map.contains_key(tool) {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "cannot benchmark tool '{}'",
+                tool
+            ))
+            .into());
+        }
+        if EXTERNAL_TOOLS.contains(&tool) && params.allow_external != Some(true) {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "{} runs ntpq or reaches outside the process; pass allow_external=true to benchmark it",
+                tool
+            ))
+            .into());
+        }
+
+        let call = |context: RequestContext<RoleServer>| {
+            let request = CallToolRequestParam {
+                name: params.tool.clone().into(),
+                arguments: params.arguments.clone(),
+            };
+            self.tool_router
+                .call(ToolCallContext::new(self, request, context))
+        };
+        // Untimed warm-up; bad arguments fail here rather than in every sample
+        call(context.clone()).await?;
+
+        let iterations = params
+            .iterations
+            .unwrap_or(DEFAULT_ITERATIONS)
+            .clamp(1, MAX_ITERATIONS);
+        let mut recorder = LatencyRecorder::new(iterations);
+        for i in 0..iterations {
+            let _ = recorder.time(call(context.clone())).await;
+            // Most tools finish without awaiting anything; yield so the
+            // call's time budget can still stop the run
+            if i % 256 == 255 {
+                tokio::task::yield_now().await;
+            }
+        }
+        let stats = recorder.finish().expect("at least one iteration");
+        let mut result = json!(stats);
+        result["tool"] = json!(tool);
+        json_result(&result, params.pretty)
+    }
+
+    /// Example calls and responses for each tool (read-only)
+    #[tool(
+        description = "Example calls for each tool (read-only): realistic arguments with the response or error they produce, including invalid timezone and format errors. Pass tool to get one tool's examples. Values are illustrative; the shapes match what the tools return."
+    )]
+    async fn get_examples(
+        &self,
+        Parameters(params): Parameters<GetExamplesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_examples tool={:?}", params.tool);
+        let catalog = crate::mcp::examples::catalog(params.tool.as_deref())?;
+        json_result(&catalog, params.pretty)
+    }
+
+    /// Cross-check the local clock against independent references (read-only)
+    #[tool(
+        description = "Check whether the local clock can be trusted (read-only): CLOCK_TAI vs CLOCK_REALTIME against the leap offset, a fresh temp file's timestamp vs the clock, TZ and /etc/localtime vs UTC, and (with SANITY_HTTP_CHECK=true) an HTTPS Date header vs the clock. Each check reports pass/warn/fail/skip with its raw numbers."
+    )]
+    async fn check_time_sanity(
+        &self,
+        Parameters(params): Parameters<TimeSanityParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: check_time_sanity skip={:?}", params.skip);
+        let report = crate::sanity::run_checks(
+            SanityConfig::global(),
+            params.skip.as_deref().unwrap_or_default(),
+        )
+        .await;
+        json_result(&report, params.pretty)
+    }
+
+    /// Upcoming maintenance windows of this server (read-only)
+    #[tool(
+        description = "List this server's declared maintenance windows (read-only, MAINTENANCE_WINDOW): the next occurrences as start/end intervals in UTC, starting with any window open now. The server keeps serving during a window but may restart; results then carry a MAINTENANCE_ACTIVE warning. count sets how many (default 5). Listed only when a window is configured."
+    )]
+    async fn get_maintenance_window(
+        &self,
+        Parameters(params): Parameters<MaintenanceWindowParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_maintenance_window count={:?}", params.count);
+        // Only routed when a window is configured
+        let Some(window) = &self.maintenance else {
+            return Err(TimeServerError::ToolDisabled {
+                tool: "get_maintenance_window".into(),
+            }
+            .into());
+        };
+        let now = chaos::now();
+        let count = params
+            .count
+            .unwrap_or(DEFAULT_MAINTENANCE_OCCURRENCES)
+            .clamp(1, MAX_MAINTENANCE_OCCURRENCES);
         let result = json!({
-            "nanoseconds": unix_time.nanos_since_epoch,
-            "seconds": unix_time.seconds,
-            "subsec_nanos": unix_time.nanos,
+            "schedule": window.schedule().expression(),
+            "duration_seconds": window.duration_seconds(),
+            "active": window.active_at(now).is_some(),
+            "windows": window.occurrences(now, count),
         });
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&result)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+        json_result(&result, params.pretty)
     }
 
-    /// Get time formatted with strftime format string
+    /// The clock anomaly CHAOS_MODE injects, if any (read-only)
     #[tool(
-        description = "Get time formatted with strftime format string (e.g., '%Y-%m-%d %H:%M:%S')"
+        description = "Report whether chaos mode is on (read-only). CHAOS_MODE is a test-only setting (it also needs ALLOW_CHAOS=true) that makes every time this server reports deliberately wrong: a fixed offset, drift in ppm, gaussian jitter, periodic backward steps or frozen time. When active: mode, anomaly, description, max_regression_ms, the start time it counts from and the current skew_ms; every tool result then carries a CHAOS_ACTIVE warning."
     )]
-    async fn get_time_formatted(
+    async fn get_chaos_status(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_chaos_status");
+        json_result(&chaos::status(), None)
+    }
+
+    /// Signed statement of the current time
+    #[tool(
+        description = "Signed time attestation for audit trails: the Unix time, NTP quality (available, synced, offset_ms, stratum) and a sequence number that only increases, even across restarts, signed with Ed25519. 'canonical' is the exact signed text: compact JSON of ntp, sequence and time with sorted keys. Check it offline with verify_signed_time and the key from get_signing_key."
+    )]
+    async fn get_signed_time(
         &self,
-        Parameters(params): Parameters<FormatParams>,
+        Parameters(params): Parameters<SignedTimeParams>,
     ) -> Result<CallToolResult, McpError> {
-        let format = params.format;
-        debug!("Tool: get_time_formatted with format: {}", format);
-        let response = EnhancedTimeResponse::now();
-        let formatted = response
-            .format_custom(&format)
-            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
-
-        let result = json!({
-            "formatted": formatted,
-            "format": format,
-            "unix_seconds": response.unix.seconds,
-            "unix_nanos": response.unix.nanos,
-        });
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&result)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+        debug!("Tool: get_signed_time");
+        let signer = attestation::global()?;
+        let (now, _, _) = self.current_time(Some(false), None).await?;
+        #[cfg(feature = "ntp")]
+        let ntp = if crate::ntp::NtpSyncedClock::is_container_environment() {
+            attestation::NtpQuality::unavailable()
+        } else {
+            attestation::NtpQuality::from_status(
+                self.state.ntp_clock().get_status_async().await.as_ref(),
+            )
+        };
+        #[cfg(not(feature = "ntp"))]
+        let ntp = attestation::NtpQuality::unavailable();
+        let signed = signer.sign(crate::time::UnixTime::from_datetime(now), ntp)?;
+        json_result(&signed, params.pretty)
     }
 
-    /// Get time in specified timezone
-    #[tool(description = "Get time in specified timezone (IANA name like 'America/New_York')")]
-    async fn get_time_with_timezone(
+    /// Public key used by get_signed_time (read-only)
+    #[tool(
+        description = "Public Ed25519 key that signs get_signed_time attestations (read-only), as lowercase hex. persistent is false when no SIGNING_KEY_FILE is configured and the key lasts only until restart. Also served at /api/signing-key."
+    )]
+    async fn get_signing_key(
         &self,
-        Parameters(params): Parameters<TimezoneParams>,
+        Parameters(params): Parameters<SignedTimeParams>,
     ) -> Result<CallToolResult, McpError> {
-        let timezone = params.timezone;
-        debug!("Tool: get_time_with_timezone for {}", timezone);
-        let response = EnhancedTimeResponse::with_timezone(&timezone)
-            .map_err(|e| McpError::invalid_params(e, None))?;
-
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&response)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+        debug!("Tool: get_signing_key");
+        json_result(&attestation::global()?.key_info(), params.pretty)
     }
 
-    /// List all available IANA timezones
-    #[tool(description = "List all available IANA timezones")]
-    async fn list_timezones(&self) -> Result<CallToolResult, McpError> {
-        debug!("Tool: list_timezones");
-        let timezones = TimezoneConverter::list_timezones();
-        let result = json!({
-            "timezones": timezones,
-            "count": timezones.len(),
-        });
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&result)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+    /// Check a get_signed_time attestation (read-only)
+    #[tool(
+        description = "Verify a get_signed_time attestation (read-only) without trusting this server: rebuilds the canonical text from ntp, sequence and time and checks the Ed25519 signature against public_key (or the key named in the attestation). Returns valid, and a reason when it is not."
+    )]
+    async fn verify_signed_time(
+        &self,
+        Parameters(params): Parameters<VerifySignedTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        debug!("Tool: verify_signed_time");
+        let verification = attestation::verify(&params.attestation, params.public_key.as_deref());
+        json_result(&verification, params.pretty)
     }
 
-    /// Convert timestamp between timezones
-    #[tool(description = "Convert Unix timestamp between timezones")]
-    async fn convert_time(
+    /// RFC 3161 timestamp token from the TSA at TSA_URL
+    #[tool(
+        description = "Get an RFC 3161 timestamp token for a SHA-256 digest (64 hex digits or base64) from the timestamp authority at TSA_URL; needs a build with the tsa feature. Returns the TSA's DER TimeStampResp as timestamp_response_base64 with gen_time, serial_number, policy_oid and hash_algorithm read from it. The server checks that the token answers this digest and nonce but does not verify its signature or certificate chain: signature_verified is always false and verifying is the caller's job (e.g. openssl ts -verify)."
+    )]
+    async fn get_trusted_timestamp(
         &self,
-        Parameters(params): Parameters<ConvertTimeParams>,
+        Parameters(params): Parameters<TrustedTimestampParams>,
     ) -> Result<CallToolResult, McpError> {
-        let timestamp = params.timestamp;
-        let to_timezone = params.to_timezone;
-        let from_timezone = params.from_timezone;
-        let from_tz = from_timezone.as_deref().unwrap_or("UTC");
-        debug!("Tool: convert_time from {} to {}", from_tz, to_timezone);
-
-        use chrono::{Offset, TimeZone, Utc};
-
-        let utc = Utc
-            .timestamp_opt(timestamp, 0)
-            .single()
-            .ok_or_else(|| McpError::invalid_params("Invalid timestamp".to_string(), None))?;
-
-        let converted = TimezoneConverter::convert_to_tz(utc, &to_timezone)
-            .map_err(|e| McpError::invalid_params(e, None))?;
-
-        let result = json!({
-            "original": {
-                "timestamp": timestamp,
-                "timezone": from_tz,
-                "formatted": utc.to_rfc3339(),
-            },
-            "converted": {
-                "timestamp": converted.timestamp(),
-                "timezone": to_timezone,
-                "formatted": converted.to_rfc3339(),
-                "offset": converted.offset().fix().local_minus_utc(),
-            }
-        });
-
-        Ok(CallToolResult::success(vec![Content::text(
-            serde_json::to_string_pretty(&result)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-        )]))
+        debug!("Tool: get_trusted_timestamp");
+        let digest = tsa::parse_digest(&params.digest)?;
+        let stamp = tsa::request(self.tsa_url.as_deref(), &digest).await?;
+        json_result(&stamp, params.pretty)
     }
+}
 
+// NTP, PPS, GPS and PTP tools; absent from builds without the ntp feature
+#[cfg(feature = "ntp")]
+#[tool_router(router = ntp_tool_router)]
+impl TimeServer {
     /// Get NTP synchronization status (read-only) via shared memory interface
     #[tool(
-        description = "Get NTP synchronization status and performance metrics (read-only). Includes hardware clock (PPS) status if available."
+        description = "Get NTP synchronization status and performance metrics (read-only). Includes hardware clock (PPS) status if available, and with MANAGE_NTPD the state of the ntpd the server runs (managed, restarts, last_exit)."
     )]
     async fn get_ntp_status(&self) -> Result<CallToolResult, McpError> {
         debug!("Tool: get_ntp_status (SHM interface)");
 
+        use crate::ntp::platform::{self, StatusBackend};
         use crate::ntp::NtpSyncedClock;
 
+        let backend = StatusBackend::current();
+        if backend == StatusBackend::Unsupported {
+            let mut result = platform::unsupported_status();
+            let health = self.grade(Err(result["message"].as_str().unwrap_or_default()), false);
+            result["health"] = json!(health.status);
+            result["checks"] = json!(health.checks);
+            return json_result(&result, None);
+        }
+
         // In container environments, NTP is not available
         if NtpSyncedClock::is_container_environment() {
+            let assumed = self.state.ntp_clock().get_status_async().await;
+            let health = self.grade(assumed.as_ref().map_err(|_| "NTP not available"), true);
             let result = json!({
                 "available": false,
                 "message": "NTP not available in container environment. Container uses host system time.",
                 "container_mode": true,
                 "synced": false,
-                "shm_interface": "not_available"
+                "shm_interface": "not_available",
+                "health": health.status,
+                "checks": health.checks
             });
-            return Ok(CallToolResult::success(vec![Content::text(
-                serde_json::to_string_pretty(&result)
-                    .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-            )]));
+            let mut warnings = Warnings::new();
+            warnings.push(Warning::ntp_container_assumed());
+            return json_result(
+                &warnings.attach(self.ntp_validity().attach(Self::with_managed_ntpd(result))),
+                None,
+            );
         }
 
-        // Create NTP clock instance with SHM interface
-        let ntp_clock = NtpSyncedClock::new();
+        // Shared NTP clock with the SHM interface attached
+        let ntp_clock = self.state.ntp_clock();
 
         // Check if NTP is available (async)
-        let is_synced = NtpSyncedClock::is_synced_async().await.unwrap_or(false);
+        let is_synced = match NtpSyncedClock::is_synced_async().await {
+            Err(e @ TimeServerError::Busy { .. }) => return Err(e.into()),
+            result => result.unwrap_or(false),
+        };
 
         if !is_synced {
+            self.clock_guard.observe_ntp(false);
+            let reason = "NTP not available or not synchronized";
+            let health = self.grade(Err(reason), false);
             let result = json!({
                 "available": false,
-                "message": "NTP not available or not synchronized",
+                "message": reason,
                 "synced": false,
-                "shm_interface": "not_connected"
+                "shm_interface": "not_connected",
+                "health": health.status,
+                "checks": health.checks
             });
-            return Ok(CallToolResult::success(vec![Content::text(
-                serde_json::to_string_pretty(&result)
-                    .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-            )]));
+            return json_result(
+                &self.ntp_validity().attach(Self::with_managed_ntpd(result)),
+                None,
+            );
         }
 
         // Get detailed NTP status including SHM and PPS info
         match ntp_clock.get_status_async().await {
             Ok(status) => {
-                let result = json!({
-                    "available": true,
-                    "synced": status.synced,
-                    "offset_ms": status.offset_ms,
-                    "stratum": status.stratum,
-                    "precision": status.precision,
-                    "root_delay": status.root_delay,
-                    "root_dispersion": status.root_dispersion,
-                    "shm_valid": status.shm_valid,
-                    "pps_enabled": status.pps_enabled,
-                    "shm_interface": if status.shm_valid { "connected" } else { "disconnected" },
-                    "hardware_clock": if status.pps_enabled { "PPS active" } else { "PPS inactive" },
-                    "health": if status.synced && status.offset_ms.abs() < 100.0 {
-                        "healthy"
-                    } else if status.synced {
-                        "degraded"
-                    } else {
-                        "unhealthy"
-                    }
+                correction::global().record(&status, std::time::Instant::now());
+                self.clock_guard.observe_ntp(status.synced);
+                let health = self.grade(Ok(&status), false);
+                let mut result = json!(status);
+                result["available"] = json!(true);
+                if backend != StatusBackend::Ntpq {
+                    result["backend"] = json!(backend);
+                }
+                result["shm_interface"] = json!(if status.shm_valid {
+                    "connected"
+                } else {
+                    "disconnected"
+                });
+                result["hardware_clock"] = json!(if status.pps_enabled {
+                    "PPS active"
+                } else {
+                    "PPS inactive"
                 });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
+                result["health"] = json!(health.status);
+                result["checks"] = json!(health.checks);
+                json_result(
+                    &self.ntp_validity().attach(Self::with_managed_ntpd(result)),
+                    None,
+                )
             }
             Err(e) => {
+                let health = self.grade(Err(&e.to_string()), false);
                 let result = json!({
                     "available": false,
-                    "error": e,
+                    "error": e.to_string(),
+                    "code": e.code(),
                     "synced": false,
-                    "shm_interface": "error"
+                    "shm_interface": "error",
+                    "health": health.status,
+                    "checks": health.checks
                 });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
+                json_result(
+                    &self.ntp_validity().attach(Self::with_managed_ntpd(result)),
+                    None,
+                )
             }
         }
     }
@@ -304,10 +3207,8 @@ impl TimeServer {
     async fn get_ntp_peers(&self) -> Result<CallToolResult, McpError> {
         debug!("Tool: get_ntp_peers");
 
+        use crate::ntp::query::{NtpQueryBackend, NtpQueryError};
         use crate::ntp::NtpSyncedClock;
-        use std::time::Duration;
-        use tokio::process::Command;
-        use tokio::time::timeout;
 
         // In container environment, return empty peer list
         if NtpSyncedClock::is_container_environment() {
@@ -317,82 +3218,153 @@ impl TimeServer {
                 "peers": [],
                 "container_mode": true
             });
-            return Ok(CallToolResult::success(vec![Content::text(
-                serde_json::to_string_pretty(&result)
-                    .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-            )]));
+            return json_result(&result, None);
         }
 
-        // Execute ntpq -p with timeout to get peer information
-        let result = timeout(
-            Duration::from_secs(2),
-            Command::new("ntpq").args(["-p", "-n"]).output(),
-        )
-        .await;
+        // Queued behind the other NTP queries; a full queue is a busy error
+        let result = match crate::ntp::query::global().ntpq(&["-p", "-n"]).await {
+            Ok(stdout) => json!({
+                "available": true,
+                "peers": stdout.lines().collect::<Vec<_>>(),
+                "raw_output": stdout,
+            }),
+            Err(e @ NtpQueryError::Busy { .. }) => return Err(TimeServerError::from(e).into()),
+            Err(NtpQueryError::Failed(_)) => json!({
+                "available": false,
+                "error": "NTP daemon not available or ntpq command failed"
+            }),
+            Err(e) => json!({
+                "available": false,
+                "error": e.to_string()
+            }),
+        };
+        json_result(&result, None)
+    }
 
-        match result {
-            Ok(Ok(output)) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let result = json!({
-                    "available": true,
-                    "peers": stdout.lines().collect::<Vec<_>>(),
-                    "raw_output": stdout.to_string()
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
-            }
-            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
-                let result = json!({
-                    "available": false,
-                    "error": "ntpq command not found"
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
-            }
-            Err(_) => {
-                let result = json!({
-                    "available": false,
-                    "error": "ntpq command timed out"
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
-            }
-            _ => {
-                let result = json!({
-                    "available": false,
-                    "error": "NTP daemon not available or ntpq command failed"
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
-            }
+    /// Recorded NTP offsets with summary statistics
+    #[tool(
+        description = "NTP status history: samples of offset, stratum and sync state recorded every NTP_HISTORY_INTERVAL_SECS (24 hours kept by default, persisted with NTP_HISTORY_FILE). Returns raw samples, or min/max/mean offset per bucket with 'resolution', plus p50/p95/max absolute offset and percent of time synced. Empty in container mode."
+    )]
+    async fn get_ntp_history(
+        &self,
+        Parameters(params): Parameters<NtpHistoryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        use crate::ntp::history;
+
+        debug!(
+            "Tool: get_ntp_history since {:?} at {:?}s",
+            params.since, params.resolution
+        );
+        let since = params
+            .since
+            .as_deref()
+            .map(|text| history::parse_since(text, chaos::now().timestamp()))
+            .transpose()?;
+        if params.resolution == Some(0) {
+            return Err(TimeServerError::InvalidArgument(
+                "'resolution' must be a positive number of seconds".into(),
+            )
+            .into());
+        }
+
+        let recorder = self.state.ntp_clock().history();
+        let samples = recorder
+            .map(|h| h.since(since.unwrap_or(i64::MIN)))
+            .unwrap_or_default();
+        let config = recorder.map(|h| h.config().clone()).unwrap_or_default();
+        let result = json!({
+            "recording": recorder.is_some() && !crate::ntp::NtpSyncedClock::is_container_environment(),
+            "interval_secs": config.interval_secs,
+            "capacity": config.capacity,
+            "file": config.file,
+            "since": since,
+            "resolution": params.resolution,
+            "summary": history::summarize(&samples),
+            "buckets": params.resolution.map(|secs| history::downsample(&samples, secs)),
+            "samples": params.resolution.is_none().then_some(&samples),
+        });
+        json_result(&result, params.pretty)
+    }
+
+    /// Get PPS device status (read-only)
+    #[tool(
+        description = "Get PPS (pulse-per-second) status (read-only): whether pulses are arriving, last pulse time, pulse count and interval jitter. Enabled with ENABLE_PPS=yes."
+    )]
+    async fn get_pps_status(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_pps_status");
+
+        let status = crate::ntp::pps::global()
+            .map(|pps| pps.status())
+            .unwrap_or_else(crate::ntp::pps::PpsStatus::disabled);
+        json_result(&status, None)
+    }
+
+    /// Get PTP status (read-only)
+    #[tool(
+        description = "Get PTP (Precision Time Protocol, linuxptp) status (read-only): PTP hardware clocks from sysfs, and when pmc is installed ptp4l's master offset, grandmaster presence and port state, plus the PHC-to-system offset from phc_ctl. synced is true for a slave port locked to a grandmaster; check grades the master offset against HEALTH_MAX_OFFSET_MS. notes say what could not be read."
+    )]
+    async fn get_ptp_status(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_ptp_status");
+
+        #[derive(Serialize)]
+        struct Report<'a> {
+            #[serde(flatten)]
+            status: &'a crate::ptp::PtpStatus,
+            check: Option<HealthCheck>,
         }
+
+        let status = crate::ptp::refresh().await;
+        let check = status
+            .available
+            .then(|| crate::health::ptp_check(&status, &self.health));
+        let report = Report {
+            status: &status,
+            check,
+        };
+        json_result(&self.ntp_validity().attach(report), None)
+    }
+
+    /// Get GPS receiver status (read-only)
+    #[tool(
+        description = "Get GPS receiver status (read-only): fix mode (no_fix/2d/3d), satellites used, last GPS time and SHM refclock publishing. Enabled with ENABLE_GPS=yes."
+    )]
+    async fn get_gps_status(&self) -> Result<CallToolResult, McpError> {
+        debug!("Tool: get_gps_status");
+
+        let status = crate::ntp::gps::global()
+            .map(|gps| gps.status())
+            .unwrap_or_else(crate::ntp::gps::GpsStatus::disabled);
+        json_result(&status, None)
     }
 }
 
 // Prompt implementations
+#[cfg(feature = "prompts")]
 #[prompt_router]
 impl TimeServer {
-    /// Get current UTC time
+    /// Get current time (UTC unless a timezone preference is set)
     #[prompt(
         name = "time",
         description = "⏰ Get current UTC time with detailed information"
     )]
-    async fn prompt_time(&self) -> Vec<PromptMessage> {
-        let time_data = EnhancedTimeResponse::now();
+    async fn prompt_time(&self) -> Result<Vec<PromptMessage>, McpError> {
+        let mut applied = Vec::new();
+        let timezone = or_preference(None, self.preferences().timezone, "timezone", &mut applied);
+        let time_data = match &timezone {
+            Some(tz) => EnhancedTimeResponse::with_timezone(tz)?,
+            None => EnhancedTimeResponse::now(),
+        };
+        let response = WithDefaults {
+            body: time_data,
+            defaults_applied: applied,
+        };
         let text = format!(
-            "Here is the current UTC time:\n\n{}",
-            serde_json::to_string_pretty(&time_data).unwrap_or_else(|_| "Error".to_string())
+            "Here is the current {} time:\n\n{}",
+            timezone.as_deref().unwrap_or("UTC"),
+            crate::output::to_json(&response, None).unwrap_or_else(|_| "Error".to_string())
         );
 
-        vec![PromptMessage::new_text(PromptMessageRole::User, text)]
+        Ok(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
     }
 
     /// Get Unix timestamp
@@ -404,7 +3376,7 @@ impl TimeServer {
         let unix_time = UnixTime::now();
         let text = format!(
             "Here is the current Unix timestamp:\n\n{}",
-            serde_json::to_string_pretty(&unix_time).unwrap_or_else(|_| "Error".to_string())
+            crate::output::to_json(&unix_time, None).unwrap_or_else(|_| "Error".to_string())
         );
 
         vec![PromptMessage::new_text(PromptMessageRole::User, text)]
@@ -417,16 +3389,25 @@ impl TimeServer {
     )]
     async fn prompt_time_in(
         &self,
-        Parameters(params): Parameters<TimezoneParams>,
+        Parameters(params): Parameters<TimezonePromptParams>,
     ) -> Result<Vec<PromptMessage>, McpError> {
-        let timezone = params.timezone;
-        let time_data = EnhancedTimeResponse::with_timezone(&timezone)
-            .map_err(|e| McpError::invalid_params(e, None))?;
+        let mut applied = Vec::new();
+        let timezone = or_preference(
+            params.timezone,
+            self.preferences().timezone,
+            "timezone",
+            &mut applied,
+        )
+        .ok_or_else(|| missing_argument("timezone"))?;
+        let response = WithDefaults {
+            body: EnhancedTimeResponse::with_timezone(&timezone)?,
+            defaults_applied: applied,
+        };
 
         let text = format!(
             "Here is the current time in {}:\n\n{}",
             timezone,
-            serde_json::to_string_pretty(&time_data).unwrap_or_else(|_| "Error".to_string())
+            crate::output::to_json(&response, None).unwrap_or_else(|_| "Error".to_string())
         );
 
         Ok(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
@@ -439,24 +3420,94 @@ impl TimeServer {
     )]
     async fn prompt_format_time(
         &self,
-        Parameters(params): Parameters<FormatParams>,
+        Parameters(params): Parameters<FormatPromptParams>,
     ) -> Result<Vec<PromptMessage>, McpError> {
-        let format = params.format;
+        let mut applied = Vec::new();
+        let format = or_preference(
+            params.format,
+            self.preferences().format,
+            "format",
+            &mut applied,
+        )
+        .ok_or_else(|| missing_argument("format"))?;
         let response = EnhancedTimeResponse::now();
-        let formatted = response
-            .format_custom(&format)
-            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
-
-        let result = json!({
-            "formatted": formatted,
-            "format": format,
-            "unix_seconds": response.unix.seconds,
-        });
+        let formatted = response.format_custom(&format, None)?;
+
+        let result = WithDefaults {
+            body: json!({
+                "formatted": formatted,
+                "format": format,
+                "unix_seconds": response.unix.seconds,
+            }),
+            defaults_applied: applied,
+        };
 
         let text = format!(
             "Here is the current time formatted as '{}':\n\n{}",
             format,
-            serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error".to_string())
+            crate::output::to_json(&result, None).unwrap_or_else(|_| "Error".to_string())
+        );
+
+        Ok(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
+    }
+
+    /// Build a format from a description
+    #[prompt(
+        name = "build_format",
+        description = "🧩 Turn a description like 'year-month-day hour:minute with timezone' into a strftime format"
+    )]
+    async fn prompt_build_format(
+        &self,
+        Parameters(params): Parameters<BuildFormatPromptParams>,
+    ) -> Vec<PromptMessage> {
+        let build = FormatBuild::from_description(&params.description);
+        let text = format!(
+            "Here is the strftime format for '{}': {}\n\nExample: {}\n\n{}",
+            params.description,
+            build.format,
+            build.example,
+            crate::output::to_json(&build, None).unwrap_or_else(|_| "Error".to_string())
+        );
+
+        vec![PromptMessage::new_text(PromptMessageRole::User, text)]
+    }
+
+    /// Show a timestamp card
+    #[prompt(
+        name = "card",
+        description = "🪪 Everything about one timestamp: UTC, how far away, weekday, ISO week, Unix value and local times"
+    )]
+    async fn prompt_card(
+        &self,
+        Parameters(params): Parameters<CardPromptParams>,
+    ) -> Result<Vec<PromptMessage>, McpError> {
+        let card = timestamp_card_at(&params.timestamp, params.timezones.as_deref(), chaos::now())?;
+        let text = format!(
+            "Here is the timestamp {} described for a person:\n\n```\n{}```",
+            params.timestamp.trim(),
+            card.render()
+        );
+
+        Ok(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
+    }
+
+    /// Compare several timezones
+    #[prompt(
+        name = "world_clock",
+        description = "🗺️ Compare the current time across several timezones in a table"
+    )]
+    async fn prompt_world_clock(
+        &self,
+        Parameters(params): Parameters<WorldClockPromptParams>,
+    ) -> Result<Vec<PromptMessage>, McpError> {
+        let clock = world_clock_at(params.timezones.as_deref(), chaos::now())?;
+        let text = format!(
+            "Here is the current time in {} timezones, all at the same instant {} (Unix {}). \
+             Day is the local date minus the UTC date.\n\n```\n{}```",
+            clock.rows.len(),
+            clock.utc,
+            clock.unix_timestamp,
+            world_clock::render_table(&clock.rows)
         );
 
         Ok(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
@@ -464,29 +3515,195 @@ impl TimeServer {
 }
 
 // Server handler implementation
-#[tool_handler]
-#[prompt_handler]
+#[cfg_attr(feature = "prompts", prompt_handler)]
 impl ServerHandler for TimeServer {
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let started = std::time::Instant::now();
+        let name = request.name.clone();
+        let arguments = self
+            .audit
+            .as_ref()
+            .and_then(|_| request.arguments.clone().map(serde_json::Value::Object));
+        // Every tool takes `hash`; the tool itself never sees it
+        let hash = content_hash::take_flag(request.arguments.as_mut());
+        let checked = match (
+            self.tool_router.map.get(name.as_ref()),
+            request.arguments.as_mut(),
+        ) {
+            (Some(route), Some(supplied)) => {
+                let known = crate::mcp::params::schema_fields(&route.attr.input_schema);
+                crate::mcp::params::check_arguments(&name, supplied, &known, self.lenient_params)
+            }
+            _ => Ok(()),
+        };
+        let (hash, checked) = match hash {
+            Ok(hash) => (hash, checked),
+            Err(e) => (false, Err(e)),
+        };
+        let client = context
+            .peer
+            .peer_info()
+            .map(|info| info.client_info.clone());
+        let admitted = match &self.rate_limiter {
+            Some(limiter) => limiter.admit(&name),
+            None => Ok(()),
+        };
+        if admitted.is_err() {
+            self.stats.record_rate_limited();
+        } else if self.tool_router.has_route(&name) {
+            // Only known tools, so arbitrary names cannot grow the per-tool map
+            self.stats.record_tool_call(&name);
+        }
+        let tcc = ToolCallContext::new(self, request, context);
+
+        let result = match admitted.and(checked) {
+            Err(e) => Err(e.into()),
+            _ if self.disabled_tools.is_disabled(&name) => Err(TimeServerError::ToolDisabled {
+                tool: name.to_string(),
+            }
+            .into()),
+            Ok(()) => match self.clock_guard.check_tool(&name) {
+                Err(e) => Err(e.into()),
+                Ok(()) => {
+                    timeouts::enforce(&name, self.tool_budget(&name), self.tool_router.call(tcc))
+                        .await
+                }
+            },
+        };
+
+        let result = match (&self.maintenance, result) {
+            (Some(window), Ok(result)) if window.active_at(chaos::now()).is_some() => {
+                Ok(with_warning(
+                    result,
+                    &Warning::new(
+                        WarningCode::MaintenanceActive,
+                        "a maintenance window is open; the server may restart",
+                    ),
+                ))
+            }
+            (_, result) => result,
+        };
+        let result = match (chaos::global(), result) {
+            (Some(chaos), Ok(result)) => Ok(with_warning(
+                result,
+                &Warning::new(
+                    WarningCode::ChaosActive,
+                    format!(
+                        "CHAOS_MODE {} is on; times in this result are deliberately wrong",
+                        chaos.config().anomaly
+                    ),
+                ),
+            )),
+            (_, result) => result,
+        };
+        let result = match result {
+            Ok(result) if hash => Ok(edit_json_objects(result, |body| {
+                content_hash::add(&name, body)
+            })),
+            result => result,
+        };
+
+        if let Some(audit) = &self.audit {
+            let mut event =
+                AuditEvent::new("mcp", name.as_ref(), started).with_arguments(arguments);
+            if let Some(client) = &client {
+                event = event.with_client(&client.name, &client.version);
+            }
+            audit.record(match &result {
+                Err(e) => event.with_error(e.message.as_ref()),
+                Ok(r) if r.is_error == Some(true) => event.with_error("tool returned isError"),
+                Ok(_) => event,
+            });
+        }
+
+        result
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        debug!("client initialized");
+        self.forward_events(context.peer);
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        debug!("logging/setLevel {:?}", request.level);
+        self.log_filter
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .set_level(request.level);
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        debug!(
+            "Completion: {} = {:?}",
+            request.argument.name, request.argument.value
+        );
+        let completion = match request.r#ref {
+            Reference::Prompt(_) => crate::completion::complete_argument(
+                &request.argument.name,
+                &request.argument.value,
+            ),
+            Reference::Resource(_) => Default::default(),
+        };
+        let completion = CompletionInfo::with_pagination(
+            completion.values,
+            Some(completion.total as u32),
+            completion.has_more,
+        )
+        .map_err(|e| McpError::internal_error(e, None))?;
+        Ok(CompleteResult { completion })
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let tools = self
+            .tool_router
+            .list_all()
+            .into_iter()
+            .map(with_argument_examples)
+            .map(with_annotations)
+            .map(with_hash_param)
+            .map(|tool| self.with_budget(tool))
+            .collect();
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+
     fn get_info(&self) -> ServerInfo {
         let ntp_available = Self::is_ntp_available();
-        let instructions = if ntp_available {
-            "MCP UTC Time Server - Provides high-precision time, timezone, and NTP status services.\n\n\
-             Time Tools: get_time, get_unix_time, get_nanos, get_time_formatted, get_time_with_timezone, list_timezones, convert_time\n\
-             NTP Tools: get_ntp_status, get_ntp_peers (hardware/bare-metal only)\n\
-             Prompts: /time, /unix_time, /time_in <timezone>, /format_time <format>".to_string()
+        let mut instructions = String::from(if ntp_available {
+            "MCP UTC Time Server - Provides high-precision time, timezone, and NTP status services.\n\n"
         } else {
-            "MCP UTC Time Server - Provides high-precision time and timezone services.\n\n\
-             Time Tools: get_time, get_unix_time, get_nanos, get_time_formatted, get_time_with_timezone, list_timezones, convert_time\n\
-             Prompts: /time, /unix_time, /time_in <timezone>, /format_time <format>\n\n\
-             Note: Running in container mode. NTP tools not available - container uses host system time.".to_string()
-        };
+            "MCP UTC Time Server - Provides high-precision time and timezone services.\n\n"
+        });
+        instructions.push_str(&self.tool_groups());
+        if cfg!(feature = "prompts") {
+            instructions.push_str("\nPrompts: /time, /unix_time, /time_in <timezone>, /format_time <format>, /build_format <description>, /world_clock <timezones>, /card <timestamp>");
+        }
+        if cfg!(feature = "ntp") && !ntp_available {
+            instructions.push_str("\n\nNote: Running in container mode. The container uses host system time, so the NTP tools have no local daemon to report on.");
+        }
 
+        let capabilities = ServerCapabilities::builder().enable_tools();
+        #[cfg(feature = "prompts")]
+        let capabilities = capabilities.enable_prompts();
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
-            capabilities: ServerCapabilities::builder()
-                .enable_tools()
-                .enable_prompts()
-                .build(),
+            capabilities: capabilities.enable_logging().enable_completions().build(),
             server_info: Implementation {
                 name: "mcp-utc-time-server".into(),
                 version: env!("CARGO_PKG_VERSION").into(),
@@ -497,14 +3714,18 @@ impl ServerHandler for TimeServer {
     }
 }
 
+#[cfg(feature = "http-api")]
+pub use crate::http::run_http_api_server;
+
 /// Backward compatibility alias for run_http_api_server
+#[cfg(feature = "http-api")]
 #[deprecated(since = "0.2.0", note = "Use run_http_api_server instead")]
 pub async fn run_health_server() -> Result<()> {
-    run_http_api_server().await
+    run_http_api_server(&ServerConfig::load()?).await
 }
 
 /// Run the MCP server (STDIO transport)
-pub async fn run() -> Result<()> {
+pub async fn run(config: &ServerConfig) -> Result<()> {
     info!(
         event = "server.start",
         version = env!("CARGO_PKG_VERSION"),
@@ -512,8 +3733,9 @@ pub async fn run() -> Result<()> {
         "MCP UTC Time Server starting"
     );
 
-    let server = TimeServer::new();
+    let server = TimeServer::with_config(config);
     let service = server.serve(stdio()).await?;
+    crate::info::record_mcp_stdio();
 
     info!(
         event = "server.ready",
@@ -523,233 +3745,3 @@ pub async fn run() -> Result<()> {
 
     Ok(())
 }
-
-/// Run HTTP API server for health checks and time queries
-/// This provides a REST API at /health, /api/time, /api/unix, etc.
-pub async fn run_http_api_server() -> Result<()> {
-    use std::net::SocketAddr;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    use tokio::net::TcpListener;
-
-    let port = std::env::var("HTTP_API_PORT")
-        .or_else(|_| std::env::var("HEALTH_PORT")) // Backward compatibility
-        .unwrap_or_else(|_| "3000".into())
-        .parse::<u16>()
-        .unwrap_or(3000);
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let listener = TcpListener::bind(&addr).await?;
-
-    info!(
-        event = "http.server.start",
-        port = port,
-        "HTTP API server listening"
-    );
-
-    let server = TimeServer::new();
-
-    loop {
-        let (mut socket, peer_addr) = listener.accept().await?;
-        let server_clone = server.clone();
-
-        tokio::spawn(async move {
-            let mut buf = vec![0u8; 8192];
-
-            match tokio::time::timeout(std::time::Duration::from_secs(5), socket.read(&mut buf))
-                .await
-            {
-                Ok(Ok(n)) if n > 0 => {
-                    let request = String::from_utf8_lossy(&buf[..n]);
-                    debug!(event = "http.request", peer = %peer_addr, request = %request.lines().next().unwrap_or(""));
-
-                    let response = handle_http_request(&request, &server_clone).await;
-
-                    if let Err(e) = socket.write_all(response.as_bytes()).await {
-                        debug!(event = "http.write_error", error = %e, peer = %peer_addr);
-                    }
-                }
-                Ok(Ok(_)) => {
-                    debug!(event = "http.empty_request", peer = %peer_addr);
-                }
-                Ok(Err(e)) => {
-                    debug!(event = "http.read_error", error = %e, peer = %peer_addr);
-                }
-                Err(_) => {
-                    debug!(event = "http.timeout", peer = %peer_addr);
-                    let response = "HTTP/1.1 408 Request Timeout\r\nConnection: close\r\n\r\n";
-                    let _ = socket.write_all(response.as_bytes()).await;
-                }
-            }
-
-            let _ = socket.shutdown().await;
-        });
-    }
-}
-
-async fn handle_http_request(request: &str, _server: &TimeServer) -> String {
-    use crate::ntp::NtpSyncedClock;
-
-    let lines: Vec<&str> = request.lines().collect();
-    if lines.is_empty() {
-        return "HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n".to_string();
-    }
-
-    let parts: Vec<&str> = lines[0].split_whitespace().collect();
-    if parts.len() < 2 {
-        return "HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n".to_string();
-    }
-
-    let method = parts[0];
-    let path = parts[1];
-
-    match (method, path) {
-        ("GET", "/health") | ("GET", "/") => {
-            let ntp_clock = NtpSyncedClock::new();
-            let ntp_status = match ntp_clock.get_status_async().await {
-                Ok(s) => json!({
-                    "synced": s.synced,
-                    "offset_ms": s.offset_ms,
-                    "stratum": s.stratum,
-                    "shm_valid": s.shm_valid,
-                    "pps_enabled": s.pps_enabled
-                }),
-                Err(_) => json!({"available": false}),
-            };
-
-            let health = json!({
-                "status": "healthy",
-                "version": env!("CARGO_PKG_VERSION"),
-                "service": "mcp-utc-time-server",
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "ntp": ntp_status
-            });
-
-            http_json_response(200, "OK", &health)
-        }
-        ("GET", "/metrics") => {
-            let unix_time = crate::time::UnixTime::now();
-            let metrics = format!(
-                "# HELP mcp_time_seconds Current Unix timestamp\n\
-                 # TYPE mcp_time_seconds gauge\n\
-                 mcp_time_seconds {}\n\
-                 # HELP mcp_time_nanos Current nanoseconds component\n\
-                 # TYPE mcp_time_nanos gauge\n\
-                 mcp_time_nanos {}\n",
-                unix_time.seconds, unix_time.nanos
-            );
-            http_text_response(200, "OK", &metrics, "text/plain")
-        }
-        ("GET", "/api/time") => {
-            let response = EnhancedTimeResponse::now();
-            http_json_response(200, "OK", &response)
-        }
-        ("GET", "/api/unix") => {
-            let unix_time = crate::time::UnixTime::now();
-            http_json_response(200, "OK", &unix_time)
-        }
-        ("GET", "/api/nanos") => {
-            let unix_time = crate::time::UnixTime::now();
-            let result = json!({
-                "nanoseconds": unix_time.nanos_since_epoch,
-                "seconds": unix_time.seconds,
-                "subsec_nanos": unix_time.nanos,
-            });
-            http_json_response(200, "OK", &result)
-        }
-        ("GET", "/api/timezones") => {
-            let timezones = crate::time::TimezoneConverter::list_timezones();
-            let result = json!({
-                "timezones": timezones,
-                "count": timezones.len(),
-            });
-            http_json_response(200, "OK", &result)
-        }
-        ("GET", path) if path.starts_with("/api/time/timezone/") => {
-            let tz = &path[19..]; // Skip "/api/time/timezone/"
-            match EnhancedTimeResponse::with_timezone(tz) {
-                Ok(response) => http_json_response(200, "OK", &response),
-                Err(e) => {
-                    let error = json!({"error": e});
-                    http_json_response(400, "Bad Request", &error)
-                }
-            }
-        }
-        ("GET", "/api/ntp/status") => {
-            let ntp_clock = NtpSyncedClock::new();
-            if NtpSyncedClock::is_container_environment() {
-                let result = json!({
-                    "available": false,
-                    "message": "NTP not available in container environment",
-                    "container_mode": true
-                });
-                http_json_response(200, "OK", &result)
-            } else {
-                match ntp_clock.get_status_async().await {
-                    Ok(status) => {
-                        let result = json!({
-                            "available": true,
-                            "synced": status.synced,
-                            "offset_ms": status.offset_ms,
-                            "stratum": status.stratum,
-                            "precision": status.precision,
-                            "root_delay": status.root_delay,
-                            "root_dispersion": status.root_dispersion,
-                            "shm_valid": status.shm_valid,
-                            "pps_enabled": status.pps_enabled,
-                        });
-                        http_json_response(200, "OK", &result)
-                    }
-                    Err(e) => {
-                        let error = json!({"error": e});
-                        http_json_response(500, "Internal Server Error", &error)
-                    }
-                }
-            }
-        }
-        _ => {
-            let error = json!({
-                "error": "Not Found",
-                "path": path,
-                "available_endpoints": [
-                    "/health",
-                    "/metrics",
-                    "/api/time",
-                    "/api/unix",
-                    "/api/nanos",
-                    "/api/timezones",
-                    "/api/time/timezone/:tz",
-                    "/api/ntp/status"
-                ]
-            });
-            http_json_response(404, "Not Found", &error)
-        }
-    }
-}
-
-fn http_json_response(status: u16, status_text: &str, body: &impl serde::Serialize) -> String {
-    let json = serde_json::to_string_pretty(body).unwrap_or_else(|_| "{}".to_string());
-    let content_length = json.len();
-    format!(
-        "HTTP/1.1 {} {}\r\n\
-         Content-Type: application/json\r\n\
-         Content-Length: {}\r\n\
-         Connection: close\r\n\
-         Access-Control-Allow-Origin: *\r\n\
-         \r\n\
-         {}",
-        status, status_text, content_length, json
-    )
-}
-
-fn http_text_response(status: u16, status_text: &str, body: &str, content_type: &str) -> String {
-    let content_length = body.len();
-    format!(
-        "HTTP/1.1 {} {}\r\n\
-         Content-Type: {}\r\n\
-         Content-Length: {}\r\n\
-         Connection: close\r\n\
-         \r\n\
-         {}",
-        status, status_text, content_type, content_length, body
-    )
-}
@@ -0,0 +1,427 @@
+// Startup report for operators
+//
+// Under a supervisor the log is often all an operator sees of the server, so
+// once initialization is done `main` emits one `server.startup` event that
+// says what actually came up: transports, the addresses the HTTP listeners
+// bound (with the real port when 0 was asked for), auth and key count, which
+// NTP query tool is on PATH, every SHM unit the server tried to attach and
+// why an attach failed, container detection with the signal that triggered
+// it, and the default timezone. `STARTUP_BANNER=pretty` also writes the same
+// facts as a block of aligned lines to stderr.
+//
+// Components record their results as they initialize (SHM attaches are
+// recorded by `NtpShmInterface::new`); the report only reads them.
+
+use crate::config::ServerConfig;
+#[cfg(feature = "http-api")]
+use crate::http::listener::{HttpServer, ListenerConfig};
+use std::collections::BTreeMap;
+#[cfg(not(feature = "http-api"))]
+use std::convert::Infallible as HttpServer;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tracing::info;
+
+/// How the startup report is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BannerStyle {
+    /// The `server.startup` event only
+    #[default]
+    Structured,
+    /// The event plus a human-readable block on stderr
+    Pretty,
+}
+
+impl FromStr for BannerStyle {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "structured" => Ok(Self::Structured),
+            "pretty" => Ok(Self::Pretty),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The NTP query tool found on PATH; ntpq wins when both are installed,
+/// since it is the one the NTP tools run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NtpBackend {
+    Ntpq,
+    Chronyc,
+    None,
+}
+
+impl NtpBackend {
+    /// Look for `ntpq`, then `chronyc`, in the directories of `path`
+    pub fn detect(path: Option<&OsStr>) -> Self {
+        let Some(path) = path else {
+            return Self::None;
+        };
+        let found = |program: &str| {
+            std::env::split_paths(path).any(|dir| is_executable(&dir.join(program)))
+        };
+        if found("ntpq") {
+            Self::Ntpq
+        } else if found("chronyc") {
+            Self::Chronyc
+        } else {
+            Self::None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ntpq => "ntpq",
+            Self::Chronyc => "chronyc",
+            Self::None => "none",
+        }
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// The outcome of attaching one SHM unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShmAttach {
+    pub unit: u8,
+    /// Why the attach failed; `None` when it succeeded
+    pub error: Option<String>,
+}
+
+impl fmt::Display for ShmAttach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            None => write!(f, "unit {} attached", self.unit),
+            Some(error) => write!(f, "unit {} failed ({})", self.unit, error),
+        }
+    }
+}
+
+fn shm_attaches() -> &'static Mutex<BTreeMap<u8, Option<String>>> {
+    static ATTACHES: OnceLock<Mutex<BTreeMap<u8, Option<String>>>> = OnceLock::new();
+    ATTACHES.get_or_init(Mutex::default)
+}
+
+/// Called for every attach of SHM unit `unit`; the latest outcome is kept
+pub fn record_shm_attach<E: fmt::Display>(unit: u8, result: Result<(), E>) {
+    shm_attaches()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(unit, result.err().map(|e| e.to_string()));
+}
+
+/// SHM units tried so far, by unit
+pub fn shm_attach_results() -> Vec<ShmAttach> {
+    shm_attaches()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|(&unit, error)| ShmAttach {
+            unit,
+            error: error.clone(),
+        })
+        .collect()
+}
+
+/// What the server started with
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupReport {
+    pub version: &'static str,
+    /// `stdio`, `http` and `sse` (the event stream served by the HTTP API)
+    pub transports: Vec<&'static str>,
+    /// Bound HTTP listeners; empty when the HTTP API is off
+    #[cfg(feature = "http-api")]
+    pub listeners: Vec<ListenerConfig>,
+    pub auth_enabled: bool,
+    pub api_key_count: usize,
+    pub ntp_backend: NtpBackend,
+    pub shm: Vec<ShmAttach>,
+    pub container_mode: bool,
+    /// `/.dockerenv` or the variable that made `container_mode` true
+    pub container_signal: Option<&'static str>,
+    pub default_timezone: Option<String>,
+}
+
+impl StartupReport {
+    /// The report for `config`, with `http` the running HTTP API if any
+    pub fn collect(config: &ServerConfig, http: Option<&HttpServer>) -> Self {
+        let mut transports = Vec::new();
+        if !config.mode.http_only {
+            transports.push("stdio");
+        }
+        if http.is_some() {
+            transports.extend(["http", "sse"]);
+        }
+        #[cfg(feature = "auth")]
+        let api_key_count = config.auth.api_keys.len();
+        #[cfg(not(feature = "auth"))]
+        let api_key_count = 0;
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            transports,
+            #[cfg(feature = "http-api")]
+            listeners: http.map(|h| h.listeners().to_vec()).unwrap_or_default(),
+            auth_enabled: api_key_count > 0,
+            api_key_count,
+            ntp_backend: NtpBackend::detect(std::env::var_os("PATH").as_deref()),
+            shm: shm_attach_results(),
+            container_mode: config.mode.container,
+            container_signal: config.mode.container_signal,
+            default_timezone: config.default_timezone.clone(),
+        }
+    }
+
+    /// Emit the `server.startup` event, and the banner when `style` asks for it
+    pub fn log(&self, style: BannerStyle) {
+        info!(
+            event = "server.startup",
+            version = self.version,
+            transports = %self.transports.join(","),
+            http = %self.listeners_text(),
+            auth = self.auth_enabled,
+            api_keys = self.api_key_count,
+            ntp_backend = self.ntp_backend.as_str(),
+            shm = %self.shm_text(),
+            container = self.container_mode,
+            container_signal = self.container_signal.unwrap_or("none"),
+            default_timezone = self.default_timezone.as_deref().unwrap_or("UTC"),
+            "Startup report"
+        );
+        if style == BannerStyle::Pretty {
+            eprint!("{}", self.banner());
+        }
+    }
+
+    /// The report as aligned `name: value` lines
+    pub fn banner(&self) -> String {
+        let auth = if self.auth_enabled {
+            let plural = if self.api_key_count == 1 { "" } else { "s" };
+            format!("on ({} API key{})", self.api_key_count, plural)
+        } else {
+            "off".to_string()
+        };
+        let container = match self.container_signal {
+            Some(signal) if self.container_mode => format!("yes ({})", signal),
+            _ => "no".to_string(),
+        };
+        let rows = [
+            ("version", self.version.to_string()),
+            ("transports", self.transports.join(", ")),
+            ("http", self.listeners_text()),
+            ("auth", auth),
+            ("ntp backend", self.ntp_backend.as_str().to_string()),
+            ("shm", self.shm_text()),
+            ("container", container),
+            (
+                "default timezone",
+                self.default_timezone
+                    .as_deref()
+                    .unwrap_or("UTC")
+                    .to_string(),
+            ),
+        ];
+        let mut banner = format!("{} {}\n", env!("CARGO_PKG_NAME"), self.version);
+        for (name, value) in rows {
+            banner.push_str(&format!("  {:<17}{}\n", format!("{}:", name), value));
+        }
+        banner
+    }
+
+    #[cfg(feature = "http-api")]
+    fn listeners_text(&self) -> String {
+        if self.listeners.is_empty() {
+            return "off".to_string();
+        }
+        let listeners: Vec<String> = self.listeners.iter().map(ToString::to_string).collect();
+        listeners.join(", ")
+    }
+
+    #[cfg(not(feature = "http-api"))]
+    fn listeners_text(&self) -> String {
+        "not built".to_string()
+    }
+
+    fn shm_text(&self) -> String {
+        if self.shm.is_empty() {
+            return "none tried".to_string();
+        }
+        let units: Vec<String> = self.shm.iter().map(ToString::to_string).collect();
+        units.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(feature = "http-api", feature = "auth"))]
+    use crate::config::EnvVars;
+    #[cfg(all(feature = "http-api", feature = "auth"))]
+    use crate::http::{start_http_api, HttpSettings};
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Collects what a fmt layer writes
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(all(feature = "http-api", feature = "auth"))]
+    #[tokio::test]
+    async fn test_startup_report_names_what_came_up() {
+        let config = ServerConfig::from_vars(&EnvVars::from_pairs([
+            ("HTTP_API_BIND", "127.0.0.1"),
+            ("HTTP_API_PORT", "0"),
+            ("HTTP_API_ONLY", "true"),
+            ("API_KEY_OPS", "startup-report-key"),
+            ("DEFAULT_TIMEZONE", "Europe/Berlin"),
+            ("SKIP_NTP_CHECK", "1"),
+        ]))
+        .unwrap();
+        let http = start_http_api(&config, HttpSettings::from_config(&config))
+            .await
+            .unwrap();
+        record_shm_attach(3, Err("permission denied"));
+
+        let report = StartupReport::collect(&config, Some(&http));
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone()),
+        );
+        tracing::subscriber::with_default(subscriber, || report.log(BannerStyle::Structured));
+        let output = String::from_utf8(
+            captured
+                .0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+        )
+        .unwrap();
+
+        let port = http.listeners()[0].addr.port();
+        assert_ne!(port, 0);
+        let signal = if Path::new("/.dockerenv").exists() {
+            "/.dockerenv"
+        } else {
+            "SKIP_NTP_CHECK"
+        };
+        for field in [
+            "event=\"server.startup\"".to_string(),
+            format!("version=\"{}\"", env!("CARGO_PKG_VERSION")),
+            "transports=http,sse".to_string(),
+            format!("http=127.0.0.1:{};noauth", port),
+            "auth=true api_keys=1".to_string(),
+            format!(
+                "ntp_backend=\"{}\"",
+                NtpBackend::detect(std::env::var_os("PATH").as_deref()).as_str()
+            ),
+            "unit 3 failed (permission denied)".to_string(),
+            format!("container=true container_signal=\"{}\"", signal),
+            "default_timezone=\"Europe/Berlin\"".to_string(),
+        ] {
+            assert!(output.contains(&field), "{} not in {}", field, output);
+        }
+        assert_eq!(output.lines().count(), 1);
+
+        let banner = report.banner();
+        assert!(banner.contains(&format!("  http:            127.0.0.1:{};noauth\n", port)));
+        assert!(banner.contains("  auth:            on (1 API key)\n"));
+        http.shutdown().await;
+    }
+
+    #[test]
+    fn test_ntp_backend_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let install = |name: &str, mode: u32| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "#!/bin/sh\n").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            }
+        };
+        let path = dir.path().as_os_str();
+
+        assert_eq!(NtpBackend::detect(None), NtpBackend::None);
+        assert_eq!(NtpBackend::detect(Some(path)), NtpBackend::None);
+        install("ntpq", 0o644);
+        assert_eq!(NtpBackend::detect(Some(path)), NtpBackend::None);
+        install("chronyc", 0o755);
+        assert_eq!(NtpBackend::detect(Some(path)), NtpBackend::Chronyc);
+        install("ntpq", 0o755);
+        assert_eq!(NtpBackend::detect(Some(path)), NtpBackend::Ntpq);
+    }
+
+    #[test]
+    fn test_stdio_only_report() {
+        let report = StartupReport::collect(&ServerConfig::default(), None);
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone()),
+        );
+        tracing::subscriber::with_default(subscriber, || report.log(BannerStyle::Structured));
+        let output = String::from_utf8(
+            captured
+                .0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+        )
+        .unwrap();
+
+        assert!(output.contains("transports=stdio "), "{}", output);
+        assert!(output.contains("auth=false api_keys=0"), "{}", output);
+        let http = if cfg!(feature = "http-api") {
+            "off"
+        } else {
+            "not built"
+        };
+        assert!(report
+            .banner()
+            .contains(&format!("  http:            {}\n", http)));
+    }
+
+    #[test]
+    fn test_banner_style_names() {
+        assert_eq!("pretty".parse(), Ok(BannerStyle::Pretty));
+        assert_eq!("Structured".parse(), Ok(BannerStyle::Structured));
+        assert!("fancy".parse::<BannerStyle>().is_err());
+    }
+}
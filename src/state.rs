@@ -0,0 +1,178 @@
+// Read-mostly data shared by every MCP session and HTTP request
+//
+// Built once per process and handed out behind an `Arc`, so hot paths reuse
+// the timezone list, parsed format templates, recently formatted times and
+// the attached NTP SHM segment instead of rebuilding them per call.
+
+use crate::config::ServerConfig;
+#[cfg(feature = "ntp")]
+use crate::ntp::{NtpHistory, NtpSyncedClock};
+use crate::time::source::DEFAULT_TIME_SOURCE_PRIORITY;
+use crate::time::{
+    FormatCache, FormatTemplates, TimeSource, TimeSourceManager, TimezoneConverter,
+    TimezoneListQuery,
+};
+use std::sync::{Arc, OnceLock};
+
+static SHARED: OnceLock<Arc<ServerState>> = OnceLock::new();
+
+pub struct ServerState {
+    timezones: &'static [&'static str],
+    formats: &'static FormatTemplates,
+    /// get_time_formatted formats, parsed once, and this second's renderings
+    format_cache: FormatCache,
+    #[cfg(feature = "ntp")]
+    ntp_clock: Arc<NtpSyncedClock>,
+    /// Current-time sources in priority order; the SHM one shares `ntp_clock`
+    time_sources: TimeSourceManager,
+    /// Unfiltered `list_timezones` result, compact and pretty, serialized on first use
+    timezone_listing: [OnceLock<String>; 2],
+}
+
+impl ServerState {
+    #[cfg(feature = "ntp")]
+    pub fn new() -> Self {
+        Self::with_ntp_clock(Arc::new(NtpSyncedClock::new()))
+    }
+
+    #[cfg(not(feature = "ntp"))]
+    pub fn new() -> Self {
+        Self::build(DEFAULT_TIME_SOURCE_PRIORITY)
+    }
+
+    #[cfg(feature = "ntp")]
+    pub fn with_ntp_clock(ntp_clock: Arc<NtpSyncedClock>) -> Self {
+        Self::build(ntp_clock, DEFAULT_TIME_SOURCE_PRIORITY)
+    }
+
+    #[cfg(feature = "ntp")]
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let history = NtpHistory::new(config.ntp.history.clone());
+        Self::build(
+            Arc::new(
+                NtpSyncedClock::with_shm_retry(config.ntp.shm_retry.clone())
+                    .with_history(Arc::new(history)),
+            ),
+            &config.time_source.priority,
+        )
+    }
+
+    #[cfg(not(feature = "ntp"))]
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self::build(&config.time_source.priority)
+    }
+
+    #[cfg(feature = "ntp")]
+    fn build(ntp_clock: Arc<NtpSyncedClock>, priority: &[TimeSource]) -> Self {
+        Self {
+            timezones: TimezoneConverter::timezone_names(),
+            formats: FormatTemplates::global(),
+            format_cache: FormatCache::default(),
+            time_sources: TimeSourceManager::from_priority(priority, ntp_clock.clone()),
+            ntp_clock,
+            timezone_listing: Default::default(),
+        }
+    }
+
+    #[cfg(not(feature = "ntp"))]
+    fn build(priority: &[TimeSource]) -> Self {
+        Self {
+            timezones: TimezoneConverter::timezone_names(),
+            formats: FormatTemplates::global(),
+            format_cache: FormatCache::default(),
+            time_sources: TimeSourceManager::from_priority(priority),
+            timezone_listing: Default::default(),
+        }
+    }
+
+    /// Build the process-wide state from `config`; call once at startup,
+    /// before the first [`ServerState::shared`]
+    pub fn init(config: &ServerConfig) {
+        let _ = SHARED.set(Arc::new(Self::from_config(config)));
+    }
+
+    /// The process-wide state
+    pub fn shared() -> Arc<Self> {
+        SHARED.get_or_init(|| Arc::new(Self::new())).clone()
+    }
+
+    /// Every IANA name in alphabetical order
+    pub fn timezones(&self) -> &'static [&'static str] {
+        self.timezones
+    }
+
+    pub fn formats(&self) -> &'static FormatTemplates {
+        self.formats
+    }
+
+    pub fn format_cache(&self) -> &FormatCache {
+        &self.format_cache
+    }
+
+    /// NTP clock with the SHM segment attached once
+    #[cfg(feature = "ntp")]
+    pub fn ntp_clock(&self) -> &NtpSyncedClock {
+        &self.ntp_clock
+    }
+
+    /// The clock behind an `Arc`, for tasks that outlive a call
+    #[cfg(feature = "ntp")]
+    pub fn shared_ntp_clock(&self) -> Arc<NtpSyncedClock> {
+        self.ntp_clock.clone()
+    }
+
+    pub fn time_sources(&self) -> &TimeSourceManager {
+        &self.time_sources
+    }
+
+    /// JSON of the unfiltered timezone listing
+    pub fn timezone_listing(&self, pretty: bool) -> &str {
+        self.timezone_listing[pretty as usize].get_or_init(|| {
+            crate::output::to_json(
+                &TimezoneConverter::list_timezones_page(&TimezoneListQuery::default()),
+                Some(pretty),
+            )
+            .unwrap_or_default()
+        })
+    }
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_state_is_reused() {
+        let a = ServerState::shared();
+        let b = ServerState::shared();
+        assert!(Arc::ptr_eq(&a, &b));
+        #[cfg(feature = "ntp")]
+        assert!(std::ptr::eq(a.ntp_clock(), b.ntp_clock()));
+    }
+
+    #[test]
+    fn test_timezone_listing_matches_uncached_page() {
+        let state = ServerState::new();
+        let listing: serde_json::Value =
+            serde_json::from_str(state.timezone_listing(true)).unwrap();
+        assert_eq!(listing["count"], state.timezones().len());
+        assert_eq!(listing["timezones"][0], state.timezones()[0]);
+        assert!(std::ptr::eq(
+            state.timezone_listing(true),
+            state.timezone_listing(true)
+        ));
+
+        let compact = state.timezone_listing(false);
+        assert!(!compact.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(compact).unwrap(),
+            listing
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// Process statistics for get_process_stats
+//
+// Request counters are plain atomics in one process-wide `ServerStats`, so
+// any exporter can read the same numbers without a second bookkeeping path.
+// Per-tool counters are created on a tool's first call and then only
+// incremented under a read lock. `ErrorCapture` is a tracing layer that keeps
+// the last few error-level events, so a client can see what went wrong
+// without access to the server's stderr.
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Error events kept for `recent_errors`
+pub const RECENT_ERRORS: usize = 16;
+
+/// An error-level event captured by [`ErrorCapture`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoggedError {
+    /// RFC 3339, UTC
+    pub at: String,
+    pub target: String,
+    /// The message followed by the event's other fields as `name=value`
+    pub message: String,
+}
+
+/// The counters as saved to `STATE_FILE`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    pub mcp_requests: u64,
+    pub http_requests: u64,
+    pub tools: BTreeMap<String, u64>,
+}
+
+/// Request counters and recent errors of this process
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    mcp_requests: AtomicU64,
+    tools: RwLock<BTreeMap<String, Arc<AtomicU64>>>,
+    http_requests: AtomicU64,
+    rate_limited: AtomicU64,
+    /// Heartbeat tasks running now, across sessions
+    heartbeat_tasks: AtomicU64,
+    errors: Mutex<VecDeque<LoggedError>>,
+}
+
+impl ServerStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one MCP tool call
+    pub fn record_tool_call(&self, tool: &str) {
+        self.mcp_requests.fetch_add(1, Ordering::Relaxed);
+        let counter = self
+            .tools
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(tool)
+            .cloned();
+        let counter = counter.unwrap_or_else(|| {
+            self.tools
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .entry(tool.to_string())
+                .or_default()
+                .clone()
+        });
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count one tool call refused by a session's rate limit
+    pub fn record_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_heartbeat_started(&self) {
+        self.heartbeat_tasks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_heartbeat_stopped(&self) {
+        self.heartbeat_tasks.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn record_http_request(&self) {
+        self.http_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn mcp_requests(&self) -> u64 {
+        self.mcp_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn http_requests(&self) -> u64 {
+        self.http_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn rate_limited(&self) -> u64 {
+        self.rate_limited.load(Ordering::Relaxed)
+    }
+
+    pub fn heartbeat_tasks(&self) -> u64 {
+        self.heartbeat_tasks.load(Ordering::Relaxed)
+    }
+
+    /// Calls per tool, by name
+    pub fn tool_calls(&self) -> BTreeMap<String, u64> {
+        self.tools
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(tool, count)| (tool.clone(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Current counter values, for saving
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            mcp_requests: self.mcp_requests(),
+            http_requests: self.http_requests(),
+            tools: self.tool_calls(),
+        }
+    }
+
+    /// Continue from saved counters: their values are added to the live ones
+    pub fn restore(&self, saved: &StatsSnapshot) {
+        self.mcp_requests
+            .fetch_add(saved.mcp_requests, Ordering::Relaxed);
+        self.http_requests
+            .fetch_add(saved.http_requests, Ordering::Relaxed);
+        let mut tools = self
+            .tools
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (tool, count) in &saved.tools {
+            tools
+                .entry(tool.clone())
+                .or_default()
+                .fetch_add(*count, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_error(&self, error: LoggedError) {
+        let mut errors = self
+            .errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if errors.len() == RECENT_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(error);
+    }
+
+    /// Captured errors, oldest first
+    pub fn recent_errors(&self) -> Vec<LoggedError> {
+        self.errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+/// The process-wide counters
+pub fn global() -> &'static Arc<ServerStats> {
+    static GLOBAL: OnceLock<Arc<ServerStats>> = OnceLock::new();
+    GLOBAL.get_or_init(|| Arc::new(ServerStats::new()))
+}
+
+/// Tracing layer that records error-level events into a [`ServerStats`]
+pub struct ErrorCapture {
+    stats: Arc<ServerStats>,
+}
+
+impl ErrorCapture {
+    pub fn new(stats: Arc<ServerStats>) -> Self {
+        Self { stats }
+    }
+
+    /// Capture into [`global`]
+    pub fn global() -> Self {
+        Self::new(global().clone())
+    }
+}
+
+/// Renders an event's fields as `message name=value ...`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ErrorCapture {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        visitor.message.push_str(&visitor.fields);
+        self.stats.record_error(LoggedError {
+            at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            target: event.metadata().target().to_string(),
+            message: visitor.message.trim_start().to_string(),
+        });
+    }
+}
+
+/// Resident set size in bytes, from `/proc/self/statm`; `None` where that
+/// file does not exist
+#[cfg(unix)]
+pub fn resident_memory_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(page_size).ok().map(|size| pages * size)
+}
+
+#[cfg(not(unix))]
+pub fn resident_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Tasks alive on the current tokio runtime; `None` outside one
+pub fn tokio_tasks() -> Option<usize> {
+    tokio::runtime::Handle::try_current()
+        .ok()
+        .map(|handle| handle.metrics().num_alive_tasks())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_tool_counters() {
+        let stats = ServerStats::new();
+        stats.record_tool_call("get_time");
+        stats.record_tool_call("get_time");
+        stats.record_tool_call("list_timezones");
+        stats.record_http_request();
+        assert_eq!(stats.mcp_requests(), 3);
+        assert_eq!(stats.http_requests(), 1);
+        assert_eq!(stats.tool_calls()["get_time"], 2);
+        assert_eq!(stats.tool_calls()["list_timezones"], 1);
+    }
+
+    #[test]
+    fn test_error_layer_keeps_the_latest_errors() {
+        let stats = Arc::new(ServerStats::new());
+        let subscriber = tracing_subscriber::registry().with(ErrorCapture::new(stats.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("not an error");
+            for i in 0..RECENT_ERRORS + 2 {
+                tracing::error!(attempt = i, "ntpq failed");
+            }
+        });
+        let errors = stats.recent_errors();
+        assert_eq!(errors.len(), RECENT_ERRORS);
+        let last = errors.last().unwrap();
+        assert_eq!(
+            last.message,
+            format!("ntpq failed attempt={}", RECENT_ERRORS + 1)
+        );
+        assert_eq!(last.target, module_path!());
+        assert_eq!(errors[0].message, "ntpq failed attempt=2");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_resident_memory_is_positive() {
+        assert!(resident_memory_bytes().unwrap() > 0);
+    }
+}
@@ -0,0 +1,267 @@
+// Reverse lookup of timezone abbreviations
+//
+// "3pm CST" names an offset only through the abbreviation, and several are
+// used by unrelated zones. The lookup formats the offset name of every zone in
+// TZ_VARIANTS at noon UTC of the requested date and groups the zones that use
+// the abbreviation by offset. That is ~600 evaluations, so results are cached
+// per (abbreviation, date). Abbreviations the tz database does not resolve on
+// its own (CST, IST, BST) also list every meaning from a fixed table.
+
+use crate::error::TimeServerError;
+use chrono::{DateTime, FixedOffset, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, TZ_VARIANTS};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Zones listed per offset; `zone_count` has the full number
+pub const MAX_REPRESENTATIVE_ZONES: usize = 10;
+
+/// Cached (abbreviation, date) scans kept before the cache is cleared
+const MAX_CACHED_LOOKUPS: usize = 1024;
+
+type LookupCache = Mutex<HashMap<(String, NaiveDate), Arc<AbbreviationLookup>>>;
+
+/// Meanings of abbreviations that are ambiguous in everyday use:
+/// (abbreviation, meaning, UTC offset in seconds)
+pub const AMBIGUOUS_ABBREVIATIONS: [(&str, &str, i32); 9] = [
+    ("CST", "China Standard Time", 8 * 3600),
+    ("CST", "Central Standard Time (North America)", -6 * 3600),
+    ("CST", "Cuba Standard Time", -5 * 3600),
+    ("IST", "India Standard Time", 5 * 3600 + 1800),
+    ("IST", "Irish Standard Time", 3600),
+    ("IST", "Israel Standard Time", 2 * 3600),
+    ("BST", "British Summer Time", 3600),
+    ("BST", "Bangladesh Standard Time", 6 * 3600),
+    ("BST", "Bougainville Standard Time", 11 * 3600),
+];
+
+/// Zones using the abbreviation with one UTC offset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AbbreviationOffset {
+    pub offset_seconds: i32,
+    /// "+05:30"
+    pub utc_offset: String,
+    pub is_dst: bool,
+    pub zone_count: usize,
+    /// Up to [`MAX_REPRESENTATIVE_ZONES`], region/city names first
+    pub zones: Vec<String>,
+}
+
+/// One entry of [`AMBIGUOUS_ABBREVIATIONS`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AbbreviationMeaning {
+    pub name: String,
+    pub offset_seconds: i32,
+    pub utc_offset: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AbbreviationLookup {
+    /// Spelled as in the tz database when found ("ChST"), else upper-cased
+    pub abbreviation: String,
+    /// Date the zones were evaluated on (YYYY-MM-DD, at 12:00 UTC)
+    pub date: String,
+    /// Exactly one UTC offset across the matching zones and known meanings
+    pub unique: bool,
+    /// Sorted by offset; empty when no zone uses the abbreviation that day
+    pub offsets: Vec<AbbreviationOffset>,
+    /// Every known meaning, for abbreviations that are ambiguous in use
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub meanings: Vec<AbbreviationMeaning>,
+}
+
+/// Zones using `abbreviation` (case-insensitive) on the UTC date of `at`
+pub fn lookup_abbreviation(
+    abbreviation: &str,
+    at: DateTime<Utc>,
+) -> Result<Arc<AbbreviationLookup>, TimeServerError> {
+    let key = abbreviation.trim().to_uppercase();
+    if key.is_empty() {
+        return Err(TimeServerError::InvalidArgument(
+            "abbreviation must not be empty".to_string(),
+        ));
+    }
+    let date = at.date_naive();
+
+    static CACHE: OnceLock<LookupCache> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(hit) = lock(cache).get(&(key.clone(), date)) {
+        return Ok(hit.clone());
+    }
+
+    let lookup = Arc::new(scan(&key, date));
+    let mut cache = lock(cache);
+    if cache.len() >= MAX_CACHED_LOOKUPS {
+        cache.clear();
+    }
+    cache.insert((key, date), lookup.clone());
+    Ok(lookup)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn scan(key: &str, date: NaiveDate) -> AbbreviationLookup {
+    let noon = date.and_hms_opt(12, 0, 0).unwrap_or_default().and_utc();
+
+    let mut spelling = None;
+    let mut groups: BTreeMap<(i32, bool), Vec<&'static str>> = BTreeMap::new();
+    for tz in TZ_VARIANTS.iter() {
+        let offset = tz.offset_from_utc_datetime(&noon.naive_utc());
+        let name = offset.abbreviation();
+        if !name.eq_ignore_ascii_case(key) {
+            continue;
+        }
+        spelling.get_or_insert_with(|| name.to_string());
+        groups
+            .entry((
+                offset.fix().local_minus_utc(),
+                !offset.dst_offset().is_zero(),
+            ))
+            .or_default()
+            .push(tz.name());
+    }
+
+    let offsets: Vec<AbbreviationOffset> = groups
+        .into_iter()
+        .map(|((offset_seconds, is_dst), mut zones)| {
+            zones.sort_by_key(|name| (is_legacy_name(name), *name));
+            AbbreviationOffset {
+                offset_seconds,
+                utc_offset: utc_offset(offset_seconds),
+                is_dst,
+                zone_count: zones.len(),
+                zones: zones
+                    .into_iter()
+                    .take(MAX_REPRESENTATIVE_ZONES)
+                    .map(str::to_string)
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let meanings: Vec<AbbreviationMeaning> = AMBIGUOUS_ABBREVIATIONS
+        .iter()
+        .filter(|(abbreviation, _, _)| *abbreviation == key)
+        .map(|&(_, name, offset_seconds)| AbbreviationMeaning {
+            name: name.to_string(),
+            offset_seconds,
+            utc_offset: utc_offset(offset_seconds),
+        })
+        .collect();
+
+    let mut distinct: Vec<i32> = offsets
+        .iter()
+        .map(|o| o.offset_seconds)
+        .chain(meanings.iter().map(|m| m.offset_seconds))
+        .collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+
+    AbbreviationLookup {
+        abbreviation: spelling.unwrap_or_else(|| key.to_string()),
+        date: date.to_string(),
+        unique: distinct.len() == 1,
+        offsets,
+        meanings,
+    }
+}
+
+/// Backward-compatible link names ("US/Central", "Etc/GMT+6", "EST5EDT")
+fn is_legacy_name(name: &str) -> bool {
+    const LEGACY_REGIONS: [&str; 7] = [
+        "Brazil", "Canada", "Chile", "Etc", "Mexico", "SystemV", "US",
+    ];
+    match name.split_once('/') {
+        Some((region, _)) => LEGACY_REGIONS.contains(&region),
+        None => true,
+    }
+}
+
+fn utc_offset(seconds: i32) -> String {
+    FixedOffset::east_opt(seconds)
+        .map(|offset| offset.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> DateTime<Utc> {
+        format!("{}T00:00:00Z", date).parse().unwrap()
+    }
+
+    #[test]
+    fn test_jst_is_unique() {
+        let lookup = lookup_abbreviation("jst", at("2025-01-15")).unwrap();
+        assert_eq!(lookup.abbreviation, "JST");
+        assert!(lookup.unique);
+        assert_eq!(lookup.offsets.len(), 1);
+        assert_eq!(lookup.offsets[0].utc_offset, "+09:00");
+        assert_eq!(lookup.offsets[0].zones[0], "Asia/Tokyo");
+        assert!(lookup.meanings.is_empty());
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_cst_lists_china_us_and_cuba() {
+        let lookup = lookup_abbreviation("CST", at("2025-01-15")).unwrap();
+        assert!(!lookup.unique);
+        let names: Vec<&str> = lookup.meanings.iter().map(|m| m.name.as_str()).collect();
+        for meaning in ["China", "North America", "Cuba"] {
+            assert!(names.iter().any(|n| n.contains(meaning)), "{:?}", names);
+        }
+        // In January all three are in use at once
+        let zones: Vec<&str> = lookup
+            .offsets
+            .iter()
+            .flat_map(|o| o.zones.iter().map(String::as_str))
+            .collect();
+        for zone in ["Asia/Shanghai", "America/Chicago", "America/Havana"] {
+            assert!(zones.contains(&zone), "{:?}", zones);
+        }
+    }
+
+    #[test]
+    fn test_unknown_abbreviation_is_empty() {
+        let lookup = lookup_abbreviation("XYZ", at("2025-01-15")).unwrap();
+        assert!(lookup.offsets.is_empty());
+        assert!(lookup.meanings.is_empty());
+        assert!(!lookup.unique);
+        assert!(lookup_abbreviation("  ", at("2025-01-15")).is_err());
+    }
+
+    #[test]
+    fn test_historical_lookup_follows_rule_changes() {
+        // Russia dropped summer time in 2011 and moved MSK back to +03 in 2014
+        let summer_2010 = lookup_abbreviation("MSD", at("2010-07-01")).unwrap();
+        assert_eq!(summer_2010.offsets[0].utc_offset, "+04:00");
+        assert!(summer_2010.offsets[0].is_dst);
+        assert!(summer_2010.offsets[0]
+            .zones
+            .contains(&"Europe/Moscow".to_string()));
+        assert!(lookup_abbreviation("MSD", at("2020-07-01"))
+            .unwrap()
+            .offsets
+            .is_empty());
+
+        let msk_2012 = lookup_abbreviation("MSK", at("2012-07-01")).unwrap();
+        let msk_2020 = lookup_abbreviation("MSK", at("2020-07-01")).unwrap();
+        assert_eq!(msk_2012.offsets[0].utc_offset, "+04:00");
+        assert_eq!(msk_2020.offsets[0].utc_offset, "+03:00");
+    }
+
+    #[test]
+    fn test_lookups_are_cached_per_date() {
+        let first = lookup_abbreviation("CET", at("2024-02-01")).unwrap();
+        let again = lookup_abbreviation("cet", at("2024-02-01")).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        let other_day = lookup_abbreviation("CET", at("2024-02-02")).unwrap();
+        assert!(!Arc::ptr_eq(&first, &other_day));
+    }
+}
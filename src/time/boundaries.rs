@@ -0,0 +1,698 @@
+// Coarse timezone boundaries for `locate`
+//
+// Hand-simplified outlines of the most populated timezones, a few dozen
+// vertices each, as (longitude, latitude) in degrees. Neighbouring zones
+// share their border vertices so the outlines meet without gaps. Borders are
+// off by tens of kilometres in places and small zones are left out; points
+// that no outline covers get a nautical zone from `locate`.
+
+/// Zone name and its outlines
+pub type ZoneOutline = (&'static str, &'static [&'static [(f64, f64)]]);
+
+pub const ZONES: &[ZoneOutline] = &[
+    // North America
+    (
+        "America/Los_Angeles",
+        &[&[
+            (-125.0, 32.5),
+            (-117.1, 32.5),
+            (-114.7, 32.5),
+            (-114.6, 35.0),
+            (-114.0, 36.0),
+            (-114.0, 42.0),
+            (-117.0, 42.0),
+            (-117.0, 49.0),
+            (-125.0, 49.0),
+        ]],
+    ),
+    (
+        "America/Phoenix",
+        &[&[
+            (-114.7, 32.5),
+            (-114.8, 31.3),
+            (-109.0, 31.3),
+            (-109.0, 37.0),
+            (-114.0, 37.0),
+            (-114.0, 36.0),
+            (-114.6, 35.0),
+        ]],
+    ),
+    (
+        "America/Denver",
+        &[&[
+            (-117.0, 42.0),
+            (-114.0, 42.0),
+            (-114.0, 37.0),
+            (-109.0, 37.0),
+            (-109.0, 31.3),
+            (-106.5, 31.8),
+            (-103.0, 32.0),
+            (-103.0, 37.0),
+            (-102.0, 37.0),
+            (-102.0, 41.0),
+            (-104.0, 41.0),
+            (-104.0, 49.0),
+            (-117.0, 49.0),
+        ]],
+    ),
+    (
+        "America/Chicago",
+        &[&[
+            (-104.0, 49.0),
+            (-104.0, 41.0),
+            (-102.0, 41.0),
+            (-102.0, 37.0),
+            (-103.0, 37.0),
+            (-103.0, 32.0),
+            (-106.5, 31.8),
+            (-104.0, 29.5),
+            (-97.5, 26.0),
+            (-89.0, 29.0),
+            (-85.0, 30.5),
+            (-87.5, 35.0),
+            (-86.5, 38.0),
+            (-87.5, 41.7),
+            (-86.5, 46.0),
+            (-89.5, 48.0),
+            (-95.0, 49.0),
+        ]],
+    ),
+    (
+        "America/New_York",
+        &[&[
+            (-86.5, 46.0),
+            (-87.5, 41.7),
+            (-86.5, 38.0),
+            (-87.5, 35.0),
+            (-85.0, 30.5),
+            (-80.0, 24.5),
+            (-75.0, 35.0),
+            (-70.0, 41.0),
+            (-67.0, 44.5),
+            (-67.8, 47.1),
+            (-71.0, 45.0),
+            (-75.0, 45.0),
+            (-79.0, 43.5),
+            (-83.0, 42.0),
+            (-82.5, 45.5),
+            (-84.5, 46.5),
+        ]],
+    ),
+    (
+        "America/Toronto",
+        &[&[
+            (-67.8, 47.1),
+            (-64.5, 49.0),
+            (-70.0, 49.0),
+            (-79.5, 51.0),
+            (-89.5, 48.0),
+            (-86.5, 46.0),
+            (-84.5, 46.5),
+            (-82.5, 45.5),
+            (-83.0, 42.0),
+            (-79.0, 43.5),
+            (-75.0, 45.0),
+            (-71.0, 45.0),
+        ]],
+    ),
+    (
+        "America/Mexico_City",
+        &[&[
+            (-106.5, 31.8),
+            (-104.0, 29.5),
+            (-97.5, 26.0),
+            (-97.2, 22.0),
+            (-96.0, 19.0),
+            (-94.5, 18.2),
+            (-90.5, 21.0),
+            (-87.0, 21.5),
+            (-88.3, 18.5),
+            (-92.0, 14.5),
+            (-97.0, 15.8),
+            (-105.5, 20.5),
+            (-105.7, 22.8),
+            (-108.5, 26.5),
+            (-109.0, 31.3),
+        ]],
+    ),
+    // South America
+    (
+        "America/Bogota",
+        &[&[
+            (-77.3, 8.6),
+            (-71.0, 12.3),
+            (-72.5, 7.0),
+            (-67.5, 6.2),
+            (-67.0, 1.2),
+            (-69.9, -4.2),
+            (-70.2, -0.1),
+            (-75.5, 0.1),
+            (-78.9, 1.5),
+        ]],
+    ),
+    (
+        "America/Sao_Paulo",
+        &[&[
+            (-53.5, -33.7),
+            (-48.5, -28.5),
+            (-41.0, -22.9),
+            (-39.0, -17.5),
+            (-39.0, -13.0),
+            (-46.0, -13.0),
+            (-50.0, -16.0),
+            (-51.0, -19.5),
+            (-54.2, -22.5),
+            (-54.6, -25.6),
+            (-57.6, -30.2),
+        ]],
+    ),
+    (
+        "America/Argentina/Buenos_Aires",
+        &[&[
+            (-57.6, -30.2),
+            (-54.6, -25.6),
+            (-58.3, -24.1),
+            (-62.8, -22.0),
+            (-65.8, -22.0),
+            (-68.6, -27.0),
+            (-70.2, -36.0),
+            (-71.7, -44.0),
+            (-72.3, -51.7),
+            (-68.3, -52.3),
+            (-65.7, -47.7),
+            (-65.0, -42.0),
+            (-62.0, -39.0),
+            (-57.0, -36.3),
+            (-58.4, -33.5),
+        ]],
+    ),
+    // Europe
+    (
+        "Europe/London",
+        &[&[
+            (-6.0, 49.8),
+            (1.8, 50.9),
+            (1.8, 53.0),
+            (-1.5, 55.8),
+            (-1.8, 58.7),
+            (-5.5, 58.7),
+            (-6.5, 56.0),
+            (-5.0, 54.5),
+            (-3.5, 54.5),
+            (-5.0, 51.5),
+        ]],
+    ),
+    (
+        "Europe/Dublin",
+        &[&[
+            (-10.5, 51.4),
+            (-6.0, 52.0),
+            (-6.0, 54.0),
+            (-7.5, 55.4),
+            (-10.0, 54.5),
+        ]],
+    ),
+    (
+        "Europe/Lisbon",
+        &[&[
+            (-9.5, 37.0),
+            (-7.4, 37.2),
+            (-7.0, 39.0),
+            (-8.2, 42.1),
+            (-8.9, 42.1),
+            (-9.5, 38.8),
+        ]],
+    ),
+    (
+        "Europe/Madrid",
+        &[&[
+            (-7.4, 37.2),
+            (-6.0, 36.0),
+            (-2.0, 36.7),
+            (0.0, 38.8),
+            (3.2, 42.4),
+            (-1.8, 43.4),
+            (-8.2, 43.7),
+            (-9.3, 43.0),
+            (-8.9, 42.1),
+            (-8.2, 42.1),
+            (-7.0, 39.0),
+        ]],
+    ),
+    (
+        "Europe/Paris",
+        &[&[
+            (-1.8, 43.4),
+            (3.2, 42.4),
+            (7.5, 43.8),
+            (7.0, 45.9),
+            (6.0, 46.2),
+            (7.6, 47.6),
+            (8.2, 48.97),
+            (6.4, 49.5),
+            (4.8, 50.0),
+            (2.5, 51.1),
+            (-1.8, 49.7),
+            (-4.8, 48.5),
+            (-2.5, 47.3),
+        ]],
+    ),
+    (
+        "Europe/Brussels",
+        &[&[
+            (2.5, 51.1),
+            (4.8, 50.0),
+            (6.4, 49.5),
+            (6.0, 50.7),
+            (5.9, 51.2),
+            (3.4, 51.4),
+        ]],
+    ),
+    (
+        "Europe/Amsterdam",
+        &[&[
+            (3.4, 51.4),
+            (5.9, 51.2),
+            (6.0, 51.9),
+            (7.0, 53.3),
+            (4.8, 53.2),
+        ]],
+    ),
+    (
+        "Europe/Berlin",
+        &[&[
+            (6.4, 49.5),
+            (8.2, 48.97),
+            (7.6, 47.6),
+            (10.0, 47.5),
+            (13.0, 47.5),
+            (13.8, 48.7),
+            (12.1, 50.3),
+            (15.0, 51.0),
+            (14.2, 53.9),
+            (11.0, 54.0),
+            (8.7, 54.9),
+            (8.6, 53.5),
+            (7.0, 53.3),
+            (6.0, 51.9),
+            (5.9, 51.2),
+            (6.0, 50.7),
+        ]],
+    ),
+    (
+        "Europe/Zurich",
+        &[&[
+            (6.0, 46.2),
+            (7.0, 45.9),
+            (8.5, 46.5),
+            (10.5, 46.8),
+            (9.5, 47.5),
+            (7.6, 47.6),
+        ]],
+    ),
+    (
+        "Europe/Rome",
+        &[&[
+            (7.0, 45.9),
+            (7.5, 43.8),
+            (8.5, 44.3),
+            (10.5, 42.9),
+            (15.6, 38.0),
+            (16.0, 38.2),
+            (18.5, 40.1),
+            (12.3, 44.5),
+            (13.7, 45.7),
+            (12.5, 46.8),
+            (10.5, 46.8),
+            (8.5, 46.5),
+        ]],
+    ),
+    (
+        "Europe/Vienna",
+        &[&[
+            (10.0, 47.5),
+            (9.5, 47.5),
+            (10.5, 46.8),
+            (12.5, 46.8),
+            (14.5, 46.4),
+            (16.5, 46.8),
+            (17.0, 48.0),
+            (16.9, 48.7),
+            (15.0, 49.0),
+            (13.8, 48.7),
+            (13.0, 47.5),
+        ]],
+    ),
+    (
+        "Europe/Prague",
+        &[&[
+            (12.1, 50.3),
+            (13.8, 48.7),
+            (15.0, 49.0),
+            (16.9, 48.7),
+            (18.8, 49.5),
+            (15.0, 51.0),
+        ]],
+    ),
+    (
+        "Europe/Warsaw",
+        &[&[
+            (14.2, 53.9),
+            (15.0, 51.0),
+            (18.8, 49.5),
+            (22.8, 49.0),
+            (24.1, 50.8),
+            (23.5, 52.3),
+            (23.6, 54.0),
+            (19.5, 54.4),
+        ]],
+    ),
+    (
+        "Europe/Kyiv",
+        &[&[
+            (24.1, 50.8),
+            (22.8, 49.0),
+            (22.2, 48.4),
+            (26.5, 48.2),
+            (28.2, 46.5),
+            (30.0, 45.3),
+            (33.0, 46.0),
+            (35.0, 46.5),
+            (38.0, 47.0),
+            (40.0, 49.6),
+            (35.0, 52.3),
+            (32.0, 52.3),
+            (30.5, 51.5),
+            (26.0, 51.9),
+        ]],
+    ),
+    (
+        "Europe/Moscow",
+        &[&[
+            (27.5, 56.0),
+            (28.0, 59.5),
+            (30.0, 61.0),
+            (28.0, 69.0),
+            (33.0, 69.5),
+            (41.0, 67.0),
+            (44.0, 68.5),
+            (48.0, 67.5),
+            (53.0, 68.0),
+            (60.0, 69.0),
+            (60.0, 55.0),
+            (50.0, 51.0),
+            (47.0, 48.0),
+            (47.0, 42.0),
+            (40.0, 43.5),
+            (38.0, 47.0),
+            (40.0, 49.6),
+            (35.0, 52.3),
+            (32.0, 52.3),
+            (31.5, 53.0),
+            (32.7, 53.4),
+            (31.0, 55.8),
+            (28.2, 56.1),
+        ]],
+    ),
+    (
+        "Europe/Istanbul",
+        &[&[
+            (26.0, 40.5),
+            (26.6, 41.8),
+            (28.0, 42.0),
+            (35.0, 42.0),
+            (41.5, 41.5),
+            (44.8, 39.7),
+            (44.8, 37.2),
+            (42.3, 37.1),
+            (36.6, 36.8),
+            (36.0, 35.8),
+            (32.5, 36.1),
+            (30.5, 36.3),
+            (27.3, 37.0),
+        ]],
+    ),
+    // Africa
+    (
+        "Africa/Cairo",
+        &[&[
+            (25.0, 31.6),
+            (25.0, 22.0),
+            (37.0, 22.0),
+            (34.2, 27.8),
+            (34.9, 29.5),
+            (34.2, 31.3),
+            (32.0, 31.6),
+        ]],
+    ),
+    (
+        "Africa/Lagos",
+        &[&[
+            (2.7, 6.3),
+            (2.7, 12.0),
+            (4.0, 13.5),
+            (13.6, 13.7),
+            (14.6, 12.0),
+            (12.0, 7.0),
+            (8.5, 4.5),
+            (4.5, 6.3),
+        ]],
+    ),
+    (
+        "Africa/Nairobi",
+        &[&[
+            (34.0, -1.0),
+            (34.0, 4.6),
+            (36.0, 4.5),
+            (41.9, 4.0),
+            (41.0, -1.7),
+            (39.3, -4.7),
+            (37.6, -3.0),
+        ]],
+    ),
+    (
+        "Africa/Johannesburg",
+        &[&[
+            (16.5, -28.6),
+            (20.0, -24.8),
+            (25.0, -25.8),
+            (27.0, -23.5),
+            (31.3, -22.4),
+            (32.9, -26.8),
+            (30.0, -31.3),
+            (25.6, -34.0),
+            (20.0, -34.8),
+            (18.4, -34.3),
+        ]],
+    ),
+    // Asia
+    (
+        "Asia/Dubai",
+        &[&[
+            (51.6, 24.2),
+            (55.5, 22.7),
+            (56.4, 24.9),
+            (56.3, 26.2),
+            (54.5, 24.6),
+        ]],
+    ),
+    (
+        "Asia/Kolkata",
+        &[&[
+            (68.2, 23.7),
+            (70.0, 20.8),
+            (72.8, 19.0),
+            (73.5, 16.0),
+            (76.5, 8.3),
+            (78.1, 8.1),
+            (80.3, 13.4),
+            (80.2, 15.7),
+            (82.4, 17.0),
+            (86.9, 21.3),
+            (88.8, 21.6),
+            (88.1, 26.4),
+            (81.0, 30.0),
+            (78.5, 32.5),
+            (79.5, 35.5),
+            (77.8, 35.5),
+            (74.0, 34.5),
+            (74.6, 31.0),
+            (71.0, 28.0),
+            (70.0, 25.6),
+        ]],
+    ),
+    (
+        "Asia/Shanghai",
+        &[&[
+            (73.5, 39.5),
+            (80.0, 45.0),
+            (87.0, 49.0),
+            (97.0, 42.8),
+            (105.0, 41.6),
+            (111.5, 45.0),
+            (116.0, 46.3),
+            (119.5, 50.0),
+            (121.0, 53.3),
+            (126.0, 53.0),
+            (131.0, 48.0),
+            (134.7, 48.3),
+            (131.0, 42.8),
+            (124.4, 40.0),
+            (121.7, 39.0),
+            (122.0, 37.3),
+            (119.0, 35.0),
+            (122.0, 31.0),
+            (122.0, 29.5),
+            (119.5, 25.5),
+            (117.0, 23.5),
+            (110.0, 21.0),
+            (108.0, 21.5),
+            (106.5, 22.8),
+            (101.5, 21.2),
+            (98.0, 24.0),
+            (97.5, 28.2),
+            (92.0, 27.8),
+            (89.0, 28.0),
+            (81.0, 30.0),
+            (78.5, 32.5),
+            (79.5, 35.5),
+            (74.5, 37.1),
+        ]],
+    ),
+    (
+        "Asia/Singapore",
+        &[&[(103.6, 1.2), (104.1, 1.2), (104.1, 1.47), (103.6, 1.47)]],
+    ),
+    (
+        "Asia/Seoul",
+        &[&[
+            (126.0, 34.3),
+            (129.5, 35.0),
+            (129.5, 37.5),
+            (128.4, 38.6),
+            (126.5, 37.7),
+            (126.1, 36.5),
+        ]],
+    ),
+    (
+        "Asia/Tokyo",
+        &[&[
+            (129.5, 33.0),
+            (131.0, 31.0),
+            (135.0, 33.5),
+            (140.0, 35.0),
+            (141.0, 38.0),
+            (142.0, 41.5),
+            (145.8, 43.3),
+            (145.0, 44.5),
+            (141.6, 45.5),
+            (139.8, 42.0),
+            (139.5, 38.5),
+            (136.5, 37.3),
+            (133.0, 35.5),
+            (130.8, 34.5),
+        ]],
+    ),
+    // Oceania
+    (
+        "Australia/Perth",
+        &[&[
+            (129.0, -31.6),
+            (129.0, -26.0),
+            (129.0, -14.9),
+            (126.0, -14.0),
+            (122.0, -17.0),
+            (114.0, -22.0),
+            (113.5, -26.0),
+            (115.0, -34.0),
+            (118.0, -35.0),
+            (124.0, -33.8),
+        ]],
+    ),
+    (
+        "Australia/Darwin",
+        &[&[
+            (129.0, -26.0),
+            (138.0, -26.0),
+            (138.0, -16.5),
+            (137.0, -12.0),
+            (132.0, -11.0),
+            (129.0, -14.9),
+        ]],
+    ),
+    (
+        "Australia/Adelaide",
+        &[&[
+            (129.0, -26.0),
+            (138.0, -26.0),
+            (141.0, -26.0),
+            (141.0, -29.0),
+            (141.0, -34.0),
+            (141.0, -38.1),
+            (140.0, -38.0),
+            (138.0, -35.7),
+            (136.0, -35.2),
+            (134.0, -32.8),
+            (129.0, -31.6),
+        ]],
+    ),
+    (
+        "Australia/Brisbane",
+        &[&[
+            (138.0, -26.0),
+            (141.0, -26.0),
+            (141.0, -29.0),
+            (153.6, -28.2),
+            (153.0, -25.0),
+            (146.0, -19.0),
+            (145.3, -15.0),
+            (142.5, -10.7),
+            (141.5, -13.0),
+            (140.8, -17.5),
+            (138.0, -16.5),
+        ]],
+    ),
+    (
+        "Australia/Sydney",
+        &[&[
+            (141.0, -29.0),
+            (153.6, -28.2),
+            (153.0, -31.0),
+            (151.6, -33.9),
+            (150.1, -37.5),
+            (149.9, -37.5),
+            (148.2, -36.8),
+            (141.0, -34.0),
+        ]],
+    ),
+    (
+        "Australia/Melbourne",
+        &[&[
+            (141.0, -34.0),
+            (148.2, -36.8),
+            (149.9, -37.5),
+            (146.3, -39.1),
+            (141.0, -38.1),
+        ]],
+    ),
+    (
+        "Pacific/Auckland",
+        &[
+            &[
+                (172.6, -34.4),
+                (178.6, -37.7),
+                (175.0, -41.6),
+                (174.6, -39.8),
+                (174.3, -36.8),
+            ],
+            &[
+                (172.7, -40.5),
+                (174.3, -41.7),
+                (171.2, -44.3),
+                (169.0, -46.7),
+                (166.5, -46.0),
+                (168.2, -43.9),
+            ],
+        ],
+    ),
+];
@@ -0,0 +1,967 @@
+// Meeting slots across timezones
+//
+// Each participant's working hours are resolved to UTC windows on the local
+// dates around the requested day, so windows that cross local midnight and
+// days with a DST change come out at their real instants. The search covers
+// the requested date as a local day in the first participant's zone; every
+// function below except `find_overlap` works on resolved windows only.
+//
+// Local times skipped by a forward transition move forward by the length of
+// the gap, as `local_to_utc` does with `shift_forward`; repeated local times take the earlier instant for a window start and the
+// later one for its end, so a window never loses the repeated hour.
+//
+// Time-window guards for assert_time_window are here as well. A window is an
+// explicit interval, the same local hours every day (overnight when the end
+// is at or before the start), or a cron expression matched against local
+// wall-clock time with a fixed elapsed duration. Daily windows follow the
+// rules above, so a window is its wall-clock length less any gap inside it
+// plus any repeated hour: 01:00-05:00 in New York lasts three hours on
+// 2024-03-10 and five on 2024-11-03, and 09:00-17:00 stays eight hours on
+// both days since neither change falls inside it. A cron start the clocks
+// skip moves forward by the gap like any other. Windows that overlap or
+// touch count as one, and a change more than a year ahead is not reported.
+//
+// Week numbers for get_week_number are here too. Systems disagree on where a
+// week starts and which week is the first: ISO 8601 (Monday start, week 1
+// holds the first Thursday, the week-year can differ from the calendar
+// year), `%U` (Sunday start, days before the first Sunday are week 0), `%W`
+// (the same from Monday), Saturday start as used in much of the Middle East
+// (the week holding January 1 is week 1, as in spreadsheet WEEKNUM), and
+// simple weeks of seven days counted from January 1.
+
+use super::cron::CronSchedule;
+use crate::error::TimeServerError;
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    SecondsFormat, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Working hours used when a participant has none of their own
+pub const DEFAULT_WORKING_HOURS: &str = "09:00-17:00";
+
+/// Slot length used when none is given
+pub const DEFAULT_MIN_SLOT_MINUTES: u32 = 30;
+
+/// Longest recurring window accepted
+pub const MAX_RECURRING_WINDOW_DAYS: i64 = 31;
+
+/// How far past the tested instant window changes are looked for
+const WINDOW_LOOKAHEAD_DAYS: i64 = 366;
+
+/// A local time-of-day range; an end at or before the start is on the next day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl WorkingHours {
+    /// `HH:MM-HH:MM`, e.g. `09:00-17:00` or `22:00-06:00`
+    pub fn parse(text: &str) -> Result<Self, TimeServerError> {
+        let invalid = || {
+            TimeServerError::InvalidArgument(format!(
+                "working hours must be HH:MM-HH:MM, got '{}'",
+                text
+            ))
+        };
+        let (start, end) = text.trim().split_once('-').ok_or_else(invalid)?;
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        let hours = Self {
+            start: time(start)?,
+            end: time(end)?,
+        };
+        if hours.start == hours.end {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "working hours '{}' are empty",
+                text
+            )));
+        }
+        Ok(hours)
+    }
+
+    pub fn crosses_midnight(&self) -> bool {
+        self.end <= self.start
+    }
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self::parse(DEFAULT_WORKING_HOURS).unwrap()
+    }
+}
+
+impl std::fmt::Display for WorkingHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// A half-open UTC interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    pub fn minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+
+    /// The part of `self` inside `other`, if any
+    pub fn intersect(&self, other: &Window) -> Option<Window> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then(|| Window::new(start, end))
+    }
+}
+
+/// `local` in `tz`; a skipped time is moved forward by the gap, a repeated
+/// one resolves to the earlier or later instant
+fn resolve(tz: Tz, local: NaiveDateTime, later: bool) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => dt.with_timezone(&Utc),
+        LocalResult::Ambiguous(a, b) => {
+            let (a, b) = (a.with_timezone(&Utc), b.with_timezone(&Utc));
+            if later {
+                a.max(b)
+            } else {
+                a.min(b)
+            }
+        }
+        LocalResult::None => {
+            // Read with the offset in force before the gap, the time lands after it
+            let before = tz
+                .offset_from_utc_datetime(&(local - Duration::days(1)))
+                .fix();
+            (local - Duration::seconds(before.local_minus_utc() as i64)).and_utc()
+        }
+    }
+}
+
+/// `date` as a local day in `tz`, midnight to midnight
+pub fn local_day(tz: Tz, date: NaiveDate) -> Window {
+    let next = date.succ_opt().unwrap_or(date);
+    Window::new(
+        resolve(tz, date.and_time(NaiveTime::MIN), false),
+        resolve(tz, next.and_time(NaiveTime::MIN), false),
+    )
+}
+
+/// The working window starting on local `day`, unless transitions empty it
+fn daily_window(tz: Tz, day: NaiveDate, hours: &WorkingHours) -> Option<Window> {
+    let end_day = if hours.crosses_midnight() {
+        day.succ_opt()?
+    } else {
+        day
+    };
+    let window = Window::new(
+        resolve(tz, day.and_time(hours.start), false),
+        resolve(tz, end_day.and_time(hours.end), true),
+    );
+    (window.start < window.end).then_some(window)
+}
+
+/// Working windows starting on the local dates from the day before `date`
+/// to the day after, in order
+pub fn working_windows(tz: Tz, date: NaiveDate, hours: &WorkingHours) -> Vec<Window> {
+    (-1..=1)
+        .filter_map(|days| date.checked_add_signed(Duration::days(days)))
+        .filter_map(|day| daily_window(tz, day, hours))
+        .collect()
+}
+
+/// The parts of sorted `windows` inside `range`
+pub fn clip(windows: &[Window], range: &Window) -> Vec<Window> {
+    windows.iter().filter_map(|w| w.intersect(range)).collect()
+}
+
+/// Intersection of two sorted lists of disjoint windows
+pub fn intersect_all(a: &[Window], b: &[Window]) -> Vec<Window> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        if let Some(w) = a[i].intersect(&b[j]) {
+            out.push(w);
+        }
+        if a[i].end <= b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Windows where every participant is working
+pub fn common_windows(participants: &[Vec<Window>]) -> Vec<Window> {
+    match participants.split_first() {
+        Some((first, rest)) => rest
+            .iter()
+            .fold(first.clone(), |acc, windows| intersect_all(&acc, windows)),
+        None => Vec::new(),
+    }
+}
+
+/// Minutes of `slot` outside `windows`
+pub fn out_of_hours_minutes(slot: &Window, windows: &[Window]) -> i64 {
+    let inside: i64 = windows
+        .iter()
+        .filter_map(|w| w.intersect(slot))
+        .map(|w| w.minutes())
+        .sum();
+    slot.minutes() - inside
+}
+
+/// The `length` slot within `range` with the fewest out-of-hours minutes
+/// summed over participants, and that sum; the earliest wins a tie.
+///
+/// The sum is piecewise linear in the slot start with corners where the
+/// start or end meets a window edge, so only those starts are tried.
+pub fn nearest_slot(
+    participants: &[Vec<Window>],
+    range: &Window,
+    length: Duration,
+) -> Option<(Window, i64)> {
+    let mut starts = vec![range.start, range.end - length];
+    for w in participants.iter().flatten() {
+        for edge in [w.start, w.end] {
+            starts.extend([edge, edge - length]);
+        }
+    }
+    starts.sort();
+    starts.dedup();
+    starts
+        .into_iter()
+        .map(|start| Window::new(start, start + length))
+        .filter(|slot| slot.start >= range.start && slot.end <= range.end)
+        .map(|slot| {
+            let cost = participants
+                .iter()
+                .map(|windows| out_of_hours_minutes(&slot, windows))
+                .sum();
+            (slot, cost)
+        })
+        .min_by_key(|(slot, cost)| (*cost, slot.start))
+}
+
+/// A participant's zone and working hours
+#[derive(Debug, Clone)]
+pub struct Participant {
+    pub timezone: Tz,
+    pub hours: WorkingHours,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalSlot {
+    pub timezone: String,
+    pub working_hours: String,
+    pub start: String,
+    pub end: String,
+    /// Minutes of the slot outside this participant's working hours
+    pub out_of_hours_minutes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Slot {
+    pub start_utc: String,
+    pub end_utc: String,
+    pub minutes: i64,
+    /// The slot in every participant's local time, in request order
+    pub local: Vec<LocalSlot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NearMiss {
+    /// Summed over participants
+    pub out_of_hours_minutes: i64,
+    pub slot: Slot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlapResult {
+    /// Local date in `reference_timezone` that was searched
+    pub date: String,
+    pub reference_timezone: String,
+    pub min_slot_minutes: u32,
+    pub overlap: bool,
+    /// Common working time of at least `min_slot_minutes`, in order
+    pub slots: Vec<Slot>,
+    /// With no overlap: the `min_slot_minutes` slot with the fewest
+    /// out-of-hours minutes
+    pub near_miss: Option<NearMiss>,
+}
+
+/// Slots on `date` (a local day in the first participant's zone) where
+/// everyone is within working hours for at least `min_minutes`
+pub fn find_overlap(
+    participants: &[Participant],
+    date: NaiveDate,
+    min_minutes: u32,
+) -> Result<OverlapResult, TimeServerError> {
+    let reference = participants.first().ok_or_else(|| {
+        TimeServerError::InvalidArgument("at least one timezone is required".into())
+    })?;
+    if min_minutes == 0 || min_minutes > 24 * 60 {
+        return Err(TimeServerError::InvalidArgument(format!(
+            "min_minutes must be 1-1440, got {}",
+            min_minutes
+        )));
+    }
+
+    let range = local_day(reference.timezone, date);
+    let windows: Vec<Vec<Window>> = participants
+        .iter()
+        .map(|p| clip(&working_windows(p.timezone, date, &p.hours), &range))
+        .collect();
+    let render = |slot: &Window| Slot {
+        start_utc: slot.start.to_rfc3339(),
+        end_utc: slot.end.to_rfc3339(),
+        minutes: slot.minutes(),
+        local: participants
+            .iter()
+            .zip(&windows)
+            .map(|(p, windows)| LocalSlot {
+                timezone: p.timezone.name().to_string(),
+                working_hours: p.hours.to_string(),
+                start: slot.start.with_timezone(&p.timezone).to_rfc3339(),
+                end: slot.end.with_timezone(&p.timezone).to_rfc3339(),
+                out_of_hours_minutes: out_of_hours_minutes(slot, windows),
+            })
+            .collect(),
+    };
+
+    let slots: Vec<Slot> = common_windows(&windows)
+        .iter()
+        .filter(|w| w.minutes() >= min_minutes as i64)
+        .map(render)
+        .collect();
+    let near_miss = if slots.is_empty() {
+        nearest_slot(&windows, &range, Duration::minutes(min_minutes as i64)).map(|(slot, cost)| {
+            NearMiss {
+                out_of_hours_minutes: cost,
+                slot: render(&slot),
+            }
+        })
+    } else {
+        None
+    };
+
+    Ok(OverlapResult {
+        date: date.to_string(),
+        reference_timezone: reference.timezone.name().to_string(),
+        min_slot_minutes: min_minutes,
+        overlap: !slots.is_empty(),
+        slots,
+        near_miss,
+    })
+}
+
+/// When a time-window guard holds
+#[derive(Debug, Clone)]
+pub enum WindowSpec {
+    /// One explicit interval
+    Between(Window),
+    /// The same local hours every day
+    Daily { timezone: Tz, hours: WorkingHours },
+    /// Windows of `duration` starting at each local time `schedule` matches
+    Recurring {
+        timezone: Tz,
+        schedule: CronSchedule,
+        duration: Duration,
+    },
+}
+
+/// Where an instant stands against a [`WindowSpec`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowStatus {
+    pub inside: bool,
+    /// Start of the earliest window open at the instant
+    pub current_start: Option<DateTime<Utc>>,
+    /// When `inside` next flips: the end of the current window or the start
+    /// of the next; None when it does not within a year
+    pub change_at: Option<DateTime<Utc>>,
+    /// The window after the current one, or the upcoming one from outside
+    pub next_start: Option<DateTime<Utc>>,
+    /// None when that window has not closed within a year
+    pub next_end: Option<DateTime<Utc>>,
+}
+
+/// The assert_time_window result; instants are RFC 3339, UTC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindowAssertion {
+    pub inside: bool,
+    /// Rounded up; null when `inside` does not change within a year
+    pub seconds_until_change: Option<i64>,
+    /// Null outside a window
+    pub current_window_start: Option<String>,
+    pub current_window_end: Option<String>,
+    pub next_window_start: Option<String>,
+    pub next_window_end: Option<String>,
+}
+
+impl WindowStatus {
+    /// The status of `at` as assert_time_window reports it
+    pub fn assertion(&self, at: DateTime<Utc>) -> WindowAssertion {
+        let text =
+            |t: Option<DateTime<Utc>>| t.map(|t| t.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+        WindowAssertion {
+            inside: self.inside,
+            seconds_until_change: self
+                .change_at
+                .map(|t| ((t - at).num_milliseconds() + 999).div_euclid(1000)),
+            current_window_start: text(self.current_start),
+            current_window_end: text(self.change_at.filter(|_| self.inside)),
+            next_window_start: text(self.next_start),
+            next_window_end: text(self.next_end),
+        }
+    }
+}
+
+impl WindowSpec {
+    /// Windows ending after `from`, in start order, beginning with any that
+    /// is open at `from`
+    fn windows_from(&self, from: DateTime<Utc>) -> Box<dyn Iterator<Item = Window> + '_> {
+        let windows: Box<dyn Iterator<Item = Window> + '_> = match self {
+            Self::Between(window) => Box::new(std::iter::once(*window)),
+            Self::Daily { timezone, hours } => {
+                let first = from.with_timezone(timezone).date_naive() - Duration::days(1);
+                Box::new(
+                    first
+                        .iter_days()
+                        .filter_map(move |day| daily_window(*timezone, day, hours)),
+                )
+            }
+            Self::Recurring {
+                timezone,
+                schedule,
+                duration,
+            } => {
+                // Cron matches local wall-clock times read as if they were UTC;
+                // a day of slack covers any offset change since `from - duration`
+                let mut local =
+                    (from - *duration).with_timezone(timezone).naive_local() - Duration::days(1);
+                Box::new(std::iter::from_fn(move || {
+                    local = schedule.next_after(local.and_utc())?.naive_utc();
+                    let start = resolve(*timezone, local, false);
+                    Some(Window::new(start, start + *duration))
+                }))
+            }
+        };
+        Box::new(windows.filter(move |w| w.end > from))
+    }
+}
+
+/// Whether `at` is inside a window of `spec`, and when that changes
+pub fn window_status(spec: &WindowSpec, at: DateTime<Utc>) -> WindowStatus {
+    let horizon = at + Duration::days(WINDOW_LOOKAHEAD_DAYS);
+    // The first two runs of overlapping or touching windows
+    let mut runs: Vec<Window> = Vec::with_capacity(2);
+    let mut last_closed = true;
+    for window in spec.windows_from(at) {
+        if let Some(last) = runs.last_mut().filter(|last| window.start <= last.end) {
+            if window.start > horizon {
+                last_closed = false;
+                break;
+            }
+            last.end = last.end.max(window.end);
+        } else if runs.len() == 2 || window.start > horizon {
+            break;
+        } else {
+            runs.push(window);
+        }
+    }
+
+    let end = |window: &Window| (last_closed || runs.last() != Some(window)).then_some(window.end);
+    let current = runs.first().filter(|w| w.start <= at);
+    let next = if current.is_some() {
+        runs.get(1)
+    } else {
+        runs.first()
+    };
+    WindowStatus {
+        inside: current.is_some(),
+        current_start: current.map(|w| w.start),
+        change_at: match current {
+            Some(window) => end(window),
+            None => next.map(|w| w.start),
+        },
+        next_start: next.map(|w| w.start),
+        next_end: next.and_then(end),
+    }
+}
+
+/// A week-numbering scheme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WeekConvention {
+    /// ISO 8601: Monday start, week 1 holds the year's first Thursday
+    #[default]
+    Iso,
+    /// `%U`: Sunday start, days before the first Sunday are week 0
+    Sunday,
+    /// `%W`: Monday start, days before the first Monday are week 0
+    Monday,
+    /// Saturday start, the week holding January 1 is week 1
+    Saturday,
+    /// Seven-day blocks from January 1; week 53 has one or two days
+    Simple,
+}
+
+/// A date's week under one convention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WeekNumber {
+    pub week: u32,
+    /// Differs from the calendar year only for ISO weeks around New Year
+    pub week_year: i32,
+    /// First and last day of the week holding the date; a simple week stops
+    /// at December 31, the others may reach into the neighbouring year
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+}
+
+impl WeekConvention {
+    pub const ALL: [WeekConvention; 5] = [
+        Self::Iso,
+        Self::Sunday,
+        Self::Monday,
+        Self::Saturday,
+        Self::Simple,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Iso => "iso",
+            Self::Sunday => "sunday",
+            Self::Monday => "monday",
+            Self::Saturday => "saturday",
+            Self::Simple => "simple",
+        }
+    }
+
+    /// The week holding `date`
+    pub fn week_of(self, date: NaiveDate) -> WeekNumber {
+        let year = date.year();
+        let jan1 = NaiveDate::from_yo_opt(year, 1).unwrap_or(date);
+        let week_start = |first: Weekday| date.week(first).first_day();
+        match self {
+            Self::Iso => {
+                let iso = date.iso_week();
+                let first_day = week_start(Weekday::Mon);
+                WeekNumber {
+                    week: iso.week(),
+                    week_year: iso.year(),
+                    first_day,
+                    last_day: first_day + Duration::days(6),
+                }
+            }
+            Self::Sunday | Self::Monday => {
+                let first = if self == Self::Sunday {
+                    Weekday::Sun
+                } else {
+                    Weekday::Mon
+                };
+                let first_day = week_start(first);
+                // Week 1 starts on the year's first `first` weekday
+                let week1 = jan1.week(first).first_day();
+                let week1 = if week1 < jan1 {
+                    week1 + Duration::days(7)
+                } else {
+                    week1
+                };
+                WeekNumber {
+                    week: ((first_day - week1).num_days().div_euclid(7) + 1) as u32,
+                    week_year: year,
+                    first_day,
+                    last_day: first_day + Duration::days(6),
+                }
+            }
+            Self::Saturday => {
+                let first_day = week_start(Weekday::Sat);
+                let week1 = jan1.week(Weekday::Sat).first_day();
+                WeekNumber {
+                    week: ((first_day - week1).num_days() / 7 + 1) as u32,
+                    week_year: year,
+                    first_day,
+                    last_day: first_day + Duration::days(6),
+                }
+            }
+            Self::Simple => {
+                let index = date.ordinal0() / 7;
+                let first_day = jan1 + Duration::days(i64::from(index) * 7);
+                let dec31 = NaiveDate::from_ymd_opt(year, 12, 31).unwrap_or(date);
+                WeekNumber {
+                    week: index + 1,
+                    week_year: year,
+                    first_day,
+                    last_day: (first_day + Duration::days(6)).min(dec31),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participants(zones: &[(&str, &str)]) -> Vec<Participant> {
+        zones
+            .iter()
+            .map(|(tz, hours)| Participant {
+                timezone: tz.parse().unwrap(),
+                hours: WorkingHours::parse(hours).unwrap(),
+            })
+            .collect()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_new_york_london_tokyo_has_no_overlap() {
+        let people = participants(&[
+            ("America/New_York", "09:00-17:00"),
+            ("Europe/London", "09:00-17:00"),
+            ("Asia/Tokyo", "09:00-17:00"),
+        ]);
+        let result = find_overlap(&people, date("2024-06-12"), 60).unwrap();
+        assert!(!result.overlap);
+        assert!(result.slots.is_empty());
+
+        // New York and London share 13:00-16:00 UTC; Tokyo is an hour out at best
+        let near = result.near_miss.unwrap();
+        assert_eq!(near.out_of_hours_minutes, 60);
+        assert_eq!(near.slot.start_utc, "2024-06-12T13:00:00+00:00");
+        assert_eq!(near.slot.local[0].start, "2024-06-12T09:00:00-04:00");
+        assert_eq!(near.slot.local[2].start, "2024-06-12T22:00:00+09:00");
+        let out: Vec<i64> = near
+            .slot
+            .local
+            .iter()
+            .map(|l| l.out_of_hours_minutes)
+            .collect();
+        assert_eq!(out, [0, 0, 60]);
+    }
+
+    #[test]
+    fn test_adjacent_zones_overlap() {
+        let people = participants(&[
+            ("Europe/London", "09:00-17:00"),
+            ("Europe/Paris", "09:00-17:00"),
+        ]);
+        let result = find_overlap(&people, date("2024-06-12"), 30).unwrap();
+        assert!(result.overlap);
+        assert!(result.near_miss.is_none());
+        assert_eq!(result.slots.len(), 1);
+        let slot = &result.slots[0];
+        assert_eq!(slot.start_utc, "2024-06-12T08:00:00+00:00");
+        assert_eq!(slot.end_utc, "2024-06-12T15:00:00+00:00");
+        assert_eq!(slot.minutes, 420);
+        assert_eq!(slot.local[1].start, "2024-06-12T10:00:00+02:00");
+    }
+
+    #[test]
+    fn test_dst_change_in_one_zone() {
+        let people = participants(&[
+            ("America/New_York", "09:00-17:00"),
+            ("Europe/London", "09:00-17:00"),
+        ]);
+        // New York moved to EDT on 2024-03-10; London stays on GMT until the 31st
+        let before = find_overlap(&people, date("2024-03-08"), 30).unwrap();
+        let after = find_overlap(&people, date("2024-03-10"), 30).unwrap();
+        assert_eq!(before.slots[0].start_utc, "2024-03-08T14:00:00+00:00");
+        assert_eq!(before.slots[0].minutes, 180);
+        assert_eq!(after.slots[0].start_utc, "2024-03-10T13:00:00+00:00");
+        assert_eq!(after.slots[0].minutes, 240);
+    }
+
+    #[test]
+    fn test_windows_crossing_midnight_and_gaps() {
+        let people = participants(&[
+            ("Europe/London", "09:00-17:00"),
+            ("Asia/Tokyo", "22:00-06:00"),
+        ]);
+        let result = find_overlap(&people, date("2024-06-12"), 30).unwrap();
+        assert_eq!(result.slots[0].start_utc, "2024-06-12T13:00:00+00:00");
+        assert_eq!(result.slots[0].end_utc, "2024-06-12T16:00:00+00:00");
+
+        // 02:30 does not exist in New York on 2024-03-10; it is read as 03:30 EDT
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let hours = WorkingHours::parse("02:30-04:00").unwrap();
+        let windows = working_windows(tz, date("2024-03-10"), &hours);
+        assert_eq!(windows[1].start.to_rfc3339(), "2024-03-10T07:30:00+00:00");
+        assert_eq!(windows[1].minutes(), 30);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(WorkingHours::parse("9-5").is_err());
+        assert!(WorkingHours::parse("09:00-09:00").is_err());
+        let people = participants(&[("UTC", "09:00-17:00")]);
+        assert!(find_overlap(&people, date("2024-06-12"), 0).is_err());
+        assert!(find_overlap(&[], date("2024-06-12"), 30).is_err());
+    }
+
+    #[test]
+    fn test_window_status() {
+        let between = |start: &str, end: &str| {
+            WindowSpec::Between(Window::new(start.parse().unwrap(), end.parse().unwrap()))
+        };
+        let daily = |tz: &str, hours: &str| WindowSpec::Daily {
+            timezone: tz.parse().unwrap(),
+            hours: WorkingHours::parse(hours).unwrap(),
+        };
+        let recurring = |tz: &str, cron: &str, minutes: i64| WindowSpec::Recurring {
+            timezone: tz.parse().unwrap(),
+            schedule: cron.parse().unwrap(),
+            duration: Duration::minutes(minutes),
+        };
+        let z = |text: &str| Some(text.parse::<DateTime<Utc>>().unwrap());
+        // (spec, instant, inside, change_at, next_start, next_end)
+        let cases = [
+            (
+                between("2024-06-12T09:00:00Z", "2024-06-12T17:00:00Z"),
+                "2024-06-12T08:00:00Z",
+                false,
+                z("2024-06-12T09:00:00Z"),
+                z("2024-06-12T09:00:00Z"),
+                z("2024-06-12T17:00:00Z"),
+            ),
+            (
+                between("2024-06-12T09:00:00Z", "2024-06-12T17:00:00Z"),
+                "2024-06-12T12:00:00Z",
+                true,
+                z("2024-06-12T17:00:00Z"),
+                None,
+                None,
+            ),
+            // Half-open: the end is outside, and nothing follows
+            (
+                between("2024-06-12T09:00:00Z", "2024-06-12T17:00:00Z"),
+                "2024-06-12T17:00:00Z",
+                false,
+                None,
+                None,
+                None,
+            ),
+            // 12:00 CEST
+            (
+                daily("Europe/Berlin", "09:00-17:00"),
+                "2024-06-12T10:00:00Z",
+                true,
+                z("2024-06-12T15:00:00Z"),
+                z("2024-06-13T07:00:00Z"),
+                z("2024-06-13T15:00:00Z"),
+            ),
+            // 18:00 CEST
+            (
+                daily("Europe/Berlin", "09:00-17:00"),
+                "2024-06-12T16:00:00Z",
+                false,
+                z("2024-06-13T07:00:00Z"),
+                z("2024-06-13T07:00:00Z"),
+                z("2024-06-13T15:00:00Z"),
+            ),
+            // Overnight: 03:00 JST on the 13th is in the window opened on the 12th
+            (
+                daily("Asia/Tokyo", "22:00-06:00"),
+                "2024-06-12T18:00:00Z",
+                true,
+                z("2024-06-12T21:00:00Z"),
+                z("2024-06-13T13:00:00Z"),
+                z("2024-06-13T21:00:00Z"),
+            ),
+            (
+                daily("Asia/Tokyo", "22:00-06:00"),
+                "2024-06-12T12:00:00Z",
+                false,
+                z("2024-06-12T13:00:00Z"),
+                z("2024-06-12T13:00:00Z"),
+                z("2024-06-12T21:00:00Z"),
+            ),
+            // Spring forward: 01:00 EST to 05:00 EDT is three hours
+            (
+                daily("America/New_York", "01:00-05:00"),
+                "2024-03-10T05:00:00Z",
+                false,
+                z("2024-03-10T06:00:00Z"),
+                z("2024-03-10T06:00:00Z"),
+                z("2024-03-10T09:00:00Z"),
+            ),
+            // The gap is outside 09:00-17:00, which stays eight hours
+            (
+                daily("America/New_York", "09:00-17:00"),
+                "2024-03-10T12:00:00Z",
+                false,
+                z("2024-03-10T13:00:00Z"),
+                z("2024-03-10T13:00:00Z"),
+                z("2024-03-10T21:00:00Z"),
+            ),
+            // Fall back: 01:00 EDT to 05:00 EST is five hours
+            (
+                daily("America/New_York", "01:00-05:00"),
+                "2024-11-03T04:00:00Z",
+                false,
+                z("2024-11-03T05:00:00Z"),
+                z("2024-11-03T05:00:00Z"),
+                z("2024-11-03T10:00:00Z"),
+            ),
+            // Weekdays from 09:00 BST for eight hours; Friday evening waits for Monday
+            (
+                recurring("Europe/London", "0 9 * * MON-FRI", 480),
+                "2024-06-14T16:30:00Z",
+                false,
+                z("2024-06-17T08:00:00Z"),
+                z("2024-06-17T08:00:00Z"),
+                z("2024-06-17T16:00:00Z"),
+            ),
+            (
+                recurring("Europe/London", "0 9 * * MON-FRI", 480),
+                "2024-06-14T10:00:00Z",
+                true,
+                z("2024-06-14T16:00:00Z"),
+                z("2024-06-17T08:00:00Z"),
+                z("2024-06-17T16:00:00Z"),
+            ),
+            // Overnight cron window, open since 22:00 CEST the day before
+            (
+                recurring("Europe/Berlin", "0 22 * * *", 600),
+                "2024-06-12T02:00:00Z",
+                true,
+                z("2024-06-12T06:00:00Z"),
+                z("2024-06-12T20:00:00Z"),
+                z("2024-06-13T06:00:00Z"),
+            ),
+            // 02:30 is skipped in New York on 2024-03-10 and starts at 03:30 EDT
+            (
+                recurring("America/New_York", "30 2 * * *", 60),
+                "2024-03-10T07:00:00Z",
+                false,
+                z("2024-03-10T07:30:00Z"),
+                z("2024-03-10T07:30:00Z"),
+                z("2024-03-10T08:30:00Z"),
+            ),
+            (
+                recurring("UTC", "0 */2 * * *", 60),
+                "2024-06-12T12:30:00Z",
+                true,
+                z("2024-06-12T13:00:00Z"),
+                z("2024-06-12T14:00:00Z"),
+                z("2024-06-12T15:00:00Z"),
+            ),
+            // Windows that touch never close
+            (
+                recurring("UTC", "0 * * * *", 60),
+                "2024-06-12T12:30:00Z",
+                true,
+                None,
+                None,
+                None,
+            ),
+        ];
+        for (i, (spec, at, inside, change_at, next_start, next_end)) in
+            cases.into_iter().enumerate()
+        {
+            let status = window_status(&spec, at.parse().unwrap());
+            assert_eq!(
+                (
+                    status.inside,
+                    status.change_at,
+                    status.next_start,
+                    status.next_end
+                ),
+                (inside, change_at, next_start, next_end),
+                "case {} ({:?} at {})",
+                i,
+                spec,
+                at
+            );
+            assert_eq!(status.current_start.is_some(), inside, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn test_week_numbers_where_conventions_diverge() {
+        // (date, iso week-year, iso week, %U, %W, saturday, simple)
+        let cases = [
+            ("2022-01-01", 2021, 52, 0, 0, 1, 1), // Saturday
+            ("2023-01-01", 2022, 52, 1, 0, 1, 1), // Sunday
+            ("2024-01-01", 2024, 1, 0, 1, 1, 1),  // Monday
+            ("2019-01-01", 2019, 1, 0, 0, 1, 1),  // Tuesday
+            ("2025-01-01", 2025, 1, 0, 0, 1, 1),  // Wednesday
+            ("2026-01-01", 2026, 1, 0, 0, 1, 1),  // Thursday
+            ("2021-01-01", 2020, 53, 0, 0, 1, 1), // Friday
+            ("2021-01-02", 2020, 53, 0, 0, 2, 1),
+            // 2020 and 2026 have an ISO week 53
+            ("2020-12-29", 2020, 53, 52, 52, 53, 52),
+            ("2020-12-31", 2020, 53, 52, 52, 53, 53),
+            ("2026-12-31", 2026, 53, 52, 52, 53, 53),
+            // Leap year: day 366 is the start of simple week 53
+            ("2024-12-31", 2025, 1, 52, 53, 53, 53),
+        ];
+        for (text, iso_year, iso, sunday, monday, saturday, simple) in cases {
+            let day = date(text);
+            let week = |convention: WeekConvention| convention.week_of(day);
+            assert_eq!(
+                (
+                    week(WeekConvention::Iso).week_year,
+                    week(WeekConvention::Iso).week
+                ),
+                (iso_year, iso),
+                "{}",
+                text
+            );
+            assert_eq!(week(WeekConvention::Sunday).week, sunday, "{} %U", text);
+            assert_eq!(week(WeekConvention::Monday).week, monday, "{} %W", text);
+            assert_eq!(
+                week(WeekConvention::Saturday).week,
+                saturday,
+                "{} sat",
+                text
+            );
+            assert_eq!(week(WeekConvention::Simple).week, simple, "{} simple", text);
+            // chrono's own %U and %W agree
+            assert_eq!(day.format("%U").to_string(), format!("{:02}", sunday));
+            assert_eq!(day.format("%W").to_string(), format!("{:02}", monday));
+        }
+    }
+
+    #[test]
+    fn test_week_ranges() {
+        let range = |convention: WeekConvention, text: &str| {
+            let week = convention.week_of(date(text));
+            (week.first_day.to_string(), week.last_day.to_string())
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            range(WeekConvention::Iso, "2021-01-01"),
+            pair("2020-12-28", "2021-01-03")
+        );
+        assert_eq!(
+            range(WeekConvention::Sunday, "2022-01-01"),
+            pair("2021-12-26", "2022-01-01")
+        );
+        assert_eq!(
+            range(WeekConvention::Saturday, "2025-01-01"),
+            pair("2024-12-28", "2025-01-03")
+        );
+        assert_eq!(
+            range(WeekConvention::Simple, "2024-12-31"),
+            pair("2024-12-30", "2024-12-31")
+        );
+    }
+}
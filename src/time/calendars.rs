@@ -0,0 +1,587 @@
+// Dates in non-Gregorian calendars
+//
+// All conversions are arithmetic on day numbers (R.D., where R.D. 1 is
+// 0001-01-01 Gregorian); nothing is looked up at runtime.
+//
+// - Hebrew: the fixed arithmetic calendar (molad and postponement rules), as
+//   in Reingold & Dershowitz, "Calendrical Calculations". Months are numbered
+//   from Nisan (1) as in that book, so the year starts at Tishri (7); Adar II
+//   is 13. Days are counted midnight to midnight, not from sunset.
+// - Islamic: the *tabular* (arithmetic, civil epoch) calendar with leap years
+//   2, 5, 7, 10, 13, 16, 18, 21, 24, 26, 29 of each 30-year cycle. Observed
+//   calendars start months on moon sightings and can differ by a day or two.
+// - Persian (Solar Hijri): Borkowski's algorithm, which matches the official
+//   astronomical calendar for years 1-3177 AP.
+// - Japanese: Gregorian months and days counted in imperial eras from Meiji.
+
+use crate::error::TimeServerError;
+use chrono::{Datelike, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// R.D. of 1 Muharram 1 AH (16 July 622 Julian, civil epoch)
+const ISLAMIC_EPOCH: i64 = 227_015;
+
+/// Julian Day Number of R.D. 0
+const JDN_OFFSET: i64 = 1_721_425;
+
+/// Years where the Persian leap pattern changes (Borkowski)
+const PERSIAN_BREAKS: [i64; 20] = [
+    -61, 9, 38, 199, 426, 686, 756, 818, 1111, 1181, 1210, 1635, 2060, 2097, 2192, 2262, 2324,
+    2394, 2456, 3178,
+];
+
+/// (romanized name, kanji, first day); each era runs until the next begins
+const JAPANESE_ERAS: [(&str, &str, (i32, u32, u32)); 5] = [
+    ("Meiji", "明治", (1868, 9, 8)),
+    ("Taisho", "大正", (1912, 7, 30)),
+    ("Showa", "昭和", (1926, 12, 25)),
+    ("Heisei", "平成", (1989, 1, 8)),
+    ("Reiwa", "令和", (2019, 5, 1)),
+];
+
+const HEBREW_MONTHS: [&str; 13] = [
+    "Nisan", "Iyyar", "Sivan", "Tammuz", "Av", "Elul", "Tishri", "Heshvan", "Kislev", "Tevet",
+    "Shevat", "Adar", "Adar II",
+];
+
+const ISLAMIC_MONTHS: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabi' al-Awwal",
+    "Rabi' al-Thani",
+    "Jumada al-Ula",
+    "Jumada al-Akhirah",
+    "Rajab",
+    "Sha'ban",
+    "Ramadan",
+    "Shawwal",
+    "Dhu al-Qa'dah",
+    "Dhu al-Hijjah",
+];
+
+const PERSIAN_MONTHS: [&str; 12] = [
+    "Farvardin",
+    "Ordibehesht",
+    "Khordad",
+    "Tir",
+    "Mordad",
+    "Shahrivar",
+    "Mehr",
+    "Aban",
+    "Azar",
+    "Dey",
+    "Bahman",
+    "Esfand",
+];
+
+const GREGORIAN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const ISLAMIC_NOTE: &str =
+    "Tabular Islamic calendar; observed dates from moon sightings may differ by a day or two";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Calendar {
+    Hebrew,
+    /// Tabular (arithmetic) Islamic calendar
+    #[serde(alias = "islamic_tabular", alias = "hijri")]
+    Islamic,
+    /// Solar Hijri
+    #[serde(alias = "solar_hijri", alias = "jalali")]
+    Persian,
+    Japanese,
+}
+
+/// A day in one of the supported calendars
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalendarDate {
+    pub calendar: Calendar,
+    /// "AM", "AH", "AP", or the Japanese era ("Reiwa")
+    pub era: String,
+    pub year: i64,
+    /// Hebrew months count from Nisan (1); Tishri is 7 and Adar II 13
+    pub month: u32,
+    pub month_name: String,
+    pub day: u32,
+    pub leap_year: bool,
+    /// "1 Farvardin 1403 AP", "Reiwa 1-05-01"
+    pub formatted: String,
+    /// Japanese only: "令和元年5月1日"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native: Option<String>,
+    /// Proleptic Gregorian date (YYYY-MM-DD)
+    pub gregorian: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<&'static str>,
+}
+
+/// `date` in `calendar`
+pub fn from_gregorian(
+    calendar: Calendar,
+    date: NaiveDate,
+) -> Result<CalendarDate, TimeServerError> {
+    let rd = date.num_days_from_ce() as i64;
+    let (era, year, month, day, leap_year, month_name) = match calendar {
+        Calendar::Hebrew => {
+            let (year, month, day) = hebrew_from_fixed(rd);
+            if year < 1 {
+                return Err(out_of_range(calendar, date));
+            }
+            let name = match (month, hebrew_leap(year)) {
+                (12, true) => "Adar I",
+                _ => HEBREW_MONTHS[month as usize - 1],
+            };
+            ("AM".to_string(), year, month, day, hebrew_leap(year), name)
+        }
+        Calendar::Islamic => {
+            let (year, month, day) = islamic_from_fixed(rd);
+            if year < 1 {
+                return Err(out_of_range(calendar, date));
+            }
+            let name = ISLAMIC_MONTHS[month as usize - 1];
+            ("AH".to_string(), year, month, day, islamic_leap(year), name)
+        }
+        Calendar::Persian => {
+            let (year, month, day) = persian_from_jdn(rd + JDN_OFFSET)
+                .filter(|&(year, _, _)| year >= 1)
+                .ok_or_else(|| out_of_range(calendar, date))?;
+            let name = PERSIAN_MONTHS[month as usize - 1];
+            let leap = persian_leap(year).unwrap_or(false);
+            ("AP".to_string(), year, month, day, leap, name)
+        }
+        Calendar::Japanese => {
+            let index = japanese_era_index(date).ok_or_else(|| out_of_range(calendar, date))?;
+            let (name, _, (start_year, _, _)) = JAPANESE_ERAS[index];
+            let year = (date.year() - start_year + 1) as i64;
+            let leap = date.leap_year();
+            let month_name = GREGORIAN_MONTHS[date.month0() as usize];
+            (
+                name.to_string(),
+                year,
+                date.month(),
+                date.day(),
+                leap,
+                month_name,
+            )
+        }
+    };
+
+    let (formatted, native) = match calendar {
+        Calendar::Japanese => {
+            let index = japanese_era_index(date).unwrap_or_default();
+            let year_text = if year == 1 {
+                "元".to_string()
+            } else {
+                year.to_string()
+            };
+            (
+                format!("{} {}-{:02}-{:02}", era, year, month, day),
+                Some(format!(
+                    "{}{}年{}月{}日",
+                    JAPANESE_ERAS[index].1, year_text, month, day
+                )),
+            )
+        }
+        _ => (format!("{} {} {} {}", day, month_name, year, era), None),
+    };
+
+    Ok(CalendarDate {
+        calendar,
+        era,
+        year,
+        month,
+        month_name: month_name.to_string(),
+        day,
+        leap_year,
+        formatted,
+        native,
+        gregorian: date.to_string(),
+        note: (calendar == Calendar::Islamic).then_some(ISLAMIC_NOTE),
+    })
+}
+
+/// The Gregorian date of `year`-`month`-`day` in `calendar`. Japanese dates
+/// need `era` (romanized or kanji, case-insensitive); it is ignored otherwise.
+pub fn to_gregorian(
+    calendar: Calendar,
+    era: Option<&str>,
+    year: i64,
+    month: u32,
+    day: u32,
+) -> Result<NaiveDate, TimeServerError> {
+    let invalid = || {
+        TimeServerError::InvalidArgument(format!(
+            "{}-{:02}-{:02} is not a date in the {:?} calendar",
+            year, month, day, calendar
+        ))
+    };
+    if year < 1 || !(1..=13).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    let date = match calendar {
+        Calendar::Hebrew => fixed_to_date(fixed_from_hebrew(year, month, day)),
+        Calendar::Islamic => fixed_to_date(fixed_from_islamic(year, month, day)),
+        Calendar::Persian => {
+            persian_to_jdn(year, month, day).and_then(|jdn| fixed_to_date(jdn - JDN_OFFSET))
+        }
+        Calendar::Japanese => {
+            let era = era.ok_or_else(|| {
+                TimeServerError::InvalidArgument(
+                    "'era' is required for Japanese dates (Meiji, Taisho, Showa, Heisei, Reiwa)"
+                        .to_string(),
+                )
+            })?;
+            let (_, _, (start_year, _, _)) = JAPANESE_ERAS
+                .iter()
+                .find(|(name, kanji, _)| {
+                    name.eq_ignore_ascii_case(era.trim()) || *kanji == era.trim()
+                })
+                .ok_or_else(|| {
+                    TimeServerError::InvalidArgument(format!("Unknown Japanese era: {}", era))
+                })?;
+            i32::try_from(year)
+                .ok()
+                .and_then(|year| NaiveDate::from_ymd_opt(start_year + year - 1, month, day))
+        }
+    }
+    .ok_or_else(invalid)?;
+
+    // Day and month overflow (30 Heshvan in a short year, Reiwa 1-04-30)
+    // lands on a different date; reject rather than roll over
+    let check = from_gregorian(calendar, date).map_err(|_| invalid())?;
+    let same_era = match (calendar, era) {
+        (Calendar::Japanese, Some(era)) => {
+            check.era.eq_ignore_ascii_case(era.trim())
+                || check
+                    .native
+                    .as_deref()
+                    .is_some_and(|n| n.starts_with(era.trim()))
+        }
+        _ => true,
+    };
+    if (check.year, check.month, check.day) != (year, month, day) || !same_era {
+        return Err(invalid());
+    }
+    Ok(date)
+}
+
+fn out_of_range(calendar: Calendar, date: NaiveDate) -> TimeServerError {
+    TimeServerError::InvalidArgument(format!(
+        "{} is outside the supported range of the {:?} calendar",
+        date, calendar
+    ))
+}
+
+fn fixed_to_date(rd: i64) -> Option<NaiveDate> {
+    NaiveDate::from_num_days_from_ce_opt(i32::try_from(rd).ok()?)
+}
+
+// Hebrew
+
+fn hebrew_leap(year: i64) -> bool {
+    (7 * year + 1).rem_euclid(19) < 7
+}
+
+/// Days from the Hebrew epoch to 1 Tishri of `year`, after postponements
+fn hebrew_elapsed_days(year: i64) -> i64 {
+    let y = year - 1;
+    let months = 235 * y.div_euclid(19) + 12 * y.rem_euclid(19) + (7 * y.rem_euclid(19) + 1) / 19;
+    let parts_elapsed = 204 + 793 * (months % 1080);
+    let hours = 5 + 12 * months + 793 * (months / 1080) + parts_elapsed / 1080;
+    let parts = parts_elapsed % 1080 + 1080 * (hours % 24);
+    let day = 1 + 29 * months + hours / 24;
+    let day = if parts >= 19440
+        || (day % 7 == 2 && parts >= 9924 && !hebrew_leap(year))
+        || (day % 7 == 1 && parts >= 16789 && hebrew_leap(year - 1))
+    {
+        day + 1
+    } else {
+        day
+    };
+    if matches!(day % 7, 0 | 3 | 5) {
+        day + 1
+    } else {
+        day
+    }
+}
+
+fn hebrew_year_days(year: i64) -> i64 {
+    hebrew_elapsed_days(year + 1) - hebrew_elapsed_days(year)
+}
+
+fn hebrew_month_days(month: u32, year: i64) -> u32 {
+    let year_days = hebrew_year_days(year);
+    match month {
+        2 | 4 | 6 | 10 | 13 => 29,
+        12 if !hebrew_leap(year) => 29,
+        8 if year_days % 10 != 5 => 29,
+        9 if year_days % 10 == 3 => 29,
+        _ => 30,
+    }
+}
+
+fn hebrew_last_month(year: i64) -> u32 {
+    if hebrew_leap(year) {
+        13
+    } else {
+        12
+    }
+}
+
+fn fixed_from_hebrew(year: i64, month: u32, day: u32) -> i64 {
+    let mut days = day as i64;
+    if month < 7 {
+        days += (7..=hebrew_last_month(year))
+            .map(|m| hebrew_month_days(m, year) as i64)
+            .sum::<i64>();
+        days += (1..month)
+            .map(|m| hebrew_month_days(m, year) as i64)
+            .sum::<i64>();
+    } else {
+        days += (7..month)
+            .map(|m| hebrew_month_days(m, year) as i64)
+            .sum::<i64>();
+    }
+    days + hebrew_elapsed_days(year) - 1_373_429
+}
+
+fn hebrew_from_fixed(rd: i64) -> (i64, u32, u32) {
+    // An underestimate: no Hebrew year is longer than 385 days
+    let mut year = (rd + 1_373_429).div_euclid(366);
+    while rd >= fixed_from_hebrew(year + 1, 7, 1) {
+        year += 1;
+    }
+    let mut month = if rd < fixed_from_hebrew(year, 1, 1) {
+        7
+    } else {
+        1
+    };
+    while rd > fixed_from_hebrew(year, month, hebrew_month_days(month, year)) {
+        month += 1;
+    }
+    let day = rd - fixed_from_hebrew(year, month, 1) + 1;
+    (year, month, day as u32)
+}
+
+// Islamic (tabular)
+
+fn islamic_leap(year: i64) -> bool {
+    (14 + 11 * year).rem_euclid(30) < 11
+}
+
+fn fixed_from_islamic(year: i64, month: u32, day: u32) -> i64 {
+    let month = month as i64;
+    day as i64
+        + 29 * (month - 1)
+        + (6 * month - 1).div_euclid(11)
+        + (year - 1) * 354
+        + (3 + 11 * year).div_euclid(30)
+        + ISLAMIC_EPOCH
+        - 1
+}
+
+fn islamic_from_fixed(rd: i64) -> (i64, u32, u32) {
+    let year = (30 * (rd - ISLAMIC_EPOCH) + 10646).div_euclid(10631);
+    let prior_days = rd - fixed_from_islamic(year, 1, 1);
+    let month = (11 * prior_days + 330).div_euclid(325);
+    let day = rd - fixed_from_islamic(year, month as u32, 1) + 1;
+    (year, month as u32, day as u32)
+}
+
+// Persian (Borkowski)
+
+/// Leap status, Gregorian year of Farvardin 1 and its March day
+fn persian_year(year: i64) -> Option<(i64, i64, i64)> {
+    let last = PERSIAN_BREAKS[PERSIAN_BREAKS.len() - 1];
+    if year < PERSIAN_BREAKS[0] || year >= last {
+        return None;
+    }
+    let gregorian_year = year + 621;
+    let mut leap_persian = -14;
+    let mut previous = PERSIAN_BREAKS[0];
+    let mut jump = 0;
+    for &brk in &PERSIAN_BREAKS[1..] {
+        jump = brk - previous;
+        if year < brk {
+            break;
+        }
+        leap_persian += jump / 33 * 8 + jump % 33 / 4;
+        previous = brk;
+    }
+    let mut n = year - previous;
+    leap_persian += n / 33 * 8 + (n % 33 + 3) / 4;
+    if jump % 33 == 4 && jump - n == 4 {
+        leap_persian += 1;
+    }
+    let leap_gregorian = gregorian_year / 4 - (gregorian_year / 100 + 1) * 3 / 4 - 150;
+    let march = 20 + leap_persian - leap_gregorian;
+    if jump - n < 6 {
+        n = n - jump + (jump + 4) / 33 * 33;
+    }
+    let mut leap = ((n + 1) % 33 - 1) % 4;
+    if leap == -1 {
+        leap = 4;
+    }
+    Some((leap, gregorian_year, march))
+}
+
+fn persian_leap(year: i64) -> Option<bool> {
+    persian_year(year).map(|(leap, _, _)| leap == 0)
+}
+
+/// Julian Day Number of `day` `march` of Gregorian `year`
+fn march_jdn(year: i64, march: i64) -> Option<i64> {
+    let first = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, 3, 1)?;
+    Some(first.num_days_from_ce() as i64 + march - 1 + JDN_OFFSET)
+}
+
+fn persian_to_jdn(year: i64, month: u32, day: u32) -> Option<i64> {
+    let (_, gregorian_year, march) = persian_year(year)?;
+    let month = month as i64;
+    Some(
+        march_jdn(gregorian_year, march)? + (month - 1) * 31 - month / 7 * (month - 7) + day as i64
+            - 1,
+    )
+}
+
+fn persian_from_jdn(jdn: i64) -> Option<(i64, u32, u32)> {
+    let gregorian_year = fixed_to_date(jdn - JDN_OFFSET)?.year() as i64;
+    let mut year = gregorian_year - 621;
+    let (leap, _, march) = persian_year(year)?;
+    let mut k = jdn - march_jdn(gregorian_year, march)?;
+    if k >= 0 {
+        if k <= 185 {
+            return Some((year, (1 + k / 31) as u32, (k % 31 + 1) as u32));
+        }
+        k -= 186;
+    } else {
+        year -= 1;
+        k += 179;
+        if leap == 1 {
+            k += 1;
+        }
+    }
+    Some((year, (7 + k / 30) as u32, (k % 30 + 1) as u32))
+}
+
+// Japanese
+
+fn japanese_era_index(date: NaiveDate) -> Option<usize> {
+    JAPANESE_ERAS.iter().rposition(|(_, _, (y, m, d))| {
+        NaiveDate::from_ymd_opt(*y, *m, *d).is_some_and(|start| date >= start)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn ymd(calendar: Calendar, gregorian: &str) -> (i64, u32, u32) {
+        let converted = from_gregorian(calendar, date(gregorian)).unwrap();
+        (converted.year, converted.month, converted.day)
+    }
+
+    #[test]
+    fn test_known_correspondences() {
+        assert_eq!(ymd(Calendar::Persian, "2024-03-20"), (1403, 1, 1));
+        assert_eq!(ymd(Calendar::Persian, "2025-03-21"), (1404, 1, 1));
+        assert_eq!(ymd(Calendar::Persian, "2024-03-19"), (1402, 12, 29));
+        assert_eq!(ymd(Calendar::Hebrew, "2024-10-03"), (5785, 7, 1));
+        assert_eq!(ymd(Calendar::Hebrew, "2023-09-16"), (5784, 7, 1));
+        // Passover 5784
+        assert_eq!(ymd(Calendar::Hebrew, "2024-04-23"), (5784, 1, 15));
+        assert_eq!(ymd(Calendar::Islamic, "2023-07-19"), (1445, 1, 1));
+        assert_eq!(ymd(Calendar::Islamic, "2024-03-11"), (1445, 9, 1));
+        assert_eq!(ymd(Calendar::Japanese, "2019-05-01"), (1, 5, 1));
+        assert_eq!(ymd(Calendar::Japanese, "2019-04-30"), (31, 4, 30));
+        assert_eq!(ymd(Calendar::Japanese, "1989-01-07"), (64, 1, 7));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let reiwa = from_gregorian(Calendar::Japanese, date("2019-05-01")).unwrap();
+        assert_eq!(reiwa.era, "Reiwa");
+        assert_eq!(reiwa.formatted, "Reiwa 1-05-01");
+        assert_eq!(reiwa.native.as_deref(), Some("令和元年5月1日"));
+
+        let nowruz = from_gregorian(Calendar::Persian, date("2024-03-20")).unwrap();
+        assert_eq!(nowruz.formatted, "1 Farvardin 1403 AP");
+        assert!(nowruz.leap_year);
+
+        let adar = from_gregorian(Calendar::Hebrew, date("2024-03-01")).unwrap();
+        assert_eq!(adar.month_name, "Adar I");
+        let ramadan = from_gregorian(Calendar::Islamic, date("2024-03-11")).unwrap();
+        assert_eq!(ramadan.month_name, "Ramadan");
+        assert!(ramadan.note.unwrap().starts_with("Tabular"));
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let start = date("1900-01-01");
+        for offset in (0..80_000).step_by(7) {
+            let day = start + chrono::Duration::days(offset);
+            for calendar in [
+                Calendar::Hebrew,
+                Calendar::Islamic,
+                Calendar::Persian,
+                Calendar::Japanese,
+            ] {
+                let converted = from_gregorian(calendar, day).unwrap();
+                let era = (calendar == Calendar::Japanese).then_some(converted.era.as_str());
+                let back = to_gregorian(
+                    calendar,
+                    era,
+                    converted.year,
+                    converted.month,
+                    converted.day,
+                )
+                .unwrap();
+                assert_eq!(back, day, "{:?} {:?}", calendar, converted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_conversion() {
+        assert_eq!(
+            to_gregorian(Calendar::Japanese, Some("reiwa"), 1, 5, 1).unwrap(),
+            date("2019-05-01")
+        );
+        assert_eq!(
+            to_gregorian(Calendar::Japanese, Some("平成"), 31, 4, 30).unwrap(),
+            date("2019-04-30")
+        );
+        assert_eq!(
+            to_gregorian(Calendar::Persian, None, 1403, 1, 1).unwrap(),
+            date("2024-03-20")
+        );
+        // Esfand 30 only in leap years (1403 is one, 1402 is not)
+        assert!(to_gregorian(Calendar::Persian, None, 1403, 12, 30).is_ok());
+        assert!(to_gregorian(Calendar::Persian, None, 1402, 12, 30).is_err());
+        // Reiwa began on 1 May; Heisei ended on 30 April
+        assert!(to_gregorian(Calendar::Japanese, Some("Reiwa"), 1, 4, 30).is_err());
+        assert!(to_gregorian(Calendar::Japanese, Some("Heisei"), 31, 5, 1).is_err());
+        assert!(to_gregorian(Calendar::Japanese, None, 1, 5, 1).is_err());
+        assert!(to_gregorian(Calendar::Japanese, Some("Edo"), 1, 5, 1).is_err());
+        assert!(from_gregorian(Calendar::Japanese, date("1850-01-01")).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+// Timestamp cards: everything about one instant on a few readable lines
+//
+// A card pairs a plain-text block, meant to be shown to a person as is, with
+// the same facts as structured fields. The facts come from the existing
+// pieces: the relative phrase from `humanize`, the ISO week from
+// `WeekConvention::Iso`, the local times from the world clock rows. `render`
+// is a fixed template, so the same card always renders to the same text.
+
+use super::calendar::WeekConvention;
+use super::humanize;
+use super::unix;
+use super::world_clock::{self, WorldClockRow};
+use crate::error::TimeServerError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Width of the label column in the text block
+const LABEL_WIDTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimestampCard {
+    /// RFC 3339, UTC
+    pub utc: String,
+    pub unix_timestamp: i64,
+    /// "in 3 days", "2 hours ago"
+    pub relative: String,
+    /// What `relative` is measured from, RFC 3339 UTC
+    pub reference: String,
+    /// Day of the week of the UTC date
+    pub weekday: String,
+    /// ISO 8601 week of the UTC date, e.g. 2024-W11
+    pub iso_week: String,
+    /// One row per requested zone, sorted by UTC offset
+    pub zones: Vec<WorldClockRow>,
+}
+
+/// Unix seconds or RFC 3339, the two forms people paste
+pub fn parse_timestamp(text: &str) -> Result<DateTime<Utc>, TimeServerError> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<i64>() {
+        return unix::datetime_at(seconds);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| {
+            TimeServerError::InvalidArgument(format!(
+                "'{}' is neither Unix seconds nor an RFC 3339 timestamp",
+                text
+            ))
+        })
+}
+
+impl TimestampCard {
+    /// The card for `at` as seen from `now`; `zones` may be empty
+    pub fn new<S: AsRef<str>>(
+        at: DateTime<Utc>,
+        now: DateTime<Utc>,
+        zones: &[S],
+    ) -> Result<Self, TimeServerError> {
+        let rows = if zones.is_empty() {
+            Vec::new()
+        } else {
+            world_clock::world_clock(at, zones)?.rows
+        };
+        let week = WeekConvention::Iso.week_of(at.date_naive());
+        Ok(Self {
+            utc: at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            unix_timestamp: at.timestamp(),
+            relative: humanize::relative(at, now),
+            reference: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+            weekday: at.format("%A").to_string(),
+            iso_week: format!("{}-W{:02}", week.week_year, week.week),
+            zones: rows,
+        })
+    }
+
+    /// The plain-text block: one labelled line per fact, then a line per zone
+    pub fn render(&self) -> String {
+        let line = |label: &str, value: &str| {
+            format!("{:<width$}  {}\n", label, value, width = LABEL_WIDTH)
+        };
+        let mut out = line("UTC", &self.utc);
+        out.push_str(&line("Relative", &self.relative));
+        out.push_str(&line("Weekday", &self.weekday));
+        out.push_str(&line("ISO week", &self.iso_week));
+        out.push_str(&line("Unix", &self.unix_timestamp.to_string()));
+
+        if self.zones.is_empty() {
+            out.push_str(&line("Local", "(no timezones requested)"));
+            return out;
+        }
+        let name_width = self
+            .zones
+            .iter()
+            .map(|row| row.timezone.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, row) in self.zones.iter().enumerate() {
+            let value = format!(
+                "{:<width$}  {} {}  {}",
+                row.timezone,
+                row.date,
+                row.local_time,
+                row.utc_offset,
+                width = name_width
+            );
+            out.push_str(&line(if i == 0 { "Local" } else { "" }, value.trim_end()));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        parse_timestamp(text).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_with_three_zones() {
+        let card = TimestampCard::new(
+            at("1710497730"),
+            at("2024-03-12T08:00:00Z"),
+            &["Asia/Tokyo", "America/New_York", "Europe/London"],
+        )
+        .unwrap();
+        assert_eq!(
+            card.render(),
+            "\
+UTC         2024-03-15T10:15:30Z
+Relative    in 3 days
+Weekday     Friday
+ISO week    2024-W11
+Unix        1710497730
+Local       America/New_York  2024-03-15 06:15:30  -04:00
+            Europe/London     2024-03-15 10:15:30  +00:00
+            Asia/Tokyo        2024-03-15 19:15:30  +09:00
+"
+        );
+        assert_eq!(card.zones.len(), 3);
+        assert_eq!(card.reference, "2024-03-12T08:00:00Z");
+    }
+
+    #[test]
+    fn test_empty_zone_list() {
+        let zones: [&str; 0] = [];
+        let card = TimestampCard::new(
+            at("2020-12-31T23:59:59.5Z"),
+            at("2021-01-01T00:00:00Z"),
+            &zones,
+        )
+        .unwrap();
+        assert!(card.zones.is_empty());
+        assert_eq!(card.iso_week, "2020-W53");
+        assert_eq!(card.relative, "now");
+        assert!(card
+            .render()
+            .ends_with("Local       (no timezones requested)\n"));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(at("0").timestamp(), 0);
+        assert_eq!(at(" 2024-03-15T11:15:30+01:00 ").timestamp(), 1_710_497_730);
+        assert!(parse_timestamp("next tuesday").is_err());
+        assert!(parse_timestamp("99999999999999").is_err());
+        assert!(TimestampCard::new(at("0"), at("0"), &["Mars/Olympus"]).is_err());
+    }
+}
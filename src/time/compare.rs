@@ -0,0 +1,681 @@
+// Ordering instants given in different representations
+//
+// Comparing "1710496800" with "2024-03-15T10:00:00Z" as strings gives the
+// wrong answer, so each value is first resolved to nanoseconds since the
+// epoch. A value says how to read it with `representation` (or a strftime
+// `format`); `auto` takes numbers as Unix seconds and tries RFC 3339, then
+// RFC 2822, on text. A value that cannot be read fails with `InvalidValue`
+// naming it, so the caller knows which of the inputs to fix.
+//
+// The difference `b` minus `a` goes through `duration::normalize_interval`:
+// under order_policy absolute a `b` before `a` gives a positive difference
+// and `inputs_swapped`, while `earlier` still says which came first.
+
+use super::duration::{self, DurationStyle, FormatOptions, IntervalRules};
+use super::unix::{self, UnixTime, NANOS_PER_SECOND};
+use crate::error::TimeServerError;
+use crate::warnings::Warnings;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How a time value is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Representation {
+    /// Numbers are Unix seconds; text is RFC 3339, RFC 2822 or Unix seconds
+    #[default]
+    Auto,
+    UnixSeconds,
+    UnixMs,
+    Rfc3339,
+    Rfc2822,
+    /// strftime `format`; without an offset in it the time is taken as UTC
+    Custom,
+}
+
+/// A time value as JSON carries it
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RawTime {
+    Integer(i64),
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for RawTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(n) => write!(f, "{}", n),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// One input to `compare`
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimeValue {
+    /// Unix seconds or milliseconds, or a timestamp string
+    pub value: RawTime,
+    /// How to read `value` (defaults to auto)
+    #[serde(default)]
+    pub representation: Representation,
+    /// strftime format for a custom representation (implies custom)
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+impl TimeValue {
+    pub fn new(value: RawTime) -> Self {
+        Self {
+            value,
+            representation: Representation::Auto,
+            format: None,
+        }
+    }
+
+    /// Read with `format` instead of guessing
+    pub fn custom(value: &str, format: &str) -> Self {
+        Self {
+            value: RawTime::Text(value.to_string()),
+            representation: Representation::Custom,
+            format: Some(format.to_string()),
+        }
+    }
+
+    /// The instant this value names; errors are not yet tied to a field
+    pub fn resolve(&self) -> Result<ResolvedTime, TimeServerError> {
+        let representation = match (self.representation, &self.format) {
+            (Representation::Auto | Representation::Custom, Some(_)) => Representation::Custom,
+            (Representation::Custom, None) => {
+                return Err(TimeServerError::InvalidArgument(
+                    "a custom representation needs a format".into(),
+                ))
+            }
+            (other, Some(_)) => {
+                return Err(TimeServerError::InvalidArgument(format!(
+                    "format only applies to the custom representation, not {}",
+                    representation_name(other)
+                )))
+            }
+            (other, None) => other,
+        };
+        let (nanos, representation) = match (representation, &self.value) {
+            (Representation::Auto, RawTime::Text(text)) => auto_text(text)?,
+            (Representation::Auto | Representation::UnixSeconds, value) => (
+                unix_nanos(value, NANOS_PER_SECOND)?,
+                Representation::UnixSeconds,
+            ),
+            (Representation::UnixMs, value) => (
+                unix_nanos(value, NANOS_PER_SECOND / 1000)?,
+                Representation::UnixMs,
+            ),
+            (other, RawTime::Text(text)) => {
+                let at = match other {
+                    Representation::Rfc3339 => rfc3339(text)?,
+                    Representation::Rfc2822 => rfc2822(text)?,
+                    _ => custom(text, self.format.as_deref().unwrap_or_default())?,
+                };
+                (UnixTime::from_datetime(at).nanos_since_epoch, other)
+            }
+            (other, value) => {
+                return Err(TimeServerError::InvalidTimestamp(format!(
+                    "{} is a number, but {} expects text",
+                    value,
+                    representation_name(other)
+                )))
+            }
+        };
+        let at = UnixTime::from_nanos(nanos)?.to_datetime();
+        Ok(ResolvedTime {
+            input: self.value.to_string(),
+            representation,
+            utc: at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            unix_nanos: nanos,
+        })
+    }
+}
+
+/// A time value on its own, or with how to read it
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TimeInput {
+    Bare(RawTime),
+    Described(TimeValue),
+}
+
+impl From<TimeInput> for TimeValue {
+    fn from(input: TimeInput) -> Self {
+        match input {
+            TimeInput::Bare(value) => Self::new(value),
+            TimeInput::Described(value) => value,
+        }
+    }
+}
+
+fn representation_name(representation: Representation) -> &'static str {
+    match representation {
+        Representation::Auto => "auto",
+        Representation::UnixSeconds => "unix_seconds",
+        Representation::UnixMs => "unix_ms",
+        Representation::Rfc3339 => "rfc3339",
+        Representation::Rfc2822 => "rfc2822",
+        Representation::Custom => "custom",
+    }
+}
+
+/// Text under `auto`: RFC 3339, then RFC 2822, then a Unix seconds count
+fn auto_text(text: &str) -> Result<(i128, Representation), TimeServerError> {
+    if let Ok(at) = rfc3339(text) {
+        return Ok((
+            UnixTime::from_datetime(at).nanos_since_epoch,
+            Representation::Rfc3339,
+        ));
+    }
+    if let Ok(at) = rfc2822(text) {
+        return Ok((
+            UnixTime::from_datetime(at).nanos_since_epoch,
+            Representation::Rfc2822,
+        ));
+    }
+    if let Some(nanos) = decimal_nanos(text.trim(), NANOS_PER_SECOND) {
+        return Ok((nanos, Representation::UnixSeconds));
+    }
+    Err(TimeServerError::InvalidTimestamp(format!(
+        "'{}' is not Unix seconds, RFC 3339 or RFC 2822; set representation or format",
+        text
+    )))
+}
+
+/// A Unix count in units of `per_unit` nanoseconds
+fn unix_nanos(value: &RawTime, per_unit: i128) -> Result<i128, TimeServerError> {
+    let nanos = match value {
+        RawTime::Integer(n) => Some(i128::from(*n) * per_unit),
+        // Whole and fraction apart: 1.7e18 ns is already past f64's exact range
+        RawTime::Number(n) if n.is_finite() && n.abs() < 1e21 => {
+            Some(n.trunc() as i128 * per_unit + (n.fract() * per_unit as f64).round() as i128)
+        }
+        RawTime::Number(_) => None,
+        RawTime::Text(text) => decimal_nanos(text.trim(), per_unit),
+    };
+    nanos.ok_or_else(|| {
+        TimeServerError::InvalidTimestamp(format!("'{}' is not a Unix time count", value))
+    })
+}
+
+/// "-12.5" in units of `per_unit` nanoseconds, exact up to nanoseconds;
+/// further digits are dropped
+fn decimal_nanos(text: &str, per_unit: i128) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() && fraction.is_empty()
+        || !all_digits(whole)
+        || !all_digits(fraction)
+        || whole.len() > 21
+    {
+        return None;
+    }
+    let mut nanos = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<i128>().ok()? * per_unit
+    };
+    let mut scale = per_unit;
+    for digit in fraction.bytes() {
+        scale /= 10;
+        if scale == 0 {
+            break;
+        }
+        nanos += i128::from(digit - b'0') * scale;
+    }
+    Some(if negative { -nanos } else { nanos })
+}
+
+fn rfc3339(text: &str) -> Result<DateTime<Utc>, TimeServerError> {
+    DateTime::parse_from_rfc3339(text.trim())
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|e| {
+            TimeServerError::InvalidTimestamp(format!("'{}' is not RFC 3339: {}", text, e))
+        })
+}
+
+fn rfc2822(text: &str) -> Result<DateTime<Utc>, TimeServerError> {
+    DateTime::parse_from_rfc2822(text.trim())
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|e| {
+            TimeServerError::InvalidTimestamp(format!("'{}' is not RFC 2822: {}", text, e))
+        })
+}
+
+/// `text` read with strftime `format`: with an offset if the format has
+/// one, else a date and time in UTC, else a date at midnight UTC
+fn custom(text: &str, format: &str) -> Result<DateTime<Utc>, TimeServerError> {
+    if let Ok(at) = DateTime::parse_from_str(text, format) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(at) = NaiveDateTime::parse_from_str(text, format) {
+        return Ok(at.and_utc());
+    }
+    NaiveDate::parse_from_str(text, format)
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|e| {
+            TimeServerError::InvalidTimestamp(format!(
+                "'{}' does not match format '{}': {}",
+                text, format, e
+            ))
+        })
+}
+
+/// One input after resolution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedTime {
+    /// The value as given
+    pub input: String,
+    /// How it was read; `auto` is replaced by what matched
+    pub representation: Representation,
+    /// RFC 3339, UTC
+    pub utc: String,
+    #[serde(serialize_with = "unix::serialize_nanos")]
+    pub unix_nanos: i128,
+}
+
+/// Which range ends count as inside
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeBounds {
+    /// [start, end]
+    #[default]
+    Inclusive,
+    /// (start, end)
+    Exclusive,
+    /// [start, end)
+    HalfOpen,
+}
+
+/// Where `a` falls relative to a range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangePosition {
+    Before,
+    AtStart,
+    Inside,
+    AtEnd,
+    After,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RangeCheck {
+    /// The earlier of `b` and `c`, RFC 3339 UTC
+    pub start: String,
+    /// The later of `b` and `c`
+    pub end: String,
+    pub bounds: RangeBounds,
+    pub position: RangePosition,
+    /// Whether `a` is in the range under `bounds`
+    pub within: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub a: ResolvedTime,
+    pub b: ResolvedTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c: Option<ResolvedTime>,
+    /// "a", "b" or "equal"
+    pub earlier: &'static str,
+    /// `b` minus `a`: positive when `a` is earlier, and never negative
+    /// under order_policy absolute
+    #[serde(serialize_with = "unix::serialize_nanos")]
+    pub difference_nanos: i128,
+    pub difference_seconds: f64,
+    /// The difference in short human form, "2h 5m"
+    pub difference: String,
+    /// Present when `c` was given: `a` against the range from `b` to `c`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<RangeCheck>,
+    /// `b` was before `a` and order_policy absolute swapped them
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inputs_swapped: bool,
+    #[serde(skip_serializing_if = "Warnings::is_empty")]
+    pub warnings: Warnings,
+}
+
+/// Order `a` against `b`, and against the range `b`..`c` when `c` is given
+pub fn compare(
+    a: &TimeValue,
+    b: &TimeValue,
+    c: Option<&TimeValue>,
+    bounds: RangeBounds,
+    rules: IntervalRules,
+) -> Result<Comparison, TimeServerError> {
+    let resolve = |field: &str, value: &TimeValue| {
+        value
+            .resolve()
+            .map_err(|e| TimeServerError::for_value(field, e))
+    };
+    let a = resolve("a", a)?;
+    let b = resolve("b", b)?;
+    let c = c.map(|c| resolve("c", c)).transpose()?;
+
+    let interval = duration::normalize_interval(a.unix_nanos, b.unix_nanos, ("a", "b"), rules)?;
+    let difference_nanos = interval.nanos();
+    let earlier = match b.unix_nanos.cmp(&a.unix_nanos) {
+        Ordering::Greater => "a",
+        Ordering::Less => "b",
+        Ordering::Equal => "equal",
+    };
+    let range = c.as_ref().map(|c| {
+        let (start, end) = if b.unix_nanos <= c.unix_nanos {
+            (&b, c)
+        } else {
+            (c, &b)
+        };
+        range_check(a.unix_nanos, start, end, bounds)
+    });
+    Ok(Comparison {
+        difference_seconds: difference_nanos as f64 / NANOS_PER_SECOND as f64,
+        difference: duration::format(
+            difference_nanos,
+            FormatOptions {
+                style: DurationStyle::HumanShort,
+                ..Default::default()
+            },
+        ),
+        a,
+        b,
+        c,
+        earlier,
+        difference_nanos,
+        range,
+        inputs_swapped: interval.swapped,
+        warnings: interval.warning(("a", "b")).into_iter().collect(),
+    })
+}
+
+fn range_check(
+    at: i128,
+    start: &ResolvedTime,
+    end: &ResolvedTime,
+    bounds: RangeBounds,
+) -> RangeCheck {
+    // A zero-length range: the instant is its start, and also its end
+    let position = match (at.cmp(&start.unix_nanos), at.cmp(&end.unix_nanos)) {
+        (Ordering::Less, _) => RangePosition::Before,
+        (Ordering::Equal, _) => RangePosition::AtStart,
+        (_, Ordering::Less) => RangePosition::Inside,
+        (_, Ordering::Equal) => RangePosition::AtEnd,
+        (_, Ordering::Greater) => RangePosition::After,
+    };
+    let empty = start.unix_nanos == end.unix_nanos;
+    let within = match (position, bounds) {
+        (RangePosition::Inside, _) => true,
+        (RangePosition::AtStart, RangeBounds::Inclusive) => true,
+        (RangePosition::AtStart, RangeBounds::HalfOpen) => !empty,
+        (RangePosition::AtEnd, RangeBounds::Inclusive) => true,
+        _ => false,
+    };
+    RangeCheck {
+        start: start.utc.clone(),
+        end: end.utc.clone(),
+        bounds,
+        position,
+        within,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(text: &str) -> TimeValue {
+        TimeValue::new(RawTime::Text(text.to_string()))
+    }
+
+    fn with(value: RawTime, representation: Representation) -> TimeValue {
+        TimeValue {
+            representation,
+            ..TimeValue::new(value)
+        }
+    }
+
+    #[test]
+    fn test_same_instant_in_every_representation() {
+        let values = [
+            TimeValue::new(RawTime::Integer(1_710_496_800)),
+            auto("1710496800"),
+            with(RawTime::Integer(1_710_496_800_000), Representation::UnixMs),
+            with(
+                RawTime::Text("1710496800000".into()),
+                Representation::UnixMs,
+            ),
+            auto("2024-03-15T10:00:00Z"),
+            auto("2024-03-15T19:00:00+09:00"),
+            auto("Fri, 15 Mar 2024 06:00:00 -0400"),
+            TimeValue::custom("15/03/2024 10:00", "%d/%m/%Y %H:%M"),
+            TimeValue::custom("2024-03-15 12:00 +0200", "%Y-%m-%d %H:%M %z"),
+        ];
+        for value in &values {
+            let comparison = compare(
+                &values[0],
+                value,
+                None,
+                RangeBounds::default(),
+                IntervalRules::default(),
+            )
+            .unwrap();
+            assert_eq!(comparison.difference_nanos, 0, "{:?}", value);
+            assert_eq!(comparison.earlier, "equal");
+            assert_eq!(comparison.b.utc, "2024-03-15T10:00:00Z");
+        }
+        let detected = |value: &TimeValue| value.resolve().unwrap().representation;
+        assert_eq!(detected(&values[1]), Representation::UnixSeconds);
+        assert_eq!(detected(&values[5]), Representation::Rfc3339);
+        assert_eq!(detected(&values[6]), Representation::Rfc2822);
+        assert_eq!(detected(&values[7]), Representation::Custom);
+    }
+
+    #[test]
+    fn test_sub_second_difference() {
+        let a = auto("2024-03-15T10:00:00.000000001Z");
+        let b = with(RawTime::Number(1_710_496_800.25), Representation::Auto);
+        let comparison = compare(
+            &a,
+            &b,
+            None,
+            RangeBounds::default(),
+            IntervalRules::default(),
+        )
+        .unwrap();
+        assert_eq!(comparison.difference_nanos, 249_999_999);
+        assert_eq!(comparison.earlier, "a");
+
+        let ms = with(
+            RawTime::Text("1710496800000.5".into()),
+            Representation::UnixMs,
+        );
+        let reversed = compare(
+            &ms,
+            &a,
+            None,
+            RangeBounds::default(),
+            IntervalRules::default(),
+        )
+        .unwrap();
+        assert_eq!(reversed.difference_nanos, -499_999);
+        assert_eq!(reversed.earlier, "b");
+        assert!(reversed.difference.starts_with('-'));
+    }
+
+    #[test]
+    fn test_order_policy() {
+        use super::duration::OrderPolicy;
+        let (a, b) = (auto("2024-03-15T11:00:00Z"), auto("2024-03-15T10:00:00Z"));
+        let with_policy = |policy| {
+            compare(
+                &a,
+                &b,
+                None,
+                RangeBounds::default(),
+                IntervalRules {
+                    policy,
+                    ..IntervalRules::default()
+                },
+            )
+        };
+
+        let signed = with_policy(OrderPolicy::Signed).unwrap();
+        assert_eq!(signed.difference_nanos, -3600 * NANOS_PER_SECOND);
+        assert!(!signed.inputs_swapped);
+        let value = serde_json::to_value(&signed).unwrap();
+        assert!(value.get("inputs_swapped").is_none());
+        assert!(value.get("warnings").is_none());
+
+        let absolute = with_policy(OrderPolicy::Absolute).unwrap();
+        assert_eq!(absolute.difference_nanos, 3600 * NANOS_PER_SECOND);
+        assert_eq!(absolute.difference, "1h");
+        assert_eq!(absolute.earlier, "b");
+        let value = serde_json::to_value(&absolute).unwrap();
+        assert_eq!(value["inputs_swapped"], true);
+        assert_eq!(value["warnings"][0]["code"], "INPUTS_SWAPPED");
+
+        let error = with_policy(OrderPolicy::Strict).unwrap_err();
+        assert_eq!(error.code(), "invalid_argument");
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let start = auto("2024-03-15T10:00:00Z");
+        let end = auto("2024-03-15T11:00:00Z");
+        let check = |at: &str, bounds| {
+            compare(
+                &auto(at),
+                &start,
+                Some(&end),
+                bounds,
+                IntervalRules::default(),
+            )
+            .unwrap()
+            .range
+            .unwrap()
+        };
+
+        let at_start = check("2024-03-15T10:00:00Z", RangeBounds::Inclusive);
+        assert_eq!(at_start.position, RangePosition::AtStart);
+        assert!(at_start.within);
+        assert!(!check("2024-03-15T10:00:00Z", RangeBounds::Exclusive).within);
+        assert!(check("2024-03-15T10:00:00Z", RangeBounds::HalfOpen).within);
+
+        let at_end = check("2024-03-15T11:00:00Z", RangeBounds::Inclusive);
+        assert_eq!(at_end.position, RangePosition::AtEnd);
+        assert!(at_end.within);
+        assert!(!check("2024-03-15T11:00:00Z", RangeBounds::Exclusive).within);
+        assert!(!check("2024-03-15T11:00:00Z", RangeBounds::HalfOpen).within);
+
+        let inside = check("2024-03-15T10:30:00Z", RangeBounds::Exclusive);
+        assert_eq!(inside.position, RangePosition::Inside);
+        assert!(inside.within);
+        let after = check("2024-03-15T11:00:00.000000001Z", RangeBounds::Inclusive);
+        assert_eq!(after.position, RangePosition::After);
+        assert!(!after.within);
+
+        // The range ends may come in either order
+        let swapped = compare(
+            &auto("2024-03-15T10:30:00Z"),
+            &end,
+            Some(&start),
+            RangeBounds::Inclusive,
+            IntervalRules::default(),
+        )
+        .unwrap()
+        .range
+        .unwrap();
+        assert_eq!(swapped.start, "2024-03-15T10:00:00Z");
+        assert!(swapped.within);
+    }
+
+    #[test]
+    fn test_bad_value_names_itself() {
+        let good = auto("2024-03-15T10:00:00Z");
+        let error = compare(
+            &good,
+            &auto("next tuesday"),
+            None,
+            RangeBounds::default(),
+            IntervalRules::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), "invalid_value");
+        assert_eq!(error.details()["field"], "b");
+        assert_eq!(error.details()["cause"], "invalid_timestamp");
+        let message = error.to_string();
+        assert!(message.starts_with("b: "), "{}", message);
+        assert!(message.contains("'next tuesday'"), "{}", message);
+
+        let error = compare(
+            &good,
+            &good,
+            Some(&TimeValue::custom("15.03.2024", "%Y-%m-%d")),
+            RangeBounds::default(),
+            IntervalRules::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.details()["field"], "c");
+
+        let out_of_range = with(RawTime::Integer(i64::MAX), Representation::UnixMs);
+        let error = compare(
+            &out_of_range,
+            &good,
+            None,
+            RangeBounds::default(),
+            IntervalRules::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.details()["field"], "a");
+        assert_eq!(error.details()["cause"], "timestamp_out_of_range");
+
+        let number_as_rfc3339 = with(RawTime::Integer(0), Representation::Rfc3339);
+        assert!(number_as_rfc3339.resolve().is_err());
+        let format_without_custom = TimeValue {
+            format: Some("%Y".into()),
+            ..with(RawTime::Text("2024".into()), Representation::UnixSeconds)
+        };
+        assert!(format_without_custom.resolve().is_err());
+    }
+
+    #[test]
+    fn test_bare_and_described_inputs() {
+        let bare: TimeInput = serde_json::from_value(serde_json::json!(1_710_496_800)).unwrap();
+        assert_eq!(
+            TimeValue::from(bare),
+            TimeValue::new(RawTime::Integer(1_710_496_800))
+        );
+        let described: TimeInput = serde_json::from_value(serde_json::json!({
+            "value": "15/03/2024",
+            "format": "%d/%m/%Y"
+        }))
+        .unwrap();
+        let value = TimeValue::from(described);
+        assert_eq!(value.resolve().unwrap().utc, "2024-03-15T00:00:00Z");
+    }
+
+    #[test]
+    fn test_decimal_nanos() {
+        assert_eq!(decimal_nanos("1.5", NANOS_PER_SECOND), Some(1_500_000_000));
+        assert_eq!(decimal_nanos("-0.000000001", NANOS_PER_SECOND), Some(-1));
+        assert_eq!(decimal_nanos(".25", 1_000_000), Some(250_000));
+        assert_eq!(
+            decimal_nanos("1.0000000019", NANOS_PER_SECOND),
+            Some(1_000_000_001)
+        );
+        assert_eq!(decimal_nanos("1e9", NANOS_PER_SECOND), None);
+        assert_eq!(decimal_nanos(".", NANOS_PER_SECOND), None);
+        assert_eq!(decimal_nanos("", NANOS_PER_SECOND), None);
+    }
+}
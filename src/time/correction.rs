@@ -0,0 +1,106 @@
+// What a corrected-time response says about the correction
+//
+// `corrected=true` asks for the system time shifted by the measured NTP
+// offset; the offset cache in `ntp::correction` decides whether it can be
+// applied. These are the fields every such response carries, kept apart
+// from the cache so builds without the `ntp` feature can still say that no
+// correction was made.
+
+use super::TimeSource;
+use crate::warnings::{Warning, WarningCode};
+use serde::Serialize;
+
+/// What was done to a timestamp; flattened into corrected responses
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Correction {
+    /// Whether the returned time includes the NTP offset
+    pub corrected: bool,
+    pub correction_applied_ms: f64,
+    /// Age of the offset measurement in seconds
+    pub offset_age_s: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correction_note: Option<String>,
+    /// Set when a requested correction could not be applied
+    #[serde(skip)]
+    pub fallback: Option<WarningCode>,
+}
+
+impl Correction {
+    pub(crate) fn skipped(offset_age_s: Option<f64>, note: String) -> Self {
+        Self {
+            corrected: false,
+            correction_applied_ms: 0.0,
+            offset_age_s,
+            correction_note: Some(note),
+            fallback: None,
+        }
+    }
+
+    pub(crate) fn fell_back(mut self, code: WarningCode) -> Self {
+        self.fallback = Some(code);
+        self
+    }
+
+    /// The coded warning for a correction that was requested but not applied
+    pub fn warning(&self) -> Option<Warning> {
+        let code = self.fallback?;
+        let message = self.correction_note.clone().unwrap_or_default();
+        Some(Warning::new(code, message).with_field("corrected"))
+    }
+
+    /// The time came from a reference clock, so there was nothing to correct
+    pub fn from_reference(source: TimeSource) -> Self {
+        Self::skipped(
+            None,
+            format!(
+                "time read from the {} reference clock; no offset applied",
+                source
+            ),
+        )
+    }
+}
+
+/// A response body with its time source and the correction fields alongside it
+#[derive(Debug, Serialize)]
+pub struct Corrected<T> {
+    #[serde(flatten)]
+    pub time: T,
+    pub source: TimeSource,
+    #[serde(flatten)]
+    pub correction: Option<Correction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrected_response_flattens() {
+        let body = Corrected {
+            time: serde_json::json!({"seconds": 1}),
+            source: TimeSource::Ntp,
+            correction: Some(Correction {
+                corrected: true,
+                correction_applied_ms: 1.5,
+                offset_age_s: Some(2.0),
+                correction_note: None,
+                fallback: None,
+            }),
+        };
+        let value = serde_json::to_value(&body).unwrap();
+        assert_eq!(value["seconds"], 1);
+        assert_eq!(value["correction_applied_ms"], 1.5);
+        assert_eq!(value["source"], "ntp");
+        assert!(value.get("correction_note").is_none());
+
+        let plain = Corrected {
+            time: serde_json::json!({"seconds": 1}),
+            source: TimeSource::System,
+            correction: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            serde_json::json!({"seconds": 1, "source": "system"})
+        );
+    }
+}
@@ -0,0 +1,277 @@
+// Five-field cron expressions, evaluated in UTC
+//
+// `minute hour day-of-month month day-of-week`, each field `*`, a number, a
+// range `a-b`, a step `*/n` or `a-b/n`, or a comma-separated list of those.
+// Months and weekdays also take three-letter names (`JAN`, `SUN`), and
+// Sunday is both 0 and 7. As in Vixie cron, when both day fields are
+// restricted a day matches if either does. `@hourly`, `@daily`, `@weekly`,
+// `@monthly` and `@yearly` stand for the usual expressions.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// Days searched for the next match; a Feb 29 that must fall on a given
+/// weekday recurs within 28 years
+const SEARCH_DAYS: i64 = 366 * 29;
+
+const MONTH_NAMES: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const DAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// A cron expression that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronError(pub String);
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CronError {}
+
+/// A parsed cron expression; each field is a bit set of allowed values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Day-of-month was `*`
+    any_day: bool,
+    /// Day-of-week was `*`
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The expression as written
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first matching minute strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after
+            .with_second(0)
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(after)
+            + Duration::minutes(1);
+        let first_day = start.date_naive();
+        for offset in 0..SEARCH_DAYS {
+            let date = first_day + Duration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+            let (from_hour, from_minute) = if offset == 0 {
+                (start.hour(), start.minute())
+            } else {
+                (0, 0)
+            };
+            for hour in from_hour..24 {
+                if self.hours & (1 << hour) == 0 {
+                    continue;
+                }
+                let first_minute = if hour == from_hour { from_minute } else { 0 };
+                if let Some(minute) = (first_minute..60).find(|m| self.minutes & (1 << m) != 0) {
+                    let time = date.and_hms_opt(hour, minute, 0)?;
+                    return Some(Utc.from_utc_datetime(&time));
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            (false, true) => day,
+            (true, false) => weekday,
+            (true, true) => true,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let expanded = match text.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ if text.starts_with('@') => {
+                return Err(CronError(format!("unknown cron macro '{}'", text)))
+            }
+            _ => text,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(CronError(format!(
+                "expected 5 cron fields (minute hour day month weekday), got {}",
+                fields.len()
+            )));
+        };
+        // Sunday may be written as 7
+        let weekdays = parse_field(weekday, "weekday", 0, 7, DAY_NAMES, 0)?;
+        Ok(Self {
+            expression: text.to_string(),
+            minutes: parse_field(minute, "minute", 0, 59, &[], 0)?,
+            hours: parse_field(hour, "hour", 0, 23, &[], 0)? as u32,
+            days: parse_field(day, "day of month", 1, 31, &[], 0)? as u32,
+            months: parse_field(month, "month", 1, 12, MONTH_NAMES, 1)? as u16,
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// Bit set of the values one field allows; `names[i]` stands for `first_name + i`
+fn parse_field(
+    field: &str,
+    label: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    first_name: u32,
+) -> Result<u64, CronError> {
+    let invalid = || {
+        CronError(format!(
+            "invalid {} field '{}' (values {}-{})",
+            label, field, min, max
+        ))
+    };
+    let value = |text: &str| -> Result<u32, CronError> {
+        let upper = text.to_ascii_uppercase();
+        let named = names
+            .iter()
+            .position(|name| *name == upper)
+            .map(|i| i as u32 + first_name);
+        named
+            .or_else(|| text.parse().ok())
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(invalid)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(invalid)?,
+            ),
+            None => (part, 1),
+        };
+        let (low, high) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((low, high)) => (value(low)?, value(high)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let v = value(range)?;
+                    (v, v)
+                }
+            },
+        };
+        if low > high {
+            return Err(invalid());
+        }
+        for v in (low..=high).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn next(expression: &str, after: &str) -> String {
+        let schedule: CronSchedule = expression.parse().unwrap();
+        schedule.next_after(at(after)).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        assert_eq!(
+            next("0 2 * * SUN", "2025-01-15T12:00:00Z"),
+            "2025-01-19T02:00:00+00:00"
+        );
+        // Strictly after: a match at the instant itself is skipped
+        assert_eq!(
+            next("0 2 * * 0", "2025-01-19T02:00:00Z"),
+            "2025-01-26T02:00:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 9-17 * * MON-FRI", "2025-01-17T17:50:30Z"),
+            "2025-01-20T09:00:00+00:00"
+        );
+        // Sunday as 7, across a month boundary
+        assert_eq!(
+            next("30 1 * * 7", "2025-01-27T00:00:00Z"),
+            "2025-02-02T01:30:00+00:00"
+        );
+        assert_eq!(
+            next("@monthly", "2025-01-31T23:59:59Z"),
+            "2025-02-01T00:00:00+00:00"
+        );
+        // Day of month and weekday both restricted: either matches
+        assert_eq!(
+            next("0 0 13 * FRI", "2025-06-01T00:00:00Z"),
+            "2025-06-06T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2025-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for bad in [
+            "0 2 * *",
+            "60 * * * *",
+            "0 24 * * *",
+            "0 0 0 * *",
+            "0 0 * 13 *",
+            "0 0 * * FUNDAY",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@sometimes",
+        ] {
+            assert!(bad.parse::<CronSchedule>().is_err(), "{}", bad);
+        }
+        assert!("31 2 * * *".parse::<CronSchedule>().is_ok());
+        let never: CronSchedule = "0 0 31 2 *".parse().unwrap();
+        assert!(never.next_after(at("2025-01-01T00:00:00Z")).is_none());
+    }
+}
@@ -0,0 +1,643 @@
+// Durations: ISO 8601 parsing and formatting in several styles
+//
+// A duration is a signed count of nanoseconds. `format` renders it as
+// ISO 8601 (`P2DT3H5M`), a clock (`2d 03:05:00`), words (`2 days, 3 hours,
+// 5 minutes`), abbreviations (`2d 3h 5m`) or a rounded phrase
+// (`about 2 days`); a negative duration is the positive rendering behind a
+// single leading minus in every style. Days are the largest exact unit:
+// years and months have no fixed length without an anchor date, so a year
+// renders as `P365D` and `parse_iso8601` rejects Y and M date components.
+// Weeks parse as seven days. Whatever `format` writes in ISO style,
+// `parse_iso8601` reads back to the same value.
+//
+// `normalize_interval` is what every tool taking two ends of an interval
+// goes through. Its `OrderPolicy` says what an end before the start means:
+// an error, a negative interval (the default, as before the policy existed),
+// or a mistake to swap and report with `inputs_swapped`. Whatever the order,
+// an interval longer than `max_years` is refused as `IntervalTooLong`, which
+// almost always means one end is in milliseconds and was read as seconds.
+
+use super::humanize;
+use crate::error::TimeServerError;
+use crate::warnings::{Warning, WarningCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Exact units from largest to smallest, in seconds
+const UNITS: [(&str, &str, i128); 4] = [
+    ("day", "d", 86_400),
+    ("hour", "h", 3_600),
+    ("minute", "m", 60),
+    ("second", "s", 1),
+];
+
+/// How `format` writes a duration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationStyle {
+    /// P2DT3H5M
+    Iso8601,
+    /// 2d 03:05:00, with fractional seconds when there are any
+    Clock,
+    /// 2 days, 3 hours, 5 minutes
+    #[default]
+    HumanLong,
+    /// 2d 3h 5m
+    HumanShort,
+    /// about 2 days: the largest unit that fits, rounded
+    LargestUnit,
+}
+
+/// Unit of a duration given as a number
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationUnit {
+    #[default]
+    Seconds,
+    Ms,
+    Ns,
+}
+
+impl DurationUnit {
+    /// `value` of this unit in whole nanoseconds, rounded
+    pub fn to_nanos(self, value: f64) -> Result<i128, TimeServerError> {
+        let per_unit = match self {
+            Self::Seconds => 1e9,
+            Self::Ms => 1e6,
+            Self::Ns => 1.0,
+        };
+        let nanos = (value * per_unit).round();
+        // Far beyond any span of dates this server handles
+        if !nanos.is_finite() || nanos.abs() > 1e30 {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "duration {} is not a usable number",
+                value
+            )));
+        }
+        Ok(nanos as i128)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub style: DurationStyle,
+    /// Keep at most this many units, dropping (not rounding) the rest;
+    /// ignored by the clock and largest_unit styles
+    pub max_units: Option<usize>,
+    /// Also write zero units below the largest non-zero one
+    pub include_zero: bool,
+}
+
+/// Render `nanos` in `options.style`
+pub fn format(nanos: i128, options: FormatOptions) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let magnitude = nanos.unsigned_abs() as i128;
+    let body = match options.style {
+        DurationStyle::Iso8601 => iso8601(magnitude, options),
+        DurationStyle::Clock => clock(magnitude),
+        DurationStyle::HumanLong => {
+            let parts: Vec<String> = components(magnitude, options)
+                .into_iter()
+                .map(|(unit, value)| match value {
+                    Component::Whole(count) => humanize::quantity(count as i64, unit.0),
+                    Component::Seconds(text) => format!("{} seconds", text),
+                })
+                .collect();
+            parts.join(", ")
+        }
+        DurationStyle::HumanShort => {
+            let parts: Vec<String> = components(magnitude, options)
+                .into_iter()
+                .map(|(unit, value)| match value {
+                    Component::Whole(count) => format!("{}{}", count, unit.1),
+                    Component::Seconds(text) => format!("{}s", text),
+                })
+                .collect();
+            parts.join(" ")
+        }
+        DurationStyle::LargestUnit => largest_unit(magnitude),
+    };
+    format!("{}{}", sign, body)
+}
+
+/// A unit's value: whole, or seconds with a fraction
+enum Component {
+    Whole(i128),
+    Seconds(String),
+}
+
+/// Units to write for the human styles, largest first
+fn components(
+    nanos: i128,
+    options: FormatOptions,
+) -> Vec<((&'static str, &'static str), Component)> {
+    let fraction = nanos % NANOS_PER_SECOND;
+    let mut rest = nanos / NANOS_PER_SECOND;
+    let mut parts = Vec::new();
+    for (name, short, size) in UNITS {
+        let count = rest / size;
+        rest %= size;
+        let value = if size == 1 && fraction != 0 {
+            Component::Seconds(seconds_text(count, fraction))
+        } else {
+            Component::Whole(count)
+        };
+        let zero = count == 0 && !(size == 1 && fraction != 0);
+        if !zero || (options.include_zero && !parts.is_empty()) {
+            parts.push(((name, short), value));
+        }
+    }
+    if parts.is_empty() {
+        parts.push((("second", "s"), Component::Whole(0)));
+    }
+    if let Some(max) = options.max_units {
+        parts.truncate(max.max(1));
+    }
+    parts
+}
+
+/// `seconds.fraction` with trailing zeros dropped, e.g. 1.5
+fn seconds_text(seconds: i128, fraction: i128) -> String {
+    let mut text = format!("{}.{:09}", seconds, fraction);
+    while text.ends_with('0') {
+        text.pop();
+    }
+    text.trim_end_matches('.').to_string()
+}
+
+fn iso8601(nanos: i128, options: FormatOptions) -> String {
+    let parts = components(nanos, options);
+    let mut out = String::from("P");
+    let mut in_time = false;
+    for ((name, _), value) in parts {
+        if name != "day" && !in_time {
+            out.push('T');
+            in_time = true;
+        }
+        let designator = match name {
+            "day" => 'D',
+            "hour" => 'H',
+            "minute" => 'M',
+            _ => 'S',
+        };
+        match value {
+            Component::Whole(count) => {
+                let _ = write!(out, "{}{}", count, designator);
+            }
+            Component::Seconds(text) => {
+                let _ = write!(out, "{}S", text);
+            }
+        }
+    }
+    out
+}
+
+fn clock(nanos: i128) -> String {
+    let fraction = nanos % NANOS_PER_SECOND;
+    let total = nanos / NANOS_PER_SECOND;
+    let (days, rest) = (total / 86_400, total % 86_400);
+    let mut out = String::new();
+    if days > 0 {
+        let _ = write!(out, "{}d ", days);
+    }
+    let _ = write!(
+        out,
+        "{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    );
+    if fraction != 0 {
+        out.push_str(&seconds_text(0, fraction)[1..]);
+    }
+    out
+}
+
+/// "about 2 days"; exact counts, and anything under a second, drop the "about"
+fn largest_unit(nanos: i128) -> String {
+    if nanos < NANOS_PER_SECOND {
+        return match nanos {
+            0 => humanize::quantity(0, "second"),
+            _ => format!("{} seconds", seconds_text(0, nanos)),
+        };
+    }
+    let (unit, size) = humanize::UNITS
+        .iter()
+        .map(|&(unit, seconds)| (unit, i128::from(seconds) * NANOS_PER_SECOND))
+        .find(|&(_, size)| nanos >= size)
+        .unwrap_or(("second", NANOS_PER_SECOND));
+    let count = (nanos + size / 2) / size;
+    let phrase = humanize::quantity(count.min(i128::from(i64::MAX)) as i64, unit);
+    if nanos % size == 0 {
+        phrase
+    } else {
+        format!("about {}", phrase)
+    }
+}
+
+/// Interval-taking tools refuse anything longer unless MAX_INTERVAL_YEARS says otherwise
+pub const DEFAULT_MAX_INTERVAL_YEARS: u32 = 10_000;
+
+/// Mean Gregorian year, for the length check only
+const NANOS_PER_YEAR: i128 = 31_556_952 * NANOS_PER_SECOND;
+
+/// What an interval does when its end comes before its start
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderPolicy {
+    /// Refuse it
+    Strict,
+    /// Keep the order: the difference is negative
+    #[default]
+    Signed,
+    /// Swap the ends and set `inputs_swapped`
+    Absolute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntervalRules {
+    pub policy: OrderPolicy,
+    /// Longest interval taken, in years (MAX_INTERVAL_YEARS)
+    pub max_years: u32,
+}
+
+impl Default for IntervalRules {
+    fn default() -> Self {
+        Self {
+            policy: OrderPolicy::default(),
+            max_years: DEFAULT_MAX_INTERVAL_YEARS,
+        }
+    }
+}
+
+/// An interval as a tool should use it, in nanoseconds since the epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub start: i128,
+    pub end: i128,
+    /// The ends were given the other way round
+    pub swapped: bool,
+}
+
+impl Interval {
+    /// `end` minus `start`; negative only under the signed policy
+    pub fn nanos(&self) -> i128 {
+        self.end - self.start
+    }
+
+    /// `start` and `end` in the order this interval uses them
+    pub fn order<T>(&self, start: T, end: T) -> (T, T) {
+        if self.swapped {
+            (end, start)
+        } else {
+            (start, end)
+        }
+    }
+
+    /// The `INPUTS_SWAPPED` warning, when the ends were swapped
+    pub fn warning(&self, names: (&str, &str)) -> Option<Warning> {
+        self.swapped.then(|| {
+            Warning::new(
+                WarningCode::InputsSwapped,
+                format!(
+                    "'{}' was after '{}', so the two were swapped (order_policy absolute)",
+                    names.0, names.1
+                ),
+            )
+            .with_field(names.0)
+        })
+    }
+}
+
+/// The interval from `start` to `end` under `rules`; `names` are the
+/// parameters the two ends came from, for messages
+pub fn normalize_interval(
+    start: i128,
+    end: i128,
+    names: (&str, &str),
+    rules: IntervalRules,
+) -> Result<Interval, TimeServerError> {
+    let length = (end - start).unsigned_abs();
+    if length > rules.max_years as u128 * NANOS_PER_YEAR as u128 {
+        return Err(TimeServerError::IntervalTooLong {
+            years: (length / NANOS_PER_YEAR as u128).min(u64::MAX as u128) as u64,
+            max_years: rules.max_years,
+        });
+    }
+    let reversed = end < start;
+    match (reversed, rules.policy) {
+        (true, OrderPolicy::Strict) => Err(TimeServerError::InvalidArgument(format!(
+            "'{}' is after '{}'; give them in order, or set order_policy to signed or absolute",
+            names.0, names.1
+        ))),
+        (true, OrderPolicy::Absolute) => Ok(Interval {
+            start: end,
+            end: start,
+            swapped: true,
+        }),
+        _ => Ok(Interval {
+            start,
+            end,
+            swapped: false,
+        }),
+    }
+}
+
+/// `[-]PnWnDTnHnMnS` to nanoseconds; only the last component may have a
+/// fraction, and Y and M date components are refused
+pub fn parse_iso8601(text: &str) -> Result<i128, TimeServerError> {
+    let invalid = |why: &str| {
+        TimeServerError::InvalidArgument(format!("'{}' is not an ISO 8601 duration: {}", text, why))
+    };
+    let trimmed = text.trim();
+    let (negative, rest) = match trimmed.as_bytes().first() {
+        Some(b'-') => (true, &trimmed[1..]),
+        Some(b'+') => (false, &trimmed[1..]),
+        _ => (false, trimmed),
+    };
+    let rest = rest
+        .strip_prefix(['P', 'p'])
+        .ok_or_else(|| invalid("it must start with P"))?;
+
+    let mut total: i128 = 0;
+    let mut in_time = false;
+    let mut number = String::new();
+    let mut components = 0;
+    let mut time_components = 0;
+    let mut fraction_seen = false;
+    for c in rest.chars() {
+        match c.to_ascii_uppercase() {
+            '0'..='9' => number.push(c),
+            '.' | ',' => number.push('.'),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            designator => {
+                if number.is_empty() {
+                    return Err(invalid("a designator has no number"));
+                }
+                if fraction_seen {
+                    return Err(invalid("only the last component may have a fraction"));
+                }
+                let seconds: i128 = match (in_time, designator) {
+                    (false, 'W') => 7 * 86_400,
+                    (false, 'D') => 86_400,
+                    (true, 'H') => 3_600,
+                    (true, 'M') => 60,
+                    (true, 'S') => 1,
+                    (false, 'Y' | 'M') => {
+                        return Err(invalid("years and months have no fixed length; use days"))
+                    }
+                    _ => return Err(invalid("unexpected designator")),
+                };
+                fraction_seen = number.contains('.');
+                total = total
+                    .checked_add(scaled(&number, seconds).ok_or_else(|| invalid("bad number"))?)
+                    .ok_or_else(|| invalid("too long"))?;
+                number.clear();
+                components += 1;
+                time_components += usize::from(in_time);
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid("a number has no designator"));
+    }
+    if components == 0 || (in_time && time_components == 0) {
+        return Err(invalid("it has no components after P or T"));
+    }
+    Ok(if negative { -total } else { total })
+}
+
+/// `number` (decimal, dot separator) times `seconds`, in nanoseconds
+fn scaled(number: &str, seconds: i128) -> Option<i128> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() || fraction.contains('.') || fraction.len() > 9 {
+        return None;
+    }
+    let whole: i128 = whole.parse().ok()?;
+    let fraction: i128 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", fraction).parse().ok()?
+    };
+    let nanos = whole.checked_mul(NANOS_PER_SECOND)?.checked_add(fraction)?;
+    nanos.checked_mul(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S: i128 = NANOS_PER_SECOND;
+
+    fn styled(style: DurationStyle) -> FormatOptions {
+        FormatOptions {
+            style,
+            ..FormatOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_styles() {
+        use DurationStyle::*;
+        let day = 86_400 * S;
+        for (nanos, style, expected) in [
+            (0, Iso8601, "PT0S"),
+            (0, Clock, "00:00:00"),
+            (0, HumanLong, "0 seconds"),
+            (0, HumanShort, "0s"),
+            (0, LargestUnit, "0 seconds"),
+            (2 * day + 3 * 3600 * S + 5 * 60 * S, Iso8601, "P2DT3H5M"),
+            (2 * day + 3 * 3600 * S + 5 * 60 * S, Clock, "2d 03:05:00"),
+            (
+                2 * day + 3 * 3600 * S + 5 * 60 * S,
+                HumanLong,
+                "2 days, 3 hours, 5 minutes",
+            ),
+            (2 * day + 3 * 3600 * S + 5 * 60 * S, HumanShort, "2d 3h 5m"),
+            (
+                2 * day + 3 * 3600 * S + 5 * 60 * S,
+                LargestUnit,
+                "about 2 days",
+            ),
+            (day, LargestUnit, "1 day"),
+            (S + S / 2, Iso8601, "PT1.5S"),
+            (S + S / 2, Clock, "00:00:01.5"),
+            (S + S / 2, HumanLong, "1.5 seconds"),
+            (S + S / 2, HumanShort, "1.5s"),
+            (S / 4, LargestUnit, "0.25 seconds"),
+            (1, Iso8601, "PT0.000000001S"),
+            // Over a year: days, never years
+            (400 * day + 1, Iso8601, "P400DT0.000000001S"),
+            (400 * day, HumanShort, "400d"),
+            (400 * day, LargestUnit, "about 1 year"),
+            (-(3600 * S), Iso8601, "-PT1H"),
+            (-(3600 * S), Clock, "-01:00:00"),
+            (-(3600 * S), HumanLong, "-1 hour"),
+            (-(3600 * S), HumanShort, "-1h"),
+            (-(90 * 60 * S), LargestUnit, "-about 2 hours"),
+        ] {
+            assert_eq!(
+                format(nanos, styled(style)),
+                expected,
+                "{} {:?}",
+                nanos,
+                style
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_units_and_zero_components() {
+        let nanos = (86_400 + 5 * 60 + 7) * S;
+        let options = |max_units, include_zero| FormatOptions {
+            style: DurationStyle::HumanLong,
+            max_units,
+            include_zero,
+        };
+        assert_eq!(
+            format(nanos, options(None, false)),
+            "1 day, 5 minutes, 7 seconds"
+        );
+        assert_eq!(
+            format(nanos, options(None, true)),
+            "1 day, 0 hours, 5 minutes, 7 seconds"
+        );
+        assert_eq!(format(nanos, options(Some(2), false)), "1 day, 5 minutes");
+        assert_eq!(format(nanos, options(Some(2), true)), "1 day, 0 hours");
+        assert_eq!(format(nanos, options(Some(0), false)), "1 day");
+    }
+
+    #[test]
+    fn test_iso_round_trip() {
+        for text in [
+            "PT0S",
+            "P1D",
+            "PT36M",
+            "P2DT3H5M",
+            "P400DT23H59M59.999999999S",
+            "-PT0.5S",
+            "-P7DT1S",
+        ] {
+            let nanos = parse_iso8601(text).unwrap();
+            assert_eq!(format(nanos, styled(DurationStyle::Iso8601)), text);
+        }
+        for nanos in [1, S - 1, 123_456 * S + 789, -(31 * 86_400 * S)] {
+            let text = format(nanos, styled(DurationStyle::Iso8601));
+            assert_eq!(parse_iso8601(&text).unwrap(), nanos, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_iso8601() {
+        assert_eq!(parse_iso8601("P2W").unwrap(), 14 * 86_400 * S);
+        assert_eq!(parse_iso8601("pt1,5s").unwrap(), S + S / 2);
+        assert_eq!(parse_iso8601("PT36H").unwrap(), 36 * 3600 * S);
+        for bad in [
+            "",
+            "P",
+            "PT",
+            "1D",
+            "P1Y",
+            "P1M",
+            "PT1.5H30M",
+            "P1H",
+            "PT1",
+            "P1DT",
+        ] {
+            assert!(parse_iso8601(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(DurationUnit::Seconds.to_nanos(1.5).unwrap(), S + S / 2);
+        assert_eq!(DurationUnit::Ms.to_nanos(-250.0).unwrap(), -S / 4);
+        assert_eq!(DurationUnit::Ns.to_nanos(7.0).unwrap(), 7);
+        assert!(DurationUnit::Seconds.to_nanos(f64::INFINITY).is_err());
+    }
+
+    fn rules(policy: OrderPolicy) -> IntervalRules {
+        IntervalRules {
+            policy,
+            ..IntervalRules::default()
+        }
+    }
+
+    const NAMES: (&str, &str) = ("a", "b");
+
+    #[test]
+    fn test_signed_policy_keeps_the_order() {
+        let interval =
+            normalize_interval(10 * S, 4 * S, NAMES, rules(OrderPolicy::Signed)).unwrap();
+        assert_eq!(interval.nanos(), -6 * S);
+        assert!(!interval.swapped);
+        assert_eq!(interval.order("a", "b"), ("a", "b"));
+        assert_eq!(interval.warning(NAMES), None);
+        // The default, as before there was a policy
+        assert_eq!(IntervalRules::default().policy, OrderPolicy::Signed);
+    }
+
+    #[test]
+    fn test_strict_policy_refuses_a_reversed_interval() {
+        let error =
+            normalize_interval(10 * S, 4 * S, NAMES, rules(OrderPolicy::Strict)).unwrap_err();
+        assert_eq!(error.code(), "invalid_argument");
+        assert!(
+            error.to_string().starts_with("'a' is after 'b'"),
+            "{}",
+            error
+        );
+        // In order, and empty, are fine
+        for end in [10 * S, 11 * S] {
+            let interval =
+                normalize_interval(10 * S, end, NAMES, rules(OrderPolicy::Strict)).unwrap();
+            assert_eq!(interval.nanos(), end - 10 * S);
+        }
+    }
+
+    #[test]
+    fn test_absolute_policy_swaps_and_says_so() {
+        let interval =
+            normalize_interval(10 * S, 4 * S, NAMES, rules(OrderPolicy::Absolute)).unwrap();
+        assert_eq!((interval.start, interval.end), (4 * S, 10 * S));
+        assert_eq!(interval.nanos(), 6 * S);
+        assert!(interval.swapped);
+        assert_eq!(interval.order("a", "b"), ("b", "a"));
+        let warning = interval.warning(NAMES).unwrap();
+        assert_eq!(warning.code, WarningCode::InputsSwapped);
+        assert_eq!(warning.field.as_deref(), Some("a"));
+
+        let in_order =
+            normalize_interval(4 * S, 10 * S, NAMES, rules(OrderPolicy::Absolute)).unwrap();
+        assert!(!in_order.swapped);
+    }
+
+    #[test]
+    fn test_overlong_interval_is_refused_in_either_order() {
+        // 2024-03-15 in seconds, and the same instant in milliseconds read as seconds
+        let seconds = 1_710_496_800 * S;
+        let millis_as_seconds = 1_710_496_800_000 * S;
+        for policy in [
+            OrderPolicy::Strict,
+            OrderPolicy::Signed,
+            OrderPolicy::Absolute,
+        ] {
+            for (start, end) in [(seconds, millis_as_seconds), (millis_as_seconds, seconds)] {
+                let error = normalize_interval(start, end, NAMES, rules(policy)).unwrap_err();
+                assert_eq!(error.code(), "interval_too_long");
+                assert_eq!(error.details()["max_years"], DEFAULT_MAX_INTERVAL_YEARS);
+                assert_eq!(error.details()["years"], 54_149);
+            }
+        }
+
+        let tight = IntervalRules {
+            max_years: 1,
+            ..IntervalRules::default()
+        };
+        assert!(normalize_interval(0, 365 * 86_400 * S, NAMES, tight).is_ok());
+        assert!(normalize_interval(0, 366 * 86_400 * S, NAMES, tight).is_err());
+    }
+}
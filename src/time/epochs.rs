@@ -0,0 +1,474 @@
+// Conversions between Unix time and other epochs
+//
+// Every epoch is an origin plus a unit: 100 ns ticks for FILETIME and .NET,
+// days for Excel, seconds for the rest. Values are parsed as exact decimals
+// and converted through i128 nanoseconds, so 18-digit tick counts survive;
+// the result must lie in the supported range of `UnixTime`, the years 1 to
+// 9999 UTC, which reaches past the 2262 end of i64 nanoseconds.
+// Only GPS counts leap seconds; the others repeat a second like Unix time.
+
+use super::leap;
+use super::unix::{self, UnixTime};
+use crate::error::TimeServerError;
+use chrono::SecondsFormat;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const NANOS_PER_SECOND: i128 = 1_000_000_000;
+pub const NANOS_PER_TICK: i128 = 100;
+pub const NANOS_PER_DAY: i128 = 86_400 * NANOS_PER_SECOND;
+
+/// FILETIME of 1970-01-01 (100 ns ticks since 1601-01-01)
+pub const FILETIME_UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+/// .NET `DateTime.Ticks` of 1970-01-01 (100 ns ticks since 0001-01-01)
+pub const DOTNET_UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+/// NTP seconds of 1970-01-01 (seconds since 1900-01-01, era 0)
+pub const NTP_UNIX_EPOCH_SECONDS: i64 = 2_208_988_800;
+/// Seconds in one NTP era; era 1 starts 2036-02-07T06:28:16Z
+pub const NTP_ERA_SECONDS: i64 = 1 << 32;
+/// Excel serial of 1970-01-01 (days since 1899-12-30, 1900 date system)
+pub const EXCEL_UNIX_EPOCH_DAYS: i64 = 25_569;
+/// Unix time of the Cocoa reference date, 2001-01-01
+pub const COCOA_EPOCH_UNIX_SECONDS: i64 = 978_307_200;
+/// Unix time of the GPS epoch, 1980-01-06
+pub const GPS_EPOCH_UNIX_SECONDS: i64 = 315_964_800;
+
+/// Fraction digits kept in `exact`; days need 14 for nanoseconds
+const MAX_FRACTION_DIGITS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Epoch {
+    /// Seconds since 1970-01-01
+    Unix,
+    /// Windows FILETIME: 100 ns ticks since 1601-01-01
+    Filetime,
+    /// .NET DateTime.Ticks: 100 ns ticks since 0001-01-01
+    DotnetTicks,
+    /// NTP: seconds since 1900-01-01, era-extended past 2036
+    Ntp,
+    /// Excel serial date: days since 1899-12-30
+    Excel,
+    /// Apple Cocoa / Core Data: seconds since 2001-01-01
+    Cocoa,
+    /// GPS: seconds since 1980-01-06, counting leap seconds
+    Gps,
+}
+
+impl Epoch {
+    pub const ALL: [Epoch; 7] = [
+        Self::Unix,
+        Self::Filetime,
+        Self::DotnetTicks,
+        Self::Ntp,
+        Self::Excel,
+        Self::Cocoa,
+        Self::Gps,
+    ];
+
+    pub fn unit_nanos(self) -> i128 {
+        match self {
+            Self::Filetime | Self::DotnetTicks => NANOS_PER_TICK,
+            Self::Excel => NANOS_PER_DAY,
+            Self::Unix | Self::Ntp | Self::Cocoa | Self::Gps => NANOS_PER_SECOND,
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Filetime | Self::DotnetTicks => "100ns_ticks",
+            Self::Excel => "days",
+            Self::Unix | Self::Ntp | Self::Cocoa | Self::Gps => "seconds",
+        }
+    }
+
+    /// Nanoseconds from 1970-01-01 to this epoch's zero
+    pub fn origin_unix_nanos(self) -> i128 {
+        match self {
+            Self::Unix => 0,
+            Self::Filetime => -(FILETIME_UNIX_EPOCH_TICKS as i128) * NANOS_PER_TICK,
+            Self::DotnetTicks => -(DOTNET_UNIX_EPOCH_TICKS as i128) * NANOS_PER_TICK,
+            Self::Ntp => -(NTP_UNIX_EPOCH_SECONDS as i128) * NANOS_PER_SECOND,
+            Self::Excel => -(EXCEL_UNIX_EPOCH_DAYS as i128) * NANOS_PER_DAY,
+            Self::Cocoa => COCOA_EPOCH_UNIX_SECONDS as i128 * NANOS_PER_SECOND,
+            Self::Gps => GPS_EPOCH_UNIX_SECONDS as i128 * NANOS_PER_SECOND,
+        }
+    }
+
+    pub fn origin(self) -> &'static str {
+        match self {
+            Self::Unix => "1970-01-01T00:00:00Z",
+            Self::Filetime => "1601-01-01T00:00:00Z",
+            Self::DotnetTicks => "0001-01-01T00:00:00Z",
+            Self::Ntp => "1900-01-01T00:00:00Z",
+            Self::Excel => "1899-12-30T00:00:00Z",
+            Self::Cocoa => "2001-01-01T00:00:00Z",
+            Self::Gps => "1980-01-06T00:00:00Z",
+        }
+    }
+
+    /// How the epoch treats leap seconds
+    pub fn leap_seconds(self) -> &'static str {
+        match self {
+            Self::Unix => "ignored: every day is 86400 s and a leap second repeats the last one",
+            Self::Filetime => "ignored: converted like Unix time (Windows leap second support is off by default)",
+            Self::DotnetTicks => "ignored: DateTime has no leap seconds",
+            Self::Ntp => "ignored: NTP seconds repeat across a leap second like Unix time",
+            Self::Excel => "ignored: every day is 86400 s; serials before 61 are off by one day because Excel treats 1900 as a leap year",
+            Self::Cocoa => "ignored: converted like Unix time",
+            Self::Gps => "counted: GPS time runs ahead of UTC by the leap seconds since 1980 (TAI - UTC - 19)",
+        }
+    }
+}
+
+/// One instant in one epoch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EpochValue {
+    pub epoch: Epoch,
+    /// Integer for whole values, float otherwise
+    pub value: Value,
+    /// Decimal without rounding, for values past f64 precision
+    pub exact: String,
+    pub unit: &'static str,
+    pub origin: &'static str,
+    pub leap_seconds: &'static str,
+    /// NTP era number (0 until 2036-02-07)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntp_era: Option<i64>,
+    /// Seconds within the era, as carried by a 32-bit NTP timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntp_era_seconds: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EpochConversion {
+    pub unix_timestamp: i64,
+    #[serde(serialize_with = "unix::serialize_nanos")]
+    pub unix_nanos: i128,
+    /// RFC 3339 UTC
+    pub utc: String,
+    pub epochs: Vec<EpochValue>,
+}
+
+/// `text` times `unit_nanos`, exact down to the nanosecond (truncated)
+fn scaled(text: &str, unit_nanos: i128) -> Result<i128, TimeServerError> {
+    let invalid = || TimeServerError::InvalidTimestamp(format!("not a decimal number: {:?}", text));
+    let trimmed = text.trim();
+    let (negative, body) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (mantissa, exponent) = match body.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?),
+        None => (body, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (int.is_empty() && frac.is_empty())
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    // value = digits * 10^(point - digits.len()); fraction digits beyond 20
+    // are below a nanosecond for every unit
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    let point = (int.len() as i64 - (int.len() + frac.len() - digits.len()) as i64)
+        .saturating_add(exponent as i64);
+    let keep = digits.len().min((point.max(0) + 20) as usize);
+    let digits = &digits[..keep];
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    let out_of_range = || TimeServerError::out_of_range(text.trim());
+    if digits.len() > 38 {
+        return Err(out_of_range());
+    }
+    let mantissa: i128 = digits.parse().map_err(|_| invalid())?;
+    let shift = point - digits.len() as i64;
+    let magnitude = if shift >= 0 {
+        u32::try_from(shift)
+            .ok()
+            .and_then(|shift| 10i128.checked_pow(shift))
+            .and_then(|scale| mantissa.checked_mul(scale))
+            .and_then(|value| value.checked_mul(unit_nanos))
+    } else {
+        // A divisor past i128 leaves nothing above a nanosecond
+        match u32::try_from(-shift)
+            .ok()
+            .and_then(|s| 10i128.checked_pow(s))
+        {
+            Some(divisor) => mantissa
+                .checked_mul(unit_nanos)
+                .map(|value| value / divisor),
+            None => Some(0),
+        }
+    }
+    .ok_or_else(out_of_range)?;
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// `numer / denom` as a decimal, trailing zeros trimmed
+fn decimal(numer: i128, denom: i128) -> String {
+    let sign = if numer < 0 { "-" } else { "" };
+    let mut rem = numer.abs() % denom;
+    let mut out = format!("{}{}", sign, numer.abs() / denom);
+    if rem != 0 {
+        out.push('.');
+        for _ in 0..MAX_FRACTION_DIGITS {
+            if rem == 0 {
+                break;
+            }
+            rem *= 10;
+            out.push(char::from(b'0' + (rem / denom) as u8));
+            rem %= denom;
+        }
+        let trimmed = out.trim_end_matches('0').trim_end_matches('.').len();
+        out.truncate(trimmed);
+    }
+    out
+}
+
+/// GPS seconds are ahead of Unix seconds by the leap seconds since 1980
+fn gps_offset_nanos(unix_nanos: i128) -> i128 {
+    let seconds = unix_nanos.div_euclid(NANOS_PER_SECOND) as i64;
+    leap::gps_utc_offset(seconds) as i128 * NANOS_PER_SECOND
+}
+
+/// `value`, counted in `epoch`, as nanoseconds since 1970-01-01 UTC.
+/// `ntp_era` places a 32-bit NTP seconds value in that era.
+pub fn to_unix_nanos(
+    value: &str,
+    epoch: Epoch,
+    ntp_era: Option<i64>,
+) -> Result<i128, TimeServerError> {
+    let out_of_range = || TimeServerError::out_of_range(value.trim());
+    let mut since_origin = scaled(value, epoch.unit_nanos())?;
+    if let Some(era) = ntp_era {
+        if epoch != Epoch::Ntp {
+            return Err(TimeServerError::InvalidArgument(
+                "ntp_era only applies to the ntp epoch".to_string(),
+            ));
+        }
+        if !(0..NTP_ERA_SECONDS as i128 * NANOS_PER_SECOND).contains(&since_origin) {
+            return Err(TimeServerError::InvalidArgument(
+                "with ntp_era the value must be 32-bit NTP seconds (0 to 4294967295)".to_string(),
+            ));
+        }
+        since_origin = (era as i128)
+            .checked_mul(NTP_ERA_SECONDS as i128 * NANOS_PER_SECOND)
+            .and_then(|era| era.checked_add(since_origin))
+            .ok_or_else(out_of_range)?;
+    }
+
+    let mut unix = epoch
+        .origin_unix_nanos()
+        .checked_add(since_origin)
+        .ok_or_else(out_of_range)?;
+    if epoch == Epoch::Gps {
+        // Look the offset up at the UTC instant, which is at most 18 s earlier
+        let offset = gps_offset_nanos(unix - gps_offset_nanos(unix));
+        unix -= offset;
+    }
+    UnixTime::from_nanos(unix).map_err(|_| out_of_range())?;
+    Ok(unix)
+}
+
+/// The instant `unix_nanos` in every epoch; it must be in the supported range
+pub fn from_unix_nanos(unix_nanos: i128) -> Result<EpochConversion, TimeServerError> {
+    let time = UnixTime::from_nanos(unix_nanos)?;
+    let unix = unix_nanos;
+    let epochs = Epoch::ALL
+        .into_iter()
+        .map(|epoch| {
+            let mut since_origin = unix - epoch.origin_unix_nanos();
+            if epoch == Epoch::Gps {
+                since_origin += gps_offset_nanos(unix);
+            }
+            let exact = decimal(since_origin, epoch.unit_nanos());
+            let value = if exact.contains('.') {
+                exact.parse::<f64>().map(Value::from).unwrap_or(Value::Null)
+            } else {
+                exact.parse::<i64>().map(Value::from).unwrap_or(Value::Null)
+            };
+            let (ntp_era, ntp_era_seconds) = if epoch == Epoch::Ntp {
+                let seconds = since_origin.div_euclid(NANOS_PER_SECOND) as i64;
+                (
+                    Some(seconds.div_euclid(NTP_ERA_SECONDS)),
+                    Some(seconds.rem_euclid(NTP_ERA_SECONDS) as u32),
+                )
+            } else {
+                (None, None)
+            };
+            EpochValue {
+                epoch,
+                value,
+                exact,
+                unit: epoch.unit(),
+                origin: epoch.origin(),
+                leap_seconds: epoch.leap_seconds(),
+                ntp_era,
+                ntp_era_seconds,
+            }
+        })
+        .collect();
+
+    Ok(EpochConversion {
+        unix_timestamp: time.seconds,
+        unix_nanos,
+        utc: time
+            .to_datetime()
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        epochs,
+    })
+}
+
+/// `value` counted in `epoch`, in every epoch
+pub fn convert_epoch(
+    value: &str,
+    epoch: Epoch,
+    ntp_era: Option<i64>,
+) -> Result<EpochConversion, TimeServerError> {
+    from_unix_nanos(to_unix_nanos(value, epoch, ntp_era)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(conversion: &EpochConversion, epoch: Epoch) -> &str {
+        &conversion
+            .epochs
+            .iter()
+            .find(|e| e.epoch == epoch)
+            .unwrap()
+            .exact
+    }
+
+    #[test]
+    fn test_unix_zero_in_every_epoch() {
+        let zero = from_unix_nanos(0).unwrap();
+        assert_eq!(zero.utc, "1970-01-01T00:00:00Z");
+        assert_eq!(exact(&zero, Epoch::Unix), "0");
+        assert_eq!(exact(&zero, Epoch::Filetime), "116444736000000000");
+        assert_eq!(exact(&zero, Epoch::DotnetTicks), "621355968000000000");
+        assert_eq!(exact(&zero, Epoch::Ntp), "2208988800");
+        assert_eq!(exact(&zero, Epoch::Excel), "25569");
+        assert_eq!(exact(&zero, Epoch::Cocoa), "-978307200");
+        assert_eq!(exact(&zero, Epoch::Gps), "-315964800");
+        assert_eq!(
+            zero.epochs[1].value,
+            Value::from(116_444_736_000_000_000i64)
+        );
+    }
+
+    #[test]
+    fn test_round_trip_through_every_epoch() {
+        // 2024-02-29T12:34:56.789012345Z
+        let nanos = 1_709_210_096_789_012_345;
+        let conversion = from_unix_nanos(nanos).unwrap();
+        for value in &conversion.epochs {
+            let back = to_unix_nanos(&value.exact, value.epoch, None).unwrap();
+            // Excel keeps 15 fraction digits of a day: sub-nanosecond truncation
+            assert!((back - nanos).abs() <= 1, "{:?}: {}", value.epoch, back);
+        }
+        assert_eq!(exact(&conversion, Epoch::Unix), "1709210096.789012345");
+        assert_eq!(exact(&conversion, Epoch::Filetime), "133536836967890123.45");
+    }
+
+    #[test]
+    fn test_known_reference_instants() {
+        assert_eq!(to_unix_nanos("25569.0", Epoch::Excel, None).unwrap(), 0);
+        assert_eq!(
+            to_unix_nanos("45000.5", Epoch::Excel, None).unwrap(),
+            1_678_881_600_000_000_000
+        );
+        assert_eq!(
+            to_unix_nanos("116444736000000000", Epoch::Filetime, None).unwrap(),
+            0
+        );
+        assert_eq!(
+            to_unix_nanos("0", Epoch::Cocoa, None).unwrap() / 1_000_000_000,
+            COCOA_EPOCH_UNIX_SECONDS as i128
+        );
+        assert_eq!(
+            to_unix_nanos("1.5e3", Epoch::Unix, None).unwrap(),
+            1_500_000_000_000
+        );
+        assert_eq!(
+            to_unix_nanos("-0.25", Epoch::Unix, None).unwrap(),
+            -250_000_000
+        );
+    }
+
+    #[test]
+    fn test_gps_counts_leap_seconds() {
+        // 2017-01-01T00:00:00Z is GPS week 1930, 18 s ahead of UTC
+        let conversion = convert_epoch("1483228800", Epoch::Unix, None).unwrap();
+        assert_eq!(exact(&conversion, Epoch::Gps), "1167264018");
+        assert_eq!(
+            to_unix_nanos("1167264018", Epoch::Gps, None).unwrap(),
+            1_483_228_800_000_000_000
+        );
+        assert_eq!(
+            to_unix_nanos("0", Epoch::Gps, None).unwrap() / 1_000_000_000,
+            GPS_EPOCH_UNIX_SECONDS as i128
+        );
+    }
+
+    #[test]
+    fn test_ntp_era_rollover() {
+        // 2036-02-07T06:28:16Z starts era 1 at 32-bit seconds 0
+        let rollover = convert_epoch("2085978496", Epoch::Unix, None).unwrap();
+        let ntp = rollover
+            .epochs
+            .iter()
+            .find(|e| e.epoch == Epoch::Ntp)
+            .unwrap();
+        assert_eq!(ntp.ntp_era, Some(1));
+        assert_eq!(ntp.ntp_era_seconds, Some(0));
+        assert_eq!(ntp.exact, "4294967296");
+
+        assert_eq!(
+            to_unix_nanos("0", Epoch::Ntp, Some(1)).unwrap(),
+            2_085_978_496_000_000_000
+        );
+        assert!(to_unix_nanos("4294967296", Epoch::Ntp, Some(0)).is_err());
+        assert!(to_unix_nanos("1", Epoch::Unix, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_values_outside_years_1_to_9999_are_rejected() {
+        // Past i64::MAX nanoseconds (2262-04-11T23:47:16.854775807Z) is fine
+        assert_eq!(
+            to_unix_nanos("9223372036.854775808", Epoch::Unix, None).unwrap(),
+            i64::MAX as i128 + 1
+        );
+        assert!(to_unix_nanos("-9223372037", Epoch::Unix, None).is_ok());
+        // .NET DateTime.MinValue and MaxValue are the ends of the range
+        let max = convert_epoch("3155378975999999999", Epoch::DotnetTicks, None).unwrap();
+        assert_eq!(max.utc, "9999-12-31T23:59:59.999999900Z");
+        assert_eq!(max.unix_timestamp, unix::MAX_SECONDS);
+        assert_eq!(
+            serde_json::to_value(&max).unwrap()["unix_nanos"],
+            "253402300799999999900"
+        );
+        let min = convert_epoch("0", Epoch::DotnetTicks, None).unwrap();
+        assert_eq!(min.utc, "0001-01-01T00:00:00Z");
+
+        for (value, epoch) in [
+            ("3155378976000000000", Epoch::DotnetTicks),
+            ("-1", Epoch::DotnetTicks),
+            ("253402300800", Epoch::Unix),
+            ("-62135596801", Epoch::Unix),
+            ("1e400", Epoch::Unix),
+        ] {
+            let error = to_unix_nanos(value, epoch, None).unwrap_err();
+            assert_eq!(error.code(), "timestamp_out_of_range", "{}", value);
+        }
+        assert!(to_unix_nanos("9".repeat(60).as_str(), Epoch::Excel, None).is_err());
+        assert!(to_unix_nanos("12abc", Epoch::Unix, None).is_err());
+        assert!(to_unix_nanos("", Epoch::Unix, None).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+// Step-by-step explanations of conversion results
+//
+// With `explain: true`, convert_time and local_to_utc add an `explanation`
+// array: how the input was read, the offset period the instant falls in, any
+// DST decision and the final arithmetic. Each step has a kind, machine
+// fields and one line of text. The conversion code records steps into an
+// `Explain` collector as it goes; a disabled collector never calls the
+// closures it is given, so nothing is looked up or formatted when explain is
+// off.
+
+use super::timezone::{find_transitions, LocalCandidate, OffsetTransition};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, SecondsFormat, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// How far either side of an instant to look for the transitions that bound
+/// its offset period: past any annual DST change
+const PERIOD_SEARCH_SECS: i64 = 400 * 86400;
+
+/// One step of an explanation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplainStep {
+    /// input, offset_rule, dst or arithmetic
+    pub step: &'static str,
+    pub details: Value,
+    pub text: String,
+}
+
+/// Collects steps while enabled; ignores them otherwise
+#[derive(Debug, Default)]
+pub struct Explain {
+    steps: Option<Vec<ExplainStep>>,
+}
+
+impl Explain {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            steps: enabled.then(Vec::new),
+        }
+    }
+
+    /// A collector that records nothing
+    pub fn off() -> Self {
+        Self::default()
+    }
+
+    /// Record the step `build` makes, if enabled
+    pub fn step(&mut self, build: impl FnOnce() -> ExplainStep) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(build());
+        }
+    }
+
+    /// The recorded steps; `None` when disabled
+    pub fn finish(self) -> Option<Vec<ExplainStep>> {
+        self.steps
+    }
+}
+
+/// A result with its explanation alongside, when one was asked for
+#[derive(Debug, Serialize)]
+pub struct Explained<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Vec<ExplainStep>>,
+}
+
+fn utc_offset(seconds: i32) -> String {
+    FixedOffset::east_opt(seconds)
+        .map(|offset| offset.to_string())
+        .unwrap_or_default()
+}
+
+fn rfc3339(utc: DateTime<Utc>) -> String {
+    utc.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn timezone_origin(defaulted: bool) -> &'static str {
+    if defaulted {
+        "default"
+    } else {
+        "given"
+    }
+}
+
+/// A Unix timestamp input, shown in `timezone`
+pub fn timestamp_input(timestamp: i64, timezone: &str, defaulted: bool) -> ExplainStep {
+    ExplainStep {
+        step: "input",
+        details: json!({
+            "timestamp": timestamp,
+            "unit": "seconds",
+            "timezone": timezone,
+            "timezone_source": timezone_origin(defaulted),
+        }),
+        text: format!(
+            "{} read as Unix seconds, an absolute instant; {}{} only changes how it is shown",
+            timestamp,
+            timezone,
+            if defaulted { " (the default)" } else { "" }
+        ),
+    }
+}
+
+/// A wall-clock input, parsed to `parsed` in `timezone`
+pub fn local_input(
+    input: &str,
+    parsed: NaiveDateTime,
+    timezone: &str,
+    defaulted: bool,
+) -> ExplainStep {
+    let parsed = parsed.format("%Y-%m-%dT%H:%M:%S%.f").to_string();
+    ExplainStep {
+        step: "input",
+        text: format!(
+            "'{}' read as wall-clock time {} in {}{}",
+            input,
+            parsed,
+            timezone,
+            if defaulted { " (the default)" } else { "" }
+        ),
+        details: json!({
+            "local_datetime": input,
+            "parsed": parsed,
+            "timezone": timezone,
+            "timezone_source": timezone_origin(defaulted),
+        }),
+    }
+}
+
+/// The transitions either side of `at` in `tz`, when within a year or so
+fn offset_period(
+    tz: Tz,
+    at: DateTime<Utc>,
+) -> (Option<OffsetTransition>, Option<OffsetTransition>) {
+    let t = at.timestamp();
+    let since = find_transitions(tz, t.saturating_sub(PERIOD_SEARCH_SECS), t + 1).pop();
+    let until = find_transitions(tz, t + 1, t.saturating_add(PERIOD_SEARCH_SECS))
+        .into_iter()
+        .next();
+    (since, until)
+}
+
+/// The offset `tz` applies at `at` and the period it holds for
+pub fn offset_rule(tz: Tz, at: DateTime<Utc>) -> ExplainStep {
+    let offset = tz.offset_from_utc_datetime(&at.naive_utc());
+    let seconds = offset.fix().local_minus_utc();
+    let is_dst = !offset.dst_offset().is_zero();
+    let (since, until) = offset_period(tz, at);
+    let period = match (&since, &until) {
+        (Some(since), Some(until)) => format!("from {} until {}", since.utc, until.utc),
+        (Some(since), None) => format!("since {}, with no change in the year after", since.utc),
+        (None, Some(until)) => format!("until {}, unchanged in the year before", until.utc),
+        (None, None) => "with no change within a year either side".to_string(),
+    };
+    ExplainStep {
+        step: "offset_rule",
+        text: format!(
+            "{} is at UTC{} ({}, {}) {}",
+            tz.name(),
+            utc_offset(seconds),
+            offset.abbreviation(),
+            if is_dst {
+                "daylight time"
+            } else {
+                "standard time"
+            },
+            period
+        ),
+        details: json!({
+            "timezone": tz.name(),
+            "at": rfc3339(at),
+            "offset_seconds": seconds,
+            "abbreviation": offset.abbreviation(),
+            "is_dst": is_dst,
+            "period_start": since.as_ref().map(|t| &t.utc),
+            "period_end": until.as_ref().map(|t| &t.utc),
+        }),
+    }
+}
+
+/// A local time that occurs twice, and the candidate `policy` chose
+pub fn ambiguous(
+    local: NaiveDateTime,
+    timezone: &str,
+    candidates: &[LocalCandidate],
+    policy: &str,
+    chosen: &LocalCandidate,
+) -> ExplainStep {
+    ExplainStep {
+        step: "dst",
+        text: format!(
+            "{} occurs twice in {} (clocks go back): at UTC{} or UTC{}; policy '{}' chose {} (UTC{})",
+            local.format("%Y-%m-%dT%H:%M:%S"),
+            timezone,
+            utc_offset(candidates[0].offset_seconds),
+            utc_offset(candidates[1].offset_seconds),
+            policy,
+            chosen.utc,
+            utc_offset(chosen.offset_seconds)
+        ),
+        details: json!({
+            "status": "ambiguous",
+            "candidates": candidates,
+            "policy": policy,
+            "chosen": chosen,
+        }),
+    }
+}
+
+/// A local time skipped by a forward transition, moved by `gap_secs`
+pub fn nonexistent(
+    local: NaiveDateTime,
+    timezone: &str,
+    gap_secs: i32,
+    shifted: NaiveDateTime,
+    chosen: &LocalCandidate,
+) -> ExplainStep {
+    ExplainStep {
+        step: "dst",
+        text: format!(
+            "{} does not exist in {} (clocks skip {} min forward); policy 'shift_forward' moved it to {}",
+            local.format("%Y-%m-%dT%H:%M:%S"),
+            timezone,
+            gap_secs / 60,
+            shifted.format("%Y-%m-%dT%H:%M:%S")
+        ),
+        details: json!({
+            "status": "nonexistent",
+            "policy": "shift_forward",
+            "gap_seconds": gap_secs,
+            "shifted": shifted.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "chosen": chosen,
+        }),
+    }
+}
+
+/// Wall-clock time minus its offset gives the instant
+pub fn local_to_utc_arithmetic(chosen: &LocalCandidate) -> ExplainStep {
+    let utc = DateTime::from_timestamp(chosen.unix_timestamp, 0).unwrap_or_default();
+    let offset_seconds = chosen.offset_seconds;
+    let local = utc.naive_utc() + chrono::Duration::seconds(i64::from(offset_seconds));
+    ExplainStep {
+        step: "arithmetic",
+        text: format!(
+            "{} - ({}) = {}",
+            local.format("%Y-%m-%dT%H:%M:%S"),
+            utc_offset(offset_seconds),
+            rfc3339(utc)
+        ),
+        details: json!({
+            "local": local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "offset_seconds": offset_seconds,
+            "utc": rfc3339(utc),
+            "unix_timestamp": utc.timestamp(),
+        }),
+    }
+}
+
+/// The instant plus the target zone's offset gives its wall-clock time
+pub fn utc_to_local_arithmetic(utc: DateTime<Utc>, local: DateTime<Tz>) -> ExplainStep {
+    let offset_seconds = local.offset().fix().local_minus_utc();
+    ExplainStep {
+        step: "arithmetic",
+        text: format!(
+            "{} + ({}) = {} in {}",
+            rfc3339(utc),
+            utc_offset(offset_seconds),
+            local.format("%Y-%m-%dT%H:%M:%S"),
+            local.timezone().name()
+        ),
+        details: json!({
+            "utc": rfc3339(utc),
+            "offset_seconds": offset_seconds,
+            "local": local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "timezone": local.timezone().name(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_collector_skips_steps() {
+        let mut explain = Explain::off();
+        explain.step(|| unreachable!("built while disabled"));
+        assert!(explain.finish().is_none());
+
+        let mut explain = Explain::new(true);
+        explain.step(|| timestamp_input(0, "UTC", true));
+        let steps = explain.finish().unwrap();
+        assert_eq!(steps[0].step, "input");
+        assert_eq!(steps[0].details["timezone_source"], "default");
+    }
+
+    #[test]
+    fn test_offset_rule_names_the_period() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let step = offset_rule(tz, "2024-12-01T12:00:00Z".parse().unwrap());
+        assert_eq!(step.details["offset_seconds"], -5 * 3600);
+        assert_eq!(step.details["abbreviation"], "EST");
+        assert_eq!(step.details["period_start"], "2024-11-03T06:00:00+00:00");
+        assert_eq!(step.details["period_end"], "2025-03-09T07:00:00+00:00");
+        assert!(step
+            .text
+            .starts_with("America/New_York is at UTC-05:00 (EST, standard time)"));
+
+        let utc: Tz = "UTC".parse().unwrap();
+        let step = offset_rule(utc, "2024-12-01T12:00:00Z".parse().unwrap());
+        assert_eq!(step.details["period_start"], Value::Null);
+        assert!(step
+            .text
+            .ends_with("(UTC, standard time) with no change within a year either side"));
+    }
+}
@@ -0,0 +1,297 @@
+// Caches for get_time_formatted
+//
+// Agents tend to ask for the same few formats over and over. Parsing a
+// format into chrono items is the dominant cost, so each distinct format is
+// validated and parsed once; and within one second the rendered string of a
+// (format, timezone) pair cannot change unless the format shows sub-second
+// digits, so that is kept too. Both maps are bounded and evict the least
+// recently used entry; rendered strings are dropped as soon as a request for
+// a later second arrives.
+
+use crate::error::TimeServerError;
+use crate::time::formats::tokenize;
+use crate::time::{StrftimeFormatter, TimezoneConverter};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Distinct formats kept parsed
+pub const PARSED_FORMAT_CAPACITY: usize = 256;
+
+/// Rendered strings kept for the current second
+pub const RENDERED_CAPACITY: usize = 256;
+
+/// A map that evicts its least recently used entry when full
+#[derive(Debug)]
+struct LruMap<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(value, used)| {
+            *used = tick;
+            value.clone()
+        })
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+type Items = Arc<[Item<'static>]>;
+
+/// (format, timezone) rendered within `second`
+#[derive(Debug)]
+struct Rendered {
+    second: i64,
+    strings: LruMap<(String, Option<String>), String>,
+}
+
+/// Parsed formats and rendered strings, shared by every session
+#[derive(Debug)]
+pub struct FormatCache {
+    parsed: Mutex<LruMap<String, (Items, bool)>>,
+    rendered: Mutex<Rendered>,
+}
+
+impl Default for FormatCache {
+    fn default() -> Self {
+        Self::new(PARSED_FORMAT_CAPACITY, RENDERED_CAPACITY)
+    }
+}
+
+impl FormatCache {
+    pub fn new(parsed_capacity: usize, rendered_capacity: usize) -> Self {
+        Self {
+            parsed: Mutex::new(LruMap::new(parsed_capacity)),
+            rendered: Mutex::new(Rendered {
+                second: i64::MIN,
+                strings: LruMap::new(rendered_capacity),
+            }),
+        }
+    }
+
+    /// `now` formatted with `format`, in `timezone` or UTC. Same result as
+    /// [`StrftimeFormatter::format`], including its errors.
+    pub fn format(
+        &self,
+        now: DateTime<Utc>,
+        format: &str,
+        timezone: Option<&str>,
+    ) -> Result<String, TimeServerError> {
+        let (items, subsecond) = self.items(format)?;
+        // A leap second shares its timestamp with the second before it
+        let subsecond = subsecond || now.timestamp_subsec_nanos() >= 1_000_000_000;
+        let second = now.timestamp();
+        let key = (format.to_string(), timezone.map(str::to_string));
+
+        if !subsecond {
+            let mut rendered = self
+                .rendered
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if rendered.second == second {
+                if let Some(hit) = rendered.strings.get(&key) {
+                    return Ok(hit);
+                }
+            }
+        }
+
+        let formatted = match timezone {
+            Some(tz) => TimezoneConverter::convert_to_tz(now, tz)?
+                .format_with_items(items.iter())
+                .to_string(),
+            None => now.format_with_items(items.iter()).to_string(),
+        };
+
+        if !subsecond {
+            let mut rendered = self
+                .rendered
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if second > rendered.second {
+                rendered.second = second;
+                rendered.strings.clear();
+            }
+            if rendered.second == second {
+                rendered.strings.insert(key, formatted.clone());
+            }
+        }
+        Ok(formatted)
+    }
+
+    /// Parsed items of `format` and whether it renders sub-second digits
+    fn items(&self, format: &str) -> Result<(Items, bool), TimeServerError> {
+        let key = format.to_string();
+        if let Some(hit) = self
+            .parsed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&key)
+        {
+            return Ok(hit);
+        }
+        StrftimeFormatter::validate(format)?;
+        let items: Items = StrftimeItems::new(format)
+            .parse_to_owned()
+            .map_err(|_| TimeServerError::InvalidFormat {
+                spec: format.to_string(),
+                offset: None,
+            })?
+            .into();
+        let subsecond = tokenize(format)
+            .iter()
+            .any(|token| token.is_directive && (token.text.ends_with('f') || token.text == "%+"));
+        self.parsed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key, (items.clone(), subsecond));
+        Ok((items, subsecond))
+    }
+
+    /// Formats currently kept parsed
+    pub fn parsed_len(&self) -> usize {
+        self.parsed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Rendered strings currently kept
+    pub fn rendered_len(&self) -> usize {
+        self.rendered
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .strings
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(seconds: i64, nanos: u32) -> DateTime<Utc> {
+        Utc.timestamp_opt(seconds, nanos).unwrap()
+    }
+
+    #[test]
+    fn test_matches_uncached_formatter() {
+        let cache = FormatCache::default();
+        let now = at(1_700_000_000, 123_456_789);
+        for format in ["%Y-%m-%d %H:%M:%S", "%a %Z %z", "%s.%3f", "%+"] {
+            assert_eq!(
+                cache.format(now, format, None).unwrap(),
+                StrftimeFormatter::format(&now, format).unwrap()
+            );
+            // Second call is served from the caches
+            assert_eq!(
+                cache.format(now, format, None).unwrap(),
+                StrftimeFormatter::format(&now, format).unwrap()
+            );
+        }
+        assert!(cache.format(now, "%Q", None).is_err());
+        assert_eq!(cache.parsed_len(), 4);
+    }
+
+    #[test]
+    fn test_rendered_entry_expires_with_the_second() {
+        let cache = FormatCache::default();
+        let format = "%H:%M:%S";
+        assert_eq!(cache.format(at(60, 0), format, None).unwrap(), "00:01:00");
+        assert_eq!(
+            cache.format(at(60, 999_999_999), format, None).unwrap(),
+            "00:01:00"
+        );
+        assert_eq!(cache.format(at(61, 0), format, None).unwrap(), "00:01:01");
+        assert_eq!(cache.rendered_len(), 1);
+
+        // A late request for an earlier second is rendered, not served
+        assert_eq!(cache.format(at(60, 5), format, None).unwrap(), "00:01:00");
+        assert_eq!(cache.format(at(61, 5), format, None).unwrap(), "00:01:01");
+    }
+
+    #[test]
+    fn test_subsecond_formats_are_not_reused() {
+        let cache = FormatCache::default();
+        assert_eq!(
+            cache.format(at(0, 100_000_000), "%.3f", None).unwrap(),
+            ".100"
+        );
+        assert_eq!(
+            cache.format(at(0, 200_000_000), "%.3f", None).unwrap(),
+            ".200"
+        );
+        assert_eq!(cache.rendered_len(), 0);
+    }
+
+    #[test]
+    fn test_timezones_do_not_collide() {
+        let cache = FormatCache::default();
+        let now = at(1_700_000_000, 0);
+        let format = "%H:%M %Z";
+        assert_eq!(cache.format(now, format, None).unwrap(), "22:13 UTC");
+        assert_eq!(
+            cache.format(now, format, Some("Asia/Tokyo")).unwrap(),
+            "07:13 JST"
+        );
+        assert_eq!(
+            cache.format(now, format, Some("America/New_York")).unwrap(),
+            "17:13 EST"
+        );
+        assert_eq!(cache.format(now, format, None).unwrap(), "22:13 UTC");
+        assert!(cache.format(now, format, Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_distinct_formats_are_bounded() {
+        let cache = FormatCache::new(8, 4);
+        let now = at(0, 0);
+        for i in 0..100 {
+            cache.format(now, &format!("{} %Y", i), None).unwrap();
+        }
+        assert_eq!(cache.parsed_len(), 8);
+        assert_eq!(cache.rendered_len(), 4);
+
+        // The most recently used entries survive eviction
+        cache.format(now, "99 %Y", None).unwrap();
+        cache.format(now, "new %Y", None).unwrap();
+        assert_eq!(cache.format(now, "99 %Y", None).unwrap(), "99 1970");
+    }
+}
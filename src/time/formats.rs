@@ -1,7 +1,13 @@
 // C-style strftime format support
 
-use chrono::{DateTime, Utc};
-use std::error::Error;
+use crate::error::TimeServerError;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::OnceLock;
 
 /// Format time using C strftime format strings
 pub struct StrftimeFormatter;
@@ -19,10 +25,1327 @@ impl StrftimeFormatter {
     /// %Z - Timezone name
     /// %c - Locale's date and time
     /// %s - Unix timestamp
-    pub fn format(dt: &DateTime<Utc>, format: &str) -> Result<String, Box<dyn Error>> {
-        // Use chrono's strftime-compatible formatting
+    pub fn format<Tz: TimeZone>(dt: &DateTime<Tz>, format: &str) -> Result<String, TimeServerError>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        // Validation also catches parse-only directives, which chrono
+        // accepts here but fails to render
+        Self::validate(format)?;
         Ok(dt.format(format).to_string())
     }
+
+    /// Reject format strings chrono cannot render; the error gives the byte
+    /// offset of the first bad specifier
+    pub fn validate(format: &str) -> Result<(), TimeServerError> {
+        match tokenize(format)
+            .iter()
+            .find(|token| token.is_directive && directive_problem(token.text).is_some())
+        {
+            None => Ok(()),
+            Some(token) => Err(TimeServerError::InvalidFormat {
+                spec: format.to_string(),
+                offset: Some(token.offset),
+            }),
+        }
+    }
+
+    /// Meaning of each directive in `format`, and the format rendered at
+    /// `now` and at [`REFERENCE_TIME`]. Unknown directives are listed
+    /// separately and copied to the rendered strings unchanged.
+    pub fn explain<Tz: TimeZone>(format: &str, now: &DateTime<Tz>) -> FormatExplanation
+    where
+        Tz::Offset: fmt::Display,
+    {
+        Self::explain_dialect(format, FormatDialect::Chrono, now)
+    }
+
+    /// [`Self::explain`] for a format written in `dialect`; GNU directives
+    /// are listed as written and rendered through their chrono equivalent
+    pub fn explain_dialect<Tz: TimeZone>(
+        format: &str,
+        dialect: FormatDialect,
+        now: &DateTime<Tz>,
+    ) -> FormatExplanation
+    where
+        Tz::Offset: fmt::Display,
+    {
+        let reference = reference_time();
+        let mut directives = Vec::new();
+        let mut unknown = Vec::new();
+        let mut rendered_now = String::new();
+        let mut rendered_reference = String::new();
+
+        for piece in pieces(format, dialect) {
+            let token = piece.token;
+            if !token.is_directive {
+                rendered_now.push_str(token.text);
+                rendered_reference.push_str(token.text);
+                continue;
+            }
+            let chrono = match piece.chrono {
+                Ok(chrono) => chrono,
+                Err(reason) => {
+                    unknown.push(UnknownDirective {
+                        directive: token.text.to_string(),
+                        offset: token.offset,
+                        reason,
+                    });
+                    rendered_now.push_str(token.text);
+                    rendered_reference.push_str(token.text);
+                    continue;
+                }
+            };
+
+            let example = reference.format(&chrono).to_string();
+            rendered_now.push_str(&now.format(&chrono).to_string());
+            rendered_reference.push_str(&example);
+            directives.push(DirectiveExplanation {
+                directive: token.text.to_string(),
+                offset: token.offset,
+                meaning: gnu_directive(token.text, dialect).map_or_else(
+                    || describe_directive(&chrono),
+                    |gnu| gnu.meaning.to_string(),
+                ),
+                example,
+            });
+        }
+
+        FormatExplanation {
+            format: format.to_string(),
+            dialect,
+            directives,
+            unknown,
+            rendered_now,
+            rendered_reference,
+            reference_time: REFERENCE_TIME,
+        }
+    }
+
+    /// Rewrite a GNU `date` format (with or without date's leading `+`) in
+    /// chrono's syntax. GNU directives chrono cannot reproduce are all named
+    /// in one error; directives neither knows fail as in [`Self::validate`].
+    pub fn translate_gnu(format: &str) -> Result<String, TimeServerError> {
+        let mut translated = String::with_capacity(format.len() + 4);
+        let mut unsupported = Vec::new();
+        for token in gnu_tokenize(format) {
+            if !token.is_directive {
+                translated.push_str(token.text);
+                continue;
+            }
+            match gnu_to_chrono(token.text) {
+                Ok(chrono) if directive_problem(&chrono).is_none() => translated.push_str(&chrono),
+                Ok(_) => {
+                    return Err(TimeServerError::InvalidFormat {
+                        spec: format.to_string(),
+                        offset: Some(token.offset),
+                    })
+                }
+                Err(reason) => unsupported.push(format!("{} ({})", token.text, reason)),
+            }
+        }
+        if !unsupported.is_empty() {
+            return Err(TimeServerError::InvalidValue {
+                field: "format".to_string(),
+                reason: format!(
+                    "GNU directives with no chrono equivalent: {}",
+                    unsupported.join(", ")
+                ),
+                cause: "invalid_format",
+            });
+        }
+        Ok(translated)
+    }
+
+    /// `format` in chrono's syntax, translated first when written in `dialect` gnu
+    pub fn to_chrono(
+        format: &str,
+        dialect: FormatDialect,
+    ) -> Result<Cow<'_, str>, TimeServerError> {
+        match dialect {
+            FormatDialect::Chrono => Ok(Cow::Borrowed(format)),
+            FormatDialect::Gnu => Self::translate_gnu(format).map(Cow::Owned),
+        }
+    }
+}
+
+/// Fixed instant whose fields are all distinct (Go's reference time plus
+/// nanoseconds), so each number in a rendering identifies its directive
+pub const REFERENCE_TIME: &str = "2006-01-02T15:04:05.123456789Z";
+
+fn reference_time() -> DateTime<Utc> {
+    REFERENCE_TIME.parse().expect("reference time is valid")
+}
+
+/// A directive chrono can render, with its meaning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectiveInfo {
+    pub spec: &'static str,
+    pub meaning: &'static str,
+}
+
+const fn directive(spec: &'static str, meaning: &'static str) -> DirectiveInfo {
+    DirectiveInfo { spec, meaning }
+}
+
+/// Every strftime directive chrono supports for formatting
+pub const DIRECTIVES: &[DirectiveInfo] = &[
+    directive("%Y", "year, at least 4 digits (2006)"),
+    directive("%C", "century, year / 100 (00-99)"),
+    directive("%y", "year modulo 100 (00-99)"),
+    directive("%q", "quarter of the year (1-4)"),
+    directive("%m", "month (01-12)"),
+    directive("%b", "abbreviated month name (Jan)"),
+    directive("%B", "full month name (January)"),
+    directive("%h", "abbreviated month name, same as %b"),
+    directive("%d", "day of month (01-31)"),
+    directive("%e", "day of month, space padded ( 1-31)"),
+    directive("%a", "abbreviated weekday name (Mon)"),
+    directive("%A", "full weekday name (Monday)"),
+    directive("%w", "weekday, Sunday = 0 (0-6)"),
+    directive("%u", "ISO weekday, Monday = 1 (1-7)"),
+    directive("%U", "week of year, weeks start Sunday (00-53)"),
+    directive("%W", "week of year, weeks start Monday (00-53)"),
+    directive("%G", "ISO 8601 week-based year"),
+    directive("%g", "ISO 8601 week-based year modulo 100 (00-99)"),
+    directive("%V", "ISO 8601 week number (01-53)"),
+    directive("%j", "day of year (001-366)"),
+    directive("%D", "month/day/year, same as %m/%d/%y"),
+    directive("%x", "date as month/day/year, same as %D"),
+    directive("%F", "year-month-day, same as %Y-%m-%d"),
+    directive("%v", "day-month-year, same as %e-%b-%Y"),
+    directive("%H", "hour, 24-hour clock (00-23)"),
+    directive("%k", "hour, 24-hour clock, space padded ( 0-23)"),
+    directive("%I", "hour, 12-hour clock (01-12)"),
+    directive("%l", "hour, 12-hour clock, space padded ( 1-12)"),
+    directive("%P", "am or pm"),
+    directive("%p", "AM or PM"),
+    directive("%M", "minute (00-59)"),
+    directive("%S", "second (00-60, 60 only for a leap second)"),
+    directive(
+        "%f",
+        "nanoseconds since the last whole second (0-999999999)",
+    ),
+    directive(
+        "%.f",
+        "fraction of a second with its dot, as many digits as needed",
+    ),
+    directive("%.3f", "milliseconds with a leading dot (.123)"),
+    directive("%.6f", "microseconds with a leading dot (.123456)"),
+    directive("%.9f", "nanoseconds with a leading dot (.123456789)"),
+    directive("%3f", "milliseconds without a dot (123)"),
+    directive("%6f", "microseconds without a dot (123456)"),
+    directive("%9f", "nanoseconds without a dot (123456789)"),
+    directive("%R", "hour:minute, same as %H:%M"),
+    directive("%T", "hour:minute:second, same as %H:%M:%S"),
+    directive("%X", "time as hour:minute:second, same as %T"),
+    directive("%r", "12-hour time, same as %I:%M:%S %p"),
+    directive(
+        "%Z",
+        "timezone abbreviation (UTC, CEST; numeric where none exists)",
+    ),
+    directive("%z", "UTC offset without colon (+0930)"),
+    directive("%:z", "UTC offset with colon (+09:30)"),
+    directive("%::z", "UTC offset with seconds (+09:30:00)"),
+    directive("%:::z", "UTC offset hours only (+09)"),
+    directive("%c", "date and time, same as %a %b %e %T %Y"),
+    directive("%+", "ISO 8601 / RFC 3339 date and time"),
+    directive("%s", "seconds since the Unix epoch"),
+    directive("%t", "literal tab"),
+    directive("%n", "literal newline"),
+    directive("%%", "literal percent sign"),
+];
+
+/// Directives chrono accepts for parsing but cannot render
+const PARSE_ONLY: &[&str] = &["%#z"];
+
+/// The strftime syntax a format is written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatDialect {
+    /// chrono's strftime: %f, %.9f, %3f for fractions
+    #[default]
+    Chrono,
+    /// GNU `date`: %N, %3N for fractions; a leading `+` is ignored
+    Gnu,
+}
+
+impl FormatDialect {
+    pub fn is_chrono(&self) -> bool {
+        *self == Self::Chrono
+    }
+}
+
+/// A GNU `date` directive that differs from chrono's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GnuDirective {
+    pub gnu: &'static str,
+    /// The chrono directive printing the same, or why there is none
+    pub chrono: Result<&'static str, &'static str>,
+    pub meaning: &'static str,
+}
+
+const fn gnu(
+    gnu: &'static str,
+    chrono: Result<&'static str, &'static str>,
+    meaning: &'static str,
+) -> GnuDirective {
+    GnuDirective {
+        gnu,
+        chrono,
+        meaning,
+    }
+}
+
+/// GNU directives chrono spells differently or cannot print. Every other
+/// GNU directive, padding flags (`-`, `_`, `0`) included, means the same in
+/// both; `E` and `O` modifiers are dropped, as in the C locale.
+pub const GNU_DIRECTIVES: &[GnuDirective] = &[
+    gnu("%N", Ok("%9f"), "nanoseconds (000000000-999999999)"),
+    gnu("%3N", Ok("%3f"), "milliseconds (000-999)"),
+    gnu("%6N", Ok("%6f"), "microseconds (000000-999999)"),
+    gnu("%9N", Ok("%9f"), "nanoseconds (000000000-999999999)"),
+    gnu(
+        "%:::z",
+        Err("GNU prints +hh or +hh:mm as needed; chrono's %:::z always drops the minutes"),
+        "UTC offset with colons, as precise as needed (+09, +05:30)",
+    ),
+];
+
+fn gnu_directive(directive: &str, dialect: FormatDialect) -> Option<&'static GnuDirective> {
+    (dialect == FormatDialect::Gnu)
+        .then(|| GNU_DIRECTIVES.iter().find(|d| d.gnu == directive))
+        .flatten()
+}
+
+/// The chrono directive for one GNU directive, or why there is none
+fn gnu_to_chrono(directive: &str) -> Result<Cow<'_, str>, &'static str> {
+    if let Some(gnu) = gnu_directive(directive, FormatDialect::Gnu) {
+        return gnu.chrono.map(Cow::Borrowed);
+    }
+    let body = &directive[1..];
+    let flags = body.bytes().take_while(|b| b"-_0^#".contains(b)).count();
+    let (flags, rest) = body.split_at(flags);
+    if flags.contains(['^', '#']) {
+        return Err("chrono has no case flags");
+    }
+    if rest.ends_with('N') {
+        return Err("chrono prints 3, 6 or 9 digits of a second, unpadded only");
+    }
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err("chrono has no field widths");
+    }
+    match rest.strip_prefix(|c| c == 'E' || c == 'O') {
+        Some(conversion) if !conversion.is_empty() => {
+            Ok(Cow::Owned(format!("%{}{}", flags, conversion)))
+        }
+        _ => Ok(Cow::Borrowed(directive)),
+    }
+}
+
+/// A token of a format with the chrono text it renders as, or why it has none
+struct Piece<'a> {
+    token: FormatToken<'a>,
+    chrono: Result<Cow<'a, str>, &'static str>,
+}
+
+/// Tokenize `format` as written in `dialect`, pairing each token with its
+/// chrono equivalent
+fn pieces(format: &str, dialect: FormatDialect) -> Vec<Piece<'_>> {
+    match dialect {
+        FormatDialect::Chrono => tokenize(format)
+            .into_iter()
+            .map(|token| Piece {
+                token,
+                chrono: match token
+                    .is_directive
+                    .then(|| directive_problem(token.text))
+                    .flatten()
+                {
+                    Some(problem) => Err(problem),
+                    None => Ok(Cow::Borrowed(token.text)),
+                },
+            })
+            .collect(),
+        FormatDialect::Gnu => gnu_tokenize(format)
+            .into_iter()
+            .map(|token| {
+                let chrono = if token.is_directive {
+                    gnu_to_chrono(token.text).and_then(|chrono| match directive_problem(&chrono) {
+                        Some(problem) => Err(problem),
+                        None => Ok(chrono),
+                    })
+                } else {
+                    Ok(Cow::Borrowed(token.text))
+                };
+                Piece { token, chrono }
+            })
+            .collect(),
+    }
+}
+
+/// Tokens of a GNU `date` format; a leading `+` (as in `date +FORMAT`) is
+/// skipped, and offsets count from the start of `format`
+fn gnu_tokenize(format: &str) -> Vec<FormatToken<'_>> {
+    let skip = usize::from(format.starts_with('+'));
+    tokenize_with(&format[skip..], gnu_specifier_len)
+        .into_iter()
+        .map(|token| FormatToken {
+            offset: token.offset + skip,
+            ..token
+        })
+        .collect()
+}
+
+/// A piece of a format string: literal text or one `%` directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatToken<'a> {
+    /// Byte offset in the format string
+    pub offset: usize,
+    pub text: &'a str,
+    pub is_directive: bool,
+}
+
+/// Split `format` into literal runs and directives. A directive is `%`,
+/// an optional padding modifier (`-`, `_`, `0`) and one specifier, where
+/// `.f`, `.3f`, `3f`, `:z` ... `:::z` and `#z` count as one specifier.
+pub fn tokenize(format: &str) -> Vec<FormatToken<'_>> {
+    tokenize_with(format, specifier_len)
+}
+
+/// [`tokenize`] with `specifier_len` deciding where each directive ends
+fn tokenize_with(format: &str, specifier_len: fn(&str) -> usize) -> Vec<FormatToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < format.len() {
+        if format.as_bytes()[i] != b'%' {
+            i += 1;
+            continue;
+        }
+        if literal_start < i {
+            tokens.push(FormatToken {
+                offset: literal_start,
+                text: &format[literal_start..i],
+                is_directive: false,
+            });
+        }
+        let end = i + 1 + specifier_len(&format[i + 1..]);
+        tokens.push(FormatToken {
+            offset: i,
+            text: &format[i..end],
+            is_directive: true,
+        });
+        i = end;
+        literal_start = end;
+    }
+    if literal_start < format.len() {
+        tokens.push(FormatToken {
+            offset: literal_start,
+            text: &format[literal_start..],
+            is_directive: false,
+        });
+    }
+    tokens
+}
+
+/// Length of the specifier (with padding modifier) following a `%`
+fn specifier_len(rest: &str) -> usize {
+    let bytes = rest.as_bytes();
+    let start = usize::from(matches!(bytes.first(), Some(b'-' | b'_' | b'0')));
+    let at = |i: usize| bytes.get(start + i).copied();
+    match at(0) {
+        Some(b'.') => match (at(1), at(2)) {
+            (Some(b'f'), _) => start + 2,
+            (Some(b'3' | b'6' | b'9'), Some(b'f')) => start + 3,
+            _ => start + 1,
+        },
+        Some(b'3' | b'6' | b'9') if at(1) == Some(b'f') => start + 2,
+        Some(b':') => {
+            let colons = (0..3).take_while(|&i| at(i) == Some(b':')).count();
+            if at(colons) == Some(b'z') {
+                start + colons + 1
+            } else {
+                start + 1
+            }
+        }
+        Some(b'#') if at(1) == Some(b'z') => start + 2,
+        Some(_) => start + rest[start..].chars().next().map_or(0, char::len_utf8),
+        None => start,
+    }
+}
+
+/// Length of a GNU `date` specifier following a `%`: flags (`-_0^#`), a
+/// field width, an `E` or `O` modifier and one conversion, where `:z` ...
+/// `:::z` count as one
+fn gnu_specifier_len(rest: &str) -> usize {
+    let bytes = rest.as_bytes();
+    let mut i = bytes.iter().take_while(|b| b"-_0^#".contains(b)).count();
+    i += bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+    if matches!(bytes.get(i), Some(b'E' | b'O')) {
+        i += 1;
+    }
+    let colons = bytes[i..]
+        .iter()
+        .take(3)
+        .take_while(|&&b| b == b':')
+        .count();
+    if colons > 0 && bytes.get(i + colons) == Some(&b'z') {
+        return i + colons + 1;
+    }
+    i + rest[i..].chars().next().map_or(0, char::len_utf8)
+}
+
+/// Why chrono cannot render `directive`, if it cannot
+fn directive_problem(directive: &str) -> Option<&'static str> {
+    if PARSE_ONLY.contains(&directive) {
+        Some("only valid when parsing")
+    } else if directive.len() == 1 {
+        Some("'%' at the end of the format; write %% for a literal percent sign")
+    } else if StrftimeItems::new(directive).any(|item| item == Item::Error) {
+        if matches!(directive.as_bytes()[1], b'-' | b'_' | b'0') && directive.len() > 2 {
+            Some("padding modifiers only apply to numeric directives")
+        } else {
+            Some("not a strftime directive")
+        }
+    } else {
+        None
+    }
+}
+
+/// Dictionary meaning of a valid directive, with its padding modifier
+fn describe_directive(directive: &str) -> String {
+    let (base, padding) = match directive.as_bytes().get(1) {
+        Some(b'-') => (format!("%{}", &directive[2..]), Some("no padding")),
+        Some(b'_') => (format!("%{}", &directive[2..]), Some("padded with spaces")),
+        Some(b'0') if directive.len() > 2 => {
+            (format!("%{}", &directive[2..]), Some("padded with zeros"))
+        }
+        _ => (directive.to_string(), None),
+    };
+    let meaning = DIRECTIVES
+        .iter()
+        .find(|d| d.spec == base)
+        .map_or("strftime directive", |d| d.meaning);
+    match padding {
+        Some(padding) => format!("{}, {}", meaning, padding),
+        None => meaning.to_string(),
+    }
+}
+
+/// What a format token prints, which decides the separator placed before
+/// the next token when none is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A numeric date field
+    Date,
+    /// A month or weekday name
+    Name,
+    /// A numeric clock field
+    Clock,
+    /// Fraction of a second, which brings its own dot
+    Fraction,
+    Meridiem,
+    Zone,
+    /// A composite or standalone directive
+    Other,
+}
+
+/// A named directive for `build_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatTokenInfo {
+    pub name: &'static str,
+    pub spec: &'static str,
+    pub kind: TokenKind,
+}
+
+const fn token(name: &'static str, spec: &'static str, kind: TokenKind) -> FormatTokenInfo {
+    FormatTokenInfo { name, spec, kind }
+}
+
+/// Token vocabulary of `build_format`; each directive has at most one name,
+/// so a decomposed format builds back to itself
+pub const FORMAT_TOKENS: &[FormatTokenInfo] = &[
+    token("year", "%Y", TokenKind::Date),
+    token("year2", "%y", TokenKind::Date),
+    token("century", "%C", TokenKind::Date),
+    token("quarter", "%q", TokenKind::Date),
+    token("month", "%m", TokenKind::Date),
+    token("month_name", "%B", TokenKind::Name),
+    token("month_name_short", "%b", TokenKind::Name),
+    token("day", "%d", TokenKind::Date),
+    token("day_space", "%e", TokenKind::Date),
+    token("day_of_year", "%j", TokenKind::Date),
+    token("weekday", "%A", TokenKind::Name),
+    token("weekday_short", "%a", TokenKind::Name),
+    token("weekday_number", "%u", TokenKind::Date),
+    token("iso_year", "%G", TokenKind::Date),
+    token("iso_week", "%V", TokenKind::Date),
+    token("week_sunday", "%U", TokenKind::Date),
+    token("week_monday", "%W", TokenKind::Date),
+    token("hour24", "%H", TokenKind::Clock),
+    token("hour24_space", "%k", TokenKind::Clock),
+    token("hour12", "%I", TokenKind::Clock),
+    token("hour12_space", "%l", TokenKind::Clock),
+    token("minute", "%M", TokenKind::Clock),
+    token("second", "%S", TokenKind::Clock),
+    token("fraction", "%.f", TokenKind::Fraction),
+    token("millis", "%.3f", TokenKind::Fraction),
+    token("micros", "%.6f", TokenKind::Fraction),
+    token("nanos", "%.9f", TokenKind::Fraction),
+    token("am_pm", "%p", TokenKind::Meridiem),
+    token("am_pm_lower", "%P", TokenKind::Meridiem),
+    token("tz_abbr", "%Z", TokenKind::Zone),
+    token("offset", "%:z", TokenKind::Zone),
+    token("offset_compact", "%z", TokenKind::Zone),
+    token("date", "%F", TokenKind::Other),
+    token("time", "%T", TokenKind::Other),
+    token("datetime", "%c", TokenKind::Other),
+    token("iso8601", "%+", TokenKind::Other),
+    token("unix", "%s", TokenKind::Other),
+    token("percent", "%%", TokenKind::Other),
+];
+
+/// Other words a description may use for a token
+const TOKEN_ALIASES: &[(&str, &str)] = &[
+    ("years", "year"),
+    ("months", "month"),
+    ("days", "day"),
+    ("hour", "hour24"),
+    ("hours", "hour24"),
+    ("min", "minute"),
+    ("minutes", "minute"),
+    ("sec", "second"),
+    ("seconds", "second"),
+    ("ms", "millis"),
+    ("milliseconds", "millis"),
+    ("microseconds", "micros"),
+    ("nanoseconds", "nanos"),
+    ("ampm", "am_pm"),
+    ("timezone", "tz_abbr"),
+    ("tz", "tz_abbr"),
+    ("zone", "tz_abbr"),
+    ("utc_offset", "offset"),
+    ("week", "iso_week"),
+    ("epoch", "unix"),
+];
+
+/// Description words that join tokens without adding to the format
+const FILLER_WORDS: &[&str] = &["a", "and", "at", "in", "plus", "the", "then", "with"];
+
+/// Suggestions listed for an unknown token
+const TOKEN_SUGGESTIONS: usize = 3;
+
+/// The token named `word` (a name or alias, any case)
+fn lookup_token(word: &str) -> Option<&'static FormatTokenInfo> {
+    let word = word.to_ascii_lowercase();
+    let name = TOKEN_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == word)
+        .map_or(word.as_str(), |(_, name)| name);
+    FORMAT_TOKENS.iter().find(|t| t.name == name)
+}
+
+/// Token names close to `word`: containing it, contained in it, having all
+/// of its `_`-separated parts, or a couple of edits away, closest first
+fn suggest_tokens(word: &str) -> Vec<&'static str> {
+    let word = word.to_ascii_lowercase();
+    let mut scored: Vec<(usize, &'static str)> = FORMAT_TOKENS
+        .iter()
+        .filter_map(|t| {
+            let distance = crate::time::timezone::levenshtein(&word, t.name);
+            let related = word.len() > 1 && (t.name.contains(&word) || word.contains(t.name))
+                || word
+                    .split('_')
+                    .all(|part| t.name.split('_').any(|name| name == part));
+            (distance <= 2 || related).then_some((distance, t.name))
+        })
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(TOKEN_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Separator put between two tokens given without one
+fn default_separator(previous: TokenKind, next: TokenKind) -> &'static str {
+    match (previous, next) {
+        (_, TokenKind::Fraction) => "",
+        (TokenKind::Clock, TokenKind::Clock) => ":",
+        (TokenKind::Date, TokenKind::Date) => "-",
+        _ => " ",
+    }
+}
+
+/// One piece of a built or decomposed format, in format order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormatPart {
+    Token {
+        token: &'static str,
+        directive: &'static str,
+    },
+    Literal {
+        text: String,
+    },
+    /// A valid directive with no token name (`%-d`, `%w`, ...)
+    Directive {
+        directive: String,
+    },
+}
+
+impl FormatPart {
+    fn token(info: &FormatTokenInfo) -> Self {
+        FormatPart::Token {
+            token: info.name,
+            directive: info.spec,
+        }
+    }
+
+    /// The part's text in a strftime format
+    fn spec(&self) -> &str {
+        match self {
+            FormatPart::Token { directive, .. } => directive,
+            FormatPart::Literal { text } => text,
+            FormatPart::Directive { directive } => directive,
+        }
+    }
+}
+
+/// A word of a description or list that names no token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownToken {
+    pub token: String,
+    /// Close token names, best first
+    pub suggestions: Vec<&'static str>,
+}
+
+/// A strftime format with its tokens, from `build_format`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FormatBuild {
+    pub format: String,
+    pub parts: Vec<FormatPart>,
+    /// Left out of `format`
+    pub unknown: Vec<UnknownToken>,
+    /// The format rendered at [`REFERENCE_TIME`]
+    pub example: String,
+    pub reference_time: &'static str,
+}
+
+impl FormatBuild {
+    fn new(parts: Vec<FormatPart>, unknown: Vec<UnknownToken>) -> Self {
+        let format: String = parts.iter().map(FormatPart::spec).collect();
+        let example = reference_time().format(&format).to_string();
+        Self {
+            format,
+            parts,
+            unknown,
+            example,
+            reference_time: REFERENCE_TIME,
+        }
+    }
+
+    /// A format from token names in order. Items without letters or digits
+    /// are literal separators; between two tokens with none, a separator
+    /// suited to both is added ("-" between date fields, ":" between clock
+    /// fields, a space otherwise).
+    pub fn from_tokens<S: AsRef<str>>(tokens: &[S]) -> Self {
+        let mut parts = Vec::new();
+        let mut unknown = Vec::new();
+        let mut previous: Option<TokenKind> = None;
+        for item in tokens {
+            let item = item.as_ref();
+            if !item.chars().any(char::is_alphanumeric) {
+                parts.push(FormatPart::Literal {
+                    text: item.replace('%', "%%"),
+                });
+                previous = None;
+            } else if let Some(info) = lookup_token(item.trim()) {
+                if let Some(previous) = previous {
+                    push_literal(&mut parts, default_separator(previous, info.kind));
+                }
+                parts.push(FormatPart::token(info));
+                previous = Some(info.kind);
+            } else {
+                unknown.push(UnknownToken {
+                    token: item.to_string(),
+                    suggestions: suggest_tokens(item.trim()),
+                });
+            }
+        }
+        Self::new(parts, unknown)
+    }
+
+    /// A format from a description such as "year-month-day hour:minute with
+    /// timezone, 24h". Words are token names or aliases ("hour", "timezone")
+    /// and the text between two of them is kept as written; filler words
+    /// ("with", "and") are skipped, and "12h" turns 24-hour fields into
+    /// 12-hour ones with AM/PM after the last clock field.
+    pub fn from_description(description: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut unknown = Vec::new();
+        let mut twelve_hour = false;
+        // Text since the last token, and whether a word was skipped since
+        let mut pending: Option<String> = None;
+        let mut skipped = false;
+        for (is_word, piece) in description_pieces(description) {
+            if !is_word {
+                let any_token = parts.iter().any(|p| matches!(p, FormatPart::Token { .. }));
+                if any_token && !skipped {
+                    pending.get_or_insert_with(String::new).push_str(piece);
+                }
+                continue;
+            }
+            let lower = piece.to_ascii_lowercase();
+            match lower.as_str() {
+                "12h" => twelve_hour = true,
+                "24h" => {}
+                word if FILLER_WORDS.contains(&word) => {}
+                _ => match lookup_token(piece) {
+                    Some(info) => {
+                        if let Some(text) = pending.take() {
+                            push_literal(&mut parts, &text);
+                        }
+                        parts.push(FormatPart::token(info));
+                        skipped = false;
+                        continue;
+                    }
+                    None => unknown.push(UnknownToken {
+                        token: piece.to_string(),
+                        suggestions: suggest_tokens(piece),
+                    }),
+                },
+            }
+            // A skipped word stands in for one space before the next token
+            if !skipped && pending.is_none() && !parts.is_empty() {
+                pending = Some(" ".to_string());
+            }
+            skipped = true;
+        }
+        if twelve_hour {
+            to_twelve_hour(&mut parts);
+        }
+        Self::new(parts, unknown)
+    }
+
+    /// Best-effort split of an existing format into tokens and literals,
+    /// in order; directives without a token name are kept as they are
+    pub fn decompose(format: &str) -> Result<Self, TimeServerError> {
+        StrftimeFormatter::validate(format)?;
+        let parts = tokenize(format)
+            .into_iter()
+            .map(|piece| {
+                if !piece.is_directive {
+                    return FormatPart::Literal {
+                        text: piece.text.to_string(),
+                    };
+                }
+                match FORMAT_TOKENS.iter().find(|t| t.spec == piece.text) {
+                    Some(info) => FormatPart::token(info),
+                    None => FormatPart::Directive {
+                        directive: piece.text.to_string(),
+                    },
+                }
+            })
+            .collect();
+        Ok(Self::new(parts, Vec::new()))
+    }
+}
+
+/// Words (letters, digits, `_`) and the text between them, in order
+fn description_pieces(description: &str) -> Vec<(bool, &str)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, c) in description.char_indices().skip(1) {
+        let previous = description[..i].chars().next_back().unwrap_or(c);
+        if is_word(c) != is_word(previous) {
+            pieces.push((is_word(previous), &description[start..i]));
+            start = i;
+        }
+    }
+    if start < description.len() {
+        let last = description[start..].chars().next().unwrap_or(' ');
+        pieces.push((is_word(last), &description[start..]));
+    }
+    pieces
+}
+
+/// Append literal text, escaping `%`
+fn push_literal(parts: &mut Vec<FormatPart>, text: &str) {
+    if !text.is_empty() {
+        parts.push(FormatPart::Literal {
+            text: text.replace('%', "%%"),
+        });
+    }
+}
+
+/// Swap 24-hour fields for 12-hour ones and add AM/PM after the last clock
+/// field if the parts have no meridiem yet
+fn to_twelve_hour(parts: &mut Vec<FormatPart>) {
+    let twelve = |name: &str| match name {
+        "hour24" => lookup_token("hour12"),
+        "hour24_space" => lookup_token("hour12_space"),
+        _ => None,
+    };
+    for part in parts.iter_mut() {
+        if let FormatPart::Token { token, .. } = part {
+            if let Some(info) = twelve(token) {
+                *part = FormatPart::token(info);
+            }
+        }
+    }
+    let kind_of = |part: &FormatPart| match part {
+        FormatPart::Token { token, .. } => lookup_token(token).map(|t| t.kind),
+        _ => None,
+    };
+    if parts
+        .iter()
+        .any(|p| kind_of(p) == Some(TokenKind::Meridiem))
+    {
+        return;
+    }
+    let last_clock = parts
+        .iter()
+        .rposition(|p| matches!(kind_of(p), Some(TokenKind::Clock | TokenKind::Fraction)));
+    if let Some(at) = last_clock {
+        let am_pm = lookup_token("am_pm").expect("am_pm is a token");
+        parts.insert(at + 1, FormatPart::token(am_pm));
+        parts.insert(
+            at + 1,
+            FormatPart::Literal {
+                text: " ".to_string(),
+            },
+        );
+    }
+}
+
+/// Digit set for formatted output, named by its CLDR numbering system id
+///
+/// Transliteration happens after chrono renders each directive, so only
+/// directive output changes: literal text in the format keeps its digits.
+/// Directives that print several fields at once (`%c`, `%T`, `%Z` for
+/// zones without an abbreviation) have all of their digits converted, and
+/// names stay English whatever the digit set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberSystem {
+    /// ASCII digits 0-9
+    #[default]
+    Latn,
+    /// Arabic-Indic digits (U+0660-U+0669)
+    Arab,
+    /// Extended Arabic-Indic digits used for Persian and Urdu (U+06F0-U+06F9)
+    Arabext,
+    /// Devanagari digits (U+0966-U+096F)
+    Deva,
+}
+
+impl NumberSystem {
+    /// CLDR numbering system id
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Latn => "latn",
+            Self::Arab => "arab",
+            Self::Arabext => "arabext",
+            Self::Deva => "deva",
+        }
+    }
+
+    fn zero(self) -> char {
+        match self {
+            Self::Latn => '0',
+            Self::Arab => '\u{0660}',
+            Self::Arabext => '\u{06F0}',
+            Self::Deva => '\u{0966}',
+        }
+    }
+
+    /// Separator before fractional seconds
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Self::Arab | Self::Arabext => '\u{066B}',
+            Self::Latn | Self::Deva => '.',
+        }
+    }
+
+    /// The system named by a BCP 47 `-u-nu-` extension, as in
+    /// `ar-EG-u-nu-arab`; `None` when absent or not one of ours
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let subtags: Vec<String> = locale
+            .split(['-', '_'])
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let unicode = subtags.iter().position(|tag| tag == "u")?;
+        let nu = subtags[unicode + 1..]
+            .iter()
+            .take_while(|tag| tag.len() > 1)
+            .position(|tag| tag == "nu")?;
+        let id = subtags.get(unicode + 1 + nu + 1)?;
+        serde_json::from_value(serde_json::Value::String(id.clone())).ok()
+    }
+
+    /// `text` with ASCII digits replaced by this system's digits
+    pub fn transliterate(self, text: &str) -> String {
+        let zero = self.zero() as u32;
+        text.chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .and_then(|digit| char::from_u32(zero + digit))
+                    .unwrap_or(c)
+            })
+            .collect()
+    }
+
+    /// Render `format` for `dt` in this digit set. Each directive is
+    /// rendered alone and transliterated; fractional-second directives
+    /// (`%.f`, `%.3f` ...) also get the decimal separator.
+    pub fn format<Tz: TimeZone>(
+        self,
+        dt: &DateTime<Tz>,
+        format: &str,
+    ) -> Result<String, TimeServerError>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        StrftimeFormatter::validate(format)?;
+        let mut rendered = String::with_capacity(format.len() * 2);
+        for token in tokenize(format) {
+            if !token.is_directive {
+                rendered.push_str(token.text);
+                continue;
+            }
+            let mut text = self.transliterate(&dt.format(token.text).to_string());
+            let fractional = token.text[1..]
+                .trim_start_matches(['-', '_', '0'])
+                .starts_with('.');
+            if fractional {
+                text = text.replacen('.', &self.decimal_separator().to_string(), 1);
+            }
+            rendered.push_str(&text);
+        }
+        Ok(rendered)
+    }
+}
+
+/// Zone names RFC 5322 §4.3 keeps for parsing, with their offsets in hours
+const OBSOLETE_ZONES: &[(&str, i32)] = &[
+    ("UT", 0),
+    ("GMT", 0),
+    ("EST", -5),
+    ("EDT", -4),
+    ("CST", -6),
+    ("CDT", -5),
+    ("MST", -7),
+    ("MDT", -6),
+    ("PST", -8),
+    ("PDT", -7),
+];
+
+const EMAIL_MONTHS: &[&str] = &[
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const EMAIL_DAYS: &[&str] = &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// An email `Date:` value, parsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmailDate {
+    pub unix_seconds: i64,
+    /// The instant in UTC, RFC 3339
+    pub utc: String,
+    /// Offset the header gave, `+hh:mm`
+    pub offset: String,
+    pub offset_seconds: i32,
+    /// `-0000` or an unknown zone name: the offset says nothing about where
+    /// the sender was
+    pub offset_unknown: bool,
+    /// The same instant and offset as a compliant `Date:` value
+    pub normalized: String,
+    /// What was accepted beyond the strict RFC 5322 grammar
+    pub notes: Vec<String>,
+}
+
+/// A strictly RFC 5322 `Date:` value: `Tue, 01 Jul 2003 10:52:37 +0200`
+pub fn format_email_date<Tz: TimeZone>(dt: &DateTime<Tz>) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    dt.format("%a, %d %b %Y %H:%M:%S %z").to_string()
+}
+
+/// Parse a `Date:` header value, accepting the obsolete forms of RFC 5322
+/// §4.3 that real mail still carries: comments, one-digit time fields,
+/// two- and three-digit years, and zone names. Military one-letter zones and unknown names are read as `-0000`
+/// (RFC 5322 says their meaning cannot be trusted). Each accepted deviation
+/// is listed in `notes`.
+pub fn parse_email_date(input: &str) -> Result<EmailDate, TimeServerError> {
+    use chrono::{Datelike, FixedOffset, NaiveDate};
+
+    let invalid =
+        |why: String| TimeServerError::InvalidTimestamp(format!("'{}': {}", input.trim(), why));
+    let mut notes = Vec::new();
+
+    let mut text = input.trim();
+    if text
+        .get(..5)
+        .is_some_and(|name| name.eq_ignore_ascii_case("date:"))
+    {
+        text = &text[5..];
+    }
+    let (text, commented) = strip_comments(text).map_err(|why| invalid(why.into()))?;
+    if commented {
+        notes.push("comments removed".to_string());
+    }
+
+    // Optional day-of-week, checked against the date once that is known
+    let day_index = |name: &str| EMAIL_DAYS.iter().position(|d| d.eq_ignore_ascii_case(name));
+    let (day_name, rest) = match text.split_once(',') {
+        Some((name, rest)) => {
+            let name = name.trim();
+            let index = day_index(name)
+                .ok_or_else(|| invalid(format!("unknown day of week '{}'", name)))?;
+            (Some(index), rest)
+        }
+        None => match text.split_once(' ') {
+            Some((name, rest)) if day_index(name).is_some() => {
+                notes.push("no comma after the day of week".to_string());
+                (day_index(name), rest)
+            }
+            _ => (None, text.as_str()),
+        },
+    };
+
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let (day, month, year, time, zone) = match fields[..] {
+        [day, month, year, time, zone] => (day, month, year, time, Some(zone)),
+        [day, month, year, time] => (day, month, year, time, None),
+        _ => {
+            return Err(invalid(
+                "expected 'day month year hh:mm[:ss] zone'".to_string(),
+            ))
+        }
+    };
+
+    let day: u32 = day
+        .parse()
+        .ok()
+        .filter(|_| (1..=2).contains(&day.len()))
+        .ok_or_else(|| invalid(format!("bad day '{}'", day)))?;
+    let month = EMAIL_MONTHS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(month))
+        .ok_or_else(|| invalid(format!("unknown month '{}'", month)))? as u32
+        + 1;
+    let year = {
+        let value: i32 = year
+            .parse()
+            .ok()
+            .filter(|_| year.bytes().all(|b| b.is_ascii_digit()))
+            .ok_or_else(|| invalid(format!("bad year '{}'", year)))?;
+        match year.len() {
+            2 => {
+                let full = if value < 50 {
+                    2000 + value
+                } else {
+                    1900 + value
+                };
+                notes.push(format!("two-digit year {} read as {}", year, full));
+                full
+            }
+            3 => {
+                notes.push(format!(
+                    "three-digit year {} read as {}",
+                    year,
+                    1900 + value
+                ));
+                1900 + value
+            }
+            _ => value,
+        }
+    };
+
+    let parts: Vec<&str> = time.split(':').collect();
+    let number = |text: &str, what: &str| {
+        text.parse::<u32>()
+            .ok()
+            .filter(|_| (1..=2).contains(&text.len()))
+            .ok_or_else(|| invalid(format!("bad {} in '{}'", what, time)))
+    };
+    let (hour, minute, second) = match parts[..] {
+        [h, m] => (number(h, "hour")?, number(m, "minute")?, 0),
+        [h, m, s] => (
+            number(h, "hour")?,
+            number(m, "minute")?,
+            number(s, "second")?,
+        ),
+        _ => return Err(invalid(format!("bad time '{}'", time))),
+    };
+    if parts.iter().any(|part| part.len() == 1) {
+        notes.push("one-digit time field".to_string());
+    }
+    // A leap second is kept as chrono's 60th second
+    let (second, nanos) = if second == 60 {
+        notes.push("leap second".to_string());
+        (59, 1_000_000_000)
+    } else {
+        (second, 0)
+    };
+
+    let (offset_seconds, offset_unknown) = match zone {
+        None => {
+            notes.push("no zone; UTC assumed".to_string());
+            (0, true)
+        }
+        Some(zone) => parse_email_zone(zone, &mut notes)
+            .ok_or_else(|| invalid(format!("bad zone '{}'", zone)))?,
+    };
+
+    let date = NaiveDate::from_ymd_opt(year, month, day)
+        .ok_or_else(|| invalid(format!("no such date {}-{:02}-{:02}", year, month, day)))?;
+    let local = date
+        .and_hms_nano_opt(hour, minute, second, nanos)
+        .ok_or_else(|| invalid(format!("no such time '{}'", time)))?;
+    let offset = FixedOffset::east_opt(offset_seconds)
+        .ok_or_else(|| invalid("offset out of range".to_string()))?;
+    let at = offset
+        .from_local_datetime(&local)
+        .single()
+        .ok_or_else(|| invalid("offset out of range".to_string()))?;
+
+    if let Some(index) = day_name {
+        let actual = date.weekday().num_days_from_monday() as usize;
+        if index != actual {
+            notes.push(format!(
+                "day of week {} does not match the date ({})",
+                EMAIL_DAYS[index], EMAIL_DAYS[actual]
+            ));
+        }
+    }
+
+    let normalized = if offset_unknown {
+        at.format("%a, %d %b %Y %H:%M:%S -0000").to_string()
+    } else {
+        format_email_date(&at)
+    };
+    Ok(EmailDate {
+        unix_seconds: at.timestamp(),
+        utc: at
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        offset: offset.to_string(),
+        offset_seconds,
+        offset_unknown,
+        normalized,
+        notes,
+    })
+}
+
+/// Offset in seconds and whether it is unknown; `None` when malformed
+fn parse_email_zone(zone: &str, notes: &mut Vec<String>) -> Option<(i32, bool)> {
+    if let Some(sign) = zone.strip_prefix(['+', '-']).map(|_| zone.as_bytes()[0]) {
+        let digits: String = zone[1..].chars().filter(|c| *c != ':').collect();
+        if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if zone.contains(':') {
+            notes.push(format!("colon in offset '{}'", zone));
+        }
+        let hours: i32 = digits[..2].parse().ok()?;
+        let minutes: i32 = digits[2..].parse().ok()?;
+        if minutes > 59 {
+            return None;
+        }
+        let seconds = (hours * 60 + minutes) * 60;
+        return Some(match sign {
+            b'-' if seconds == 0 => (0, true),
+            b'-' => (-seconds, false),
+            _ => (seconds, false),
+        });
+    }
+    if !zone.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    let upper = zone.to_ascii_uppercase();
+    if upper == "Z" {
+        notes.push("military zone Z read as +0000".to_string());
+        return Some((0, false));
+    }
+    match OBSOLETE_ZONES.iter().find(|(name, _)| *name == upper) {
+        Some((_, hours)) => {
+            notes.push(format!("obsolete zone {} read as {:+03}00", upper, hours));
+            Some((hours * 3600, false))
+        }
+        None => {
+            notes.push(format!("unknown zone {} read as -0000", upper));
+            Some((0, true))
+        }
+    }
+}
+
+/// `text` without RFC 5322 comments (nested, with `\` escapes), and
+/// whether there were any
+fn strip_comments(text: &str) -> Result<(String, bool), &'static str> {
+    let mut out = String::with_capacity(text.len());
+    let mut commented = false;
+    let mut depth = 0usize;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if depth > 0 => {
+                chars.next();
+            }
+            '(' => {
+                depth += 1;
+                commented = true;
+            }
+            ')' if depth == 0 => return Err("unbalanced ')'"),
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    out.push(' ');
+                }
+            }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("unclosed comment");
+    }
+    Ok((out.trim().to_string(), commented))
+}
+
+/// A recognized directive in an explained format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectiveExplanation {
+    pub directive: String,
+    /// Byte offset in the format string
+    pub offset: usize,
+    pub meaning: String,
+    /// The directive rendered at the reference time
+    pub example: String,
+}
+
+/// A directive that cannot be rendered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownDirective {
+    pub directive: String,
+    pub offset: usize,
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FormatExplanation {
+    pub format: String,
+    #[serde(skip_serializing_if = "FormatDialect::is_chrono")]
+    pub dialect: FormatDialect,
+    pub directives: Vec<DirectiveExplanation>,
+    pub unknown: Vec<UnknownDirective>,
+    pub rendered_now: String,
+    pub rendered_reference: String,
+    pub reference_time: &'static str,
 }
 
 /// Common Unix time formats
@@ -37,12 +1360,94 @@ impl StandardFormats {
     pub const SYSLOG: &'static str = "%b %d %H:%M:%S";
     pub const APACHE_LOG: &'static str = "%d/%b/%Y:%H:%M:%S %z";
     pub const UNIX_TIMESTAMP: &'static str = "%s";
+
+    /// Every constant above with a short description
+    pub const NAMED: &'static [NamedFormat] = &[
+        NamedFormat::new(
+            "ISO_8601",
+            Self::ISO_8601,
+            "ISO 8601 with fractional seconds and offset",
+        ),
+        NamedFormat::new(
+            "RFC_3339",
+            Self::RFC_3339,
+            "RFC 3339 with a space separator",
+        ),
+        NamedFormat::new("RFC_2822", Self::RFC_2822, "RFC 2822 email date"),
+        NamedFormat::new("CTIME", Self::CTIME, "C library ctime() style"),
+        NamedFormat::new(
+            "UNIX_DATE",
+            Self::UNIX_DATE,
+            "Output of the Unix date command",
+        ),
+        NamedFormat::new("SYSLOG", Self::SYSLOG, "Syslog timestamp (no year)"),
+        NamedFormat::new("APACHE_LOG", Self::APACHE_LOG, "Apache common log format"),
+        NamedFormat::new(
+            "UNIX_TIMESTAMP",
+            Self::UNIX_TIMESTAMP,
+            "Seconds since the Unix epoch",
+        ),
+    ];
+}
+
+/// `StandardFormats` used by every time response, parsed once
+#[derive(Debug)]
+pub struct FormatTemplates {
+    pub ctime: Vec<Item<'static>>,
+    pub unix_date: Vec<Item<'static>>,
+    pub syslog: Vec<Item<'static>>,
+    pub apache_log: Vec<Item<'static>>,
+}
+
+impl FormatTemplates {
+    fn parse() -> Self {
+        let parse = |format: &'static str| {
+            StrftimeItems::new(format)
+                .parse()
+                .expect("standard formats are valid")
+        };
+        Self {
+            ctime: parse(StandardFormats::CTIME),
+            unix_date: parse(StandardFormats::UNIX_DATE),
+            syslog: parse(StandardFormats::SYSLOG),
+            apache_log: parse(StandardFormats::APACHE_LOG),
+        }
+    }
+
+    /// The process-wide templates
+    pub fn global() -> &'static Self {
+        static TEMPLATES: OnceLock<FormatTemplates> = OnceLock::new();
+        TEMPLATES.get_or_init(Self::parse)
+    }
+
+    /// Render `items` for `dt`
+    pub fn render(dt: &DateTime<Utc>, items: &[Item<'static>]) -> String {
+        dt.format_with_items(items.iter()).to_string()
+    }
+}
+
+/// A `StandardFormats` constant by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedFormat {
+    pub name: &'static str,
+    pub format: &'static str,
+    pub description: &'static str,
+}
+
+impl NamedFormat {
+    const fn new(name: &'static str, format: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            format,
+            description,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{Datelike, Utc};
+    use chrono::{Datelike, FixedOffset, Utc};
 
     #[test]
     fn test_strftime_formats() {
@@ -59,4 +1464,499 @@ mod tests {
         let custom = StrftimeFormatter::format(&now, "%Y-%m-%d %H:%M:%S").unwrap();
         assert_eq!(custom.len(), 19);
     }
+
+    #[test]
+    fn test_invalid_format_reports_offset() {
+        let offset = |f: &str| match StrftimeFormatter::validate(f) {
+            Err(TimeServerError::InvalidFormat { offset, .. }) => offset,
+            other => panic!("{:?} for {}", other, f),
+        };
+        assert_eq!(offset("%Y-%m %Q"), Some(6));
+        assert_eq!(offset("100%% %"), Some(6));
+        assert_eq!(offset("%Y-%m-%d %:"), Some(9));
+
+        assert!(StrftimeFormatter::validate("100%% done %-d %.3f %::z").is_ok());
+        assert!(StrftimeFormatter::format(&Utc::now(), "%Q").is_err());
+        assert!(StrftimeFormatter::format(&Utc::now(), "%#z").is_err());
+    }
+
+    #[test]
+    fn test_every_chrono_directive_is_in_the_dictionary() {
+        // Single-character specifiers, found by asking chrono
+        for c in (0x21u8..0x7f).map(char::from) {
+            let spec = format!("%{}", c);
+            let supported = !StrftimeItems::new(&spec).any(|item| item == Item::Error)
+                && !PARSE_ONLY.contains(&spec.as_str());
+            let listed = DIRECTIVES.iter().any(|d| d.spec == spec);
+            assert_eq!(supported, listed, "{}", spec);
+        }
+        // Every entry is a single token that renders
+        for d in DIRECTIVES {
+            let tokens = tokenize(d.spec);
+            assert_eq!(tokens.len(), 1, "{}", d.spec);
+            assert!(directive_problem(d.spec).is_none(), "{}", d.spec);
+        }
+        assert!(StrftimeFormatter::validate("%#z").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_literals_and_escapes() {
+        let tokens: Vec<(&str, bool)> = tokenize("at %H:%M%% %-d%.3f %::z é%")
+            .iter()
+            .map(|t| (t.text, t.is_directive))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                ("at ", false),
+                ("%H", true),
+                (":", false),
+                ("%M", true),
+                ("%%", true),
+                (" ", false),
+                ("%-d", true),
+                ("%.3f", true),
+                (" ", false),
+                ("%::z", true),
+                (" é", false),
+                ("%", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_agrees_with_chrono() {
+        for format in [
+            "%Y-%m-%d",
+            "%Q",
+            "%-Z",
+            "%.3x",
+            "%::::z",
+            "%_q",
+            "100%% done",
+            "%",
+            "%E",
+            "%+",
+        ] {
+            let chrono_ok = !StrftimeItems::new(format).any(|item| item == Item::Error);
+            assert_eq!(
+                StrftimeFormatter::validate(format).is_ok(),
+                chrono_ok,
+                "{}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_format() {
+        let now = reference_time();
+        let explained = StrftimeFormatter::explain("Day %j of %Y, 100%% %Q%-m", &now);
+        assert_eq!(explained.rendered_reference, "Day 002 of 2006, 100% %Q1");
+        assert_eq!(explained.rendered_now, explained.rendered_reference);
+
+        let specs: Vec<&str> = explained
+            .directives
+            .iter()
+            .map(|d| d.directive.as_str())
+            .collect();
+        assert_eq!(specs, ["%j", "%Y", "%%", "%-m"]);
+        assert_eq!(explained.directives[0].meaning, "day of year (001-366)");
+        assert_eq!(explained.directives[0].example, "002");
+        assert_eq!(explained.directives[3].meaning, "month (01-12), no padding");
+
+        assert_eq!(explained.unknown.len(), 1);
+        assert_eq!(explained.unknown[0].directive, "%Q");
+        assert_eq!(explained.unknown[0].offset, 20);
+    }
+
+    #[test]
+    fn test_gnu_dialect() {
+        let date_style = "+%Y-%m-%dT%H:%M:%S.%N%:z";
+        let chrono = StrftimeFormatter::translate_gnu(date_style).unwrap();
+        assert_eq!(chrono, "%Y-%m-%dT%H:%M:%S.%9f%:z");
+        let kolkata = reference_time().with_timezone(&FixedOffset::east_opt(19800).unwrap());
+        assert_eq!(
+            StrftimeFormatter::format(&kolkata, &chrono).unwrap(),
+            "2006-01-02T20:34:05.123456789+05:30"
+        );
+
+        // chrono has no %N (and keeps the '+' as text)
+        match StrftimeFormatter::validate(date_style) {
+            Err(TimeServerError::InvalidFormat { offset, .. }) => assert_eq!(offset, Some(19)),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Padding flags mean the same; E and O modifiers are dropped
+        assert_eq!(
+            StrftimeFormatter::translate_gnu("%-d/%_m %0e %Ey %Od %3N %6N %9N %%").unwrap(),
+            "%-d/%_m %0e %y %d %3f %6f %9f %%"
+        );
+
+        let error = StrftimeFormatter::translate_gnu("%^a %:::z %5N %-N %10Y").unwrap_err();
+        let message = error.to_string();
+        for named in ["%^a", "%:::z", "%5N", "%-N", "%10Y"] {
+            assert!(message.contains(named), "{} not in {}", named, message);
+        }
+        assert_eq!(error.code(), "invalid_value");
+
+        // Unknown to both: reported where it stands in the GNU format
+        match StrftimeFormatter::translate_gnu("+%Y %Q") {
+            Err(TimeServerError::InvalidFormat { offset, .. }) => assert_eq!(offset, Some(4)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_explain_gnu_format() {
+        let now = reference_time();
+        let explained = StrftimeFormatter::explain_dialect("+%s.%N %^a", FormatDialect::Gnu, &now);
+        assert_eq!(explained.rendered_reference, "1136214245.123456789 %^a");
+        assert_eq!(explained.directives[1].directive, "%N");
+        assert_eq!(explained.directives[1].offset, 4);
+        assert_eq!(
+            explained.directives[1].meaning,
+            "nanoseconds (000000000-999999999)"
+        );
+        assert_eq!(explained.unknown[0].directive, "%^a");
+        assert_eq!(explained.unknown[0].reason, "chrono has no case flags");
+        assert_eq!(serde_json::to_value(&explained).unwrap()["dialect"], "gnu");
+
+        let chrono = StrftimeFormatter::explain("%s", &now);
+        assert!(serde_json::to_value(&chrono)
+            .unwrap()
+            .get("dialect")
+            .is_none());
+    }
+
+    #[test]
+    fn test_build_format_from_tokens() {
+        let cases: &[(&[&str], &str, &str)] = &[
+            (&["year", "month", "day"], "%Y-%m-%d", "2006-01-02"),
+            (&["hour24", "minute", "second"], "%H:%M:%S", "15:04:05"),
+            (
+                &[
+                    "year",
+                    "month_name_short",
+                    "day",
+                    "hour24",
+                    "minute",
+                    "tz_abbr",
+                ],
+                "%Y %b %d %H:%M %Z",
+                "2006 Jan 02 15:04 UTC",
+            ),
+            (
+                &["day", "/", "month", "/", "year"],
+                "%d/%m/%Y",
+                "02/01/2006",
+            ),
+            (
+                &["weekday", ", ", "month_name", "day"],
+                "%A, %B %d",
+                "Monday, January 02",
+            ),
+            (&["hour12", "minute", "am_pm"], "%I:%M %p", "03:04 PM"),
+            (&["second", "millis"], "%S%.3f", "05.123"),
+            (
+                &["date", "time", "offset"],
+                "%F %T %:z",
+                "2006-01-02 15:04:05 +00:00",
+            ),
+            (
+                &["iso_year", "iso_week", "weekday_number"],
+                "%G-%V-%u",
+                "2006-01-1",
+            ),
+            (&["unix"], "%s", "1136214245"),
+            (&["day_of_year", "percent"], "%j %%", "002 %"),
+            (&["Year", "Quarter"], "%Y-%q", "2006-1"),
+        ];
+        for (tokens, format, example) in cases {
+            let build = FormatBuild::from_tokens(tokens);
+            assert_eq!(build.unknown, vec![], "{:?}", tokens);
+            assert_eq!(
+                (build.format.as_str(), build.example.as_str()),
+                (*format, *example)
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_format_from_description() {
+        let build = FormatBuild::from_description("year-month-day hour:minute with timezone, 24h");
+        assert_eq!(build.format, "%Y-%m-%d %H:%M %Z");
+        assert_eq!(build.example, "2006-01-02 15:04 UTC");
+
+        let build = FormatBuild::from_description("hour:minute:second, 12h");
+        assert_eq!(build.format, "%I:%M:%S %p");
+        assert_eq!(build.example, "03:04:05 PM");
+
+        let build = FormatBuild::from_description("the date and time");
+        assert_eq!(build.format, "%F %T");
+        assert_eq!(build.example, "2006-01-02 15:04:05");
+    }
+
+    #[test]
+    fn test_unknown_tokens_get_suggestions() {
+        let build = FormatBuild::from_tokens(&["yeer", "month_short", "day"]);
+        assert_eq!(build.format, "%d");
+        assert_eq!(
+            build.unknown,
+            vec![
+                UnknownToken {
+                    token: "yeer".into(),
+                    suggestions: vec!["year", "year2"],
+                },
+                UnknownToken {
+                    token: "month_short".into(),
+                    suggestions: vec!["month_name_short", "month"],
+                },
+            ]
+        );
+
+        let build = FormatBuild::from_description("year and fortnight");
+        assert_eq!(build.format, "%Y");
+        assert_eq!(build.unknown[0].token, "fortnight");
+        assert!(build.unknown[0].suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_standard_formats_decompose_and_rebuild() {
+        for named in StandardFormats::NAMED {
+            let build = FormatBuild::decompose(named.format).unwrap();
+            assert_eq!(build.format, named.format);
+            assert!(
+                !build
+                    .parts
+                    .iter()
+                    .any(|p| matches!(p, FormatPart::Directive { .. })),
+                "{} has a directive without a token",
+                named.name
+            );
+        }
+
+        let build = FormatBuild::decompose(StandardFormats::ISO_8601).unwrap();
+        let tokens: Vec<&str> = build
+            .parts
+            .iter()
+            .filter_map(|p| match p {
+                FormatPart::Token { token, .. } => Some(*token),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            ["year", "month", "day", "hour24", "minute", "second", "fraction", "offset"]
+        );
+        assert_eq!(
+            build.parts[5],
+            FormatPart::Literal {
+                text: "T".to_string()
+            }
+        );
+
+        // Padded variants have no name but keep their place
+        let build = FormatBuild::decompose("%-d.%m").unwrap();
+        assert_eq!(
+            build.parts[0],
+            FormatPart::Directive {
+                directive: "%-d".to_string()
+            }
+        );
+        assert!(FormatBuild::decompose("%Y-%Q").is_err());
+    }
+
+    #[test]
+    fn test_token_vocabulary_is_consistent() {
+        for info in FORMAT_TOKENS {
+            assert!(
+                DIRECTIVES.iter().any(|d| d.spec == info.spec),
+                "{} is not in the dictionary",
+                info.spec
+            );
+            assert_eq!(
+                FORMAT_TOKENS.iter().filter(|t| t.spec == info.spec).count(),
+                1,
+                "{} has two names",
+                info.spec
+            );
+        }
+        for (alias, name) in TOKEN_ALIASES {
+            assert!(lookup_token(name).is_some(), "{} -> {}", alias, name);
+        }
+    }
+
+    #[test]
+    fn test_templates_match_format_strings() {
+        let now = Utc::now();
+        let templates = FormatTemplates::global();
+        assert_eq!(
+            FormatTemplates::render(&now, &templates.apache_log),
+            now.format(StandardFormats::APACHE_LOG).to_string()
+        );
+        assert_eq!(
+            FormatTemplates::render(&now, &templates.ctime),
+            now.format(StandardFormats::CTIME).to_string()
+        );
+    }
+
+    #[test]
+    fn test_number_system_transliterates_directive_output() {
+        let dt = Utc.with_ymd_and_hms(2025, 3, 7, 14, 5, 9).unwrap()
+            + chrono::Duration::milliseconds(250);
+        let arab = NumberSystem::Arab
+            .format(&dt, "%Y-%m-%d %H:%M:%S%.3f")
+            .unwrap();
+        assert_eq!(
+            arab,
+            "\u{0662}\u{0660}\u{0662}\u{0665}-\u{0660}\u{0663}-\u{0660}\u{0667} \
+             \u{0661}\u{0664}:\u{0660}\u{0665}:\u{0660}\u{0669}\u{066B}\u{0662}\u{0665}\u{0660}"
+        );
+        assert_eq!(
+            NumberSystem::Deva.format(&dt, "%d").unwrap(),
+            "\u{0966}\u{096D}"
+        );
+        assert_eq!(
+            NumberSystem::Latn.format(&dt, "%.3f").unwrap(),
+            StrftimeFormatter::format(&dt, "%.3f").unwrap()
+        );
+        assert!(NumberSystem::Arab.format(&dt, "%Q").is_err());
+    }
+
+    #[test]
+    fn test_number_system_leaves_literal_text_alone() {
+        let dt = Utc.with_ymd_and_hms(2025, 3, 7, 14, 5, 9).unwrap();
+        assert_eq!(
+            NumberSystem::Arabext
+                .format(&dt, "Room 101 at %H.00")
+                .unwrap(),
+            "Room 101 at \u{06F1}\u{06F4}.00"
+        );
+    }
+
+    #[test]
+    fn test_number_system_from_locale_extension() {
+        assert_eq!(
+            NumberSystem::from_locale("ar-EG-u-nu-arab"),
+            Some(NumberSystem::Arab)
+        );
+        assert_eq!(
+            NumberSystem::from_locale("hi-IN-u-ca-gregory-nu-deva"),
+            Some(NumberSystem::Deva)
+        );
+        assert_eq!(NumberSystem::from_locale("ar-EG"), None);
+        assert_eq!(NumberSystem::from_locale("th-u-nu-thai"), None);
+        assert!(serde_json::from_str::<NumberSystem>("\"roman\"").is_err());
+    }
+
+    #[test]
+    fn test_email_date_corpus() {
+        // (header, unix seconds, offset seconds)
+        let good = [
+            ("Tue, 1 Jul 2003 10:52:37 +0200", 1057049557, 7200),
+            ("Date: Tue, 01 Jul 2003 10:52:37 +0200", 1057049557, 7200),
+            (
+                "Thu, 13 Feb 1969 23:32 -0330 (Newfoundland Time)",
+                -27723480,
+                -12600,
+            ),
+            ("Fri, 21 Nov 1997 09:55:06 -0600", 880127706, -21600),
+            ("21 Nov 97 09:55:06 GMT", 880106106, 0),
+            ("Mon, 3 Mar 2025 7:05:09 EST", 1741003509, -18000),
+            ("Wed , 5 Mar 2025 12:00:00 PDT", 1741201200, -25200),
+            ("Thu, 6 Mar 2025 12:00:00 -0000", 1741262400, 0),
+            ("Sat, 31 Dec 2016 23:59:60 +0000", 1483228799, 0),
+            ("Tue 1 Jul 2003 10:52:37 +02:00", 1057049557, 7200),
+            (
+                "Sun, 2 Mar 2025 (a (nested) comment) 08:00:00 +0100",
+                1740898800,
+                3600,
+            ),
+        ];
+        for (header, unix, offset) in good {
+            let parsed = parse_email_date(header).unwrap_or_else(|e| panic!("{}: {}", header, e));
+            assert_eq!(parsed.unix_seconds, unix, "{}", header);
+            assert_eq!(parsed.offset_seconds, offset, "{}", header);
+        }
+
+        let bad = [
+            "",
+            "yesterday",
+            "Tue, 1 Jul 2003",
+            "Tue, 32 Jul 2003 10:52:37 +0200",
+            "Tue, 1 Jly 2003 10:52:37 +0200",
+            "Tue, 1 Jul 2003 25:00:00 +0200",
+            "Tue, 1 Jul 2003 10:52:37 +02",
+            "Tue, 1 Jul 2003 10:52:37 +0200 (unclosed",
+            "Tuesday, 1 Jul 2003 10:52:37 +0200",
+            "2003-07-01T10:52:37+02:00",
+        ];
+        for header in bad {
+            assert!(parse_email_date(header).is_err(), "{}", header);
+        }
+    }
+
+    #[test]
+    fn test_email_date_notes_and_obsolete_zones() {
+        let strict = parse_email_date("Tue, 01 Jul 2003 10:52:37 +0200").unwrap();
+        assert!(strict.notes.is_empty(), "{:?}", strict.notes);
+        assert_eq!(strict.normalized, "Tue, 01 Jul 2003 10:52:37 +0200");
+        assert_eq!(strict.offset, "+02:00");
+
+        for (zone, offset) in [
+            ("UT", 0),
+            ("EDT", -4),
+            ("CST", -6),
+            ("MDT", -6),
+            ("pst", -8),
+        ] {
+            let parsed = parse_email_date(&format!("1 Jan 2025 00:00:00 {}", zone)).unwrap();
+            assert_eq!(parsed.offset_seconds, offset * 3600, "{}", zone);
+            assert!(!parsed.offset_unknown, "{}", zone);
+            assert!(
+                parsed.notes[0].starts_with("obsolete zone"),
+                "{:?}",
+                parsed.notes
+            );
+        }
+        // Military letters other than Z, and unknown names, mean nothing
+        for zone in ["A", "M", "Y", "CET"] {
+            let parsed = parse_email_date(&format!("1 Jan 2025 00:00:00 {}", zone)).unwrap();
+            assert_eq!(parsed.offset_seconds, 0, "{}", zone);
+            assert!(parsed.offset_unknown, "{}", zone);
+            assert!(
+                parsed.normalized.ends_with("-0000"),
+                "{}",
+                parsed.normalized
+            );
+        }
+
+        let wrong_day = parse_email_date("Mon, 01 Jul 2003 10:52:37 +0200").unwrap();
+        assert!(
+            wrong_day.notes[0].contains("does not match"),
+            "{:?}",
+            wrong_day.notes
+        );
+        let old = parse_email_date("1 Jul 103 10:52:37 +0200 (three-digit year)").unwrap();
+        assert_eq!(old.unix_seconds, 1057049557);
+        assert_eq!(old.notes.len(), 2, "{:?}", old.notes);
+    }
+
+    #[test]
+    fn test_email_date_round_trip() {
+        let tz: chrono_tz::Tz = "Asia/Kolkata".parse().unwrap();
+        for unix in [0, 1_057_049_557, 1_741_003_509, -27_723_480, 4_102_444_800] {
+            let at = Utc.timestamp_opt(unix, 0).unwrap();
+            for header in [
+                format_email_date(&at),
+                format_email_date(&at.with_timezone(&tz)),
+            ] {
+                let parsed = parse_email_date(&header).unwrap();
+                assert_eq!(parsed.unix_seconds, unix, "{}", header);
+                assert_eq!(parsed.normalized, header);
+                assert!(parsed.notes.is_empty(), "{}: {:?}", header, parsed.notes);
+            }
+        }
+    }
 }
@@ -0,0 +1,588 @@
+// Public holiday calendars
+//
+// A calendar is a list of rules (fixed date, nth weekday of a month, offset
+// from Easter, explicit dates) evaluated per year, plus an observance policy
+// that moves weekend holidays onto a weekday. US federal, UK (England and
+// Wales) and German nationwide holidays are built in; `HOLIDAY_CALENDAR_FILE`
+// adds calendars, or replaces a built-in one with the same region, from JSON:
+//
+// {"calendars": [{"region": "ACME", "holidays": [
+//     {"name": "Founders' Day", "type": "fixed", "month": 6, "day": 2},
+//     {"name": "Harvest Friday", "type": "nth_weekday", "month": 9,
+//      "weekday": "Fri", "nth": -1, "observance": "none"},
+//     {"name": "Spring Holiday", "type": "easter", "offset_days": 1},
+//     {"name": "Move day", "type": "dates", "dates": ["2025-03-14"]}]}]}
+//
+// One-off changes to the built-in sets (royal events, moved bank holidays)
+// are not included; add them from a file as `dates` rules.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+
+/// Regions with built-in rules
+pub const BUILT_IN_REGIONS: [&str; 3] = ["US", "UK", "DE"];
+
+/// Days searched for the next business day before giving up
+const MAX_BUSINESS_DAY_SEARCH: u32 = 366;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HolidayRule {
+    /// Same month and day every year
+    Fixed { month: u32, day: u32 },
+    /// The `nth` `weekday` of `month`; negative counts from the end (-1 is the last)
+    NthWeekday {
+        month: u32,
+        weekday: Weekday,
+        nth: i8,
+    },
+    /// Days after Easter Sunday, negative for days before
+    Easter { offset_days: i64 },
+    /// Explicit dates, for one-off holidays
+    Dates { dates: Vec<NaiveDate> },
+}
+
+/// Where a holiday falling on a weekend is taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Observance {
+    /// It stays on the weekend
+    #[default]
+    None,
+    /// Saturday moves to Friday, Sunday to Monday (US federal)
+    NearestWeekday,
+    /// The next weekday that is not already a holiday (UK substitute days)
+    NextWeekday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HolidayDefinition {
+    pub name: String,
+    #[serde(flatten)]
+    pub rule: HolidayRule,
+    #[serde(default)]
+    pub observance: Observance,
+    /// First year the holiday is kept
+    #[serde(default)]
+    pub from_year: Option<i32>,
+}
+
+/// One holiday in one year
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Holiday {
+    pub name: String,
+    /// The date the rule gives
+    pub date: NaiveDate,
+    /// The day off; differs from `date` when a weekend holiday was moved
+    pub observed: NaiveDate,
+}
+
+pub trait HolidayCalendar: Send + Sync {
+    /// "US", "UK", or the name of a custom calendar
+    fn region(&self) -> &str;
+
+    /// Every holiday whose rule date is in `year`, ordered by observed date
+    fn holidays_in_year(&self, year: i32) -> Vec<Holiday>;
+
+    /// Holidays dated or observed on `date`
+    fn holidays_on(&self, date: NaiveDate) -> Vec<Holiday> {
+        // Observance can cross the year boundary (1 January on a Saturday)
+        (date.year() - 1..=date.year() + 1)
+            .flat_map(|year| self.holidays_in_year(year))
+            .filter(|holiday| holiday.date == date || holiday.observed == date)
+            .collect()
+    }
+
+    fn is_business_day(&self, date: NaiveDate) -> bool {
+        !is_weekend(date) && self.holidays_on(date).is_empty()
+    }
+
+    /// First business day after `date`; `None` if there is none within a year
+    fn next_business_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        date.iter_days()
+            .skip(1)
+            .take(MAX_BUSINESS_DAY_SEARCH as usize)
+            .find(|&day| self.is_business_day(day))
+    }
+}
+
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Easter Sunday in the Gregorian calendar (anonymous Gregorian computus)
+pub fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year.rem_euclid(19);
+    let b = year.div_euclid(100);
+    let c = year.rem_euclid(100);
+    let (d, e) = (b / 4, b % 4);
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let (i, k) = (c / 4, c % 4);
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, nth: i8) -> Option<NaiveDate> {
+    if nth > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, nth as u8)
+    } else {
+        let next_month = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)
+        }?;
+        let last = next_month.pred_opt()?;
+        let back = (last.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+        let date = last - Duration::days(back as i64 + 7 * (-(nth as i64) - 1));
+        (date.month() == month).then_some(date)
+    }
+}
+
+impl HolidayRule {
+    fn dates_in(&self, year: i32) -> Vec<NaiveDate> {
+        match self {
+            HolidayRule::Fixed { month, day } => NaiveDate::from_ymd_opt(year, *month, *day)
+                .into_iter()
+                .collect(),
+            HolidayRule::NthWeekday {
+                month,
+                weekday,
+                nth,
+            } => nth_weekday(year, *month, *weekday, *nth)
+                .into_iter()
+                .collect(),
+            HolidayRule::Easter { offset_days } => easter_sunday(year)
+                .map(|easter| easter + Duration::days(*offset_days))
+                .into_iter()
+                .collect(),
+            HolidayRule::Dates { dates } => dates
+                .iter()
+                .copied()
+                .filter(|date| date.year() == year)
+                .collect(),
+        }
+    }
+}
+
+impl HolidayDefinition {
+    /// Why the definition can never produce a date, if it cannot
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("holiday name must not be empty".to_string());
+        }
+        let valid = match &self.rule {
+            // 2024 is a leap year, so 29 February is accepted
+            HolidayRule::Fixed { month, day } => {
+                NaiveDate::from_ymd_opt(2024, *month, *day).is_some()
+            }
+            HolidayRule::NthWeekday { month, nth, .. } => {
+                (1..=12).contains(month) && *nth != 0 && (-5..=5).contains(nth)
+            }
+            HolidayRule::Easter { offset_days } => offset_days.abs() <= 366,
+            HolidayRule::Dates { dates } => !dates.is_empty(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' has an invalid rule {:?}",
+                self.name, self.rule
+            ))
+        }
+    }
+}
+
+/// A calendar evaluated from [`HolidayDefinition`]s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCalendar {
+    region: String,
+    definitions: Vec<HolidayDefinition>,
+}
+
+impl RuleCalendar {
+    pub fn new(region: impl Into<String>, definitions: Vec<HolidayDefinition>) -> Self {
+        Self {
+            region: region.into(),
+            definitions,
+        }
+    }
+}
+
+impl HolidayCalendar for RuleCalendar {
+    fn region(&self) -> &str {
+        &self.region
+    }
+
+    fn holidays_in_year(&self, year: i32) -> Vec<Holiday> {
+        let dated: Vec<(&HolidayDefinition, NaiveDate)> = self
+            .definitions
+            .iter()
+            .filter(|def| def.from_year.is_none_or(|from| year >= from))
+            .flat_map(|def| {
+                def.rule
+                    .dates_in(year)
+                    .into_iter()
+                    .map(move |date| (def, date))
+            })
+            .collect();
+
+        // Substitute days go to the first weekday not already taken, so
+        // weekday holidays are placed before any weekend one is moved
+        let mut taken: HashSet<NaiveDate> = dated
+            .iter()
+            .map(|&(_, date)| date)
+            .filter(|&date| !is_weekend(date))
+            .collect();
+
+        let mut holidays: Vec<Holiday> = dated
+            .into_iter()
+            .map(|(def, date)| {
+                let observed = match (def.observance, date.weekday()) {
+                    (Observance::NearestWeekday, Weekday::Sat) => date - Duration::days(1),
+                    (Observance::NearestWeekday, Weekday::Sun) => date + Duration::days(1),
+                    (Observance::NextWeekday, _) if is_weekend(date) => {
+                        let observed = date
+                            .iter_days()
+                            .find(|day| !is_weekend(*day) && !taken.contains(day))
+                            .unwrap_or(date);
+                        taken.insert(observed);
+                        observed
+                    }
+                    _ => date,
+                };
+                Holiday {
+                    name: def.name.clone(),
+                    date,
+                    observed,
+                }
+            })
+            .collect();
+        holidays.sort_by_key(|holiday| (holiday.observed, holiday.date));
+        holidays
+    }
+}
+
+fn holiday(name: &str, rule: HolidayRule, observance: Observance) -> HolidayDefinition {
+    HolidayDefinition {
+        name: name.to_string(),
+        rule,
+        observance,
+        from_year: None,
+    }
+}
+
+fn fixed(month: u32, day: u32) -> HolidayRule {
+    HolidayRule::Fixed { month, day }
+}
+
+fn nth(nth: i8, weekday: Weekday, month: u32) -> HolidayRule {
+    HolidayRule::NthWeekday {
+        month,
+        weekday,
+        nth,
+    }
+}
+
+fn easter(offset_days: i64) -> HolidayRule {
+    HolidayRule::Easter { offset_days }
+}
+
+/// The built-in calendar for `region` ("US", "UK" or "DE")
+pub fn built_in(region: &str) -> Option<RuleCalendar> {
+    use Observance::{NearestWeekday, NextWeekday};
+    use Weekday::{Mon, Thu};
+
+    let definitions = match region {
+        "US" => vec![
+            holiday("New Year's Day", fixed(1, 1), NearestWeekday),
+            holiday(
+                "Martin Luther King Jr. Day",
+                nth(3, Mon, 1),
+                Observance::None,
+            ),
+            holiday("Washington's Birthday", nth(3, Mon, 2), Observance::None),
+            holiday("Memorial Day", nth(-1, Mon, 5), Observance::None),
+            HolidayDefinition {
+                from_year: Some(2021),
+                ..holiday(
+                    "Juneteenth National Independence Day",
+                    fixed(6, 19),
+                    NearestWeekday,
+                )
+            },
+            holiday("Independence Day", fixed(7, 4), NearestWeekday),
+            holiday("Labor Day", nth(1, Mon, 9), Observance::None),
+            holiday("Columbus Day", nth(2, Mon, 10), Observance::None),
+            holiday("Veterans Day", fixed(11, 11), NearestWeekday),
+            holiday("Thanksgiving Day", nth(4, Thu, 11), Observance::None),
+            holiday("Christmas Day", fixed(12, 25), NearestWeekday),
+        ],
+        "UK" => vec![
+            holiday("New Year's Day", fixed(1, 1), NextWeekday),
+            holiday("Good Friday", easter(-2), Observance::None),
+            holiday("Easter Monday", easter(1), Observance::None),
+            holiday("Early May Bank Holiday", nth(1, Mon, 5), Observance::None),
+            holiday("Spring Bank Holiday", nth(-1, Mon, 5), Observance::None),
+            holiday("Summer Bank Holiday", nth(-1, Mon, 8), Observance::None),
+            holiday("Christmas Day", fixed(12, 25), NextWeekday),
+            holiday("Boxing Day", fixed(12, 26), NextWeekday),
+        ],
+        "DE" => vec![
+            holiday("New Year's Day", fixed(1, 1), Observance::None),
+            holiday("Good Friday", easter(-2), Observance::None),
+            holiday("Easter Monday", easter(1), Observance::None),
+            holiday("Labour Day", fixed(5, 1), Observance::None),
+            holiday("Ascension Day", easter(39), Observance::None),
+            holiday("Whit Monday", easter(50), Observance::None),
+            holiday("German Unity Day", fixed(10, 3), Observance::None),
+            holiday("Christmas Day", fixed(12, 25), Observance::None),
+            holiday("Second Day of Christmas", fixed(12, 26), Observance::None),
+        ],
+        _ => return None,
+    };
+    Some(RuleCalendar::new(region, definitions))
+}
+
+#[derive(Debug, Error)]
+pub enum HolidayFileError {
+    #[error("cannot read holiday calendar file {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("malformed holiday calendar file {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid holiday calendar file {path}: {message}")]
+    Invalid { path: String, message: String },
+}
+
+#[derive(Deserialize)]
+struct CalendarFile {
+    calendars: Vec<CalendarSpec>,
+}
+
+#[derive(Deserialize)]
+struct CalendarSpec {
+    region: String,
+    holidays: Vec<HolidayDefinition>,
+}
+
+/// Calendars from the JSON text of a calendar file; `path` is for errors
+pub fn parse_calendars(text: &str, path: &str) -> Result<Vec<RuleCalendar>, HolidayFileError> {
+    let file: CalendarFile =
+        serde_json::from_str(text).map_err(|source| HolidayFileError::Parse {
+            path: path.to_string(),
+            source,
+        })?;
+    let invalid = |message: String| HolidayFileError::Invalid {
+        path: path.to_string(),
+        message,
+    };
+
+    file.calendars
+        .into_iter()
+        .map(|spec| {
+            let region = spec.region.trim().to_uppercase();
+            if region.is_empty() {
+                return Err(invalid("region must not be empty".to_string()));
+            }
+            for definition in &spec.holidays {
+                definition
+                    .validate()
+                    .map_err(|message| invalid(format!("{}: {}", region, message)))?;
+            }
+            Ok(RuleCalendar::new(region, spec.holidays))
+        })
+        .collect()
+}
+
+pub fn load_file(path: &str) -> Result<Vec<RuleCalendar>, HolidayFileError> {
+    let text = std::fs::read_to_string(path).map_err(|source| HolidayFileError::Io {
+        path: path.to_string(),
+        source,
+    })?;
+    parse_calendars(&text, path)
+}
+
+static CALENDARS: OnceLock<Vec<Arc<dyn HolidayCalendar>>> = OnceLock::new();
+
+fn built_in_calendars() -> Vec<Arc<dyn HolidayCalendar>> {
+    BUILT_IN_REGIONS
+        .iter()
+        .filter_map(|region| built_in(region))
+        .map(|calendar| Arc::new(calendar) as Arc<dyn HolidayCalendar>)
+        .collect()
+}
+
+/// Install the built-in calendars plus those in `file`, which replace
+/// built-in ones of the same region. Later calls are ignored.
+pub fn init(file: Option<&str>) -> Result<(), HolidayFileError> {
+    let mut calendars = built_in_calendars();
+    if let Some(path) = file {
+        for custom in load_file(path)? {
+            calendars.retain(|calendar| calendar.region() != custom.region());
+            calendars.push(Arc::new(custom));
+        }
+    }
+    let _ = CALENDARS.set(calendars);
+    Ok(())
+}
+
+fn calendars() -> &'static [Arc<dyn HolidayCalendar>] {
+    CALENDARS.get_or_init(built_in_calendars)
+}
+
+/// The calendar for `region`, case-insensitive; "GB" finds "UK"
+pub fn calendar(region: &str) -> Option<Arc<dyn HolidayCalendar>> {
+    let region = match region.trim().to_uppercase().as_str() {
+        "GB" => "UK".to_string(),
+        other => other.to_string(),
+    };
+    calendars()
+        .iter()
+        .find(|calendar| calendar.region() == region)
+        .cloned()
+}
+
+/// Every region with a calendar
+pub fn regions() -> Vec<String> {
+    calendars()
+        .iter()
+        .map(|calendar| calendar.region().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn names_on(calendar: &dyn HolidayCalendar, day: &str) -> Vec<String> {
+        calendar
+            .holidays_on(date(day))
+            .into_iter()
+            .map(|holiday| holiday.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_us_thanksgiving_is_fourth_thursday() {
+        let us = built_in("US").unwrap();
+        for day in ["2023-11-23", "2024-11-28", "2025-11-27", "2026-11-26"] {
+            assert_eq!(names_on(&us, day), ["Thanksgiving Day"], "{}", day);
+        }
+        assert!(us.holidays_on(date("2024-11-21")).is_empty());
+        assert_eq!(names_on(&us, "2024-05-27"), ["Memorial Day"]);
+    }
+
+    #[test]
+    fn test_uk_easter_monday() {
+        let uk = built_in("UK").unwrap();
+        for day in ["2019-04-22", "2023-04-10", "2024-04-01", "2025-04-21"] {
+            assert_eq!(names_on(&uk, day), ["Easter Monday"], "{}", day);
+        }
+        assert_eq!(easter_sunday(2024), Some(date("2024-03-31")));
+        assert_eq!(easter_sunday(2038), Some(date("2038-04-25")));
+    }
+
+    #[test]
+    fn test_us_observed_independence_day() {
+        let us = built_in("US").unwrap();
+        // 2026-07-04 is a Saturday: observed on Friday the 3rd
+        let observed = us.holidays_on(date("2026-07-03"));
+        assert_eq!(observed[0].name, "Independence Day");
+        assert_eq!(observed[0].date, date("2026-07-04"));
+        assert!(!us.is_business_day(date("2026-07-03")));
+        // 2027-07-04 is a Sunday: observed on Monday the 5th
+        assert_eq!(names_on(&us, "2027-07-05"), ["Independence Day"]);
+        // 2022-01-01 was a Saturday: observed on Friday 2021-12-31
+        assert_eq!(names_on(&us, "2021-12-31"), ["New Year's Day"]);
+        assert!(us.holidays_on(date("2020-06-19")).is_empty());
+        assert_eq!(
+            us.next_business_day(date("2026-07-02")),
+            Some(date("2026-07-06"))
+        );
+    }
+
+    #[test]
+    fn test_uk_substitute_days_do_not_collide() {
+        let uk = built_in("UK").unwrap();
+        // 2022: Christmas on Sunday, Boxing Day on Monday; Christmas moves to Tuesday
+        assert_eq!(names_on(&uk, "2022-12-26"), ["Boxing Day"]);
+        assert_eq!(names_on(&uk, "2022-12-27"), ["Christmas Day"]);
+        // 2021: Christmas on Saturday, Boxing Day on Sunday
+        assert_eq!(names_on(&uk, "2021-12-27"), ["Christmas Day"]);
+        assert_eq!(names_on(&uk, "2021-12-28"), ["Boxing Day"]);
+    }
+
+    #[test]
+    fn test_custom_calendar_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("holidays.json");
+        std::fs::write(
+            &path,
+            r#"{"calendars": [{"region": "acme", "holidays": [
+                {"name": "Founders' Day", "type": "fixed", "month": 6, "day": 2},
+                {"name": "Last Friday", "type": "nth_weekday", "month": 9, "weekday": "Fri", "nth": -1},
+                {"name": "Move day", "type": "dates", "dates": ["2025-03-14"]}
+            ]}]}"#,
+        )
+        .unwrap();
+
+        let calendars = load_file(path.to_str().unwrap()).unwrap();
+        let acme = &calendars[0];
+        assert_eq!(acme.region(), "ACME");
+        assert_eq!(names_on(acme, "2025-06-02"), ["Founders' Day"]);
+        assert_eq!(names_on(acme, "2025-09-26"), ["Last Friday"]);
+        assert_eq!(names_on(acme, "2025-03-14"), ["Move day"]);
+        assert!(acme.holidays_on(date("2026-03-14")).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_calendar_file_is_an_error() {
+        let error = parse_calendars(r#"{"calendars": [{"region": "X""#, "bad.json").unwrap_err();
+        assert!(matches!(error, HolidayFileError::Parse { .. }));
+        assert!(error.to_string().contains("bad.json"));
+
+        let unknown_type = r#"{"calendars": [{"region": "X", "holidays": [
+            {"name": "Y", "type": "lunar", "month": 1}]}]}"#;
+        assert!(matches!(
+            parse_calendars(unknown_type, "f.json").unwrap_err(),
+            HolidayFileError::Parse { .. }
+        ));
+
+        let bad_date = r#"{"calendars": [{"region": "X", "holidays": [
+            {"name": "Y", "type": "fixed", "month": 2, "day": 30}]}]}"#;
+        let error = parse_calendars(bad_date, "f.json").unwrap_err();
+        assert!(matches!(error, HolidayFileError::Invalid { .. }));
+        assert!(error.to_string().contains("'Y'"));
+
+        assert!(matches!(
+            load_file("/nonexistent/holidays.json").unwrap_err(),
+            HolidayFileError::Io { .. }
+        ));
+    }
+
+    #[test]
+    fn test_region_lookup() {
+        assert_eq!(calendar("gb").unwrap().region(), "UK");
+        assert_eq!(calendar("de").unwrap().region(), "DE");
+        assert!(calendar("XX").is_none());
+    }
+}
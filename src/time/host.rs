@@ -0,0 +1,325 @@
+// The host's own timezone and locale
+//
+// The server reports UTC, but an agent correlating local log files needs the
+// zone the machine itself runs in. It is looked for the way the C library and
+// systemd find it, first match wins: the `TZ` variable, the `/etc/localtime`
+// symlink target, `/etc/timezone`, then `timedatectl show`. Every name found
+// is checked against the IANA database before it is used; a source that is
+// missing or holds something else is recorded in `checked` and the next one
+// is tried. When none gives a zone (common in scratch containers) the result
+// is `determined: false` rather than an error.
+
+use super::TimezoneConverter;
+use crate::error::TimeServerError;
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Where the host timezone was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostTimezoneSource {
+    /// The `TZ` environment variable
+    TzEnv,
+    /// The target of the `/etc/localtime` symlink
+    Localtime,
+    /// The contents of `/etc/timezone`
+    TimezoneFile,
+    /// The `Timezone=` line of `timedatectl show`
+    Timedatectl,
+}
+
+/// What one source held
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceCheck {
+    pub source: HostTimezoneSource,
+    /// "Europe/London", "not set", "file, not symlink", ...
+    pub outcome: String,
+}
+
+/// The host's zone; the fields past `determined` are null when it is not
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostTimezone {
+    /// Whether any source gave a valid IANA name
+    pub determined: bool,
+    pub timezone: Option<String>,
+    pub source: Option<HostTimezoneSource>,
+    pub offset_seconds: Option<i32>,
+    /// "+05:30"
+    pub utc_offset: Option<String>,
+    pub is_dst: Option<bool>,
+    /// `LC_ALL`, `LC_TIME` or `LANG`, whichever is set first
+    pub locale: Option<String>,
+    /// Each source looked at, in order, up to the one used
+    pub checked: Vec<SourceCheck>,
+}
+
+/// The places to look, so tests can point them at fixtures
+pub struct HostSources {
+    pub tz: Option<String>,
+    pub localtime: PathBuf,
+    pub timezone_file: PathBuf,
+    /// Output of `timedatectl show`, or `None` if it could not be run
+    pub timedatectl: fn() -> Option<String>,
+    pub locale: Option<String>,
+}
+
+impl HostSources {
+    /// The running host's environment and files
+    pub fn system() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            tz: var("TZ"),
+            localtime: PathBuf::from("/etc/localtime"),
+            timezone_file: PathBuf::from("/etc/timezone"),
+            timedatectl: run_timedatectl,
+            locale: var("LC_ALL")
+                .or_else(|| var("LC_TIME"))
+                .or_else(|| var("LANG")),
+        }
+    }
+}
+
+fn run_timedatectl() -> Option<String> {
+    let output = std::process::Command::new("timedatectl")
+        .arg("show")
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The host timezone per `sources`, with its offset at `at`
+pub fn detect(sources: &HostSources, at: DateTime<Utc>) -> Result<HostTimezone, TimeServerError> {
+    let mut checked = Vec::new();
+    let mut found = None;
+    for source in [
+        HostTimezoneSource::TzEnv,
+        HostTimezoneSource::Localtime,
+        HostTimezoneSource::TimezoneFile,
+        HostTimezoneSource::Timedatectl,
+    ] {
+        let outcome = match read_source(sources, source) {
+            Ok(name) if name.parse::<Tz>().is_ok() => {
+                found = Some((source, name.clone()));
+                name
+            }
+            Ok(name) => format!("not an IANA timezone: {}", name),
+            Err(outcome) => outcome,
+        };
+        checked.push(SourceCheck { source, outcome });
+        if found.is_some() {
+            break;
+        }
+    }
+
+    let mut host = HostTimezone {
+        determined: found.is_some(),
+        timezone: None,
+        source: None,
+        offset_seconds: None,
+        utc_offset: None,
+        is_dst: None,
+        locale: sources.locale.clone(),
+        checked,
+    };
+    if let Some((source, name)) = found {
+        let info = TimezoneConverter::get_timezone_info(&name, Some(at))?;
+        host.utc_offset = FixedOffset::east_opt(info.offset_seconds).map(|o| o.to_string());
+        host.offset_seconds = Some(info.offset_seconds);
+        host.is_dst = Some(info.is_dst);
+        host.timezone = Some(name);
+        host.source = Some(source);
+    }
+    Ok(host)
+}
+
+/// The zone name `source` holds, or why it holds none
+fn read_source(sources: &HostSources, source: HostTimezoneSource) -> Result<String, String> {
+    match source {
+        HostTimezoneSource::TzEnv => {
+            let tz = sources.tz.as_deref().ok_or("not set")?;
+            // POSIX lets TZ start with ':' and name a file
+            let tz = tz.strip_prefix(':').unwrap_or(tz);
+            Ok(zone_from_path(Path::new(tz)).unwrap_or_else(|| tz.to_string()))
+        }
+        HostTimezoneSource::Localtime => {
+            let metadata = std::fs::symlink_metadata(&sources.localtime)
+                .map_err(|_| "not present".to_string())?;
+            if !metadata.file_type().is_symlink() {
+                return Err("file, not symlink".into());
+            }
+            let target = std::fs::read_link(&sources.localtime).map_err(|e| e.to_string())?;
+            zone_from_path(&target)
+                .ok_or_else(|| format!("target outside zoneinfo: {}", target.display()))
+        }
+        HostTimezoneSource::TimezoneFile => {
+            let contents = std::fs::read_to_string(&sources.timezone_file)
+                .map_err(|_| "not present".to_string())?;
+            contents
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .ok_or_else(|| "empty".to_string())
+        }
+        HostTimezoneSource::Timedatectl => {
+            let output = (sources.timedatectl)().ok_or("not available")?;
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix("Timezone="))
+                .map(|zone| zone.trim().to_string())
+                .filter(|zone| !zone.is_empty())
+                .ok_or_else(|| "no Timezone= line".to_string())
+        }
+    }
+}
+
+/// "Europe/London" from ".../zoneinfo/Europe/London", skipping the
+/// `posix/` and `right/` variants
+fn zone_from_path(path: &Path) -> Option<String> {
+    let path = path.to_str()?;
+    let (_, zone) = path.split_once("zoneinfo/")?;
+    let zone = zone
+        .strip_prefix("posix/")
+        .or_else(|| zone.strip_prefix("right/"))
+        .unwrap_or(zone);
+    (!zone.is_empty()).then(|| zone.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn no_timedatectl() -> Option<String> {
+        None
+    }
+
+    fn timedatectl_tokyo() -> Option<String> {
+        Some("Timezone=Asia/Tokyo\nLocalRTC=no\nNTP=yes\n".into())
+    }
+
+    /// Sources pointing at files in `dir` that do not exist yet
+    fn empty_sources(dir: &TempDir) -> HostSources {
+        HostSources {
+            tz: None,
+            localtime: dir.path().join("localtime"),
+            timezone_file: dir.path().join("timezone"),
+            timedatectl: no_timedatectl,
+            locale: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn link_localtime(sources: &HostSources, target: &str) {
+        std::os::unix::fs::symlink(target, &sources.localtime).unwrap();
+    }
+
+    fn summer() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tz_env_wins() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = empty_sources(&dir);
+        sources.tz = Some(":America/New_York".into());
+        link_localtime(&sources, "/usr/share/zoneinfo/Europe/London");
+
+        let host = detect(&sources, summer()).unwrap();
+        assert_eq!(host.source, Some(HostTimezoneSource::TzEnv));
+        assert_eq!(host.timezone.as_deref(), Some("America/New_York"));
+        assert_eq!(host.offset_seconds, Some(-4 * 3600));
+        assert_eq!(host.utc_offset.as_deref(), Some("-04:00"));
+        assert_eq!(host.is_dst, Some(true));
+        assert_eq!(host.checked.len(), 1);
+
+        sources.tz = Some("/usr/share/zoneinfo/Asia/Kolkata".into());
+        let host = detect(&sources, summer()).unwrap();
+        assert_eq!(host.timezone.as_deref(), Some("Asia/Kolkata"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_localtime_symlink() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = empty_sources(&dir);
+        sources.tz = Some("Not/AZone".into());
+        link_localtime(&sources, "../usr/share/zoneinfo/posix/Europe/London");
+        std::fs::write(&sources.timezone_file, "Asia/Tokyo\n").unwrap();
+
+        let host = detect(&sources, summer()).unwrap();
+        assert_eq!(host.source, Some(HostTimezoneSource::Localtime));
+        assert_eq!(host.timezone.as_deref(), Some("Europe/London"));
+        assert_eq!(
+            host.checked[0].outcome,
+            "not an IANA timezone: Not/AZone".to_string()
+        );
+    }
+
+    #[test]
+    fn test_localtime_file_falls_through_to_etc_timezone() {
+        let dir = TempDir::new().unwrap();
+        let sources = empty_sources(&dir);
+        std::fs::write(&sources.localtime, b"TZif2").unwrap();
+        std::fs::write(
+            &sources.timezone_file,
+            "# set by installer\nAustralia/Sydney\n",
+        )
+        .unwrap();
+
+        let host = detect(&sources, summer()).unwrap();
+        assert_eq!(host.source, Some(HostTimezoneSource::TimezoneFile));
+        assert_eq!(host.timezone.as_deref(), Some("Australia/Sydney"));
+        assert_eq!(host.is_dst, Some(false));
+        assert_eq!(host.checked[0].outcome, "not set");
+        assert_eq!(host.checked[1].outcome, "file, not symlink");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timedatectl_is_last() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = empty_sources(&dir);
+        sources.timedatectl = timedatectl_tokyo;
+        link_localtime(&sources, "/opt/tz/custom");
+        std::fs::write(&sources.timezone_file, "\n").unwrap();
+
+        let host = detect(&sources, summer()).unwrap();
+        assert_eq!(host.source, Some(HostTimezoneSource::Timedatectl));
+        assert_eq!(host.timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(host.offset_seconds, Some(9 * 3600));
+        assert_eq!(
+            host.checked[1].outcome,
+            "target outside zoneinfo: /opt/tz/custom"
+        );
+        assert_eq!(host.checked[2].outcome, "empty");
+    }
+
+    #[test]
+    fn test_undetermined() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = empty_sources(&dir);
+        sources.locale = Some("C.UTF-8".into());
+
+        let host = detect(&sources, summer()).unwrap();
+        assert!(!host.determined);
+        assert_eq!(host.timezone, None);
+        assert_eq!(host.offset_seconds, None);
+        assert_eq!(host.locale.as_deref(), Some("C.UTF-8"));
+        let outcomes: Vec<_> = host.checked.iter().map(|c| c.outcome.as_str()).collect();
+        assert_eq!(
+            outcomes,
+            ["not set", "not present", "not present", "not available"]
+        );
+        let value = serde_json::to_value(&host).unwrap();
+        assert_eq!(value["determined"], false);
+        assert!(value["timezone"].is_null());
+    }
+}
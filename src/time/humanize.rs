@@ -0,0 +1,80 @@
+// Human descriptions of how far away an instant is
+//
+// `relative` names the distance in the largest whole unit that fits, the
+// way people say it: "in 3 days", "2 hours ago", "now". Months are 30 days
+// and years 365, since the phrase is a rough guide rather than calendar
+// arithmetic; the wording is what parse_natural_time reads back.
+
+use chrono::{DateTime, Utc};
+
+/// Units from largest to smallest, in seconds
+pub const UNITS: [(&str, i64); 7] = [
+    ("year", 365 * 86400),
+    ("month", 30 * 86400),
+    ("week", 7 * 86400),
+    ("day", 86400),
+    ("hour", 3600),
+    ("minute", 60),
+    ("second", 1),
+];
+
+/// `count unit(s)`, e.g. "1 day", "3 days"
+pub fn quantity(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+/// Whole seconds as "3 days", "1 hour", "0 seconds"
+pub fn span(seconds: i64) -> String {
+    let seconds = seconds.unsigned_abs().min(i64::MAX as u64) as i64;
+    UNITS
+        .iter()
+        .find(|(_, size)| seconds >= *size)
+        .map(|(unit, size)| quantity(seconds / size, unit))
+        .unwrap_or_else(|| quantity(0, "second"))
+}
+
+/// Where `at` lies from `now`: "in 3 days", "2 hours ago" or "now"
+pub fn relative(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (at - now).num_seconds();
+    match seconds {
+        0 => "now".to_string(),
+        s if s > 0 => format!("in {}", span(s)),
+        s => format!("{} ago", span(s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_relative_picks_the_largest_unit() {
+        let now = DateTime::from_timestamp(1_710_497_730, 0).unwrap();
+        for (offset, expected) in [
+            (0, "now"),
+            (1, "in 1 second"),
+            (59, "in 59 seconds"),
+            (-60, "1 minute ago"),
+            (3 * 3600 + 1800, "in 3 hours"),
+            (-86400, "1 day ago"),
+            (3 * 86400 + 7200, "in 3 days"),
+            (15 * 86400, "in 2 weeks"),
+            (-45 * 86400, "1 month ago"),
+            (800 * 86400, "in 2 years"),
+        ] {
+            assert_eq!(relative(now + Duration::seconds(offset), now), expected);
+        }
+    }
+
+    #[test]
+    fn test_sub_second_differences_are_now() {
+        let now = DateTime::from_timestamp(1_710_497_730, 0).unwrap();
+        assert_eq!(relative(now + Duration::milliseconds(400), now), "now");
+        assert_eq!(span(i64::MIN), quantity(i64::MAX / (365 * 86400), "year"));
+    }
+}
@@ -0,0 +1,131 @@
+// Leap second table
+//
+// TAI - UTC from each leap second onwards, as published by the IERS in
+// leap-seconds.list. Unix time ignores leap seconds; this table is what
+// converts to and from the scales that count them (TAI, GPS).
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+/// (year, month, TAI - UTC from the first day of that month)
+pub const LEAP_SECONDS: [(i32, u32, i64); 28] = [
+    (1972, 1, 10),
+    (1972, 7, 11),
+    (1973, 1, 12),
+    (1974, 1, 13),
+    (1975, 1, 14),
+    (1976, 1, 15),
+    (1977, 1, 16),
+    (1978, 1, 17),
+    (1979, 1, 18),
+    (1980, 1, 19),
+    (1981, 7, 20),
+    (1982, 7, 21),
+    (1983, 7, 22),
+    (1985, 7, 23),
+    (1988, 1, 24),
+    (1990, 1, 25),
+    (1991, 1, 26),
+    (1992, 7, 27),
+    (1993, 7, 28),
+    (1994, 7, 29),
+    (1996, 1, 30),
+    (1997, 7, 31),
+    (1999, 1, 32),
+    (2006, 1, 33),
+    (2009, 1, 34),
+    (2012, 7, 35),
+    (2015, 7, 36),
+    (2017, 1, 37),
+];
+
+/// Expiry date of the IERS leap-seconds.list the table was last checked
+/// against; no leap second is announced before it
+pub const LEAP_SECONDS_EXPIRE: (i32, u32, u32) = (2026, 12, 28);
+
+/// TAI - GPS, fixed when GPS time started in 1980
+pub const TAI_GPS_OFFSET_SECONDS: i64 = 19;
+
+/// Unix time at which an entry of [`LEAP_SECONDS`] takes effect
+fn effective_at(year: i32, month: u32) -> i64 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+        .unwrap_or_default()
+}
+
+/// TAI - UTC in whole seconds at `unix_seconds`; 10 before 1972, when the
+/// offset was not yet a whole number of seconds
+pub fn tai_utc_offset(unix_seconds: i64) -> i64 {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|&&(year, month, _)| unix_seconds >= effective_at(year, month))
+        .map_or(LEAP_SECONDS[0].2, |&(_, _, offset)| offset)
+}
+
+/// GPS - UTC in seconds at `unix_seconds`; 0 before GPS time began in 1980
+pub fn gps_utc_offset(unix_seconds: i64) -> i64 {
+    (tai_utc_offset(unix_seconds) - TAI_GPS_OFFSET_SECONDS).max(0)
+}
+
+/// What the table knows and how long that is guaranteed to hold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeapTableInfo {
+    pub entries: usize,
+    /// Date of the most recent leap second's new offset (YYYY-MM-DD)
+    pub last_entry: String,
+    pub tai_utc_offset: i64,
+    /// After this date a leap second may have been announced that the table lacks
+    pub expires: String,
+    pub expired: bool,
+}
+
+pub fn table_info(now: DateTime<Utc>) -> LeapTableInfo {
+    let (year, month, offset) = LEAP_SECONDS[LEAP_SECONDS.len() - 1];
+    let (expire_year, expire_month, expire_day) = LEAP_SECONDS_EXPIRE;
+    let expires =
+        NaiveDate::from_ymd_opt(expire_year, expire_month, expire_day).unwrap_or_default();
+    LeapTableInfo {
+        entries: LEAP_SECONDS.len(),
+        last_entry: format!("{:04}-{:02}-01", year, month),
+        tai_utc_offset: offset,
+        expires: expires.to_string(),
+        expired: now.date_naive() > expires,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets_change_at_leap_seconds() {
+        // 2016-12-31T23:59:59Z, then 2017-01-01T00:00:00Z
+        assert_eq!(tai_utc_offset(1_483_228_799), 36);
+        assert_eq!(tai_utc_offset(1_483_228_800), 37);
+        assert_eq!(gps_utc_offset(1_483_228_800), 18);
+        assert_eq!(effective_at(2012, 7), 1_341_100_800);
+        assert_eq!(tai_utc_offset(0), 10);
+        assert_eq!(gps_utc_offset(315_964_800), 0);
+        assert_eq!(gps_utc_offset(0), 0);
+    }
+
+    #[test]
+    fn test_table_info() {
+        let info = table_info("2025-01-01T00:00:00Z".parse().unwrap());
+        assert_eq!(info.entries, 28);
+        assert_eq!(info.last_entry, "2017-01-01");
+        assert_eq!(info.tai_utc_offset, 37);
+        assert!(!info.expired);
+        assert!(table_info("2099-01-01T00:00:00Z".parse().unwrap()).expired);
+    }
+
+    #[test]
+    fn test_table_is_ordered_one_second_apart() {
+        for pair in LEAP_SECONDS.windows(2) {
+            assert!(effective_at(pair[0].0, pair[0].1) < effective_at(pair[1].0, pair[1].1));
+            assert_eq!(pair[1].2 - pair[0].2, 1);
+        }
+    }
+}
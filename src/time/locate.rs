@@ -0,0 +1,255 @@
+// Timezone for a latitude/longitude, without a network lookup
+//
+// With the `geo` feature the point is tested against the coarse outlines in
+// `boundaries`. When it lies within `BOUNDARY_EPSILON_DEG` of another zone's
+// border, that zone is listed as a candidate, since the outlines are only
+// good to tens of kilometres. A point no outline covers (open sea, or land
+// the dataset leaves out) gets the nautical zone for its longitude,
+// Etc/GMT±N, flagged `approximate`. Without the feature, `locate` still
+// validates its input and then fails with `NotCompiledIn`.
+
+use super::TimezoneConverter;
+use crate::error::TimeServerError;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+
+/// Distance to a border, in degrees of latitude, inside which the zone on
+/// the other side is listed as a candidate (about 11 km)
+pub const BOUNDARY_EPSILON_DEG: f64 = 0.1;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocatedTimezone {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// IANA name
+    pub timezone: String,
+    pub offset_seconds: i32,
+    /// "+05:30"
+    pub utc_offset: String,
+    pub abbreviation: String,
+    pub is_dst: bool,
+    /// No outline covers the point; `timezone` is the nautical zone for its longitude
+    pub approximate: bool,
+    /// Other zones whose border is within `BOUNDARY_EPSILON_DEG`
+    pub candidates: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// The timezone at `latitude`, `longitude` with its offset at `at`
+pub fn locate(
+    latitude: f64,
+    longitude: f64,
+    at: DateTime<Utc>,
+) -> Result<LocatedTimezone, TimeServerError> {
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(TimeServerError::InvalidArgument(format!(
+            "latitude {} is outside -90 to 90",
+            latitude
+        )));
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(TimeServerError::InvalidArgument(format!(
+            "longitude {} is outside -180 to 180",
+            longitude
+        )));
+    }
+    let (timezone, candidates) = lookup(latitude, longitude)?;
+    let approximate = timezone.is_none();
+    let timezone = timezone.unwrap_or_else(|| nautical_zone(longitude));
+    let info = TimezoneConverter::get_timezone_info(&timezone, Some(at))?;
+    let note = if approximate {
+        Some("No timezone outline covers this point; the zone follows from the longitude".into())
+    } else if !candidates.is_empty() {
+        Some("Close to a timezone border; the outlines are coarse, so check the candidates".into())
+    } else {
+        None
+    };
+    Ok(LocatedTimezone {
+        latitude,
+        longitude,
+        timezone,
+        offset_seconds: info.offset_seconds,
+        utc_offset: FixedOffset::east_opt(info.offset_seconds)
+            .map(|offset| offset.to_string())
+            .unwrap_or_default(),
+        abbreviation: info.abbreviation,
+        is_dst: info.is_dst,
+        approximate,
+        candidates,
+        note,
+    })
+}
+
+/// The zone whose outline holds the point, and the other zones near it
+#[cfg(feature = "geo")]
+fn lookup(latitude: f64, longitude: f64) -> Result<(Option<String>, Vec<String>), TimeServerError> {
+    let point = (longitude, latitude);
+    let mut found = None;
+    let mut candidates = Vec::new();
+    for (zone, outlines) in super::boundaries::ZONES {
+        if found.is_none() && outlines.iter().any(|ring| contains(ring, point)) {
+            found = Some(zone.to_string());
+        } else if outlines
+            .iter()
+            .any(|ring| border_distance(ring, point) <= BOUNDARY_EPSILON_DEG)
+        {
+            candidates.push(zone.to_string());
+        }
+    }
+    Ok((found, candidates))
+}
+
+#[cfg(not(feature = "geo"))]
+fn lookup(
+    _latitude: f64,
+    _longitude: f64,
+) -> Result<(Option<String>, Vec<String>), TimeServerError> {
+    Err(TimeServerError::NotCompiledIn {
+        feature: "geo".into(),
+    })
+}
+
+/// Etc/GMT±N for the 15-degree band around `longitude`; the sign is POSIX's,
+/// so east of Greenwich is Etc/GMT-N
+fn nautical_zone(longitude: f64) -> String {
+    let hours = (longitude / 15.0).round() as i32;
+    match hours {
+        0 => "Etc/GMT".to_string(),
+        h if h > 0 => format!("Etc/GMT-{}", h),
+        h => format!("Etc/GMT+{}", -h),
+    }
+}
+
+/// Even-odd test of `point` against the closed `ring`
+#[cfg(feature = "geo")]
+fn contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut previous = ring[ring.len() - 1];
+    for &(x1, y1) in ring {
+        let (x0, y0) = previous;
+        if (y1 > y) != (y0 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+            inside = !inside;
+        }
+        previous = (x1, y1);
+    }
+    inside
+}
+
+/// Distance from `point` to the nearest edge of `ring`, in degrees of
+/// latitude; longitude is scaled by cos(latitude) so east-west and
+/// north-south distances compare
+#[cfg(feature = "geo")]
+fn border_distance(ring: &[(f64, f64)], (x, y): (f64, f64)) -> f64 {
+    let scale = y.to_radians().cos();
+    let mut nearest = f64::INFINITY;
+    let mut previous = ring[ring.len() - 1];
+    for &(x1, y1) in ring {
+        let (x0, y0) = previous;
+        let (ax, ay) = ((x0 - x) * scale, y0 - y);
+        let (bx, by) = ((x1 - x) * scale, y1 - y);
+        let (dx, dy) = (bx - ax, by - ay);
+        let length = dx * dx + dy * dy;
+        let t = if length == 0.0 {
+            0.0
+        } else {
+            (-(ax * dx + ay * dy) / length).clamp(0.0, 1.0)
+        };
+        nearest = nearest.min((ax + t * dx).hypot(ay + t * dy));
+        previous = (x1, y1);
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn winter() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_705_320_000, 0).unwrap() // 2024-01-15 12:00 UTC
+    }
+
+    #[test]
+    fn test_coordinates_are_validated() {
+        for (lat, lon) in [(90.5, 0.0), (-91.0, 0.0), (0.0, 180.1), (f64::NAN, 0.0)] {
+            let error = locate(lat, lon, winter()).unwrap_err();
+            assert_eq!(error.code(), "invalid_argument", "{} {}", lat, lon);
+        }
+    }
+
+    #[test]
+    fn test_nautical_zone() {
+        assert_eq!(nautical_zone(-40.0), "Etc/GMT+3");
+        assert_eq!(nautical_zone(7.4), "Etc/GMT");
+        assert_eq!(nautical_zone(172.0), "Etc/GMT-11");
+        assert_eq!(nautical_zone(180.0), "Etc/GMT-12");
+    }
+
+    #[cfg(not(feature = "geo"))]
+    #[test]
+    fn test_without_geo_feature() {
+        let error = locate(51.5, -0.13, winter()).unwrap_err();
+        assert_eq!(error.code(), "not_compiled_in");
+        assert!(error.to_string().contains("--features geo"));
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_cities() {
+        for (lat, lon, zone) in [
+            (40.71, -74.01, "America/New_York"),
+            (39.74, -104.99, "America/Denver"),
+            (34.05, -118.24, "America/Los_Angeles"),
+            (51.51, -0.13, "Europe/London"),
+            (48.86, 2.35, "Europe/Paris"),
+            (52.52, 13.40, "Europe/Berlin"),
+            (55.75, 37.62, "Europe/Moscow"),
+            (28.61, 77.21, "Asia/Kolkata"),
+            (31.23, 121.47, "Asia/Shanghai"),
+            (35.69, 139.69, "Asia/Tokyo"),
+            (-33.87, 151.21, "Australia/Sydney"),
+            (-23.55, -46.63, "America/Sao_Paulo"),
+            (-26.20, 28.05, "Africa/Johannesburg"),
+        ] {
+            let located = locate(lat, lon, winter()).unwrap();
+            assert_eq!(located.timezone, zone, "{} {}", lat, lon);
+            assert!(!located.approximate);
+            assert!(
+                located.candidates.is_empty(),
+                "{}: {:?}",
+                zone,
+                located.candidates
+            );
+            assert!(located.note.is_none());
+        }
+
+        let sydney = locate(-33.87, 151.21, winter()).unwrap();
+        assert_eq!(sydney.utc_offset, "+11:00");
+        assert!(sydney.is_dst);
+        assert_eq!(sydney.abbreviation, "AEDT");
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_ocean_point() {
+        let located = locate(30.0, -40.0, winter()).unwrap();
+        assert_eq!(located.timezone, "Etc/GMT+3");
+        assert_eq!(located.utc_offset, "-03:00");
+        assert!(located.approximate);
+        assert!(!located.is_dst);
+        assert!(located.note.is_some());
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_boundary_lists_candidates() {
+        // On the Rhine between Strasbourg and Kehl
+        let located = locate(48.5, 7.98, winter()).unwrap();
+        let mut zones = located.candidates.clone();
+        zones.push(located.timezone.clone());
+        zones.sort();
+        assert_eq!(zones, ["Europe/Berlin", "Europe/Paris"]);
+        assert!(!located.approximate);
+        assert!(located.note.unwrap().contains("border"));
+    }
+}
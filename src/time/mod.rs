@@ -1,9 +1,47 @@
+pub mod abbreviations;
+#[cfg(feature = "geo")]
+pub mod boundaries;
+pub mod calendar;
+pub mod calendars;
+pub mod card;
+pub mod compare;
+pub mod correction;
+pub mod cron;
+pub mod duration;
+pub mod epochs;
+pub mod explain;
+pub mod format_cache;
 pub mod formats;
+pub mod holidays;
+pub mod host;
+pub mod humanize;
+pub mod leap;
+pub mod locate;
+pub mod natural;
+pub mod reference;
+pub mod resolution;
+pub mod rfc9557;
+pub mod source;
+pub mod step;
 pub mod timezone;
+pub mod tzdata;
 pub mod unix;
 pub mod utc;
+pub mod world_clock;
 
 // Re-export commonly used types
-pub use formats::{StandardFormats, StrftimeFormatter};
-pub use timezone::{TimezoneConverter, TimezoneInfo};
+pub use epochs::{Epoch, EpochConversion};
+pub use format_cache::FormatCache;
+pub use formats::{
+    FormatExplanation, FormatTemplates, NamedFormat, NumberSystem, StandardFormats,
+    StrftimeFormatter,
+};
+pub use rfc9557::{format_rfc9557, parse_rfc9557, ConflictPolicy, ParsedTime};
+pub use source::{SourcedTime, TimeQuality, TimeSource, TimeSourceManager};
+pub use timezone::{
+    AmbiguityPolicy, ConversionOrigin, DatedOffsets, DifferenceSegment, GapPolicy, LocalResolution,
+    LocalTimeStatus, OffsetDifference, OffsetSegment, OffsetTransition, TimezoneConverter,
+    TimezoneInfo, TimezoneListQuery, TimezonePage, TransitionKind,
+};
 pub use unix::UnixTime;
+pub use world_clock::{WorldClock, WorldClockRow};
@@ -0,0 +1,707 @@
+// Natural-language times: "tomorrow at 3pm", "next friday noon", "in 45 minutes"
+//
+// A small hand-written grammar, resolved against a reference instant and an
+// IANA timezone. The input is split into words; each word or phrase fills one
+// slot (a relative offset, a day, a time of day) and records what it was read
+// as in the trace. Anything outside the grammar is rejected rather than
+// guessed, as are phrases with two readings ("next friday" on a Friday, "at 3"
+// without am/pm). The local date and time are resolved like local_to_utc:
+// the earlier instant when the time occurs twice, shifted forward when a DST
+// gap skips it, with a trace step saying so.
+
+use super::timezone::{AmbiguityPolicy, GapPolicy, LocalTimeStatus, TimezoneConverter};
+use crate::error::TimeServerError;
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone,
+    Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+/// Words that only join the others ("at 3pm", "on friday")
+const FILLER_WORDS: &[&str] = &["at", "on", "and"];
+
+/// What one word or phrase of the input was read as
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// The words, as given (lowercased); empty for defaults the grammar filled in
+    pub text: String,
+    /// now, relative, day, time, filler, default or dst
+    pub kind: &'static str,
+    pub meaning: String,
+}
+
+/// A natural-language time resolved to an instant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NaturalTime {
+    pub input: String,
+    pub timezone: String,
+    /// The instant the input is relative to, in `timezone`
+    pub reference: String,
+    pub unix_timestamp: i64,
+    /// The result in `timezone`
+    pub rfc3339: String,
+    pub utc: String,
+    /// How the local date and time mapped to an instant; null when the input
+    /// was only an offset from the reference
+    pub local_status: Option<LocalTimeStatus>,
+    pub trace: Vec<TraceStep>,
+}
+
+/// The slots a parsed input fills
+#[derive(Debug, Default)]
+struct Slots {
+    /// Seconds, minutes and hours: added to the instant
+    seconds: Option<i64>,
+    /// Days and weeks: added to the local date, so the wall-clock time stays
+    days: Option<i64>,
+    day: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+}
+
+/// Resolve `input` relative to `reference` in `timezone`
+pub fn parse_natural_time(
+    input: &str,
+    reference: DateTime<Utc>,
+    timezone: &str,
+) -> Result<NaturalTime, TimeServerError> {
+    let tz: Tz = timezone
+        .parse()
+        .map_err(|_| TimeServerError::invalid_timezone(timezone))?;
+    let local_reference = reference.with_timezone(&tz);
+    let today = local_reference.date_naive();
+
+    let words: Vec<String> = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return Err(invalid("the input is empty"));
+    }
+
+    let mut slots = Slots::default();
+    let mut trace = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let next = words.get(i + 1).map(String::as_str);
+        let step = |len: usize, kind, meaning: String| TraceStep {
+            text: words[i..i + len].join(" "),
+            kind,
+            meaning,
+        };
+
+        if word == "now" {
+            trace.push(step(1, "now", "the reference instant".to_string()));
+            i += 1;
+        } else if FILLER_WORDS.contains(&word) && !(word == "at" && next.is_some_and(is_number)) {
+            trace.push(step(1, "filler", "ignored".to_string()));
+            i += 1;
+        } else if word == "in" {
+            let (span, len) = parse_span(&words[i + 1..]).ok_or_else(|| {
+                invalid("'in' must be followed by an amount and a unit, as in 'in 2 hours'")
+            })?;
+            let meaning = span.fill(&mut slots, 1, &words[i..=i + len].join(" "))?;
+            trace.push(step(1 + len, "relative", meaning));
+            i += 1 + len;
+        } else if let Some((span, len)) = parse_span(&words[i..]) {
+            let sign = match (
+                words.get(i + len).map(String::as_str),
+                words.get(i + len + 1).map(String::as_str),
+            ) {
+                (Some("ago"), _) => (-1, 1),
+                (Some("from"), Some("now")) => (1, 2),
+                _ => {
+                    let text = words[i..i + len].join(" ");
+                    return Err(invalid(&format!(
+                        "'{}' needs a direction: 'in {}' or '{} ago'",
+                        text, text, text
+                    )));
+                }
+            };
+            let text = words[i..i + len + sign.1].join(" ");
+            let meaning = span.fill(&mut slots, sign.0, &text)?;
+            trace.push(step(len + sign.1, "relative", meaning));
+            i += len + sign.1;
+        } else if let Some((day, len, meaning)) = parse_day(&words[i..], today)? {
+            if let Some(existing) = slots.day {
+                return Err(invalid(&format!(
+                    "two days given: {} and '{}'",
+                    existing,
+                    words[i..i + len].join(" ")
+                )));
+            }
+            slots.day = Some(day);
+            trace.push(step(len, "day", meaning));
+            i += len;
+        } else if let Some((time, len, meaning)) = parse_clock(&words[i..])? {
+            if slots.time.is_some() {
+                return Err(invalid(&format!(
+                    "two times of day given; '{}' is the second",
+                    words[i..i + len].join(" ")
+                )));
+            }
+            slots.time = Some(time);
+            trace.push(step(len, "time", meaning));
+            i += len;
+        } else {
+            return Err(invalid(&format!(
+                "'{}' is not understood; the grammar knows now, today, tomorrow, yesterday, weekday names with next/last/this, times such as 3pm, 15:30, noon and midnight, 'in N units' and 'N units ago'",
+                word
+            )));
+        }
+    }
+
+    if slots.seconds.is_some() && (slots.time.is_some() || slots.day.is_some()) {
+        return Err(invalid(
+            "an offset in seconds, minutes or hours fixes the time already; drop the day or time of day, or offset in days",
+        ));
+    }
+    if slots.days.is_some() && slots.day.is_some() {
+        return Err(invalid(
+            "an offset in days or weeks and a day both pick the date; give one of them",
+        ));
+    }
+
+    let (instant, local_status) =
+        if slots.day.is_none() && slots.days.is_none() && slots.time.is_none() {
+            (
+                reference + Duration::seconds(slots.seconds.unwrap_or(0)),
+                None,
+            )
+        } else {
+            let date = match (slots.day, slots.days) {
+                (Some(day), _) => day,
+                (None, days) => {
+                    let date = today + Duration::days(days.unwrap_or(0));
+                    if slots.days.is_none() {
+                        trace.push(TraceStep {
+                            text: String::new(),
+                            kind: "default",
+                            meaning: format!("no day given: the reference day {}", date),
+                        });
+                    }
+                    date
+                }
+            };
+            let time = match slots.time {
+                Some(time) => time,
+                None if slots.day.is_some() => {
+                    trace.push(TraceStep {
+                        text: String::new(),
+                        kind: "default",
+                        meaning: "no time given: 00:00:00, the start of the day".to_string(),
+                    });
+                    NaiveTime::MIN
+                }
+                None => {
+                    let time = local_reference.time();
+                    trace.push(TraceStep {
+                        text: String::new(),
+                        kind: "default",
+                        meaning: format!(
+                            "no time given: the reference time {}",
+                            time.format("%H:%M:%S")
+                        ),
+                    });
+                    time
+                }
+            };
+            let (instant, status) = resolve_local(tz, timezone, date.and_time(time), &mut trace)?;
+            (
+                instant + Duration::seconds(slots.seconds.unwrap_or(0)),
+                Some(status),
+            )
+        };
+
+    let local = instant.with_timezone(&tz);
+    Ok(NaturalTime {
+        input: input.to_string(),
+        timezone: tz.name().to_string(),
+        reference: local_reference.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        unix_timestamp: instant.timestamp(),
+        rfc3339: local.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        utc: instant.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        local_status,
+        trace,
+    })
+}
+
+fn invalid(message: &str) -> TimeServerError {
+    TimeServerError::InvalidArgument(format!("Cannot parse time: {}", message))
+}
+
+fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// A sum of amounts in units, as in "2 hours 30 minutes"
+#[derive(Debug, Default)]
+struct Span {
+    seconds: i64,
+    days: i64,
+    /// Whether any unit was seconds, minutes or hours
+    has_clock_units: bool,
+    has_calendar_units: bool,
+}
+
+impl Span {
+    /// Add the span, times `sign`, to the slots; the trace meaning
+    fn fill(&self, slots: &mut Slots, sign: i64, text: &str) -> Result<String, TimeServerError> {
+        if slots.seconds.is_some() || slots.days.is_some() {
+            return Err(invalid(&format!(
+                "'{}' is a second offset; combine them, as in 'in 1 hour 30 minutes'",
+                text
+            )));
+        }
+        let mut meaning = Vec::new();
+        if self.has_calendar_units {
+            slots.days = Some(sign * self.days);
+            meaning.push(format!("{:+} calendar days", sign * self.days));
+        }
+        if self.has_clock_units {
+            slots.seconds = Some(sign * self.seconds);
+            meaning.push(format!("{:+} s", sign * self.seconds));
+        }
+        Ok(format!("{} from the reference", meaning.join(" and ")))
+    }
+}
+
+/// "2 hours", "an hour and 30 minutes": the span and the words it used
+fn parse_span(words: &[String]) -> Option<(Span, usize)> {
+    let mut span = Span::default();
+    let mut used = 0;
+    loop {
+        let at = match words.get(used).map(String::as_str) {
+            Some("and") if used > 0 => used + 1,
+            _ => used,
+        };
+        let amount = match words.get(at).map(String::as_str) {
+            Some("a" | "an") => 1,
+            Some(word) => match word.parse::<i64>() {
+                Ok(amount) if amount <= 1_000_000 => amount,
+                _ => break,
+            },
+            None => break,
+        };
+        let unit = match words.get(at + 1).map(String::as_str) {
+            Some("second" | "seconds" | "sec" | "secs") => 1,
+            Some("minute" | "minutes" | "min" | "mins") => 60,
+            Some("hour" | "hours" | "hr" | "hrs") => 3600,
+            Some("day" | "days") => -1,
+            Some("week" | "weeks") => -7,
+            _ => break,
+        };
+        if unit > 0 {
+            span.seconds += amount * unit;
+            span.has_clock_units = true;
+        } else {
+            span.days += amount * -unit;
+            span.has_calendar_units = true;
+        }
+        used = at + 2;
+    }
+    (used > 0).then_some((span, used))
+}
+
+fn weekday_named(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" | "thur" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// A day reference: the date, the words used and the trace meaning
+fn parse_day(
+    words: &[String],
+    today: NaiveDate,
+) -> Result<Option<(NaiveDate, usize, String)>, TimeServerError> {
+    let relative = |days: i64, what: &str| {
+        let date = today + Duration::days(days);
+        Some((date, 1, format!("{} ({})", date, what)))
+    };
+    let first = words[0].as_str();
+    match first {
+        "today" => return Ok(relative(0, "the reference day")),
+        "tomorrow" => return Ok(relative(1, "the day after the reference day")),
+        "yesterday" => return Ok(relative(-1, "the day before the reference day")),
+        _ => {}
+    }
+
+    let (modifier, name) = match first {
+        "next" | "last" | "this" => match words.get(1) {
+            Some(name) => (Some(first), name.as_str()),
+            None => {
+                return Err(invalid(&format!(
+                    "'{}' must be followed by a weekday",
+                    first
+                )))
+            }
+        },
+        _ => (None, first),
+    };
+    let Some(weekday) = weekday_named(name) else {
+        return match modifier {
+            Some(modifier) => Err(invalid(&format!(
+                "'{} {}' is not understood; use a weekday name, as in '{} friday'",
+                modifier, name, modifier
+            ))),
+            None => Ok(None),
+        };
+    };
+
+    let len = 1 + usize::from(modifier.is_some());
+    let text = words[..len].join(" ");
+    let ahead = i64::from(
+        (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7,
+    );
+    let is_today = ahead == 0;
+    let name = weekday_name(weekday);
+    let (days, what) = match modifier {
+        Some("next") if is_today => {
+            return Err(invalid(&format!(
+                "'{}' is ambiguous when the reference day is a {}: it could mean {} or {}; specify 'in 7 days' or the date",
+                text,
+                name,
+                today + Duration::days(7),
+                today + Duration::days(14)
+            )))
+        }
+        Some("last") if is_today => {
+            return Err(invalid(&format!(
+                "'{}' is ambiguous when the reference day is a {}: it could mean {} or {}; specify '7 days ago' or the date",
+                text,
+                name,
+                today - Duration::days(7),
+                today - Duration::days(14)
+            )))
+        }
+        Some("next") => (ahead, format!("the first {} after the reference day", name)),
+        Some("last") => (ahead - 7, format!("the last {} before the reference day", name)),
+        _ if is_today => (0, format!("the reference day, a {}", name)),
+        _ => (ahead, format!("the coming {}", name)),
+    };
+    let date = today + Duration::days(days);
+    Ok(Some((date, len, format!("{} ({})", date, what))))
+}
+
+/// A time of day: "3pm", "3 pm", "3:30pm", "15:30", "15:30:45", "noon",
+/// "midnight", or a bare hour after "at" when it cannot be a 12-hour one
+fn parse_clock(words: &[String]) -> Result<Option<(NaiveTime, usize, String)>, TimeServerError> {
+    let first = words[0].as_str();
+    match first {
+        "noon" | "midday" => {
+            return Ok(Some((
+                NaiveTime::from_hms_opt(12, 0, 0).unwrap_or(NaiveTime::MIN),
+                1,
+                "12:00:00".to_string(),
+            )))
+        }
+        "midnight" => {
+            return Ok(Some((
+                NaiveTime::MIN,
+                1,
+                "00:00:00, the start of the day".to_string(),
+            )))
+        }
+        _ => {}
+    }
+
+    // A bare hour is only a time after "at"
+    let (words, at) = match first {
+        "at" => (&words[1..], 1),
+        _ => (words, 0),
+    };
+    let Some(word) = words.first().map(String::as_str) else {
+        return Ok(None);
+    };
+    let (clock, meridiem, len) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else {
+        match words.get(1).map(String::as_str) {
+            Some("am") => (word, Some(false), 2),
+            Some("pm") => (word, Some(true), 2),
+            _ => (word, None, 1),
+        }
+    };
+
+    let fields: Vec<&str> = clock.split(':').collect();
+    if fields.is_empty()
+        || fields.len() > 3
+        || fields
+            .iter()
+            .any(|f| f.is_empty() || f.len() > 2 || !f.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Ok(None);
+    }
+    let number = |i: usize| fields.get(i).map_or(Ok(0), |f| f.parse::<u32>());
+    let (Ok(hour), Ok(minute), Ok(second)) = (number(0), number(1), number(2)) else {
+        return Ok(None);
+    };
+    let text = words[..len].join(" ");
+
+    let hour = match meridiem {
+        Some(pm) => {
+            if !(1..=12).contains(&hour) {
+                return Err(invalid(&format!("'{}': a 12-hour time needs an hour from 1 to 12", text)));
+            }
+            hour % 12 + if pm { 12 } else { 0 }
+        }
+        None if fields.len() > 1 => hour,
+        None if at == 0 => {
+            return Err(invalid(&format!(
+                "'{}' is a bare number; write a time such as '{}pm' or '{}:00', or an offset such as 'in {} hours'",
+                text, text, text, text
+            )))
+        }
+        None if (1..=12).contains(&hour) => {
+            return Err(invalid(&format!(
+                "'at {}' is ambiguous: it could be {:02}:00 or {:02}:00; add am or pm, or use 24-hour time",
+                text,
+                hour % 12,
+                hour % 12 + 12
+            )))
+        }
+        None => hour,
+    };
+    let time = NaiveTime::from_hms_opt(hour, minute, second)
+        .ok_or_else(|| invalid(&format!("'{}' is not a valid time of day", text)))?;
+    Ok(Some((time, len + at, time.format("%H:%M:%S").to_string())))
+}
+
+/// `local` in the zone: the earlier instant when it occurs twice, shifted
+/// forward when a gap skips it
+fn resolve_local(
+    tz: Tz,
+    timezone: &str,
+    local: NaiveDateTime,
+    trace: &mut Vec<TraceStep>,
+) -> Result<(DateTime<Utc>, LocalTimeStatus), TimeServerError> {
+    let text = local.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let resolution = TimezoneConverter::local_to_utc(
+        &text,
+        tz.name(),
+        AmbiguityPolicy::Earlier,
+        GapPolicy::ShiftForward,
+    )?;
+    let instant = Utc
+        .timestamp_opt(resolution.unix_timestamp, 0)
+        .single()
+        .ok_or_else(|| TimeServerError::out_of_range(resolution.unix_timestamp.to_string()))?;
+    let meaning = match resolution.status {
+        LocalTimeStatus::Unique => None,
+        LocalTimeStatus::Ambiguous => Some(format!(
+            "{} occurs twice in {} (clocks go back); took the earlier, at UTC offset {} s",
+            text, timezone, resolution.offset_seconds
+        )),
+        LocalTimeStatus::Nonexistent => Some(format!(
+            "{} does not exist in {} (clocks skip forward); moved forward to {}",
+            text,
+            timezone,
+            instant.with_timezone(&tz).format("%Y-%m-%dT%H:%M:%S")
+        )),
+    };
+    if let Some(meaning) = meaning {
+        trace.push(TraceStep {
+            text: String::new(),
+            kind: "dst",
+            meaning,
+        });
+    }
+    Ok((instant, resolution.status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Friday 2024-03-15 10:15:30 UTC
+    fn reference() -> DateTime<Utc> {
+        "2024-03-15T10:15:30Z".parse().unwrap()
+    }
+
+    fn resolve(input: &str, timezone: &str) -> Result<NaturalTime, TimeServerError> {
+        parse_natural_time(input, reference(), timezone)
+    }
+
+    #[test]
+    fn test_resolves_in_utc() {
+        let cases = [
+            ("now", "2024-03-15T10:15:30Z"),
+            ("in 45 minutes", "2024-03-15T11:00:30Z"),
+            ("in 2 hours", "2024-03-15T12:15:30Z"),
+            ("in an hour and 30 minutes", "2024-03-15T11:45:30Z"),
+            ("in 90 seconds", "2024-03-15T10:17:00Z"),
+            ("45 minutes ago", "2024-03-15T09:30:30Z"),
+            ("3 hours from now", "2024-03-15T13:15:30Z"),
+            ("in 2 days", "2024-03-17T10:15:30Z"),
+            ("2 weeks ago", "2024-03-01T10:15:30Z"),
+            ("in 1 day 2 hours", "2024-03-16T12:15:30Z"),
+            ("today", "2024-03-15T00:00:00Z"),
+            ("tomorrow", "2024-03-16T00:00:00Z"),
+            ("yesterday", "2024-03-14T00:00:00Z"),
+            ("tomorrow at 3pm", "2024-03-16T15:00:00Z"),
+            ("3pm tomorrow", "2024-03-16T15:00:00Z"),
+            ("tomorrow at 3 pm", "2024-03-16T15:00:00Z"),
+            ("yesterday 9:45am", "2024-03-14T09:45:00Z"),
+            ("today at 15:30", "2024-03-15T15:30:00Z"),
+            ("today 15:30:45", "2024-03-15T15:30:45Z"),
+            ("3pm", "2024-03-15T15:00:00Z"),
+            ("noon", "2024-03-15T12:00:00Z"),
+            ("midnight", "2024-03-15T00:00:00Z"),
+            ("tomorrow midnight", "2024-03-16T00:00:00Z"),
+            ("12am", "2024-03-15T00:00:00Z"),
+            ("12pm", "2024-03-15T12:00:00Z"),
+            ("12:30am", "2024-03-15T00:30:00Z"),
+            ("at 15", "2024-03-15T15:00:00Z"),
+            ("at 0", "2024-03-15T00:00:00Z"),
+            ("next saturday noon", "2024-03-16T12:00:00Z"),
+            ("next monday", "2024-03-18T00:00:00Z"),
+            ("next Thursday at 9am", "2024-03-21T09:00:00Z"),
+            ("last monday", "2024-03-11T00:00:00Z"),
+            ("last saturday 20:00", "2024-03-09T20:00:00Z"),
+            ("friday", "2024-03-15T00:00:00Z"),
+            ("this friday at 5pm", "2024-03-15T17:00:00Z"),
+            ("on wednesday at noon", "2024-03-20T12:00:00Z"),
+            ("sunday", "2024-03-17T00:00:00Z"),
+            ("thu 8am", "2024-03-21T08:00:00Z"),
+            ("in 3 days at noon", "2024-03-18T12:00:00Z"),
+            ("2 days ago at 6pm", "2024-03-13T18:00:00Z"),
+            ("Tomorrow, 10:00", "2024-03-16T10:00:00Z"),
+        ];
+        for (input, expected) in cases {
+            let resolved = resolve(input, "UTC").unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(resolved.utc, expected, "{}", input);
+            assert_eq!(
+                resolved.unix_timestamp,
+                expected.parse::<DateTime<Utc>>().unwrap().timestamp()
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolves_in_a_timezone() {
+        // 06:15:30 on Friday in New York, daylight time since 2024-03-10
+        let cases = [
+            ("now", "2024-03-15T06:15:30-04:00"),
+            ("tomorrow at 3pm", "2024-03-16T15:00:00-04:00"),
+            ("in 2 days", "2024-03-17T06:15:30-04:00"),
+            // A calendar day before the change keeps the wall-clock time
+            ("6 days ago", "2024-03-09T06:15:30-05:00"),
+            ("last saturday noon", "2024-03-09T12:00:00-05:00"),
+            ("in 6 hours", "2024-03-15T12:15:30-04:00"),
+        ];
+        for (input, expected) in cases {
+            let resolved = resolve(input, "America/New_York").unwrap();
+            assert_eq!(resolved.rfc3339, expected, "{}", input);
+        }
+
+        // Evening in Tokyo, where "tomorrow" is Saturday
+        let resolved = resolve("tomorrow", "Asia/Tokyo").unwrap();
+        assert_eq!(resolved.rfc3339, "2024-03-16T00:00:00+09:00");
+        assert_eq!(resolved.reference, "2024-03-15T19:15:30+09:00");
+    }
+
+    #[test]
+    fn test_dst_gap_and_overlap() {
+        // 02:30 on 2024-03-10 is skipped in New York
+        let resolved = resolve("last sunday at 2:30am", "America/New_York").unwrap();
+        assert_eq!(resolved.rfc3339, "2024-03-10T03:30:00-04:00");
+        assert_eq!(resolved.local_status, Some(LocalTimeStatus::Nonexistent));
+        let dst = resolved.trace.last().unwrap();
+        assert_eq!(dst.kind, "dst");
+        assert!(dst.meaning.contains("does not exist"), "{}", dst.meaning);
+
+        // 01:30 on 2024-11-03 happens twice
+        let friday = "2024-11-01T12:00:00Z".parse().unwrap();
+        let resolved = parse_natural_time("sunday 1:30am", friday, "America/New_York").unwrap();
+        assert_eq!(resolved.rfc3339, "2024-11-03T01:30:00-04:00");
+        assert_eq!(resolved.local_status, Some(LocalTimeStatus::Ambiguous));
+        assert!(resolved.trace.last().unwrap().meaning.contains("earlier"));
+    }
+
+    #[test]
+    fn test_trace_names_each_part() {
+        let resolved = resolve("next tuesday at noon", "UTC").unwrap();
+        let trace: Vec<(&str, &str)> = resolved
+            .trace
+            .iter()
+            .map(|s| (s.text.as_str(), s.kind))
+            .collect();
+        assert_eq!(
+            trace,
+            [("next tuesday", "day"), ("at", "filler"), ("noon", "time")]
+        );
+        assert_eq!(
+            resolved.trace[0].meaning,
+            "2024-03-19 (the first Tuesday after the reference day)"
+        );
+
+        let resolved = resolve("3pm", "UTC").unwrap();
+        assert_eq!(resolved.trace[1].kind, "default");
+        assert_eq!(
+            resolved.trace[1].meaning,
+            "no day given: the reference day 2024-03-15"
+        );
+
+        let resolved = resolve("in 45 minutes", "UTC").unwrap();
+        assert_eq!(resolved.trace[0].meaning, "+2700 s from the reference");
+        assert_eq!(resolved.local_status, None);
+    }
+
+    #[test]
+    fn test_rejects_ambiguous_and_unknown_input() {
+        let cases = [
+            (
+                "next friday",
+                "is ambiguous when the reference day is a Friday",
+            ),
+            ("last friday", "2024-03-08 or 2024-03-01"),
+            ("at 3", "could be 03:00 or 15:00"),
+            ("3", "bare number"),
+            ("2 hours", "needs a direction"),
+            ("in", "must be followed by an amount"),
+            ("next", "must be followed by a weekday"),
+            ("next week", "use a weekday name"),
+            ("tomorrow friday", "two days given"),
+            ("3pm noon", "two times of day"),
+            ("in 2 hours at 3pm", "fixes the time already"),
+            ("in 2 days tomorrow", "both pick the date"),
+            ("in 2 hours in 3 hours", "second offset"),
+            ("13pm", "hour from 1 to 12"),
+            ("25:00", "not a valid time"),
+            ("demain", "'demain' is not understood"),
+            ("", "empty"),
+        ];
+        for (input, message) in cases {
+            let error = resolve(input, "UTC").unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", input, error);
+        }
+
+        assert_eq!(
+            resolve("now", "Mars/Base").unwrap_err().code(),
+            "invalid_timezone"
+        );
+    }
+}
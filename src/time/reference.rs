@@ -0,0 +1,143 @@
+// Reference instants for now-based tools
+//
+// `as_of` pins the instant a call computes from, so several calls in one
+// workflow agree on "now". A pinned instant reaches the tools as a reading
+// from `TimeSource::Client` in place of the configured sources, so every
+// derived field (offsets, validity, relative text) follows from it. Responses
+// echo the instant as `reference_time` with `reference_source` "client", or
+// "server" when the clock was read. AS_OF_MAX_SKEW_SECS bounds how far a
+// pinned instant may be from the clock; unset, any instant in the supported
+// range is accepted.
+
+use super::card;
+use super::TimeSource;
+use crate::error::TimeServerError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Where a call's reference instant came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceSource {
+    /// Pinned with `as_of`
+    Client,
+    /// Read from the server's time sources
+    Server,
+}
+
+impl From<TimeSource> for ReferenceSource {
+    fn from(source: TimeSource) -> Self {
+        match source {
+            TimeSource::Client => Self::Client,
+            _ => Self::Server,
+        }
+    }
+}
+
+/// The instant a call computed from, echoed in its response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReferenceTime {
+    /// RFC 3339, UTC
+    pub reference_time: String,
+    pub reference_source: ReferenceSource,
+}
+
+/// `body` with the reference it was computed from
+#[derive(Debug, Serialize)]
+pub struct WithReference<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(flatten)]
+    pub reference: ReferenceTime,
+}
+
+impl ReferenceTime {
+    pub fn new(at: DateTime<Utc>, source: ReferenceSource) -> Self {
+        Self {
+            reference_time: at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            reference_source: source,
+        }
+    }
+
+    pub fn attach<T>(self, body: T) -> WithReference<T> {
+        WithReference {
+            body,
+            reference: self,
+        }
+    }
+}
+
+/// The instant `as_of` names (Unix seconds or RFC 3339), refused when it is
+/// more than `max_skew_secs` from the clock reading `now`
+pub fn pinned(
+    as_of: &str,
+    now: DateTime<Utc>,
+    max_skew_secs: Option<u64>,
+) -> Result<DateTime<Utc>, TimeServerError> {
+    let at = card::parse_timestamp(as_of).map_err(|e| TimeServerError::for_value("as_of", e))?;
+    if let Some(max) = max_skew_secs {
+        let skew = (at - now).num_seconds().unsigned_abs();
+        if skew > max {
+            return Err(TimeServerError::InvalidValue {
+                field: "as_of".to_string(),
+                reason: format!(
+                    "{} is {}s from the server clock; AS_OF_MAX_SKEW_SECS allows {}s",
+                    as_of.trim(),
+                    skew,
+                    max
+                ),
+                cause: "as_of_skew",
+            });
+        }
+    }
+    Ok(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_accepts_both_forms() {
+        let now = Utc::now();
+        let unix = pinned("1710497730", now, None).unwrap();
+        let rfc3339 = pinned("2024-03-15T19:15:30+09:00", now, None).unwrap();
+        assert_eq!(unix, rfc3339);
+
+        let error = pinned("yesterday", now, None).unwrap_err();
+        assert_eq!(error.code(), "invalid_value");
+        assert!(error.to_string().contains("as_of"), "{}", error);
+    }
+
+    #[test]
+    fn test_pinned_applies_max_skew() {
+        let now: DateTime<Utc> = "2024-03-15T10:15:30Z".parse().unwrap();
+        assert!(pinned("2024-03-15T10:20:30Z", now, Some(300)).is_ok());
+        assert!(pinned("2024-03-15T10:10:30Z", now, Some(300)).is_ok());
+        match pinned("2024-03-15T10:20:31Z", now, Some(300)) {
+            Err(TimeServerError::InvalidValue { field, cause, .. }) => {
+                assert_eq!(field, "as_of");
+                assert_eq!(cause, "as_of_skew");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(pinned("1970-01-01T00:00:00Z", now, None).is_ok());
+    }
+
+    #[test]
+    fn test_reference_echo() {
+        let at: DateTime<Utc> = "2024-03-15T10:15:30.5Z".parse().unwrap();
+        let echoed = serde_json::to_value(
+            ReferenceTime::new(at, ReferenceSource::Client).attach(serde_json::json!({"a": 1})),
+        )
+        .unwrap();
+        assert_eq!(
+            echoed,
+            serde_json::json!({
+                "a": 1,
+                "reference_time": "2024-03-15T10:15:30.500Z",
+                "reference_source": "client",
+            })
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// Clock resolution: what precision the server can actually deliver
+//
+// The nanosecond fields in time responses are nominal. `clock_getres` says
+// what the kernel claims for each clock; the measurement reads the clock
+// back to back and reports the smallest step it ever saw, how the steps are
+// distributed and what one read costs. The loop is bounded and CPU-bound,
+// so async callers run it on the blocking pool.
+
+use super::UnixTime;
+use serde::Serialize;
+use std::time::Instant;
+
+/// Back-to-back reads per measurement
+pub const DEFAULT_SAMPLES: usize = 10_000;
+
+/// Upper bound for caller-chosen sample counts
+pub const MAX_SAMPLES: usize = 100_000;
+
+/// Differences between consecutive `UnixTime::now()` reads
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeltaMeasurement {
+    pub samples: usize,
+    /// Smallest nonzero difference; None when the clock never moved
+    pub min_delta_ns: Option<u64>,
+    /// Reads that returned the same time as the one before
+    pub zero_deltas: usize,
+    pub p50_delta_ns: u64,
+    pub p90_delta_ns: u64,
+    pub p99_delta_ns: u64,
+    pub max_delta_ns: u64,
+    /// Average cost of one `now()` call
+    pub now_cost_ns: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClockResolution {
+    /// `clock_getres(CLOCK_REALTIME)`
+    pub realtime_res_ns: Option<u64>,
+    /// `clock_getres(CLOCK_MONOTONIC)`
+    pub monotonic_res_ns: Option<u64>,
+    /// /proc/self/timerslack_ns (Linux)
+    pub timer_slack_ns: Option<u64>,
+    pub measured: DeltaMeasurement,
+}
+
+/// Read the clock `samples` times back to back (at least 2, at most
+/// [`MAX_SAMPLES`]) and summarize the differences. Blocks for the duration.
+pub fn measure_deltas(samples: usize) -> DeltaMeasurement {
+    let samples = samples.clamp(2, MAX_SAMPLES);
+    let mut reads = Vec::with_capacity(samples);
+    let started = Instant::now();
+    for _ in 0..samples {
+        reads.push(UnixTime::now().nanos_since_epoch);
+    }
+    let elapsed = started.elapsed();
+
+    // A wall-clock step backwards shows up as a negative difference; count
+    // it as no movement rather than a huge step
+    let mut deltas: Vec<u64> = reads
+        .windows(2)
+        .map(|pair| u64::try_from(pair[1] - pair[0]).unwrap_or(0))
+        .collect();
+    deltas.sort_unstable();
+    let percentile = |p: usize| deltas[(deltas.len() * p / 100).min(deltas.len() - 1)];
+
+    DeltaMeasurement {
+        samples,
+        min_delta_ns: deltas.iter().copied().find(|&delta| delta > 0),
+        zero_deltas: deltas.iter().take_while(|&&delta| delta == 0).count(),
+        p50_delta_ns: percentile(50),
+        p90_delta_ns: percentile(90),
+        p99_delta_ns: percentile(99),
+        max_delta_ns: deltas[deltas.len() - 1],
+        now_cost_ns: elapsed.as_nanos() as f64 / samples as f64,
+    }
+}
+
+/// Kernel-reported resolutions, timer slack and a fresh measurement
+pub fn clock_resolution(samples: usize) -> ClockResolution {
+    let (realtime_res_ns, monotonic_res_ns) = kernel_resolutions();
+    ClockResolution {
+        realtime_res_ns,
+        monotonic_res_ns,
+        timer_slack_ns: timer_slack_ns(),
+        measured: measure_deltas(samples),
+    }
+}
+
+/// REALTIME and MONOTONIC resolutions; clock_getres is POSIX only
+#[cfg(unix)]
+fn kernel_resolutions() -> (Option<u64>, Option<u64>) {
+    (
+        clock_getres(libc::CLOCK_REALTIME),
+        clock_getres(libc::CLOCK_MONOTONIC),
+    )
+}
+
+#[cfg(not(unix))]
+fn kernel_resolutions() -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
+#[cfg(unix)]
+fn clock_getres(clock: libc::clockid_t) -> Option<u64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_getres only writes to the timespec we pass
+    let rc = unsafe { libc::clock_getres(clock, &mut ts) };
+    (rc == 0).then(|| ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn timer_slack_ns() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/timerslack_ns")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn timer_slack_ns() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolutions_are_positive() {
+        let resolution = clock_resolution(1_000);
+        if cfg!(unix) {
+            assert!(resolution.realtime_res_ns.unwrap() > 0);
+            assert!(resolution.monotonic_res_ns.unwrap() > 0);
+        }
+        if cfg!(target_os = "linux") {
+            assert!(resolution.timer_slack_ns.is_some());
+        }
+    }
+
+    #[test]
+    fn test_measured_deltas_are_below_a_millisecond() {
+        let measured = measure_deltas(DEFAULT_SAMPLES);
+        assert_eq!(measured.samples, DEFAULT_SAMPLES);
+        let min = measured.min_delta_ns.unwrap();
+        assert!(min > 0 && min < 1_000_000, "{}", min);
+        assert!(measured.p50_delta_ns <= measured.p90_delta_ns);
+        assert!(measured.p99_delta_ns <= measured.max_delta_ns);
+        assert!(measured.now_cost_ns > 0.0);
+
+        assert_eq!(measure_deltas(0).samples, 2);
+        assert_eq!(measure_deltas(usize::MAX).samples, MAX_SAMPLES);
+    }
+}
@@ -0,0 +1,378 @@
+// RFC 9557 (Internet Extended Date/Time Format) rendering and parsing
+//
+// `2024-03-15T10:00:00+09:00[Asia/Tokyo][u-ca=iso8601]`: an RFC 3339
+// timestamp followed by a time zone annotation and optional key=value tags.
+// UTC is rendered as `+00:00[UTC]`; `Z` in RFC 9557 means the local offset is
+// unknown, which is not the case for a time we produce ourselves.
+
+use super::TimezoneConverter;
+use crate::error::TimeServerError;
+use chrono::{DateTime, FixedOffset, Offset, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Calendar tag appended when the calendar annotation is requested
+pub const ISO_CALENDAR_ANNOTATION: &str = "[u-ca=iso8601]";
+
+/// Render `dt` with its numeric offset and a bracketed zone annotation
+pub fn format_rfc9557(dt: &DateTime<Tz>, calendar_annotation: bool) -> String {
+    format_with_zone(
+        &dt.fixed_offset(),
+        Some(dt.timezone().name()),
+        calendar_annotation,
+    )
+}
+
+/// Render an instant with `offset` and an optional zone name
+pub fn format_with_zone(
+    dt: &DateTime<FixedOffset>,
+    zone: Option<&str>,
+    calendar_annotation: bool,
+) -> String {
+    let mut out = dt.to_rfc3339_opts(SecondsFormat::AutoSi, false);
+    if let Some(zone) = zone {
+        out.push('[');
+        out.push_str(zone);
+        out.push(']');
+    }
+    if calendar_annotation {
+        out.push_str(ISO_CALENDAR_ANNOTATION);
+    }
+    out
+}
+
+/// What to do when the offset and the zone annotation disagree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the instant given by the offset and ignore the zone. Not allowed
+    /// when the annotation is critical (`[!Zone]`), which is then rejected
+    #[default]
+    Offset,
+    /// Keep the local wall-clock time and re-resolve it in the zone
+    Zone,
+    /// Fail on any inconsistency
+    Reject,
+}
+
+/// A parsed RFC 3339 / RFC 9557 timestamp
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParsedTime {
+    pub input: String,
+    /// The instant in UTC
+    pub utc: String,
+    pub unix_timestamp: i64,
+    pub nanos: u32,
+    /// Offset of the returned local time
+    pub offset_seconds: i32,
+    /// Zone annotation, if any (an IANA name or a numeric offset)
+    pub timezone: Option<String>,
+    /// Whether the zone annotation carried the critical flag
+    pub critical: bool,
+    pub calendar: Option<String>,
+    /// False when the offset and the zone disagreed
+    pub consistent: bool,
+    /// "offset" or "zone" when an inconsistency was resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<&'static str>,
+    pub rfc3339: String,
+    pub rfc9557: String,
+    /// Elective annotations that were not understood
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_annotations: Vec<String>,
+}
+
+/// Zone named by an annotation
+enum Zone {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    fn offset_at(&self, utc: &DateTime<Utc>) -> FixedOffset {
+        match self {
+            Zone::Named(tz) => tz.offset_from_utc_datetime(&utc.naive_utc()).fix(),
+            Zone::Fixed(offset) => *offset,
+        }
+    }
+}
+
+/// Parse an RFC 3339 timestamp with optional RFC 9557 suffix annotations
+pub fn parse_rfc9557(input: &str, policy: ConflictPolicy) -> Result<ParsedTime, TimeServerError> {
+    let input = input.trim();
+    let invalid = |why: &str| TimeServerError::InvalidTimestamp(format!("'{}': {}", input, why));
+
+    let (datetime, suffix) = input.split_at(input.find('[').unwrap_or(input.len()));
+    let parsed = DateTime::parse_from_rfc3339(datetime)
+        .map_err(|e| invalid(&format!("not an RFC 3339 date-time ({})", e)))?;
+    // "Z" and "-00:00" give the UTC instant without a known local offset
+    let offset_unknown = datetime.ends_with(['Z', 'z']) || datetime.ends_with("-00:00");
+
+    let mut zone = None;
+    let mut zone_label = None;
+    let mut critical = false;
+    let mut calendar = None;
+    let mut ignored = Vec::new();
+
+    for (index, annotation) in annotations(suffix).map_err(&invalid)?.enumerate() {
+        let (is_critical, body) = match annotation.strip_prefix('!') {
+            Some(body) => (true, body),
+            None => (false, annotation),
+        };
+        match body.split_once('=') {
+            None if index == 0 => {
+                let (label, parsed_zone) = parse_zone(body)?;
+                zone = Some(parsed_zone);
+                zone_label = Some(label);
+                critical = is_critical;
+            }
+            None => return Err(invalid("the time zone annotation must come first")),
+            Some(("u-ca", value)) => {
+                if value != "iso8601" && is_critical {
+                    return Err(invalid(&format!("unsupported calendar '{}'", value)));
+                }
+                if value == "iso8601" {
+                    calendar = Some(value.to_string());
+                } else {
+                    ignored.push(annotation.to_string());
+                }
+            }
+            Some((key, _)) => {
+                if is_critical {
+                    return Err(invalid(&format!(
+                        "unsupported critical annotation '{}'",
+                        key
+                    )));
+                }
+                ignored.push(annotation.to_string());
+            }
+        }
+    }
+
+    let utc = parsed.with_timezone(&Utc);
+    let (local, consistent, resolved_by) = match &zone {
+        None => (parsed, true, None),
+        Some(zone) => {
+            let zone_offset = zone.offset_at(&utc);
+            if offset_unknown || zone_offset == *parsed.offset() {
+                (utc.with_timezone(&zone_offset), true, None)
+            } else {
+                let conflict = || {
+                    TimeServerError::InvalidArgument(format!(
+                        "Offset {} in '{}' does not match {} ({}) at that instant",
+                        parsed.offset(),
+                        input,
+                        zone_label.as_deref().unwrap_or_default(),
+                        zone_offset
+                    ))
+                };
+                match policy {
+                    ConflictPolicy::Reject => return Err(conflict()),
+                    ConflictPolicy::Offset if critical => return Err(conflict()),
+                    ConflictPolicy::Offset => (parsed, false, Some("offset")),
+                    ConflictPolicy::Zone => (resolve_in_zone(&parsed, zone)?, false, Some("zone")),
+                }
+            }
+        }
+    };
+
+    // An ignored zone is not repeated in the normalized output
+    let output_zone = match resolved_by {
+        Some("offset") => None,
+        _ => zone_label.clone(),
+    };
+    let utc = local.with_timezone(&Utc);
+    Ok(ParsedTime {
+        input: input.to_string(),
+        utc: utc.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        unix_timestamp: utc.timestamp(),
+        nanos: utc.timestamp_subsec_nanos(),
+        offset_seconds: local.offset().local_minus_utc(),
+        timezone: zone_label,
+        critical,
+        rfc3339: local.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        rfc9557: format_with_zone(&local, output_zone.as_deref(), calendar.is_some()),
+        calendar,
+        consistent,
+        resolved_by,
+        ignored_annotations: ignored,
+    })
+}
+
+/// Split `[a][b]...` into its bracket contents
+fn annotations(suffix: &str) -> Result<impl Iterator<Item = &str>, &'static str> {
+    let mut parts = Vec::new();
+    let mut rest = suffix;
+    while !rest.is_empty() {
+        let inner = rest
+            .strip_prefix('[')
+            .ok_or("unexpected text after the date-time")?;
+        let end = inner.find(']').ok_or("unterminated annotation")?;
+        if end == 0 {
+            return Err("empty annotation");
+        }
+        parts.push(&inner[..end]);
+        rest = &inner[end + 1..];
+    }
+    Ok(parts.into_iter())
+}
+
+/// An IANA name or a numeric offset like `+05:45`
+fn parse_zone(body: &str) -> Result<(String, Zone), TimeServerError> {
+    if body.starts_with(['+', '-']) {
+        let offset = DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", body))
+            .map(|dt| *dt.offset())
+            .map_err(|_| TimeServerError::invalid_timezone(body))?;
+        return Ok((body.to_string(), Zone::Fixed(offset)));
+    }
+    let name = TimezoneConverter::resolve_name(body)
+        .ok_or_else(|| TimeServerError::invalid_timezone(body))?;
+    let tz: Tz = name
+        .parse()
+        .map_err(|_| TimeServerError::invalid_timezone(body))?;
+    Ok((name, Zone::Named(tz)))
+}
+
+/// Keep the wall-clock time of `parsed` and find its offset in `zone`
+fn resolve_in_zone(
+    parsed: &DateTime<FixedOffset>,
+    zone: &Zone,
+) -> Result<DateTime<FixedOffset>, TimeServerError> {
+    let naive = parsed.naive_local();
+    let unresolved = || {
+        TimeServerError::InvalidArgument(format!("{} does not exist in the annotated zone", naive))
+    };
+    match zone {
+        Zone::Fixed(offset) => offset
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or_else(unresolved),
+        // Ambiguous times take the earlier instant; times in a gap move
+        // forward by the length of the gap, as `local_to_utc` does
+        Zone::Named(tz) => {
+            let resolution = TimezoneConverter::local_to_utc(
+                &naive.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+                tz.name(),
+                super::AmbiguityPolicy::Earlier,
+                super::GapPolicy::ShiftForward,
+            )?;
+            DateTime::from_timestamp(
+                resolution.unix_timestamp,
+                naive.and_utc().timestamp_subsec_nanos(),
+            )
+            .map(|utc| utc.with_timezone(tz).fixed_offset())
+            .ok_or_else(unresolved)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zoned(utc: &str, zone: &str) -> DateTime<Tz> {
+        let tz: Tz = zone.parse().unwrap();
+        utc.parse::<DateTime<Utc>>().unwrap().with_timezone(&tz)
+    }
+
+    #[test]
+    fn test_render_utc_uses_numeric_offset() {
+        let dt = zoned("2024-03-15T10:00:00Z", "UTC");
+        assert_eq!(format_rfc9557(&dt, false), "2024-03-15T10:00:00+00:00[UTC]");
+        assert_eq!(
+            format_rfc9557(&dt, true),
+            "2024-03-15T10:00:00+00:00[UTC][u-ca=iso8601]"
+        );
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_render_non_integer_hour_offset() {
+        let dt = zoned("2024-03-15T10:00:00.5Z", "Asia/Kathmandu");
+        assert_eq!(
+            format_rfc9557(&dt, false),
+            "2024-03-15T15:45:00.500+05:45[Asia/Kathmandu]"
+        );
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_round_trip() {
+        for input in [
+            "2024-03-15T10:00:00+09:00[Asia/Tokyo]",
+            "2024-03-15T15:45:00+05:45[Asia/Kathmandu][u-ca=iso8601]",
+            "2024-07-01T12:00:00+01:00[Europe/London]",
+            "2024-03-15T10:00:00+00:00[UTC]",
+            "2024-03-15T10:00:00-03:30[-03:30]",
+        ] {
+            let parsed = parse_rfc9557(input, ConflictPolicy::Reject).unwrap();
+            assert!(parsed.consistent);
+            assert_eq!(parsed.rfc9557, input);
+        }
+
+        // Z takes the local offset from the zone
+        let parsed =
+            parse_rfc9557("2024-03-15T01:00:00Z[Asia/Tokyo]", ConflictPolicy::Reject).unwrap();
+        assert_eq!(parsed.rfc9557, "2024-03-15T10:00:00+09:00[Asia/Tokyo]");
+        assert_eq!(parsed.offset_seconds, 9 * 3600);
+    }
+
+    #[test]
+    fn test_conflict_policies() {
+        // London is on GMT in January, so +01:00 is inconsistent
+        let input = "2024-01-15T12:00:00+01:00[Europe/London]";
+
+        let offset = parse_rfc9557(input, ConflictPolicy::Offset).unwrap();
+        assert!(!offset.consistent);
+        assert_eq!(offset.resolved_by, Some("offset"));
+        assert_eq!(offset.utc, "2024-01-15T11:00:00Z");
+        assert_eq!(offset.rfc9557, "2024-01-15T12:00:00+01:00");
+
+        let zone = parse_rfc9557(input, ConflictPolicy::Zone).unwrap();
+        assert_eq!(zone.resolved_by, Some("zone"));
+        assert_eq!(zone.utc, "2024-01-15T12:00:00Z");
+        assert_eq!(zone.rfc9557, "2024-01-15T12:00:00+00:00[Europe/London]");
+
+        let rejected = parse_rfc9557(input, ConflictPolicy::Reject).unwrap_err();
+        assert_eq!(rejected.code(), "invalid_argument");
+
+        // A critical annotation cannot be ignored
+        let critical = "2024-01-15T12:00:00+01:00[!Europe/London]";
+        assert!(parse_rfc9557(critical, ConflictPolicy::Offset).is_err());
+        let zone = parse_rfc9557(critical, ConflictPolicy::Zone).unwrap();
+        assert!(zone.critical);
+        assert_eq!(zone.offset_seconds, 0);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let parsed = parse_rfc9557(
+            "2024-03-15T10:00:00+09:00[Asia/Tokyo][x-foo=bar]",
+            ConflictPolicy::Reject,
+        )
+        .unwrap();
+        assert_eq!(parsed.ignored_annotations, ["x-foo=bar"]);
+
+        for bad in [
+            "2024-03-15T10:00:00+09:00[Asia/Tokyo][!x-foo=bar]",
+            "2024-03-15T10:00:00+09:00[!u-ca=hebrew]",
+            "2024-03-15T10:00:00+09:00[u-ca=iso8601][Asia/Tokyo]",
+            "2024-03-15T10:00:00+09:00[Asia/Tokyo",
+            "2024-03-15T10:00:00[Asia/Tokyo]",
+        ] {
+            assert!(
+                parse_rfc9557(bad, ConflictPolicy::Offset).is_err(),
+                "{}",
+                bad
+            );
+        }
+
+        let unknown = parse_rfc9557(
+            "2024-03-15T10:00:00+09:00[Asia/Tokio]",
+            ConflictPolicy::Offset,
+        )
+        .unwrap_err();
+        assert_eq!(unknown.code(), "invalid_timezone");
+    }
+}
@@ -0,0 +1,592 @@
+// Time sources in priority order, with the chosen source reported
+//
+// Each source is a `TimeProvider` that either returns the current time or
+// says why it cannot (not attached, sample too old, no offset measured).
+// `TimeSourceManager` asks them in the configured order
+// (`TIME_SOURCE_PRIORITY`, default `shm,system`) and returns the first
+// answer together with the source and its quality. A source that keeps
+// failing is logged once per `FAILURE_LOG_INTERVAL`, not on every request,
+// and once more when it recovers. Builds without the `ntp` feature have only
+// the system clock.
+
+use super::UnixTime;
+use crate::error::TimeServerError;
+#[cfg(feature = "ntp")]
+use crate::ntp::correction::{self, OffsetCache};
+#[cfg(feature = "ntp")]
+use crate::ntp::gps::{self, GpsMonitor};
+#[cfg(feature = "ntp")]
+use crate::ntp::NtpSyncedClock;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Priority used when `TIME_SOURCE_PRIORITY` is not set
+#[cfg(feature = "ntp")]
+pub const DEFAULT_TIME_SOURCE_PRIORITY: &[TimeSource] = &[TimeSource::Shm, TimeSource::System];
+#[cfg(not(feature = "ntp"))]
+pub const DEFAULT_TIME_SOURCE_PRIORITY: &[TimeSource] = &[TimeSource::System];
+
+/// Refclock samples (SHM, GPS) older than this are not used
+pub const MAX_SAMPLE_AGE: Duration = Duration::from_secs(60);
+
+/// A failing source is logged at most this often
+pub const FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeSource {
+    /// Refclock sample in NTP shared memory, projected to now
+    Shm,
+    /// System clock plus the measured NTP offset
+    Ntp,
+    /// Last GPS fix, projected to now
+    Gps,
+    /// System clock while ptp4l reports a locked slave port (phc2sys disciplines it)
+    Ptp,
+    /// CLOCK_REALTIME as is
+    System,
+    /// Instant the caller pinned with `as_of`; never configured or read
+    Client,
+}
+
+impl TimeSource {
+    pub const ALL: [TimeSource; 5] = [Self::Shm, Self::Ntp, Self::Gps, Self::Ptp, Self::System];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Shm => "shm",
+            Self::Ntp => "ntp",
+            Self::Gps => "gps",
+            Self::Ptp => "ptp",
+            Self::System => "system",
+            Self::Client => "client",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|source| source.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Parse a comma-separated priority list; duplicates are dropped
+    pub fn parse_priority(list: &str) -> Result<Vec<Self>, String> {
+        let mut priority = Vec::new();
+        for name in list.split(',').filter(|name| !name.trim().is_empty()) {
+            let source = Self::parse(name).ok_or_else(|| name.trim().to_string())?;
+            if !priority.contains(&source) {
+                priority.push(source);
+            }
+        }
+        if priority.is_empty() {
+            return Err(list.to_string());
+        }
+        Ok(priority)
+    }
+}
+
+impl std::fmt::Display for TimeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How much to trust a reading
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TimeQuality {
+    /// Age of the sample or offset the time is derived from; 0 for a live clock read
+    pub age_s: f64,
+    /// Precision the source reports for itself, in seconds
+    pub precision_s: Option<f64>,
+}
+
+impl TimeQuality {
+    pub const LIVE: TimeQuality = TimeQuality {
+        age_s: 0.0,
+        precision_s: None,
+    };
+}
+
+/// The current time and where it came from
+#[derive(Debug, Clone)]
+pub struct SourcedTime {
+    pub time: UnixTime,
+    pub source: TimeSource,
+    pub quality: TimeQuality,
+}
+
+impl SourcedTime {
+    pub fn to_datetime(&self) -> DateTime<Utc> {
+        self.time.to_datetime()
+    }
+}
+
+/// One source of the current time
+pub trait TimeProvider: Send + Sync {
+    fn source(&self) -> TimeSource;
+
+    /// The current time, or why this source cannot give it right now
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError>;
+}
+
+#[cfg(any(feature = "ntp", test))]
+fn unavailable(reason: impl Into<String>) -> TimeServerError {
+    TimeServerError::NtpUnavailable {
+        reason: reason.into(),
+    }
+}
+
+/// A sample taken at `received` (system time), projected to `now`
+#[cfg(feature = "ntp")]
+fn project(
+    reference: DateTime<Utc>,
+    received: DateTime<Utc>,
+    now: DateTime<Utc>,
+    precision_s: Option<f64>,
+) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+    let age = now - received;
+    let age_s = age.num_nanoseconds().unwrap_or(i64::MAX) as f64 / 1e9;
+    if age_s < 0.0 || age_s > MAX_SAMPLE_AGE.as_secs_f64() {
+        return Err(unavailable(format!(
+            "sample is stale ({:.0}s old, limit {}s)",
+            age_s,
+            MAX_SAMPLE_AGE.as_secs()
+        )));
+    }
+    Ok((
+        UnixTime::from_datetime(reference + age),
+        TimeQuality { age_s, precision_s },
+    ))
+}
+
+/// CLOCK_REALTIME; never fails
+pub struct SystemClock;
+
+impl TimeProvider for SystemClock {
+    fn source(&self) -> TimeSource {
+        TimeSource::System
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        Ok((UnixTime::from_datetime(Utc::now()), TimeQuality::LIVE))
+    }
+}
+
+/// The attached SHM unit's refclock sample
+#[cfg(feature = "ntp")]
+impl TimeProvider for NtpSyncedClock {
+    fn source(&self) -> TimeSource {
+        TimeSource::Shm
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        let shm = self
+            .shm()
+            .ok_or_else(|| unavailable("no SHM unit attached"))?;
+        let sample = shm
+            .read_sample()
+            .ok_or_else(|| unavailable(format!("SHM unit {} has no valid sample", shm.unit())))?;
+        let (Some(reference), Some(received)) = (
+            DateTime::from_timestamp(sample.clock_sec, sample.clock_nsec),
+            DateTime::from_timestamp(sample.receive_sec, sample.receive_nsec),
+        ) else {
+            return Err(unavailable("SHM sample out of range"));
+        };
+        project(
+            reference,
+            received,
+            Utc::now(),
+            Some(2f64.powi(sample.precision)),
+        )
+    }
+}
+
+/// System clock shifted by the cached NTP offset, while it is fresh
+#[cfg(feature = "ntp")]
+pub struct NtpOffset {
+    cache: &'static OffsetCache,
+}
+
+#[cfg(feature = "ntp")]
+impl NtpOffset {
+    pub fn new(cache: &'static OffsetCache) -> Self {
+        Self { cache }
+    }
+}
+
+#[cfg(feature = "ntp")]
+impl TimeProvider for NtpOffset {
+    fn source(&self) -> TimeSource {
+        TimeSource::Ntp
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        let (time, applied) = self.cache.correct(Utc::now(), Instant::now());
+        if !applied.corrected {
+            return Err(unavailable(applied.correction_note.unwrap_or_default()));
+        }
+        Ok((
+            UnixTime::from_datetime(time),
+            TimeQuality {
+                age_s: applied.offset_age_s.unwrap_or_default(),
+                precision_s: None,
+            },
+        ))
+    }
+}
+
+/// The GPS reader's last trusted time
+#[cfg(feature = "ntp")]
+pub struct GpsClock {
+    monitor: Option<&'static GpsMonitor>,
+}
+
+#[cfg(feature = "ntp")]
+impl GpsClock {
+    pub fn new(monitor: Option<&'static GpsMonitor>) -> Self {
+        Self { monitor }
+    }
+}
+
+#[cfg(feature = "ntp")]
+impl TimeProvider for GpsClock {
+    fn source(&self) -> TimeSource {
+        TimeSource::Gps
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        let monitor = self
+            .monitor
+            .ok_or_else(|| unavailable("GPS is not enabled"))?;
+        let sample = monitor
+            .last_sample()
+            .ok_or_else(|| unavailable("no GPS time received yet"))?;
+        project(sample.time, sample.received, Utc::now(), None)
+    }
+}
+
+/// CLOCK_REALTIME while the latest PTP status says it is locked
+#[cfg(feature = "ntp")]
+pub struct PtpClock;
+
+#[cfg(feature = "ntp")]
+impl TimeProvider for PtpClock {
+    fn source(&self) -> TimeSource {
+        TimeSource::Ptp
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        let (age, status) = crate::ptp::latest(Instant::now()).ok_or_else(|| {
+            unavailable("no recent PTP status (ENABLE_PTP=yes starts the monitor)")
+        })?;
+        if !status.synced {
+            return Err(unavailable(format!(
+                "PTP not locked (port state {})",
+                status.port_state.as_deref().unwrap_or("unknown")
+            )));
+        }
+        let (time, _) = SystemClock.read()?;
+        Ok((
+            time,
+            TimeQuality {
+                age_s: age.as_secs_f64(),
+                precision_s: status
+                    .master_offset_ns
+                    .map(|offset| offset.unsigned_abs() as f64 / 1e9),
+            },
+        ))
+    }
+}
+
+/// When each failing source was last logged
+#[derive(Debug, Default)]
+struct FailureLog {
+    last_logged: HashMap<TimeSource, Instant>,
+}
+
+impl FailureLog {
+    /// Whether this failure should be logged
+    fn failed(&mut self, source: TimeSource, now: Instant) -> bool {
+        match self.last_logged.get(&source) {
+            Some(&at) if now.saturating_duration_since(at) < FAILURE_LOG_INTERVAL => false,
+            _ => {
+                self.last_logged.insert(source, now);
+                true
+            }
+        }
+    }
+
+    /// Whether the source had been failing, i.e. its recovery should be logged
+    fn recovered(&mut self, source: TimeSource) -> bool {
+        self.last_logged.remove(&source).is_some()
+    }
+}
+
+/// Asks each source in priority order and reports which one answered
+pub struct TimeSourceManager {
+    providers: Vec<Box<dyn TimeProvider>>,
+    failures: Mutex<FailureLog>,
+}
+
+impl TimeSourceManager {
+    pub fn new(providers: Vec<Box<dyn TimeProvider>>) -> Self {
+        Self {
+            providers,
+            failures: Mutex::default(),
+        }
+    }
+
+    /// The built-in provider for each source in `priority`
+    #[cfg(feature = "ntp")]
+    pub fn from_priority(priority: &[TimeSource], clock: Arc<NtpSyncedClock>) -> Self {
+        let providers = priority
+            .iter()
+            .map(|source| -> Box<dyn TimeProvider> {
+                match source {
+                    TimeSource::Shm => Box::new(clock.clone()),
+                    TimeSource::Ntp => Box::new(NtpOffset::new(correction::global())),
+                    TimeSource::Gps => Box::new(GpsClock::new(gps::global())),
+                    TimeSource::Ptp => Box::new(PtpClock),
+                    // Client never parses from a priority list
+                    TimeSource::System | TimeSource::Client => Box::new(SystemClock),
+                }
+            })
+            .collect();
+        Self::new(providers)
+    }
+
+    /// The system clock, the only source in a build without the `ntp` feature
+    #[cfg(not(feature = "ntp"))]
+    pub fn from_priority(_priority: &[TimeSource]) -> Self {
+        Self::new(vec![Box::new(SystemClock)])
+    }
+
+    pub fn priority(&self) -> Vec<TimeSource> {
+        self.providers.iter().map(|p| p.source()).collect()
+    }
+
+    /// The first source in priority order that can give the time, with
+    /// any chaos anomaly applied
+    pub fn now(&self) -> Result<SourcedTime, TimeServerError> {
+        let mut sourced = self.now_at(Instant::now())?;
+        sourced.time = UnixTime::from_datetime(crate::chaos::distort(sourced.time.to_datetime()));
+        Ok(sourced)
+    }
+
+    fn now_at(&self, at: Instant) -> Result<SourcedTime, TimeServerError> {
+        let mut reasons = Vec::new();
+        for provider in &self.providers {
+            let source = provider.source();
+            match provider.read() {
+                Ok((time, quality)) => {
+                    if self
+                        .failures
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .recovered(source)
+                    {
+                        info!(event = "time_source.recovered", source = %source);
+                    }
+                    return Ok(SourcedTime {
+                        time,
+                        source,
+                        quality,
+                    });
+                }
+                Err(e) => {
+                    if self
+                        .failures
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .failed(source, at)
+                    {
+                        warn!(event = "time_source.unavailable", source = %source, error = %e);
+                    }
+                    reasons.push(format!("{}: {}", source, e));
+                }
+            }
+        }
+        Err(TimeServerError::ClockError(format!(
+            "no time source available ({})",
+            reasons.join("; ")
+        )))
+    }
+}
+
+/// Lets the shared `Arc<NtpSyncedClock>` sit in the provider list
+impl<P: TimeProvider + ?Sized> TimeProvider for Arc<P> {
+    fn source(&self) -> TimeSource {
+        (**self).source()
+    }
+
+    fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+        (**self).read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers with a fixed time, or fails once `stale` is set
+    struct Mock {
+        source: TimeSource,
+        seconds: i64,
+        stale: Arc<Mutex<bool>>,
+    }
+
+    impl Mock {
+        fn boxed(source: TimeSource, seconds: i64) -> (Box<dyn TimeProvider>, Arc<Mutex<bool>>) {
+            let stale = Arc::new(Mutex::new(false));
+            let mock = Mock {
+                source,
+                seconds,
+                stale: stale.clone(),
+            };
+            (Box::new(mock), stale)
+        }
+    }
+
+    impl TimeProvider for Mock {
+        fn source(&self) -> TimeSource {
+            self.source
+        }
+
+        fn read(&self) -> Result<(UnixTime, TimeQuality), TimeServerError> {
+            if *self
+                .stale
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+            {
+                return Err(unavailable("sample is stale"));
+            }
+            let time = UnixTime::from_datetime(DateTime::from_timestamp(self.seconds, 0).unwrap());
+            Ok((time, TimeQuality::LIVE))
+        }
+    }
+
+    #[test]
+    fn test_first_available_source_wins() {
+        let (shm, _) = Mock::boxed(TimeSource::Shm, 100);
+        let (system, _) = Mock::boxed(TimeSource::System, 200);
+        let manager = TimeSourceManager::new(vec![shm, system]);
+        let reading = manager.now().unwrap();
+        assert_eq!(reading.source, TimeSource::Shm);
+        assert_eq!(reading.time.seconds, 100);
+
+        let (shm, _) = Mock::boxed(TimeSource::Shm, 100);
+        let (system, _) = Mock::boxed(TimeSource::System, 200);
+        let manager = TimeSourceManager::new(vec![system, shm]);
+        assert_eq!(manager.now().unwrap().source, TimeSource::System);
+        assert_eq!(manager.priority(), [TimeSource::System, TimeSource::Shm]);
+    }
+
+    #[test]
+    fn test_falls_back_when_source_goes_stale_and_returns_when_fresh() {
+        let (shm, shm_stale) = Mock::boxed(TimeSource::Shm, 100);
+        let (system, _) = Mock::boxed(TimeSource::System, 200);
+        let manager = TimeSourceManager::new(vec![shm, system]);
+        assert_eq!(manager.now().unwrap().source, TimeSource::Shm);
+
+        *shm_stale
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        let reading = manager.now().unwrap();
+        assert_eq!(reading.source, TimeSource::System);
+        assert_eq!(reading.time.seconds, 200);
+
+        *shm_stale
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = false;
+        assert_eq!(manager.now().unwrap().source, TimeSource::Shm);
+        assert!(manager
+            .failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .last_logged
+            .is_empty());
+    }
+
+    #[test]
+    fn test_all_sources_failed() {
+        let (shm, shm_stale) = Mock::boxed(TimeSource::Shm, 100);
+        let (gps, gps_stale) = Mock::boxed(TimeSource::Gps, 100);
+        *shm_stale
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        *gps_stale
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        let manager = TimeSourceManager::new(vec![shm, gps]);
+        let error = manager.now().unwrap_err();
+        assert_eq!(error.code(), "clock_error");
+        let message = error.to_string();
+        assert!(message.contains("shm: "), "{}", message);
+        assert!(message.contains("gps: "), "{}", message);
+
+        assert!(TimeSourceManager::new(Vec::new()).now().is_err());
+    }
+
+    #[test]
+    fn test_failures_are_logged_once_per_interval() {
+        let mut log = FailureLog::default();
+        let start = Instant::now();
+        assert!(log.failed(TimeSource::Shm, start));
+        assert!(!log.failed(TimeSource::Shm, start + Duration::from_secs(1)));
+        assert!(log.failed(TimeSource::Gps, start + Duration::from_secs(1)));
+        assert!(log.failed(TimeSource::Shm, start + FAILURE_LOG_INTERVAL));
+
+        assert!(log.recovered(TimeSource::Shm));
+        assert!(!log.recovered(TimeSource::Shm));
+        assert!(log.failed(TimeSource::Shm, start + FAILURE_LOG_INTERVAL));
+    }
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(
+            TimeSource::parse_priority(" SHM, ntp ,system,shm").unwrap(),
+            [TimeSource::Shm, TimeSource::Ntp, TimeSource::System]
+        );
+        assert_eq!(
+            TimeSource::parse_priority("shm,sundial").unwrap_err(),
+            "sundial"
+        );
+        assert_eq!(
+            TimeSource::parse_priority("ptp,system").unwrap(),
+            [TimeSource::Ptp, TimeSource::System]
+        );
+        assert!(TimeSource::parse_priority(" , ").is_err());
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_samples_are_projected_and_age_checked() {
+        let received: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let reference = received + chrono::Duration::milliseconds(5);
+        let now = received + chrono::Duration::seconds(2);
+        let (time, quality) = project(reference, received, now, Some(1e-6)).unwrap();
+        assert_eq!(time.to_datetime(), now + chrono::Duration::milliseconds(5));
+        assert_eq!(quality.age_s, 2.0);
+
+        let late = received + chrono::Duration::seconds(61);
+        assert!(project(reference, received, late, None).is_err());
+        let early = received - chrono::Duration::seconds(1);
+        assert!(project(reference, received, early, None).is_err());
+    }
+
+    #[cfg(feature = "ntp")]
+    #[test]
+    fn test_system_clock_always_answers() {
+        let manager = TimeSourceManager::from_priority(
+            &[TimeSource::Gps, TimeSource::System],
+            Arc::new(NtpSyncedClock::new()),
+        );
+        let reading = manager.now().unwrap();
+        assert_eq!(reading.source, TimeSource::System);
+        assert!((reading.to_datetime() - Utc::now()).num_seconds().abs() < 5);
+    }
+}
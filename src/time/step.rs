@@ -0,0 +1,112 @@
+// Clock step detection
+//
+// The wall clock and the monotonic clock advance together unless something
+// sets the wall clock: ntpd stepping it, an administrator, a VM resuming. A
+// watcher samples both each second and publishes `clock.step` when the wall
+// clock has moved more than `CLOCK_STEP_THRESHOLD` away from the monotonic
+// clock since the previous sample.
+
+use crate::events::{self, ServerEvent};
+use crate::time::UnixTime;
+use rmcp::model::LoggingLevel;
+use std::time::{Duration, Instant};
+
+/// Smallest wall-clock jump reported; slewing never gets near it
+pub const CLOCK_STEP_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// How often the watcher samples the clocks
+pub const CLOCK_STEP_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct StepDetector {
+    threshold_nanos: i128,
+    last: Option<(i128, Instant)>,
+}
+
+impl StepDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold_nanos: threshold.as_nanos() as i128,
+            last: None,
+        }
+    }
+
+    /// Record a sample; returns the step in nanoseconds (positive when the
+    /// wall clock jumped forward) if it exceeds the threshold
+    pub fn observe(&mut self, wall_nanos: i128, mono: Instant) -> Option<i128> {
+        let previous = self.last.replace((wall_nanos, mono));
+        let (last_wall, last_mono) = previous?;
+        let elapsed = mono.saturating_duration_since(last_mono).as_nanos() as i128;
+        let step = (wall_nanos - last_wall) - elapsed;
+        (step.abs() > self.threshold_nanos).then_some(step)
+    }
+}
+
+fn step_event(step_nanos: i128) -> ServerEvent {
+    let step_ms = step_nanos as f64 / 1e6;
+    let direction = if step_nanos > 0 {
+        "forward"
+    } else {
+        "backward"
+    };
+    ServerEvent::new(
+        "clock.step",
+        LoggingLevel::Warning,
+        format!(
+            "System clock stepped {} by {:.1} ms",
+            direction,
+            step_ms.abs()
+        ),
+    )
+    .with_details(serde_json::json!({ "step_ms": step_ms }))
+}
+
+/// Sample the clocks every `interval` for the life of the process
+pub fn spawn_watch(interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut detector = StepDetector::new(CLOCK_STEP_THRESHOLD);
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            let step = detector.observe(UnixTime::now().nanos_since_epoch, Instant::now());
+            if let Some(step) = step {
+                events::publish(step_event(step));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: i128 = 1_000_000_000;
+
+    #[test]
+    fn test_steps_beyond_threshold_are_reported() {
+        let mut detector = StepDetector::new(CLOCK_STEP_THRESHOLD);
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(detector.observe(100 * SECOND, at(0)), None);
+        assert_eq!(detector.observe(101 * SECOND, at(1)), None);
+        // 100 ms of slew is not a step
+        assert_eq!(detector.observe(102 * SECOND + SECOND / 10, at(2)), None);
+        assert_eq!(
+            detector.observe(110 * SECOND + SECOND / 10, at(3)),
+            Some(7 * SECOND)
+        );
+        assert_eq!(
+            detector.observe(105 * SECOND + SECOND / 10, at(4)),
+            Some(-6 * SECOND)
+        );
+    }
+
+    #[test]
+    fn test_step_event_shape() {
+        let event = step_event(-2_500_000_000);
+        assert_eq!(event.kind, "clock.step");
+        assert_eq!(event.message, "System clock stepped backward by 2500.0 ms");
+        assert_eq!(event.details["step_ms"], -2500.0);
+    }
+}
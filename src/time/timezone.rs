@@ -1,55 +1,1015 @@
 // Timezone support and conversion
 
-use chrono::{DateTime, FixedOffset, Offset, Utc};
-use chrono_tz::{Tz, TZ_VARIANTS};
+use super::explain::{self, Explain};
+use crate::error::TimeServerError;
+use chrono::{
+    DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz, TZ_VARIANTS};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Step used for the coarse scan when searching for offset transitions.
+/// Six hours is well below the shortest gap between two real transitions,
+/// so a change followed by a change back cannot hide between two samples.
+const TRANSITION_SCAN_STEP_SECS: i64 = 6 * 3600;
+
+/// Range searched per step by `next_transition`
+const NEXT_TRANSITION_CHUNK_SECS: i64 = 92 * 86400;
+
+/// Page size when a timezone listing is filtered or paged without a `limit`
+pub const DEFAULT_TIMEZONE_PAGE_LIMIT: usize = 100;
+
+/// Largest page a timezone listing will return
+pub const MAX_TIMEZONE_PAGE_LIMIT: usize = 600;
+
+/// Longest range an offset timeline covers (ten years of 366 days)
+pub const MAX_TIMELINE_RANGE_SECS: i64 = 10 * 366 * 86400;
+
+/// Days either side of a date scanned for changes of an offset difference
+pub const OFFSET_DIFFERENCE_WINDOW_DAYS: i64 = 14;
+
+/// Longest date range an offset difference is computed over
+pub const MAX_OFFSET_DIFFERENCE_RANGE_DAYS: i64 = MAX_TIMELINE_RANGE_SECS / 86400;
+
+/// How long a detailed listing is reused before offsets are recomputed
+pub const TIMEZONE_DETAIL_TTL_SECS: i64 = 60;
+
+/// Region key for names without a `Region/` part ("UTC", "EST5EDT", ...)
+const UNGROUPED_REGION: &str = "Other";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimezoneInfo {
     pub name: String,
     pub offset_seconds: i32,
+    /// Abbreviation from the tz database ("JST", "CEST", or numeric like "-0430")
+    pub abbreviation: String,
+    pub is_dst: bool,
+    /// Instant the rules were evaluated at (RFC 3339, UTC)
+    pub at: String,
+}
+
+/// Direction of a UTC offset change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Clocks move forward; local times in the gap do not exist
+    Gap,
+    /// Clocks move back; local times in the overlap occur twice
+    Overlap,
+}
+
+/// A single UTC offset transition of a timezone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OffsetTransition {
+    /// Instant of the transition (RFC 3339, UTC)
+    pub utc: String,
+    /// Instant of the transition as Unix seconds
+    pub unix_timestamp: i64,
+    /// Local wall-clock time just before the transition
+    pub local_before: String,
+    /// Local wall-clock time just after the transition
+    pub local_after: String,
+    pub offset_before_seconds: i32,
+    pub offset_after_seconds: i32,
+    /// Offset change in minutes (positive when clocks move forward)
+    pub change_minutes: i32,
+    pub kind: TransitionKind,
+}
+
+/// A stretch of time over which a timezone keeps one UTC offset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OffsetSegment {
+    /// Start of the segment, inclusive (RFC 3339, UTC)
+    pub from_utc: String,
+    /// End of the segment, exclusive (RFC 3339, UTC)
+    pub to_utc: String,
+    pub from_unix: i64,
+    pub to_unix: i64,
+    pub offset_seconds: i32,
+    /// Abbreviation at the start of the segment
+    pub abbreviation: String,
+    pub is_dst: bool,
+}
+
+/// Offsets of two zones on one date and how far apart they are
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatedOffsets {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub from_offset_seconds: i32,
+    pub to_offset_seconds: i32,
+    /// `to` minus `from`: positive when `to` is ahead
+    pub difference_minutes: i32,
+}
+
+/// The offset difference of two zones on a date, and its changes nearby
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OffsetDifference {
+    pub from_timezone: String,
+    pub to_timezone: String,
+    #[serde(flatten)]
+    pub offsets: DatedOffsets,
+    /// Days either side of `date` scanned for changes
+    pub window_days: i64,
+    pub changes_in_window: bool,
+    /// First date of each new difference within the window, in date order
+    pub changes: Vec<DatedOffsets>,
+}
+
+/// Dates over which two zones keep one offset difference
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DifferenceSegment {
+    /// First date, inclusive (YYYY-MM-DD)
+    pub from_date: String,
+    /// Last date, inclusive (YYYY-MM-DD)
+    pub to_date: String,
+    pub from_offset_seconds: i32,
+    pub to_offset_seconds: i32,
+    pub difference_minutes: i32,
+}
+
+/// How to resolve a local time that occurs twice (clocks moving back)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmbiguityPolicy {
+    /// Pick the earlier instant (the pre-transition offset)
+    #[default]
+    Earlier,
+    /// Pick the later instant (the post-transition offset)
+    Later,
+    /// Fail and report both candidates
+    Reject,
+}
+
+/// How to resolve a local time skipped by a forward transition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    /// Fail for nonexistent local times
+    #[default]
+    Reject,
+    /// Move the local time forward by the length of the gap
+    ShiftForward,
+}
+
+/// Classification of a local wall-clock time in a timezone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalTimeStatus {
+    Unique,
+    Ambiguous,
+    Nonexistent,
+}
+
+/// One possible UTC instant for a local wall-clock time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalCandidate {
+    pub unix_timestamp: i64,
+    pub utc: String,
+    pub offset_seconds: i32,
+}
+
+/// Result of resolving a local wall-clock time to a UTC instant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalResolution {
+    pub local_datetime: String,
+    pub timezone: String,
+    pub status: LocalTimeStatus,
+    /// Policy used to pick the result, if the local time was not unique
+    pub policy_applied: Option<String>,
+    pub unix_timestamp: i64,
+    pub utc: String,
+    pub offset_seconds: i32,
+    /// All instants the local time maps to (two when ambiguous, none when nonexistent)
+    pub candidates: Vec<LocalCandidate>,
+}
+
+/// Sent with every convert_time result, since the timezone parameters are
+/// easily read as shifting the timestamp
+pub const CONVERSION_NOTE: &str =
+    "timestamp is absolute Unix time; the timezones only change how it is shown";
+
+/// What a conversion starts from: the instant as the caller gave it
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ConversionOrigin {
+    /// Unix seconds; absolute, the same whichever timezone renders it
+    pub timestamp: i64,
+    pub timezone: String,
+    /// The instant in `timezone`, with its offset
+    pub formatted: String,
+    pub offset: i32,
+    /// The wall-clock input, when the instant was resolved from one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_datetime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<LocalTimeStatus>,
+    /// Policy used because the local time was ambiguous or skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_applied: Option<String>,
+}
+
+/// The same instant rendered in the target timezone
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ConvertedTime {
+    pub timestamp: i64,
+    pub timezone: String,
+    pub formatted: String,
+    pub offset: i32,
+    pub rfc9557: String,
     pub abbreviation: String,
     pub is_dst: bool,
 }
 
+impl ConvertedTime {
+    /// `converted` under the name the caller gave, with the abbreviation and
+    /// DST flag in force at that instant rather than today
+    pub fn new(
+        converted: &DateTime<Tz>,
+        timezone: String,
+        calendar_annotation: bool,
+    ) -> Result<Self, TimeServerError> {
+        let info =
+            TimezoneConverter::get_timezone_info(&timezone, Some(converted.with_timezone(&Utc)))?;
+        Ok(Self {
+            timestamp: converted.timestamp(),
+            formatted: converted.to_rfc3339(),
+            offset: converted.offset().fix().local_minus_utc(),
+            rfc9557: super::format_rfc9557(converted, calendar_annotation),
+            abbreviation: info.abbreviation,
+            is_dst: info.is_dst,
+            timezone,
+        })
+    }
+}
+
+/// A convert_time result
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Conversion {
+    pub original: ConversionOrigin,
+    /// Always the same reminder that the timestamp is absolute
+    pub note: &'static str,
+    pub converted: ConvertedTime,
+}
+
+/// Filter and paging options for timezone listings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimezoneListQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    /// Case-insensitive prefix of the full IANA name
+    pub prefix: Option<String>,
+    /// Case-insensitive first path segment ("Europe", "america")
+    pub region: Option<String>,
+    /// Return `region -> [city, ...]` instead of a flat list
+    pub grouped: bool,
+    /// Return `zones` with each name's current offset; takes precedence over `grouped`
+    pub detail: bool,
+}
+
+impl TimezoneListQuery {
+    /// True when the caller asked for the legacy full listing
+    fn is_unbounded(&self) -> bool {
+        self.offset.is_none()
+            && self.limit.is_none()
+            && self.prefix.is_none()
+            && self.region.is_none()
+    }
+}
+
+/// The sorted names and their checksum, built together on first use (the
+/// shared state asks for the names at startup)
+struct TimezoneList {
+    names: Vec<&'static str>,
+    checksum: String,
+}
+
+fn timezone_list() -> &'static TimezoneList {
+    static LIST: OnceLock<TimezoneList> = OnceLock::new();
+    LIST.get_or_init(|| {
+        let mut names: Vec<&'static str> = TZ_VARIANTS.iter().map(|tz| tz.name()).collect();
+        names.sort_unstable();
+        let checksum = list_checksum(&names, super::tzdata::tzdata_version());
+        TimezoneList { names, checksum }
+    })
+}
+
+/// FNV-1a (64 bit) of the tzdata version and each name, newline separated,
+/// as 16 hex digits; stable across builds and platforms
+pub fn list_checksum(names: &[&str], tzdata_version: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET_BASIS;
+    for part in std::iter::once(tzdata_version).chain(names.iter().copied()) {
+        for byte in part.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// One page of a timezone listing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimezonePage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezones: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, Vec<String>>>,
+    /// Number of names in this page
+    pub count: usize,
+    /// Number of names matching the filters, across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub has_more: bool,
+    /// Identifies the full list and the tzdata it came from, whatever the
+    /// filters; see [`TimezoneConverter::timezone_list_checksum`]
+    pub checksum: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zones: Option<Vec<TimezoneDetail>>,
+    /// RFC 3339 instant every entry in `zones` was computed at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captured_at: Option<String>,
+}
+
+impl TimezonePage {
+    /// When the offsets in `zones` are next recomputed; None without `detail`
+    pub fn detail_expires_at(&self) -> Option<DateTime<Utc>> {
+        let captured_at = DateTime::parse_from_rfc3339(self.captured_at.as_deref()?).ok()?;
+        Some(captured_at.with_timezone(&Utc) + chrono::Duration::seconds(TIMEZONE_DETAIL_TTL_SECS))
+    }
+}
+
+/// A timezone name with its offset at the listing's `captured_at`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimezoneDetail {
+    pub name: String,
+    pub offset_seconds: i32,
+    /// "+05:30"
+    pub utc_offset: String,
+    pub is_dst: bool,
+    /// Hour of the day (0-23) in the zone
+    pub local_hour: u32,
+}
+
+/// Every zone's detail at one instant
+#[derive(Debug)]
+struct DetailSnapshot {
+    captured_at: DateTime<Utc>,
+    zones: HashMap<&'static str, TimezoneDetail>,
+}
+
+impl DetailSnapshot {
+    fn capture(at: DateTime<Utc>) -> Self {
+        let zones = TZ_VARIANTS
+            .iter()
+            .map(|tz| {
+                let local = at.with_timezone(tz);
+                let offset = local.offset();
+                let offset_seconds = offset.fix().local_minus_utc();
+                let detail = TimezoneDetail {
+                    name: tz.name().to_string(),
+                    offset_seconds,
+                    utc_offset: offset.fix().to_string(),
+                    is_dst: !offset.dst_offset().is_zero(),
+                    local_hour: local.hour(),
+                };
+                (tz.name(), detail)
+            })
+            .collect();
+        Self {
+            captured_at: at,
+            zones,
+        }
+    }
+}
+
+/// The cached detail snapshot, recaptured once it is
+/// [`TIMEZONE_DETAIL_TTL_SECS`] away from `now` in either direction
+fn detail_snapshot(now: DateTime<Utc>) -> Arc<DetailSnapshot> {
+    static CACHE: Mutex<Option<Arc<DetailSnapshot>>> = Mutex::new(None);
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(snapshot) = cache.as_ref() {
+        if (now - snapshot.captured_at).num_seconds().abs() < TIMEZONE_DETAIL_TTL_SECS {
+            return snapshot.clone();
+        }
+    }
+    let snapshot = Arc::new(DetailSnapshot::capture(now));
+    *cache = Some(snapshot.clone());
+    snapshot
+}
+
 pub struct TimezoneConverter;
 
 impl TimezoneConverter {
     /// Convert UTC time to specified timezone
-    pub fn convert_to_tz(utc: DateTime<Utc>, timezone: &str) -> Result<DateTime<Tz>, String> {
-        let tz: Tz = timezone
-            .parse()
-            .map_err(|_| format!("Invalid timezone: {}", timezone))?;
+    pub fn convert_to_tz(
+        utc: DateTime<Utc>,
+        timezone: &str,
+    ) -> Result<DateTime<Tz>, TimeServerError> {
+        let tz = parse_tz(timezone)?;
         Ok(utc.with_timezone(&tz))
     }
 
+    /// Convert one instant to several timezones; names are resolved as in
+    /// [`Self::resolve_name`] and the first unknown one is an error
+    pub fn convert_many<S: AsRef<str>>(
+        utc: DateTime<Utc>,
+        timezones: &[S],
+    ) -> Result<Vec<DateTime<Tz>>, TimeServerError> {
+        timezones
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                Self::resolve_name(name)
+                    .and_then(|resolved| resolved.parse::<Tz>().ok())
+                    .map(|tz| utc.with_timezone(&tz))
+                    .ok_or_else(|| TimeServerError::invalid_timezone(name))
+            })
+            .collect()
+    }
+
+    /// String-error form of [`Self::convert_to_tz`]
+    #[deprecated(
+        since = "0.2.0",
+        note = "Use convert_to_tz, which returns TimeServerError"
+    )]
+    pub fn convert_to_tz_str(utc: DateTime<Utc>, timezone: &str) -> Result<DateTime<Tz>, String> {
+        Ok(Self::convert_to_tz(utc, timezone)?)
+    }
+
     /// Get all available timezones
     pub fn list_timezones() -> Vec<String> {
-        TZ_VARIANTS.iter().map(|tz| tz.to_string()).collect()
+        Self::timezone_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// Every IANA name in alphabetical order, built once per process
+    pub fn timezone_names() -> &'static [&'static str] {
+        &timezone_list().names
+    }
+
+    /// Checksum of [`Self::timezone_names`] and the tzdata version; it only
+    /// changes when chrono-tz does, so clients can cache the list by it
+    pub fn timezone_list_checksum() -> &'static str {
+        &timezone_list().checksum
+    }
+
+    /// Filtered, paged timezone listing.
+    ///
+    /// With no filters or paging the full list is returned; otherwise `limit`
+    /// defaults to [`DEFAULT_TIMEZONE_PAGE_LIMIT`]. Limits are clamped to
+    /// [`MAX_TIMEZONE_PAGE_LIMIT`].
+    pub fn list_timezones_page(query: &TimezoneListQuery) -> TimezonePage {
+        let prefix = query.prefix.as_deref().map(str::to_ascii_lowercase);
+        let matching: Vec<String> = Self::timezone_names()
+            .iter()
+            .copied()
+            .filter(|name| {
+                prefix
+                    .as_deref()
+                    .is_none_or(|p| name.to_ascii_lowercase().starts_with(p))
+            })
+            .filter(|name| {
+                query
+                    .region
+                    .as_deref()
+                    .is_none_or(|r| region_of(name).eq_ignore_ascii_case(r.trim_matches('/')))
+            })
+            .map(str::to_string)
+            .collect();
+
+        let total = matching.len();
+        let offset = query.offset.unwrap_or(0);
+        let limit = if query.is_unbounded() {
+            total
+        } else {
+            query
+                .limit
+                .unwrap_or(DEFAULT_TIMEZONE_PAGE_LIMIT)
+                .min(MAX_TIMEZONE_PAGE_LIMIT)
+        };
+
+        let page: Vec<String> = matching.into_iter().skip(offset).take(limit).collect();
+        let count = page.len();
+        let has_more = offset.saturating_add(count) < total;
+
+        if query.detail {
+            let snapshot = detail_snapshot(crate::chaos::now());
+            let zones = page
+                .iter()
+                .filter_map(|name| snapshot.zones.get(name.as_str()).cloned())
+                .collect();
+            return TimezonePage {
+                timezones: None,
+                groups: None,
+                count,
+                total,
+                offset,
+                has_more,
+                checksum: Self::timezone_list_checksum(),
+                zones: Some(zones),
+                captured_at: Some(
+                    snapshot
+                        .captured_at
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                ),
+            };
+        }
+
+        let (timezones, groups) = if query.grouped {
+            let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for name in page {
+                let city = name.split_once('/').map_or(name.as_str(), |(_, city)| city);
+                groups
+                    .entry(region_of(&name).to_string())
+                    .or_default()
+                    .push(city.to_string());
+            }
+            (None, Some(groups))
+        } else {
+            (Some(page), None)
+        };
+
+        TimezonePage {
+            timezones,
+            groups,
+            count,
+            total,
+            offset,
+            has_more,
+            checksum: Self::timezone_list_checksum(),
+            zones: None,
+            captured_at: None,
+        }
+    }
+
+    /// Resolve user input to a canonical IANA name.
+    ///
+    /// Accepts spaces in place of underscores ("America/New York") and
+    /// case-insensitive matches ("europe/london").
+    pub fn resolve_name(input: &str) -> Option<String> {
+        let normalized = input.trim().trim_matches('/').replace(' ', "_");
+        if normalized.is_empty() {
+            return None;
+        }
+        if let Ok(tz) = normalized.parse::<Tz>() {
+            return Some(tz.name().to_string());
+        }
+        TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(&normalized))
+            .map(|tz| tz.name().to_string())
+    }
+
+    /// Suggest up to `limit` IANA names close to `input`, best matches first
+    pub fn suggest_timezones(input: &str, limit: usize) -> Vec<String> {
+        let needle = input.trim().replace(' ', "_").to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let needle_city = needle.rsplit('/').next().unwrap_or(&needle);
+
+        let mut scored: Vec<(usize, &str)> = TZ_VARIANTS
+            .iter()
+            .map(|tz| {
+                let name = tz.name();
+                let lower = name.to_lowercase();
+                let city = lower.rsplit('/').next().unwrap_or(&lower);
+                let score = if lower.starts_with(&needle) {
+                    0
+                } else if city.starts_with(needle_city) || lower.contains(&needle) {
+                    1
+                } else {
+                    // Offset by 2 so substring matches always rank first
+                    2 + levenshtein(&needle, &lower).min(levenshtein(needle_city, city))
+                };
+                (score, name)
+            })
+            .collect();
+
+        // Drop candidates that need more edits than half the input length
+        let max_score = 2 + needle_city.len().max(3) / 2;
+        scored.retain(|(score, _)| *score <= max_score);
+        scored.sort();
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, name)| name.to_string())
+            .collect()
     }
 
     /// Get timezone info for a given timezone
-    pub fn get_timezone_info(timezone: &str) -> Result<TimezoneInfo, String> {
-        let tz: Tz = timezone
-            .parse()
-            .map_err(|_| format!("Invalid timezone: {}", timezone))?;
+    /// Offset, abbreviation and DST state of `timezone` under the rules in
+    /// force at `at` (defaults to now)
+    pub fn get_timezone_info(
+        timezone: &str,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<TimezoneInfo, TimeServerError> {
+        let tz = parse_tz(timezone)?;
 
-        let now = Utc::now().with_timezone(&tz);
-        let offset = now.offset();
+        let at = at.unwrap_or_else(Utc::now);
+        let offset = *at.with_timezone(&tz).offset();
 
         Ok(TimezoneInfo {
             name: timezone.to_string(),
             offset_seconds: offset.fix().local_minus_utc(),
-            abbreviation: format!("{}", offset),
-            is_dst: false, // Would need additional logic for DST detection
+            abbreviation: offset.abbreviation().to_string(),
+            is_dst: !offset.dst_offset().is_zero(),
+            at: at.to_rfc3339(),
+        })
+    }
+
+    /// Time zone abbreviation in force at `at` ("EST" vs "EDT", "MSD" vs "MSK")
+    pub fn abbreviation_at(timezone: &str, at: DateTime<Utc>) -> Result<String, TimeServerError> {
+        Self::get_timezone_info(timezone, Some(at)).map(|info| info.abbreviation)
+    }
+
+    /// Get every UTC offset transition of `timezone` during `year` (UTC calendar year)
+    pub fn dst_transitions(
+        timezone: &str,
+        year: i32,
+    ) -> Result<Vec<OffsetTransition>, TimeServerError> {
+        let tz = parse_tz(timezone)?;
+        let (start, end) = year_bounds(year)?;
+        Ok(find_transitions(tz, start, end))
+    }
+
+    /// Whether `timezone` keeps daylight saving time at any point of `year`.
+    /// A year can have transitions without DST (a standard offset change)
+    /// or DST without transitions (DST kept all year)
+    pub fn observes_dst(timezone: &str, year: i32) -> Result<bool, TimeServerError> {
+        let tz = parse_tz(timezone)?;
+        let (start, end) = year_bounds(year)?;
+        let is_dst = |secs: i64| {
+            DateTime::from_timestamp(secs, 0)
+                .is_some_and(|at| !at.with_timezone(&tz).offset().dst_offset().is_zero())
+        };
+        Ok(is_dst(start)
+            || find_transitions(tz, start, end)
+                .iter()
+                .any(|transition| is_dst(transition.unix_timestamp)))
+    }
+
+    /// First UTC offset transition of `timezone` after `after`, looking at
+    /// most `horizon_secs` ahead; None for zones without one in that window
+    pub fn next_transition(
+        timezone: &str,
+        after: DateTime<Utc>,
+        horizon_secs: i64,
+    ) -> Result<Option<OffsetTransition>, TimeServerError> {
+        let tz = parse_tz(timezone)?;
+        let start = after.timestamp() + 1;
+        let end = start.saturating_add(horizon_secs);
+        // Scan a quarter at a time: most lookups stop within the first one
+        let mut lo = start;
+        while lo < end {
+            let hi = lo.saturating_add(NEXT_TRANSITION_CHUNK_SECS).min(end);
+            if let Some(transition) = find_transitions(tz, lo, hi).into_iter().next() {
+                return Ok(Some(transition));
+            }
+            lo = hi;
+        }
+        Ok(None)
+    }
+
+    /// The offset of `timezone` over `[start, end)` as consecutive segments.
+    /// The first segment starts at `start` and the last ends at `end`, not at
+    /// the surrounding transitions; the range is capped at
+    /// [`MAX_TIMELINE_RANGE_SECS`].
+    pub fn offset_timeline(
+        timezone: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OffsetSegment>, TimeServerError> {
+        let tz = parse_tz(timezone)?;
+        let instant = super::unix::datetime_at;
+        instant(start)?;
+        instant(end)?;
+        if end <= start {
+            return Err(TimeServerError::InvalidArgument(
+                "'end' must be after 'start'".to_string(),
+            ));
+        }
+        if end - start > MAX_TIMELINE_RANGE_SECS {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "Range of {} days exceeds the maximum of {} days",
+                (end - start) / 86400,
+                MAX_TIMELINE_RANGE_SECS / 86400
+            )));
+        }
+
+        let boundaries: Vec<i64> = std::iter::once(start)
+            .chain(
+                find_transitions(tz, start, end)
+                    .iter()
+                    .map(|t| t.unix_timestamp),
+            )
+            .chain(std::iter::once(end))
+            .collect();
+
+        boundaries
+            .windows(2)
+            .map(|pair| {
+                let (from, to) = (instant(pair[0])?, instant(pair[1])?);
+                let offset = *from.with_timezone(&tz).offset();
+                Ok(OffsetSegment {
+                    from_utc: from.to_rfc3339(),
+                    to_utc: to.to_rfc3339(),
+                    from_unix: pair[0],
+                    to_unix: pair[1],
+                    offset_seconds: offset.fix().local_minus_utc(),
+                    abbreviation: offset.abbreviation().to_string(),
+                    is_dst: !offset.dst_offset().is_zero(),
+                })
+            })
+            .collect()
+    }
+
+    /// Offsets of `from` and `to` on `date`, their difference, and every
+    /// change of that difference within [`OFFSET_DIFFERENCE_WINDOW_DAYS`]
+    /// either side of `date`
+    pub fn offset_difference(
+        from: &str,
+        to: &str,
+        date: NaiveDate,
+    ) -> Result<OffsetDifference, TimeServerError> {
+        let zones = (parse_tz(from)?, parse_tz(to)?);
+        let window = chrono::Duration::days(OFFSET_DIFFERENCE_WINDOW_DAYS);
+        let (first, last) = date
+            .checked_sub_signed(window)
+            .zip(date.checked_add_signed(window))
+            .ok_or_else(|| {
+                TimeServerError::InvalidArgument(format!("Date out of range: {}", date))
+            })?;
+
+        let changes: Vec<DatedOffsets> = difference_changes(zones, first, last)
+            .into_iter()
+            .skip(1)
+            .map(|date| dated_offsets(zones, date))
+            .collect();
+        Ok(OffsetDifference {
+            from_timezone: from.to_string(),
+            to_timezone: to.to_string(),
+            offsets: dated_offsets(zones, date),
+            window_days: OFFSET_DIFFERENCE_WINDOW_DAYS,
+            changes_in_window: !changes.is_empty(),
+            changes,
         })
     }
 
+    /// The offset difference of `from` and `to` over `start..=end` as
+    /// consecutive segments of dates, at most
+    /// [`MAX_OFFSET_DIFFERENCE_RANGE_DAYS`] long
+    pub fn offset_difference_range(
+        from: &str,
+        to: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<DifferenceSegment>, TimeServerError> {
+        let zones = (parse_tz(from)?, parse_tz(to)?);
+        if end < start {
+            return Err(TimeServerError::InvalidArgument(
+                "'end_date' must not be before 'date'".to_string(),
+            ));
+        }
+        let days = (end - start).num_days() + 1;
+        if days > MAX_OFFSET_DIFFERENCE_RANGE_DAYS {
+            return Err(TimeServerError::InvalidArgument(format!(
+                "Range of {} days exceeds the maximum of {} days",
+                days, MAX_OFFSET_DIFFERENCE_RANGE_DAYS
+            )));
+        }
+
+        let starts = difference_changes(zones, start, end);
+        let ends = starts
+            .iter()
+            .skip(1)
+            .map(|next| next.pred_opt().unwrap_or(end))
+            .chain(std::iter::once(end));
+        Ok(starts
+            .iter()
+            .zip(ends)
+            .map(|(&from_date, to_date)| {
+                let offsets = dated_offsets(zones, from_date);
+                DifferenceSegment {
+                    from_date: offsets.date,
+                    to_date: to_date.format("%Y-%m-%d").to_string(),
+                    from_offset_seconds: offsets.from_offset_seconds,
+                    to_offset_seconds: offsets.to_offset_seconds,
+                    difference_minutes: offsets.difference_minutes,
+                }
+            })
+            .collect())
+    }
+
+    /// Resolve a local wall-clock time (no offset) in `timezone` to a UTC instant
+    pub fn local_to_utc(
+        local_datetime: &str,
+        timezone: &str,
+        ambiguity: AmbiguityPolicy,
+        gap: GapPolicy,
+    ) -> Result<LocalResolution, TimeServerError> {
+        Self::local_to_utc_explained(
+            local_datetime,
+            timezone,
+            ambiguity,
+            gap,
+            &mut Explain::off(),
+        )
+    }
+
+    /// [`Self::local_to_utc`], recording each step in `explain`
+    pub fn local_to_utc_explained(
+        local_datetime: &str,
+        timezone: &str,
+        ambiguity: AmbiguityPolicy,
+        gap: GapPolicy,
+        explain: &mut Explain,
+    ) -> Result<LocalResolution, TimeServerError> {
+        Self::resolve_local(local_datetime, timezone, false, ambiguity, gap, explain)
+    }
+
+    /// `local_datetime` in `timezone`, which the caller gave or (`defaulted`) assumed
+    fn resolve_local(
+        local_datetime: &str,
+        timezone: &str,
+        defaulted: bool,
+        ambiguity: AmbiguityPolicy,
+        gap: GapPolicy,
+        explain: &mut Explain,
+    ) -> Result<LocalResolution, TimeServerError> {
+        let tz = parse_tz(timezone)?;
+        let naive = parse_local_datetime(local_datetime)?;
+        explain.step(|| explain::local_input(local_datetime, naive, timezone, defaulted));
+
+        let candidate = |dt: DateTime<Tz>| LocalCandidate {
+            unix_timestamp: dt.timestamp(),
+            utc: dt.with_timezone(&Utc).to_rfc3339(),
+            offset_seconds: dt.offset().fix().local_minus_utc(),
+        };
+
+        let (status, policy_applied, chosen, candidates) = match tz.from_local_datetime(&naive) {
+            LocalResult::Single(dt) => (
+                LocalTimeStatus::Unique,
+                None,
+                candidate(dt),
+                vec![candidate(dt)],
+            ),
+            LocalResult::Ambiguous(a, b) => {
+                let (earlier, later) = if a.timestamp() <= b.timestamp() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                let candidates = vec![candidate(earlier), candidate(later)];
+                let (policy, chosen) = match ambiguity {
+                    AmbiguityPolicy::Earlier => ("earlier", candidate(earlier)),
+                    AmbiguityPolicy::Later => ("later", candidate(later)),
+                    AmbiguityPolicy::Reject => {
+                        return Err(TimeServerError::InvalidArgument(format!(
+                            "Ambiguous local time {} in {}: could be {} or {}",
+                            local_datetime, timezone, candidates[0].utc, candidates[1].utc
+                        )))
+                    }
+                };
+                explain.step(|| explain::ambiguous(naive, timezone, &candidates, policy, &chosen));
+                (
+                    LocalTimeStatus::Ambiguous,
+                    Some(policy.to_string()),
+                    chosen,
+                    candidates,
+                )
+            }
+            LocalResult::None => match gap {
+                GapPolicy::Reject => {
+                    return Err(TimeServerError::InvalidArgument(format!(
+                        "Nonexistent local time {} in {} (skipped by a forward transition)",
+                        local_datetime, timezone
+                    )))
+                }
+                GapPolicy::ShiftForward => {
+                    // Offsets a day either side of the gap give its length
+                    let local_ts = naive.and_utc().timestamp();
+                    let gap_secs =
+                        offset_at(tz, local_ts + 86400) - offset_at(tz, local_ts - 86400);
+                    let shifted = naive + chrono::Duration::seconds(gap_secs.max(0) as i64);
+                    let dt = tz.from_local_datetime(&shifted).earliest().ok_or_else(|| {
+                        TimeServerError::InvalidArgument(format!(
+                            "Unable to resolve {} in {}",
+                            local_datetime, timezone
+                        ))
+                    })?;
+                    let chosen = candidate(dt);
+                    explain
+                        .step(|| explain::nonexistent(naive, timezone, gap_secs, shifted, &chosen));
+                    (
+                        LocalTimeStatus::Nonexistent,
+                        Some("shift_forward".to_string()),
+                        chosen,
+                        Vec::new(),
+                    )
+                }
+            },
+        };
+
+        explain.step(|| {
+            let utc = DateTime::from_timestamp(chosen.unix_timestamp, 0).unwrap_or_default();
+            explain::offset_rule(tz, utc)
+        });
+        explain.step(|| explain::local_to_utc_arithmetic(&chosen));
+
+        Ok(LocalResolution {
+            local_datetime: local_datetime.to_string(),
+            timezone: timezone.to_string(),
+            status,
+            policy_applied,
+            unix_timestamp: chosen.unix_timestamp,
+            utc: chosen.utc,
+            offset_seconds: chosen.offset_seconds,
+            candidates,
+        })
+    }
+
+    /// The starting instant of convert_time: either a Unix `timestamp`,
+    /// rendered in `from_timezone`, or a `local_datetime` wall-clock time in
+    /// `from_timezone` resolved with the given policies. `from_timezone`
+    /// defaults to UTC and must be an IANA name.
+    pub fn conversion_origin(
+        timestamp: Option<i64>,
+        local_datetime: Option<&str>,
+        from_timezone: Option<&str>,
+        ambiguity: AmbiguityPolicy,
+        gap: GapPolicy,
+    ) -> Result<(DateTime<Utc>, ConversionOrigin), TimeServerError> {
+        Self::conversion_origin_explained(
+            timestamp,
+            local_datetime,
+            from_timezone,
+            ambiguity,
+            gap,
+            &mut Explain::off(),
+        )
+    }
+
+    /// [`Self::conversion_origin`], recording each step in `explain`
+    pub fn conversion_origin_explained(
+        timestamp: Option<i64>,
+        local_datetime: Option<&str>,
+        from_timezone: Option<&str>,
+        ambiguity: AmbiguityPolicy,
+        gap: GapPolicy,
+        explain: &mut Explain,
+    ) -> Result<(DateTime<Utc>, ConversionOrigin), TimeServerError> {
+        let timezone = from_timezone.unwrap_or("UTC");
+        let defaulted = from_timezone.is_none();
+        let tz = parse_tz(timezone)?;
+        let (utc, resolution) = match (timestamp, local_datetime) {
+            (Some(timestamp), None) => {
+                let utc = super::unix::datetime_at(timestamp)?;
+                explain.step(|| explain::timestamp_input(timestamp, timezone, defaulted));
+                (utc, None)
+            }
+            (None, Some(local)) => {
+                let resolution =
+                    Self::resolve_local(local, timezone, defaulted, ambiguity, gap, explain)?;
+                let utc = Utc
+                    .timestamp_opt(resolution.unix_timestamp, 0)
+                    .single()
+                    .ok_or_else(|| {
+                        TimeServerError::InvalidTimestamp(format!("'{}' is out of range", local))
+                    })?;
+                (utc, Some(resolution))
+            }
+            _ => {
+                return Err(TimeServerError::InvalidArgument(
+                    "give exactly one of 'timestamp' and 'local_datetime'".to_string(),
+                ))
+            }
+        };
+        let local = utc.with_timezone(&tz);
+        let origin = ConversionOrigin {
+            timestamp: utc.timestamp(),
+            timezone: timezone.to_string(),
+            formatted: local.to_rfc3339(),
+            offset: local.offset().fix().local_minus_utc(),
+            local_datetime: resolution.as_ref().map(|r| r.local_datetime.clone()),
+            status: resolution.as_ref().map(|r| r.status),
+            policy_applied: resolution.and_then(|r| r.policy_applied),
+        };
+        Ok((utc, origin))
+    }
+
     /// Convert using POSIX TZ string (e.g., "PST8PDT,M3.2.0,M11.1.0")
     pub fn from_posix_tz(
         utc: DateTime<Utc>,
         tz_string: &str,
-    ) -> Result<DateTime<FixedOffset>, String> {
+    ) -> Result<DateTime<FixedOffset>, TimeServerError> {
         // Parse POSIX TZ string and apply offset
         // This is a simplified implementation
         let offset_hours = if tz_string.contains("EST") {
@@ -59,14 +1019,230 @@ impl TimezoneConverter {
         } else if tz_string.contains("GMT") || tz_string.contains("UTC") {
             0
         } else {
-            return Err("Unsupported TZ string".to_string());
+            return Err(TimeServerError::InvalidTimezone {
+                name: tz_string.to_string(),
+                suggestions: Vec::new(),
+            });
         };
 
-        let offset = FixedOffset::west_opt(offset_hours * 3600).ok_or("Invalid offset")?;
+        let offset = FixedOffset::west_opt(offset_hours * 3600)
+            .ok_or_else(|| TimeServerError::InvalidArgument("Invalid offset".to_string()))?;
         Ok(utc.with_timezone(&offset))
     }
 }
 
+/// Parse an exact IANA name; the error carries suggestions
+fn parse_tz(timezone: &str) -> Result<Tz, TimeServerError> {
+    timezone
+        .parse()
+        .map_err(|_| TimeServerError::invalid_timezone(timezone))
+}
+
+/// First path segment of an IANA name, or [`UNGROUPED_REGION`] for bare names
+fn region_of(name: &str) -> &str {
+    name.split_once('/')
+        .map_or(UNGROUPED_REGION, |(region, _)| region)
+}
+
+/// Levenshtein edit distance between two strings
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut curr = vec![0; b_chars.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b_chars.len()]
+}
+
+/// Parse an ISO-like local date-time without offset ("2024-11-03T01:30", "2024-11-03 01:30:00")
+pub fn parse_local_datetime(input: &str) -> Result<NaiveDateTime, TimeServerError> {
+    const FORMATS: [&str; 4] = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ];
+
+    let input = input.trim();
+    FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(input, f).ok())
+        .ok_or_else(|| {
+            TimeServerError::InvalidTimestamp(format!(
+                "'{}': expected YYYY-MM-DDTHH:MM[:SS] without offset",
+                input
+            ))
+        })
+}
+
+/// UTC offset of `tz` at the given Unix timestamp, in seconds
+fn offset_at(tz: Tz, timestamp: i64) -> i32 {
+    match Utc.timestamp_opt(timestamp, 0).single() {
+        Some(utc) => tz
+            .offset_from_utc_datetime(&utc.naive_utc())
+            .fix()
+            .local_minus_utc(),
+        None => 0,
+    }
+}
+
+/// UTC offset of `tz` on `date`, taken at local noon.
+///
+/// Transitions happen at night or in the early morning, so a date's noon
+/// is past any change that date makes and clear of the hour that is skipped
+/// or repeated; midnight could fall on either side of a change made at
+/// 00:00 and would report the day before's offset.
+fn noon_offset(tz: Tz, date: NaiveDate) -> i32 {
+    let noon = date.and_hms_opt(12, 0, 0).unwrap_or_default();
+    match tz.from_local_datetime(&noon).earliest() {
+        Some(local) => local.offset().fix().local_minus_utc(),
+        // Noon skipped by a transition: the offset at that instant in UTC
+        None => offset_at(tz, noon.and_utc().timestamp()),
+    }
+}
+
+fn dated_offsets((from, to): (Tz, Tz), date: NaiveDate) -> DatedOffsets {
+    let (from_offset, to_offset) = (noon_offset(from, date), noon_offset(to, date));
+    DatedOffsets {
+        date: date.format("%Y-%m-%d").to_string(),
+        from_offset_seconds: from_offset,
+        to_offset_seconds: to_offset,
+        difference_minutes: (to_offset - from_offset) / 60,
+    }
+}
+
+/// `first`, then each later date up to `last` whose offset difference is
+/// not the one before it.
+///
+/// The difference can only change from one date to the next when either
+/// zone has a transition between their noons, so only the dates after
+/// the transitions [`find_transitions`] reports are evaluated.
+fn difference_changes(zones: (Tz, Tz), first: NaiveDate, last: NaiveDate) -> Vec<NaiveDate> {
+    // Local dates start up to a day before or after the UTC date
+    let utc_midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let start = (utc_midnight(first) - chrono::Duration::days(1)).timestamp();
+    let end = (utc_midnight(last) + chrono::Duration::days(2)).timestamp();
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap_or_default();
+
+    let mut candidates: Vec<NaiveDate> = [zones.0, zones.1]
+        .into_iter()
+        .flat_map(|tz| {
+            find_transitions(tz, start, end)
+                .into_iter()
+                .filter_map(move |transition| {
+                    let local = DateTime::from_timestamp(transition.unix_timestamp, 0)?
+                        .with_timezone(&tz)
+                        .naive_local();
+                    // The first noon at or after the change
+                    if local.time() <= noon {
+                        Some(local.date())
+                    } else {
+                        local.date().succ_opt()
+                    }
+                })
+        })
+        .filter(|date| *date > first && *date <= last)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let difference = |date| dated_offsets(zones, date).difference_minutes;
+    let mut changes = vec![first];
+    let mut current = difference(first);
+    for date in candidates {
+        let next = difference(date);
+        if next != current {
+            changes.push(date);
+            current = next;
+        }
+    }
+    changes
+}
+
+/// Unix seconds at the start of `year` and of the year after
+fn year_bounds(year: i32) -> Result<(i64, i64), TimeServerError> {
+    let year_start = |y: i32| {
+        NaiveDate::from_ymd_opt(y, 1, 1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc().timestamp())
+            .ok_or_else(|| TimeServerError::InvalidArgument(format!("Year out of range: {}", year)))
+    };
+    Ok((year_start(year)?, year_start(year + 1)?))
+}
+
+/// Find all offset transitions of `tz` in the half-open range `[start, end)`.
+///
+/// chrono-tz does not expose its transition table, so the range is scanned in
+/// coarse steps and every step whose endpoints disagree is narrowed down to the
+/// exact second with a binary search.
+pub fn find_transitions(tz: Tz, start: i64, end: i64) -> Vec<OffsetTransition> {
+    let mut transitions = Vec::new();
+    let mut lo = start;
+    let mut lo_offset = offset_at(tz, lo);
+
+    while lo < end {
+        let hi = (lo + TRANSITION_SCAN_STEP_SECS).min(end);
+        let hi_offset = offset_at(tz, hi);
+
+        if hi_offset != lo_offset {
+            // Invariant: offset_at(left) == lo_offset, offset_at(right) != lo_offset
+            let (mut left, mut right) = (lo, hi);
+            while right - left > 1 {
+                let mid = left + (right - left) / 2;
+                if offset_at(tz, mid) == lo_offset {
+                    left = mid;
+                } else {
+                    right = mid;
+                }
+            }
+
+            if right < end {
+                let after = offset_at(tz, right);
+                transitions.push(build_transition(right, lo_offset, after));
+                lo_offset = after;
+            }
+            lo = right;
+            continue;
+        }
+
+        lo = hi;
+    }
+
+    transitions
+}
+
+fn build_transition(timestamp: i64, before: i32, after: i32) -> OffsetTransition {
+    let utc = Utc.timestamp_opt(timestamp, 0).single().unwrap_or_default();
+    let wall = |offset: i32| {
+        (utc.naive_utc() + chrono::Duration::seconds(offset as i64))
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    };
+
+    OffsetTransition {
+        utc: utc.to_rfc3339(),
+        unix_timestamp: timestamp,
+        local_before: wall(before),
+        local_after: wall(after),
+        offset_before_seconds: before,
+        offset_after_seconds: after,
+        change_minutes: (after - before) / 60,
+        kind: if after > before {
+            TransitionKind::Gap
+        } else {
+            TransitionKind::Overlap
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,8 +1271,684 @@ mod tests {
     #[test]
     fn test_list_timezones() {
         let timezones = TimezoneConverter::list_timezones();
-        assert!(timezones.len() > 100);
+        if cfg!(feature = "timezones-minimal") {
+            // The CHRONO_TZ_TIMEZONE_FILTER allowlist of .cargo/config.toml and its links
+            assert!(timezones.len() < 100, "{} zones", timezones.len());
+            assert!(timezones.contains(&"UTC".to_string()));
+        } else {
+            assert!(timezones.len() > 100);
+        }
         assert!(timezones.contains(&"America/New_York".to_string()));
         assert!(timezones.contains(&"Europe/London".to_string()));
     }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_timezone_info_uses_historical_rules() {
+        // Moscow: MSD (+4, DST) in summer 2010, permanent MSK +3 from late 2014
+        let moscow_2010 = TimezoneConverter::get_timezone_info(
+            "Europe/Moscow",
+            Some(utc("2010-07-01T12:00:00Z")),
+        )
+        .unwrap();
+        assert_eq!(moscow_2010.offset_seconds, 4 * 3600);
+        assert_eq!(moscow_2010.abbreviation, "MSD");
+        assert!(moscow_2010.is_dst);
+
+        let moscow_2015 = TimezoneConverter::get_timezone_info(
+            "Europe/Moscow",
+            Some(utc("2015-07-01T12:00:00Z")),
+        )
+        .unwrap();
+        assert_eq!(moscow_2015.offset_seconds, 3 * 3600);
+        assert_eq!(moscow_2015.abbreviation, "MSK");
+        assert!(!moscow_2015.is_dst);
+
+        // Caracas used -04:30 between December 2007 and May 2016
+        let caracas = TimezoneConverter::get_timezone_info(
+            "America/Caracas",
+            Some(utc("2012-01-01T00:00:00Z")),
+        )
+        .unwrap();
+        assert_eq!(caracas.offset_seconds, -(4 * 3600 + 1800));
+        assert_eq!(caracas.abbreviation, "-0430");
+        let caracas_now = TimezoneConverter::get_timezone_info(
+            "America/Caracas",
+            Some(utc("2020-01-01T00:00:00Z")),
+        )
+        .unwrap();
+        assert_eq!(caracas_now.offset_seconds, -4 * 3600);
+    }
+
+    #[test]
+    fn test_timezone_info_before_1970() {
+        // New York observed EDT in summer 1960
+        let info = TimezoneConverter::get_timezone_info(
+            "America/New_York",
+            Some(utc("1960-07-04T16:00:00Z")),
+        )
+        .unwrap();
+        assert_eq!(info.offset_seconds, -4 * 3600);
+        assert_eq!(info.abbreviation, "EDT");
+        assert!(info.is_dst);
+        assert!(info.at.starts_with("1960-07-04"));
+    }
+
+    #[test]
+    fn test_abbreviation_at() {
+        let winter = utc("2024-01-15T12:00:00Z");
+        let summer = utc("2024-07-15T12:00:00Z");
+        assert_eq!(
+            TimezoneConverter::abbreviation_at("Asia/Tokyo", winter).unwrap(),
+            "JST"
+        );
+        assert_eq!(
+            TimezoneConverter::abbreviation_at("Europe/Berlin", winter).unwrap(),
+            "CET"
+        );
+        assert_eq!(
+            TimezoneConverter::abbreviation_at("Europe/Berlin", summer).unwrap(),
+            "CEST"
+        );
+        assert!(TimezoneConverter::abbreviation_at("Nowhere/Land", winter).is_err());
+    }
+
+    #[test]
+    fn test_list_timezones_page_unbounded_by_default() {
+        let page = TimezoneConverter::list_timezones_page(&TimezoneListQuery::default());
+        let all = TimezoneConverter::list_timezones();
+        assert_eq!(page.count, all.len());
+        assert_eq!(page.total, all.len());
+        assert_eq!(page.offset, 0);
+        assert!(!page.has_more);
+        assert_eq!(page.timezones.as_deref(), Some(&all[..]));
+    }
+
+    #[test]
+    fn test_list_checksum_tracks_list_and_tzdata() {
+        let names = TimezoneConverter::timezone_names();
+        let version = crate::time::tzdata::tzdata_version();
+        let checksum = TimezoneConverter::timezone_list_checksum();
+        assert_eq!(checksum.len(), 16);
+        assert_eq!(checksum, list_checksum(names, version));
+        assert_eq!(
+            TimezoneConverter::list_timezones_page(&TimezoneListQuery::default()).checksum,
+            checksum
+        );
+
+        // A zone added, dropped or renamed, or a new tzdata release, changes it
+        let mut altered = names.to_vec();
+        altered.push("Mars/Olympus_Mons");
+        assert_ne!(list_checksum(&altered, version), checksum);
+        assert_ne!(list_checksum(&names[1..], version), checksum);
+        assert_ne!(list_checksum(names, "1970a"), checksum);
+        // Separators keep different splits of the same text apart
+        assert_ne!(
+            list_checksum(&["ab", "c"], ""),
+            list_checksum(&["a", "bc"], "")
+        );
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_list_timezones_page_filters_and_paging() {
+        let query = TimezoneListQuery {
+            prefix: Some("america/argentina".into()),
+            limit: Some(3),
+            ..Default::default()
+        };
+        let page = TimezoneConverter::list_timezones_page(&query);
+        assert_eq!(page.count, 3);
+        assert!(page.total > 3);
+        assert!(page.has_more);
+        assert!(page
+            .timezones
+            .unwrap()
+            .iter()
+            .all(|tz| tz.starts_with("America/Argentina/")));
+
+        // Filtering without a limit uses the default page size
+        let region = TimezoneConverter::list_timezones_page(&TimezoneListQuery {
+            region: Some("america".into()),
+            ..Default::default()
+        });
+        assert_eq!(region.count, DEFAULT_TIMEZONE_PAGE_LIMIT);
+        assert!(region.has_more);
+
+        let past_end = TimezoneConverter::list_timezones_page(&TimezoneListQuery {
+            offset: Some(10_000),
+            ..Default::default()
+        });
+        assert_eq!(past_end.count, 0);
+        assert!(!past_end.has_more);
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_list_timezones_page_grouped() {
+        let page = TimezoneConverter::list_timezones_page(&TimezoneListQuery {
+            grouped: true,
+            limit: Some(MAX_TIMEZONE_PAGE_LIMIT),
+            ..Default::default()
+        });
+        assert!(page.timezones.is_none());
+        let groups = page.groups.unwrap();
+        assert!(groups["Europe"].contains(&"London".to_string()));
+        assert!(groups["America"].contains(&"Argentina/Buenos_Aires".to_string()));
+        assert!(groups[UNGROUPED_REGION].contains(&"UTC".to_string()));
+    }
+
+    #[test]
+    fn test_list_timezones_page_detail() {
+        let query = TimezoneListQuery {
+            region: Some("asia".into()),
+            detail: true,
+            grouped: true,
+            limit: Some(MAX_TIMEZONE_PAGE_LIMIT),
+            ..Default::default()
+        };
+        let page = TimezoneConverter::list_timezones_page(&query);
+        assert!(page.timezones.is_none());
+        assert!(page.groups.is_none());
+        let zones = page.zones.unwrap();
+        assert_eq!(zones.len(), page.count);
+        assert!(zones.iter().all(|zone| zone.name.starts_with("Asia/")));
+        let kolkata = zones
+            .iter()
+            .find(|zone| zone.name == "Asia/Kolkata")
+            .unwrap();
+        assert_eq!(kolkata.offset_seconds, 19_800);
+        assert_eq!(kolkata.utc_offset, "+05:30");
+        assert!(!kolkata.is_dst);
+        assert!(kolkata.local_hour < 24);
+
+        let utc = TimezoneConverter::list_timezones_page(&TimezoneListQuery {
+            prefix: Some("UTC".into()),
+            detail: true,
+            ..Default::default()
+        });
+        let utc = &utc.zones.unwrap()[0];
+        assert_eq!(utc.name, "UTC");
+        assert_eq!(utc.offset_seconds, 0);
+        assert_eq!(utc.utc_offset, "+00:00");
+        assert!(!utc.is_dst);
+    }
+
+    #[test]
+    fn test_timezone_detail_is_cached() {
+        let query = TimezoneListQuery {
+            prefix: Some("Europe/L".into()),
+            detail: true,
+            ..Default::default()
+        };
+        let first = TimezoneConverter::list_timezones_page(&query);
+        let second = TimezoneConverter::list_timezones_page(&query);
+        assert!(first.captured_at.is_some());
+        assert_eq!(first.captured_at, second.captured_at);
+        assert_eq!(first.zones, second.zones);
+
+        let now = Utc::now();
+        let snapshot = detail_snapshot(now);
+        assert!(Arc::ptr_eq(
+            &snapshot,
+            &detail_snapshot(now + chrono::Duration::seconds(TIMEZONE_DETAIL_TTL_SECS - 1)),
+        ));
+        let later = detail_snapshot(now + chrono::Duration::seconds(TIMEZONE_DETAIL_TTL_SECS));
+        assert!(!Arc::ptr_eq(&snapshot, &later));
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_resolve_name() {
+        assert_eq!(
+            TimezoneConverter::resolve_name("America/New York").as_deref(),
+            Some("America/New_York")
+        );
+        assert_eq!(
+            TimezoneConverter::resolve_name("europe/london").as_deref(),
+            Some("Europe/London")
+        );
+        assert_eq!(
+            TimezoneConverter::resolve_name("America/Argentina/Buenos_Aires").as_deref(),
+            Some("America/Argentina/Buenos_Aires")
+        );
+        assert_eq!(TimezoneConverter::resolve_name("Invalid/Zone"), None);
+        assert_eq!(TimezoneConverter::resolve_name(""), None);
+    }
+
+    #[test]
+    fn test_suggest_timezones() {
+        let suggestions = TimezoneConverter::suggest_timezones("America/New_Yrok", 5);
+        assert_eq!(
+            suggestions.first().map(String::as_str),
+            Some("America/New_York")
+        );
+
+        let suggestions = TimezoneConverter::suggest_timezones("tokyo", 5);
+        assert!(suggestions.contains(&"Asia/Tokyo".to_string()));
+
+        assert!(TimezoneConverter::suggest_timezones("", 5).is_empty());
+        assert!(TimezoneConverter::suggest_timezones("qqqqqqqqqqqqqqqq", 5).is_empty());
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_dst_transitions_new_york_2024() {
+        let transitions = TimezoneConverter::dst_transitions("America/New_York", 2024).unwrap();
+        assert_eq!(transitions.len(), 2);
+
+        // 2024-03-10 02:00 EST -> 03:00 EDT
+        assert_eq!(transitions[0].utc, "2024-03-10T07:00:00+00:00");
+        assert_eq!(transitions[0].local_before, "2024-03-10T02:00:00");
+        assert_eq!(transitions[0].local_after, "2024-03-10T03:00:00");
+        assert_eq!(transitions[0].change_minutes, 60);
+        assert_eq!(transitions[0].kind, TransitionKind::Gap);
+
+        // 2024-11-03 02:00 EDT -> 01:00 EST
+        assert_eq!(transitions[1].utc, "2024-11-03T06:00:00+00:00");
+        assert_eq!(transitions[1].local_before, "2024-11-03T02:00:00");
+        assert_eq!(transitions[1].local_after, "2024-11-03T01:00:00");
+        assert_eq!(transitions[1].change_minutes, -60);
+        assert_eq!(transitions[1].kind, TransitionKind::Overlap);
+    }
+
+    #[test]
+    fn test_dst_transitions_london_2024() {
+        let transitions = TimezoneConverter::dst_transitions("Europe/London", 2024).unwrap();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].utc, "2024-03-31T01:00:00+00:00");
+        assert_eq!(transitions[0].offset_after_seconds, 3600);
+        assert_eq!(transitions[1].utc, "2024-10-27T01:00:00+00:00");
+        assert_eq!(transitions[1].offset_after_seconds, 0);
+    }
+
+    #[test]
+    fn test_dst_transitions_sydney_southern_hemisphere() {
+        let transitions = TimezoneConverter::dst_transitions("Australia/Sydney", 2024).unwrap();
+        assert_eq!(transitions.len(), 2);
+
+        // Clocks go back in April and forward in October
+        assert_eq!(transitions[0].utc, "2024-04-06T16:00:00+00:00");
+        assert_eq!(transitions[0].kind, TransitionKind::Overlap);
+        assert_eq!(transitions[0].local_before, "2024-04-07T03:00:00");
+        assert_eq!(transitions[1].utc, "2024-10-05T16:00:00+00:00");
+        assert_eq!(transitions[1].kind, TransitionKind::Gap);
+        assert_eq!(transitions[1].local_after, "2024-10-06T03:00:00");
+    }
+
+    #[test]
+    fn test_local_to_utc_ambiguous_fall_back() {
+        let zone = "America/New_York";
+        let earlier = TimezoneConverter::local_to_utc(
+            "2024-11-03T01:30",
+            zone,
+            AmbiguityPolicy::Earlier,
+            GapPolicy::Reject,
+        )
+        .unwrap();
+        assert_eq!(earlier.status, LocalTimeStatus::Ambiguous);
+        assert_eq!(earlier.candidates.len(), 2);
+        assert_eq!(earlier.utc, "2024-11-03T05:30:00+00:00");
+        assert_eq!(earlier.offset_seconds, -4 * 3600);
+        assert_eq!(earlier.policy_applied.as_deref(), Some("earlier"));
+
+        let later = TimezoneConverter::local_to_utc(
+            "2024-11-03 01:30:00",
+            zone,
+            AmbiguityPolicy::Later,
+            GapPolicy::Reject,
+        )
+        .unwrap();
+        assert_eq!(later.utc, "2024-11-03T06:30:00+00:00");
+        assert_eq!(later.offset_seconds, -5 * 3600);
+        assert_eq!(later.unix_timestamp - earlier.unix_timestamp, 3600);
+
+        let rejected = TimezoneConverter::local_to_utc(
+            "2024-11-03T01:30",
+            zone,
+            AmbiguityPolicy::Reject,
+            GapPolicy::Reject,
+        );
+        assert!(rejected.unwrap_err().to_string().contains("Ambiguous"));
+    }
+
+    #[test]
+    fn test_local_to_utc_nonexistent_spring_forward() {
+        let zone = "America/New_York";
+        let rejected = TimezoneConverter::local_to_utc(
+            "2024-03-10T02:30",
+            zone,
+            AmbiguityPolicy::Earlier,
+            GapPolicy::Reject,
+        );
+        assert!(rejected.unwrap_err().to_string().contains("Nonexistent"));
+
+        let shifted = TimezoneConverter::local_to_utc(
+            "2024-03-10T02:30",
+            zone,
+            AmbiguityPolicy::Earlier,
+            GapPolicy::ShiftForward,
+        )
+        .unwrap();
+        assert_eq!(shifted.status, LocalTimeStatus::Nonexistent);
+        assert_eq!(shifted.policy_applied.as_deref(), Some("shift_forward"));
+        // 02:30 does not exist; shifted by the one hour gap to 03:30 EDT
+        assert_eq!(shifted.utc, "2024-03-10T07:30:00+00:00");
+        assert!(shifted.candidates.is_empty());
+    }
+
+    #[test]
+    fn test_local_to_utc_explains_policy_choice() {
+        let mut explain = Explain::new(true);
+        let resolved = TimezoneConverter::local_to_utc_explained(
+            "2024-11-03T01:30",
+            "America/New_York",
+            AmbiguityPolicy::Earlier,
+            GapPolicy::Reject,
+            &mut explain,
+        )
+        .unwrap();
+        let steps = explain.finish().unwrap();
+        let kinds: Vec<&str> = steps.iter().map(|s| s.step).collect();
+        assert_eq!(kinds, ["input", "dst", "offset_rule", "arithmetic"]);
+
+        let dst = &steps[1];
+        assert_eq!(dst.details["policy"], "earlier");
+        assert_eq!(dst.details["chosen"]["utc"], resolved.utc);
+        assert_eq!(dst.details["candidates"].as_array().unwrap().len(), 2);
+        assert!(dst
+            .text
+            .contains("policy 'earlier' chose 2024-11-03T05:30:00+00:00"));
+        // The earlier reading is the last hour of daylight time
+        assert_eq!(steps[2].details["abbreviation"], "EDT");
+        assert_eq!(steps[2].details["period_end"], "2024-11-03T06:00:00+00:00");
+        assert_eq!(
+            steps[3].text,
+            "2024-11-03T01:30:00 - (-04:00) = 2024-11-03T05:30:00Z"
+        );
+
+        let mut explain = Explain::new(true);
+        TimezoneConverter::local_to_utc_explained(
+            "2024-03-10T02:30",
+            "America/New_York",
+            AmbiguityPolicy::Earlier,
+            GapPolicy::ShiftForward,
+            &mut explain,
+        )
+        .unwrap();
+        let steps = explain.finish().unwrap();
+        assert_eq!(steps[1].details["status"], "nonexistent");
+        assert_eq!(steps[1].details["shifted"], "2024-03-10T03:30:00");
+    }
+
+    #[test]
+    fn test_local_to_utc_normal_time() {
+        let resolved = TimezoneConverter::local_to_utc(
+            "2024-07-04T12:00:00",
+            "Europe/London",
+            AmbiguityPolicy::Reject,
+            GapPolicy::Reject,
+        )
+        .unwrap();
+        assert_eq!(resolved.status, LocalTimeStatus::Unique);
+        assert_eq!(resolved.utc, "2024-07-04T11:00:00+00:00");
+        assert_eq!(resolved.policy_applied, None);
+
+        assert!(TimezoneConverter::local_to_utc(
+            "2024-07-04T12:00:00+01:00",
+            "Europe/London",
+            AmbiguityPolicy::Earlier,
+            GapPolicy::Reject,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dst_transitions_fixed_offset_zone() {
+        assert!(TimezoneConverter::dst_transitions("Asia/Tokyo", 2024)
+            .unwrap()
+            .is_empty());
+        assert!(TimezoneConverter::dst_transitions("Etc/GMT-5", 2024)
+            .unwrap()
+            .is_empty());
+        assert!(TimezoneConverter::dst_transitions("Invalid/Zone", 2024).is_err());
+    }
+
+    #[test]
+    fn test_observes_dst() {
+        assert!(TimezoneConverter::observes_dst("Europe/London", 2024).unwrap());
+        assert!(TimezoneConverter::observes_dst("Australia/Sydney", 2024).unwrap());
+        assert!(!TimezoneConverter::observes_dst("Asia/Tokyo", 2024).unwrap());
+        assert!(TimezoneConverter::observes_dst("Invalid/Zone", 2024).is_err());
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_observes_dst_ignores_standard_offset_changes() {
+        // Moscow moved from +4 to +3 standard time in October 2014
+        assert!(!TimezoneConverter::dst_transitions("Europe/Moscow", 2014)
+            .unwrap()
+            .is_empty());
+        assert!(!TimezoneConverter::observes_dst("Europe/Moscow", 2014).unwrap());
+        assert!(TimezoneConverter::observes_dst("Europe/Moscow", 2010).unwrap());
+    }
+
+    fn unix(s: &str) -> i64 {
+        utc(s).timestamp()
+    }
+
+    #[test]
+    fn test_offset_timeline_new_york_year() {
+        let start = unix("2024-01-01T00:00:00Z");
+        let end = unix("2025-01-01T00:00:00Z");
+        let segments = TimezoneConverter::offset_timeline("America/New_York", start, end).unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].from_unix, start);
+        assert_eq!(segments[2].to_unix, end);
+        assert_eq!(segments[1].from_utc, "2024-03-10T07:00:00+00:00");
+        assert_eq!(segments[1].abbreviation, "EDT");
+        assert!(segments[1].is_dst);
+        assert_eq!(segments[2].offset_seconds, -5 * 3600);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].to_unix, pair[1].from_unix);
+        }
+    }
+
+    #[test]
+    fn test_offset_timeline_fixed_offset_zone() {
+        let start = unix("2020-01-01T00:00:00Z");
+        let segments = TimezoneConverter::offset_timeline(
+            "Asia/Tokyo",
+            start,
+            start + MAX_TIMELINE_RANGE_SECS,
+        )
+        .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].offset_seconds, 9 * 3600);
+
+        assert!(TimezoneConverter::offset_timeline(
+            "Asia/Tokyo",
+            start,
+            start + MAX_TIMELINE_RANGE_SECS + 1
+        )
+        .is_err());
+        assert!(TimezoneConverter::offset_timeline("Asia/Tokyo", start, start).is_err());
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_london_sydney_gap_moves_in_late_march() {
+        // London starts BST on March 31, Sydney ends AEDT on April 7
+        let difference = TimezoneConverter::offset_difference(
+            "Europe/London",
+            "Australia/Sydney",
+            date("2024-03-30"),
+        )
+        .unwrap();
+        assert_eq!(difference.offsets.from_offset_seconds, 0);
+        assert_eq!(difference.offsets.to_offset_seconds, 11 * 3600);
+        assert_eq!(difference.offsets.difference_minutes, 660);
+        assert!(difference.changes_in_window);
+        let changes: Vec<(&str, i32)> = difference
+            .changes
+            .iter()
+            .map(|c| (c.date.as_str(), c.difference_minutes))
+            .collect();
+        assert_eq!(changes, [("2024-03-31", 600), ("2024-04-07", 540)]);
+
+        // The change date itself reads the new offset: noon is past 01:00 UTC
+        let on_change = TimezoneConverter::offset_difference(
+            "Europe/London",
+            "Australia/Sydney",
+            date("2024-03-31"),
+        )
+        .unwrap();
+        assert_eq!(on_change.offsets.difference_minutes, 600);
+
+        let segments = TimezoneConverter::offset_difference_range(
+            "Europe/London",
+            "Australia/Sydney",
+            date("2024-03-01"),
+            date("2024-04-30"),
+        )
+        .unwrap();
+        let segments: Vec<(&str, &str, i32)> = segments
+            .iter()
+            .map(|s| {
+                (
+                    s.from_date.as_str(),
+                    s.to_date.as_str(),
+                    s.difference_minutes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                ("2024-03-01", "2024-03-30", 660),
+                ("2024-03-31", "2024-04-06", 600),
+                ("2024-04-07", "2024-04-30", 540),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_offset_zones_never_differ() {
+        let difference = TimezoneConverter::offset_difference(
+            "Europe/Berlin",
+            "Europe/Paris",
+            date("2024-03-31"),
+        )
+        .unwrap();
+        assert_eq!(difference.offsets.difference_minutes, 0);
+        assert!(!difference.changes_in_window);
+
+        // Both switch at the same instant, so the year is one segment
+        let segments = TimezoneConverter::offset_difference_range(
+            "Europe/Berlin",
+            "Europe/Paris",
+            date("2024-01-01"),
+            date("2024-12-31"),
+        )
+        .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].to_date, "2024-12-31");
+    }
+
+    #[test]
+    fn test_offset_difference_errors() {
+        let error = TimezoneConverter::offset_difference(
+            "Europe/London",
+            "Europe/Londn",
+            date("2024-03-30"),
+        )
+        .unwrap_err();
+        assert!(matches!(error, TimeServerError::InvalidTimezone { .. }));
+
+        let range = |start, end| {
+            TimezoneConverter::offset_difference_range("UTC", "Asia/Tokyo", date(start), date(end))
+        };
+        assert!(range("2024-03-02", "2024-03-01").is_err());
+        assert_eq!(range("2024-03-01", "2024-03-01").unwrap().len(), 1);
+        assert!(range("2000-01-01", "2024-01-01").is_err());
+    }
+
+    #[test]
+    fn test_conversion_origin_renders_in_from_timezone() {
+        let (utc, origin) = TimezoneConverter::conversion_origin(
+            Some(1704067200),
+            None,
+            Some("America/Chicago"),
+            AmbiguityPolicy::default(),
+            GapPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(utc.timestamp(), 1704067200);
+        assert_eq!(origin.formatted, "2023-12-31T18:00:00-06:00");
+        assert_eq!(origin.offset, -6 * 3600);
+        assert!(origin.status.is_none());
+
+        // 01:30 happens twice in Chicago on 2024-11-03
+        let (utc, origin) = TimezoneConverter::conversion_origin(
+            None,
+            Some("2024-11-03T01:30:00"),
+            Some("America/Chicago"),
+            AmbiguityPolicy::Later,
+            GapPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(utc.to_rfc3339(), "2024-11-03T07:30:00+00:00");
+        assert_eq!(origin.formatted, "2024-11-03T01:30:00-06:00");
+        assert_eq!(origin.status, Some(LocalTimeStatus::Ambiguous));
+        assert_eq!(origin.policy_applied.as_deref(), Some("later"));
+
+        let bad = TimezoneConverter::conversion_origin(
+            Some(0),
+            None,
+            Some("America/Chicgo"),
+            AmbiguityPolicy::default(),
+            GapPolicy::default(),
+        );
+        assert_eq!(bad.unwrap_err().code(), "invalid_timezone");
+        for (timestamp, local) in [(None, None), (Some(0), Some("2024-01-01T00:00"))] {
+            let error = TimezoneConverter::conversion_origin(
+                timestamp,
+                local,
+                None,
+                AmbiguityPolicy::default(),
+                GapPolicy::default(),
+            )
+            .unwrap_err();
+            assert_eq!(error.code(), "invalid_argument");
+        }
+    }
+
+    #[test]
+    fn test_offset_timeline_base_offset_change() {
+        // Moscow stayed on +04 from March 2011 until 26 October 2014
+        let segments = TimezoneConverter::offset_timeline(
+            "Europe/Moscow",
+            unix("2014-06-01T00:00:00Z"),
+            unix("2015-06-01T00:00:00Z"),
+        )
+        .unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].offset_seconds, 4 * 3600);
+        assert!(!segments[0].is_dst);
+        assert_eq!(segments[1].offset_seconds, 3 * 3600);
+        assert_eq!(segments[1].from_utc, "2014-10-25T22:00:00+00:00");
+    }
 }
@@ -0,0 +1,81 @@
+// Caveat for timezone results far in the future
+//
+// Offsets and DST dates come from the tz database compiled into chrono-tz.
+// Governments change their rules, sometimes with weeks of notice, so a
+// result for an instant years ahead is only as good as today's rules.
+// Tools that return timezone-dependent data attach `tzdata_caveat` once the
+// instant is more than `TZDATA_CAVEAT_DAYS` ahead.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Days ahead after which results carry the caveat
+pub const DEFAULT_TZDATA_CAVEAT_DAYS: u32 = 365;
+
+/// IANA tz database release compiled into chrono-tz
+pub fn tzdata_version() -> &'static str {
+    chrono_tz::IANA_TZDB_VERSION
+}
+
+/// A warning when `at` is more than `threshold_days` after `now`
+pub fn tzdata_caveat(at: DateTime<Utc>, now: DateTime<Utc>, threshold_days: u32) -> Option<String> {
+    let days = (at - now).num_days();
+    (days > threshold_days as i64).then(|| {
+        format!(
+            "{} is {} days ahead; computed with tzdata {}, and the timezone's rules may change before then",
+            at.format("%Y-%m-%d"),
+            days,
+            tzdata_version()
+        )
+    })
+}
+
+/// A response body with the caveat alongside it
+#[derive(Debug, Serialize)]
+pub struct WithCaveat<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tzdata_caveat: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caveat_threshold() {
+        let now: DateTime<Utc> = "2025-01-01T00:00:00Z".parse().unwrap();
+        let days = |n: i64| now + chrono::Duration::days(n);
+
+        assert!(tzdata_caveat(days(365), now, 365).is_none());
+        assert!(tzdata_caveat(days(-4000), now, 365).is_none());
+        let caveat = tzdata_caveat(days(366), now, 365).unwrap();
+        assert!(
+            caveat.starts_with("2026-01-02 is 366 days ahead"),
+            "{}",
+            caveat
+        );
+        assert!(caveat.contains(tzdata_version()));
+        assert!(tzdata_caveat(days(1), now, 0).is_some());
+    }
+
+    #[test]
+    fn test_caveat_is_omitted_from_json_when_absent() {
+        let body = WithCaveat {
+            body: serde_json::json!({"utc": "x"}),
+            tzdata_caveat: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            serde_json::json!({"utc": "x"})
+        );
+    }
+
+    #[test]
+    fn test_tzdata_version_looks_like_a_release() {
+        let version = tzdata_version();
+        assert_eq!(version.len(), 5, "{}", version);
+        assert!(version[..4].parse::<u16>().unwrap() >= 2024);
+    }
+}
@@ -1,32 +1,102 @@
 // Unix timestamp with nanosecond precision
+//
+// Timestamps a caller supplies are accepted for the years 1 to 9999 UTC
+// (`MIN_SECONDS` to `MAX_SECONDS`); anything else is a
+// `timestamp_out_of_range` error rather than a wrapped or clamped value.
+// Within that range nanoseconds since the epoch need more than 64 bits, so
+// they are kept as i128 and every conversion to a narrower unit is checked.
+//
+// A JSON number is a double to most clients, exact only up to 2^53 - 1, and
+// nanosecond counts have been past that since 1970-04-15. They are therefore
+// written as strings beyond `MAX_SAFE_INTEGER`; `JSON_NANOS_AS_NUMBER=true`
+// keeps them numeric (while they fit in 64 bits) for existing consumers.
 
-use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::TimeServerError;
+use chrono::{DateTime, Utc};
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Earliest supported instant, 0001-01-01T00:00:00Z
+pub const MIN_SECONDS: i64 = -62_135_596_800;
+
+/// Latest supported second, 9999-12-31T23:59:59Z
+pub const MAX_SECONDS: i64 = 253_402_300_799;
+
+pub const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Largest integer every JSON parser reads exactly (2^53 - 1)
+pub const MAX_SAFE_INTEGER: i128 = 9_007_199_254_740_991;
 
 /// Unix timestamp with nanosecond precision
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct UnixTime {
     /// Seconds since Unix epoch (1970-01-01 00:00:00 UTC)
     pub seconds: i64,
     /// Nanoseconds within the current second (0-999999999)
     pub nanos: u32,
     /// Combined nanoseconds since epoch
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    #[schemars(schema_with = "nanos_schema")]
     pub nanos_since_epoch: i128,
 }
 
+/// The `get_nanos` reading
+#[derive(Debug, Clone, Serialize)]
+pub struct NanosReading {
+    #[serde(serialize_with = "serialize_nanos")]
+    pub nanoseconds: i128,
+    pub seconds: i64,
+    pub subsec_nanos: u32,
+}
+
 impl UnixTime {
     pub fn now() -> Self {
-        let duration = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before Unix epoch");
+        Self::from_datetime(crate::chaos::now())
+    }
 
+    /// `dt` as a Unix time; a leap second (":60") reads as a repeat of the
+    /// second before it, as POSIX counts it
+    pub fn from_datetime(dt: DateTime<Utc>) -> Self {
+        let seconds = dt.timestamp();
+        let nanos = dt.timestamp_subsec_nanos() % NANOS_PER_SECOND as u32;
         Self {
-            seconds: duration.as_secs() as i64,
-            nanos: duration.subsec_nanos(),
-            nanos_since_epoch: duration.as_nanos() as i128,
+            seconds,
+            nanos,
+            nanos_since_epoch: seconds as i128 * NANOS_PER_SECOND + nanos as i128,
         }
     }
 
+    /// `seconds` plus `nanos`, within the supported range
+    pub fn from_parts(seconds: i64, nanos: u32) -> Result<Self, TimeServerError> {
+        if nanos >= NANOS_PER_SECOND as u32 {
+            return Err(TimeServerError::InvalidTimestamp(format!(
+                "nanoseconds must be below 1000000000, got {}",
+                nanos
+            )));
+        }
+        check_seconds(seconds)?;
+        Ok(Self {
+            seconds,
+            nanos,
+            nanos_since_epoch: seconds as i128 * NANOS_PER_SECOND + nanos as i128,
+        })
+    }
+
+    /// The instant `nanos` nanoseconds after the epoch, within the supported range
+    pub fn from_nanos(nanos: i128) -> Result<Self, TimeServerError> {
+        let seconds = i64::try_from(nanos.div_euclid(NANOS_PER_SECOND))
+            .map_err(|_| TimeServerError::out_of_range(nanos.to_string()))?;
+        check_seconds(seconds).map_err(|_| TimeServerError::out_of_range(nanos.to_string()))?;
+        Self::from_parts(seconds, nanos.rem_euclid(NANOS_PER_SECOND) as u32)
+    }
+
+    pub fn to_datetime(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.seconds, self.nanos).unwrap_or_default()
+    }
+
     pub fn to_timespec(&self) -> libc::timespec {
         libc::timespec {
             tv_sec: self.seconds,
@@ -34,18 +104,101 @@ impl UnixTime {
         }
     }
 
-    pub fn to_microseconds(&self) -> i64 {
-        self.seconds * 1_000_000 + (self.nanos as i64 / 1000)
+    /// Microseconds since the epoch; an error where that overflows i64
+    pub fn to_microseconds(&self) -> Result<i64, TimeServerError> {
+        self.scaled(1_000_000)
+    }
+
+    /// Milliseconds since the epoch; an error where that overflows i64
+    pub fn to_milliseconds(&self) -> Result<i64, TimeServerError> {
+        self.scaled(1000)
+    }
+
+    /// Nanoseconds since the epoch as i64, which ends at 2262-04-11
+    pub fn to_nanoseconds(&self) -> Result<i64, TimeServerError> {
+        self.scaled(NANOS_PER_SECOND as i64)
+    }
+
+    fn scaled(&self, per_second: i64) -> Result<i64, TimeServerError> {
+        // Exact in i128 for any i64 seconds; only the narrowing can fail
+        let nanos = self.seconds as i128 * NANOS_PER_SECOND + self.nanos as i128;
+        i64::try_from(nanos.div_euclid(NANOS_PER_SECOND / per_second as i128))
+            .map_err(|_| TimeServerError::out_of_range(self.seconds.to_string()))
+    }
+
+    pub fn nanos_reading(&self) -> NanosReading {
+        NanosReading {
+            nanoseconds: self.nanos_since_epoch,
+            seconds: self.seconds,
+            subsec_nanos: self.nanos,
+        }
+    }
+}
+
+/// `seconds` if it is within the supported range (years 1 to 9999)
+pub fn check_seconds(seconds: i64) -> Result<i64, TimeServerError> {
+    if (MIN_SECONDS..=MAX_SECONDS).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(TimeServerError::out_of_range(seconds.to_string()))
     }
+}
+
+/// The instant of a caller-supplied Unix timestamp in seconds
+pub fn datetime_at(seconds: i64) -> Result<DateTime<Utc>, TimeServerError> {
+    check_seconds(seconds)?;
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| TimeServerError::out_of_range(seconds.to_string()))
+}
 
-    pub fn to_milliseconds(&self) -> i64 {
-        self.seconds * 1000 + (self.nanos as i64 / 1_000_000)
+/// A nanosecond count: a number when JSON carries it exactly, otherwise a
+/// string unless numbers were asked for and it fits in 64 bits
+pub fn serialize_nanos<S: Serializer>(nanos: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+    match nanos_as_number(*nanos) {
+        Some(number) => serializer.serialize_i64(number),
+        None => serializer.collect_str(nanos),
+    }
+}
+
+/// The count as [`serialize_nanos`] writes a number, or None for a string
+pub fn nanos_as_number(nanos: i128) -> Option<i64> {
+    i64::try_from(nanos)
+        .ok()
+        .filter(|_| nanos.abs() <= MAX_SAFE_INTEGER || crate::output::nanos_as_numbers())
+}
+
+/// Schema of a count written by [`serialize_nanos`]
+pub fn nanos_schema(_: &mut SchemaGenerator) -> Schema {
+    schemars::json_schema!({
+        "anyOf": [
+            { "type": "integer" },
+            { "type": "string", "pattern": "^-?[0-9]+$" }
+        ]
+    })
+}
+
+/// A nanosecond count written by [`serialize_nanos`], as a number or a string
+pub fn deserialize_nanos<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Nanos {
+        // Larger counts are written as strings
+        Number(i64),
+        Text(String),
+    }
+    match Nanos::deserialize(deserializer)? {
+        Nanos::Number(nanos) => Ok(nanos.into()),
+        Nanos::Text(text) => text.trim().parse().map_err(serde::de::Error::custom),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    /// i64::MAX nanoseconds: 2262-04-11T23:47:16.854775807Z
+    const I64_NANOS_LAST_SECOND: i64 = 9_223_372_036;
 
     #[test]
     fn test_unix_time_precision() {
@@ -61,14 +214,149 @@ mod tests {
         assert_eq!(timespec.tv_nsec as u32, unix_time.nanos);
     }
 
+    #[test]
+    fn test_from_datetime_before_epoch() {
+        let dt = DateTime::from_timestamp(-2, 250_000_000).unwrap();
+        let unix_time = UnixTime::from_datetime(dt);
+        assert_eq!(unix_time.seconds, -2);
+        assert_eq!(unix_time.nanos, 250_000_000);
+        assert_eq!(unix_time.nanos_since_epoch, -1_750_000_000);
+    }
+
     #[test]
     fn test_time_conversions() {
         let unix_time = UnixTime::now();
 
-        let micros = unix_time.to_microseconds();
-        let millis = unix_time.to_milliseconds();
+        let micros = unix_time.to_microseconds().unwrap();
+        let millis = unix_time.to_milliseconds().unwrap();
 
         assert!(micros > unix_time.seconds * 1_000_000);
         assert!(millis > unix_time.seconds * 1000);
     }
+
+    #[test]
+    fn test_supported_range_bounds() {
+        assert_eq!(
+            datetime_at(MIN_SECONDS).unwrap().to_rfc3339(),
+            "0001-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            datetime_at(MAX_SECONDS).unwrap().to_rfc3339(),
+            "9999-12-31T23:59:59+00:00"
+        );
+        for seconds in [MIN_SECONDS - 1, MAX_SECONDS + 1, i64::MIN, i64::MAX] {
+            let error = datetime_at(seconds).unwrap_err();
+            assert_eq!(error.code(), "timestamp_out_of_range");
+        }
+    }
+
+    #[test]
+    fn test_nanoseconds_end_at_2262() {
+        let last = UnixTime::from_parts(I64_NANOS_LAST_SECOND, 854_775_807).unwrap();
+        assert_eq!(last.to_nanoseconds().unwrap(), i64::MAX);
+        assert_eq!(last.nanos_since_epoch, i64::MAX as i128);
+
+        let next = UnixTime::from_parts(I64_NANOS_LAST_SECOND, 854_775_808).unwrap();
+        assert!(next.to_nanoseconds().is_err());
+        assert_eq!(next.nanos_since_epoch, i64::MAX as i128 + 1);
+        assert_eq!(
+            next.to_datetime().to_rfc3339(),
+            "2262-04-11T23:47:16.854775808+00:00"
+        );
+        // Microseconds and milliseconds still fit
+        assert_eq!(next.to_microseconds().unwrap(), 9_223_372_036_854_775);
+    }
+
+    #[test]
+    fn test_large_nanos_serialize_as_strings() {
+        let early = UnixTime::from_parts(9_007_199, 254_740_991).unwrap();
+        let value = serde_json::to_value(&early).unwrap();
+        assert_eq!(value["nanos_since_epoch"], 9_007_199_254_740_991_i64);
+
+        let late = UnixTime::from_parts(MAX_SECONDS, 999_999_999).unwrap();
+        let value = serde_json::to_value(&late).unwrap();
+        assert_eq!(value["nanos_since_epoch"], "253402300799999999999");
+        let back: UnixTime = serde_json::from_value(value).unwrap();
+        assert_eq!(back, late);
+
+        let numeric: UnixTime = serde_json::from_str(
+            r#"{"seconds":1735601415,"nanos":123456789,"nanos_since_epoch":1735601415123456789}"#,
+        )
+        .unwrap();
+        assert_eq!(numeric.nanos_since_epoch, 1_735_601_415_123_456_789);
+    }
+
+    #[test]
+    fn test_numbers_kept_on_request_while_they_fit() {
+        let now = UnixTime::from_parts(1_735_601_415, 123_456_789).unwrap();
+        let late = UnixTime::from_parts(MAX_SECONDS, 0).unwrap();
+        crate::output::with_nanos_as_numbers(|| {
+            let value = serde_json::to_value(&now).unwrap();
+            assert_eq!(value["nanos_since_epoch"], 1_735_601_415_123_456_789_i64);
+            // Past 2262 a JSON number cannot hold it at all
+            let value = serde_json::to_value(&late).unwrap();
+            assert_eq!(value["nanos_since_epoch"], "253402300799000000000");
+        });
+        let value = serde_json::to_value(&now).unwrap();
+        assert_eq!(value["nanos_since_epoch"], "1735601415123456789");
+    }
+
+    fn any_seconds() -> impl Strategy<Value = i64> {
+        prop_oneof![
+            Just(i64::MIN),
+            Just(i64::MAX),
+            Just(MIN_SECONDS - 1),
+            Just(MIN_SECONDS),
+            Just(MAX_SECONDS),
+            Just(MAX_SECONDS + 1),
+            Just(I64_NANOS_LAST_SECOND),
+            Just(-I64_NANOS_LAST_SECOND - 1),
+            (I64_NANOS_LAST_SECOND - 2)..(I64_NANOS_LAST_SECOND + 2),
+            (MIN_SECONDS - 10)..(MIN_SECONDS + 10),
+            (MAX_SECONDS - 10)..(MAX_SECONDS + 10),
+            any::<i64>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_every_conversion_is_exact_or_an_error(
+            seconds in any_seconds(),
+            nanos in 0u32..1_000_000_000,
+        ) {
+            let supported = (MIN_SECONDS..=MAX_SECONDS).contains(&seconds);
+            let Ok(time) = UnixTime::from_parts(seconds, nanos) else {
+                prop_assert!(!supported);
+                return Ok(());
+            };
+            prop_assert!(supported);
+
+            let exact = seconds as i128 * NANOS_PER_SECOND + nanos as i128;
+            prop_assert_eq!(time.nanos_since_epoch, exact);
+            prop_assert_eq!(&UnixTime::from_nanos(exact).unwrap(), &time);
+            prop_assert_eq!(&UnixTime::from_datetime(time.to_datetime()), &time);
+            for (result, unit) in [
+                (time.to_milliseconds(), 1_000_000),
+                (time.to_microseconds(), 1000),
+                (time.to_nanoseconds(), 1),
+            ] {
+                let expected = exact.div_euclid(unit);
+                match result {
+                    Ok(value) => prop_assert_eq!(value as i128, expected),
+                    Err(_) => prop_assert!(i64::try_from(expected).is_err()),
+                }
+            }
+
+            let json = serde_json::to_string(&time).unwrap();
+            let back: UnixTime = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(back, time);
+        }
+
+        #[test]
+        fn prop_out_of_range_nanos_are_rejected(nanos in any::<i128>()) {
+            let seconds = nanos.div_euclid(NANOS_PER_SECOND);
+            let supported = (MIN_SECONDS as i128..=MAX_SECONDS as i128).contains(&seconds);
+            prop_assert_eq!(UnixTime::from_nanos(nanos).is_ok(), supported);
+        }
+    }
 }
@@ -1,11 +1,23 @@
 // Enhanced UTC time response with Unix/POSIX features
 
-use super::{StandardFormats, StrftimeFormatter, TimezoneConverter, UnixTime};
+use super::{FormatTemplates, StrftimeFormatter, TimezoneConverter, UnixTime};
+use crate::error::TimeServerError;
 use chrono::{DateTime, Datelike, Offset, SecondsFormat, Timelike, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EnhancedTimeResponse {
     // Unix epoch times
     pub unix: UnixTime,
@@ -13,10 +25,18 @@ pub struct EnhancedTimeResponse {
     // Standard formats
     pub iso8601: String,
     pub rfc3339: String,
+    /// RFC 9557: RFC 3339 plus the zone annotation ("...+09:00[Asia/Tokyo]")
+    #[serde(default)]
+    pub rfc9557: String,
     pub rfc2822: String,
     pub ctime: String,
 
     // Nanosecond precision
+    #[serde(
+        serialize_with = "super::unix::serialize_nanos",
+        deserialize_with = "super::unix::deserialize_nanos"
+    )]
+    #[schemars(schema_with = "super::unix::nanos_schema")]
     pub nanos_since_epoch: i128,
     pub seconds: i64,
     pub microseconds: i64,
@@ -40,43 +60,47 @@ pub struct EnhancedTimeResponse {
     pub week_of_year: u32,
     pub day_of_year: u32,
 
-    // Custom formats
-    pub custom_formats: HashMap<String, String>,
+    // Custom formats, serialized in key order
+    pub custom_formats: BTreeMap<String, String>,
 }
 
 impl EnhancedTimeResponse {
+    /// The current time, read once: every field describes the same instant
     pub fn now() -> Self {
-        let now_utc = Utc::now();
-        let unix_time = UnixTime::now();
+        Self::at(crate::chaos::now())
+    }
+
+    /// Response describing `now_utc` rather than the current system time
+    ///
+    /// Every field is derived from the one `UnixTime`, so the strings, the
+    /// components and `seconds` agree even for a leap second.
+    pub fn at(now_utc: DateTime<Utc>) -> Self {
+        let unix_time = UnixTime::from_datetime(now_utc);
+        let now_utc = unix_time.to_datetime();
 
-        let mut custom_formats = HashMap::new();
+        let templates = FormatTemplates::global();
+        let render = |items| FormatTemplates::render(&now_utc, items);
 
         // Add common Unix formats
-        custom_formats.insert(
-            "unix_date".to_string(),
-            StrftimeFormatter::format(&now_utc, StandardFormats::UNIX_DATE).unwrap_or_default(),
-        );
-        custom_formats.insert(
-            "syslog".to_string(),
-            StrftimeFormatter::format(&now_utc, StandardFormats::SYSLOG).unwrap_or_default(),
-        );
-        custom_formats.insert(
-            "apache_log".to_string(),
-            StrftimeFormatter::format(&now_utc, StandardFormats::APACHE_LOG).unwrap_or_default(),
-        );
+        let mut custom_formats = BTreeMap::new();
+        custom_formats.insert("unix_date".to_string(), render(&templates.unix_date));
+        custom_formats.insert("syslog".to_string(), render(&templates.syslog));
+        custom_formats.insert("apache_log".to_string(), render(&templates.apache_log));
         custom_formats.insert("unix_timestamp".to_string(), unix_time.seconds.to_string());
 
         Self {
             unix: unix_time.clone(),
             iso8601: now_utc.to_rfc3339_opts(SecondsFormat::Nanos, true),
             rfc3339: now_utc.to_rfc3339(),
+            rfc9557: super::format_rfc9557(&now_utc.with_timezone(&chrono_tz::UTC), false),
             rfc2822: now_utc.to_rfc2822(),
-            ctime: now_utc.format("%c").to_string(),
+            ctime: render(&templates.ctime),
 
             nanos_since_epoch: unix_time.nanos_since_epoch,
             seconds: unix_time.seconds,
-            microseconds: unix_time.to_microseconds(),
-            milliseconds: unix_time.to_milliseconds(),
+            // chrono's range fits in i64 microseconds
+            microseconds: now_utc.timestamp_micros(),
+            milliseconds: now_utc.timestamp_millis(),
 
             year: now_utc.year(),
             month: now_utc.month(),
@@ -89,20 +113,24 @@ impl EnhancedTimeResponse {
             timezone: "UTC".to_string(),
             offset: 0,
 
-            weekday: now_utc.format("%A").to_string(),
-            week_of_year: now_utc.format("%U").to_string().parse().unwrap_or(0),
+            weekday: WEEKDAY_NAMES[now_utc.weekday().num_days_from_monday() as usize].to_string(),
+            // %U: weeks starting on Sunday, days before the first Sunday are week 0
+            week_of_year: (now_utc.ordinal0() + 7 - now_utc.weekday().num_days_from_sunday()) / 7,
             day_of_year: now_utc.ordinal(),
 
             custom_formats,
         }
     }
 
-    pub fn with_timezone(tz: &str) -> Result<Self, String> {
-        let now_utc = Utc::now();
-        let converted = TimezoneConverter::convert_to_tz(now_utc, tz)?;
+    pub fn with_timezone(tz: &str) -> Result<Self, TimeServerError> {
+        Self::at_timezone(crate::chaos::now(), tz)
+    }
 
-        // Create response with converted timezone
-        let mut response = Self::now();
+    /// Response describing `now_utc` as seen in `tz`
+    pub fn at_timezone(now_utc: DateTime<Utc>, tz: &str) -> Result<Self, TimeServerError> {
+        // Create response with converted timezone, from the instant `at` settled on
+        let mut response = Self::at(now_utc);
+        let converted = TimezoneConverter::convert_to_tz(response.unix.to_datetime(), tz)?;
         response.timezone = tz.to_string();
         response.offset = converted.offset().fix().local_minus_utc();
 
@@ -110,20 +138,81 @@ impl EnhancedTimeResponse {
         response.iso8601 = converted.to_rfc3339_opts(SecondsFormat::Nanos, true);
         response.rfc3339 = converted.to_rfc3339();
         response.rfc2822 = converted.to_rfc2822();
+        response.rfc9557 = super::format_rfc9557(&converted, false);
 
         Ok(response)
     }
 
-    pub fn format_custom(&self, format: &str) -> Result<String, Box<dyn std::error::Error>> {
+    /// String-error form of [`Self::with_timezone`]
+    #[deprecated(
+        since = "0.2.0",
+        note = "Use with_timezone, which returns TimeServerError"
+    )]
+    pub fn with_timezone_str(tz: &str) -> Result<Self, String> {
+        Ok(Self::with_timezone(tz)?)
+    }
+
+    /// Append the `[u-ca=iso8601]` calendar annotation to `rfc9557`
+    pub fn with_calendar_annotation(mut self) -> Self {
+        if !self
+            .rfc9557
+            .ends_with(super::rfc9557::ISO_CALENDAR_ANNOTATION)
+        {
+            self.rfc9557
+                .push_str(super::rfc9557::ISO_CALENDAR_ANNOTATION);
+        }
+        self
+    }
+
+    /// Re-render `iso8601`, `rfc3339` and `rfc9557` with `precision` sub-second digits
+    pub fn with_precision(mut self, precision: SecondsFormat) -> Self {
+        let Some(utc) = DateTime::<Utc>::from_timestamp(self.unix.seconds, self.unix.nanos) else {
+            return self;
+        };
+        let Some(offset) = chrono::FixedOffset::east_opt(self.offset) else {
+            return self;
+        };
+        let local = utc.with_timezone(&offset);
+        let calendar = self
+            .rfc9557
+            .ends_with(super::rfc9557::ISO_CALENDAR_ANNOTATION);
+
+        self.iso8601 = local.to_rfc3339_opts(precision, true);
+        self.rfc3339 = local.to_rfc3339_opts(precision, false);
+        self.rfc9557 = format!("{}[{}]", self.rfc3339, self.timezone);
+        if calendar {
+            self.rfc9557
+                .push_str(super::rfc9557::ISO_CALENDAR_ANNOTATION);
+        }
+        self
+    }
+
+    /// Format this instant with strftime `format`, in `timezone` when given
+    /// and in UTC otherwise (also for a response built in another zone).
+    ///
+    /// `%z` is the zone's offset and `%Z` its abbreviation from the tz
+    /// database: "JST" for Asia/Tokyo, "IST" for Asia/Kolkata. Zones the
+    /// database gives no abbreviation render `%Z` as the numeric offset the
+    /// database uses instead, e.g. "+04" for Asia/Dubai.
+    pub fn format_custom(
+        &self,
+        format: &str,
+        timezone: Option<&str>,
+    ) -> Result<String, TimeServerError> {
         let dt = DateTime::<Utc>::from_timestamp(self.unix.seconds, self.unix.nanos)
-            .ok_or("Invalid timestamp")?;
-        StrftimeFormatter::format(&dt, format)
+            .ok_or_else(|| TimeServerError::InvalidTimestamp(self.unix.seconds.to_string()))?;
+        match timezone {
+            Some(tz) => {
+                StrftimeFormatter::format(&TimezoneConverter::convert_to_tz(dt, tz)?, format)
+            }
+            None => StrftimeFormatter::format(&dt, format),
+        }
     }
 }
 
 // Legacy function for backwards compatibility
 pub fn get_current_utc_time() -> String {
-    let utc_time: DateTime<Utc> = Utc::now();
+    let utc_time: DateTime<Utc> = crate::chaos::now();
     utc_time.to_rfc3339()
 }
 
@@ -139,12 +228,135 @@ mod tests {
         assert!(response.unix.seconds > 0);
         assert!(response.iso8601.contains("T"));
         assert!(response.custom_formats.contains_key("unix_date"));
+        assert!(response.rfc9557.ends_with("+00:00[UTC]"));
+        assert!(response
+            .with_calendar_annotation()
+            .rfc9557
+            .ends_with("[UTC][u-ca=iso8601]"));
+    }
+
+    #[test]
+    fn test_custom_formats_serialize_in_key_order() {
+        let keys = |response: &EnhancedTimeResponse| {
+            let json = serde_json::to_string(response).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let start = json.find("\"custom_formats\"").unwrap();
+            let mut keys: Vec<(usize, String)> = value["custom_formats"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(|k| {
+                    (
+                        json[start..].find(&format!("\"{}\"", k)).unwrap(),
+                        k.clone(),
+                    )
+                })
+                .collect();
+            keys.sort();
+            keys.into_iter().map(|(_, k)| k).collect::<Vec<_>>()
+        };
+        let expected = ["apache_log", "syslog", "unix_date", "unix_timestamp"];
+        for _ in 0..3 {
+            assert_eq!(keys(&EnhancedTimeResponse::now()), expected);
+        }
+    }
+
+    #[test]
+    fn test_with_precision() {
+        let dt = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        let response = EnhancedTimeResponse::at(dt).with_precision(SecondsFormat::Millis);
+        assert_eq!(response.iso8601, "2023-11-14T22:13:20.123Z");
+        assert_eq!(response.rfc3339, "2023-11-14T22:13:20.123+00:00");
+        assert_eq!(response.rfc9557, "2023-11-14T22:13:20.123+00:00[UTC]");
+
+        let response = EnhancedTimeResponse::at_timezone(dt, "Asia/Kolkata")
+            .unwrap()
+            .with_precision(SecondsFormat::Secs);
+        assert_eq!(response.rfc9557, "2023-11-15T03:43:20+05:30[Asia/Kolkata]");
     }
 
     #[test]
     fn test_custom_format() {
         let response = EnhancedTimeResponse::now();
-        let formatted = response.format_custom("%Y-%m-%d").unwrap();
+        let formatted = response.format_custom("%Y-%m-%d", None).unwrap();
         assert_eq!(formatted.len(), 10);
     }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_custom_format_in_a_timezone() {
+        let dt = DateTime::from_timestamp(1_700_000_000, 0).unwrap(); // 2023-11-14T22:13:20Z
+        let response = EnhancedTimeResponse::at(dt);
+        let zoned = |tz| response.format_custom("%H:%M %Z %z", tz).unwrap();
+        assert_eq!(zoned(None), "22:13 UTC +0000");
+        assert_eq!(zoned(Some("Asia/Tokyo")), "07:13 JST +0900");
+        assert_eq!(zoned(Some("Asia/Kolkata")), "03:43 IST +0530");
+        // No abbreviation in the tz database: %Z is the offset it lists
+        assert_eq!(zoned(Some("Asia/Dubai")), "02:13 +04 +0400");
+
+        // A zoned response still formats in UTC unless asked
+        let tokyo = EnhancedTimeResponse::at_timezone(dt, "Asia/Tokyo").unwrap();
+        assert_eq!(tokyo.format_custom("%H %Z", None).unwrap(), "22 UTC");
+        assert!(matches!(
+            response.format_custom("%H", Some("Asia/Tokio")),
+            Err(TimeServerError::InvalidTimezone { .. })
+        ));
+    }
+
+    #[test]
+    fn test_calendar_fields_match_strftime() {
+        // Two years so both a Sunday and a non-Sunday January 1st are covered
+        let start = DateTime::from_timestamp(1_672_531_200, 0).unwrap(); // 2023-01-01
+        for day in 0..731 {
+            let dt = start + chrono::Duration::days(day);
+            let response = EnhancedTimeResponse::at(dt);
+            assert_eq!(response.weekday, dt.format("%A").to_string());
+            assert_eq!(
+                response.week_of_year.to_string(),
+                dt.format("%-U").to_string(),
+                "{}",
+                dt
+            );
+            assert_eq!(response.ctime, dt.format("%c").to_string());
+        }
+    }
+
+    #[test]
+    fn test_fields_come_from_one_instant() {
+        // Enough reads to cross many millisecond and some second boundaries
+        for _ in 0..20_000 {
+            let response = EnhancedTimeResponse::now();
+            let rfc3339 = DateTime::parse_from_rfc3339(&response.rfc3339).unwrap();
+            let iso8601 = DateTime::parse_from_rfc3339(&response.iso8601).unwrap();
+            assert_eq!(
+                response.seconds,
+                rfc3339.timestamp(),
+                "{}",
+                response.rfc3339
+            );
+            assert_eq!(response.nanosecond, iso8601.timestamp_subsec_nanos());
+            assert_eq!(response.unix.nanos, response.nanosecond);
+            assert_eq!(response.second, iso8601.second());
+            assert_eq!(
+                response.custom_formats["unix_timestamp"],
+                response.seconds.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_leap_second_reads_as_the_second_before() {
+        let leap: DateTime<Utc> = "2016-12-31T23:59:60.5Z".parse().unwrap();
+        for response in [
+            EnhancedTimeResponse::at(leap),
+            EnhancedTimeResponse::at_timezone(leap, "Asia/Tokyo").unwrap(),
+        ] {
+            assert_eq!(response.seconds, 1_483_228_799);
+            assert_eq!(response.nanosecond, 500_000_000);
+            assert_eq!(response.unix.nanos_since_epoch, 1_483_228_799_500_000_000);
+            assert_eq!(response.second, 59);
+            let iso8601 = DateTime::parse_from_rfc3339(&response.iso8601).unwrap();
+            assert_eq!(iso8601.timestamp(), response.seconds);
+        }
+    }
 }
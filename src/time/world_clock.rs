@@ -0,0 +1,248 @@
+// World clock: one UTC instant shown in several timezones
+//
+// Rows come from `TimezoneConverter::convert_many`, so every zone is
+// computed from the same instant. `render_table` is plain text with
+// fixed-width columns sized to their content.
+
+use super::{TimezoneConverter, UnixTime};
+use crate::error::TimeServerError;
+use chrono::{DateTime, Offset, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Zones shown when `WORLD_CLOCK_DEFAULT_ZONES` is not set
+pub const DEFAULT_WORLD_CLOCK_ZONES: &str = "America/Los_Angeles,America/New_York,Europe/London,Europe/Berlin,Asia/Kolkata,Asia/Tokyo,Australia/Sydney";
+
+const HEADERS: [&str; 5] = ["Zone", "Local time", "Date", "UTC offset", "Day"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldClockRow {
+    pub timezone: String,
+    /// HH:MM:SS
+    pub local_time: String,
+    /// YYYY-MM-DD
+    pub date: String,
+    pub offset_seconds: i32,
+    /// "+05:30"
+    pub utc_offset: String,
+    /// Local date minus the UTC date: -1, 0 or +1
+    pub day_difference: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldClock {
+    /// The shared instant, RFC 3339 UTC
+    pub utc: String,
+    pub unix_timestamp: i64,
+    /// Sorted by UTC offset, then name
+    pub rows: Vec<WorldClockRow>,
+}
+
+/// Split a comma-separated zone list, dropping empty entries
+pub fn parse_zone_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|zone| !zone.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Zones from `WORLD_CLOCK_DEFAULT_ZONES`, or [`DEFAULT_WORLD_CLOCK_ZONES`]
+pub fn default_zones() -> Vec<String> {
+    let zones = std::env::var("WORLD_CLOCK_DEFAULT_ZONES")
+        .map(|list| parse_zone_list(&list))
+        .unwrap_or_default();
+    if zones.is_empty() {
+        parse_zone_list(DEFAULT_WORLD_CLOCK_ZONES)
+    } else {
+        zones
+    }
+}
+
+/// `at` in each of `zones`
+pub fn world_clock<S: AsRef<str>>(
+    at: DateTime<Utc>,
+    zones: &[S],
+) -> Result<WorldClock, TimeServerError> {
+    if zones.is_empty() {
+        return Err(TimeServerError::InvalidArgument(
+            "at least one timezone is required".to_string(),
+        ));
+    }
+
+    // Settle a leap second first so every row and `unix_timestamp` agree
+    let at = UnixTime::from_datetime(at).to_datetime();
+    let mut rows: Vec<WorldClockRow> = TimezoneConverter::convert_many(at, zones)?
+        .into_iter()
+        .map(|local| {
+            let offset = local.offset().fix();
+            WorldClockRow {
+                timezone: local.timezone().name().to_string(),
+                local_time: local.format("%H:%M:%S").to_string(),
+                date: local.format("%Y-%m-%d").to_string(),
+                offset_seconds: offset.local_minus_utc(),
+                utc_offset: offset.to_string(),
+                day_difference: (local.date_naive() - at.date_naive()).num_days(),
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| (a.offset_seconds, &a.timezone).cmp(&(b.offset_seconds, &b.timezone)));
+    rows.dedup_by(|a, b| a.timezone == b.timezone);
+
+    Ok(WorldClock {
+        utc: at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        unix_timestamp: at.timestamp(),
+        rows,
+    })
+}
+
+/// Plain-text table, one line per row under a header and a rule
+pub fn render_table(rows: &[WorldClockRow]) -> String {
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.timezone.clone(),
+                row.local_time.clone(),
+                row.date.clone(),
+                row.utc_offset.clone(),
+                match row.day_difference {
+                    0 => "0".to_string(),
+                    days => format!("{:+}", days),
+                },
+            ]
+        })
+        .collect();
+
+    let mut widths = HEADERS.map(|h| h.chars().count());
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[&str]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+
+    let mut out = line(&HEADERS);
+    out.push('\n');
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    out.push_str(&rule.join("  "));
+    for row in &cells {
+        out.push('\n');
+        out.push_str(&line(&row.each_ref().map(String::as_str)));
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rows_share_instant_and_sort_by_offset() {
+        let clock = world_clock(
+            at("2024-06-01T12:00:00Z"),
+            &["Asia/Tokyo", "utc", "America/New_York"],
+        )
+        .unwrap();
+        assert_eq!(clock.utc, "2024-06-01T12:00:00Z");
+        let zones: Vec<&str> = clock.rows.iter().map(|r| r.timezone.as_str()).collect();
+        assert_eq!(zones, ["America/New_York", "UTC", "Asia/Tokyo"]);
+        assert_eq!(clock.rows[0].local_time, "08:00:00");
+        assert_eq!(clock.rows[0].utc_offset, "-04:00");
+        assert_eq!(clock.rows[2].local_time, "21:00:00");
+
+        assert!(world_clock(at("2024-06-01T12:00:00Z"), &["Nowhere/Land"]).is_err());
+        assert!(world_clock::<&str>(at("2024-06-01T12:00:00Z"), &[]).is_err());
+    }
+
+    #[test]
+    fn test_leap_second_rows_agree() {
+        let clock = world_clock(at("2016-12-31T23:59:60Z"), &["UTC"]).unwrap();
+        assert_eq!(clock.utc, "2016-12-31T23:59:59Z");
+        assert_eq!(clock.unix_timestamp, 1_483_228_799);
+        assert_eq!(clock.rows[0].local_time, "23:59:59");
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_day_difference_across_date_line() {
+        // Late in the UTC day Kiritimati (+14) is already on the next date;
+        // early in the UTC day Pago Pago (-11) is still on the previous one
+        let clock = world_clock(
+            at("2024-01-15T23:30:00Z"),
+            &["Pacific/Kiritimati", "Pacific/Pago_Pago", "Europe/London"],
+        )
+        .unwrap();
+        let day = |zone: &str| {
+            clock
+                .rows
+                .iter()
+                .find(|r| r.timezone == zone)
+                .unwrap()
+                .day_difference
+        };
+        assert_eq!(day("Pacific/Kiritimati"), 1);
+        assert_eq!(day("Europe/London"), 0);
+        assert_eq!(day("Pacific/Pago_Pago"), 0);
+
+        let clock = world_clock(at("2024-01-15T03:00:00Z"), &["Pacific/Pago_Pago"]).unwrap();
+        assert_eq!(clock.rows[0].day_difference, -1);
+        assert_eq!(clock.rows[0].date, "2024-01-14");
+        assert!(render_table(&clock.rows)
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("-1"));
+    }
+
+    #[cfg(not(feature = "timezones-minimal"))]
+    #[test]
+    fn test_table_columns_align() {
+        let clock = world_clock(
+            at("2024-01-15T23:30:00Z"),
+            &[
+                "UTC",
+                "America/Argentina/ComodRivadavia",
+                "Pacific/Kiritimati",
+            ],
+        )
+        .unwrap();
+        let table = render_table(&clock.rows);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with(&"-".repeat("America/Argentina/ComodRivadavia".len())));
+
+        // Columns start where the rule's dash runs start, on every line
+        let column_starts: Vec<usize> = lines[1].match_indices("  -").map(|(i, _)| i + 2).collect();
+        assert_eq!(column_starts.len(), HEADERS.len() - 1);
+        for line in lines.iter().take(1).chain(&lines[2..]) {
+            for &start in &column_starts {
+                assert_eq!(&line[start - 2..start], "  ", "{}", line);
+                assert_ne!(&line[start..start + 1], " ", "{}", line);
+            }
+        }
+        let kiritimati = lines[4];
+        assert!(kiritimati.starts_with("Pacific/Kiritimati "));
+        assert!(kiritimati.ends_with("+14:00      +1"), "{}", kiritimati);
+    }
+
+    #[test]
+    fn test_parse_zone_list() {
+        assert_eq!(
+            parse_zone_list(" Asia/Tokyo, ,Europe/Paris,"),
+            ["Asia/Tokyo", "Europe/Paris"]
+        );
+    }
+}
@@ -0,0 +1,645 @@
+// RFC 3161 trusted timestamps from an external timestamp authority
+//
+// get_trusted_timestamp hands a client's SHA-256 digest to the TSA at
+// TSA_URL and passes the DER TimeStampResp back untouched, so the token can
+// be stored and verified later with standard tools (`openssl ts -verify`).
+// Only the few ASN.1 structures involved are read and written here, by hand:
+// the request, the PKIStatusInfo, and the TSTInfo inside the signed token.
+// The server checks that the reply grants the request and that its message
+// imprint and nonce echo what was sent. It does not check the token's CMS
+// signature or the TSA's certificate chain; that stays with the caller, who
+// has to decide which TSAs to trust anyway.
+
+use crate::error::TimeServerError;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use chrono::{NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::Serialize;
+
+/// Content-Type of the request body (RFC 3161 section 3.4)
+pub const QUERY_CONTENT_TYPE: &str = "application/timestamp-query";
+
+#[cfg(feature = "tsa")]
+const TSA_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// What the response says about signature checks, next to `signature_verified`
+pub const VERIFICATION_NOTE: &str = "The server checked that the token answers this digest and nonce but not its signature or certificate chain; verify timestamp_response_base64 against the TSA's certificate before relying on it (e.g. openssl ts -verify).";
+
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OID: u8 = 0x06;
+const UTF8_STRING: u8 = 0x0c;
+const GENERALIZED_TIME: u8 = 0x18;
+/// [0] EXPLICIT, constructed
+const CONTEXT_0: u8 = 0xa0;
+/// [0] / [1] IMPLICIT INTEGER inside Accuracy
+const ACCURACY_MILLIS: u8 = 0x80;
+const ACCURACY_MICROS: u8 = 0x81;
+
+/// 2.16.840.1.101.3.4.2.1, content octets
+const SHA256_OID: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const SIGNED_DATA_OID: &str = "1.2.840.113549.1.7.2";
+const TST_INFO_OID: &str = "1.2.840.113549.1.9.16.1.4";
+
+/// PKIStatus values, by number
+const STATUSES: [&str; 6] = [
+    "granted",
+    "granted_with_mods",
+    "rejection",
+    "waiting",
+    "revocation_warning",
+    "revocation_notification",
+];
+
+/// PKIFailureInfo bits, by position
+const FAILURES: [(usize, &str); 8] = [
+    (0, "bad_alg"),
+    (2, "bad_request"),
+    (5, "bad_data_format"),
+    (14, "time_not_available"),
+    (15, "unaccepted_policy"),
+    (16, "unaccepted_extension"),
+    (17, "add_info_not_available"),
+    (25, "system_failure"),
+];
+
+/// A SHA-256 digest given as 64 hex digits or base64 (standard or URL-safe)
+pub fn parse_digest(text: &str) -> Result<[u8; 32], TimeServerError> {
+    let text = text.trim();
+    let bytes = if text.len() == 64 && text.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..32)
+            .map(|i| u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).unwrap_or_default())
+            .collect()
+    } else {
+        [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+            .iter()
+            .find_map(|engine| engine.decode(text).ok())
+            .ok_or_else(|| invalid_digest("not hex or base64".into()))?
+    };
+    let length = bytes.len();
+    bytes.try_into().map_err(|_| {
+        invalid_digest(format!(
+            "a SHA-256 digest is 32 bytes (64 hex digits or 44 base64 characters), got {}",
+            length
+        ))
+    })
+}
+
+fn invalid_digest(reason: String) -> TimeServerError {
+    TimeServerError::for_value("digest", TimeServerError::InvalidArgument(reason))
+}
+
+/// DER TimeStampReq for `digest`, asking for the TSA certificate in the token
+pub fn encode_request(digest: &[u8; 32], nonce: Option<u64>) -> Vec<u8> {
+    let algorithm = tlv(SEQUENCE, &[tlv(OID, SHA256_OID), tlv(NULL, &[])].concat());
+    let imprint = tlv(SEQUENCE, &[algorithm, tlv(OCTET_STRING, digest)].concat());
+    let mut fields = [tlv(INTEGER, &[1]), imprint].concat();
+    if let Some(nonce) = nonce {
+        fields.extend(tlv(INTEGER, &unsigned(&nonce.to_be_bytes())));
+    }
+    // certReq
+    fields.extend(tlv(BOOLEAN, &[0xff]));
+    tlv(SEQUENCE, &fields)
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let length = content.len().to_be_bytes();
+        let length = &length[length.iter().position(|&b| b != 0).unwrap_or(0)..];
+        out.push(0x80 | length.len() as u8);
+        out.extend(length);
+    }
+    out.extend(content);
+    out
+}
+
+/// Minimal big-endian INTEGER content for an unsigned value
+fn unsigned(bytes: &[u8]) -> Vec<u8> {
+    let start = bytes
+        .iter()
+        .position(|&b| b != 0)
+        .unwrap_or(bytes.len() - 1);
+    let mut out = Vec::with_capacity(bytes.len() - start + 1);
+    if bytes[start] & 0x80 != 0 {
+        out.push(0);
+    }
+    out.extend(&bytes[start..]);
+    out
+}
+
+/// Reads the elements of one DER constructed value in order
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    /// The next element's tag and content octets
+    fn next(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let truncated = || "truncated".to_string();
+        let (&tag, rest) = self.0.split_first().ok_or_else(truncated)?;
+        let (&first, rest) = rest.split_first().ok_or_else(truncated)?;
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err(format!("unsupported length encoding {:#04x}", first));
+            }
+            let length = rest[..count]
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (length, &rest[count..])
+        };
+        if rest.len() < length {
+            return Err(truncated());
+        }
+        let (content, rest) = rest.split_at(length);
+        self.0 = rest;
+        Ok((tag, content))
+    }
+
+    /// Content of the next element, which must be a `what` with `tag`
+    fn expect(&mut self, tag: u8, what: &str) -> Result<&'a [u8], String> {
+        match self.next()? {
+            (found, content) if found == tag => Ok(content),
+            (found, _) => Err(format!("expected {}, found tag {:#04x}", what, found)),
+        }
+    }
+
+    /// Content of the next element if it has `tag`
+    fn optional(&mut self, tag: u8, what: &str) -> Result<Option<&'a [u8]>, String> {
+        if self.peek() == Some(tag) {
+            self.expect(tag, what).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn decode_oid(content: &[u8]) -> Result<String, String> {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for &byte in content {
+        value = value
+            .checked_mul(128)
+            .ok_or("object identifier arc too large")?
+            | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    if arcs.is_empty() || content.last().is_some_and(|b| b & 0x80 != 0) {
+        return Err("malformed object identifier".into());
+    }
+    Ok(arcs
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+fn decode_small_integer(content: &[u8]) -> Result<u64, String> {
+    if content.is_empty() || content.len() > 8 || content[0] & 0x80 != 0 {
+        return Err("integer out of range".into());
+    }
+    Ok(content.iter().fold(0, |acc, &b| (acc << 8) | b as u64))
+}
+
+/// Hex of an INTEGER's value, without DER's sign padding
+fn integer_hex(content: &[u8]) -> String {
+    let start = content
+        .iter()
+        .position(|&b| b != 0)
+        .unwrap_or(content.len().saturating_sub(1));
+    content[start..]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// RFC 3339 for a GeneralizedTime such as 20261017200307.25Z
+fn decode_generalized_time(content: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "genTime is not ASCII")?;
+    let malformed = || format!("genTime '{}' is not YYYYMMDDHHMMSS[.fff]Z", text);
+    let text_utc = text.strip_suffix('Z').ok_or_else(malformed)?;
+    let (whole, fraction) = text_utc.split_once('.').unwrap_or((text_utc, ""));
+    let naive = NaiveDateTime::parse_from_str(whole, "%Y%m%d%H%M%S").map_err(|_| malformed())?;
+    if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(malformed());
+    }
+    let nanos: u32 = format!("{:0<9}", fraction).parse().unwrap_or_default();
+    let instant = Utc.from_utc_datetime(&naive) + chrono::Duration::nanoseconds(nanos.into());
+    Ok(instant.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+fn hash_name(oid: &str) -> String {
+    match oid {
+        "2.16.840.1.101.3.4.2.1" => "sha256",
+        "2.16.840.1.101.3.4.2.2" => "sha384",
+        "2.16.840.1.101.3.4.2.3" => "sha512",
+        "1.3.14.3.2.26" => "sha1",
+        other => other,
+    }
+    .to_string()
+}
+
+/// The fields of a token's TSTInfo a client usually wants without parsing DER
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenInfo {
+    /// When the TSA stamped the digest, RFC 3339 in UTC
+    pub gen_time: String,
+    /// The TSA's serial number for the token, hex
+    pub serial_number: String,
+    /// TSA policy the token was issued under
+    pub policy_oid: String,
+    /// "sha256", or the OID of an algorithm without a name here
+    pub hash_algorithm: String,
+    pub hash_algorithm_oid: String,
+    /// Accuracy the TSA claims for gen_time, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy_seconds: Option<f64>,
+    /// The nonce echoed from the request, hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip)]
+    hashed_message: Vec<u8>,
+}
+
+/// A granted TimeStampResp and what was read from it
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustedTimestamp {
+    /// The DER TimeStampResp exactly as the TSA sent it
+    pub timestamp_response_base64: String,
+    /// "granted" or "granted_with_mods"
+    pub status: &'static str,
+    #[serde(flatten)]
+    pub token: TokenInfo,
+    pub tsa_url: String,
+    /// Always false: checking the signature is the caller's job
+    pub signature_verified: bool,
+    pub note: &'static str,
+}
+
+impl TrustedTimestamp {
+    /// Read the TSA's reply to a request for `digest` with `nonce`
+    pub fn from_reply(
+        der: &[u8],
+        digest: &[u8; 32],
+        nonce: Option<u64>,
+        tsa_url: &str,
+    ) -> Result<Self, TimeServerError> {
+        let (status, token) = parse_response(der).map_err(|e| match e {
+            ParseError::Rejected(error) => error,
+            ParseError::Malformed(reason) => unavailable(format!(
+                "malformed TimeStampResp from {}: {}",
+                tsa_url, reason
+            )),
+        })?;
+        if token.hashed_message != digest {
+            return Err(unavailable(format!(
+                "{} answered with a token for a different digest",
+                tsa_url
+            )));
+        }
+        let sent = nonce.map(|n| integer_hex(&n.to_be_bytes()));
+        if token.nonce != sent {
+            return Err(unavailable(format!(
+                "{} answered with nonce {:?}, expected {:?}",
+                tsa_url, token.nonce, sent
+            )));
+        }
+        Ok(Self {
+            timestamp_response_base64: STANDARD.encode(der),
+            status,
+            token,
+            tsa_url: tsa_url.to_string(),
+            signature_verified: false,
+            note: VERIFICATION_NOTE,
+        })
+    }
+}
+
+fn unavailable(reason: String) -> TimeServerError {
+    TimeServerError::TsaUnavailable { reason }
+}
+
+/// Status and TSTInfo of a granted TimeStampResp; other statuses are `Rejected`
+fn parse_response(der: &[u8]) -> Result<(&'static str, TokenInfo), ParseError> {
+    let mut outer = Der(der);
+    let mut response = Der(outer.expect(SEQUENCE, "TimeStampResp")?);
+    let mut status_info = Der(response.expect(SEQUENCE, "PKIStatusInfo")?);
+    let status = decode_small_integer(status_info.expect(INTEGER, "PKIStatus")?)?;
+    let status_name = STATUSES.get(status as usize).copied().unwrap_or("unknown");
+    if status > 1 {
+        let text = match status_info.optional(SEQUENCE, "PKIFreeText")? {
+            Some(free_text) => {
+                let mut free_text = Der(free_text);
+                let mut lines = Vec::new();
+                while free_text.peek().is_some() {
+                    let line = free_text.expect(UTF8_STRING, "UTF8String")?;
+                    lines.push(String::from_utf8_lossy(line).into_owned());
+                }
+                Some(lines.join("; "))
+            }
+            None => None,
+        };
+        let failures = match status_info.optional(BIT_STRING, "PKIFailureInfo")? {
+            Some(bits) => failure_names(bits),
+            None => Vec::new(),
+        };
+        return Err(ParseError::Rejected(TimeServerError::TsaRejected {
+            status: status_name.to_string(),
+            text,
+            failures,
+        }));
+    }
+    let token = response.expect(SEQUENCE, "timeStampToken")?;
+    Ok((status_name, parse_token(token)?))
+}
+
+/// Names of the bits set in a PKIFailureInfo BIT STRING
+fn failure_names(bits: &[u8]) -> Vec<String> {
+    let Some((_, bytes)) = bits.split_first() else {
+        return Vec::new();
+    };
+    let is_set = |bit: usize| {
+        bytes
+            .get(bit / 8)
+            .is_some_and(|b| b & (0x80 >> (bit % 8)) != 0)
+    };
+    FAILURES
+        .iter()
+        .filter(|(bit, _)| is_set(*bit))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// TSTInfo from the ContentInfo / SignedData of a timeStampToken
+fn parse_token(content_info: &[u8]) -> Result<TokenInfo, String> {
+    let mut content_info = Der(content_info);
+    let content_type = decode_oid(content_info.expect(OID, "contentType")?)?;
+    if content_type != SIGNED_DATA_OID {
+        return Err(format!(
+            "token content type {} is not signedData",
+            content_type
+        ));
+    }
+    let mut explicit = Der(content_info.expect(CONTEXT_0, "[0] content")?);
+    let mut signed_data = Der(explicit.expect(SEQUENCE, "SignedData")?);
+    signed_data.expect(INTEGER, "SignedData version")?;
+    signed_data.expect(SET, "digestAlgorithms")?;
+    let mut encap = Der(signed_data.expect(SEQUENCE, "encapContentInfo")?);
+    let encap_type = decode_oid(encap.expect(OID, "eContentType")?)?;
+    if encap_type != TST_INFO_OID {
+        return Err(format!("eContentType {} is not TSTInfo", encap_type));
+    }
+    let mut explicit = Der(encap.expect(CONTEXT_0, "[0] eContent")?);
+    let mut wrapper = Der(explicit.expect(OCTET_STRING, "eContent")?);
+    let mut info = Der(wrapper.expect(SEQUENCE, "TSTInfo")?);
+
+    info.expect(INTEGER, "TSTInfo version")?;
+    let policy_oid = decode_oid(info.expect(OID, "policy")?)?;
+    let mut imprint = Der(info.expect(SEQUENCE, "messageImprint")?);
+    let mut algorithm = Der(imprint.expect(SEQUENCE, "hashAlgorithm")?);
+    let hash_algorithm_oid = decode_oid(algorithm.expect(OID, "hashAlgorithm")?)?;
+    let hashed_message = imprint.expect(OCTET_STRING, "hashedMessage")?.to_vec();
+    let serial_number = integer_hex(info.expect(INTEGER, "serialNumber")?);
+    let gen_time = decode_generalized_time(info.expect(GENERALIZED_TIME, "genTime")?)?;
+    let accuracy_seconds = match info.optional(SEQUENCE, "accuracy")? {
+        Some(accuracy) => {
+            let mut accuracy = Der(accuracy);
+            let seconds = match accuracy.optional(INTEGER, "accuracy seconds")? {
+                Some(seconds) => decode_small_integer(seconds)?,
+                None => 0,
+            };
+            let millis = match accuracy.optional(ACCURACY_MILLIS, "accuracy millis")? {
+                Some(millis) => decode_small_integer(millis)?,
+                None => 0,
+            };
+            let micros = match accuracy.optional(ACCURACY_MICROS, "accuracy micros")? {
+                Some(micros) => decode_small_integer(micros)?,
+                None => 0,
+            };
+            Some(seconds as f64 + millis as f64 / 1e3 + micros as f64 / 1e6)
+        }
+        None => None,
+    };
+    info.optional(BOOLEAN, "ordering")?;
+    let nonce = info.optional(INTEGER, "nonce")?.map(integer_hex);
+
+    Ok(TokenInfo {
+        gen_time,
+        serial_number,
+        policy_oid,
+        hash_algorithm: hash_name(&hash_algorithm_oid),
+        hash_algorithm_oid,
+        accuracy_seconds,
+        nonce,
+        hashed_message,
+    })
+}
+
+/// Why a TimeStampResp could not be used
+enum ParseError {
+    Malformed(String),
+    Rejected(TimeServerError),
+}
+
+impl From<String> for ParseError {
+    fn from(reason: String) -> Self {
+        Self::Malformed(reason)
+    }
+}
+
+/// Timestamp `digest` at the TSA at `url` (TSA_URL)
+#[cfg(feature = "tsa")]
+pub async fn request(
+    url: Option<&str>,
+    digest: &[u8; 32],
+) -> Result<TrustedTimestamp, TimeServerError> {
+    let url = url.ok_or_else(|| unavailable("TSA_URL is not set".into()))?;
+    let mut nonce = [0u8; 8];
+    getrandom::getrandom(&mut nonce)
+        .map_err(|e| unavailable(format!("no randomness for the nonce: {}", e)))?;
+    let nonce = u64::from_be_bytes(nonce);
+
+    let client = reqwest::Client::builder()
+        .timeout(TSA_TIMEOUT)
+        .build()
+        .map_err(|e| unavailable(e.to_string()))?;
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, QUERY_CONTENT_TYPE)
+        .body(encode_request(digest, Some(nonce)))
+        .send()
+        .await
+        .map_err(|e| unavailable(format!("{}: {}", url, e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(unavailable(format!("{} answered HTTP {}", url, status)));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| unavailable(format!("{}: {}", url, e)))?;
+    TrustedTimestamp::from_reply(&body, digest, Some(nonce), url)
+}
+
+/// Without the tsa feature there is no HTTP client to reach a TSA with
+#[cfg(not(feature = "tsa"))]
+pub async fn request(
+    _url: Option<&str>,
+    _digest: &[u8; 32],
+) -> Result<TrustedTimestamp, TimeServerError> {
+    Err(TimeServerError::NotCompiledIn {
+        feature: "tsa".into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-256 of "hello world"
+    const DIGEST_HEX: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+    /// The nonce openssl put in the request behind the granted fixture
+    const FIXTURE_NONCE: u64 = 0xe415_0bd1_4f74_e223;
+    /// `openssl ts -reply` answers, granted and refused
+    const GRANTED: &[u8] = include_bytes!("../tests/fixtures/tsa/granted.tsr");
+    const REJECTED: &[u8] = include_bytes!("../tests/fixtures/tsa/rejected.tsr");
+
+    fn digest() -> [u8; 32] {
+        parse_digest(DIGEST_HEX).unwrap()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        let expected = digest();
+        assert_eq!(expected[0], 0xb9);
+        for text in [
+            DIGEST_HEX.to_uppercase(),
+            STANDARD.encode(expected),
+            URL_SAFE_NO_PAD.encode(expected),
+        ] {
+            assert_eq!(parse_digest(&text).unwrap(), expected, "{}", text);
+        }
+        for text in ["", "b94d27", "not a digest!", &STANDARD.encode([0u8; 20])] {
+            let error = parse_digest(text).unwrap_err();
+            assert_eq!(error.code(), "invalid_value", "{}", text);
+            assert_eq!(error.details()["field"], "digest");
+        }
+    }
+
+    #[test]
+    fn test_encode_request_matches_openssl() {
+        // openssl ts -query -digest <DIGEST_HEX> -sha256 -cert [-no_nonce]
+        assert_eq!(
+            hex(&encode_request(&digest(), None)),
+            format!(
+                "30390201013031300d060960864801650304020105000420{}0101ff",
+                DIGEST_HEX
+            )
+        );
+        assert_eq!(
+            hex(&encode_request(&digest(), Some(FIXTURE_NONCE))),
+            format!(
+                "30440201013031300d060960864801650304020105000420{}020900e4150bd14f74e2230101ff",
+                DIGEST_HEX
+            )
+        );
+        // Short nonces lose their leading zero bytes
+        assert!(hex(&encode_request(&digest(), Some(5))).ends_with("0201050101ff"));
+    }
+
+    #[test]
+    fn test_reads_granted_response() {
+        let url = "https://tsa.example.com";
+        let stamp =
+            TrustedTimestamp::from_reply(GRANTED, &digest(), Some(FIXTURE_NONCE), url).unwrap();
+        assert_eq!(stamp.status, "granted");
+        assert_eq!(
+            stamp.token,
+            TokenInfo {
+                gen_time: "2026-10-17T20:03:07Z".into(),
+                serial_number: "2b".into(),
+                policy_oid: "1.2.3.4.1".into(),
+                hash_algorithm: "sha256".into(),
+                hash_algorithm_oid: "2.16.840.1.101.3.4.2.1".into(),
+                accuracy_seconds: Some(1.5),
+                nonce: Some("e4150bd14f74e223".into()),
+                hashed_message: digest().to_vec(),
+            }
+        );
+        assert_eq!(
+            STANDARD.decode(&stamp.timestamp_response_base64).unwrap(),
+            GRANTED
+        );
+        assert!(!stamp.signature_verified);
+
+        // A token for another request is not passed on
+        let error = TrustedTimestamp::from_reply(GRANTED, &digest(), Some(1), url).unwrap_err();
+        assert!(error.to_string().contains("nonce"), "{}", error);
+        let error =
+            TrustedTimestamp::from_reply(GRANTED, &[0; 32], Some(FIXTURE_NONCE), url).unwrap_err();
+        assert!(error.to_string().contains("different digest"), "{}", error);
+        let error =
+            TrustedTimestamp::from_reply(&GRANTED[..600], &digest(), None, url).unwrap_err();
+        assert_eq!(error.code(), "tsa_unavailable");
+        assert!(error.to_string().contains("malformed"), "{}", error);
+    }
+
+    #[test]
+    fn test_reads_rejection() {
+        let error =
+            TrustedTimestamp::from_reply(REJECTED, &digest(), None, "https://tsa.example.com")
+                .unwrap_err();
+        assert_eq!(error.code(), "tsa_rejected");
+        assert_eq!(
+            error.details(),
+            serde_json::json!({
+                "status": "rejection",
+                "status_text": "Error during serial number generation.",
+                "failure_info": ["add_info_not_available"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_generalized_time() {
+        assert_eq!(
+            decode_generalized_time(b"20261017200307.25Z").unwrap(),
+            "2026-10-17T20:03:07.250Z"
+        );
+        assert!(decode_generalized_time(b"20261017200307").is_err());
+    }
+
+    #[cfg(not(feature = "tsa"))]
+    #[tokio::test]
+    async fn test_request_needs_the_feature() {
+        let error = request(Some("https://tsa.example.com"), &digest())
+            .await
+            .unwrap_err();
+        assert_eq!(error.details()["feature"], "tsa");
+    }
+}
@@ -0,0 +1,226 @@
+// How long a response stays correct
+//
+// Agents cache tool results between turns, and a cached "current time" is
+// wrong a moment later. Responses carry `valid_for_ms` and the matching
+// `expires_at`, worked out from what the answer depends on: a clock reading
+// is good for a short window (`CURRENT_TIME_VALID_MS`), a zone's offset
+// until its next transition, the timezone list for as long as the
+// compiled-in tzdata, and NTP status until the cached offset is due for a
+// refresh. Clock readings stay `no-store` over HTTP; NTP status, which
+// really is a cached measurement, sends its figure as `Cache-Control: max-age`.
+
+use crate::error::TimeServerError;
+use crate::time::TimezoneConverter;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Window for clock readings unless `CURRENT_TIME_VALID_MS` says otherwise
+pub const DEFAULT_CURRENT_TIME_VALID_MS: u64 = 1000;
+
+/// Stand-in for "does not expire while this build runs": one year, the
+/// longest `max-age` HTTP caches are expected to honor
+pub const INDEFINITE_VALID_MS: u64 = 365 * 86_400_000;
+
+/// How far ahead to look for a zone's next offset transition
+const TRANSITION_HORIZON_SECS: i64 = 2 * 366 * 86400;
+
+/// Validity window of a response, measured from when it was built
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Validity {
+    pub valid_for_ms: u64,
+    /// RFC 3339, UTC, millisecond precision
+    pub expires_at: String,
+}
+
+impl Validity {
+    pub fn for_duration(now: DateTime<Utc>, valid_for: Duration) -> Self {
+        let valid_for_ms = valid_for.as_millis().min(u64::MAX as u128) as u64;
+        Self {
+            valid_for_ms,
+            expires_at: Self::expiry(now, valid_for_ms)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+
+    /// `now` plus `valid_for_ms`, saturating at the latest representable time
+    pub fn expiry(now: DateTime<Utc>, valid_for_ms: u64) -> DateTime<Utc> {
+        chrono::Duration::try_milliseconds(valid_for_ms.min(i64::MAX as u64) as i64)
+            .and_then(|window| now.checked_add_signed(window))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Valid until `expires_at` (immediately stale if that has passed)
+    pub fn until(now: DateTime<Utc>, expires_at: DateTime<Utc>) -> Self {
+        let remaining = (expires_at - now).to_std().unwrap_or_default();
+        Self {
+            expires_at: expires_at
+                .max(now)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            ..Self::for_duration(now, remaining)
+        }
+    }
+
+    pub fn indefinite(now: DateTime<Utc>) -> Self {
+        Self::for_duration(now, Duration::from_millis(INDEFINITE_VALID_MS))
+    }
+
+    /// Whole seconds for `Cache-Control: max-age`
+    pub fn max_age_secs(&self) -> u64 {
+        self.valid_for_ms / 1000
+    }
+
+    /// `Cache-Control` value: `no-store` for windows under a second
+    pub fn cache_control(&self) -> String {
+        match self.max_age_secs() {
+            0 => "no-store".to_string(),
+            secs => format!("max-age={}", secs),
+        }
+    }
+
+    /// Wrap `body` so the validity fields serialize alongside its own
+    pub fn attach<T>(self, body: T) -> WithValidity<T> {
+        WithValidity {
+            body,
+            validity: self,
+        }
+    }
+}
+
+/// A response body with `valid_for_ms` and `expires_at` alongside it
+#[derive(Debug, Serialize)]
+pub struct WithValidity<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(flatten)]
+    pub validity: Validity,
+}
+
+/// A clock reading in a zone: the reading's window plus how long the zone's
+/// offset, abbreviation and DST state hold
+#[derive(Debug, Serialize)]
+pub struct ZoneReading<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(flatten)]
+    pub validity: Validity,
+    pub offset_validity: Validity,
+}
+
+/// Per-kind validity rules, shared by the MCP tools and the HTTP API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ValidityConfig {
+    /// Window for clock readings (`CURRENT_TIME_VALID_MS`)
+    pub current_time_ms: u64,
+}
+
+impl Default for ValidityConfig {
+    fn default() -> Self {
+        Self {
+            current_time_ms: DEFAULT_CURRENT_TIME_VALID_MS,
+        }
+    }
+}
+
+impl ValidityConfig {
+    /// A clock reading taken at `now`
+    pub fn current_time(&self, now: DateTime<Utc>) -> Validity {
+        Validity::for_duration(now, Duration::from_millis(self.current_time_ms))
+    }
+
+    /// Offset, abbreviation and DST state of `timezone`: until the next
+    /// transition, or indefinitely for zones without one ahead
+    pub fn timezone(
+        &self,
+        timezone: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Validity, TimeServerError> {
+        let next = TimezoneConverter::next_transition(timezone, now, TRANSITION_HORIZON_SECS)?;
+        Ok(
+            match next.and_then(|t| DateTime::from_timestamp(t.unix_timestamp, 0)) {
+                Some(at) => Validity::until(now, at),
+                None => Validity::indefinite(now),
+            },
+        )
+    }
+
+    /// `body`, a reading taken at `now` in `timezone`
+    pub fn zone_reading<T>(
+        &self,
+        body: T,
+        timezone: &str,
+        now: DateTime<Utc>,
+    ) -> Result<ZoneReading<T>, TimeServerError> {
+        Ok(ZoneReading {
+            body,
+            validity: self.current_time(now),
+            offset_validity: self.timezone(timezone, now)?,
+        })
+    }
+
+    /// Data that only changes with a new build (timezone names)
+    pub fn static_data(&self, now: DateTime<Utc>) -> Validity {
+        Validity::indefinite(now)
+    }
+
+    /// A cached measurement with `remaining` left before it is refreshed
+    pub fn cached(&self, now: DateTime<Utc>, remaining: Duration) -> Validity {
+        Validity::for_duration(now, remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_timezone_validity_ends_at_next_transition() {
+        let config = ValidityConfig::default();
+        let now = at("2025-01-15T12:00:00.000400Z");
+
+        // New York springs forward at 2025-03-09T07:00:00Z
+        let validity = config.timezone("America/New_York", now).unwrap();
+        assert_eq!(validity.expires_at, "2025-03-09T07:00:00.000Z");
+        assert_eq!(
+            validity.valid_for_ms,
+            (at("2025-03-09T07:00:00Z") - now).num_milliseconds() as u64
+        );
+        assert_eq!(validity.valid_for_ms % 1000, 999);
+
+        // No DST in Tokyo
+        let tokyo = config.timezone("Asia/Tokyo", now).unwrap();
+        assert_eq!(tokyo.valid_for_ms, INDEFINITE_VALID_MS);
+        assert!(config.timezone("Mars/Olympus", now).is_err());
+    }
+
+    #[test]
+    fn test_current_time_window_and_cache_control() {
+        let now = at("2025-01-15T12:00:00Z");
+        let config = ValidityConfig {
+            current_time_ms: 2500,
+        };
+        let validity = config.current_time(now);
+        assert_eq!(validity.valid_for_ms, 2500);
+        assert_eq!(validity.expires_at, "2025-01-15T12:00:02.500Z");
+        assert_eq!(validity.cache_control(), "max-age=2");
+
+        let short = ValidityConfig {
+            current_time_ms: 250,
+        }
+        .current_time(now);
+        assert_eq!(short.cache_control(), "no-store");
+        assert_eq!(
+            Validity::until(now, at("2025-01-15T11:00:00Z")).valid_for_ms,
+            0
+        );
+
+        let value =
+            serde_json::to_value(validity.attach(serde_json::json!({"seconds": 1}))).unwrap();
+        assert_eq!(value["seconds"], 1);
+        assert_eq!(value["valid_for_ms"], 2500);
+    }
+}
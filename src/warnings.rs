@@ -0,0 +1,197 @@
+// Coded warnings shared by MCP tool results and the HTTP API
+//
+// A response that fell back to something other than what was asked for
+// (host time in a container, an uncorrected clock, English month names)
+// says so in `warnings: [{code, message, field?}]`. Codes are stable and
+// meant for programs; messages are for people. The key is left out when
+// there is nothing to report, so clean responses are unchanged.
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// Running in a container: host synchronization is assumed, not checked
+    NtpContainerAssumed,
+    /// Correction requested but the measured NTP offset is too old to apply
+    OffsetStale,
+    /// Correction requested but no NTP offset has been measured
+    OffsetUnavailable,
+    /// The session locale is not supported; English names were used
+    LocaleFallback,
+    /// The result is further ahead than the compiled-in tzdata can vouch for
+    TzdataFuture,
+    /// A declared maintenance window is open; the server may restart soon
+    MaintenanceActive,
+    /// CHAOS_MODE is on: times are deliberately wrong
+    ChaosActive,
+    /// An interval's ends came the wrong way round and were swapped
+    InputsSwapped,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NtpContainerAssumed => "NTP_CONTAINER_ASSUMED",
+            Self::OffsetStale => "OFFSET_STALE",
+            Self::OffsetUnavailable => "OFFSET_UNAVAILABLE",
+            Self::LocaleFallback => "LOCALE_FALLBACK",
+            Self::TzdataFuture => "TZDATA_FUTURE",
+            Self::MaintenanceActive => "MAINTENANCE_ACTIVE",
+            Self::ChaosActive => "CHAOS_ACTIVE",
+            Self::InputsSwapped => "INPUTS_SWAPPED",
+        }
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// The parameter or preference the warning is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            field: None,
+        }
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// NTP status in a container, shared by `get_ntp_status` and `/api/ntp/status`
+    pub fn ntp_container_assumed() -> Self {
+        Self::new(
+            WarningCode::NtpContainerAssumed,
+            "running in a container; the host clock is assumed to be NTP-synchronized",
+        )
+    }
+}
+
+/// Warnings collected while building one response
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, warning: Warning) -> &mut Self {
+        self.0.push(warning);
+        self
+    }
+
+    /// Add a warning that is not tied to a parameter
+    pub fn warn(&mut self, code: WarningCode, message: impl Into<String>) -> &mut Self {
+        self.push(Warning::new(code, message))
+    }
+
+    pub fn extend(&mut self, warnings: impl IntoIterator<Item = Warning>) -> &mut Self {
+        self.0.extend(warnings);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Warning> {
+        self.0.iter()
+    }
+
+    pub fn contains(&self, code: WarningCode) -> bool {
+        self.0.iter().any(|warning| warning.code == code)
+    }
+
+    /// Wrap `body` so the warnings serialize alongside its fields
+    pub fn attach<T>(self, body: T) -> WithWarnings<T> {
+        WithWarnings {
+            body,
+            warnings: self,
+        }
+    }
+}
+
+impl FromIterator<Warning> for Warnings {
+    fn from_iter<I: IntoIterator<Item = Warning>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// A response body with its warnings alongside it
+#[derive(Debug, Serialize)]
+pub struct WithWarnings<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(skip_serializing_if = "Warnings::is_empty")]
+    pub warnings: Warnings,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_warnings_serialize_with_codes_and_optional_field() {
+        let mut warnings = Warnings::new();
+        warnings
+            .warn(WarningCode::OffsetStale, "offset is 400s old")
+            .push(
+                Warning::new(WarningCode::LocaleFallback, "de-DE unsupported").with_field("locale"),
+            );
+        assert!(warnings.contains(WarningCode::LocaleFallback));
+
+        let value = serde_json::to_value(warnings.attach(json!({"seconds": 1}))).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "seconds": 1,
+                "warnings": [
+                    {"code": "OFFSET_STALE", "message": "offset is 400s old"},
+                    {"code": "LOCALE_FALLBACK", "message": "de-DE unsupported", "field": "locale"}
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(WarningCode::NtpContainerAssumed).unwrap(),
+            json!(WarningCode::NtpContainerAssumed.as_str())
+        );
+    }
+
+    #[test]
+    fn test_no_warnings_leaves_the_body_unchanged() {
+        let value = serde_json::to_value(Warnings::new().attach(json!({"seconds": 1}))).unwrap();
+        assert_eq!(value, json!({"seconds": 1}));
+    }
+}
@@ -0,0 +1,57 @@
+// Feature combinations: every one must build, like `cargo hack --feature-powerset`
+// without the extra tool. Each combination is a full `cargo check` of the
+// library, binary and tests into its own target directory, so this takes
+// minutes and is ignored by default; CI runs it with
+//   cargo test --test feature_matrix_test -- --ignored
+
+use std::path::Path;
+use std::process::Command;
+
+/// `--features` lists checked with `--no-default-features`
+const COMBINATIONS: &[&str] = &[
+    "",
+    "timezones-minimal",
+    "timezones-full",
+    "http-api",
+    "auth",
+    "ntp",
+    "prompts",
+    "http-api,ntp",
+    "ntp,prompts",
+    "auth,prompts,timezones-minimal",
+    "http-api,ntp,prompts,timezones-full,auth",
+    "http-api,ntp,prompts,timezones-full,auth,geo",
+    "http-api,ntp,prompts,timezones-minimal,auth,geo",
+    "tsa",
+    "http-api,ntp,prompts,timezones-full,auth,tsa",
+];
+
+#[test]
+#[ignore = "runs cargo check once per feature combination"]
+fn test_feature_combinations_build() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = manifest_dir.join("target").join("feature-matrix");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut failed = Vec::new();
+    for features in COMBINATIONS {
+        let status = Command::new(&cargo)
+            .current_dir(manifest_dir)
+            .args(["check", "--quiet", "--lib", "--bins", "--tests"])
+            .arg("--no-default-features")
+            .args(["--features", features])
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .env("RUSTFLAGS", "-D warnings")
+            .status()
+            .expect("cargo did not start");
+        if !status.success() {
+            failed.push(*features);
+        }
+    }
+    assert!(
+        failed.is_empty(),
+        "failed to build with features {:?}",
+        failed
+    );
+}
@@ -0,0 +1,281 @@
+// Golden samples and schemas of the response types clients see
+//
+// Each type is built from fixed inputs, serialized, and compared with
+// tests/golden/types/<name>.json; its JSON Schema is compared with
+// tests/golden/schemas/<name>.schema.json. A difference fails with the first
+// line that changed. When the change is intended, record the new files with
+//   UPDATE_GOLDEN=1 cargo test --test golden_test
+// and commit them with the change, so the review shows the wire difference.
+
+use chrono::{DateTime, Utc};
+use mcp_utc_time_server::time::timezone::{
+    Conversion, ConvertedTime, TimezoneConverter, CONVERSION_NOTE,
+};
+use mcp_utc_time_server::time::utc::EnhancedTimeResponse;
+use mcp_utc_time_server::time::{AmbiguityPolicy, GapPolicy, UnixTime};
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A Monday: week 52 by the Sunday-based count, ISO week 1 of 2025
+fn instant() -> DateTime<Utc> {
+    "2024-12-30T23:30:15.123456789Z".parse().unwrap()
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn updating() -> bool {
+    std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1")
+}
+
+/// `actual` is the recorded file at `relative`, or is recorded with UPDATE_GOLDEN=1
+fn assert_golden(relative: &str, actual: &str) {
+    let path = golden_dir().join(relative);
+    if updating() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "tests/golden/{} is missing; record it with\n  \
+             UPDATE_GOLDEN=1 cargo test --test golden_test",
+            relative
+        )
+    });
+    if expected == actual {
+        return;
+    }
+    let (expected_lines, actual_lines): (Vec<_>, Vec<_>) =
+        (expected.lines().collect(), actual.lines().collect());
+    let line = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .position(|(e, a)| e != a)
+        .unwrap_or(expected_lines.len().min(actual_lines.len()));
+    panic!(
+        "tests/golden/{} no longer matches what the code produces.\n\
+         first difference at line {}:\n  recorded: {}\n  produced: {}\n\
+         This changes what clients receive. If that is intended, regenerate with\n  \
+         UPDATE_GOLDEN=1 cargo test --test golden_test\n\
+         and commit the updated files with the change.",
+        relative,
+        line + 1,
+        expected_lines.get(line).unwrap_or(&"<end of file>"),
+        actual_lines.get(line).unwrap_or(&"<end of file>"),
+    );
+}
+
+/// `sample` serialized matches types/<name>.json
+fn assert_sample(name: &str, sample: &impl Serialize) {
+    let json = serde_json::to_string_pretty(sample).unwrap() + "\n";
+    assert_golden(&format!("types/{}.json", name), &json);
+}
+
+/// The JSON Schema of `T` matches schemas/<name>.schema.json
+fn assert_schema<T: JsonSchema>(name: &str) {
+    let schema = serde_json::to_string_pretty(&schema_for!(T)).unwrap() + "\n";
+    assert_golden(&format!("schemas/{}.schema.json", name), &schema);
+}
+
+/// Dotted paths of every object key in `value`
+fn field_paths(value: &Value) -> BTreeSet<String> {
+    fn walk(value: &Value, prefix: &str, paths: &mut BTreeSet<String>) {
+        if let Value::Object(fields) = value {
+            for (key, child) in fields {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                walk(child, &path, paths);
+                paths.insert(path);
+            }
+        }
+    }
+    let mut paths = BTreeSet::new();
+    walk(value, "", &mut paths);
+    paths
+}
+
+/// Every field of the 0.1 shape is still in `sample`
+fn assert_keeps_fields(name: &str, sample: &impl Serialize, v0_1: &[&str]) {
+    let paths = field_paths(&serde_json::to_value(sample).unwrap());
+    let missing: Vec<_> = v0_1.iter().filter(|f| !paths.contains(**f)).collect();
+    assert!(
+        missing.is_empty(),
+        "{} dropped fields clients of 0.1 read: {:?}",
+        name,
+        missing
+    );
+}
+
+fn conversion(timestamp: Option<i64>, local: Option<&str>, from: &str, to: &str) -> Conversion {
+    let (utc, original) = TimezoneConverter::conversion_origin(
+        timestamp,
+        local,
+        Some(from),
+        AmbiguityPolicy::default(),
+        GapPolicy::default(),
+    )
+    .unwrap();
+    let converted = TimezoneConverter::convert_to_tz(utc, to).unwrap();
+    Conversion {
+        original,
+        note: CONVERSION_NOTE,
+        converted: ConvertedTime::new(&converted, to.to_string(), false).unwrap(),
+    }
+}
+
+#[cfg(feature = "ntp")]
+fn ntp_status() -> mcp_utc_time_server::ntp::NtpStatus {
+    use mcp_utc_time_server::ntp::ntpq::KernelInfo;
+    mcp_utc_time_server::ntp::NtpStatus {
+        synced: true,
+        offset_ms: -0.042,
+        stratum: 1,
+        precision: -20,
+        root_delay: 0.0,
+        root_dispersion: 0.95,
+        shm_valid: true,
+        pps_enabled: true,
+        frequency_ppm: Some(-12.5),
+        sys_jitter_ms: Some(0.002),
+        clk_jitter_ms: Some(0.001),
+        clk_wander_ppm: Some(0.003),
+        refid: Some("PPS".into()),
+        reftime: Some("2024-12-30T23:30:00.000Z".into()),
+        kernel: Some(KernelInfo {
+            pll_offset_ms: Some(-0.000042),
+            pll_frequency_ppm: Some(-12.5),
+            maximum_error_ms: Some(1.5),
+            estimated_error_ms: Some(0.002),
+            time_constant: Some(4),
+            status: vec![
+                "pll".into(),
+                "ppsfreq".into(),
+                "ppstime".into(),
+                "nano".into(),
+            ],
+        }),
+        shm_attach_attempts: 1,
+        shm_last_error: None,
+    }
+}
+
+#[test]
+fn test_time_types_match_goldens() {
+    assert_sample("unix_time", &UnixTime::from_datetime(instant()));
+    assert_schema::<UnixTime>("unix_time");
+    assert_sample("enhanced_time", &EnhancedTimeResponse::at(instant()));
+    assert_sample(
+        "enhanced_time_tokyo",
+        &EnhancedTimeResponse::at_timezone(instant(), "Asia/Tokyo").unwrap(),
+    );
+    assert_schema::<EnhancedTimeResponse>("enhanced_time");
+}
+
+#[test]
+fn test_conversion_matches_goldens() {
+    let timestamp = instant().timestamp();
+    assert_sample(
+        "conversion",
+        &conversion(Some(timestamp), None, "UTC", "Asia/Tokyo"),
+    );
+    // The fold when New York left DST: status and policy appear
+    assert_sample(
+        "conversion_ambiguous",
+        &conversion(None, Some("2024-11-03T01:30:00"), "America/New_York", "UTC"),
+    );
+    assert_schema::<Conversion>("conversion");
+}
+
+#[cfg(feature = "ntp")]
+#[test]
+fn test_ntp_status_matches_goldens() {
+    assert_sample("ntp_status", &ntp_status());
+    assert_schema::<mcp_utc_time_server::ntp::NtpStatus>("ntp_status");
+}
+
+#[test]
+fn test_0_1_fields_are_still_present() {
+    const UNIX_TIME: &[&str] = &["seconds", "nanos", "nanos_since_epoch"];
+    assert_keeps_fields("UnixTime", &UnixTime::from_datetime(instant()), UNIX_TIME);
+
+    let enhanced: Vec<String> = [
+        "iso8601",
+        "rfc3339",
+        "rfc2822",
+        "ctime",
+        "nanos_since_epoch",
+        "seconds",
+        "microseconds",
+        "milliseconds",
+        "year",
+        "month",
+        "day",
+        "hour",
+        "minute",
+        "second",
+        "nanosecond",
+        "timezone",
+        "offset",
+        "weekday",
+        "week_of_year",
+        "day_of_year",
+        "custom_formats",
+        "custom_formats.apache_log",
+        "custom_formats.syslog",
+        "custom_formats.unix_date",
+        "custom_formats.unix_timestamp",
+        "unix",
+    ]
+    .into_iter()
+    .map(String::from)
+    .chain(UNIX_TIME.iter().map(|f| format!("unix.{}", f)))
+    .collect();
+    let enhanced: Vec<&str> = enhanced.iter().map(String::as_str).collect();
+    for sample in [
+        EnhancedTimeResponse::at(instant()),
+        EnhancedTimeResponse::at_timezone(instant(), "Asia/Tokyo").unwrap(),
+    ] {
+        assert_keeps_fields("EnhancedTimeResponse", &sample, &enhanced);
+    }
+
+    assert_keeps_fields(
+        "convert_time",
+        &conversion(Some(instant().timestamp()), None, "UTC", "Asia/Tokyo"),
+        &[
+            "original",
+            "original.timestamp",
+            "original.timezone",
+            "original.formatted",
+            "converted",
+            "converted.timestamp",
+            "converted.timezone",
+            "converted.formatted",
+            "converted.offset",
+        ],
+    );
+
+    #[cfg(feature = "ntp")]
+    assert_keeps_fields(
+        "NtpStatus",
+        &mcp_utc_time_server::ntp::NtpStatus::default(),
+        &[
+            "synced",
+            "offset_ms",
+            "stratum",
+            "precision",
+            "root_delay",
+            "root_dispersion",
+            "shm_valid",
+            "pps_enabled",
+            "shm_attach_attempts",
+        ],
+    );
+}
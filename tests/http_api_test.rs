@@ -13,8 +13,9 @@ async fn start_test_server() -> tokio::task::JoinHandle<()> {
     std::env::set_var("HTTP_API_PORT", TEST_PORT.to_string());
     std::env::set_var("CONTAINER_APP_NAME", "test"); // Enable container mode
 
-    tokio::spawn(async {
-        if let Err(e) = mcp_utc_time_server::server_sdk::run_http_api_server().await {
+    let config = mcp_utc_time_server::ServerConfig::load().expect("invalid test config");
+    tokio::spawn(async move {
+        if let Err(e) = mcp_utc_time_server::server_sdk::run_http_api_server(&config).await {
             eprintln!("HTTP API server error: {}", e);
         }
     })
@@ -48,12 +49,157 @@ async fn test_health_endpoint() {
     let response = get_request("/health").await;
     assert!(response.is_ok(), "Health check failed: {:?}", response);
 
-    let body = response.unwrap();
+    let json: serde_json::Value = serde_json::from_str(&response.unwrap()).unwrap();
     assert!(
-        body.contains("healthy"),
-        "Response should contain 'healthy'"
+        json.get("version").is_some(),
+        "Response should contain version"
     );
-    assert!(body.contains("version"), "Response should contain version");
+    // Container mode only assumes the host clock is synchronized
+    assert_eq!(json["status"], "degraded");
+    assert!(json.get("maintenance").is_none());
+    let checks = json["checks"].as_array().unwrap();
+    let check = |name: &str| checks.iter().find(|c| c["name"] == name).unwrap().clone();
+    assert_eq!(check("sync")["status"], "degraded");
+    assert_eq!(check("offset")["status"], "healthy");
+    assert_eq!(check("shm")["status"], "degraded");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_health_strict_mode_unhealthy_returns_503() {
+    use mcp_utc_time_server::health::HealthProbe;
+    use mcp_utc_time_server::http::{run_http_api_server_with, HttpSettings};
+
+    std::env::set_var("HTTP_API_PORT", TEST_PORT.to_string());
+    std::env::set_var("HEALTH_STRICT", "true");
+    let config = mcp_utc_time_server::ServerConfig::load().expect("invalid test config");
+    std::env::remove_var("HEALTH_STRICT");
+    assert!(config.health.strict);
+
+    let unsynced = mcp_utc_time_server::NtpStatus {
+        synced: false,
+        offset_ms: 0.0,
+        stratum: 16,
+        precision: 0,
+        root_delay: 0.0,
+        root_dispersion: 0.0,
+        shm_valid: false,
+        pps_enabled: false,
+        ..Default::default()
+    };
+    let mut settings = HttpSettings::from_config(&config);
+    settings.health = std::sync::Arc::new(HealthProbe::with_status(
+        config.health.clone(),
+        false,
+        unsynced,
+    ));
+    let _server = tokio::spawn(async move {
+        if let Err(e) = run_http_api_server_with(&config, settings).await {
+            eprintln!("HTTP API server error: {}", e);
+        }
+    });
+    sleep(Duration::from_millis(500)).await;
+
+    let error = get_request("/health").await.unwrap_err();
+    assert!(error.starts_with("HTTP 503"), "{}", error);
+    let body = error.split_once(" - ").unwrap().1;
+    let json: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(json["status"], "unhealthy");
+    assert_eq!(json["checks"][0]["name"], "sync");
+    assert_eq!(json["checks"][0]["status"], "unhealthy");
+    assert_eq!(json["ntp"]["synced"], false);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_health_reports_open_maintenance_window() {
+    use mcp_utc_time_server::http::{run_http_api_server_with, HttpSettings};
+
+    std::env::set_var("HTTP_API_PORT", TEST_PORT.to_string());
+    let config = mcp_utc_time_server::ServerConfig::load().expect("invalid test config");
+    let mut settings = HttpSettings::from_config(&config);
+    settings.maintenance = Some("* * * * * 1m".parse().unwrap());
+    let _server = tokio::spawn(async move {
+        if let Err(e) = run_http_api_server_with(&config, settings).await {
+            eprintln!("HTTP API server error: {}", e);
+        }
+    });
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/health").await.unwrap();
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["maintenance"]["active"], true);
+    assert!(json["maintenance"]["end"].is_string());
+    assert!(json["maintenance"].get("starts_in_seconds").is_none());
+}
+
+fn in_1971() -> chrono::DateTime<chrono::Utc> {
+    "1971-03-04T05:06:07Z".parse().unwrap()
+}
+
+/// Serve with a clock guard that reads 1971
+async fn start_with_guard_in_1971(allow_implausible: bool) -> tokio::task::JoinHandle<()> {
+    use mcp_utc_time_server::guard::{ClockGuard, GuardConfig};
+    use mcp_utc_time_server::http::{run_http_api_server_with, HttpSettings};
+
+    std::env::set_var("HTTP_API_PORT", TEST_PORT.to_string());
+    let config = mcp_utc_time_server::ServerConfig::load().expect("invalid test config");
+    let mut settings = HttpSettings::from_config(&config);
+    let guard = ClockGuard::new(GuardConfig {
+        allow_implausible,
+        ..Default::default()
+    })
+    .with_clock(in_1971);
+    settings.clock_guard = std::sync::Arc::new(guard);
+    let server = tokio::spawn(async move {
+        if let Err(e) = run_http_api_server_with(&config, settings).await {
+            eprintln!("HTTP API server error: {}", e);
+        }
+    });
+    sleep(Duration::from_millis(500)).await;
+    server
+}
+
+#[tokio::test]
+#[serial]
+async fn test_implausible_clock_is_refused_and_reported() {
+    let server = start_with_guard_in_1971(false).await;
+
+    let error = get_request("/api/time").await.unwrap_err();
+    assert!(error.starts_with("HTTP 503"), "{}", error);
+    let body: serde_json::Value = serde_json::from_str(error.split_once(" - ").unwrap().1).unwrap();
+    assert_eq!(body["code"], "clock_implausible");
+
+    let health: serde_json::Value =
+        serde_json::from_str(&get_request("/health").await.unwrap()).unwrap();
+    assert_eq!(health["status"], "unhealthy");
+    let checks = health["checks"].as_array().unwrap();
+    let clock = checks.iter().find(|c| c["name"] == "clock").unwrap();
+    assert_eq!(clock["status"], "unhealthy");
+    assert!(clock["detail"]
+        .as_str()
+        .unwrap()
+        .contains("1971-03-04T05:06:07Z, before this server was built"));
+
+    let error = get_request("/ready").await.unwrap_err();
+    assert!(error.starts_with("HTTP 503"), "{}", error);
+    let body: serde_json::Value = serde_json::from_str(error.split_once(" - ").unwrap().1).unwrap();
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["clock"]["serving"], false);
+    assert!(get_request("/api/info").await.is_ok());
+    server.abort();
+    sleep(Duration::from_millis(100)).await;
+
+    // ALLOW_IMPLAUSIBLE_CLOCK serves time, and /health still says the clock is off
+    let server = start_with_guard_in_1971(true).await;
+    assert!(get_request("/api/time").await.is_ok());
+    assert!(get_request("/ready").await.is_ok());
+    let health: serde_json::Value =
+        serde_json::from_str(&get_request("/health").await.unwrap()).unwrap();
+    let checks = health["checks"].as_array().unwrap();
+    let clock = checks.iter().find(|c| c["name"] == "clock").unwrap();
+    assert_eq!(clock["status"], "degraded");
+    server.abort();
 }
 
 #[tokio::test]
@@ -74,6 +220,34 @@ async fn test_api_time_endpoint() {
     assert!(json.get("iso8601").is_some(), "Should have 'iso8601' field");
 }
 
+#[tokio::test]
+#[serial]
+async fn test_pretty_query_param_selects_compact_json() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let pretty = get_request("/api/time").await.unwrap();
+    let compact = get_request("/api/time?pretty=false").await.unwrap();
+    assert!(pretty.contains('\n'));
+    assert!(!compact.contains('\n'));
+    let keys = |body: &str| {
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        json.as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&compact), keys(&pretty));
+
+    let timezones = get_request("/api/timezones?pretty=0").await.unwrap();
+    assert!(!timezones.contains('\n'));
+    let error = get_request("/api/time/timezone/Nowhere?pretty=false")
+        .await
+        .unwrap_err();
+    assert!(!error.contains('\n'));
+}
+
 #[tokio::test]
 #[serial]
 async fn test_api_unix_endpoint() {
@@ -141,6 +315,9 @@ async fn test_api_timezones_endpoint() {
         "Should have 'timezones' field"
     );
     assert!(json.get("count").is_some(), "Should have 'count' field");
+    assert_eq!(json["offset"], 0);
+    assert_eq!(json["has_more"], false);
+    assert_eq!(json["total"], json["count"]);
 
     let timezones = json["timezones"]
         .as_array()
@@ -199,6 +376,342 @@ async fn test_api_timezone_invalid() {
     assert!(response.is_err(), "Invalid timezone should return error");
 }
 
+#[tokio::test]
+#[serial]
+async fn test_api_timezone_percent_encoded() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let response = get_request("/api/time/timezone/America%2FNew_York").await;
+    let json: serde_json::Value =
+        serde_json::from_str(&response.expect("encoded timezone failed")).expect("Invalid JSON");
+    assert_eq!(json["timezone"].as_str(), Some("America/New_York"));
+
+    // Spaces are normalized to underscores
+    let response = get_request("/api/time/timezone/America/New%20York").await;
+    let json: serde_json::Value =
+        serde_json::from_str(&response.expect("space-normalized timezone failed"))
+            .expect("Invalid JSON");
+    assert_eq!(json["timezone"].as_str(), Some("America/New_York"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezone_timeline() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    // 2024-01-01T00:00:00Z to 2025-01-01T00:00:00Z
+    let response =
+        get_request("/api/timezone/America/New_York/timeline?start=1704067200&end=1735689600")
+            .await;
+    let json: serde_json::Value =
+        serde_json::from_str(&response.expect("timeline failed")).expect("Invalid JSON");
+    assert_eq!(json["timezone"], "America/New_York");
+    assert_eq!(json["count"], 3);
+    assert_eq!(json["segments"][0]["from_unix"], 1704067200);
+    assert_eq!(json["segments"][1]["abbreviation"], "EDT");
+
+    let missing_end = get_request("/api/timezone/America/New_York/timeline?start=1704067200").await;
+    assert!(missing_end.unwrap_err().starts_with("HTTP 400"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezone_timeline_exports() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = |format: &str| {
+        format!(
+            "http://127.0.0.1:{}/api/timezone/America/New_York/timeline?start=1704067200&end=1735689600&format={}",
+            TEST_PORT, format
+        )
+    };
+
+    let csv = reqwest::get(url("csv")).await.expect("Request failed");
+    assert_eq!(csv.status(), 200);
+    assert!(csv.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let csv = csv.text().await.unwrap();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(
+        lines[0],
+        "from_utc,to_utc,from_unix,to_unix,offset_seconds,abbreviation,is_dst"
+    );
+    assert_eq!(lines.len(), 4);
+
+    let ics = reqwest::get(url("ics")).await.expect("Request failed");
+    assert!(ics.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/calendar"));
+    let ics = ics.text().await.unwrap();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 3);
+    assert!(ics.contains("DTSTART;TZID=America/New_York:20240310T030000\r\n"));
+
+    let bad = reqwest::get(url("xml")).await.expect("Request failed");
+    assert_eq!(bad.status(), 400);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezone_three_level_and_query_string() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let response = get_request("/api/time/timezone/America/Argentina/Buenos_Aires?x=1").await;
+    let json: serde_json::Value =
+        serde_json::from_str(&response.expect("three-level timezone failed"))
+            .expect("Invalid JSON");
+    assert_eq!(
+        json["timezone"].as_str(),
+        Some("America/Argentina/Buenos_Aires"),
+        "Query string must not leak into the timezone name"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezone_invalid_structured_error() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!(
+        "http://127.0.0.1:{}/api/time/timezone/America/New_Yrok",
+        TEST_PORT
+    );
+    let response = reqwest::get(&url).await.expect("Request failed");
+    assert_eq!(response.status(), 404);
+
+    let json: serde_json::Value = response.json().await.expect("Invalid JSON");
+    assert_eq!(json["timezone"].as_str(), Some("America/New_Yrok"));
+    assert!(json.get("error").is_some(), "Should have 'error' field");
+    assert!(json["hint"].as_str().unwrap().contains("America/New_York"));
+    assert_eq!(json["suggestions"][0].as_str(), Some("America/New_York"));
+    assert_eq!(json["code"], "invalid_timezone");
+    assert_eq!(json["details"]["suggestions"][0], "America/New_York");
+
+    let url = format!("http://127.0.0.1:{}/api/time/timezone/%E0%A4%A", TEST_PORT);
+    let response = reqwest::get(&url).await.expect("Request failed");
+    assert_eq!(response.status(), 400);
+    let json: serde_json::Value = response.json().await.expect("Invalid JSON");
+    assert!(json.get("hint").is_some());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_gzip_compression_negotiation() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/timezones", TEST_PORT);
+    let client = reqwest::Client::new();
+
+    let plain = client.get(&url).send().await.expect("Request failed");
+    assert!(plain.headers().get("content-encoding").is_none());
+    let plain_body = plain.bytes().await.expect("Failed to read body");
+
+    let gzipped = client
+        .get(&url)
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(gzipped.headers()["content-encoding"], "gzip");
+    assert!(gzipped
+        .headers()
+        .get_all("vary")
+        .iter()
+        .any(|v| v == "Accept-Encoding"));
+    let compressed = gzipped.bytes().await.expect("Failed to read body");
+    assert!(compressed.len() < plain_body.len());
+
+    let mut decoded = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_end(&mut decoded)
+        .expect("Invalid gzip body");
+    let decoded_json: serde_json::Value = serde_json::from_slice(&decoded).expect("Invalid JSON");
+    let plain_json: serde_json::Value = serde_json::from_slice(&plain_body).expect("Invalid JSON");
+    assert_eq!(decoded_json, plain_json);
+
+    // Small responses are not worth compressing
+    let small = client
+        .get(format!("http://127.0.0.1:{}/api/unix", TEST_PORT))
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .expect("Request failed");
+    assert!(small.headers().get("content-encoding").is_none());
+    let json: serde_json::Value = small.json().await.expect("Invalid JSON");
+    assert!(json.get("seconds").is_some());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezones_prefix_filter() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/timezones?prefix=europe/&limit=5")
+        .await
+        .expect("Request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON");
+
+    let timezones = json["timezones"].as_array().expect("timezones array");
+    assert_eq!(timezones.len(), 5);
+    assert_eq!(json["count"], 5);
+    assert!(json["total"].as_u64().unwrap() > 5);
+    assert_eq!(json["has_more"], true);
+    assert!(timezones
+        .iter()
+        .all(|tz| tz.as_str().unwrap().starts_with("Europe/")));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezones_paging_past_end() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/timezones?region=Australia&offset=1000")
+        .await
+        .expect("Request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON");
+
+    assert_eq!(json["count"], 0);
+    assert_eq!(json["offset"], 1000);
+    assert_eq!(json["has_more"], false);
+    assert!(json["total"].as_u64().unwrap() > 0);
+    assert!(json["timezones"].as_array().unwrap().is_empty());
+
+    let error = get_request("/api/timezones?limit=lots").await.unwrap_err();
+    assert!(error.starts_with("HTTP 400"), "{}", error);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezones_grouped() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/timezones?grouped=true&region=Europe")
+        .await
+        .expect("Request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON");
+
+    assert!(json.get("timezones").is_none());
+    let groups = json["groups"].as_object().expect("groups object");
+    assert_eq!(groups.len(), 1);
+    let cities: Vec<&str> = groups["Europe"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap())
+        .collect();
+    assert!(cities.contains(&"London"));
+    assert!(cities.contains(&"Paris"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_timezones_detail() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/timezones?detail=true&prefix=Asia/Tok")
+        .await
+        .expect("Request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).expect("Invalid JSON");
+
+    assert!(json.get("timezones").is_none());
+    assert!(json["captured_at"].as_str().unwrap().ends_with('Z'));
+    let zones = json["zones"].as_array().expect("zones array");
+    assert_eq!(zones.len(), 1);
+    assert_eq!(zones[0]["name"], "Asia/Tokyo");
+    assert_eq!(zones[0]["offset_seconds"], 32_400);
+    assert_eq!(zones[0]["utc_offset"], "+09:00");
+    assert_eq!(zones[0]["is_dst"], false);
+    assert!(zones[0]["local_hour"].as_u64().unwrap() < 24);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_timezones_etag_conditional_request() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/timezones", TEST_PORT);
+    let client = reqwest::Client::new();
+
+    let first = client.get(&url).send().await.expect("Request failed");
+    assert_eq!(first.status(), 200);
+    let etag = first.headers()["etag"]
+        .to_str()
+        .expect("Invalid ETag")
+        .to_string();
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert!(first.headers().get("last-modified").is_some());
+    assert!(first.headers()["cache-control"]
+        .to_str()
+        .unwrap()
+        .contains("max-age="));
+
+    // Matching validator: 304 with no body
+    let not_modified = client
+        .get(&url)
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(not_modified.status(), 304);
+    assert_eq!(not_modified.headers()["etag"], etag.as_str());
+    assert!(not_modified
+        .bytes()
+        .await
+        .expect("Failed to read body")
+        .is_empty());
+
+    // Mismatched validator: full response
+    let mismatched = client
+        .get(&url)
+        .header("If-None-Match", "\"not-the-etag\"")
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(mismatched.status(), 200);
+    let json: serde_json::Value = mismatched.json().await.expect("Invalid JSON");
+    assert!(json["count"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_time_endpoints_are_not_cacheable() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    for path in ["/api/time", "/api/unix", "/health"] {
+        let response = client
+            .get(format!("http://127.0.0.1:{}{}", TEST_PORT, path))
+            .send()
+            .await
+            .expect("Request failed");
+        assert_eq!(response.headers()["cache-control"], "no-store", "{}", path);
+        assert!(response.headers().get("etag").is_none(), "{}", path);
+        if path != "/health" {
+            let json: serde_json::Value = response.json().await.expect("Invalid JSON");
+            assert_eq!(json["valid_for_ms"], 1000, "{}", path);
+        }
+    }
+}
+
 #[tokio::test]
 #[serial]
 async fn test_api_ntp_status_container_mode() {
@@ -224,30 +737,64 @@ async fn test_api_ntp_status_container_mode() {
         json["container_mode"], true,
         "Should indicate container mode"
     );
+    assert_eq!(json["warnings"][0]["code"], "NTP_CONTAINER_ASSUMED");
+    // Nothing is measured in a container, so nothing stays valid
+    assert_eq!(json["valid_for_ms"], 0);
 }
 
 #[tokio::test]
 #[serial]
 async fn test_metrics_endpoint() {
+    use prometheus_parse::{Scrape, Value};
+
     let _server = start_test_server().await;
     sleep(Duration::from_millis(500)).await;
 
-    let response = get_request("/metrics").await;
-    assert!(response.is_ok(), "GET /metrics failed: {:?}", response);
-
-    let body = response.unwrap();
-    assert!(
-        body.contains("mcp_time_seconds"),
-        "Should contain mcp_time_seconds metric"
+    let scrape = |body: String| {
+        Scrape::parse(body.lines().map(|line| Ok(line.to_string()))).expect("unparsable metrics")
+    };
+    let ntpq_runs = mcp_utc_time_server::ntp::query::global().runs();
+    let first = scrape(get_request("/metrics").await.expect("GET /metrics failed"));
+    let second = scrape(get_request("/metrics").await.expect("GET /metrics failed"));
+    assert_eq!(
+        mcp_utc_time_server::ntp::query::global().runs(),
+        ntpq_runs,
+        "scraping must not run ntpq"
     );
+
+    let value = |scrape: &Scrape, name: &str| {
+        scrape
+            .samples
+            .iter()
+            .find(|s| s.metric == name)
+            .map(|s| s.value.clone())
+    };
+    let seconds = |scrape: &Scrape| match value(scrape, "mcp_time_seconds") {
+        Some(Value::Gauge(v)) => v,
+        other => panic!("mcp_time_seconds is {:?}", other),
+    };
+    // Fractional seconds in one gauge, moving forward between scrapes
+    assert!(seconds(&first) > 1.7e9);
+    assert!(seconds(&second) >= seconds(&first));
     assert!(
-        body.contains("mcp_time_nanos"),
-        "Should contain mcp_time_nanos metric"
+        value(&first, "mcp_time_nanos").is_none(),
+        "legacy metric without METRICS_LEGACY"
     );
+
     assert!(
-        body.contains("# TYPE"),
-        "Should contain Prometheus TYPE directives"
+        matches!(value(&first, "mcp_shm_valid"), Some(Value::Gauge(v)) if v == 0.0 || v == 1.0)
     );
+    assert!(matches!(
+        value(&first, "mcp_rate_limited_total"),
+        Some(Value::Counter(_))
+    ));
+    let source = first
+        .samples
+        .iter()
+        .find(|s| s.metric == "mcp_clock_source" && matches!(s.value, Value::Gauge(v) if v == 1.0))
+        .expect("no active clock source");
+    assert!(["shm", "ntp", "gps", "ptp", "system"].contains(&source.labels.get("source").unwrap()));
+    assert!(first.docs.contains_key("mcp_time_seconds"));
 }
 
 #[tokio::test]
@@ -291,6 +838,232 @@ async fn test_cors_headers() {
     );
 }
 
+#[tokio::test]
+#[serial]
+async fn test_head_request_returns_headers_only() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/time", TEST_PORT);
+    let response = reqwest::Client::new()
+        .head(&url)
+        .send()
+        .await
+        .expect("Request failed");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let length: usize = response.headers()["content-length"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("Invalid Content-Length");
+    assert!(length > 0, "HEAD should advertise the GET body length");
+    assert!(response.bytes().await.expect("Failed to read").is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_options_preflight_with_custom_header() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/time", TEST_PORT);
+    let response = reqwest::Client::new()
+        .request(reqwest::Method::OPTIONS, &url)
+        .header("Origin", "https://app.example")
+        .header("Access-Control-Request-Method", "GET")
+        .header("Access-Control-Request-Headers", "X-API-Key")
+        .send()
+        .await
+        .expect("Request failed");
+
+    assert_eq!(response.status(), 204);
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], "*");
+    assert!(headers["access-control-allow-methods"]
+        .to_str()
+        .unwrap()
+        .contains("GET"));
+    assert!(headers["access-control-allow-headers"]
+        .to_str()
+        .unwrap()
+        .contains("X-API-Key"));
+    assert!(headers.contains_key("access-control-max-age"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_unsupported_method_returns_405() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://127.0.0.1:{}/api/time", TEST_PORT))
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(response.status(), 405);
+    assert_eq!(response.headers()["allow"], "GET, HEAD, OPTIONS");
+
+    // Unknown paths are still 404 whatever the method
+    let response = client
+        .post(format!("http://127.0.0.1:{}/nope", TEST_PORT))
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(response.status(), 404);
+}
+
+async fn post_batch(body: serde_json::Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/api/batch", TEST_PORT))
+        .json(&body)
+        .send()
+        .await
+        .expect("Request failed")
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_batch_keeps_order_and_isolates_failures() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let response = post_batch(serde_json::json!({"operations": [
+        {"op": "timezone", "timezone": "Asia/Tokyo"},
+        {"op": "convert", "timestamp": 0, "to_timezone": "Asia/Kolkata"},
+        {"op": "timezone", "timezone": "Mars/Olympus_Mons"},
+        {"op": "time"},
+        {"op": "timezone", "timezone": "Europe/London"},
+    ]}))
+    .await;
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["api_version"], "1");
+    assert_eq!(json["count"], 5);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 5);
+
+    assert_eq!(results[0]["ok"]["timezone"], "Asia/Tokyo");
+    assert_eq!(results[1]["ok"]["converted"]["timezone"], "Asia/Kolkata");
+    assert_eq!(results[1]["ok"]["converted"]["offset"], 19800);
+    assert!(results[2].get("ok").is_none());
+    assert_eq!(results[2]["error"]["code"], "invalid_timezone");
+    assert!(results[2]["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Mars/Olympus_Mons"));
+    assert!(results[3]["ok"]["unix"].is_object());
+    assert!(results[3]["ok"].get("api_version").is_none());
+    assert_eq!(results[4]["ok"]["timezone"], "Europe/London");
+
+    // Only POST; the preflight says so
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/api/batch", TEST_PORT);
+    let get = client.get(&url).send().await.unwrap();
+    assert_eq!(get.status(), 405);
+    assert_eq!(get.headers()["allow"], "POST, OPTIONS");
+    let preflight = client
+        .request(reqwest::Method::OPTIONS, &url)
+        .header("Origin", "https://app.example")
+        .header("Access-Control-Request-Method", "POST")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(preflight.status(), 204);
+    assert_eq!(
+        preflight.headers()["access-control-allow-methods"],
+        "POST, OPTIONS"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_batch_limits() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let operations = vec![serde_json::json!({"op": "time"}); 21];
+    let response = post_batch(serde_json::json!({ "operations": operations })).await;
+    assert_eq!(response.status(), 400);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["code"], "invalid_argument");
+    assert!(json["message"].as_str().unwrap().contains("at most 20"));
+
+    let operations = vec![serde_json::json!({"op": "time"}); 20];
+    let response = post_batch(serde_json::json!({ "operations": operations })).await;
+    assert_eq!(response.status(), 200);
+
+    let response = post_batch(serde_json::json!([{"op": "time"}])).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_batch_requires_auth_when_enabled() {
+    use mcp_utc_time_server::auth::ApiKey;
+    use mcp_utc_time_server::http::{start_http_api, HttpSettings, ListenerConfig};
+
+    let mut config = mcp_utc_time_server::ServerConfig::default();
+    config.http.listeners = vec![ListenerConfig::parse("127.0.0.1:0;auth").unwrap()];
+    config.auth.api_keys = vec![ApiKey {
+        key: "batch-test-key".into(),
+        name: None,
+        rate_limit: None,
+    }];
+    let server = start_http_api(&config, HttpSettings::from_config(&config))
+        .await
+        .expect("listener binds");
+    let url = format!("http://{}/api/batch", server.listeners()[0].addr);
+    let body = serde_json::json!({"operations": [{"op": "time"}]});
+
+    let client = reqwest::Client::new();
+    let denied = client.post(&url).json(&body).send().await.unwrap();
+    assert_eq!(denied.status(), 401);
+    let allowed = client
+        .post(&url)
+        .header("X-API-Key", "batch-test-key")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(allowed.status(), 200);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_cors_allow_origin_configurable() {
+    std::env::set_var("CORS_ALLOW_ORIGIN", "https://app.example");
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/time", TEST_PORT);
+    let client = reqwest::Client::new();
+
+    let allowed = client
+        .get(&url)
+        .header("Origin", "https://app.example")
+        .send()
+        .await
+        .expect("Request failed");
+    let other = client
+        .get(&url)
+        .header("Origin", "https://other.example")
+        .send()
+        .await
+        .expect("Request failed");
+    std::env::remove_var("CORS_ALLOW_ORIGIN");
+
+    assert_eq!(
+        allowed.headers()["access-control-allow-origin"],
+        "https://app.example"
+    );
+    assert!(other.headers().get("access-control-allow-origin").is_none());
+}
+
 #[tokio::test]
 #[serial]
 async fn test_concurrent_requests() {
@@ -309,3 +1082,528 @@ async fn test_concurrent_requests() {
         assert!(result.is_ok(), "Concurrent request failed: {:?}", result);
     }
 }
+
+#[tokio::test]
+#[serial]
+async fn test_server_info_endpoint() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/info").await.expect("info request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["build"]["version"], env!("CARGO_PKG_VERSION"));
+    assert!(json["build"]["git_commit"].is_string());
+    assert!(json["build"]["tzdb_version"].is_string());
+    assert_eq!(json["modes"]["container_mode"], true);
+    assert_eq!(json["modes"]["http_api"]["port"], TEST_PORT);
+    assert!(json["host"]["os"].is_string());
+    assert!(json["started_at"].is_string());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_signing_key_endpoint() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/signing-key")
+        .await
+        .expect("signing key request failed");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["algorithm"], "ed25519");
+    let public_key = json["public_key"].as_str().unwrap();
+    assert_eq!(public_key.len(), 64);
+    assert!(public_key.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(json["persistent"], false);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_error_body_carries_code() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!("http://127.0.0.1:{}/api/timezones?limit=abc", TEST_PORT);
+    let response = reqwest::get(&url).await.expect("Request failed");
+    assert_eq!(response.status(), 400);
+    let json: serde_json::Value = response.json().await.expect("Invalid JSON");
+    assert_eq!(json["code"], "invalid_argument");
+    assert!(json["message"].as_str().unwrap().contains("'limit'"));
+    assert!(json.get("details").is_some());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_version_negotiation() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/api/time", TEST_PORT);
+
+    let v1 = client.get(&url).send().await.expect("Request failed");
+    assert_eq!(v1.headers()["api-version"], "1");
+    let v1: serde_json::Value = v1.json().await.unwrap();
+    assert_eq!(v1["api_version"], "1");
+    assert!(v1.get("week_of_year").is_some());
+
+    let v2 = client
+        .get(&url)
+        .header("Accept-Version", "2")
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(v2.headers()["api-version"], "2");
+    let v2: serde_json::Value = v2.json().await.unwrap();
+    assert_eq!(v2["api_version"], "2");
+    assert!(v2.get("iso_week").is_some());
+    assert!(v2.get("offset").is_none());
+
+    let query = get_request("/api/time/timezone/Asia/Tokyo?v=2")
+        .await
+        .unwrap();
+    let query: serde_json::Value = serde_json::from_str(&query).unwrap();
+    assert_eq!(query["offset_seconds"], 32400);
+
+    let refused = client
+        .get(format!("{}?v=3", url))
+        .send()
+        .await
+        .expect("Request failed");
+    assert_eq!(refused.status(), 406);
+    let refused: serde_json::Value = refused.json().await.unwrap();
+    assert_eq!(refused["code"], "unsupported_api_version");
+    assert_eq!(
+        refused["details"]["supported"],
+        serde_json::json!(["1", "2"])
+    );
+
+    // Probes are not versioned
+    let health = get_request("/health?v=3").await.unwrap();
+    assert!(!health.contains("api_version"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ready_endpoint_without_strict_sanity() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/ready").await.expect("ready failed");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["status"], "ready");
+    assert!(json.get("time_sanity").is_none());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_format_explain_endpoint() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let body = get_request("/api/format/explain?format=%25j%20%25Q%25%25")
+        .await
+        .expect("explain failed");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["format"], "%j %Q%%");
+    assert_eq!(json["directives"][0]["directive"], "%j");
+    assert_eq!(json["directives"][0]["example"], "002");
+    assert_eq!(json["unknown"][0]["directive"], "%Q");
+    assert_eq!(json["rendered_reference"], "002 %Q%");
+
+    let missing = get_request("/api/format/explain").await.unwrap_err();
+    assert!(missing.starts_with("HTTP 400"), "{}", missing);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_timezone_locate_endpoint() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let bad = get_request("/api/timezone/locate?lat=95&lon=0")
+        .await
+        .unwrap_err();
+    assert!(bad.starts_with("HTTP 400"), "{}", bad);
+    let missing = get_request("/api/timezone/locate?lat=51.5")
+        .await
+        .unwrap_err();
+    assert!(missing.starts_with("HTTP 400"), "{}", missing);
+
+    let located = get_request("/api/timezone/locate?lat=51.51&lon=-0.13").await;
+    if cfg!(feature = "geo") {
+        let json: serde_json::Value = serde_json::from_str(&located.unwrap()).unwrap();
+        assert_eq!(json["timezone"], "Europe/London");
+        assert_eq!(json["approximate"], false);
+    } else {
+        let error = located.unwrap_err();
+        assert!(error.starts_with("HTTP 501"), "{}", error);
+        assert!(error.contains("not_compiled_in"), "{}", error);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_time_stream_ndjson() {
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let url = format!(
+        "http://127.0.0.1:{}/api/time/stream?interval_ms=50&count=5",
+        TEST_PORT
+    );
+    let response = reqwest::get(&url).await.expect("stream request failed");
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+    let body = response.text().await.unwrap();
+
+    let samples: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("line is not JSON"))
+        .collect();
+    assert_eq!(samples.len(), 5);
+    for (i, pair) in samples.windows(2).enumerate() {
+        assert_eq!(pair[0]["seq"], i as u64);
+        assert_eq!(pair[1]["seq"], i as u64 + 1);
+        let at = |s: &serde_json::Value| {
+            (
+                s["unix_seconds"].as_i64().unwrap(),
+                s["nanos"].as_u64().unwrap(),
+            )
+        };
+        assert!(at(&pair[1]) > at(&pair[0]), "{:?}", pair);
+    }
+    assert!(samples[0]["rfc3339"].is_string());
+    assert!(samples[0]["source"].is_string());
+
+    let error = get_request("/api/time/stream?interval_ms=fast").await;
+    assert!(error.unwrap_err().starts_with("HTTP 400"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_time_stream_stops_when_client_disconnects() {
+    use mcp_utc_time_server::http::stream::active_streams;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _server = start_test_server().await;
+    sleep(Duration::from_millis(500)).await;
+
+    let mut socket = tokio::net::TcpStream::connect(("127.0.0.1", TEST_PORT))
+        .await
+        .unwrap();
+    socket
+        .write_all(
+            b"GET /api/time/stream?interval_ms=50&count=10000 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut buf = [0u8; 1024];
+    let n = socket.read(&mut buf).await.unwrap();
+    assert!(String::from_utf8_lossy(&buf[..n]).starts_with("HTTP/1.1 200 OK"));
+    assert_eq!(active_streams(), 1);
+    drop(socket);
+
+    for _ in 0..20 {
+        if active_streams() == 0 {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("stream still running after the client disconnected");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_listeners_enforce_auth_separately_and_stop_on_shutdown() {
+    use mcp_utc_time_server::auth::ApiKey;
+    use mcp_utc_time_server::http::{start_http_api, HttpSettings, ListenerConfig};
+
+    let mut config = mcp_utc_time_server::ServerConfig::default();
+    config.http.listeners = vec![
+        ListenerConfig::parse("127.0.0.1:0;noauth").unwrap(),
+        ListenerConfig::parse("127.0.0.1:0;auth").unwrap(),
+    ];
+    config.auth.api_keys = vec![ApiKey {
+        key: "listener-test-key".into(),
+        name: Some("Key TEST".into()),
+        rate_limit: None,
+    }];
+
+    let server = start_http_api(&config, HttpSettings::from_config(&config))
+        .await
+        .expect("listeners bind");
+    let (open, secured) = (server.listeners()[0], server.listeners()[1]);
+    assert!(!open.auth && secured.auth);
+    assert_ne!(open.addr.port(), 0);
+
+    let client = reqwest::Client::new();
+    let get = |addr: std::net::SocketAddr, path: &str, key: Option<&str>| {
+        let mut request = client.get(format!("http://{}{}", addr, path));
+        if let Some(key) = key {
+            request = request.header("X-API-Key", key);
+        }
+        request.send()
+    };
+
+    assert_eq!(
+        get(open.addr, "/api/unix", None).await.unwrap().status(),
+        200
+    );
+
+    let denied = get(secured.addr, "/api/unix", None).await.unwrap();
+    assert_eq!(denied.status(), 401);
+    assert_eq!(denied.headers()["www-authenticate"], "Bearer");
+    let body: serde_json::Value = denied.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+
+    let wrong = get(secured.addr, "/api/unix", Some("not-a-key"))
+        .await
+        .unwrap();
+    assert_eq!(wrong.status(), 401);
+    let allowed = get(secured.addr, "/api/unix", Some("listener-test-key"))
+        .await
+        .unwrap();
+    assert_eq!(allowed.status(), 200);
+    // Probes stay open so orchestrators need no key
+    assert_eq!(
+        get(secured.addr, "/health", None).await.unwrap().status(),
+        200
+    );
+
+    server.shutdown().await;
+    for addr in [open.addr, secured.addr] {
+        assert!(
+            tokio::net::TcpStream::connect(addr).await.is_err(),
+            "{} still accepting after shutdown",
+            addr
+        );
+    }
+}
+
+const ADMIN_TOKEN: &str = "admin-test-token-0123";
+
+/// An HTTP API on an ephemeral port with `ADMIN_TOKEN` set to `token`
+async fn start_admin_server(
+    token: Option<&str>,
+    log_filter: Option<mcp_utc_time_server::http::admin::LogFilterHandle>,
+) -> mcp_utc_time_server::http::HttpServer {
+    use mcp_utc_time_server::http::{start_http_api, HttpSettings, ListenerConfig};
+
+    let mut config = mcp_utc_time_server::ServerConfig::default();
+    config.http.listeners = vec![ListenerConfig::parse("127.0.0.1:0").unwrap()];
+    config.http.admin_token = token.map(str::to_string);
+    let mut settings = HttpSettings::from_config(&config);
+    if let Some(handle) = log_filter {
+        settings = settings.with_log_filter(handle);
+    }
+    start_http_api(&config, settings)
+        .await
+        .expect("listener binds")
+}
+
+fn admin_request(
+    server: &mcp_utc_time_server::http::HttpServer,
+    method: reqwest::Method,
+    path: &str,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let url = format!("http://{}{}", server.listeners()[0].addr, path);
+    let request = reqwest::Client::new().request(method, url);
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_admin_routes_404_without_a_token() {
+    let server = start_admin_server(None, None).await;
+    for path in ["/admin/config", "/admin/keys", "/admin/loglevel"] {
+        let response = admin_request(&server, reqwest::Method::GET, path, Some(ADMIN_TOKEN))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404, "{}", path);
+    }
+    server.shutdown().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_admin_routes_need_the_admin_token() {
+    let server = start_admin_server(Some(ADMIN_TOKEN), None).await;
+
+    let missing = admin_request(&server, reqwest::Method::GET, "/admin/config", None)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 401);
+    assert_eq!(missing.headers()["www-authenticate"], "Bearer");
+    let wrong = admin_request(
+        &server,
+        reqwest::Method::POST,
+        "/admin/keys",
+        Some("admin-test-token-0124"),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(wrong.status(), 401);
+    let body: serde_json::Value = wrong.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+
+    let config = admin_request(
+        &server,
+        reqwest::Method::GET,
+        "/admin/config",
+        Some(ADMIN_TOKEN),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(config.status(), 200);
+    let text = config.text().await.unwrap();
+    assert!(!text.contains(ADMIN_TOKEN));
+    let config: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(config["http"]["admin_token"], "[redacted]");
+
+    // Without a reload layer the filter cannot change
+    let fixed = admin_request(
+        &server,
+        reqwest::Method::PUT,
+        "/admin/loglevel",
+        Some(ADMIN_TOKEN),
+    )
+    .body("debug")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(fixed.status(), 501);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_admin_key_reload_reads_the_environment() {
+    let server = start_admin_server(Some(ADMIN_TOKEN), None).await;
+    let keys = || {
+        admin_request(
+            &server,
+            reqwest::Method::GET,
+            "/admin/keys",
+            Some(ADMIN_TOKEN),
+        )
+    };
+
+    let before: serde_json::Value = keys().send().await.unwrap().json().await.unwrap();
+    assert_eq!(before["count"], 0);
+
+    std::env::set_var(
+        "API_KEY_ADMINTEST",
+        r#"{"key": "reloaded-secret", "name": "Reloaded", "rate_limit": 7}"#,
+    );
+    let reload = admin_request(
+        &server,
+        reqwest::Method::POST,
+        "/admin/keys",
+        Some(ADMIN_TOKEN),
+    )
+    .send()
+    .await
+    .unwrap();
+    std::env::remove_var("API_KEY_ADMINTEST");
+    assert_eq!(reload.status(), 200);
+    let reloaded: serde_json::Value = reload.json().await.unwrap();
+    assert_eq!(reloaded["reloaded"], true);
+
+    let after = keys().send().await.unwrap().text().await.unwrap();
+    assert!(!after.contains("reloaded-secret"));
+    let after: serde_json::Value = serde_json::from_str(&after).unwrap();
+    assert_eq!(after["count"], 1);
+    assert_eq!(
+        after["keys"][0],
+        serde_json::json!({"name": "Reloaded", "rate_limit": 7})
+    );
+    server.shutdown().await;
+}
+
+/// Log output kept in memory
+#[derive(Clone, Default)]
+struct CapturedLog(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLog {
+    fn contains(&self, text: &str) -> bool {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).contains(text)
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_admin_loglevel_takes_effect() {
+    use tracing_subscriber::{layer::SubscriberExt, reload, EnvFilter};
+
+    let log = CapturedLog::default();
+    let (filter, handle) = reload::Layer::new(EnvFilter::new("warn"));
+    let writer = log.clone();
+    let subscriber = tracing_subscriber::registry().with(filter).with(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || writer.clone()),
+    );
+    // The test runtime is single-threaded, so the server's tasks log here too
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let server = start_admin_server(Some(ADMIN_TOKEN), Some(handle)).await;
+    let unix = || admin_request(&server, reqwest::Method::GET, "/api/unix", None);
+
+    assert_eq!(unix().send().await.unwrap().status(), 200);
+    assert!(!log.contains("http.request"));
+
+    let put = admin_request(
+        &server,
+        reqwest::Method::PUT,
+        "/admin/loglevel",
+        Some(ADMIN_TOKEN),
+    )
+    .body("mcp_utc_time_server=debug")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(put.status(), 200);
+    let body: serde_json::Value = put.json().await.unwrap();
+    assert_eq!(body["filter"], "mcp_utc_time_server=debug");
+
+    assert_eq!(unix().send().await.unwrap().status(), 200);
+    assert!(log.contains("http.request"));
+
+    let invalid = admin_request(
+        &server,
+        reqwest::Method::PUT,
+        "/admin/loglevel",
+        Some(ADMIN_TOKEN),
+    )
+    .body("mcp_utc_time_server=loud")
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(invalid.status(), 400);
+    server.shutdown().await;
+}
@@ -67,7 +67,13 @@ fn test_timezone_conversion() {
 #[test]
 fn test_list_timezones() {
     let timezones = TimezoneConverter::list_timezones();
-    assert!(timezones.len() > 100);
+    if cfg!(feature = "timezones-minimal") {
+        // The CHRONO_TZ_TIMEZONE_FILTER allowlist of .cargo/config.toml and its links
+        assert!(timezones.len() < 100, "{} zones", timezones.len());
+        assert!(timezones.contains(&"UTC".to_string()));
+    } else {
+        assert!(timezones.len() > 100);
+    }
     assert!(timezones.contains(&"America/New_York".to_string()));
     assert!(timezones.contains(&"Europe/London".to_string()));
     assert!(timezones.contains(&"Asia/Tokyo".to_string()));
@@ -78,13 +84,13 @@ fn test_custom_format() {
     let response = EnhancedTimeResponse::now();
 
     // Test various custom formats
-    let date_only = response.format_custom("%Y-%m-%d").unwrap();
+    let date_only = response.format_custom("%Y-%m-%d", None).unwrap();
     assert_eq!(date_only.len(), 10);
 
-    let time_only = response.format_custom("%H:%M:%S").unwrap();
+    let time_only = response.format_custom("%H:%M:%S", None).unwrap();
     assert_eq!(time_only.len(), 8);
 
-    let unix_timestamp = response.format_custom("%s").unwrap();
+    let unix_timestamp = response.format_custom("%s", None).unwrap();
     assert!(unix_timestamp.parse::<i64>().is_ok());
 }
 
@@ -106,8 +112,8 @@ fn test_time_components() {
 fn test_time_conversions() {
     let unix_time = UnixTime::now();
 
-    let micros = unix_time.to_microseconds();
-    let millis = unix_time.to_milliseconds();
+    let micros = unix_time.to_microseconds().unwrap();
+    let millis = unix_time.to_milliseconds().unwrap();
 
     assert!(micros > unix_time.seconds * 1_000_000);
     assert!(millis > unix_time.seconds * 1000);
@@ -0,0 +1,2567 @@
+// End-to-end MCP tests: the rmcp server and an in-process client connected
+// over an in-memory pipe
+
+use mcp_utc_time_server::server_sdk::TimeServer;
+use mcp_utc_time_server::stats::ServerStats;
+use rmcp::model::{CallToolRequestParam, CallToolResult, LoggingMessageNotificationParam};
+use rmcp::service::{NotificationContext, RunningService, ServiceError};
+use rmcp::{ClientHandler, RoleClient, ServiceExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Client that forwards every logging notification to a channel
+#[derive(Clone)]
+struct LogCollector(mpsc::UnboundedSender<LoggingMessageNotificationParam>);
+
+impl ClientHandler for LogCollector {
+    async fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.0.send(params);
+    }
+}
+
+type Client = RunningService<RoleClient, LogCollector>;
+
+async fn connect() -> (
+    Client,
+    mpsc::UnboundedReceiver<LoggingMessageNotificationParam>,
+) {
+    connect_to(TimeServer::new()).await
+}
+
+async fn connect_to(
+    server: TimeServer,
+) -> (
+    Client,
+    mpsc::UnboundedReceiver<LoggingMessageNotificationParam>,
+) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let running = server
+            .serve(server_io)
+            .await
+            .expect("server failed to initialize");
+        let _ = running.waiting().await;
+    });
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let client = LogCollector(tx)
+        .serve(client_io)
+        .await
+        .expect("client failed to initialize");
+    (client, rx)
+}
+
+async fn call(client: &Client, name: &str, arguments: Value) -> Result<Value, String> {
+    call_raw(client, name, arguments)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Like `call`, keeping the JSON-RPC error
+async fn call_raw(client: &Client, name: &str, arguments: Value) -> Result<Value, ServiceError> {
+    let text = call_text(client, name, arguments).await?;
+    Ok(serde_json::from_str(&text).expect("tool output is not JSON"))
+}
+
+/// The tool's text content as returned
+async fn call_text(client: &Client, name: &str, arguments: Value) -> Result<String, ServiceError> {
+    let result: CallToolResult = client
+        .call_tool(CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: arguments.as_object().cloned(),
+        })
+        .await?;
+    Ok(result
+        .content
+        .first()
+        .and_then(|c| c.as_text())
+        .map(|t| t.text.clone())
+        .expect("tool returned no text content"))
+}
+
+#[tokio::test]
+async fn test_schedule_notification_fires() {
+    let (client, mut notifications) = connect().await;
+
+    let entry = call(
+        &client,
+        "schedule_notification",
+        json!({"label": "stand-up", "delay_seconds": 0.1}),
+    )
+    .await
+    .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+        .await
+        .expect("notification did not arrive")
+        .unwrap();
+    assert_eq!(message.logger.as_deref(), Some("scheduler"));
+    assert_eq!(message.data["id"], entry["id"]);
+    assert_eq!(message.data["label"], "stand-up");
+    let drift = message.data["drift_ms"].as_f64().unwrap();
+    assert!((0.0..250.0).contains(&drift), "drift {}ms", drift);
+
+    let listed = call(&client, "list_scheduled", json!({})).await.unwrap();
+    assert_eq!(listed["count"], 0);
+}
+
+/// The next heartbeat notification, skipping any other messages
+async fn next_heartbeat(
+    notifications: &mut mpsc::UnboundedReceiver<LoggingMessageNotificationParam>,
+) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .expect("heartbeat did not arrive")
+            .unwrap();
+        if message.logger.as_deref() == Some("heartbeat") {
+            return message.data;
+        }
+    }
+}
+
+/// Wait until no heartbeat task of `stats` is running
+async fn heartbeats_stopped(stats: &ServerStats) {
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while stats.heartbeat_tasks() != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("heartbeat task leaked");
+}
+
+#[tokio::test]
+async fn test_heartbeat_subscription() {
+    let stats = Arc::new(ServerStats::new());
+    let server = TimeServer::new()
+        .with_stats(stats.clone())
+        .with_min_heartbeat_interval(Duration::from_millis(50));
+    let (client, mut notifications) = connect_to(server).await;
+
+    let subscribed = call(
+        &client,
+        "subscribe_heartbeat",
+        json!({"interval_seconds": 0.1}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(subscribed["replaced"], false);
+    for sequence in 1..=3 {
+        let beat = next_heartbeat(&mut notifications).await;
+        assert_eq!(beat["sequence"], sequence);
+        assert_eq!(beat["interval_seconds"], 0.1);
+        assert!(beat["utc"].as_str().unwrap().ends_with('Z'));
+        assert!(beat.get("ntp_synced").is_some());
+    }
+    assert_eq!(stats.heartbeat_tasks(), 1);
+
+    // Subscribing again replaces the heartbeat rather than adding one
+    let resubscribed = call(
+        &client,
+        "subscribe_heartbeat",
+        json!({"interval_seconds": 0.2}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resubscribed["replaced"], true);
+    let beat = loop {
+        let beat = next_heartbeat(&mut notifications).await;
+        if beat["interval_seconds"] == 0.2 {
+            break beat;
+        }
+    };
+    assert_eq!(beat["sequence"], 1);
+    assert_eq!(stats.heartbeat_tasks(), 1);
+
+    let stopped = call(&client, "unsubscribe_heartbeat", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(stopped["unsubscribed"], true);
+    heartbeats_stopped(&stats).await;
+    while notifications.try_recv().is_ok() {}
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(
+        notifications.try_recv().is_err(),
+        "heartbeat after unsubscribe"
+    );
+    let again = call(&client, "unsubscribe_heartbeat", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(again["unsubscribed"], false);
+
+    assert!(call(
+        &client,
+        "subscribe_heartbeat",
+        json!({"interval_seconds": 301}),
+    )
+    .await
+    .unwrap_err()
+    .contains("between 0.05 and 300"));
+}
+
+#[tokio::test]
+async fn test_heartbeat_stops_when_session_ends() {
+    let stats = Arc::new(ServerStats::new());
+    let server = TimeServer::new()
+        .with_stats(stats.clone())
+        .with_min_heartbeat_interval(Duration::from_millis(50));
+    let (client, mut notifications) = connect_to(server).await;
+
+    call(
+        &client,
+        "subscribe_heartbeat",
+        json!({"interval_seconds": 0.1}),
+    )
+    .await
+    .unwrap();
+    next_heartbeat(&mut notifications).await;
+    assert_eq!(stats.heartbeat_tasks(), 1);
+
+    client.cancel().await.unwrap();
+    heartbeats_stopped(&stats).await;
+}
+
+#[tokio::test]
+async fn test_server_events_become_rate_limited_notifications() {
+    use mcp_utc_time_server::events::{self, ServerEvent};
+    use rmcp::model::{LoggingLevel, SetLevelRequestParam};
+
+    let (client, mut notifications) = connect().await;
+    // Debug is below every other session's default level, so only this one sees it
+    client
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Debug,
+        })
+        .await
+        .unwrap();
+
+    let synthetic = || {
+        ServerEvent::new("test.synthetic", LoggingLevel::Debug, "synthetic event")
+            .with_details(json!({"n": 1}))
+    };
+    events::publish(synthetic());
+    events::publish(synthetic());
+
+    let message = tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+        .await
+        .expect("notification did not arrive")
+        .unwrap();
+    assert_eq!(message.level, LoggingLevel::Debug);
+    assert_eq!(message.logger.as_deref(), Some("test"));
+    assert_eq!(message.data["kind"], "test.synthetic");
+    assert_eq!(message.data["message"], "synthetic event");
+    assert_eq!(message.data["details"]["n"], 1);
+    assert!(message.data["at"].is_string());
+
+    // The repeat within a minute is dropped
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(notifications.try_recv().is_err(), "duplicate was forwarded");
+}
+
+#[tokio::test]
+async fn test_cancel_scheduled_before_firing() {
+    let (client, mut notifications) = connect().await;
+
+    let entry = call(
+        &client,
+        "schedule_notification",
+        json!({"label": "never", "delay_seconds": 0.2}),
+    )
+    .await
+    .unwrap();
+    let listed = call(&client, "list_scheduled", json!({})).await.unwrap();
+    assert_eq!(listed["scheduled"][0]["label"], "never");
+
+    let cancelled = call(&client, "cancel_scheduled", json!({"id": entry["id"]}))
+        .await
+        .unwrap();
+    assert_eq!(cancelled["cancelled"], true);
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(notifications.try_recv().is_err(), "cancelled alarm fired");
+
+    // Cancelling twice is an error
+    assert!(
+        call(&client, "cancel_scheduled", json!({"id": entry["id"]}))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_schedule_notification_rejects_bad_input() {
+    let (client, _notifications) = connect().await;
+
+    for arguments in [
+        json!({"label": "both", "at": "2099-01-01T00:00:00Z", "delay_seconds": 1}),
+        json!({"label": "neither"}),
+        json!({"label": "past", "at": "2000-01-01T00:00:00Z"}),
+        json!({"label": "negative", "delay_seconds": -1}),
+    ] {
+        assert!(
+            call(&client, "schedule_notification", arguments.clone())
+                .await
+                .is_err(),
+            "accepted {}",
+            arguments
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_convert_time_uses_rules_at_instant() {
+    let (client, _notifications) = connect().await;
+
+    // 2010-07-01T12:00:00Z: Moscow was on summer time (MSD, +04:00)
+    let result = call(
+        &client,
+        "convert_time",
+        json!({"timestamp": 1277985600, "to_timezone": "Europe/Moscow"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["converted"]["offset"], 4 * 3600);
+    assert_eq!(result["converted"]["abbreviation"], "MSD");
+    assert_eq!(result["converted"]["is_dst"], true);
+    assert_eq!(
+        result["converted"]["rfc9557"],
+        "2010-07-01T16:00:00+04:00[Europe/Moscow]"
+    );
+
+    // 1965-01-01T00:00:00Z in New York: EST
+    let result = call(
+        &client,
+        "convert_time",
+        json!({"timestamp": -157766400, "to_timezone": "America/New_York"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["converted"]["abbreviation"], "EST");
+    assert_eq!(result["converted"]["is_dst"], false);
+    assert!(result.get("tzdata_caveat").is_none());
+    assert!(result.get("warnings").is_none());
+
+    // 2100-01-01: today's rules may not hold that far ahead
+    let result = call(
+        &client,
+        "convert_time",
+        json!({"timestamp": 4102444800i64, "to_timezone": "Europe/Paris"}),
+    )
+    .await
+    .unwrap();
+    assert!(result["tzdata_caveat"]
+        .as_str()
+        .unwrap()
+        .starts_with("2100-01-01 is "));
+    assert_eq!(result["warnings"][0]["code"], "TZDATA_FUTURE");
+    assert_eq!(result["warnings"][0]["message"], result["tzdata_caveat"]);
+}
+
+#[tokio::test]
+async fn test_convert_time_honors_from_timezone() {
+    let (client, _notifications) = connect().await;
+
+    // The timestamp is absolute; from_timezone only changes how it is shown
+    let result = call(
+        &client,
+        "convert_time",
+        json!({"timestamp": 1704067200, "from_timezone": "America/Chicago", "to_timezone": "Asia/Tokyo"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["original"]["formatted"], "2023-12-31T18:00:00-06:00");
+    assert_eq!(result["original"]["offset"], -6 * 3600);
+    assert_eq!(result["converted"]["timestamp"], 1704067200);
+    assert!(result["note"].as_str().unwrap().contains("absolute"));
+
+    let error = call(
+        &client,
+        "convert_time",
+        json!({"timestamp": 0, "from_timezone": "America/Chicgo", "to_timezone": "UTC"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("America/Chicago"), "{}", error);
+
+    // 01:30 happens twice in Chicago on 2024-11-03
+    let result = call(
+        &client,
+        "convert_time",
+        json!({
+            "local_datetime": "2024-11-03T01:30:00",
+            "from_timezone": "America/Chicago",
+            "to_timezone": "UTC",
+            "ambiguity": "later"
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["original"]["timestamp"], 1730619000);
+    assert_eq!(result["original"]["status"], "ambiguous");
+    assert_eq!(
+        result["converted"]["formatted"],
+        "2024-11-03T07:30:00+00:00"
+    );
+
+    let error = call(
+        &client,
+        "convert_time",
+        json!({"local_datetime": "2024-11-03T01:30:00", "from_timezone": "America/Chicago", "to_timezone": "UTC", "ambiguity": "reject"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("Ambiguous"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_explain_names_dst_choice() {
+    let (client, _notifications) = connect().await;
+    let args = json!({
+        "local_datetime": "2024-11-03T01:30:00",
+        "from_timezone": "America/New_York",
+        "to_timezone": "Asia/Tokyo",
+        "ambiguity": "earlier"
+    });
+
+    // Off by default: no explanation key at all
+    let plain = call(&client, "convert_time", args.clone()).await.unwrap();
+    assert!(plain.get("explanation").is_none());
+
+    let mut explained_args = args;
+    explained_args["explain"] = json!(true);
+    let explained = call(&client, "convert_time", explained_args).await.unwrap();
+    assert_eq!(explained["converted"], plain["converted"]);
+    let steps = explained["explanation"].as_array().unwrap();
+    let kinds: Vec<&str> = steps.iter().map(|s| s["step"].as_str().unwrap()).collect();
+    assert_eq!(
+        kinds,
+        [
+            "input",
+            "dst",
+            "offset_rule",
+            "arithmetic",
+            "offset_rule",
+            "arithmetic"
+        ]
+    );
+    assert_eq!(steps[1]["details"]["policy"], "earlier");
+    assert_eq!(
+        steps[1]["details"]["chosen"]["utc"],
+        "2024-11-03T05:30:00+00:00"
+    );
+    assert_eq!(steps[4]["details"]["timezone"], "Asia/Tokyo");
+    assert!(steps
+        .iter()
+        .all(|s| !s["text"].as_str().unwrap().is_empty()));
+
+    let resolved = call(
+        &client,
+        "local_to_utc",
+        json!({
+            "local_datetime": "2024-11-03T01:30:00",
+            "timezone": "America/New_York",
+            "ambiguity": "later",
+            "explain": true
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resolved["utc"], "2024-11-03T06:30:00+00:00");
+    let dst = &resolved["explanation"][1];
+    assert_eq!(dst["details"]["policy"], "later");
+    assert!(dst["text"]
+        .as_str()
+        .unwrap()
+        .contains("policy 'later' chose 2024-11-03T06:30:00+00:00"));
+}
+
+#[tokio::test]
+async fn test_data_versions() {
+    let (client, _notifications) = connect().await;
+    let versions = call(&client, "get_data_versions", json!({})).await.unwrap();
+    assert_eq!(versions["tzdata_version"].as_str().unwrap().len(), 5);
+    assert_eq!(versions["leap_seconds"]["tai_utc_offset"], 37);
+}
+
+#[tokio::test]
+async fn test_abbreviation_lookup_marks_ambiguity() {
+    let (client, _notifications) = connect().await;
+
+    let jst = call(
+        &client,
+        "abbreviation_lookup",
+        json!({"abbreviation": "JST"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(jst["unique"], true);
+    assert_eq!(jst["offsets"][0]["utc_offset"], "+09:00");
+
+    let ist = call(
+        &client,
+        "abbreviation_lookup",
+        json!({"abbreviation": "ist"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(ist["unique"], false);
+    assert_eq!(ist["meanings"].as_array().unwrap().len(), 3);
+
+    // 2010-07-01: Moscow still on summer time
+    let msd = call(
+        &client,
+        "abbreviation_lookup",
+        json!({"abbreviation": "MSD", "timestamp": 1277942400}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(msd["date"], "2010-07-01");
+    assert_eq!(msd["offsets"][0]["is_dst"], true);
+}
+
+#[tokio::test]
+async fn test_offset_timeline_segments() {
+    let (client, _notifications) = connect().await;
+
+    // 2014-06-01 to 2015-06-01: Moscow moves from +04 to +03 for good
+    let timeline = call(
+        &client,
+        "get_offset_timeline",
+        json!({"timezone": "Europe/Moscow", "start": 1401580800, "end": 1433116800}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(timeline["count"], 2);
+    assert_eq!(timeline["segments"][0]["offset_seconds"], 14400);
+    assert_eq!(timeline["segments"][1]["offset_seconds"], 10800);
+    assert_eq!(timeline["segments"][1]["to_unix"], 1433116800);
+}
+
+#[tokio::test]
+async fn test_business_time_reports_observed_holidays() {
+    let (client, _notifications) = connect().await;
+
+    // Independence Day 2026 is a Saturday, observed on Friday the 3rd
+    let observed = call(
+        &client,
+        "business_time",
+        json!({"date": "2026-07-03", "region": "us"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(observed["region"], "US");
+    assert_eq!(observed["is_holiday"], true);
+    assert_eq!(observed["is_business_day"], false);
+    assert_eq!(observed["holidays"][0]["name"], "Independence Day");
+    assert_eq!(observed["holidays"][0]["date"], "2026-07-04");
+    assert_eq!(observed["next_business_day"], "2026-07-06");
+
+    let no_region = call(&client, "business_time", json!({"date": "2026-07-03"}))
+        .await
+        .unwrap();
+    assert_eq!(no_region["is_business_day"], true);
+    assert!(no_region["region"].is_null());
+
+    let error = call(
+        &client,
+        "business_time",
+        json!({"date": "2026-07-03", "region": "Atlantis"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("US"), "{}", error);
+}
+
+#[cfg(not(feature = "timezones-minimal"))]
+#[tokio::test]
+async fn test_convert_calendar_both_directions() {
+    let (client, _notifications) = connect().await;
+
+    // 2019-05-01T00:00:00Z
+    let reiwa = call(
+        &client,
+        "convert_calendar",
+        json!({"calendar": "japanese", "timestamp": 1556668800}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reiwa["date"]["formatted"], "Reiwa 1-05-01");
+    assert_eq!(reiwa["date"]["native"], "令和元年5月1日");
+
+    let nowruz = call(
+        &client,
+        "convert_calendar",
+        json!({"calendar": "persian", "year": 1403, "month": 1, "day": 1, "timezone": "Asia/Tehran"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(nowruz["date"]["gregorian"], "2024-03-20");
+    // Midnight in Tehran (+03:30)
+    assert_eq!(nowruz["utc"], "2024-03-19T20:30:00+00:00");
+
+    let islamic = call(
+        &client,
+        "convert_calendar",
+        json!({"calendar": "islamic", "timestamp": 1710115200}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(islamic["date"]["month_name"], "Ramadan");
+    assert!(islamic["date"]["note"]
+        .as_str()
+        .unwrap()
+        .contains("Tabular"));
+
+    let missing_era = call(
+        &client,
+        "convert_calendar",
+        json!({"calendar": "japanese", "year": 1, "month": 5, "day": 1}),
+    )
+    .await
+    .unwrap_err();
+    assert!(missing_era.contains("era"), "{}", missing_era);
+}
+
+#[tokio::test]
+async fn test_convert_epoch_accepts_numbers_and_exact_strings() {
+    let (client, _notifications) = connect().await;
+
+    let result = call(
+        &client,
+        "convert_epoch",
+        json!({"value": "116444736000000000", "epoch": "filetime"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["unix_timestamp"], 0);
+    assert_eq!(result["utc"], "1970-01-01T00:00:00Z");
+    let excel = result["epochs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["epoch"] == "excel")
+        .unwrap();
+    assert_eq!(excel["value"], 25569);
+    assert_eq!(excel["unit"], "days");
+
+    let result = call(
+        &client,
+        "convert_epoch",
+        json!({"value": 25569.5, "epoch": "excel"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["unix_timestamp"], 43200);
+
+    let error = call(
+        &client,
+        "convert_epoch",
+        json!({"value": "1e30", "epoch": "unix"}),
+    )
+    .await;
+    assert!(error.is_err());
+}
+
+#[tokio::test]
+async fn test_timestamps_outside_years_1_to_9999_are_rejected() {
+    let (client, _notifications) = connect().await;
+
+    for (tool, arguments) in [
+        (
+            "convert_time",
+            json!({"timestamp": i64::MAX, "to_timezone": "UTC"}),
+        ),
+        (
+            "abbreviation_lookup",
+            json!({"abbreviation": "CET", "timestamp": i64::MIN}),
+        ),
+        ("get_week_number", json!({"timestamp": 253402300800_i64})),
+        (
+            "convert_calendar",
+            json!({"calendar": "hebrew", "timestamp": -62135596801_i64}),
+        ),
+        (
+            "email_date",
+            json!({"mode": "generate", "timestamp": 253402300800_i64}),
+        ),
+        (
+            "get_offset_timeline",
+            json!({"timezone": "UTC", "start": 253402300000_i64, "end": 253402400000_i64}),
+        ),
+        (
+            "convert_epoch",
+            json!({"value": "3155378976000000000", "epoch": "dotnet_ticks"}),
+        ),
+    ] {
+        let Err(ServiceError::McpError(error)) = call_raw(&client, tool, arguments).await else {
+            panic!("{} accepted an out-of-range timestamp", tool);
+        };
+        let data = error.data.unwrap();
+        assert_eq!(data["code"], "timestamp_out_of_range", "{}", tool);
+        assert_eq!(data["details"]["max_unix_seconds"], 253402300799_i64);
+    }
+
+    // The last second of 9999 converts, with nanoseconds as a string
+    let result = call(
+        &client,
+        "convert_epoch",
+        json!({"value": "253402300799.5", "epoch": "unix"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["utc"], "9999-12-31T23:59:59.500Z");
+    assert_eq!(result["unix_nanos"], "253402300799500000000");
+
+    let nanos = call(&client, "get_nanos", json!({})).await.unwrap();
+    let text = nanos["nanoseconds"].as_str().unwrap();
+    assert_eq!(
+        text.parse::<i128>().unwrap() / 1_000_000_000,
+        nanos["seconds"].as_i64().unwrap() as i128
+    );
+}
+
+#[tokio::test]
+async fn test_corrected_time_reports_correction_fields() {
+    let (client, _notifications) = connect().await;
+
+    let plain = call(&client, "get_unix_time", json!({})).await.unwrap();
+    assert!(plain["seconds"].is_i64());
+    assert!(plain.get("corrected").is_none());
+    // Default priority is shm,system; an SHM unit may hold a fresh sample
+    assert!(["shm", "system"].contains(&plain["source"].as_str().unwrap()));
+
+    // No ntpd in the test environment: the time is returned uncorrected
+    // with the reason attached
+    let corrected = call(&client, "get_unix_time", json!({"corrected": true}))
+        .await
+        .unwrap();
+    assert!(corrected["seconds"].is_i64());
+    assert_eq!(corrected["corrected"], false);
+    assert_eq!(corrected["correction_applied_ms"], 0.0);
+    assert!(corrected["correction_note"].is_string());
+
+    let time = call(&client, "get_time", json!({"corrected": true}))
+        .await
+        .unwrap();
+    assert!(time["iso8601"].is_string());
+    assert!(time.get("correction_applied_ms").is_some());
+    assert!(time["source"].is_string());
+}
+
+#[cfg(feature = "prompts")]
+#[tokio::test]
+async fn test_prompt_argument_completion() {
+    let (client, _notifications) = connect().await;
+
+    let capabilities = &client.peer_info().unwrap().capabilities;
+    assert!(capabilities.completions.is_some());
+
+    let completion = client
+        .complete_prompt_argument("time_in", "timezone", "Amer", None)
+        .await
+        .unwrap();
+    assert_eq!(completion.values.len(), 20);
+    assert_eq!(completion.has_more, Some(true));
+    assert!(completion.values.iter().all(|v| v.starts_with("America/")));
+
+    let values = client
+        .complete_prompt_simple("time_in", "timezone", "tok")
+        .await
+        .unwrap();
+    assert_eq!(values.first().map(String::as_str), Some("Asia/Tokyo"));
+
+    let formats = client
+        .complete_prompt_simple("format_time", "format", "")
+        .await
+        .unwrap();
+    assert!(formats.contains(&"%Y-%m-%dT%H:%M:%S%.f%:z".to_string()));
+}
+
+#[tokio::test]
+async fn test_tool_errors_carry_structured_codes() {
+    let (client, _notifications) = connect().await;
+
+    let error_data = |error: ServiceError| match error {
+        ServiceError::McpError(data) => data,
+        other => panic!("unexpected error {:?}", other),
+    };
+
+    let error = call_raw(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "Europe/Londn"}),
+    )
+    .await
+    .map(|_| ())
+    .map_err(error_data)
+    .unwrap_err();
+    assert_eq!(error.code.0, -32602);
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "invalid_timezone");
+    assert_eq!(data["details"]["suggestions"][0], "Europe/London");
+
+    let error = call_raw(&client, "get_time_formatted", json!({"format": "%Y %Q"}))
+        .await
+        .map(|_| ())
+        .map_err(error_data)
+        .unwrap_err();
+    assert_eq!(error.code.0, -32602);
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "invalid_format");
+    assert_eq!(data["details"]["offset"], 3);
+
+    // schedule_notification's own checks, and a delay far past its horizon
+    for (arguments, code) in [
+        (
+            json!({"label": "x", "delay_seconds": 1e20}),
+            "invalid_argument",
+        ),
+        (
+            json!({"label": "x", "delay_seconds": -1}),
+            "invalid_argument",
+        ),
+        (json!({"label": "x"}), "invalid_argument"),
+        (json!({"label": "x", "at": "tomorrow"}), "invalid_timestamp"),
+        (
+            json!({"label": "x", "at": "2001-01-01T00:00:00Z"}),
+            "invalid_argument",
+        ),
+    ] {
+        let error = call_raw(&client, "schedule_notification", arguments.clone())
+            .await
+            .map(|_| ())
+            .map_err(error_data)
+            .unwrap_err();
+        assert_eq!(error.code.0, -32602, "{}", arguments);
+        assert_eq!(error.data.unwrap()["code"], code, "{}", arguments);
+    }
+    // The session still answers after the oversized delay
+    assert!(call(&client, "list_scheduled", json!({})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_unknown_arguments_are_rejected_with_a_suggestion() {
+    let (client, _notifications) = connect().await;
+
+    let error = call_raw(&client, "get_time", json!({"time_zone": "Asia/Tokyo"}))
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    let ServiceError::McpError(error) = error else {
+        panic!("unexpected error {:?}", error);
+    };
+    assert_eq!(error.code.0, -32602);
+    assert!(
+        error.message.contains("did you mean 'timezone'"),
+        "{}",
+        error.message
+    );
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "unknown_field");
+    assert_eq!(data["details"]["suggestion"], "timezone");
+
+    // Flattened parameters are checked too
+    let error = call(&client, "set_preferences", json!({"timezon": "UTC"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("did you mean 'timezone'"), "{}", error);
+
+    // Well-formed calls are unaffected
+    let time = call(
+        &client,
+        "get_time",
+        json!({"timezone": "Asia/Tokyo", "pretty": false}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(time["timezone"], "Asia/Tokyo");
+
+    // Lenient mode drops the typo and falls back to the default, as before
+    let (lenient, _notifications) = connect_to(TimeServer::new().with_lenient_params(true)).await;
+    let time = call(&lenient, "get_time", json!({"time_zone": "Asia/Tokyo"}))
+        .await
+        .unwrap();
+    assert_eq!(time["timezone"], "UTC");
+}
+
+#[cfg(not(feature = "timezones-minimal"))]
+#[tokio::test]
+async fn test_parse_time_round_trips_and_applies_conflict_policy() {
+    let (client, _notifications) = connect().await;
+
+    let time = call(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "Asia/Kathmandu"}),
+    )
+    .await
+    .unwrap();
+    let rendered = time["rfc9557"].as_str().unwrap().to_string();
+    assert!(rendered.contains("+05:45[Asia/Kathmandu]"));
+    let parsed = call(&client, "parse_time", json!({"input": rendered}))
+        .await
+        .unwrap();
+    assert_eq!(parsed["rfc9557"], rendered);
+    assert_eq!(parsed["consistent"], true);
+
+    // Tokyo is never +01:00
+    let input = "2024-03-15T10:00:00+01:00[Asia/Tokyo]";
+    let parsed = call(&client, "parse_time", json!({"input": input}))
+        .await
+        .unwrap();
+    assert_eq!(parsed["resolved_by"], "offset");
+    assert_eq!(parsed["utc"], "2024-03-15T09:00:00Z");
+
+    let parsed = call(
+        &client,
+        "parse_time",
+        json!({"input": input, "conflict": "zone"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(parsed["resolved_by"], "zone");
+    assert_eq!(parsed["rfc9557"], "2024-03-15T10:00:00+09:00[Asia/Tokyo]");
+
+    let error = call(
+        &client,
+        "parse_time",
+        json!({"input": input, "conflict": "reject"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("does not match"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_pretty_false_returns_compact_json() {
+    let (client, _notifications) = connect().await;
+
+    let pretty = call_text(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "Asia/Tokyo"}),
+    )
+    .await
+    .unwrap();
+    let compact = call_text(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "Asia/Tokyo", "pretty": false}),
+    )
+    .await
+    .unwrap();
+
+    assert!(pretty.contains('\n'));
+    assert!(!compact.contains('\n'));
+    let value: Value = serde_json::from_str(&compact).unwrap();
+    assert_eq!(value["timezone"], "Asia/Tokyo");
+    let formats: Vec<&String> = value["custom_formats"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    assert_eq!(
+        formats,
+        ["apache_log", "syslog", "unix_date", "unix_timestamp"]
+    );
+}
+
+#[cfg(not(feature = "timezones-minimal"))]
+#[tokio::test]
+async fn test_session_preferences_fill_omitted_parameters() {
+    let (client, _notifications) = connect().await;
+    let (other, _other_notifications) = connect().await;
+
+    let set = call(
+        &client,
+        "set_preferences",
+        json!({"timezone": "australia/melbourne", "format": "%H:%M %Z", "precision": "millis"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(set["timezone"], "Australia/Melbourne");
+    assert_eq!(
+        call(&client, "get_preferences", json!({})).await.unwrap(),
+        set
+    );
+
+    let formatted = call(&client, "get_time_formatted", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(formatted["timezone"], "Australia/Melbourne");
+    assert_eq!(formatted["defaults_applied"], json!(["format", "timezone"]));
+    let zone = formatted["formatted"].as_str().unwrap();
+    assert!(zone.ends_with("AEST") || zone.ends_with("AEDT"), "{}", zone);
+
+    // An explicit argument wins and is not reported as a default
+    let explicit = call(
+        &client,
+        "get_time_formatted",
+        json!({"format": "%Y", "timezone": "UTC"}),
+    )
+    .await
+    .unwrap();
+    assert!(explicit.get("defaults_applied").is_none());
+
+    let time = call(&client, "get_time", json!({})).await.unwrap();
+    assert_eq!(time["timezone"], "Australia/Melbourne");
+    assert_eq!(time["defaults_applied"], json!(["timezone", "precision"]));
+    let fraction = time["rfc3339"].as_str().unwrap().split('.').nth(1).unwrap();
+    assert_eq!(fraction.len(), "123+10:00".len());
+
+    // The second session sees none of it
+    assert_eq!(
+        call(&other, "get_preferences", json!({})).await.unwrap(),
+        json!({})
+    );
+    let error = call(&other, "get_time_formatted", json!({}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("set_preferences"), "{}", error);
+    let time = call(&other, "get_time", json!({})).await.unwrap();
+    assert_eq!(time["timezone"], "UTC");
+
+    call(&client, "clear_preferences", json!({})).await.unwrap();
+    assert_eq!(
+        call(&client, "get_preferences", json!({})).await.unwrap(),
+        json!({})
+    );
+
+    let error = call(&client, "set_preferences", json!({"timezone": "Mars/Base"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("Mars/Base"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_time_formatted_in_a_timezone() {
+    let (client, _notifications) = connect().await;
+    let format = |arguments: Value| {
+        let client = &client;
+        async move { call(client, "get_time_formatted", arguments).await.unwrap() }
+    };
+
+    let tokyo = format(json!({"format": "%Z %z", "timezone": "Asia/Tokyo"})).await;
+    assert_eq!(tokyo["formatted"], "JST +0900");
+    assert_eq!(tokyo["timezone"], "Asia/Tokyo");
+    let kolkata = format(json!({"format": "%Z %z", "timezone": "Asia/Kolkata"})).await;
+    assert_eq!(kolkata["formatted"], "IST +0530");
+
+    // The same instant in both: the zone changes the wall clock only
+    let both = format(json!({"format": "%s %H:%M", "timezone": "Asia/Kolkata"})).await;
+    let seconds = both["unix_seconds"].as_i64().unwrap();
+    let wall = chrono::DateTime::from_timestamp(seconds + 19_800, 0).unwrap();
+    let minutes = both["formatted"]
+        .as_str()
+        .unwrap()
+        .split(' ')
+        .nth(1)
+        .unwrap();
+    assert_eq!(minutes, wall.format("%H:%M").to_string());
+
+    // Without a timezone nothing changes
+    let utc = format(json!({"format": "%Z %z"})).await;
+    assert_eq!(utc["formatted"], "UTC +0000");
+    assert!(utc.get("timezone").is_none());
+}
+
+#[tokio::test]
+async fn test_as_of_pins_the_instant_for_every_tool() {
+    let (client, _notifications) = connect().await;
+    let as_of = "2024-03-15T10:15:30Z";
+    let pinned = |tool: &'static str, mut arguments: Value| {
+        let client = &client;
+        arguments["as_of"] = json!(as_of);
+        async move { call(client, tool, arguments).await.unwrap() }
+    };
+
+    let time = pinned("get_time", json!({"timezone": "Asia/Tokyo"})).await;
+    let zoned = pinned("get_time_with_timezone", json!({"timezone": "Asia/Tokyo"})).await;
+    let formatted = pinned(
+        "get_time_formatted",
+        json!({"format": "%s %Y-%m-%dT%H:%M:%S%:z", "timezone": "Asia/Tokyo"}),
+    )
+    .await;
+    let clock = pinned("world_clock", json!({"timezones": "Asia/Tokyo"})).await;
+    let business = pinned("business_time", json!({"timezone": "Asia/Tokyo"})).await;
+    let card = pinned("timestamp_card", json!({"timestamp": 1710584130})).await;
+
+    for response in [&time, &zoned, &formatted, &clock, &business, &card] {
+        assert_eq!(response["reference_time"], as_of);
+        assert_eq!(response["reference_source"], "client");
+    }
+    assert_eq!(time["source"], "client");
+    assert!(time.get("correction").is_none());
+    // Both describe the same instant in the same zone, field for field
+    let zoned = zoned.as_object().unwrap();
+    for (key, value) in zoned {
+        if let Some(other) = time.get(key) {
+            assert_eq!(other, value, "{} differs", key);
+        }
+    }
+    assert_eq!(zoned["unix"]["seconds"], 1710497730);
+    assert_eq!(
+        formatted["formatted"],
+        "1710497730 2024-03-15T19:15:30+09:00"
+    );
+    assert_eq!(formatted["unix_seconds"], 1710497730);
+    assert_eq!(formatted["unix_nanos"], 0);
+    assert_eq!(clock["unix_timestamp"], 1710497730);
+    assert_eq!(clock["rows"][0]["local_time"], "19:15:30");
+    assert_eq!(business["date"], "2024-03-15");
+    assert_eq!(card["relative"], "in 1 day");
+
+    // Unix seconds name the same instant
+    let unix = call(
+        &client,
+        "world_clock",
+        json!({"timezones": "Asia/Tokyo", "as_of": 1710497730}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(unix["rows"], clock["rows"]);
+}
+
+#[tokio::test]
+async fn test_as_of_skew_limit_and_omission() {
+    use mcp_utc_time_server::config::{EnvVars, ServerConfig};
+
+    let config =
+        ServerConfig::from_vars(&EnvVars::from_pairs([("AS_OF_MAX_SKEW_SECS", "60")])).unwrap();
+    let (client, _notifications) = connect_to(TimeServer::with_config(&config)).await;
+
+    let error = call_raw(
+        &client,
+        "get_time",
+        json!({"as_of": "2024-03-15T10:15:30Z"}),
+    )
+    .await
+    .map(|_| ())
+    .unwrap_err();
+    let ServiceError::McpError(error) = error else {
+        panic!("unexpected error {:?}", error);
+    };
+    assert_eq!(error.code.0, -32602);
+    assert!(
+        error.message.contains("AS_OF_MAX_SKEW_SECS allows 60s"),
+        "{}",
+        error.message
+    );
+    assert_eq!(error.data.unwrap()["code"], "invalid_value");
+
+    let now = chrono::Utc::now().timestamp();
+    let close = call(&client, "get_time", json!({"as_of": now - 30}))
+        .await
+        .unwrap();
+    assert_eq!(close["unix"]["seconds"], now - 30);
+
+    // Without as_of the clock is read, and the response says so
+    let before = chrono::Utc::now();
+    let time = call(&client, "get_time", json!({})).await.unwrap();
+    assert_eq!(time["reference_source"], "server");
+    assert_ne!(time["source"], "client");
+    let reference: chrono::DateTime<chrono::Utc> =
+        time["reference_time"].as_str().unwrap().parse().unwrap();
+    assert!(reference >= before - chrono::Duration::seconds(1));
+    assert_eq!(time["unix"]["seconds"], reference.timestamp());
+}
+
+#[tokio::test]
+async fn test_time_formatted_gnu_dialect() {
+    let (client, _notifications) = connect().await;
+    let error_data = |error: ServiceError| match error {
+        ServiceError::McpError(data) => data,
+        other => panic!("unexpected error {:?}", other),
+    };
+
+    let gnu = call(
+        &client,
+        "get_time_formatted",
+        json!({"format": "+%s.%N %:z", "dialect": "gnu", "timezone": "Asia/Kolkata"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(gnu["dialect"], "gnu");
+    assert_eq!(gnu["chrono_format"], "%s.%9f %:z");
+    let formatted = gnu["formatted"].as_str().unwrap();
+    let (seconds, rest) = formatted.split_once('.').unwrap();
+    assert_eq!(seconds, gnu["unix_seconds"].to_string());
+    assert_eq!(rest.len(), "123456789 +05:30".len(), "{}", formatted);
+    assert!(rest.ends_with(" +05:30"), "{}", formatted);
+
+    // chrono is the default and has no %N
+    let error = call_raw(&client, "get_time_formatted", json!({"format": "%s.%N"}))
+        .await
+        .map(|_| ())
+        .map_err(error_data)
+        .unwrap_err();
+    assert_eq!(error.data.unwrap()["details"]["offset"], 3);
+
+    let error = call_raw(
+        &client,
+        "get_time_formatted",
+        json!({"format": "%^a %:::z", "dialect": "gnu"}),
+    )
+    .await
+    .map(|_| ())
+    .map_err(error_data)
+    .unwrap_err();
+    assert!(error.message.contains("%^a"), "{}", error.message);
+    assert!(error.message.contains("%:::z"), "{}", error.message);
+
+    let explained = call(
+        &client,
+        "explain_format",
+        json!({"format": "+%N", "dialect": "gnu"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(explained["dialect"], "gnu");
+    assert_eq!(explained["directives"][0]["directive"], "%N");
+}
+
+#[tokio::test]
+async fn test_csv_and_ics_exports() {
+    let (client, _notifications) = connect().await;
+
+    let csv = call_text(
+        &client,
+        "world_clock",
+        json!({"timezones": "Asia/Tokyo,UTC", "output_format": "csv"}),
+    )
+    .await
+    .unwrap();
+    let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(
+        rows[0],
+        "timezone,local_time,date,offset_seconds,utc_offset,day_difference"
+    );
+    assert!(rows[1].starts_with("UTC,"), "{}", rows[1]);
+    assert!(rows[2].starts_with("Asia/Tokyo,"), "{}", rows[2]);
+    assert!(call(
+        &client,
+        "world_clock",
+        json!({"timezones": "UTC", "output_format": "ics"}),
+    )
+    .await
+    .unwrap_err()
+    .contains("json and csv"));
+
+    let ics = call_text(
+        &client,
+        "get_dst_transitions",
+        json!({"timezone": "Europe/Paris", "year": 2024, "output_format": "ics"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    assert!(ics.contains("DTSTART;TZID=Europe/Paris:20240331T030000\r\n"));
+
+    // JSON stays the default and is unchanged by the parameter
+    let arguments = json!({"timezone": "Europe/Paris", "start": 1704067200, "end": 1735689600});
+    let default = call(&client, "get_offset_timeline", arguments.clone())
+        .await
+        .unwrap();
+    let mut explicit = arguments;
+    explicit["output_format"] = json!("json");
+    assert_eq!(
+        call(&client, "get_offset_timeline", explicit)
+            .await
+            .unwrap(),
+        default
+    );
+    assert_eq!(default["count"], 3);
+}
+
+#[tokio::test]
+async fn test_number_system_transliterates_digits() {
+    let (client, _notifications) = connect().await;
+    let error_data = |error: ServiceError| match error {
+        ServiceError::McpError(data) => data,
+        other => panic!("unexpected error {:?}", other),
+    };
+
+    let arab = call(
+        &client,
+        "get_time_formatted",
+        json!({"format": "Day %d of 12", "number_system": "arab"}),
+    )
+    .await
+    .unwrap();
+    let formatted = arab["formatted"].as_str().unwrap();
+    assert!(formatted.starts_with("Day "), "{}", formatted);
+    assert!(formatted.ends_with(" of 12"), "{}", formatted);
+    let day: Vec<char> = formatted["Day ".len()..formatted.len() - " of 12".len()]
+        .chars()
+        .collect();
+    assert_eq!(day.len(), 2);
+    assert!(day.iter().all(|c| ('\u{0660}'..='\u{0669}').contains(c)));
+    assert_eq!(arab["number_system"], "arab");
+
+    // The session locale's -u-nu- extension is the default
+    call(
+        &client,
+        "set_preferences",
+        json!({"locale": "hi-IN-u-nu-deva"}),
+    )
+    .await
+    .unwrap();
+    let deva = call(&client, "get_time_formatted", json!({"format": "%Y"}))
+        .await
+        .unwrap();
+    assert_eq!(deva["number_system"], "deva");
+    assert!(deva["formatted"]
+        .as_str()
+        .unwrap()
+        .chars()
+        .all(|c| ('\u{0966}'..='\u{096F}').contains(&c)));
+
+    let error = call_raw(
+        &client,
+        "get_time_formatted",
+        json!({"format": "%Y", "number_system": "roman"}),
+    )
+    .await
+    .map(|_| ())
+    .map_err(error_data)
+    .unwrap_err();
+    assert_eq!(error.code.0, -32602);
+    assert!(error.message.contains("roman"), "{}", error.message);
+}
+
+#[tokio::test]
+async fn test_fallbacks_carry_coded_warnings() {
+    let (client, _notifications) = connect().await;
+
+    let clean = call(&client, "get_time_formatted", json!({"format": "%A %B"}))
+        .await
+        .unwrap();
+    assert!(clean.get("warnings").is_none(), "{}", clean);
+
+    // Names are English whatever the session locale says
+    call(&client, "set_preferences", json!({"locale": "de-DE"}))
+        .await
+        .unwrap();
+    let names = call(&client, "get_time_formatted", json!({"format": "%A %B"}))
+        .await
+        .unwrap();
+    assert_eq!(names["warnings"][0]["code"], "LOCALE_FALLBACK");
+    assert_eq!(names["warnings"][0]["field"], "locale");
+    let digits = call(&client, "get_time_formatted", json!({"format": "%Y-%m-%d"}))
+        .await
+        .unwrap();
+    assert!(digits.get("warnings").is_none());
+
+    // No ntpd in the test environment: a requested correction falls back
+    let corrected = call(&client, "get_time", json!({"corrected": true}))
+        .await
+        .unwrap();
+    if corrected["source"] == "system" {
+        let code = corrected["warnings"][0]["code"].as_str().unwrap();
+        assert!(
+            ["OFFSET_UNAVAILABLE", "OFFSET_STALE"].contains(&code),
+            "{}",
+            code
+        );
+        assert_eq!(corrected["warnings"][0]["field"], "corrected");
+    }
+
+    #[cfg(feature = "ntp")]
+    {
+        use mcp_utc_time_server::NtpSyncedClock;
+        let status = call(&client, "get_ntp_status", json!({})).await.unwrap();
+        if NtpSyncedClock::is_container_environment() {
+            assert_eq!(status["warnings"][0]["code"], "NTP_CONTAINER_ASSUMED");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_world_clock_tool_and_prompt_share_one_instant() {
+    use rmcp::model::{GetPromptRequestParam, PromptMessageContent};
+
+    let (client, _notifications) = connect().await;
+
+    let clock = call(
+        &client,
+        "world_clock",
+        json!({"timezones": "Asia/Tokyo, UTC, America/New_York"}),
+    )
+    .await
+    .unwrap();
+    let rows = clock["rows"].as_array().unwrap();
+    let zones: Vec<&str> = rows
+        .iter()
+        .map(|r| r["timezone"].as_str().unwrap())
+        .collect();
+    assert_eq!(zones, ["America/New_York", "UTC", "Asia/Tokyo"]);
+    let utc_row = &rows[1];
+    assert_eq!(
+        clock["utc"].as_str().unwrap()[11..19],
+        *utc_row["local_time"].as_str().unwrap()
+    );
+    assert_eq!(clock["table"].as_str().unwrap().lines().count(), 5);
+
+    let error = call(
+        &client,
+        "world_clock",
+        json!({"timezones": "UTC,Mars/Base"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("Mars/Base"), "{}", error);
+
+    if !cfg!(feature = "prompts") {
+        return;
+    }
+    let prompt = client
+        .get_prompt(GetPromptRequestParam {
+            name: "world_clock".into(),
+            arguments: json!({"timezones": "UTC,Asia/Kolkata"})
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .unwrap();
+    let PromptMessageContent::Text { text } = &prompt.messages[0].content else {
+        panic!("prompt returned non-text content");
+    };
+    assert!(text.contains("same instant"));
+    assert!(text.contains("Asia/Kolkata  "));
+    assert!(text.contains("+05:30"));
+
+    let values = client
+        .complete_prompt_simple("world_clock", "timezones", "UTC,tok")
+        .await
+        .unwrap();
+    assert_eq!(values.first().map(String::as_str), Some("UTC,Asia/Tokyo"));
+}
+
+#[cfg(not(feature = "timezones-minimal"))]
+#[tokio::test]
+async fn test_examples_match_live_responses() {
+    use mcp_utc_time_server::mcp::examples::{shape_mismatch, Host, EXAMPLES};
+
+    let (client, _) = connect().await;
+    #[cfg(feature = "ntp")]
+    let container = mcp_utc_time_server::NtpSyncedClock::is_container_environment();
+    #[cfg(not(feature = "ntp"))]
+    let container = false;
+
+    // Every tool has at least one example and its arguments in the schema
+    let tools = client.list_all_tools().await.unwrap();
+    for tool in &tools {
+        assert!(
+            EXAMPLES.iter().any(|e| e.tool == tool.name),
+            "no example for {}",
+            tool.name
+        );
+    }
+    let convert = tools.iter().find(|t| t.name == "convert_time").unwrap();
+    assert_eq!(
+        convert.input_schema["examples"][0]["to_timezone"],
+        "America/New_York"
+    );
+
+    for example in EXAMPLES {
+        let label = format!("{} ({})", example.tool, example.summary);
+        if example.host == Host::Geo && !cfg!(feature = "geo") {
+            continue;
+        }
+        // Tools of features left out of this build
+        if !tools.iter().any(|tool| tool.name == example.tool) {
+            continue;
+        }
+        let actual = match call_raw(&client, example.tool, example.arguments()).await {
+            Ok(response) => {
+                assert!(!example.is_error(), "{} succeeded", label);
+                response
+            }
+            Err(ServiceError::McpError(error)) => {
+                assert!(example.is_error(), "{} failed: {:?}", label, error);
+                serde_json::to_value(error).unwrap()
+            }
+            Err(other) => panic!("{}: {}", label, other),
+        };
+        if example.host == Host::Container && !container {
+            continue;
+        }
+        let expected = example.expected();
+        if example.is_error() {
+            assert_eq!(actual["code"], expected["code"], "{}", label);
+        }
+        if let Some(path) = shape_mismatch(&expected, &actual) {
+            panic!(
+                "{}: response no longer matches the example: {}",
+                label, path
+            );
+        }
+    }
+
+    let catalog = call(&client, "get_examples", json!({})).await.unwrap();
+    assert_eq!(catalog["count"], EXAMPLES.len());
+    let error = call(&client, "get_examples", json!({"tool": "get_weather"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("get_weather"), "{}", error);
+}
+
+#[cfg(feature = "ntp")]
+#[tokio::test]
+async fn test_ntp_history_summarizes_recorded_samples() {
+    use mcp_utc_time_server::ntp::history::{NtpHistory, NtpHistoryConfig, NtpSample};
+    use mcp_utc_time_server::state::ServerState;
+    use mcp_utc_time_server::NtpSyncedClock;
+    use std::sync::Arc;
+
+    let history = Arc::new(NtpHistory::new(NtpHistoryConfig::with_interval(60)));
+    let now = chrono::Utc::now().timestamp() / 3600 * 3600;
+    // Two hours at one sample a minute; sync lost for ten minutes in the second
+    for i in 0..120 {
+        let synced = !(70..80).contains(&i);
+        assert!(history.record(NtpSample {
+            timestamp: now - 7200 + i * 60,
+            offset_ms: if i == 90 { -42.0 } else { (i % 5) as f64 * 0.5 },
+            stratum: if synced { 2 } else { 16 },
+            synced,
+        }));
+    }
+    let clock = NtpSyncedClock::new().with_history(history);
+    let state = Arc::new(ServerState::with_ntp_clock(Arc::new(clock)));
+    let (client, _) = connect_to(TimeServer::new().with_state(state)).await;
+
+    let since = chrono::DateTime::from_timestamp(now - 1800, 0).unwrap();
+    let raw = call(
+        &client,
+        "get_ntp_history",
+        json!({"since": since.to_rfc3339()}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(raw["samples"].as_array().unwrap().len(), 30);
+    assert!(raw["buckets"].is_null());
+
+    let hourly = call(&client, "get_ntp_history", json!({"resolution": 3600}))
+        .await
+        .unwrap();
+    assert!(hourly["samples"].is_null());
+    let buckets = hourly["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0]["samples"], 60);
+    assert_eq!(buckets[0]["synced_percent"], 100.0);
+    assert_eq!(buckets[1]["min_offset_ms"], -42.0);
+    assert_eq!(buckets[1]["synced_percent"], 83.3);
+
+    let summary = &hourly["summary"];
+    assert_eq!(summary["samples"], 120);
+    assert_eq!(summary["max_offset_ms"], 42.0);
+    assert_eq!(summary["p95_offset_ms"], 2.0);
+    assert_eq!(summary["synced_percent"], 91.7);
+
+    let error = call(&client, "get_ntp_history", json!({"since": "last week"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("since"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_email_date_round_trip() {
+    let (client, _) = connect().await;
+
+    let generated = call(
+        &client,
+        "email_date",
+        json!({"mode": "generate", "timestamp": 1057049557, "timezone": "Europe/Berlin"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(generated["date"], "Tue, 01 Jul 2003 10:52:37 +0200");
+    assert_eq!(generated["header"], "Date: Tue, 01 Jul 2003 10:52:37 +0200");
+
+    let parsed = call(
+        &client,
+        "email_date",
+        json!({"mode": "parse", "input": generated["header"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(parsed["unix_seconds"], 1057049557);
+    assert_eq!(parsed["offset"], "+02:00");
+    assert_eq!(parsed["notes"], json!([]));
+
+    let now = call(&client, "email_date", json!({"mode": "generate"}))
+        .await
+        .unwrap();
+    assert!(now["date"].as_str().unwrap().ends_with(" +0000"), "{}", now);
+
+    let error = call(&client, "email_date", json!({"mode": "parse"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("input"), "{}", error);
+    let error = call(
+        &client,
+        "email_date",
+        json!({"mode": "parse", "input": "next Tuesday"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("next Tuesday"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_selftest_benchmark_reports_consistent_latencies() {
+    let (client, _) = connect().await;
+    let error = call(
+        &client,
+        "run_selftest_benchmark",
+        json!({"tool": "get_unix_time"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("ENABLE_BENCH_TOOL"), "{}", error);
+
+    let (client, _) = connect_to(TimeServer::new().with_bench_tool(true)).await;
+    let stats = call(
+        &client,
+        "run_selftest_benchmark",
+        json!({"tool": "get_unix_time", "iterations": 100}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(stats["tool"], "get_unix_time");
+    assert_eq!(stats["iterations"], 100);
+    assert_eq!(stats["errors"], 0);
+    let ns = |key: &str| stats[key].as_u64().unwrap();
+    assert!(ns("min_ns") <= ns("p50_ns"), "{}", stats);
+    assert!(ns("p50_ns") <= ns("p95_ns"), "{}", stats);
+    assert!(ns("p95_ns") <= ns("p99_ns"), "{}", stats);
+    assert!(ns("p99_ns") <= ns("max_ns"), "{}", stats);
+    let mean = stats["mean_ns"].as_f64().unwrap();
+    assert!(ns("min_ns") as f64 <= mean && mean <= ns("max_ns") as f64);
+    assert!(stats["calls_per_second"].as_f64().unwrap() > 0.0);
+
+    // Tools that shell out need allow_external
+    if cfg!(feature = "ntp") {
+        let error = call(
+            &client,
+            "run_selftest_benchmark",
+            json!({"tool": "get_ntp_peers"}),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.contains("allow_external"), "{}", error);
+    }
+
+    let error = call(
+        &client,
+        "run_selftest_benchmark",
+        json!({"tool": "no_such_tool"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("no_such_tool"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_maintenance_window_is_listed_and_flagged() {
+    use mcp_utc_time_server::config::{EnvVars, ServerConfig};
+
+    // Unset: no tool, no warnings
+    let (client, _) = connect().await;
+    let tools = client.list_all_tools().await.unwrap();
+    assert!(tools
+        .iter()
+        .all(|tool| tool.name != "get_maintenance_window"));
+    assert!(call(&client, "get_maintenance_window", json!({}))
+        .await
+        .is_err());
+    let time = call(&client, "get_time", json!({})).await.unwrap();
+    assert!(time.get("warnings").is_none(), "{}", time);
+
+    // A one-minute window every minute is always open
+    let config = ServerConfig::from_vars(&EnvVars::from_pairs([(
+        "MAINTENANCE_WINDOW",
+        "* * * * * 1m",
+    )]))
+    .unwrap();
+    let (client, _) = connect_to(TimeServer::with_config(&config)).await;
+    let result = call(&client, "get_maintenance_window", json!({"count": 2}))
+        .await
+        .unwrap();
+    assert_eq!(result["schedule"], "* * * * *");
+    assert_eq!(result["duration_seconds"], 60);
+    assert_eq!(result["active"], true);
+    assert_eq!(result["windows"].as_array().unwrap().len(), 2);
+    // The recorded example is only checked here, where the tool is listed;
+    // this window is always open, so the result also carries its warning
+    let mut result = result;
+    assert_eq!(
+        result.as_object_mut().unwrap().remove("warnings").unwrap()[0]["code"],
+        "MAINTENANCE_ACTIVE"
+    );
+    let example = mcp_utc_time_server::mcp::examples::examples(Some("get_maintenance_window"))[0];
+    assert_eq!(
+        mcp_utc_time_server::mcp::examples::shape_mismatch(&example.expected(), &result),
+        None
+    );
+
+    let time = call(&client, "get_time", json!({})).await.unwrap();
+    assert_eq!(time["warnings"][0]["code"], "MAINTENANCE_ACTIVE");
+    assert!(time["unix"].is_object(), "{}", time);
+    // Errors pass through unchanged
+    let error = call(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "Mars/Base"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("Mars/Base"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_responses_carry_validity_windows() {
+    use mcp_utc_time_server::config::{EnvVars, ServerConfig};
+    use mcp_utc_time_server::time::TimezoneConverter;
+
+    let config =
+        ServerConfig::from_vars(&EnvVars::from_pairs([("CURRENT_TIME_VALID_MS", "250")])).unwrap();
+    let (client, _) = connect_to(TimeServer::with_config(&config)).await;
+
+    for tool in ["get_time", "get_unix_time"] {
+        let result = call(&client, tool, json!({})).await.unwrap();
+        assert_eq!(result["valid_for_ms"], 250, "{}", tool);
+        let expires_at: chrono::DateTime<chrono::Utc> =
+            result["expires_at"].as_str().unwrap().parse().unwrap();
+        assert!(expires_at > chrono::Utc::now() - chrono::Duration::seconds(5));
+    }
+
+    // Offset fields hold until New York's next transition
+    let result = call(
+        &client,
+        "get_time_with_timezone",
+        json!({"timezone": "America/New_York"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["valid_for_ms"], 250);
+    let expires_at: chrono::DateTime<chrono::Utc> = result["offset_validity"]["expires_at"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let next = TimezoneConverter::next_transition(
+        "America/New_York",
+        chrono::Utc::now() - chrono::Duration::minutes(1),
+        2 * 366 * 86400,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(expires_at.timestamp(), next.unix_timestamp);
+
+    let listing = call(&client, "list_timezones", json!({})).await.unwrap();
+    assert_eq!(listing["valid_for_ms"], 365 * 86_400_000u64);
+}
+
+#[tokio::test]
+async fn test_timezones_if_changed() {
+    let (client, _) = connect().await;
+
+    let first = call(&client, "list_timezones", json!({})).await.unwrap();
+    let second = call(&client, "list_timezones", json!({"prefix": "Europe/"}))
+        .await
+        .unwrap();
+    let checksum = first["checksum"].as_str().unwrap();
+    assert_eq!(second["checksum"], checksum);
+
+    let unchanged = call(
+        &client,
+        "get_timezones_if_changed",
+        json!({"checksum": checksum}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(unchanged["unchanged"], true);
+    assert_eq!(unchanged["checksum"], checksum);
+    assert!(unchanged.get("timezones").is_none());
+
+    for stale in [json!({"checksum": "0000000000000000"}), json!({})] {
+        let changed = call(&client, "get_timezones_if_changed", stale)
+            .await
+            .unwrap();
+        assert_eq!(changed["unchanged"], false);
+        assert_eq!(changed["checksum"], checksum);
+        assert_eq!(changed["timezones"], first["timezones"]);
+    }
+}
+
+#[tokio::test]
+async fn test_signed_time_verifies_and_detects_tampering() {
+    let (client, _) = connect().await;
+
+    let first = call(&client, "get_signed_time", json!({})).await.unwrap();
+    let second = call(&client, "get_signed_time", json!({})).await.unwrap();
+    assert!(second["sequence"].as_u64() > first["sequence"].as_u64());
+    assert_eq!(first["algorithm"], "ed25519");
+
+    let key = call(&client, "get_signing_key", json!({})).await.unwrap();
+    assert_eq!(key["public_key"], first["public_key"]);
+
+    let verified = call(
+        &client,
+        "verify_signed_time",
+        json!({"attestation": first, "public_key": key["public_key"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(verified["valid"], true, "{}", verified);
+    assert_eq!(verified["sequence"], first["sequence"]);
+
+    let mut tampered = first.clone();
+    tampered["time"]["seconds"] = json!(first["time"]["seconds"].as_i64().unwrap() + 3600);
+    let rejected = call(
+        &client,
+        "verify_signed_time",
+        json!({"attestation": tampered}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(rejected["valid"], false);
+    assert!(rejected["reason"].as_str().unwrap().contains("canonical"));
+}
+
+#[tokio::test]
+async fn test_trusted_timestamp_without_a_tsa() {
+    let (client, _) = connect().await;
+    let listed = client
+        .list_all_tools()
+        .await
+        .unwrap()
+        .iter()
+        .any(|tool| tool.name == "get_trusted_timestamp");
+    let instructions = client.peer_info().unwrap().instructions.clone().unwrap();
+    assert_eq!(instructions.contains("get_trusted_timestamp"), listed);
+    // Without the feature there is no TSA to ask, so the tool is left out
+    if !cfg!(feature = "tsa") {
+        assert!(!listed);
+        assert!(
+            call(&client, "get_trusted_timestamp", json!({"digest": "abc"}))
+                .await
+                .is_err()
+        );
+        return;
+    }
+    let error_data = |error: ServiceError| match error {
+        ServiceError::McpError(data) => data,
+        other => panic!("unexpected error {:?}", other),
+    };
+
+    let error = call_raw(&client, "get_trusted_timestamp", json!({"digest": "abc"}))
+        .await
+        .map(|_| ())
+        .map_err(error_data)
+        .unwrap_err();
+    assert_eq!(error.data.unwrap()["details"]["field"], "digest");
+
+    // None is configured
+    let digest = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+    let error = call_raw(&client, "get_trusted_timestamp", json!({"digest": digest}))
+        .await
+        .map(|_| ())
+        .map_err(error_data)
+        .unwrap_err();
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "tsa_unavailable");
+    assert_eq!(data["details"]["reason"], "TSA_URL is not set");
+}
+
+#[cfg(feature = "ntp")]
+#[tokio::test]
+async fn test_disabled_tools_are_unlisted_and_refused() {
+    use mcp_utc_time_server::mcp::policy::ToolPolicy;
+
+    let server = TimeServer::new()
+        .with_disabled_tools(ToolPolicy::disabling(["get_ntp_peers", "get_ntp_status"]));
+    let (client, _) = connect_to(server).await;
+
+    let tools = client.list_all_tools().await.unwrap();
+    let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+    assert!(!names.contains(&"get_ntp_peers"));
+    assert!(!names.contains(&"get_ntp_status"));
+    assert!(names.contains(&"get_ntp_history"));
+
+    // The instructions name the same tools
+    let instructions = client.peer_info().unwrap().instructions.clone().unwrap();
+    assert!(!instructions.contains("get_ntp_peers"), "{}", instructions);
+    assert!(!instructions.contains("get_ntp_status"), "{}", instructions);
+    for name in &names {
+        assert!(
+            instructions.contains(name),
+            "{} missing: {}",
+            name,
+            instructions
+        );
+    }
+
+    let error_data = |error: ServiceError| match error {
+        ServiceError::McpError(data) => data,
+        other => panic!("unexpected error {:?}", other),
+    };
+    let error = call_raw(&client, "get_ntp_peers", json!({}))
+        .await
+        .map(|_| ())
+        .map_err(error_data)
+        .unwrap_err();
+    assert_eq!(error.code.0, -32004);
+    assert!(error.message.contains("disabled by server policy"));
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "tool_disabled");
+    assert_eq!(data["details"]["tool"], "get_ntp_peers");
+
+    let time = call(&client, "get_unix_time", json!({})).await.unwrap();
+    assert!(time["seconds"].as_i64().unwrap() > 0);
+
+    let info = call(&client, "get_server_info", json!({})).await.unwrap();
+    assert_eq!(
+        info["disabled_tools"],
+        json!(["get_ntp_peers", "get_ntp_status"])
+    );
+}
+
+#[tokio::test]
+async fn test_tool_budgets_are_listed_and_enforced() {
+    use mcp_utc_time_server::config::EnvVars;
+    use mcp_utc_time_server::mcp::timeouts::ToolTimeouts;
+
+    let env = EnvVars::from_pairs([("TOOL_TIMEOUT_RUN_SELFTEST_BENCHMARK_MS", "50")]);
+    let server = TimeServer::new()
+        .with_bench_tool(true)
+        .with_tool_timeouts(ToolTimeouts::from_vars(&env).unwrap());
+    let (client, _) = connect_to(server).await;
+
+    let tools = client.list_all_tools().await.unwrap();
+    let budget = |name: &str| {
+        tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .unwrap()
+            .input_schema["x-timeout-ms"]
+            .as_u64()
+    };
+    assert_eq!(budget("get_unix_time"), Some(2000));
+    if cfg!(feature = "ntp") {
+        assert_eq!(budget("get_ntp_peers"), Some(10000));
+    }
+    assert_eq!(budget("run_selftest_benchmark"), Some(50));
+    assert!(tools
+        .iter()
+        .all(|tool| tool.input_schema.contains_key("x-timeout-ms")));
+
+    let started = std::time::Instant::now();
+    let error = match call_raw(
+        &client,
+        "run_selftest_benchmark",
+        json!({"tool": "get_time", "iterations": 100000}),
+    )
+    .await
+    {
+        Err(ServiceError::McpError(data)) => data,
+        other => panic!("unexpected result {:?}", other),
+    };
+    assert!(started.elapsed() < Duration::from_millis(50 + 1000));
+    assert_eq!(error.code.0, -32000);
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "deadline_exceeded");
+    assert_eq!(data["details"]["tool"], "run_selftest_benchmark");
+    assert_eq!(data["details"]["budget_ms"], 50);
+}
+
+#[tokio::test]
+async fn test_tools_carry_annotations() {
+    use mcp_utc_time_server::mcp::annotations::hints;
+    use rmcp::model::ToolAnnotations;
+
+    let (client, _) = connect().await;
+    let tools = client.list_all_tools().await.unwrap();
+    // Read back from the wire form, not the server's own structs
+    let listed: Vec<Value> = tools
+        .iter()
+        .map(|tool| serde_json::to_value(tool).unwrap())
+        .collect();
+    let annotations = |name: &str| -> ToolAnnotations {
+        let tool = listed.iter().find(|tool| tool["name"] == name).unwrap();
+        serde_json::from_value(tool["annotations"].clone()).unwrap()
+    };
+    for tool in &listed {
+        let name = tool["name"].as_str().unwrap();
+        assert_eq!(
+            Some(annotations(name)),
+            hints(name).map(|hints| hints.annotations()),
+            "{}",
+            name
+        );
+    }
+
+    let get_time = annotations("get_time");
+    assert_eq!(get_time.read_only_hint, Some(true));
+    assert_eq!(get_time.idempotent_hint, Some(true));
+    assert_eq!(get_time.open_world_hint, Some(false));
+    let set_preferences = annotations("set_preferences");
+    assert_eq!(set_preferences.read_only_hint, Some(false));
+    assert_eq!(set_preferences.destructive_hint, Some(false));
+    assert_eq!(set_preferences.idempotent_hint, Some(true));
+    let schedule = annotations("schedule_notification");
+    assert_eq!(schedule.read_only_hint, Some(false));
+    assert_eq!(schedule.idempotent_hint, Some(false));
+    assert_eq!(
+        annotations("clear_preferences").destructive_hint,
+        Some(true)
+    );
+    // Listed only in tsa builds
+    assert_eq!(
+        hints("get_trusted_timestamp")
+            .unwrap()
+            .annotations()
+            .open_world_hint,
+        Some(true)
+    );
+    let read_only = listed
+        .iter()
+        .filter(|tool| tool["annotations"]["readOnlyHint"] == true)
+        .count();
+    assert_eq!(read_only, listed.len() - 7);
+}
+
+/// Milliseconds `mock_instant` has advanced past its first reading
+static MOCK_ELAPSED_MS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+fn mock_instant() -> std::time::Instant {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    *START.get_or_init(std::time::Instant::now)
+        + Duration::from_millis(MOCK_ELAPSED_MS.load(std::sync::atomic::Ordering::SeqCst))
+}
+
+#[tokio::test]
+async fn test_rate_limit_refuses_then_recovers() {
+    use mcp_utc_time_server::mcp::rate_limit::{RateLimit, RateLimiter};
+
+    let limiter = RateLimiter::new(RateLimit {
+        per_second: 2,
+        burst: 3,
+    })
+    .with_clock(mock_instant);
+    let (client, _) = connect_to(TimeServer::new().with_rate_limiter(Some(limiter))).await;
+
+    let mut refused = Vec::new();
+    for _ in 0..3 + 5 {
+        match call_raw(&client, "get_unix_time", json!({})).await {
+            Ok(_) => {}
+            Err(ServiceError::McpError(error)) => refused.push(error),
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+    assert_eq!(refused.len(), 5);
+    for error in &refused {
+        assert_eq!(error.code.0, -32006);
+        let data = error.data.as_ref().unwrap();
+        assert_eq!(data["code"], "rate_limited");
+        // Two calls a second: the next token is 500 ms away
+        assert_eq!(data["details"]["retry_after_ms"], 500);
+    }
+
+    MOCK_ELAPSED_MS.fetch_add(500, std::sync::atomic::Ordering::SeqCst);
+    assert!(call(&client, "get_unix_time", json!({})).await.is_ok());
+    assert!(call(&client, "get_unix_time", json!({})).await.is_err());
+
+    let stats = call(&client, "get_process_stats", json!({})).await;
+    assert!(stats.is_err(), "the bucket is still empty");
+    MOCK_ELAPSED_MS.fetch_add(1000, std::sync::atomic::Ordering::SeqCst);
+    let stats = call(&client, "get_process_stats", json!({})).await.unwrap();
+    assert!(stats["mcp_requests"]["rate_limited"].as_u64().unwrap() >= 7);
+}
+
+fn in_1971() -> chrono::DateTime<chrono::Utc> {
+    "1971-03-04T05:06:07Z".parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_implausible_clock_refuses_time_tools() {
+    use mcp_utc_time_server::guard::{ClockGuard, GuardConfig};
+    use std::sync::Arc;
+
+    let guard = ClockGuard::new(GuardConfig::default()).with_clock(in_1971);
+    let (client, _) = connect_to(TimeServer::new().with_clock_guard(Arc::new(guard))).await;
+
+    let error = match call_raw(&client, "get_time", json!({})).await {
+        Err(ServiceError::McpError(data)) => data,
+        other => panic!("unexpected result {:?}", other),
+    };
+    assert_eq!(error.code.0, -32005);
+    let data = error.data.unwrap();
+    assert_eq!(data["code"], "clock_implausible");
+    assert!(data["details"]["reason"]
+        .as_str()
+        .unwrap()
+        .starts_with("system clock reads 1971-03-04T05:06:07Z, before this server was built"));
+
+    // Operators can still find out why
+    if cfg!(feature = "ntp") {
+        let status = call(&client, "get_ntp_status", json!({})).await.unwrap();
+        let checks = status["checks"].as_array().unwrap();
+        let clock = checks.iter().find(|c| c["name"] == "clock").unwrap();
+        assert_eq!(clock["status"], "unhealthy");
+        assert_eq!(status["health"], "unhealthy");
+    }
+
+    let overridden = ClockGuard::new(GuardConfig {
+        allow_implausible: true,
+        ..Default::default()
+    })
+    .with_clock(in_1971);
+    let (client, _) = connect_to(TimeServer::new().with_clock_guard(Arc::new(overridden))).await;
+    assert!(call(&client, "get_time", json!({})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_process_stats_count_calls() {
+    let (client, _) = connect().await;
+
+    let first = call(&client, "get_process_stats", json!({})).await.unwrap();
+    call(&client, "get_unix_time", json!({})).await.unwrap();
+    let second = call(&client, "get_process_stats", json!({})).await.unwrap();
+
+    // Other tests share the process-wide counters, so only a lower bound holds
+    let calls = |stats: &Value, tool: &str| stats["mcp_requests"]["by_tool"][tool].as_u64();
+    assert!(calls(&first, "get_process_stats").unwrap() >= 1);
+    assert!(
+        calls(&second, "get_process_stats").unwrap() > calls(&first, "get_process_stats").unwrap()
+    );
+    assert!(calls(&second, "get_unix_time").unwrap() >= 1);
+    assert!(
+        second["mcp_requests"]["total"].as_u64().unwrap()
+            >= first["mcp_requests"]["total"].as_u64().unwrap() + 2
+    );
+    assert_eq!(second["pid"], std::process::id());
+    if cfg!(target_os = "linux") {
+        assert!(second["memory"]["rss_bytes"].as_u64().unwrap() > 0);
+    }
+    assert!(second["tokio_tasks"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn test_week_number_conventions() {
+    let (client, _) = connect().await;
+
+    let result = call(
+        &client,
+        "get_week_number",
+        json!({"date": "2024-12-31", "convention": "simple"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["iso_week"], "2025-W01");
+    assert_eq!(result["weeks"]["iso"]["week_year"], 2025);
+    assert_eq!(result["weeks"]["monday"]["week"], 53);
+    assert_eq!(result["selected"]["week"], 53);
+    assert_eq!(result["selected"]["first_day"], "2024-12-30");
+    assert_eq!(result["selected"]["last_day"], "2024-12-31");
+
+    // 2021-01-01T02:00Z is still New Year's Eve in Chicago
+    let result = call(
+        &client,
+        "get_week_number",
+        json!({"timestamp": 1609466400, "timezone": "America/Chicago", "convention": "sunday"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["date"], "2020-12-31");
+    assert_eq!(result["selected"]["first_day"], "2020-12-27");
+
+    let error = call(
+        &client,
+        "get_week_number",
+        json!({"date": "2024-12-31", "timestamp": 0}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("not both"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_build_format_tool_and_prompt() {
+    use rmcp::model::{GetPromptRequestParam, PromptMessageContent};
+
+    let (client, _notifications) = connect().await;
+
+    let result = call(
+        &client,
+        "build_format",
+        json!({"tokens": ["year", "month_name_short", "day", "hour24", "minute", "tz_abbr"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["format"], "%Y %b %d %H:%M %Z");
+    assert_eq!(result["example"], "2006 Jan 02 15:04 UTC");
+
+    // A format goes back to its tokens in order
+    let result = call(
+        &client,
+        "build_format",
+        json!({"format": "%d/%m/%Y %I:%M %p"}),
+    )
+    .await
+    .unwrap();
+    let tokens: Vec<&str> = result["parts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|p| p["token"].as_str())
+        .collect();
+    assert_eq!(
+        tokens,
+        ["day", "month", "year", "hour12", "minute", "am_pm"]
+    );
+
+    let result = call(&client, "build_format", json!({"tokens": ["yeer"]}))
+        .await
+        .unwrap();
+    assert_eq!(result["unknown"][0]["suggestions"][0], "year");
+
+    let error = call(
+        &client,
+        "build_format",
+        json!({"tokens": ["year"], "format": "%Y"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("exactly one"), "{}", error);
+
+    if !cfg!(feature = "prompts") {
+        return;
+    }
+    let prompt = client
+        .get_prompt(GetPromptRequestParam {
+            name: "build_format".into(),
+            arguments: json!({"description": "day/month/year hour:minute, 12h"})
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .unwrap();
+    let PromptMessageContent::Text { text } = &prompt.messages[0].content else {
+        panic!("prompt returned non-text content");
+    };
+    assert!(text.contains("%d/%m/%Y %I:%M %p"), "{}", text);
+    assert!(text.contains("02/01/2006 03:04 PM"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_parse_natural_time() {
+    let (client, _notifications) = connect().await;
+
+    // Friday 2024-03-15 10:15:30 UTC
+    let reference = 1_710_497_730;
+    let result = call(
+        &client,
+        "parse_natural_time",
+        json!({"input": "next tuesday at 9am", "reference_timestamp": reference, "timezone": "Europe/Berlin"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["rfc3339"], "2024-03-19T09:00:00+01:00");
+    assert_eq!(result["unix_timestamp"], 1_710_835_200);
+    assert_eq!(result["trace"][0]["kind"], "day");
+
+    let result = call(
+        &client,
+        "parse_natural_time",
+        json!({"input": "in 45 minutes", "reference_timestamp": reference}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["utc"], "2024-03-15T11:00:30Z");
+    assert!(result["local_status"].is_null());
+
+    let error = call(
+        &client,
+        "parse_natural_time",
+        json!({"input": "next friday", "reference_timestamp": reference}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("ambiguous"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_timestamp_card_tool_and_prompt() {
+    use rmcp::model::{GetPromptRequestParam, PromptMessageContent};
+
+    let (client, _notifications) = connect().await;
+
+    let result = call(
+        &client,
+        "timestamp_card",
+        json!({"timestamp": "2024-03-15T10:15:30Z", "timezones": "Asia/Tokyo, Europe/London"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["unix_timestamp"], 1_710_497_730);
+    assert_eq!(result["weekday"], "Friday");
+    assert_eq!(result["iso_week"], "2024-W11");
+    assert!(result["relative"].as_str().unwrap().ends_with("ago"));
+    assert_eq!(result["zones"][0]["timezone"], "Europe/London");
+    let text = result["text"].as_str().unwrap();
+    assert!(
+        text.contains("Asia/Tokyo     2024-03-15 19:15:30  +09:00"),
+        "{}",
+        text
+    );
+
+    let result = call(&client, "timestamp_card", json!({"timestamp": 0}))
+        .await
+        .unwrap();
+    assert_eq!(result["utc"], "1970-01-01T00:00:00Z");
+    assert_eq!(result["zones"], json!([]));
+    assert!(result["text"]
+        .as_str()
+        .unwrap()
+        .contains("(no timezones requested)"));
+
+    // Unix seconds as a string read the same as the number
+    let quoted = call(
+        &client,
+        "timestamp_card",
+        json!({"timestamp": "1710497730"}),
+    )
+    .await
+    .unwrap();
+    let number = call(&client, "timestamp_card", json!({"timestamp": 1710497730}))
+        .await
+        .unwrap();
+    assert_eq!(quoted["utc"], number["utc"]);
+    assert_eq!(number["unix_timestamp"], 1_710_497_730);
+
+    for timestamp in [json!("soon"), json!(1710497730.5)] {
+        let error = call(&client, "timestamp_card", json!({"timestamp": timestamp}))
+            .await
+            .unwrap_err();
+        assert!(error.contains("RFC 3339"), "{}", error);
+    }
+
+    if !cfg!(feature = "prompts") {
+        return;
+    }
+    let prompt = client
+        .get_prompt(GetPromptRequestParam {
+            name: "card".into(),
+            arguments: json!({"timestamp": "1710497730"}).as_object().cloned(),
+        })
+        .await
+        .unwrap();
+    let PromptMessageContent::Text { text } = &prompt.messages[0].content else {
+        panic!("prompt returned non-text content");
+    };
+    assert!(
+        text.contains("UTC         2024-03-15T10:15:30Z"),
+        "{}",
+        text
+    );
+}
+
+#[tokio::test]
+async fn test_assert_time_window() {
+    let (client, _notifications) = connect().await;
+
+    // A token valid for an hour from now
+    let now = chrono::Utc::now().timestamp();
+    let token = call(
+        &client,
+        "assert_time_window",
+        json!({"start": now - 60, "end": now + 3600}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(token["inside"], true);
+    assert_eq!(token["reference_source"], "server");
+    let remaining = token["seconds_until_change"].as_i64().unwrap();
+    assert!((3590..=3600).contains(&remaining), "{}", remaining);
+    assert!(token["next_window_start"].is_null());
+
+    // 01:00-05:00 in New York spans the spring-forward gap: three hours
+    let spring = call(
+        &client,
+        "assert_time_window",
+        json!({
+            "daily": "01:00-05:00",
+            "timezone": "America/New_York",
+            "timestamp": "2024-03-10T05:00:00Z",
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(spring["inside"], false);
+    assert_eq!(spring["next_window_start"], "2024-03-10T06:00:00Z");
+    assert_eq!(spring["next_window_end"], "2024-03-10T09:00:00Z");
+    assert_eq!(spring["seconds_until_change"], 3600);
+    assert_eq!(spring["reference_source"], "client");
+
+    let weekdays = call(
+        &client,
+        "assert_time_window",
+        json!({
+            "cron": "0 9 * * MON-FRI",
+            "duration": "PT8H",
+            "timezone": "Europe/London",
+            "timestamp": 1718382600,
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(weekdays["inside"], false);
+    assert_eq!(weekdays["next_window_start"], "2024-06-17T08:00:00Z");
+
+    for (arguments, fragment) in [
+        (json!({}), "give one window"),
+        (json!({"start": 20, "end": 10}), "after 'start'"),
+        (
+            json!({"daily": "09:00-17:00", "cron": "@daily"}),
+            "give one window",
+        ),
+        (
+            json!({"cron": "@daily", "duration": "P40D"}),
+            "at most 31 days",
+        ),
+        (json!({"cron": "61 * * * *", "duration": "PT1H"}), "'cron'"),
+    ] {
+        let error = call(&client, "assert_time_window", arguments.clone())
+            .await
+            .unwrap_err();
+        assert!(error.contains(fragment), "{}: {}", arguments, error);
+    }
+}
+
+#[tokio::test]
+async fn test_content_hash_is_opt_in_and_stable() {
+    use mcp_utc_time_server::canonical;
+    use mcp_utc_time_server::mcp::content_hash::volatile_fields;
+
+    let (client, _) = connect().await;
+    let tools = client.list_all_tools().await.unwrap();
+    assert!(tools
+        .iter()
+        .all(|tool| tool.input_schema["properties"]["hash"]["type"] == "boolean"));
+
+    let plain = call(&client, "get_time", json!({"precision": "seconds"}))
+        .await
+        .unwrap();
+    assert!(plain.get("content_hash").is_none());
+
+    let poll = || {
+        call(
+            &client,
+            "get_time",
+            json!({"precision": "seconds", "hash": true}),
+        )
+    };
+    let (first, second) = (poll().await.unwrap(), poll().await.unwrap());
+    let hash = first["content_hash"].as_str().unwrap();
+    assert_eq!(hash.len(), 64);
+    assert_eq!(
+        canonical::content_hash(&first, volatile_fields("get_time")),
+        hash
+    );
+    // Same second, same hash; the nanoseconds do not count
+    if first["seconds"] == second["seconds"] {
+        assert_eq!(second["content_hash"], hash);
+    } else {
+        assert_ne!(second["content_hash"], hash);
+    }
+
+    let week = call(
+        &client,
+        "get_week_number",
+        json!({"date": "2024-12-30", "hash": true}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        canonical::content_hash(&week, &[]),
+        week["content_hash"].as_str().unwrap()
+    );
+
+    let error = call(&client, "get_time", json!({"hash": "yes"}))
+        .await
+        .unwrap_err();
+    assert!(error.contains("hash must be true or false"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_order_policy_on_interval_tools() {
+    let (client, _notifications) = connect().await;
+    let later = "2024-03-15T11:00:00Z";
+    let earlier = "2024-03-15T10:00:00Z";
+
+    // Signed stays the default: a negative difference, nothing flagged
+    let signed = call(&client, "compare_times", json!({"a": later, "b": earlier}))
+        .await
+        .unwrap();
+    assert_eq!(signed["difference_seconds"], -3600.0);
+    assert!(signed.get("inputs_swapped").is_none());
+    assert!(signed.get("warnings").is_none());
+
+    let absolute = call(
+        &client,
+        "compare_times",
+        json!({"a": later, "b": earlier, "order_policy": "absolute"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(absolute["difference_seconds"], 3600.0);
+    assert_eq!(absolute["earlier"], "b");
+    assert_eq!(absolute["inputs_swapped"], true);
+    assert_eq!(absolute["warnings"][0]["code"], "INPUTS_SWAPPED");
+
+    let error = call(
+        &client,
+        "compare_times",
+        json!({"a": later, "b": earlier, "order_policy": "strict"}),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("'a' is after 'b'"), "{}", error);
+
+    // A range tool: reversed ends fail as before unless swapped
+    let reversed = json!({"timezone": "Europe/Paris", "start": 1735689600, "end": 1704067200});
+    assert!(call(&client, "get_offset_timeline", reversed.clone())
+        .await
+        .unwrap_err()
+        .contains("'end' must be after 'start'"));
+    let mut swapped = reversed;
+    swapped["order_policy"] = json!("absolute");
+    let timeline = call(&client, "get_offset_timeline", swapped).await.unwrap();
+    assert_eq!(timeline["start"], 1704067200);
+    assert_eq!(timeline["count"], 3);
+    assert_eq!(timeline["inputs_swapped"], true);
+
+    let window = call(
+        &client,
+        "assert_time_window",
+        json!({
+            "start": later,
+            "end": earlier,
+            "timestamp": "2024-03-15T10:30:00Z",
+            "order_policy": "absolute",
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(window["inside"], true);
+    assert_eq!(window["warnings"][0]["field"], "start");
+}
@@ -0,0 +1,116 @@
+// SNTP responder tests over a real UDP socket
+
+use chrono::Utc;
+use mcp_utc_time_server::ntp::packet::{
+    NtpPacket, NtpTimestamp, LEAP_NONE, LEAP_UNSYNCHRONIZED, MODE_SERVER, STRATUM_UNSYNCHRONIZED,
+};
+use mcp_utc_time_server::ntp::sntp::{SntpHandle, SntpResponder};
+use mcp_utc_time_server::ntp::NtpStatus;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::time::timeout;
+
+fn status(synced: bool) -> NtpStatus {
+    NtpStatus {
+        synced,
+        offset_ms: 0.0,
+        stratum: if synced { 2 } else { 16 },
+        precision: -20,
+        root_delay: 10.0,
+        root_dispersion: 1.0,
+        shm_valid: true,
+        pps_enabled: false,
+        ..Default::default()
+    }
+}
+
+async fn start(synced: bool, rate_limit: u32) -> (SntpHandle, SocketAddr) {
+    let reference = NtpTimestamp::from(Utc::now() - chrono::Duration::seconds(5));
+    let (_tx, rx) = watch::channel((status(synced), reference));
+    let responder = SntpResponder::bind("127.0.0.1:0".parse().unwrap(), rx, rate_limit)
+        .await
+        .unwrap();
+    let addr = responder.local_addr().unwrap();
+    (responder.spawn(), addr)
+}
+
+async fn query(client: &UdpSocket, request: &NtpPacket) -> Option<NtpPacket> {
+    client.send(&request.encode()).await.unwrap();
+    let mut buf = [0u8; 128];
+    let len = timeout(Duration::from_millis(500), client.recv(&mut buf))
+        .await
+        .ok()?
+        .unwrap();
+    assert_eq!(len, 48, "reply must not be larger than the request");
+    Some(NtpPacket::decode(&buf[..len]).unwrap())
+}
+
+async fn client(server: SocketAddr) -> UdpSocket {
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client.connect(server).await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_responder_answers_client_packet() {
+    let (handle, addr) = start(true, 10).await;
+    let client = client(addr).await;
+
+    let sent = NtpTimestamp::from(Utc::now());
+    let reply = query(&client, &NtpPacket::client(sent)).await.unwrap();
+    let arrived = NtpTimestamp::from(Utc::now());
+
+    assert_eq!(reply.mode, MODE_SERVER);
+    assert_eq!(reply.version, 4);
+    assert_eq!(reply.leap, LEAP_NONE);
+    assert_eq!(reply.stratum, 2);
+    assert_eq!(&reply.reference_id, b"SHM\0");
+    assert_eq!(reply.origin, sent);
+    assert!(reply.reference <= reply.receive);
+    assert!(reply.receive <= reply.transmit);
+    // Same host clock, so the server's timestamps fall inside the round trip
+    assert!(sent <= reply.receive, "{:?} > {:?}", sent, reply.receive);
+    assert!(reply.transmit <= arrived);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unsynchronized_server_sets_leap_indicator() {
+    let (handle, addr) = start(false, 10).await;
+    let client = client(addr).await;
+
+    let reply = query(&client, &NtpPacket::client(NtpTimestamp::from(Utc::now())))
+        .await
+        .unwrap();
+    assert_eq!(reply.leap, LEAP_UNSYNCHRONIZED);
+    assert_eq!(reply.stratum, STRATUM_UNSYNCHRONIZED);
+    assert_eq!(&reply.reference_id, b"INIT");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_ignores_non_client_packets_and_rate_limits() {
+    let (handle, addr) = start(true, 2).await;
+    let client = client(addr).await;
+
+    // A server-mode packet is not a request
+    let server_mode = NtpPacket {
+        mode: MODE_SERVER,
+        ..NtpPacket::client(NtpTimestamp(1))
+    };
+    assert!(query(&client, &server_mode).await.is_none());
+
+    let request = NtpPacket::client(NtpTimestamp::from(Utc::now()));
+    assert!(query(&client, &request).await.is_some());
+    assert!(query(&client, &request).await.is_some());
+    assert!(
+        query(&client, &request).await.is_none(),
+        "third reply in a second"
+    );
+
+    handle.shutdown().await;
+}